@@ -0,0 +1,46 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::kernel::env;
+use crate::kernel::error::Error;
+use crate::traced;
+
+/// Returns the value of the variable.
+pub fn get(name: &str) -> Option<String> { env::get(name) }
+
+/// Sets the value of the variable, defining it if necessary.
+pub fn set(name: &str, value: &str) -> Result<(), Error> { traced!(set_env(name, value) => env::set(name, value)) }
+
+/// Removes the variable.
+pub fn unset(name: &str) -> Result<(), Error> { traced!(unset_env(name) => env::unset(name)) }
+
+/// Calls the given function for each variable, in alphabetical order.
+pub fn for_each(f: impl FnMut(&str, &str)) { env::for_each(f); }
+
+/// Returns whether the given string can be used as the name of a variable.
+pub fn is_valid_name(name: &str) -> bool { env::is_valid_name(name) }
+
+/// Returns the directories listed in `PATH`.
+pub fn search_path() -> Vec<String> { env::search_path() }
@@ -0,0 +1,113 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "fs")]
+use crate::kernel::block::cache;
+use crate::kernel::capability;
+use crate::kernel::capability::Capability;
+use crate::kernel::error::Error;
+use crate::kernel::{fd, vfs};
+use crate::traced;
+
+pub use crate::kernel::fd::{Fd, OpenMode, SeekFrom};
+pub use crate::kernel::vfs::{DirEntry, FileType, Metadata, MountInfo};
+
+/// Opens the file in the given mode and returns its descriptor.
+pub fn open(path: &str, mode: OpenMode) -> Result<Fd, Error> { traced!(open(path, mode) => fd::open(path, mode)) }
+
+/// Reads from the file into `buf`, returning the number of bytes read (0 at the end of the file).
+pub fn read(fd: Fd, buf: &mut [u8]) -> Result<usize, Error> { traced!(read(fd, buf.len()) => fd::read(fd, buf)) }
+
+/// Writes `data` to the file, returning the number of bytes written.
+pub fn write(fd: Fd, data: &[u8]) -> Result<usize, Error> { traced!(write(fd, data.len()) => fd::write(fd, data)) }
+
+/// Moves the offset of the descriptor, returning the new offset from the start of the file.
+pub fn seek(fd: Fd, pos: SeekFrom) -> Result<usize, Error> { traced!(seek(fd, pos) => fd::seek(fd, pos)) }
+
+/// Closes the descriptor.
+pub fn close(fd: Fd) -> Result<(), Error> { traced!(close(fd) => fd::close(fd)) }
+
+/// Returns the metadata of the file or directory.
+pub fn metadata(path: &str) -> Result<Metadata, Error> { traced!(metadata(path) => vfs::metadata(path)) }
+
+/// Returns the entries of the directory, sorted by name.
+pub fn read_dir(path: &str) -> Result<Vec<DirEntry>, Error> { traced!(read_dir(path) => vfs::read_dir(path)) }
+
+/// Creates the directory, whose parent must exist.
+pub fn create_dir(path: &str) -> Result<(), Error> { traced!(create_dir(path) => vfs::create_dir(path)) }
+
+/// Removes the file or empty directory.
+pub fn remove(path: &str) -> Result<(), Error> { traced!(remove(path) => vfs::remove(path)) }
+
+/// Returns the contents of the file.
+pub fn read_file(path: &str) -> Result<Vec<u8>, Error> { traced!(read_file(path) => vfs::read(path)) }
+
+/// Returns the contents of the file as a string.
+pub fn read_to_string(path: &str) -> Result<String, Error> {
+    String::from_utf8(read_file(path)?).map_err(|_| Error::InvalidArgument)
+}
+
+/// Replaces the contents of the file, creating it if necessary.
+pub fn write_file(path: &str, data: &[u8]) -> Result<(), Error> { traced!(write_file(path, data.len()) => vfs::write(path, data)) }
+
+/// Appends to the contents of the file, creating it if necessary.
+pub fn append_file(path: &str, data: &[u8]) -> Result<(), Error> { traced!(append_file(path, data.len()) => vfs::append(path, data)) }
+
+/// Returns whether the file or directory exists.
+pub fn exists(path: &str) -> bool { vfs::exists(path) }
+
+/// Returns the canonical form of an absolute path.
+pub fn canonicalize(path: &str) -> Result<String, Error> { vfs::normalize(path) }
+
+/// Mounts the source at the target directory, detecting the filesystem unless its type is given.
+///
+/// Note: It requires the `Mount` capability.
+pub fn mount(source: &str, target: &str, fs_type: Option<&str>, read_only: bool) -> Result<(), Error> {
+    traced!(
+        mount(source, target, fs_type, read_only) => capability::require(Capability::Mount)
+            .and_then(|_| vfs::mount(source, target, fs_type, read_only))
+    )
+}
+
+/// Unmounts the filesystem mounted at the target directory.
+///
+/// Note: It requires the `Mount` capability.
+pub fn unmount(target: &str) -> Result<(), Error> {
+    traced!(unmount(target) => capability::require(Capability::Mount).and_then(|_| vfs::unmount(target)))
+}
+
+/// Calls the given function for each mount.
+pub fn for_each_mount(f: impl FnMut(&MountInfo)) { vfs::for_each_mount(f) }
+
+/// Writes all pending changes of the mounted filesystems and the cached blocks back to their devices.
+pub fn sync() -> Result<(), Error> { traced!(sync() => sync_all()) }
+
+/// Writes back the mounted filesystems, then the cached blocks.
+fn sync_all() -> Result<(), Error> {
+    vfs::sync()?;
+    #[cfg(feature = "fs")]
+    cache::sync()?;
+    Ok(())
+}
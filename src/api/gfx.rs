@@ -0,0 +1,37 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::drivers;
+use crate::kernel::error::Error;
+
+pub use crate::kernel::gfx::image::{decode, Image, MAX_PIXELS, Rgb};
+
+/// Shows the image on the framebuffer in place of the console, scaled to fit the screen.
+///
+/// Note: It fails with `Unsupported` in VGA text mode.
+pub fn show_image(image: &Image) -> Result<(), Error> { drivers::framebuffer::show_image(image) }
+
+/// Brings the console back after an image was shown.
+pub fn restore_console() {
+    drivers::framebuffer::invalidate();
+    drivers::vga::redraw();
+}
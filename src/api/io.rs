@@ -0,0 +1,193 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{print, serial_print};
+use crate::devices::console;
+use crate::encodings::ASCII;
+use crate::encodings::Charset;
+use crate::kernel::pipe;
+
+// Standard Streams
+//
+// Every command is handed a `Stdio` holding its three standard streams. By default, the input is read
+// from the console and both outputs are written to it, with errors highlighted in red. The shell
+// rewires the streams to pipes for pipelines and redirections, and any of them may be sent over the
+// serial port instead.
+
+//////////////
+/// Target
+//////////////
+enum Target {
+    Console,
+    Serial,
+    Pipe(pipe::Writer),
+}
+
+impl Target {
+    /// Writes the string to the target, wrapping it in the given SGR sequence on terminals.
+    fn write(&mut self, s: &str, sgr: Option<&str>) -> fmt::Result {
+        match (self, sgr) {
+            (Self::Console, Some(sgr)) => print!("\x1B[{}m{}\x1B[0m", sgr, s),
+            (Self::Console, None) => print!("{}", s),
+            (Self::Serial, _) => serial_print!("{}", s),
+            (Self::Pipe(writer), _) => return fmt::Write::write_str(writer, s),
+        }
+        Ok(())
+    }
+}
+
+//////////////
+/// Stdout
+//////////////
+pub struct Stdout(Target);
+
+impl Stdout {
+    /// Creates a new object that writes to the console.
+    pub fn console() -> Self { Stdout(Target::Console) }
+
+    /// Creates a new object that writes to the serial port.
+    pub fn serial() -> Self { Stdout(Target::Serial) }
+
+    /// Creates a new object that writes to the pipe.
+    pub fn pipe(writer: pipe::Writer) -> Self { Stdout(Target::Pipe(writer)) }
+}
+
+impl fmt::Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result { self.0.write(s, None) }
+}
+
+//////////////
+/// Stderr
+//////////////
+pub struct Stderr(Target);
+
+impl Stderr {
+    /// SGR parameters used to highlight errors on the console.
+    const SGR: &'static str = "91";
+
+    /// Creates a new object that writes to the console.
+    pub fn console() -> Self { Stderr(Target::Console) }
+
+    /// Creates a new object that writes to the serial port.
+    pub fn serial() -> Self { Stderr(Target::Serial) }
+
+    /// Creates a new object that writes to the pipe.
+    pub fn pipe(writer: pipe::Writer) -> Self { Stderr(Target::Pipe(writer)) }
+}
+
+impl fmt::Write for Stderr {
+    fn write_str(&mut self, s: &str) -> fmt::Result { self.0.write(s, Some(Self::SGR)) }
+}
+
+/////////////
+/// Stdin
+/////////////
+pub struct Stdin(Source);
+
+//////////////
+/// Source
+//////////////
+enum Source {
+    Console,
+    Pipe(pipe::Reader),
+    Null,
+}
+
+impl Stdin {
+    /// Creates a new object that reads from the console.
+    pub fn console() -> Self { Stdin(Source::Console) }
+
+    /// Creates a new object that reads from the pipe.
+    pub fn pipe(reader: pipe::Reader) -> Self { Stdin(Source::Pipe(reader)) }
+
+    /// Creates a new object that reads nothing.
+    pub fn null() -> Self { Stdin(Source::Null) }
+
+    /// Returns whether the input is typed by the user.
+    pub fn is_interactive(&self) -> bool { matches!(self.0, Source::Console) }
+
+    /// Returns the input received so far, without waiting.
+    ///
+    /// Note: Returns `None` for the console, whose input only ends when the user says so.
+    pub fn read_available(&mut self) -> Option<String> {
+        match &mut self.0 {
+            Source::Console => None,
+            Source::Pipe(reader) => Some(reader.read_available()),
+            Source::Null => Some(String::new()),
+        }
+    }
+
+    /// Waits for the next line, returning `None` at the end of the input.
+    pub async fn read_line(&mut self) -> Option<String> {
+        match &mut self.0 {
+            Source::Console => Some(console::next_line().await),
+            Source::Pipe(reader) => reader.read_line().await,
+            Source::Null => None,
+        }
+    }
+
+    /// Waits for the end of the input and returns all of it.
+    pub async fn read_to_end(&mut self) -> Vec<u8> {
+        match &mut self.0 {
+            Source::Console => {
+                // The input ends with the line on which the user types ^D.
+                let mut data = Vec::new();
+                while let Some(line) = self.read_line().await {
+                    match line.split_once(ASCII::<char>::EOT) {
+                        Some((last, _)) => {
+                            data.extend_from_slice(last.as_bytes());
+                            break;
+                        }
+                        None => data.extend_from_slice(line.as_bytes()),
+                    }
+                }
+                data
+            }
+            Source::Pipe(reader) => reader.read_to_end().await,
+            Source::Null => Vec::new(),
+        }
+    }
+}
+
+/////////////
+/// Stdio
+/////////////
+pub struct Stdio {
+    pub stdin: Stdin,
+    pub stdout: Stdout,
+    pub stderr: Stderr,
+}
+
+impl Stdio {
+    /// Creates a new object attached to the console.
+    pub fn console() -> Self {
+        Stdio {
+            stdin: Stdin::console(),
+            stdout: Stdout::console(),
+            stderr: Stderr::console(),
+        }
+    }
+}
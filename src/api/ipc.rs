@@ -0,0 +1,57 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+
+use crate::kernel::error::Error;
+use crate::kernel::{clipboard, mqueue, pipe};
+use crate::traced;
+
+pub use crate::kernel::clipboard::MAX_SIZE as MAX_CLIPBOARD_SIZE;
+pub use crate::kernel::mqueue::{DEFAULT_CAPACITY, Info as QueueInfo, MAX_MESSAGE_SIZE, MessageQueue};
+pub use crate::kernel::pipe::{Reader, Writer};
+
+/// Creates a new pipe and returns its reading and writing ends.
+pub fn pipe() -> (Reader, Writer) { pipe::new() }
+
+/// Creates a message queue with the given name, holding up to `capacity` messages.
+pub fn create_queue(name: &str, capacity: usize) -> Result<MessageQueue, Error> {
+    traced!(create_queue(name, capacity) => mqueue::create(name, capacity))
+}
+
+/// Returns the message queue with the given name.
+pub fn open_queue(name: &str) -> Result<MessageQueue, Error> { traced!(open_queue(name) => mqueue::open(name)) }
+
+/// Removes the name of the message queue, which lives on while handles to it remain.
+pub fn unlink_queue(name: &str) -> Result<(), Error> { traced!(unlink_queue(name) => mqueue::unlink(name)) }
+
+/// Calls the given function for each named message queue, in alphabetical order.
+pub fn for_each_queue(f: impl FnMut(&QueueInfo)) { mqueue::for_each(f); }
+
+/// Replaces the contents of the clipboard, which are also sent to the terminal on the serial port.
+pub fn set_clipboard(data: &[u8]) -> Result<(), Error> { traced!(set_clipboard(data.len()) => clipboard::set(data)) }
+
+/// Returns the contents of the clipboard.
+pub fn get_clipboard() -> Vec<u8> { clipboard::get() }
+
+/// Empties the clipboard.
+pub fn clear_clipboard() -> Result<(), Error> { traced!(clear_clipboard() => clipboard::clear()) }
@@ -22,7 +22,12 @@
 
 use core::str::FromStr;
 
+use alloc::vec::Vec;
+
 use crate::drivers;
+use crate::kernel::error::Error;
+
+pub use pc_keyboard::KeyCode;
 
 ///////////////
 /// Default
@@ -46,12 +51,12 @@ pub enum Layout {
 
 impl Layout {
     /// Creates a new object from enum index.
-    pub fn from_index(idx: u8) -> Result<Self, ()> {
+    pub fn from_index(idx: u8) -> Result<Self, Error> {
         match idx {
             0x0 => Ok(Self::AZERTY),
             0x1 => Ok(Self::Dvorak),
             0x2 => Ok(Self::QWERTY),
-            _ => Err(()),
+            _ => Err(Error::OutOfBounds),
         }
     }
 
@@ -69,18 +74,193 @@ impl Layout {
 }
 
 impl FromStr for Layout {
-    type Err = ();
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "azerty" => Ok(Self::AZERTY),
             "dvorak" => Ok(Self::Dvorak),
             "qwerty" => Ok(Self::QWERTY),
-            _ => Err(())
+            _ => Err(Error::InvalidArgument)
+        }
+    }
+}
+
+/////////////////
+/// Scancodes
+/////////////////
+/// Scancode set the keyboard is decoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Scancodes {
+    /// Set 1, usually translated by the controller from what the keyboard sends.
+    Set1 = 0x1,
+    /// Set 2, as sent by the keyboard.
+    Set2 = 0x2,
+}
+
+impl Scancodes {
+    /// Creates a new object from enum index.
+    pub fn from_index(idx: u8) -> Result<Self, Error> {
+        match idx {
+            0x1 => Ok(Self::Set1),
+            0x2 => Ok(Self::Set2),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Returns the object as an enum index.
+    pub fn as_u8(&self) -> u8 { (*self) as u8 }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Set1 => "set1",
+            Self::Set2 => "set2",
+        }
+    }
+}
+
+impl FromStr for Scancodes {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "set1" => Ok(Self::Set1),
+            "set2" => Ok(Self::Set2),
+            _ => Err(Error::InvalidArgument)
         }
     }
 }
 
+///////////
+/// Key
+///////////
+pub struct Key;
+
+impl Key {
+    /// Names of the keys, in the order of their codes.
+    pub const NAMES: [(&'static str, KeyCode); 107] = [
+        ("escape", KeyCode::Escape),
+        ("f1", KeyCode::F1),
+        ("f2", KeyCode::F2),
+        ("f3", KeyCode::F3),
+        ("f4", KeyCode::F4),
+        ("f5", KeyCode::F5),
+        ("f6", KeyCode::F6),
+        ("f7", KeyCode::F7),
+        ("f8", KeyCode::F8),
+        ("f9", KeyCode::F9),
+        ("f10", KeyCode::F10),
+        ("f11", KeyCode::F11),
+        ("f12", KeyCode::F12),
+        ("printscreen", KeyCode::PrintScreen),
+        ("sysrq", KeyCode::SysRq),
+        ("scrolllock", KeyCode::ScrollLock),
+        ("pause", KeyCode::PauseBreak),
+        ("backtick", KeyCode::Oem8),
+        ("1", KeyCode::Key1),
+        ("2", KeyCode::Key2),
+        ("3", KeyCode::Key3),
+        ("4", KeyCode::Key4),
+        ("5", KeyCode::Key5),
+        ("6", KeyCode::Key6),
+        ("7", KeyCode::Key7),
+        ("8", KeyCode::Key8),
+        ("9", KeyCode::Key9),
+        ("0", KeyCode::Key0),
+        ("minus", KeyCode::OemMinus),
+        ("equals", KeyCode::OemPlus),
+        ("backspace", KeyCode::Backspace),
+        ("insert", KeyCode::Insert),
+        ("home", KeyCode::Home),
+        ("pageup", KeyCode::PageUp),
+        ("numlock", KeyCode::NumpadLock),
+        ("kp_divide", KeyCode::NumpadDivide),
+        ("kp_multiply", KeyCode::NumpadMultiply),
+        ("kp_subtract", KeyCode::NumpadSubtract),
+        ("tab", KeyCode::Tab),
+        ("q", KeyCode::Q),
+        ("w", KeyCode::W),
+        ("e", KeyCode::E),
+        ("r", KeyCode::R),
+        ("t", KeyCode::T),
+        ("y", KeyCode::Y),
+        ("u", KeyCode::U),
+        ("i", KeyCode::I),
+        ("o", KeyCode::O),
+        ("p", KeyCode::P),
+        ("lbracket", KeyCode::Oem4),
+        ("rbracket", KeyCode::Oem6),
+        ("backslash", KeyCode::Oem7),
+        ("delete", KeyCode::Delete),
+        ("end", KeyCode::End),
+        ("pagedown", KeyCode::PageDown),
+        ("kp_7", KeyCode::Numpad7),
+        ("kp_8", KeyCode::Numpad8),
+        ("kp_9", KeyCode::Numpad9),
+        ("kp_add", KeyCode::NumpadAdd),
+        ("capslock", KeyCode::CapsLock),
+        ("a", KeyCode::A),
+        ("s", KeyCode::S),
+        ("d", KeyCode::D),
+        ("f", KeyCode::F),
+        ("g", KeyCode::G),
+        ("h", KeyCode::H),
+        ("j", KeyCode::J),
+        ("k", KeyCode::K),
+        ("l", KeyCode::L),
+        ("semicolon", KeyCode::Oem1),
+        ("quote", KeyCode::Oem3),
+        ("return", KeyCode::Return),
+        ("kp_4", KeyCode::Numpad4),
+        ("kp_5", KeyCode::Numpad5),
+        ("kp_6", KeyCode::Numpad6),
+        ("lshift", KeyCode::LShift),
+        ("z", KeyCode::Z),
+        ("x", KeyCode::X),
+        ("c", KeyCode::C),
+        ("v", KeyCode::V),
+        ("b", KeyCode::B),
+        ("n", KeyCode::N),
+        ("m", KeyCode::M),
+        ("comma", KeyCode::OemComma),
+        ("period", KeyCode::OemPeriod),
+        ("slash", KeyCode::Oem2),
+        ("rshift", KeyCode::RShift),
+        ("up", KeyCode::ArrowUp),
+        ("kp_1", KeyCode::Numpad1),
+        ("kp_2", KeyCode::Numpad2),
+        ("kp_3", KeyCode::Numpad3),
+        ("kp_enter", KeyCode::NumpadEnter),
+        ("lctrl", KeyCode::LControl),
+        ("lwin", KeyCode::LWin),
+        ("lalt", KeyCode::LAlt),
+        ("space", KeyCode::Spacebar),
+        ("altgr", KeyCode::RAltGr),
+        ("rwin", KeyCode::RWin),
+        ("menu", KeyCode::Apps),
+        ("rctrl", KeyCode::RControl),
+        ("left", KeyCode::ArrowLeft),
+        ("down", KeyCode::ArrowDown),
+        ("right", KeyCode::ArrowRight),
+        ("kp_0", KeyCode::Numpad0),
+        ("kp_period", KeyCode::NumpadPeriod),
+        ("oem9", KeyCode::Oem9),
+        ("oem10", KeyCode::Oem10),
+    ];
+
+    /// Returns the name of the key.
+    pub fn name(code: KeyCode) -> &'static str {
+        Self::NAMES.iter().find(|(_, c)| *c == code).map_or("unknown", |(name, _)| name)
+    }
+
+    /// Returns the key with the given name, ignoring case.
+    pub fn from_name(name: &str) -> Result<KeyCode, Error> {
+        Self::NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, code)| *code).ok_or(Error::InvalidArgument)
+    }
+}
+
 /// Returns the layout.
 pub fn get_layout() -> Layout { drivers::keyboard::get_layout() }
 
@@ -89,3 +269,22 @@ pub fn set_layout(lyt: Layout) { drivers::keyboard::set_layout(lyt); }
 
 /// Resets the layout.
 pub fn reset_layout() { drivers::keyboard::reset_layout(); }
+
+/// Returns the remapped keys along with the keys they act as, in the order of their codes.
+pub fn get_remap() -> Vec<(KeyCode, KeyCode)> { drivers::keyboard::get_remap() }
+
+/// Makes each key act as the one it is paired with, replacing the previous remapping.
+///
+/// Note: Remapping happens before the layout translates a key, so a key remapped to a modifier acts as
+/// that modifier.
+pub fn set_remap(remap: &[(KeyCode, KeyCode)]) { drivers::keyboard::set_remap(remap); }
+
+/// Makes each key act as itself again.
+pub fn reset_remap() { drivers::keyboard::set_remap(&[]); }
+
+/// Returns the scancode set the keyboard is decoded with.
+pub fn get_scancodes() -> Scancodes { drivers::keyboard::get_scancodes() }
+
+/// Switches to the given scancode set, or to the one the keyboard identifies with if `None`, configuring
+/// the controller to match, and returns the set switched to.
+pub fn set_scancodes(scancodes: Option<Scancodes>) -> Result<Scancodes, Error> { drivers::keyboard::set_scancodes(scancodes) }
@@ -20,6 +20,18 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+pub use crate::kernel::error::{Error, FaultKind};
+
+pub mod env;
+pub mod fs;
+pub mod gfx;
+pub mod io;
+pub mod ipc;
 pub mod keyboard;
+pub mod process;
+pub mod sensors;
+pub mod serial;
+pub mod stats;
 pub mod system;
+pub mod usb;
 pub mod vga;
@@ -0,0 +1,115 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+pub use crate::kernel::capability::{Capabilities, Capability};
+pub use crate::kernel::process::{Command, ExitCode, Info, Pid, Status, Wait};
+pub use crate::kernel::signal::{Action, Signal};
+
+use crate::api::io::{Stderr, Stdio};
+use crate::devices::console;
+use crate::kernel::capability;
+use crate::kernel::error::Error;
+use crate::kernel::{process, signal};
+use crate::{msg, traced};
+use crate::usr;
+
+/// Spawns the named command in the background and returns its process ID.
+pub fn spawn(name: &str, args: &[&str]) -> Result<Pid, Error> {
+    traced!(
+        spawn(name, args) => usr::resolve(name)
+            .ok_or(Error::NotFound)
+            .map(|command| process::spawn(name, command, args, Stdio::console()))
+    )
+}
+
+/// Runs the named command in the foreground and returns its exit code.
+pub fn run(name: &str, args: &[&str]) -> Result<ExitCode, Error> { run_with(name, args, &mut Stdio::console()) }
+
+/// Runs the named command in the foreground with the given streams and returns its exit code.
+pub fn run_with(name: &str, args: &[&str], stdio: &mut Stdio) -> Result<ExitCode, Error> {
+    traced!(
+        run(name, args) => usr::resolve(name)
+            .ok_or(Error::NotFound)
+            .map(|command| process::run(name, command, args, stdio))
+    )
+}
+
+/// Runs the named command in the foreground with all capabilities and returns its exit code.
+///
+/// Note: Unless the caller already holds all capabilities, the user is asked to confirm on the console,
+/// which a command can not answer on their behalf.
+pub fn run_elevated(name: &str, args: &[&str], stdio: &mut Stdio) -> Result<ExitCode, Error> {
+    traced!(
+        run_elevated(name, args) => usr::resolve(name)
+            .ok_or(Error::NotFound)
+            .and_then(|command| {
+                if !capability::current().contains_all(Capabilities::ALL) && !confirm_elevation(name) {
+                    return Err(Error::PermissionDenied);
+                }
+                Ok(process::run_as(name, command, args, stdio, Capabilities::ALL))
+            })
+    )
+}
+
+/// Asks the user on the console whether the command may run with all capabilities.
+fn confirm_elevation(name: &str) -> bool {
+    write!(Stderr::console(), "{}", msg!("sudo.confirm", name)).ok();
+    let answer = console::read_char();
+    writeln!(Stderr::console(), "{}", answer).ok();
+    answer == 'y' || answer == 'Y'
+}
+
+/// Returns the capabilities of the running code.
+pub fn capabilities() -> Capabilities { capability::current() }
+
+/// Gives up all the capabilities of the running code except the given ones, for good.
+pub fn restrict_capabilities(caps: Capabilities) { capability::restrict(caps); }
+
+/// Waits for the process to exit and returns its exit code.
+pub fn wait(pid: Pid) -> Wait { process::wait(pid) }
+
+/// Returns the exit code of the process if it has exited.
+pub fn try_wait(pid: Pid) -> Result<Option<ExitCode>, Error> { traced!(try_wait(pid) => process::try_wait(pid)) }
+
+/// Collects all the processes that have exited along with their exit codes.
+pub fn reap() -> Vec<(Info, ExitCode)> { process::reap() }
+
+/// Calls the given function for each process that has not been collected.
+pub fn for_each(f: impl FnMut(&Info)) { process::for_each(f); }
+
+/// Sends the signal to the process.
+pub fn kill(pid: Pid, signal: Signal) -> Result<(), Error> { traced!(kill(pid, signal) => process::kill(pid, signal)) }
+
+/// Sets the action of the running task for the signal and returns the previous one.
+pub fn set_signal_action(signal: Signal, action: Action) -> Result<Action, Error> {
+    traced!(set_signal_action(signal, action) => signal::set_action(signal, action))
+}
+
+/// Clears the signal if it is pending for the running task, and returns whether it was.
+pub fn take_signal(signal: Signal) -> bool { signal::take(signal) }
+
+/// Raises `ALRM` on the running task after the given number of seconds, and returns the seconds left on
+/// the previous alarm. Zero seconds cancel the alarm.
+pub fn alarm(seconds: f64) -> Result<Option<f64>, Error> { traced!(alarm(seconds) => signal::alarm(seconds)) }
@@ -0,0 +1,173 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::str::FromStr;
+
+use crate::drivers;
+use crate::kernel::error::Error;
+
+///////////////
+/// Default
+///////////////
+pub struct Default;
+
+impl Default {
+    pub const CONFIG: Config = Config { baud: 38400, parity: Parity::None };
+}
+
+///////////
+/// Com
+///////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Com {
+    COM1 = 0x0,
+    COM2 = 0x1,
+    COM3 = 0x2,
+    COM4 = 0x3,
+}
+
+impl Com {
+    /// All serial ports.
+    pub const ALL: [Com; 4] = [Com::COM1, Com::COM2, Com::COM3, Com::COM4];
+
+    /// Creates a new object from enum index.
+    pub fn from_index(idx: u8) -> Result<Self, Error> {
+        match idx {
+            0x0 => Ok(Self::COM1),
+            0x1 => Ok(Self::COM2),
+            0x2 => Ok(Self::COM3),
+            0x3 => Ok(Self::COM4),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Returns the object as an enum index.
+    pub fn as_u8(&self) -> u8 { (*self) as u8 }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::COM1 => "com1",
+            Self::COM2 => "com2",
+            Self::COM3 => "com3",
+            Self::COM4 => "com4",
+        }
+    }
+}
+
+impl FromStr for Com {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().find(|com| com.as_str().eq_ignore_ascii_case(s)).copied().ok_or(Error::InvalidArgument)
+    }
+}
+
+//////////////
+/// Parity
+//////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Parity {
+    None = 0x0,
+    Odd = 0x1,
+    Even = 0x2,
+    Mark = 0x3,
+    Space = 0x4,
+}
+
+impl Parity {
+    /// All parities.
+    pub const ALL: [Parity; 5] = [Parity::None, Parity::Odd, Parity::Even, Parity::Mark, Parity::Space];
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::None => "none",
+            Self::Odd => "odd",
+            Self::Even => "even",
+            Self::Mark => "mark",
+            Self::Space => "space",
+        }
+    }
+}
+
+impl FromStr for Parity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "odd" => Ok(Self::Odd),
+            "even" => Ok(Self::Even),
+            "mark" => Ok(Self::Mark),
+            "space" => Ok(Self::Space),
+            _ => Err(Error::InvalidArgument)
+        }
+    }
+}
+
+//////////////
+/// Config
+//////////////
+/// Line settings of a serial port, always with eight data bits and one stop bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub baud: u32,
+    pub parity: Parity,
+}
+
+/// Returns whether a UART answers at the port.
+pub fn is_present(com: Com) -> bool { drivers::serial::is_present(com) }
+
+/// Returns the base I/O port of the serial port, as reported by the firmware or else the standard one.
+pub fn get_base(com: Com) -> u16 { drivers::serial::get_base(com) }
+
+/// Returns the line settings of the serial port.
+pub fn get_config(com: Com) -> Result<Config, Error> { drivers::serial::get_config(com) }
+
+/// Changes the line settings of the serial port.
+///
+/// Note: The baud rate has to divide 115200 evenly.
+pub fn set_config(com: Com, config: Config) -> Result<(), Error> { drivers::serial::set_config(com, config) }
+
+/// Returns the serial port the remote console runs on, i.e. that takes input and receives `serial_print!`.
+pub fn get_console() -> Com { drivers::serial::get_console() }
+
+/// Moves the remote console to the serial port.
+pub fn set_console(com: Com) -> Result<(), Error> { drivers::serial::set_console(com) }
+
+/// Returns the serial port the logger writes to.
+pub fn get_log_port() -> Com { drivers::serial::get_log_port() }
+
+/// Has the logger write to the serial port.
+pub fn set_log_port(com: Com) -> Result<(), Error> { drivers::serial::set_log_port(com) }
+
+/// Returns whether the output is framed by channel, so that a host can tell the console from the logger.
+pub fn is_mux_enabled() -> bool { drivers::serial::is_mux_enabled() }
+
+/// Starts framing the output by channel.
+pub fn enable_mux() { drivers::serial::enable_mux(); }
+
+/// Stops framing the output by channel, going back to plain text.
+pub fn disable_mux() { drivers::serial::disable_mux(); }
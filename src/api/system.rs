@@ -20,12 +20,144 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::future::Future;
+
 use crate::kernel;
+use crate::kernel::capability;
+use crate::kernel::capability::Capability;
+use crate::kernel::cmos::RTC;
+use crate::kernel::dev;
+use crate::kernel::env;
+use crate::kernel::error::Error;
+use crate::kernel::dev::Status;
+
+pub use crate::kernel::boot::Timing;
+pub use crate::kernel::power::{Frequency, Policy};
+
+///////////////////
+/// Boot Report
+///////////////////
+#[derive(Debug, Clone)]
+pub struct BootReport {
+    /// Status of each registered device.
+    pub devices: Vec<dev::Info>,
+    /// Whether interrupts are delivered through the APIC rather than the legacy PICs.
+    pub apic_enabled: bool,
+}
+
+impl BootReport {
+    /// Returns the devices that failed to initialize.
+    pub fn failures(&self) -> impl Iterator<Item=&dev::Info> {
+        self.devices.iter().filter(|d| d.status == Status::Failed)
+    }
+
+    /// Returns whether the system booted with reduced functionality.
+    pub fn is_degraded(&self) -> bool { self.failures().next().is_some() }
+}
+
+////////////////
+/// Build Info
+////////////////
+#[derive(Debug, Clone)]
+pub struct BuildInfo {
+    /// Version of the kernel.
+    pub version: &'static str,
+    /// Whether the kernel was built with debug assertions.
+    pub debug: bool,
+    /// Cargo features the kernel was built with.
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// Returns the name of the profile the enabled features correspond to.
+    pub fn profile(&self) -> &'static str {
+        let has = |feature| self.features.contains(&feature);
+        match (has("fs") && has("gfx") && has("smp"), has("net")) {
+            (true, true) => "desktop+net",
+            (true, false) => "desktop",
+            (false, _) if self.features.is_empty() => "minimal",
+            (false, _) => "custom",
+        }
+    }
+}
+
+///////////////
+// Constants
+///////////////
+
+/// Optional subsystems and whether the kernel was built with them.
+const FEATURES: [(&str, bool); 7] = [
+    ("fs", cfg!(feature = "fs")),
+    ("gfx", cfg!(feature = "gfx")),
+    ("net", cfg!(feature = "net")),
+    ("smp", cfg!(feature = "smp")),
+    ("lock-debug", cfg!(feature = "lock-debug")),
+    ("heap-debug", cfg!(feature = "heap-debug")),
+    ("heap-redzone", cfg!(feature = "heap-redzone")),
+];
 
 ///////////////
 // Utilities
 ///////////////
 
+/// Returns the version and features the kernel was built with.
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        debug: cfg!(debug_assertions),
+        features: FEATURES.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name).collect(),
+    }
+}
+
+/// Returns the report of the boot process.
+pub fn boot_report() -> BootReport {
+    let mut devices = Vec::new();
+    dev::for_each(|info| devices.push(*info));
+
+    BootReport {
+        devices,
+        apic_enabled: kernel::apic::is_enabled(),
+    }
+}
+
+/// Returns how long each step of the boot took, in the order they ran.
+pub fn boot_timings() -> Vec<Timing> { kernel::boot::timings() }
+
+/// Returns the current time in UTC.
+///
+/// Note: Without an RTC, the time of day is unknown and the epoch is returned instead.
+pub fn utc_time() -> RTC {
+    if kernel::rtc::is_present() { RTC::new() } else { RTC::EPOCH }
+}
+
+/// Returns the current time in the time zone given by `TZ`.
+///
+/// Note: Falls back to UTC if `TZ` is unset or malformed.
+pub fn local_time() -> RTC {
+    let offset = env::get("TZ").and_then(|tz| utc_offset(&tz)).unwrap_or(0);
+    utc_time().offset_by(offset)
+}
+
+/// Parses a time zone of the form `UTC`, `UTC+H`, `UTC-HH:MM` or `+HH:MM`, and returns its offset from
+/// UTC in minutes.
+pub fn utc_offset(tz: &str) -> Option<i32> {
+    let offset = tz.trim().strip_prefix("UTC").unwrap_or(tz.trim());
+    if offset.is_empty() { return Some(0); }
+
+    let (sign, offset) = match offset.as_bytes()[0] {
+        b'+' => (1, &offset[1..]),
+        b'-' => (-1, &offset[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+    let hours = hours.parse::<i32>().ok().filter(|h| *h <= 14)?;
+    let minutes = minutes.parse::<i32>().ok().filter(|m| *m < 60)?;
+
+    Some(sign * (hours * 60 + minutes))
+}
+
 /// Returns where the PIT is initialized or not.
 pub fn is_timer_initialized() -> bool { kernel::pit::is_initialized() }
 
@@ -36,7 +168,7 @@ pub fn tick_interval() -> f64 { kernel::pit::tick_interval() }
 pub fn ticks() -> usize { kernel::pit::ticks() }
 
 /// Returns the latest RTC clock update tick.
-pub fn last_rtc_update() -> usize { kernel::pit::last_rtc_update() }
+pub fn last_rtc_update() -> usize { kernel::rtc::last_update() }
 
 /// Returns the Read Time-Stamp Counter (RDTSC).
 ///
@@ -54,7 +186,40 @@ pub fn halt() { kernel::pit::halt(); }
 /// Halts the CPU for the specified duration.
 pub fn sleep(seconds: f64) { kernel::pit::sleep(seconds); }
 
+/// Returns a future that completes once the specified duration has elapsed.
+///
+/// Note: Unlike `sleep`, it lets the executor run other tasks in the meantime.
+pub fn delay(seconds: f64) -> impl Future<Output=()> { kernel::pit::delay(seconds) }
+
 /// Shuts down the machine.
-pub fn shutdown() { kernel::power::shutdown(); }
+///
+/// Note: It requires the `Power` capability.
+pub fn shutdown() -> Result<(), Error> {
+    capability::require(Capability::Power)?;
+    kernel::power::shutdown();
+    Ok(())
+}
+
+/// Reboots the machine, and only returns if the `Power` capability is missing.
+pub fn reboot() -> Result<Infallible, Error> {
+    capability::require(Capability::Power)?;
+    kernel::power::reboot()
+}
+
+/// Returns the idle policy.
+pub fn idle_policy() -> Policy { kernel::power::policy() }
+
+/// Sets the idle policy.
+///
+/// Note: It requires the `Power` capability.
+pub fn set_idle_policy(policy: Policy) -> Result<(), Error> {
+    capability::require(Capability::Power)?;
+    kernel::power::set_policy(policy);
+    Ok(())
+}
+
+/// Returns whether the CPU supports waiting with `MWAIT`.
+pub fn has_mwait() -> bool { kernel::power::has_mwait() }
 
-pub fn reboot() { kernel::power::reboot(); }
+/// Returns the frequencies of the CPU.
+pub fn cpu_frequency() -> Frequency { kernel::power::frequency() }
@@ -0,0 +1,103 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "usb")]
+use crate::drivers;
+use crate::kernel::error::Error;
+
+/////////////
+/// Speed
+/////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Speed {
+    /// 1.5 Mbit/s.
+    Low = 0x0,
+    /// 12 Mbit/s.
+    Full = 0x1,
+    /// 480 Mbit/s.
+    High = 0x2,
+}
+
+impl Speed {
+    /// Creates a new object from enum index.
+    pub fn from_index(idx: u8) -> Result<Self, Error> {
+        match idx {
+            0x0 => Ok(Self::Low),
+            0x1 => Ok(Self::Full),
+            0x2 => Ok(Self::High),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Returns the object as an enum index.
+    pub fn as_u8(&self) -> u8 { (*self) as u8 }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Full => "full",
+            Self::High => "high",
+        }
+    }
+}
+
+////////////
+/// Info
+////////////
+#[derive(Debug, Clone)]
+pub struct Info {
+    /// Index of the host controller the device is attached to.
+    pub controller: usize,
+    /// Root hub port the device is attached to.
+    pub port: u8,
+    /// Address assigned to the device.
+    pub address: u8,
+    pub speed: Speed,
+    pub vendor: u16,
+    pub product: u16,
+    /// Class, subclass and protocol of the device, or of its first interface if the device leaves them to it.
+    pub class: (u8, u8, u8),
+    /// Name of the driver bound to the device, if any.
+    pub driver: Option<&'static str>,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the enumerated USB devices.
+///
+/// Note: Always empty in kernels built without the `usb` feature.
+pub fn devices() -> Vec<Info> {
+    #[cfg(feature = "usb")]
+    return drivers::usb::devices();
+    #[cfg(not(feature = "usb"))]
+    Vec::new()
+}
+
+/// Polls the USB devices that report input, such as keyboards; spawned once by the kernel.
+#[cfg(feature = "usb")]
+pub async fn poll_task() { drivers::usb::poll_task().await; }
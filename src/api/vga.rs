@@ -25,14 +25,19 @@ use x86_64::instructions;
 pub use color::rx::*;
 pub use font::*;
 pub use palette::rx::*;
+pub use screen::Screen;
+pub use crate::drivers::vga::width::Width;
 
 use crate::drivers;
-use crate::drivers::vga::WRITER;
+use crate::kernel::capability;
+use crate::kernel::capability::Capability;
+use crate::kernel::error::Error;
 
 pub mod color;
 pub mod cursor;
 pub mod font;
 pub mod palette;
+pub mod screen;
 
 /////////////
 // Default
@@ -46,127 +51,182 @@ impl Default {
     pub const CURSOR_ENABLED: bool = true;
     pub const CURSOR_STYLE: cursor::Style = cursor::Style::Block;
     pub const PALETTE: Palette = palette::DEFAULT;
+    pub const COLOR_MAPPING: Palette = palette::DEFAULT;
 }
 
 /// Returns the rows in the VGA buffer.
 pub fn rows() -> usize {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().rows() }
+        || { drivers::vga::writer().rows() }
     )
 }
 
 /// Returns the columns in the VGA buffer.
 pub fn columns() -> usize {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().columns() }
+        || { drivers::vga::writer().columns() }
     )
 }
 
+/// Returns the number of columns the last line of the text takes up on the screen, leaving out escape
+/// sequences.
+pub fn display_width(s: &str) -> usize { drivers::vga::width::of(s) }
+
 /// Returns the cursor's position.
 pub fn get_cursor_position() -> (usize, usize) {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().get_cursor_position() }
+        || { drivers::vga::writer().get_cursor_position() }
     )
 }
 
 /// Moves the cursor to the specified position.
 pub fn set_cursor_position(row: usize, col: usize) {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().set_cursor_position(row, col); }
+        || { drivers::vga::writer().set_cursor_position(row, col); }
     );
 }
 
 /// Returns the current foreground color.
 pub fn get_foreground() -> Color {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().get_foreground() }
+        || { drivers::vga::writer().get_foreground() }
     )
 }
 
 /// Sets the foreground color.
 pub fn set_foreground(fg: Color) {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().set_foreground(fg); }
+        || { drivers::vga::writer().set_foreground(fg); }
     );
 }
 
 /// Resets the foreground color.
 pub fn reset_foreground() {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().reset_foreground(); }
+        || { drivers::vga::writer().reset_foreground(); }
     );
 }
 
 /// Returns the current background color.
 pub fn get_background() -> Color {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().get_background() }
+        || { drivers::vga::writer().get_background() }
     )
 }
 
 /// Sets the background color.
 pub fn set_background(bg: Color) {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().set_background(bg); }
+        || { drivers::vga::writer().set_background(bg); }
     );
 }
 
 /// Resets the background colour.
 pub fn reset_background() {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().reset_background(); }
+        || { drivers::vga::writer().reset_background(); }
     );
 }
 
 /// Retrieve the color of the foreground and background.
 pub fn get_color_code() -> (Color, Color) {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().get_color_code() }
+        || { drivers::vga::writer().get_color_code() }
     )
 }
 
 /// Set the color of the foreground and background.
 pub fn set_color_code(fg: Color, bg: Color) {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().set_color_code(fg, bg); }
+        || { drivers::vga::writer().set_color_code(fg, bg); }
     );
 }
 
 /// Resets the color of the foreground and background.
 pub fn reset_color_code() {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().reset_color_code(); }
+        || { drivers::vga::writer().reset_color_code(); }
     );
 }
 
 /// Returns data at the specified position from the VGA buffer.
-pub fn query_data_at(row: usize, col: usize) -> Result<(u8, u8), ()> {
+pub fn query_data_at(row: usize, col: usize) -> Result<(u8, u8), Error> {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().query_data_at(row, col) }
+        || { drivers::vga::writer().query_data_at(row, col) }
     )
 }
 
 /// Sets the VGA color palette.
 pub fn set_palette(palette: Palette) {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().set_palette(palette); }
+        || { drivers::vga::writer().set_palette(palette); }
+    );
+}
+
+/// Sets the table that 256-color and truecolor escape sequences are mapped onto.
+pub fn set_color_mapping(palette: Palette) {
+    instructions::interrupts::without_interrupts(
+        || { drivers::vga::writer().set_color_mapping(palette); }
     );
 }
 
+/// Resets the table that 256-color and truecolor escape sequences are mapped onto.
+pub fn reset_color_mapping() { set_color_mapping(Default::COLOR_MAPPING); }
+
 /// Sets the VGA font.
-pub fn set_font(font: &Font) {
+///
+/// Note: It requires the `Hardware` capability.
+pub fn set_font(font: &Font) -> Result<(), Error> {
+    capability::require(Capability::Hardware)?;
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().set_font(&font); }
+        || { drivers::vga::writer().set_font(&font); }
     );
+    Ok(())
 }
 
 /// Clears the screen.
 pub fn clear() {
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().clear(); }
+        || { drivers::vga::writer().clear(); }
     );
 }
 
+/// Returns whether printing only queues the output, to be written to the screen by `output_task`.
+pub fn is_async_output_enabled() -> bool { drivers::vga::is_async_output_enabled() }
+
+/// Makes printing queue the output instead of writing to the screen directly.
+pub fn enable_async_output() { drivers::vga::enable_async_output(); }
+
+/// Makes printing write to the screen directly.
+pub fn disable_async_output() { drivers::vga::disable_async_output(); }
+
+/// Returns whether lines are tagged with the task that writes them.
+pub fn is_output_tags_enabled() -> bool { drivers::vga::is_output_tags_enabled() }
+
+/// Tags lines with the ID and name of the task that writes them.
+pub fn enable_output_tags() { drivers::vga::enable_output_tags(); }
+
+/// Stops tagging lines with the task that writes them.
+pub fn disable_output_tags() { drivers::vga::disable_output_tags(); }
+
+/// Writes queued output to the screen; spawned once by the kernel.
+pub async fn output_task() { drivers::vga::output_task().await; }
+
+/// Returns the minutes without input after which the screen is blanked, or zero if it never is.
+pub fn get_blank_timeout() -> usize { drivers::vga::get_blank_timeout() }
+
+/// Sets the minutes without input after which the screen is blanked, or zero to never blank it.
+pub fn set_blank_timeout(minutes: usize) { drivers::vga::set_blank_timeout(minutes); }
+
+/// Returns whether the screen is blanked.
+pub fn is_blanked() -> bool { drivers::vga::is_blanked() }
+
+/// Blanks the screen after the timeout, and brings it back on input; spawned once by the kernel.
+pub async fn blank_task() { drivers::vga::blank_task().await; }
+
+/// Returns whether the VGA text mode is available, as opposed to drawing onto a framebuffer.
+pub fn is_text_mode() -> bool { drivers::vga::is_text_mode() }
+
 /// Returns whether the cursor is enabled or not.
 pub fn is_cursor_enabled() -> bool { drivers::vga::is_cursor_enabled() }
 
@@ -195,4 +255,10 @@ pub fn set_cursor_style(cursor_style: cursor::Style) { drivers::vga::set_cursor_
 pub fn reset_cursor_style() { drivers::vga::reset_cursor_style(); }
 
 /// Sets the location for the underline.
-pub fn set_underline_location(location: u8) { drivers::vga::set_underline_location(location); }
+///
+/// Note: It requires the `Hardware` capability.
+pub fn set_underline_location(location: u8) -> Result<(), Error> {
+    capability::require(Capability::Hardware)?;
+    drivers::vga::set_underline_location(location);
+    Ok(())
+}
@@ -49,6 +49,8 @@ pub const COLORS: [Color; 16] = [
 pub(super) mod rx {
     use core::str::FromStr;
 
+    use crate::kernel::error::Error;
+
     /////////////
     /// Color
     /////////////
@@ -75,7 +77,7 @@ pub(super) mod rx {
 
     impl Color {
         /// Creates a new object from enum index.
-        pub fn from_index(idx: u8) -> Result<Self, ()> {
+        pub fn from_index(idx: u8) -> Result<Self, Error> {
             match idx {
                 0x0 => Ok(Self::Black),
                 0x1 => Ok(Self::Blue),
@@ -93,12 +95,12 @@ pub(super) mod rx {
                 0xD => Ok(Self::Pink),
                 0xE => Ok(Self::Yellow),
                 0xF => Ok(Self::White),
-                _ => Err(()),
+                _ => Err(Error::OutOfBounds),
             }
         }
 
         /// Creates a new object from ANSI code.
-        pub fn from_ansi(code: u8) -> Result<Self, ()> {
+        pub fn from_ansi(code: u8) -> Result<Self, Error> {
             match code {
                 30 => Ok(Self::Black),
                 31 => Ok(Self::Red),
@@ -116,7 +118,7 @@ pub(super) mod rx {
                 95 => Ok(Self::Pink),
                 96 => Ok(Self::LightCyan),
                 97 => Ok(Self::White),
-                _ => Err(()),
+                _ => Err(Error::InvalidArgument),
             }
         }
 
@@ -167,6 +169,17 @@ pub(super) mod rx {
             }
         }
 
+        /// Returns the color whose entry in the table is nearest to the given RGB value.
+        pub fn nearest((r, g, b): (u8, u8, u8), table: &[(u8, u8, u8); 16]) -> Self {
+            let distance = |&(tr, tg, tb): &(u8, u8, u8)| -> u32 {
+                let (dr, dg, db) = (r.abs_diff(tr) as u32, g.abs_diff(tg) as u32, b.abs_diff(tb) as u32);
+                // Weighted towards green, which the eye is most sensitive to.
+                2 * dr * dr + 4 * dg * dg + 3 * db * db
+            };
+            let (idx, _) = table.iter().enumerate().min_by_key(|(_, rgb)| distance(rgb)).unwrap();
+            Self::from_index(idx as u8).unwrap()
+        }
+
         /// Returns the associated VGA register.
         pub fn associated_vga_register(&self) -> u8 {
             match self {
@@ -191,7 +204,7 @@ pub(super) mod rx {
     }
 
     impl FromStr for Color {
-        type Err = ();
+        type Err = Error;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
             match s {
@@ -211,7 +224,7 @@ pub(super) mod rx {
                 "pink" => Ok(Self::Pink),
                 "yellow" => Ok(Self::Yellow),
                 "white" => Ok(Self::White),
-                _ => Err(()),
+                _ => Err(Error::InvalidArgument),
             }
         }
     }
@@ -22,6 +22,8 @@
 
 use core::str::FromStr;
 
+use crate::kernel::error::Error;
+
 /////////////
 // Globals
 /////////////
@@ -44,11 +46,11 @@ pub enum Style {
 
 impl Style {
     /// Creates a new object from enum index.
-    pub fn from_index(idx: u8) -> Result<Self, ()> {
+    pub fn from_index(idx: u8) -> Result<Self, Error> {
         match idx {
             0x0 => Ok(Self::Underscore),
             0x1 => Ok(Self::Block),
-            _ => Err(()),
+            _ => Err(Error::OutOfBounds),
         }
     }
 
@@ -73,13 +75,13 @@ impl Style {
 }
 
 impl FromStr for Style {
-    type Err = ();
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "underscore" => Ok(Self::Underscore),
             "block" => Ok(Self::Block),
-            _ => Err(())
+            _ => Err(Error::InvalidArgument)
         }
     }
 }
@@ -22,6 +22,8 @@
 
 use rx::Palette;
 
+use crate::kernel::error::Error;
+
 /// Default Color Palette.
 pub const DEFAULT: Palette = Palette {
     colors: [
@@ -294,3 +296,38 @@ pub(super) mod rx {
         pub colors: [(u8, u8, u8); 16],
     }
 }
+
+/// Names of the available palettes.
+pub const NAMES: [&str; 12] = [
+    "default",
+    "gruvbox",
+    "material",
+    "material-hc",
+    "material-darker",
+    "material-darker-hc",
+    "material-lighter",
+    "material-lighter-hc",
+    "material-ocean",
+    "material-ocean-hc",
+    "material-palenight",
+    "material-palenight-hc",
+];
+
+/// Returns the palette with the given name.
+pub fn from_name(name: &str) -> Result<Palette, Error> {
+    match name {
+        "default" => Ok(DEFAULT),
+        "gruvbox" => Ok(GRUVBOX),
+        "material" => Ok(MATERIAL),
+        "material-hc" => Ok(MATERIAL_HC),
+        "material-darker" => Ok(MATERIAL_DARKER),
+        "material-darker-hc" => Ok(MATERIAL_DARKER_HC),
+        "material-lighter" => Ok(MATERIAL_LIGHTER),
+        "material-lighter-hc" => Ok(MATERIAL_LIGHTER_HC),
+        "material-ocean" => Ok(MATERIAL_OCEAN),
+        "material-ocean-hc" => Ok(MATERIAL_OCEAN_HC),
+        "material-palenight" => Ok(MATERIAL_PALENIGHT),
+        "material-palenight-hc" => Ok(MATERIAL_PALENIGHT_HC),
+        _ => Err(Error::InvalidArgument),
+    }
+}
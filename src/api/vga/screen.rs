@@ -0,0 +1,205 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+
+use x86_64::instructions;
+
+use crate::api::vga::{Color, Default};
+use crate::drivers::vga;
+use crate::encodings::ASCII;
+use crate::encodings::{Charset, CP437};
+use crate::kernel::error::Error;
+
+pub use crate::drivers::vga::Rect;
+
+// Screen Regions
+//
+// A screen is a rectangular region of the text screen that a program writes to on its own, with a
+// cursor, colors, line wrapping and scrolling of its own. Output is clipped to the region, and the
+// console, as well as any other region, leaves its cells alone; regions may not overlap. This lets a
+// status bar or a log pane stay in place while the shell keeps scrolling in the remaining rows.
+//
+// Positions are relative to the top left corner of the region. The cells are handed back to the
+// console, blanked, once the screen is dropped.
+//
+// Escape sequences are not interpreted, and control characters besides line feeds, carriage returns,
+// backspaces and tabs are dropped; colors are set through the methods instead.
+
+//////////////
+/// Screen
+//////////////
+pub struct Screen {
+    rect: Rect,
+    row_pos: usize,
+    col_pos: usize,
+    fg: Color,
+    bg: Color,
+}
+
+impl Screen {
+    /// Reserves the given rectangle of the screen and clears it.
+    pub fn new(row: usize, col: usize, rows: usize, columns: usize) -> Result<Self, Error> {
+        let rect = Rect { row, col, rows, columns };
+        instructions::interrupts::without_interrupts(|| vga::writer().reserve(rect))?;
+
+        let mut screen = Screen {
+            rect,
+            row_pos: 0,
+            col_pos: 0,
+            fg: Default::FOREGROUND,
+            bg: Default::BACKGROUND,
+        };
+        screen.clear();
+
+        Ok(screen)
+    }
+
+    /// Returns the area of the screen covered by the region.
+    pub fn rect(&self) -> Rect { self.rect }
+
+    /// Returns the rows in the region.
+    pub fn rows(&self) -> usize { self.rect.rows }
+
+    /// Returns the columns in the region.
+    pub fn columns(&self) -> usize { self.rect.columns }
+
+    /// Returns the cursor's position.
+    pub fn get_cursor_position(&self) -> (usize, usize) { (self.row_pos, self.col_pos) }
+
+    /// Sets the cursor to the specified position.
+    pub fn set_cursor_position(&mut self, row: usize, col: usize) {
+        self.row_pos = row.min(self.rows() - 1);
+        self.col_pos = col.min(self.columns() - 1);
+    }
+
+    /// Returns the color of the foreground and background.
+    pub fn get_color_code(&self) -> (Color, Color) { (self.fg, self.bg) }
+
+    /// Sets the color of the foreground and background.
+    pub fn set_color_code(&mut self, fg: Color, bg: Color) {
+        self.fg = fg;
+        self.bg = bg;
+    }
+
+    /// Sets the foreground color.
+    pub fn set_foreground(&mut self, fg: Color) { self.fg = fg; }
+
+    /// Sets the background color.
+    pub fn set_background(&mut self, bg: Color) { self.bg = bg; }
+
+    /// Resets the color of the foreground and background.
+    pub fn reset_color_code(&mut self) { self.set_color_code(Default::FOREGROUND, Default::BACKGROUND); }
+
+    /// Fills the region with blanks in the current colors and moves the cursor to its origin.
+    pub fn clear(&mut self) {
+        let Self { rect, fg, bg, .. } = *self;
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut writer = vga::writer();
+                for row in rect.row..rect.bottom() {
+                    for col in rect.col..rect.right() {
+                        writer.put_region_char(row, col, ASCII::<u8>::SP, fg, bg);
+                    }
+                }
+                writer.refresh();
+            }
+        );
+        self.row_pos = 0;
+        self.col_pos = 0;
+    }
+
+    /// Fills the rest of the cursor's row with blanks in the current colors.
+    pub fn clear_line(&mut self) {
+        let Self { rect, row_pos, col_pos, fg, bg } = *self;
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut writer = vga::writer();
+                for col in (rect.col + col_pos)..rect.right() {
+                    writer.put_region_char(rect.row + row_pos, col, ASCII::<u8>::SP, fg, bg);
+                }
+                writer.refresh();
+            }
+        );
+    }
+
+    /// Writes the characters at the cursor, with the screen locked only once.
+    fn write_chars(&mut self, chars: impl Iterator<Item=char>) {
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut writer = vga::writer();
+                for c in chars {
+                    self.write_char(&mut writer, c);
+                }
+                writer.refresh();
+            }
+        );
+    }
+
+    /// Writes the character at the cursor, wrapping and scrolling as needed.
+    ///
+    /// Note: Characters that are not in the code page are shown as its replacement.
+    fn write_char(&mut self, writer: &mut vga::Writer, c: char) {
+        match c {
+            ASCII::<char>::LF => self.linefeed(writer),
+            ASCII::<char>::CR => self.col_pos = 0,
+            ASCII::<char>::BS => self.col_pos = self.col_pos.saturating_sub(1),
+            ASCII::<char>::HT => {
+                let tab_width = (vga::get_tab_width() as usize).max(1);
+                for _ in 0..(tab_width - self.col_pos % tab_width) {
+                    self.write_char(writer, ASCII::<char>::SP);
+                }
+            }
+            c if c < ASCII::<char>::SP => {}
+            c => {
+                if self.col_pos >= self.columns() { self.linefeed(writer); }
+                let byte = CP437::encode_lossy(c);
+                writer.put_region_char(self.rect.row + self.row_pos, self.rect.col + self.col_pos, byte, self.fg, self.bg);
+                self.col_pos += 1;
+            }
+        }
+    }
+
+    /// Moves the cursor to the start of the next row, scrolling the region at the bottom.
+    fn linefeed(&mut self, writer: &mut vga::Writer) {
+        if self.row_pos + 1 < self.rows() {
+            self.row_pos += 1;
+        } else {
+            writer.scroll_region(self.rect, self.fg, self.bg);
+        }
+        self.col_pos = 0;
+    }
+}
+
+impl fmt::Write for Screen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_chars(s.chars());
+        Ok(())
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let rect = self.rect;
+        instructions::interrupts::without_interrupts(|| vga::writer().release(rect));
+    }
+}
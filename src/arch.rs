@@ -0,0 +1,181 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::kernel::error::Error;
+
+#[cfg(target_arch = "riscv64")]
+pub mod riscv64;
+#[cfg(target_arch = "x86_64")]
+pub mod x86_64;
+
+// Architecture
+//
+// Code outside of this module should not reach for the instructions and registers of a particular
+// processor. Instead, the operations that differ from one architecture to the next are collected in
+// the `Arch` trait, and each supported architecture implements it once. `Current` names the
+// implementation for the target the kernel is built for, so generic code can simply write
+// `Current::disable_interrupts()` and be ported by adding an implementation rather than by
+// editing every caller.
+//
+// Operation            x86_64                                      riscv64
+// Interrupt control    RFLAGS.IF through `cli`, `sti` and `hlt`    sstatus.SIE and `wfi`
+// Paging               4-level page tables                         none yet, translation is off
+// Port I/O             the `in` and `out` instructions             none, devices are memory-mapped
+// Timers               the PIT ticks and the time-stamp counter    the `time` CSR
+//
+// Only x86_64 runs the whole kernel. The riscv64 port boots to a serial console with a timer and a
+// heap; the rest of the kernel is x86_64-only until its hardware dependencies (the APIC, the VGA
+// ports, the CMOS, ...) are moved behind this facade.
+
+////////////////
+/// Page Flags
+////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageFlags {
+    /// Whether the page can be written to.
+    pub writable: bool,
+    /// Whether code can be executed from the page.
+    pub executable: bool,
+    /// Whether the page is accessible from user mode.
+    pub user: bool,
+}
+
+impl PageFlags {
+    /// Flags for kernel data: writable, not executable and not accessible from user mode.
+    pub const DATA: Self = Self { writable: true, executable: false, user: false };
+}
+
+////////////
+/// Arch
+////////////
+pub trait Arch {
+    /// Name of the architecture.
+    const NAME: &'static str;
+    /// Size of a page in bytes.
+    const PAGE_SIZE: usize;
+
+    /// Enables interrupts on the current processor.
+    fn enable_interrupts();
+
+    /// Disables interrupts on the current processor.
+    fn disable_interrupts();
+
+    /// Returns whether interrupts are enabled on the current processor.
+    fn are_interrupts_enabled() -> bool;
+
+    /// Runs the given closure with interrupts disabled, restoring their previous state afterwards.
+    fn without_interrupts<R>(f: impl FnOnce() -> R) -> R;
+
+    /// Halts the processor until the next interrupt.
+    fn wait_for_interrupt();
+
+    /// Translates a virtual address into a physical address, if mapped.
+    fn translate(virt: usize) -> Option<usize>;
+
+    /// Returns the virtual address through which the kernel reaches the given physical address.
+    fn phys_to_virt(phys: usize) -> usize;
+
+    /// Maps the page at the given virtual address to the frame at the given physical address.
+    ///
+    /// # Safety
+    ///
+    /// The frame must be owned by the caller, or be device memory it drives, so that the mapping does
+    /// not alias memory in use elsewhere.
+    unsafe fn map_page(virt: usize, phys: usize, flags: PageFlags) -> Result<(), Error>;
+
+    /// Unmaps the page at the given virtual address, returning the physical address it was mapped to.
+    ///
+    /// Note: The frame is not released.
+    ///
+    /// # Safety
+    ///
+    /// The page must have been mapped by the caller, and nothing may reference memory through it anymore.
+    unsafe fn unmap_page(virt: usize) -> Result<usize, Error>;
+
+    /// Reads a byte from the given I/O port.
+    ///
+    /// # Safety
+    ///
+    /// Reading may have side effects on the device, such as acknowledging an interrupt or consuming
+    /// data, so the caller must own the port.
+    unsafe fn port_read_u8(port: u16) -> u8;
+
+    /// Writes a byte to the given I/O port.
+    ///
+    /// # Safety
+    ///
+    /// Writing may reconfigure the device, e.g. start a DMA transfer into arbitrary memory, so the
+    /// caller must own the port and write a value the device expects.
+    unsafe fn port_write_u8(port: u16, value: u8);
+
+    /// Reads a word from the given I/O port.
+    ///
+    /// # Safety
+    ///
+    /// Reading may have side effects on the device, such as acknowledging an interrupt or consuming
+    /// data, so the caller must own the port.
+    unsafe fn port_read_u16(port: u16) -> u16;
+
+    /// Writes a word to the given I/O port.
+    ///
+    /// # Safety
+    ///
+    /// Writing may reconfigure the device, e.g. start a DMA transfer into arbitrary memory, so the
+    /// caller must own the port and write a value the device expects.
+    unsafe fn port_write_u16(port: u16, value: u16);
+
+    /// Reads a double word from the given I/O port.
+    ///
+    /// # Safety
+    ///
+    /// Reading may have side effects on the device, such as acknowledging an interrupt or consuming
+    /// data, so the caller must own the port.
+    unsafe fn port_read_u32(port: u16) -> u32;
+
+    /// Writes a double word to the given I/O port.
+    ///
+    /// # Safety
+    ///
+    /// Writing may reconfigure the device, e.g. start a DMA transfer into arbitrary memory, so the
+    /// caller must own the port and write a value the device expects.
+    unsafe fn port_write_u32(port: u16, value: u32);
+
+    /// Returns the number of timer ticks since the timer was initialized.
+    fn ticks() -> usize;
+
+    /// Returns the duration between successive timer ticks in seconds.
+    fn tick_interval() -> f64;
+
+    /// Returns a monotonic, high-resolution cycle count.
+    fn timestamp() -> u64;
+}
+
+///////////
+// Types
+///////////
+
+/// Architecture the kernel is built for.
+#[cfg(target_arch = "riscv64")]
+pub type Current = self::riscv64::Riscv64;
+/// Architecture the kernel is built for.
+#[cfg(target_arch = "x86_64")]
+pub type Current = self::x86_64::X86_64;
@@ -0,0 +1,172 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::arch::{asm, global_asm};
+
+use linked_list_allocator::LockedHeap;
+
+use crate::kernel::error::Error;
+
+use super::{Arch, PageFlags};
+
+pub mod timer;
+pub mod uart;
+
+// RISC-V
+//
+// The port targets the QEMU `virt` machine, where OpenSBI runs in machine mode and loads the kernel
+// at 0x8020_0000 in supervisor mode, with translation off and a hart ID in `a0`. Only the bare
+// minimum is brought up: a serial console on the 16550 UART, the `time` CSR as the clock and a heap
+// in the kernel image. Interrupts stay off; sleeping arms the SBI timer and waits for it with `wfi`,
+// which resumes on a pending interrupt even when interrupts are disabled.
+//
+// Build with `cargo build --target riscv64gc-unknown-none-elf` and run the resulting ELF with
+// `qemu-system-riscv64 -machine virt -nographic -kernel`.
+
+////////////////
+// Attributes
+////////////////
+
+/// Size of the heap in the kernel image.
+const HEAP_SIZE: usize = 1024 * 1024;
+
+/// The `SIE` bit of `sstatus`, which enables interrupts in supervisor mode.
+const SSTATUS_SIE: usize = 1 << 1;
+
+/// Paging mode of `satp` when translation is off.
+const SATP_MODE_BARE: usize = 0;
+
+/////////////
+// Globals
+/////////////
+
+/// Memory backing the heap.
+static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
+
+/// Global heap allocator.
+#[global_allocator]
+static ALLOCATOR: LockedHeap = LockedHeap::empty();
+
+////////////
+// Entry
+////////////
+
+// Clears the BSS, switches to the boot stack and calls `kernel_main`, which never returns.
+global_asm!(
+    r#"
+    .section .text.entry
+    .globl _start
+_start:
+    la t0, __bss_start
+    la t1, __bss_end
+1:
+    bgeu t0, t1, 2f
+    sd zero, 0(t0)
+    addi t0, t0, 8
+    j 1b
+2:
+    la sp, __stack_top
+    call kernel_main
+3:
+    wfi
+    j 3b
+    "#
+);
+
+///////////////
+/// Riscv64
+///////////////
+pub struct Riscv64;
+
+impl Arch for Riscv64 {
+    const NAME: &'static str = "riscv64";
+    const PAGE_SIZE: usize = 4096;
+
+    fn enable_interrupts() { unsafe { asm!("csrs sstatus, {}", in(reg) SSTATUS_SIE) }; }
+
+    fn disable_interrupts() { unsafe { asm!("csrc sstatus, {}", in(reg) SSTATUS_SIE) }; }
+
+    fn are_interrupts_enabled() -> bool {
+        let sstatus: usize;
+        unsafe { asm!("csrr {}, sstatus", out(reg) sstatus) };
+        sstatus & SSTATUS_SIE != 0
+    }
+
+    fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+        let enabled = Self::are_interrupts_enabled();
+        if enabled { Self::disable_interrupts(); }
+        let res = f();
+        if enabled { Self::enable_interrupts(); }
+        res
+    }
+
+    fn wait_for_interrupt() { unsafe { asm!("wfi") }; }
+
+    /// Note: Page tables are not set up yet, so addresses only translate while translation is off.
+    fn translate(virt: usize) -> Option<usize> {
+        if is_translation_off() { Some(virt) } else { None }
+    }
+
+    fn phys_to_virt(phys: usize) -> usize { phys }
+
+    unsafe fn map_page(_virt: usize, _phys: usize, _flags: PageFlags) -> Result<(), Error> { Err(Error::Unsupported) }
+
+    unsafe fn unmap_page(_virt: usize) -> Result<usize, Error> { Err(Error::Unsupported) }
+
+    // RISC-V has no I/O space; devices are memory-mapped. Reads float high and writes go nowhere, as
+    // they would on an unpopulated bus.
+
+    unsafe fn port_read_u8(_port: u16) -> u8 { u8::MAX }
+
+    unsafe fn port_write_u8(_port: u16, _value: u8) {}
+
+    unsafe fn port_read_u16(_port: u16) -> u16 { u16::MAX }
+
+    unsafe fn port_write_u16(_port: u16, _value: u16) {}
+
+    unsafe fn port_read_u32(_port: u16) -> u32 { u32::MAX }
+
+    unsafe fn port_write_u32(_port: u16, _value: u32) {}
+
+    fn ticks() -> usize { timer::ticks() }
+
+    fn tick_interval() -> f64 { timer::tick_interval() }
+
+    fn timestamp() -> u64 { timer::time() }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Initializes the console and the heap.
+pub fn init() {
+    uart::init();
+    unsafe { ALLOCATOR.lock().init(HEAP.as_mut_ptr(), HEAP_SIZE) };
+}
+
+/// Returns whether address translation is off.
+fn is_translation_off() -> bool {
+    let satp: usize;
+    unsafe { asm!("csrr {}, satp", out(reg) satp) };
+    satp >> 60 == SATP_MODE_BARE
+}
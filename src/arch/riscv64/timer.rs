@@ -0,0 +1,98 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::arch::asm;
+
+use super::{Arch, Riscv64};
+
+// Timer
+//
+// The `time` CSR counts up at a fixed rate, 10 MHz on the `virt` machine, and serves as the clock.
+// Ticks are derived from it at about the rate the PIT runs at on x86_64, so that code measuring time in
+// ticks behaves alike on both. Timer interrupts are requested through the Timer extension of the
+// Supervisor Binary Interface (SBI).
+//
+// SBI Specification: https://github.com/riscv-non-isa/riscv-sbi-doc
+
+////////////////
+// Attributes
+////////////////
+
+/// Frequency of the `time` CSR in Hz.
+pub const TIMEBASE_FREQUENCY: u64 = 10_000_000;
+
+/// Number of ticks per second.
+const TICK_RATE: u64 = 1000;
+
+/// Extension ID of the SBI Timer extension ("TIME").
+const SBI_TIMER_EXTENSION: usize = 0x5449_4D45;
+/// Function ID of `sbi_set_timer`.
+const SBI_SET_TIMER: usize = 0x0;
+
+/// The `STIE` bit of `sie`, which enables the supervisor timer interrupt.
+const SIE_STIE: usize = 1 << 5;
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the value of the `time` CSR.
+pub fn time() -> u64 {
+    let time: u64;
+    unsafe { asm!("rdtime {}", out(reg) time) };
+    time
+}
+
+/// Returns the ticks elapsed since boot.
+pub fn ticks() -> usize { (time() / (TIMEBASE_FREQUENCY / TICK_RATE)) as usize }
+
+/// Returns the duration between successive ticks.
+pub fn tick_interval() -> f64 { 1.0 / TICK_RATE as f64 }
+
+/// Returns the time elapsed since boot.
+pub fn uptime() -> f64 { time() as f64 / TIMEBASE_FREQUENCY as f64 }
+
+/// Requests a timer interrupt once `time` reaches the given deadline.
+pub fn set_timer(deadline: u64) {
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") deadline as usize => _,
+            lateout("a1") _,
+            in("a6") SBI_SET_TIMER,
+            in("a7") SBI_TIMER_EXTENSION,
+        );
+    }
+}
+
+/// Halts the CPU for the specified duration.
+pub fn sleep(seconds: f64) {
+    let deadline = time() + (seconds * TIMEBASE_FREQUENCY as f64) as u64;
+
+    set_timer(deadline);
+    unsafe { asm!("csrs sie, {}", in(reg) SIE_STIE) };
+    while time() < deadline {
+        Riscv64::wait_for_interrupt();
+    }
+    unsafe { asm!("csrc sie, {}", in(reg) SIE_STIE) };
+    set_timer(u64::MAX);
+}
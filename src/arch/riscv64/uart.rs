@@ -0,0 +1,148 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+use core::ptr;
+
+use spin::Mutex;
+
+use super::{Arch, Riscv64};
+
+// UART
+//
+// The `virt` machine has a 16550-compatible UART memory-mapped at 0x1000_0000, with one byte per
+// register. OpenSBI has already set it up to its own liking, so it is only reprogrammed for 8 data
+// bits, no parity and one stop bit, with the FIFOs on and its interrupts off.
+
+////////////////
+// Attributes
+////////////////
+
+/// Base address of the UART.
+const BASE: usize = 0x1000_0000;
+
+/// Bit of the line status register set when a byte has been received.
+const LSR_DATA_READY: u8 = 1 << 0;
+/// Bit of the line status register set when the transmitter can take another byte.
+const LSR_THR_EMPTY: u8 = 1 << 5;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Console UART.
+static UART: Mutex<Uart> = Mutex::new(Uart::new(BASE));
+
+////////////////
+/// Register
+////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+enum Register {
+    Data = 0x0,
+    InterruptEnable = 0x1,
+    FifoControl = 0x2,
+    LineControl = 0x3,
+    LineStatus = 0x5,
+}
+
+////////////
+/// Uart
+////////////
+pub struct Uart {
+    base: usize,
+}
+
+impl Uart {
+    /// Creates a new object.
+    pub const fn new(base: usize) -> Self { Self { base } }
+
+    /// Sets the line to 8N1, enables the FIFOs and disables interrupts.
+    pub fn init(&mut self) {
+        self.write(Register::InterruptEnable, 0x00);
+        self.write(Register::LineControl, 0x03);
+        self.write(Register::FifoControl, 0x07);
+    }
+
+    /// Sends a byte, waiting for the transmitter if needed.
+    pub fn send(&mut self, byte: u8) {
+        while self.read(Register::LineStatus) & LSR_THR_EMPTY == 0 {}
+        self.write(Register::Data, byte);
+    }
+
+    /// Returns the received byte, if any.
+    pub fn receive(&mut self) -> Option<u8> {
+        if self.read(Register::LineStatus) & LSR_DATA_READY == 0 { return None; }
+        Some(self.read(Register::Data))
+    }
+
+    /// Reads a register.
+    fn read(&self, register: Register) -> u8 { unsafe { ptr::read_volatile((self.base + register as usize) as *const u8) } }
+
+    /// Writes a register.
+    fn write(&mut self, register: Register, value: u8) {
+        unsafe { ptr::write_volatile((self.base + register as usize) as *mut u8, value) }
+    }
+}
+
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            // Terminals expect a carriage return before each line feed.
+            if byte == b'\n' { self.send(b'\r'); }
+            self.send(byte);
+        }
+        Ok(())
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Initializes the console UART.
+pub(super) fn init() { Riscv64::without_interrupts(|| UART.lock().init()); }
+
+/// Returns the byte received on the console, if any.
+pub fn receive() -> Option<u8> { Riscv64::without_interrupts(|| UART.lock().receive()) }
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+
+    Riscv64::without_interrupts(|| { UART.lock().write_fmt(args).unwrap(); });
+}
+
+////////////
+// Macros
+////////////
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::arch::riscv64::uart::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
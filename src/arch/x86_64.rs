@@ -0,0 +1,109 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::instructions;
+use x86_64::instructions::port::{PortRead, PortWrite};
+use x86_64::structures::paging::{Mapper, Page, PageTableFlags, PhysFrame, Size4KiB, Translate};
+
+use crate::kernel::{memory, pit};
+use crate::kernel::error::Error;
+use crate::kernel::memory::frame::GlobalFrameAllocator;
+
+use super::{Arch, PageFlags};
+
+//////////////
+/// X86_64
+//////////////
+pub struct X86_64;
+
+impl Arch for X86_64 {
+    const NAME: &'static str = "x86_64";
+    const PAGE_SIZE: usize = memory::PAGE_SIZE;
+
+    fn enable_interrupts() { instructions::interrupts::enable(); }
+
+    fn disable_interrupts() { instructions::interrupts::disable(); }
+
+    fn are_interrupts_enabled() -> bool { instructions::interrupts::are_enabled() }
+
+    fn without_interrupts<R>(f: impl FnOnce() -> R) -> R { instructions::interrupts::without_interrupts(f) }
+
+    fn wait_for_interrupt() { instructions::hlt(); }
+
+    fn translate(virt: usize) -> Option<usize> {
+        let mapper = unsafe { memory::mapper() };
+        mapper.translate_addr(VirtAddr::new(virt as u64)).map(|addr| addr.as_u64() as usize)
+    }
+
+    fn phys_to_virt(phys: usize) -> usize { memory::phys_to_virt_addr(PhysAddr::new(phys as u64)).as_u64() as usize }
+
+    unsafe fn map_page(virt: usize, phys: usize, flags: PageFlags) -> Result<(), Error> {
+        let page: Page<Size4KiB> = Page::containing_address(VirtAddr::new(virt as u64));
+        let frame = PhysFrame::containing_address(PhysAddr::new(phys as u64));
+
+        let mut table_flags = PageTableFlags::PRESENT;
+        if flags.writable { table_flags |= PageTableFlags::WRITABLE; }
+        if !flags.executable { table_flags |= PageTableFlags::NO_EXECUTE; }
+        if flags.user { table_flags |= PageTableFlags::USER_ACCESSIBLE; }
+
+        let mut mapper = memory::mapper();
+        match mapper.map_to(page, frame, table_flags, &mut GlobalFrameAllocator) {
+            Ok(flush) => {
+                flush.flush();
+                Ok(())
+            }
+            Err(_) => Err(Error::Failed),
+        }
+    }
+
+    unsafe fn unmap_page(virt: usize) -> Result<usize, Error> {
+        let page: Page<Size4KiB> = Page::containing_address(VirtAddr::new(virt as u64));
+
+        let mut mapper = memory::mapper();
+        match mapper.unmap(page) {
+            Ok((frame, flush)) => {
+                flush.flush();
+                Ok(frame.start_address().as_u64() as usize)
+            }
+            Err(_) => Err(Error::NotFound),
+        }
+    }
+
+    unsafe fn port_read_u8(port: u16) -> u8 { u8::read_from_port(port) }
+
+    unsafe fn port_write_u8(port: u16, value: u8) { u8::write_to_port(port, value); }
+
+    unsafe fn port_read_u16(port: u16) -> u16 { u16::read_from_port(port) }
+
+    unsafe fn port_write_u16(port: u16, value: u16) { u16::write_to_port(port, value); }
+
+    unsafe fn port_read_u32(port: u16) -> u32 { u32::read_from_port(port) }
+
+    unsafe fn port_write_u32(port: u16, value: u32) { u32::write_to_port(port, value); }
+
+    fn ticks() -> usize { pit::ticks() }
+
+    fn tick_interval() -> f64 { pit::tick_interval() }
+
+    fn timestamp() -> u64 { pit::rdtsc() }
+}
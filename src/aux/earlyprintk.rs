@@ -0,0 +1,171 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use x86_64::PhysAddr;
+
+use crate::drivers::{debugcon, vga};
+use crate::drivers::vga::{TEXT_BUFFER, TEXT_BUFFER_COLS, TEXT_BUFFER_ROWS};
+use crate::kernel::{boot, memory};
+use crate::kernel::error::Error;
+
+// Early Console
+//
+// The VGA writer and the logger only come into their own once the devices they lean on are up. A
+// failure before then, while the memory map is read or the descriptor tables and the heap are set up,
+// would go unseen. The early console needs nothing but static memory: it writes straight into the VGA
+// text buffer, keeping its position in an atomic, and to the debug console of the emulator.
+//
+// The logger prints through the early console until the core devices, the heap among them, are
+// initialized. The hand-off then moves the writer's cursor below the early messages, so the boot log
+// carries on where the early console left off. Every message also reaches the log's ring buffer
+// along the way, as usual.
+
+////////////////
+// Attributes
+////////////////
+
+/// Color code of the early messages: light grey on black.
+const COLOR_CODE: u16 = 0x07;
+/// Character drawn in place of bytes the text mode font cannot show.
+const REPLACEMENT_CHAR: u8 = 0xFE;
+/// Marks the position as unset, i.e. the screen has not been written to yet.
+const UNSET: usize = usize::MAX;
+
+////////////
+// States
+////////////
+
+/// Flag to check whether the early console is in use or not.
+static ACTIVE: AtomicBool = AtomicBool::new(true);
+
+/// Offset of the next cell to write in the VGA text buffer.
+static POSITION: AtomicUsize = AtomicUsize::new(UNSET);
+
+//////////////////////
+/// Early Writer
+//////////////////////
+struct EarlyWriter;
+
+impl fmt::Write for EarlyWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        debugcon::write_unsynchronized(s);
+        if is_text_mode() { write_text_buffer(s); }
+        Ok(())
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether the early console is in use or not.
+pub fn is_active() -> bool { ACTIVE.load(Ordering::Relaxed) }
+
+/// Returns whether the early console has written to the screen.
+pub(crate) fn has_screen_output() -> bool { POSITION.load(Ordering::Relaxed) != UNSET }
+
+/// Hands the screen over to the VGA writer, which continues below the early messages.
+pub(crate) fn handoff() -> Result<(), Error> {
+    ACTIVE.store(false, Ordering::Relaxed);
+
+    let position = POSITION.load(Ordering::Relaxed);
+    if position != UNSET && vga::is_text_mode() {
+        let col = position % TEXT_BUFFER_COLS;
+        let row = position / TEXT_BUFFER_COLS + if col > 0 { 1 } else { 0 };
+        vga::writer().set_cursor_position(row.min(TEXT_BUFFER_ROWS - 1), 0);
+    }
+
+    Ok(())
+}
+
+/// Returns whether the VGA text buffer can be written to.
+///
+/// Note: `vga::is_text_mode` is not used, as it sets up the framebuffer, which must wait for the boot
+/// protocol to be known.
+fn is_text_mode() -> bool { boot::protocol().is_some() && boot::framebuffer().is_none() }
+
+/// Writes the string to the VGA text buffer, scrolling once the screen is full.
+fn write_text_buffer(s: &str) {
+    const CELLS: usize = TEXT_BUFFER_ROWS * TEXT_BUFFER_COLS;
+
+    let buffer = memory::phys_to_virt_addr(PhysAddr::new(TEXT_BUFFER)).as_mut_ptr::<u16>();
+    let blank = COLOR_CODE << 8 | b' ' as u16;
+
+    let mut position = POSITION.load(Ordering::Relaxed);
+    if position == UNSET {
+        // Clear whatever the firmware and the bootloader left on the screen.
+        for cell in 0..CELLS {
+            unsafe { ptr::write_volatile(buffer.add(cell), blank) };
+        }
+        position = 0;
+    }
+
+    for byte in s.bytes() {
+        match byte {
+            b'\n' => position = (position / TEXT_BUFFER_COLS + 1) * TEXT_BUFFER_COLS,
+            _ => {
+                let byte = if (0x20..0x7F).contains(&byte) { byte } else { REPLACEMENT_CHAR };
+                unsafe { ptr::write_volatile(buffer.add(position), COLOR_CODE << 8 | byte as u16) };
+                position += 1;
+            }
+        }
+
+        if position >= CELLS {
+            for cell in TEXT_BUFFER_COLS..CELLS {
+                unsafe { ptr::write_volatile(buffer.add(cell - TEXT_BUFFER_COLS), ptr::read_volatile(buffer.add(cell))) };
+            }
+            for cell in (CELLS - TEXT_BUFFER_COLS)..CELLS {
+                unsafe { ptr::write_volatile(buffer.add(cell), blank) };
+            }
+            position -= TEXT_BUFFER_COLS;
+        }
+    }
+
+    POSITION.store(position, Ordering::Relaxed);
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use fmt::Write;
+
+    EarlyWriter.write_fmt(args).ok();
+}
+
+////////////
+// Macros
+////////////
+
+#[macro_export]
+macro_rules! early_print {
+    ($($arg:tt)*) => ($crate::aux::earlyprintk::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! early_println {
+    () => ($crate::early_print!("\n"));
+    ($fmt:expr) => ($crate::early_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::early_print!(concat!($fmt, "\n"), $($arg)*));
+}
@@ -0,0 +1,109 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+
+use crate::aux::logger;
+use crate::drivers::{debugcon, serial, vga};
+
+// Emergency Output
+//
+// The regular `print!` path serializes writers through the locks of the VGA writer and the serial
+// port. An exception or a panic may strike while one of those locks is held by the interrupted
+// code, in which case printing from the handler would spin forever. The emergency path never waits:
+// it writes to the serial port and the debug console directly, and to the VGA text buffer through the
+// writer if it is free or straight into video memory otherwise. The debug console needs no setup, so
+// even a failure early in the boot, before the serial port is initialized, reaches the host.
+//
+// It trades consistency for progress and must only be used from exception and panic handlers.
+//
+// As the screen may have scrolled past the events leading up to a crash, or they may never have
+// been shown at the log level in use, the handlers finish by replaying the latest messages from the
+// log's ring buffer.
+
+////////////////
+// Attributes
+////////////////
+
+/// Number of log messages replayed after a crash.
+const RECENT_LOG_LENGTH: usize = 12;
+
+/////////////////////////
+/// Emergency Writer
+/////////////////////////
+struct EmergencyWriter;
+
+impl fmt::Write for EmergencyWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        vga::write_unsynchronized(s);
+        serial::write_unsynchronized(s);
+        debugcon::write_unsynchronized(s);
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use fmt::Write;
+
+    EmergencyWriter.write_fmt(args).ok();
+}
+
+/// Prints the latest messages from the log's ring buffer.
+pub fn print_recent_log() {
+    let mut total = 0;
+    logger::for_each_record_unsynchronized(|_| total += 1);
+    if total == 0 { return; }
+
+    _print(format_args!("--- last {} log messages ---\n", total.min(RECENT_LOG_LENGTH)));
+    let mut idx = 0;
+    logger::for_each_record_unsynchronized(
+        |record| {
+            idx += 1;
+            if idx + RECENT_LOG_LENGTH <= total { return; }
+            match record.uptime() {
+                Some(uptime) => _print(format_args!("[{:13.4}] ", uptime)),
+                None => _print(format_args!("[--------.----] ")),
+            }
+            _print(format_args!("{:<7} {}", record.log_level().as_str(), record.message()));
+            match record.repeats() {
+                0 => _print(format_args!("\n")),
+                repeats => _print(format_args!(" (repeated {} times)\n", repeats)),
+            }
+        }
+    );
+}
+
+////////////
+// Macros
+////////////
+
+#[macro_export]
+macro_rules! emergency_print {
+    ($($arg:tt)*) => ($crate::aux::emergency::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! emergency_println {
+    () => ($crate::emergency_print!("\n"));
+    ($($arg:tt)*) => ($crate::emergency_print!("{}\n", format_args!($($arg)*)));
+}
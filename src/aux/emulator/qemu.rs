@@ -20,7 +20,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use x86_64::instructions::port::Port;
+use crate::kernel::portio::Port;
 
 /////////////////
 /// Exit Code
@@ -0,0 +1,172 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::fmt::Write;
+use core::str::FromStr;
+
+use crate::kernel::env;
+use crate::kernel::error::Error;
+
+// Message Catalog
+//
+// Messages meant for the user are looked up by key in the catalog of the locale named by `LANG`,
+// rather than written out where they are used, so that translating the console only takes adding a
+// catalog here. `LANG` may carry a territory and an encoding, as in `de_DE.UTF-8`; only the language
+// is used. A message missing from a catalog falls back to English, and a key missing from both is
+// shown as is, which makes it easy to spot.
+//
+// A message may contain `{}` placeholders, which `msg!` fills with its arguments in order.
+//
+// Note: The console only shows ASCII, so translations spell out characters beyond it (`ue` for `ü`).
+
+/////////////
+// Globals
+/////////////
+
+/// Type of a catalog, as pairs of keys and messages sorted by key.
+type Catalog = [(&'static str, &'static str)];
+
+/// Messages in English.
+const EN: &Catalog = &[
+    ("acpi.no_namespace", "no namespace available"),
+    ("acpi.no_table", "{}: no such table"),
+    ("acpi.no_tables", "no tables available"),
+    ("boottime.no_timings", "no timings available"),
+    ("clip.no_input", "nothing to copy"),
+    ("heap.partly_untracked", "note: some allocations were not tracked"),
+    ("heap.untracked", "allocations are not tracked; build with the `heap-debug` feature"),
+    ("mq.empty", "{} is empty"),
+    ("power.no_mwait", "MWAIT is not supported, idling with HLT"),
+    ("shell.not_found", "{}: command not found"),
+    ("snake.game_over", "game over with {} points, press any key"),
+    ("snake.not_interactive", "the console must be the input"),
+    ("snake.result", "{} points in {} frames, each at most {} ms late"),
+    ("snake.status", "points: {}    arrows or WASD: steer    Q: quit"),
+    ("sudo.confirm", "run '{}' with all capabilities? [y/N] "),
+    ("usage", "usage"),
+];
+
+/// Messages in German.
+const DE: &Catalog = &[
+    ("acpi.no_namespace", "kein Namensraum verfuegbar"),
+    ("acpi.no_table", "{}: Tabelle nicht vorhanden"),
+    ("acpi.no_tables", "keine Tabellen verfuegbar"),
+    ("boottime.no_timings", "keine Zeitmessungen verfuegbar"),
+    ("clip.no_input", "nichts zu kopieren"),
+    ("heap.partly_untracked", "Hinweis: einige Speicheranforderungen wurden nicht erfasst"),
+    ("heap.untracked", "Speicheranforderungen werden nicht erfasst; mit dem Feature `heap-debug` bauen"),
+    ("mq.empty", "{} ist leer"),
+    ("power.no_mwait", "MWAIT wird nicht unterstuetzt, Leerlauf mit HLT"),
+    ("shell.not_found", "{}: Befehl nicht gefunden"),
+    ("snake.game_over", "Spiel vorbei mit {} Punkten, beliebige Taste druecken"),
+    ("snake.not_interactive", "die Eingabe muss die Konsole sein"),
+    ("snake.result", "{} Punkte in {} Bildern, jedes hoechstens {} ms zu spaet"),
+    ("snake.status", "Punkte: {}    Pfeile oder WASD: lenken    Q: beenden"),
+    ("sudo.confirm", "'{}' mit allen Berechtigungen ausfuehren? [y/N] "),
+    ("usage", "Aufruf"),
+];
+
+//////////////
+/// Locale
+//////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    English,
+    German,
+}
+
+impl Locale {
+    /// All locales.
+    pub const ALL: [Locale; 2] = [Locale::English, Locale::German];
+
+    /// Returns the locale named by `LANG`, or English if it names none.
+    pub fn current() -> Self {
+        env::get("LANG").and_then(|lang| Self::from_str(&lang).ok()).unwrap_or(Self::English)
+    }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::English => "en",
+            Self::German => "de",
+        }
+    }
+
+    /// Returns the messages of the locale.
+    fn catalog(&self) -> &'static Catalog {
+        match self {
+            Self::English => EN,
+            Self::German => DE,
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Only the language matters in `language[_territory][.encoding]`.
+        let language = s.split(['_', '.']).next().unwrap_or_default();
+        match language {
+            "C" | "POSIX" | "en" => Ok(Self::English),
+            "de" => Ok(Self::German),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the message with the given key in the current locale.
+pub fn lookup(key: &'static str) -> &'static str {
+    let find = |catalog: &'static Catalog| {
+        catalog.binary_search_by_key(&key, |&(k, _)| k).ok().map(|idx| catalog[idx].1)
+    };
+    find(Locale::current().catalog()).or_else(|| find(EN)).unwrap_or(key)
+}
+
+/// Returns the message with the given key in the current locale, its placeholders filled with the
+/// arguments in order.
+pub fn format(key: &'static str, args: &[&dyn fmt::Display]) -> String {
+    let mut parts = lookup(key).split("{}");
+    let mut message = parts.next().unwrap_or_default().to_string();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() { write!(message, "{}", arg).ok(); }
+        message.push_str(part);
+    }
+    message
+}
+
+////////////
+// Macros
+////////////
+
+#[macro_export]
+macro_rules! msg {
+    ($key:expr) => ($crate::aux::locale::lookup($key));
+    ($key:expr, $($arg:expr),+ $(,)?) => ($crate::aux::locale::format($key, &[$(&$arg as &dyn core::fmt::Display),+]));
+}
@@ -21,15 +21,62 @@
 // SOFTWARE.
 
 use core::fmt;
-use core::fmt::Debug;
+use core::fmt::{Debug, Write};
+use core::str::FromStr;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 use lazy_static::lazy_static;
-use spin::Mutex;
 use x86_64::instructions;
 
 use crate::{print, println};
 use crate::api::system;
 use crate::api::vga;
+use crate::aux::earlyprintk;
+use crate::drivers::{debugcon, serial};
+use crate::drivers::vga::tag_width;
+use crate::kernel::error::Error;
+use crate::kernel::sync::Mutex;
+
+// Logger
+//
+// Messages are written to up to four sinks: the screen, the serial port, the debug console of the
+// emulator, and a ring buffer holding the latest messages. Until the core devices are initialized,
+// the early console stands in for the screen and the debug console. The log level only filters what
+// reaches the screen, the serial port and the debug console; the ring buffer records every message,
+// so that the events leading up to a problem can be looked at afterwards without raising the
+// verbosity. The ring buffer lives in static memory and can thus be written to before the heap is
+// ready.
+//
+// The colors marking each log level come from a theme, which commands also use for their own
+// diagnostics. Besides the default colors, there is a high-contrast theme that does not rely on
+// telling red from green, and a monochrome one that uses no colors at all and leaves the textual
+// tags to tell the levels apart.
+//
+// Unless turned off, identical consecutive messages are folded into the first one, which counts its
+// repeats, and each target, i.e. the module logging the message, may only log so many messages in a
+// given time. The allowance is a token bucket: a burst of messages passes at once, after which the
+// target is held to a steady rate. Whatever the limit holds back is counted, and the count is logged
+// along with the next message that gets through, so that a storm shows up as a single line rather
+// than flooding the screen and pushing everything else out of the ring buffer.
+
+////////////////
+// Attributes
+////////////////
+
+/// Number of messages kept in the ring buffer.
+const RING_CAPACITY: usize = 128;
+
+/// Maximum length of a message kept in the ring buffer, in bytes; longer ones are truncated.
+const RECORD_LENGTH: usize = 120;
+
+/// Number of targets whose rate is tracked; the least recently seen one makes room for a new one.
+const RATE_LIMIT_TARGETS: usize = 16;
+
+/// Messages a target may log in a burst.
+const RATE_LIMIT_BURST: f64 = 20.0;
+
+/// Messages a target may log per second after a burst.
+const RATE_LIMIT_PER_SECOND: f64 = 5.0;
 
 ///////////////////////
 // Local Interfaces
@@ -40,6 +87,25 @@ lazy_static! {
     static ref LOGGER : Mutex<Logger> = Mutex::new(Logger::new());
 }
 
+////////////
+// States
+////////////
+
+/// Latest messages.
+static RING: Mutex<Ring> = Mutex::new(Ring::new());
+
+/// Repeats and rates of the messages.
+static FILTER: Mutex<Filter> = Mutex::new(Filter::new());
+
+/// Enabled sinks, as a mask of `Sink` bits.
+static SINKS: AtomicU8 = AtomicU8::new(Sink::Vga.mask() | Sink::Ring.mask());
+
+/// Theme in use.
+static THEME: AtomicU8 = AtomicU8::new(Theme::Default as u8);
+
+/// Folding of repeated messages and rate limiting enabled.
+static FILTER_ENABLED: AtomicBool = AtomicBool::new(true);
+
 /////////////////
 /// Log Level
 /////////////////
@@ -54,6 +120,384 @@ pub enum LogLevel {
     Omneity = 0x5,
 }
 
+impl LogLevel {
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Quiet => "quiet",
+            Self::Failure => "failure",
+            Self::Warning => "warning",
+            Self::Success => "success",
+            Self::Apprise => "apprise",
+            Self::Omneity => "omneity",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quiet" => Ok(Self::Quiet),
+            "failure" => Ok(Self::Failure),
+            "warning" => Ok(Self::Warning),
+            "success" => Ok(Self::Success),
+            "apprise" => Ok(Self::Apprise),
+            "omneity" => Ok(Self::Omneity),
+            _ => Err(Error::InvalidArgument)
+        }
+    }
+}
+
+////////////
+/// Sink
+////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Sink {
+    Vga = 0x0,
+    Serial = 0x1,
+    Ring = 0x2,
+    Debugcon = 0x3,
+}
+
+impl Sink {
+    /// All sinks.
+    pub const ALL: [Sink; 4] = [Sink::Vga, Sink::Serial, Sink::Ring, Sink::Debugcon];
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Vga => "vga",
+            Self::Serial => "serial",
+            Self::Ring => "ring",
+            Self::Debugcon => "debugcon",
+        }
+    }
+
+    /// Returns the bit of the sink in the mask of enabled sinks.
+    const fn mask(self) -> u8 { 1 << self as u8 }
+}
+
+impl FromStr for Sink {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vga" => Ok(Self::Vga),
+            "serial" => Ok(Self::Serial),
+            "ring" => Ok(Self::Ring),
+            "debugcon" => Ok(Self::Debugcon),
+            _ => Err(Error::InvalidArgument)
+        }
+    }
+}
+
+/////////////
+/// Theme
+/////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Theme {
+    Default = 0x0,
+    HighContrast = 0x1,
+    Monochrome = 0x2,
+}
+
+impl Theme {
+    /// All themes.
+    pub const ALL: [Theme; 3] = [Theme::Default, Theme::HighContrast, Theme::Monochrome];
+
+    /// Returns the theme with the given index.
+    pub fn from_index(idx: u8) -> Result<Self, Error> {
+        Self::ALL.get(idx as usize).copied().ok_or(Error::InvalidArgument)
+    }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Default => "default",
+            Self::HighContrast => "high-contrast",
+            Self::Monochrome => "monochrome",
+        }
+    }
+
+    /// Returns the escape sequence marking the given log level.
+    pub fn style(&self, log_level: LogLevel) -> &'static str {
+        match (self, log_level) {
+            (Self::Monochrome, _) => "",
+            (Self::Default, LogLevel::Failure) => "\x1B[31m",
+            (Self::Default, LogLevel::Warning) => "\x1B[33m",
+            (Self::Default, LogLevel::Success) => "\x1B[32m",
+            (Self::Default, LogLevel::Apprise) => "\x1B[34m",
+            (Self::HighContrast, LogLevel::Failure) => "\x1B[97;41m",
+            (Self::HighContrast, LogLevel::Warning) => "\x1B[30;43m",
+            (Self::HighContrast, LogLevel::Success) => "\x1B[97;44m",
+            (Self::HighContrast, LogLevel::Apprise) => "\x1B[96m",
+            (_, LogLevel::Quiet | LogLevel::Omneity) => "\x1B[0m",
+        }
+    }
+
+    /// Returns the escape sequence marking timestamps.
+    pub fn accent(&self) -> &'static str {
+        match self {
+            Self::Default => "\x1B[93m",
+            Self::HighContrast => "\x1B[97m",
+            Self::Monochrome => "",
+        }
+    }
+
+    /// Returns the escape sequence restoring the default colors.
+    pub fn reset(&self) -> &'static str {
+        match self {
+            Self::Monochrome => "",
+            _ => "\x1B[0m",
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "high-contrast" => Ok(Self::HighContrast),
+            "monochrome" => Ok(Self::Monochrome),
+            _ => Err(Error::InvalidArgument)
+        }
+    }
+}
+
+//////////////
+/// Record
+//////////////
+#[derive(Clone, Copy)]
+pub struct Record {
+    seq: u64,
+    uptime: Option<f64>,
+    log_level: LogLevel,
+    repeats: u32,
+    len: usize,
+    text: [u8; RECORD_LENGTH],
+}
+
+impl Record {
+    /// Creates a new object holding the given message, logged now.
+    fn new(log_level: LogLevel, fmt: fmt::Arguments) -> Self {
+        let mut record = Record::empty();
+        record.uptime = system::is_timer_initialized().then(system::uptime);
+        record.log_level = log_level;
+        record.write_fmt(fmt).ok();
+        record
+    }
+
+    /// Creates an empty object.
+    const fn empty() -> Self {
+        Record {
+            seq: 0,
+            uptime: None,
+            log_level: LogLevel::Quiet,
+            repeats: 0,
+            len: 0,
+            text: [0; RECORD_LENGTH],
+        }
+    }
+
+    /// Returns the sequence number of the message, counting from zero since boot.
+    pub fn seq(&self) -> u64 { self.seq }
+
+    /// Returns the uptime at which the message was logged, if the timer was running.
+    pub fn uptime(&self) -> Option<f64> { self.uptime }
+
+    /// Returns the log level of the message.
+    pub fn log_level(&self) -> LogLevel { self.log_level }
+
+    /// Returns how many times the message was repeated right after being logged.
+    pub fn repeats(&self) -> u32 { self.repeats }
+
+    /// Returns the message.
+    pub fn message(&self) -> &str {
+        // The text is only ever cut at character boundaries.
+        core::str::from_utf8(&self.text[..self.len]).unwrap_or("")
+    }
+}
+
+impl fmt::Write for Record {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut end = s.len().min(RECORD_LENGTH - self.len);
+        while !s.is_char_boundary(end) { end -= 1; }
+        self.text[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+
+        Ok(())
+    }
+}
+
+////////////
+/// Ring
+////////////
+struct Ring {
+    records: [Record; RING_CAPACITY],
+    /// Sequence number of the next message.
+    next: u64,
+}
+
+impl Ring {
+    /// Creates a new object.
+    const fn new() -> Self {
+        Ring {
+            records: [Record::empty(); RING_CAPACITY],
+            next: 0,
+        }
+    }
+
+    /// Records a message, overwriting the oldest one if full.
+    fn push(&mut self, record: Record) {
+        let seq = self.next;
+        self.records[seq as usize % RING_CAPACITY] = Record { seq, ..record };
+        self.next += 1;
+    }
+
+    /// Counts a repeat of the latest message, if it is the given one.
+    fn repeat(&mut self, record: &Record) {
+        if self.next == 0 { return; }
+        let latest = &mut self.records[(self.next - 1) as usize % RING_CAPACITY];
+        if latest.log_level == record.log_level && latest.message() == record.message() {
+            latest.repeats = latest.repeats.saturating_add(1);
+        }
+    }
+
+    /// Calls the given function for each kept message with a sequence number of at least `since`.
+    fn for_each_since(&self, since: u64, f: &mut impl FnMut(&Record)) {
+        let first = self.next.saturating_sub(RING_CAPACITY as u64).max(since);
+        for seq in first..self.next {
+            f(&self.records[seq as usize % RING_CAPACITY]);
+        }
+    }
+}
+
+//////////////
+/// Bucket
+//////////////
+/// The allowance of a target, as a token bucket.
+#[derive(Clone, Copy)]
+struct Bucket {
+    target: &'static str,
+    tokens: f64,
+    updated: f64,
+    suppressed: u32,
+}
+
+impl Bucket {
+    /// Creates an unused object.
+    const fn empty() -> Self {
+        Bucket {
+            target: "",
+            tokens: RATE_LIMIT_BURST,
+            updated: 0.0,
+            suppressed: 0,
+        }
+    }
+
+    /// Takes a token if there is one, after refilling the bucket for the time passed.
+    fn take(&mut self, now: f64) -> bool {
+        let refill = (now - self.updated).max(0.0) * RATE_LIMIT_PER_SECOND;
+        self.tokens = (self.tokens + refill).min(RATE_LIMIT_BURST);
+        self.updated = now;
+        if self.tokens < 1.0 { return false; }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+///////////////
+/// Verdict
+///////////////
+/// What becomes of a message.
+enum Verdict {
+    /// The message repeats the previous one, and is only counted.
+    Repeat,
+    /// The message gets through, after the given counts of repeats and of suppressed messages.
+    Pass { repeated: Option<(LogLevel, u32)>, suppressed: u32 },
+    /// The target has used up its allowance; the message is only counted.
+    Suppress { repeated: Option<(LogLevel, u32)> },
+}
+
+//////////////
+/// Filter
+//////////////
+struct Filter {
+    /// The previous message that got through, and its target.
+    last: Option<(&'static str, Record)>,
+    /// Repeats of the previous message not yet reported.
+    repeats: u32,
+    buckets: [Bucket; RATE_LIMIT_TARGETS],
+}
+
+impl Filter {
+    /// Creates a new object.
+    const fn new() -> Self {
+        Filter {
+            last: None,
+            repeats: 0,
+            buckets: [Bucket::empty(); RATE_LIMIT_TARGETS],
+        }
+    }
+
+    /// Decides what becomes of the message, logged by the given target.
+    fn check(&mut self, target: &'static str, record: &Record) -> Verdict {
+        if let Some((last_target, last)) = &self.last {
+            let is_repeat = *last_target == target
+                && last.log_level == record.log_level
+                && last.message() == record.message();
+            if is_repeat {
+                self.repeats = self.repeats.saturating_add(1);
+                return Verdict::Repeat;
+            }
+        }
+
+        let repeated = match (&self.last, self.repeats) {
+            (Some((_, last)), repeats) if repeats > 0 => Some((last.log_level, repeats)),
+            _ => None,
+        };
+        self.repeats = 0;
+
+        // Rates are only known once the timer runs.
+        let mut suppressed = 0;
+        if let Some(now) = record.uptime {
+            let bucket = self.bucket(target, now);
+            if !bucket.take(now) {
+                bucket.suppressed = bucket.suppressed.saturating_add(1);
+                self.last = None;
+                return Verdict::Suppress { repeated };
+            }
+            suppressed = core::mem::take(&mut bucket.suppressed);
+        }
+
+        self.last = Some((target, *record));
+        Verdict::Pass { repeated, suppressed }
+    }
+
+    /// Returns the bucket of the target, taking over the least recently used one if there is none.
+    fn bucket(&mut self, target: &'static str, now: f64) -> &mut Bucket {
+        let idx = match self.buckets.iter().position(|bucket| bucket.target == target) {
+            Some(idx) => idx,
+            None => {
+                let (idx, _) = self.buckets
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| a.updated.total_cmp(&b.updated))
+                    .unwrap();
+                self.buckets[idx] = Bucket { target, updated: now, ..Bucket::empty() };
+                idx
+            }
+        };
+        &mut self.buckets[idx]
+    }
+}
+
 //////////////
 /// Logger
 //////////////
@@ -90,6 +534,50 @@ pub fn set_log_level(log_level: LogLevel) {
     );
 }
 
+/// Returns the theme.
+pub fn get_theme() -> Theme { Theme::from_index(THEME.load(Ordering::Relaxed)).unwrap_or(Theme::Default) }
+
+/// Sets the theme.
+pub fn set_theme(theme: Theme) { THEME.store(theme as u8, Ordering::Relaxed); }
+
+/// Returns whether repeated messages are folded and the rate of each target is limited.
+pub fn is_filter_enabled() -> bool { FILTER_ENABLED.load(Ordering::Relaxed) }
+
+/// Folds repeated messages and limits the rate of each target.
+pub fn enable_filter() { FILTER_ENABLED.store(true, Ordering::Relaxed); }
+
+/// Lets every message through as it is.
+pub fn disable_filter() { FILTER_ENABLED.store(false, Ordering::Relaxed); }
+
+/// Returns whether the sink is enabled.
+pub fn is_sink_enabled(sink: Sink) -> bool { SINKS.load(Ordering::Relaxed) & sink.mask() != 0 }
+
+/// Enables the sink.
+pub fn enable_sink(sink: Sink) { SINKS.fetch_or(sink.mask(), Ordering::Relaxed); }
+
+/// Disables the sink.
+pub fn disable_sink(sink: Sink) { SINKS.fetch_and(!sink.mask(), Ordering::Relaxed); }
+
+/// Calls the given function for each message in the ring buffer with a sequence number of at least
+/// `since`, oldest first, and returns the sequence number of the next message.
+pub fn for_each_record(since: u64, mut f: impl FnMut(&Record)) -> u64 {
+    instructions::interrupts::without_interrupts(
+        || {
+            let ring = RING.lock();
+            ring.for_each_since(since, &mut f);
+            ring.next
+        }
+    )
+}
+
+/// Calls the given function for each message in the ring buffer without waiting for it, oldest first.
+///
+/// Note: Does nothing if the ring buffer is held, so this is only meant for exception and panic
+/// handlers.
+pub(crate) fn for_each_record_unsynchronized(mut f: impl FnMut(&Record)) {
+    if let Some(ring) = RING.try_lock() { ring.for_each_since(0, &mut f); }
+}
+
 ///////////////
 // Utilities
 ///////////////
@@ -102,62 +590,142 @@ pub(crate) fn init(log_level: LogLevel) -> Result<(), ()> {
 }
 
 #[doc(hidden)]
-pub fn _log(log_level: LogLevel, fmt: fmt::Arguments) {
+pub fn _log(log_level: LogLevel, target: &'static str, fmt: fmt::Arguments) { dispatch(log_level, target, fmt, true); }
+
+/// Logs the message to the ring buffer only, e.g. for traces that would flood the screen.
+///
+/// Note: The message is still subject to the filter.
+pub(crate) fn log_quietly(log_level: LogLevel, target: &'static str, fmt: fmt::Arguments) {
+    dispatch(log_level, target, fmt, false);
+}
+
+/// Keeps the message and, if visible, writes it out, after running it through the filter.
+fn dispatch(log_level: LogLevel, target: &'static str, fmt: fmt::Arguments, visible: bool) {
+    if !is_filter_enabled() {
+        keep(Record::new(log_level, fmt));
+        if visible { emit(log_level, fmt); }
+        return;
+    }
+
+    let record = Record::new(log_level, fmt);
+    let verdict = instructions::interrupts::without_interrupts(
+        || { FILTER.lock().check(target, &record) }
+    );
+
+    let repeated = match verdict {
+        Verdict::Repeat => {
+            if is_sink_enabled(Sink::Ring) {
+                instructions::interrupts::without_interrupts(
+                    || { RING.lock().repeat(&record); }
+                );
+            }
+            return;
+        }
+        Verdict::Pass { repeated, .. } | Verdict::Suppress { repeated } => repeated,
+    };
+
+    if let Some((log_level, repeats)) = repeated.filter(|_| visible) {
+        emit(log_level, format_args!("last message repeated {} times", repeats));
+    }
+
+    if let Verdict::Pass { suppressed, .. } = verdict {
+        if suppressed > 0 {
+            keep(Record::new(LogLevel::Warning, format_args!("{}: {} messages suppressed", target, suppressed)));
+            if visible { emit(LogLevel::Warning, format_args!("{}: {} messages suppressed", target, suppressed)); }
+        }
+        keep(record);
+        if visible { emit(log_level, fmt); }
+    }
+}
+
+/// Keeps the message in the ring buffer, if enabled.
+fn keep(record: Record) {
+    if is_sink_enabled(Sink::Ring) {
+        instructions::interrupts::without_interrupts(
+            || { RING.lock().push(record); }
+        );
+    }
+}
+
+/// Writes the message to the screen, the serial port and the debug console, if enabled and the log
+/// level allows.
+fn emit(log_level: LogLevel, fmt: fmt::Arguments) {
     const PRECISION: usize = 4;
+    const PREFIX_LENGTH: usize = 3;
     const STATUS_MARK_LENGTH: usize = 10;
     const UPTIME_LENGTH: usize = 13;
 
     if get_log_level() < log_level { return; }
 
+    if is_sink_enabled(Sink::Serial) { emit_plain(log_level, fmt, serial::_print_log); }
+
+    // Until the core devices are up, the screen and the debug console are left to the early console.
+    if earlyprintk::is_active() {
+        emit_plain(log_level, fmt, earlyprintk::_print);
+        return;
+    }
+
+    if is_sink_enabled(Sink::Debugcon) { emit_plain(log_level, fmt, debugcon::_print); }
+
+    if !is_sink_enabled(Sink::Vga) { return; }
+
+    let theme = get_theme();
     if system::is_timer_initialized() {
-        print!("\x1B[93m[{:01$.02$}] ", system::uptime(), UPTIME_LENGTH, PRECISION);
+        print!("{}[{:02$.03$}] ", theme.accent(), system::uptime(), UPTIME_LENGTH, PRECISION);
     } else {
-        print!("\x1B[91m[--------.----] ");
+        print!("{}[--------.----] ", theme.style(LogLevel::Failure));
     }
 
-    print!("\x1B[0m{} ", fmt);
+    print!("{}{} ", theme.reset(), fmt);
 
     if log_level == LogLevel::Omneity {
         println!();
         return;
     }
 
-    let (_, col) = vga::get_cursor_position();
-    for _ in col..(vga::columns() - STATUS_MARK_LENGTH) {
+    // The line is measured rather than the cursor read, as the output may still be queued. A line
+    // that exactly fills the screen leaves the cursor past its end instead of on the next line.
+    let mut width = vga::Width::at(tag_width() + UPTIME_LENGTH + PREFIX_LENGTH);
+    write!(width, "{} ", fmt).ok();
+    let columns = vga::columns();
+    let col = if width.columns() == 0 { 0 } else { (width.columns() - 1) % columns + 1 };
+    for _ in col..(columns - STATUS_MARK_LENGTH) {
         print!(".");
     }
 
+    println!(" {}[{}]{}", theme.style(log_level), log_level.as_str(), theme.reset());
+}
+
+/// Writes the message as plain text, without colors or padding, through the given print function.
+fn emit_plain(log_level: LogLevel, fmt: fmt::Arguments, print: fn(fmt::Arguments)) {
+    const PRECISION: usize = 4;
+    const UPTIME_LENGTH: usize = 13;
+
+    if system::is_timer_initialized() {
+        print(format_args!("[{:01$.02$}] ", system::uptime(), UPTIME_LENGTH, PRECISION));
+    } else {
+        print(format_args!("[--------.----] "));
+    }
     match log_level {
-        LogLevel::Failure => {
-            println!(" \x1B[31m[failure]\x1B[0m");
-        }
-        LogLevel::Warning => {
-            println!(" \x1B[33m[warning]\x1B[0m");
-        }
-        LogLevel::Success => {
-            println!(" \x1B[32m[success]\x1B[0m");
-        }
-        LogLevel::Apprise => {
-            println!(" \x1B[34m[apprise]\x1B[0m");
-        }
-        _ => {}
+        LogLevel::Omneity => print(format_args!("{}\n", fmt)),
+        _ => print(format_args!("{} [{}]\n", fmt, log_level.as_str())),
     }
 }
 
 #[doc(hidden)]
-pub fn _failure(fmt: fmt::Arguments) { _log(LogLevel::Failure, fmt); }
+pub fn _failure(target: &'static str, fmt: fmt::Arguments) { _log(LogLevel::Failure, target, fmt); }
 
 #[doc(hidden)]
-pub fn _warning(fmt: fmt::Arguments) { _log(LogLevel::Warning, fmt); }
+pub fn _warning(target: &'static str, fmt: fmt::Arguments) { _log(LogLevel::Warning, target, fmt); }
 
 #[doc(hidden)]
-pub fn _success(fmt: fmt::Arguments) { _log(LogLevel::Success, fmt); }
+pub fn _success(target: &'static str, fmt: fmt::Arguments) { _log(LogLevel::Success, target, fmt); }
 
 #[doc(hidden)]
-pub fn _apprise(fmt: fmt::Arguments) { _log(LogLevel::Apprise, fmt); }
+pub fn _apprise(target: &'static str, fmt: fmt::Arguments) { _log(LogLevel::Apprise, target, fmt); }
 
 #[doc(hidden)]
-pub fn _omneity(fmt: fmt::Arguments) { _log(LogLevel::Omneity, fmt); }
+pub fn _omneity(target: &'static str, fmt: fmt::Arguments) { _log(LogLevel::Omneity, target, fmt); }
 
 ////////////
 // Macros
@@ -165,32 +733,32 @@ pub fn _omneity(fmt: fmt::Arguments) { _log(LogLevel::Omneity, fmt); }
 
 #[macro_export]
 macro_rules! log {
-    ($log_level:expr, $($arg:tt)*) => ($crate::aux::logger::_log($log_level, format_args!($($arg)*)));
+    ($log_level:expr, $($arg:tt)*) => ($crate::aux::logger::_log($log_level, module_path!(), format_args!($($arg)*)));
 }
 
 #[macro_export]
 macro_rules! failure {
-    ($($arg:tt)*) => ($crate::aux::logger::_failure(format_args!($($arg)*)));
+    ($($arg:tt)*) => ($crate::aux::logger::_failure(module_path!(), format_args!($($arg)*)));
 }
 
 #[macro_export]
 macro_rules! warning {
-    ($($arg:tt)*) => ($crate::aux::logger::_warning(format_args!($($arg)*)));
+    ($($arg:tt)*) => ($crate::aux::logger::_warning(module_path!(), format_args!($($arg)*)));
 }
 
 #[macro_export]
 macro_rules! success {
-    ($($arg:tt)*) => ($crate::aux::logger::_success(format_args!($($arg)*)));
+    ($($arg:tt)*) => ($crate::aux::logger::_success(module_path!(), format_args!($($arg)*)));
 }
 
 #[macro_export]
 macro_rules! apprise {
-    ($($arg:tt)*) => ($crate::aux::logger::_apprise(format_args!($($arg)*)));
+    ($($arg:tt)*) => ($crate::aux::logger::_apprise(module_path!(), format_args!($($arg)*)));
 }
 
 #[macro_export]
 macro_rules! omneity {
-    ($($arg:tt)*) => ($crate::aux::logger::_omneity(format_args!($($arg)*)));
+    ($($arg:tt)*) => ($crate::aux::logger::_omneity(module_path!(), format_args!($($arg)*)));
 }
 
 //////////////////
@@ -20,6 +20,10 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+pub mod earlyprintk;
+pub mod emergency;
 pub mod emulator;
+pub mod locale;
 pub mod logger;
 pub mod testing;
+pub mod tui;
@@ -20,12 +20,37 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use alloc::string::String;
 use core::any;
+use core::future::Future;
 use core::panic::PanicInfo;
+use core::pin::pin;
+use core::ptr;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
 
-use crate::{serial_print, serial_println};
+use x86_64::instructions;
+
+use crate::{emergency_println, serial_print, serial_println};
 use crate::aux::emulator::qemu;
+use crate::devices::console;
+use crate::drivers::{keyboard, vga};
 use crate::hlt_loop;
+use crate::kernel::idt;
+use crate::kernel::idt::IRQ;
+
+// Mock Interrupts
+//
+// Interrupt-driven code cannot be tested against the timing of real hardware, and the test runner
+// has no keyboard attached. The helpers below run the same code an interrupt would, synchronously and
+// with interrupts disabled, so that a test decides exactly what the handlers see and when. Wrapping
+// a test in `without_interrupts` keeps real interrupts from interleaving with the injected ones.
+
+// Fuzzing
+//
+// Fuzz tests run inside the same QEMU test runner, so there is no coverage feedback to guide them;
+// they simply push a large number of generated inputs through a subsystem and check its invariants
+// after each one. The generator is seeded, so a failure reported along with its seed can always be
+// replayed exactly.
 
 ///////////////////
 /// Serene Test
@@ -55,8 +80,8 @@ pub fn serene_test_runner(tests: &[&dyn SereneTest]) {
 
 /// A panic handler for serene tests.
 pub fn serene_test_panic_handler(info: &PanicInfo) -> ! {
-    serial_println!("\x1B[31m[ failure ]\x1B[0m");
-    serial_println!("{}", info);
+    emergency_println!("\x1B[31m[ failure ]\x1B[0m");
+    emergency_println!("{}", info);
     qemu::exit(qemu::ExitCode::Failure);
     hlt_loop();
 }
@@ -96,3 +121,95 @@ pub fn panicky_test_panic_handler(_info: &PanicInfo) -> ! {
     qemu::exit(qemu::ExitCode::Success);
     hlt_loop();
 }
+
+/////////////////////
+// Mock Interrupts
+/////////////////////
+
+/// Runs the handler of the given IRQ `count` times, as if the interrupt had fired.
+pub fn inject_irq(irq: IRQ, count: usize) {
+    for _ in 0..count {
+        idt::dispatch_irq(irq);
+    }
+}
+
+/// Advances the timer by `count` ticks.
+pub fn tick_timer(count: usize) { inject_irq(IRQ::Timer, count); }
+
+/// Feeds the scancodes through the keyboard's decoding path, as if the keyboard had sent them.
+pub fn inject_scancodes(scancodes: &[u8]) {
+    for &scancode in scancodes {
+        instructions::interrupts::without_interrupts(
+            || { keyboard::process_scancode(scancode); }
+        );
+    }
+}
+
+/// Sends the characters to the console, as if they had been typed.
+pub fn type_keys(keys: &str) {
+    for key in keys.chars() {
+        instructions::interrupts::without_interrupts(
+            || { console::key_handle(key); }
+        );
+    }
+}
+
+/// Removes and returns everything buffered by the console.
+pub fn take_console_input() -> String { console::take_input() }
+
+/// Polls the future until it completes, halting until the next interrupt between polls.
+///
+/// Note: Nothing else runs in the meantime, so the future must complete on its own, e.g. a command
+/// line whose commands do not wait for input.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) { return output; }
+        instructions::hlt();
+    }
+}
+
+/// Returns a waker that does nothing, as `block_on` polls regardless.
+fn noop_raw_waker() -> RawWaker {
+    fn clone(_: *const ()) -> RawWaker { noop_raw_waker() }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    RawWaker::new(ptr::null(), &VTABLE)
+}
+
+/////////////
+// Fuzzing
+/////////////
+
+/// Feeds the bytes through the VGA writer's escape sequence parser, as if they had been printed.
+pub fn feed_writer(bytes: &[u8]) { vga::write_bytes(bytes); }
+
+/////////////
+/// Rng
+/////////////
+/// A small xorshift generator; reproducible from its seed and not suitable for anything but tests.
+pub struct Rng(u64);
+
+impl Rng {
+    /// Creates a new generator from the given seed.
+    pub fn new(seed: u64) -> Self { Rng(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed }) }
+
+    /// Returns the next value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a value in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize { (self.next_u64() % bound as u64) as usize }
+
+    /// Returns true with a probability of one in `n`.
+    pub fn one_in(&mut self, n: usize) -> bool { self.below(n) == 0 }
+
+    /// Returns a random element of the slice.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T { &items[self.below(items.len())] }
+}
@@ -0,0 +1,165 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+use core::fmt::Write;
+
+use crate::api::system;
+
+// Progress Indicators
+//
+// A progress bar and a spinner show that a long operation is under way without scrolling the console:
+// the position of the cursor is saved before the first drawing, and every later one goes back to it
+// and overwrites the line. Redrawing is rate-limited, so that an operation may report its progress
+// as often as it likes without spending its time on the screen; the final state is always drawn.
+//
+// Both write through any `fmt::Write`, but only make sense on the console, where escape sequences are
+// interpreted. The line they draw on must not wrap, so labels are best kept short.
+
+////////////////
+// Attributes
+////////////////
+
+/// Minimum number of seconds between redraws.
+const REDRAW_INTERVAL: f64 = 0.1;
+
+/// Number of cells the bar takes up.
+const BAR_WIDTH: usize = 30;
+
+/// Escape sequence saving the position of the cursor.
+const SAVE_CURSOR: &str = "\x1B[s";
+/// Escape sequence restoring the position of the cursor and erasing the rest of the line.
+const RESTORE_CURSOR: &str = "\x1B[u\x1B[K";
+
+//////////////
+/// Redraw
+//////////////
+/// Bookkeeping shared by the indicators.
+struct Redraw {
+    /// Uptime of the latest drawing, or `None` before the first one.
+    last: Option<f64>,
+}
+
+impl Redraw {
+    /// Creates a new object that has not drawn yet.
+    const fn new() -> Self { Redraw { last: None } }
+
+    /// Returns whether a redraw is due, or forced, and if so, moves the cursor to where to draw.
+    fn begin(&mut self, out: &mut impl Write, force: bool) -> Result<bool, fmt::Error> {
+        let now = system::uptime();
+        match self.last {
+            None => out.write_str(SAVE_CURSOR)?,
+            Some(last) if force || now - last >= REDRAW_INTERVAL => out.write_str(RESTORE_CURSOR)?,
+            Some(_) => return Ok(false),
+        }
+        self.last = Some(now);
+
+        Ok(true)
+    }
+}
+
+////////////////////
+/// Progress Bar
+////////////////////
+pub struct ProgressBar<W: Write> {
+    out: W,
+    label: &'static str,
+    total: usize,
+    done: usize,
+    redraw: Redraw,
+}
+
+impl<W: Write> ProgressBar<W> {
+    /// Creates a new progress bar with the given label, drawn at the cursor once progress is reported.
+    ///
+    /// Note: A total of zero shows as complete.
+    pub fn new(out: W, label: &'static str, total: usize) -> Self {
+        ProgressBar { out, label, total, done: 0, redraw: Redraw::new() }
+    }
+
+    /// Returns the amount of work done so far.
+    pub fn done(&self) -> usize { self.done }
+
+    /// Sets the amount of work done, redrawing the bar if due.
+    pub fn set(&mut self, done: usize) -> fmt::Result {
+        self.done = done.min(self.total);
+        self.draw(false)
+    }
+
+    /// Adds to the amount of work done, redrawing the bar if due.
+    pub fn advance(&mut self, amount: usize) -> fmt::Result { self.set(self.done.saturating_add(amount)) }
+
+    /// Draws the final state of the bar and moves on to the next line.
+    pub fn finish(mut self) -> fmt::Result {
+        self.draw(true)?;
+        writeln!(self.out)
+    }
+
+    /// Draws the bar, if due or forced.
+    fn draw(&mut self, force: bool) -> fmt::Result {
+        if !self.redraw.begin(&mut self.out, force)? { return Ok(()); }
+
+        let ratio = if self.total == 0 { 1.0 } else { self.done as f64 / self.total as f64 };
+        let filled = (ratio * BAR_WIDTH as f64) as usize;
+        write!(self.out, "{} [", self.label)?;
+        for idx in 0..BAR_WIDTH {
+            self.out.write_char(if idx < filled { '█' } else { '░' })?;
+        }
+        write!(self.out, "] {:3}%", (ratio * 100.0) as usize)
+    }
+}
+
+///////////////
+/// Spinner
+///////////////
+pub struct Spinner<W: Write> {
+    out: W,
+    label: &'static str,
+    frame: usize,
+    redraw: Redraw,
+}
+
+impl<W: Write> Spinner<W> {
+    /// Characters shown in turn.
+    const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+    /// Creates a new spinner with the given label, drawn at the cursor on the first tick.
+    pub fn new(out: W, label: &'static str) -> Self {
+        Spinner { out, label, frame: 0, redraw: Redraw::new() }
+    }
+
+    /// Turns the spinner by a step, if due.
+    pub fn tick(&mut self) -> fmt::Result {
+        if !self.redraw.begin(&mut self.out, false)? { return Ok(()); }
+
+        write!(self.out, "{} {}", self.label, Self::FRAMES[self.frame])?;
+        self.frame = (self.frame + 1) % Self::FRAMES.len();
+
+        Ok(())
+    }
+
+    /// Replaces the spinner with the given message and moves on to the next line.
+    pub fn finish(mut self, message: &str) -> fmt::Result {
+        self.redraw.begin(&mut self.out, true)?;
+        writeln!(self.out, "{} {}", self.label, message)
+    }
+}
@@ -20,26 +20,68 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use alloc::string::{String, ToString};
-use core::sync::atomic::{AtomicBool, Ordering};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::task::{Context, Poll, Waker};
 
 use spin::Mutex;
 use x86_64::instructions;
 
 use crate::api::system;
+use crate::drivers::vga;
 use crate::encodings::ASCII;
 use crate::encodings::Charset;
-use crate::print;
+use crate::kernel::error::Error;
+use crate::kernel::idt;
+use crate::kernel::idt::IRQ;
+use crate::kernel::signal;
+use crate::{print, warning};
 
 // todo: complete later; we need filesystem first.
 
-static BUFFER: Mutex<String> = Mutex::new(String::new());
+// Flow Control
+//
+// The input queue is bounded. Once it fills up to the high watermark, e.g. by a key held down while
+// nothing reads, the overflow policy kicks in: either the keys are dropped, with a warning, or the
+// keyboard is held off by masking its interrupt, so that it keeps the keys to itself. Either is
+// undone once reading drains the queue down to the low watermark.
+
+////////////////
+// Attributes
+////////////////
+
+/// Number of characters the input queue holds at most.
+const CAPACITY: usize = 4096;
+/// Number of queued characters at which the overflow policy kicks in.
+const HIGH_WATERMARK: usize = 3072;
+/// Number of queued characters at which the overflow policy is lifted.
+const LOW_WATERMARK: usize = 1024;
+
+/// Input typed but not read yet.
+static BUFFER: Mutex<LineDiscipline> = Mutex::new(LineDiscipline::new());
+
+/// Policy once the input queue reaches the high watermark.
+static OVERFLOW: AtomicU8 = AtomicU8::new(Overflow::Drop as u8);
+/// Whether keys have been dropped since the queue last drained.
+static DROPPING: AtomicBool = AtomicBool::new(false);
+/// Whether the keyboard is held off until the queue drains.
+static THROTTLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the input is pasted text, to be taken literally.
+static PASTING: AtomicBool = AtomicBool::new(false);
 
 /// Echo enabled.
 static ECHO_ENABLED: AtomicBool = AtomicBool::new(true);
 
 static RAW_ENABLED: AtomicBool = AtomicBool::new(false);
 
+/// Task waiting for a complete line.
+static LINE_WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+
 pub(crate) fn is_echo_enabled() -> bool { ECHO_ENABLED.load(Ordering::SeqCst) }
 
 pub(crate) fn enable_echo() { ECHO_ENABLED.store(true, Ordering::SeqCst); }
@@ -52,30 +94,115 @@ pub(crate) fn enable_raw() { RAW_ENABLED.store(true, Ordering::SeqCst); }
 
 pub(crate) fn disable_raw() { RAW_ENABLED.store(false, Ordering::SeqCst); }
 
+/// Returns the policy once the input queue fills up.
+pub(crate) fn get_overflow() -> Overflow { Overflow::from_index(OVERFLOW.load(Ordering::SeqCst)).unwrap() }
+
+/// Sets the policy once the input queue fills up.
+pub(crate) fn set_overflow(overflow: Overflow) {
+    OVERFLOW.store(overflow as u8, Ordering::SeqCst);
+    if overflow == Overflow::Drop { unthrottle(); }
+}
+
+/// Starts taking the input literally, until [`end_paste`].
+///
+/// Note: Pasted control characters, line terminators included, are queued as they are and echoed in caret
+/// notation, so that a paste lands in the line being edited instead of running line by line.
+pub(crate) fn begin_paste() { PASTING.store(true, Ordering::SeqCst); }
+
+/// Stops taking the input literally.
+pub(crate) fn end_paste() { PASTING.store(false, Ordering::SeqCst); }
+
 pub fn key_handle(key: char) {
+    vga::record_input();
+
     let mut stdin = BUFFER.lock();
+    let is_literal = PASTING.load(Ordering::SeqCst) && !is_raw_enabled();
 
-    if key == ASCII::<char>::BS && !is_raw_enabled() {
-        if let Some(c) = stdin.pop() {
-            if is_echo_enabled() {
-                let n = match c {
-                    ASCII::<char>::ETX | ASCII::<char>::EOT | ASCII::<char>::ESC => 2,
-                    _ => if (c as u32) < 0xFF { 1 } else { (c as char).len_utf8() },
-                };
-                print!("{}", ASCII::<char>::BS.to_string().repeat(n));
-            }
+    if is_literal {
+        if let Err(e) = stdin.reserve(key) {
+            drop(stdin);
+            drop_key(e);
+            return;
+        }
+        let width = if is_echo_enabled() { echo_literal(key) } else { 0 };
+        stdin.push(key, width);
+    } else if key == ASCII::<char>::BS && !is_raw_enabled() {
+        if let Some(width) = stdin.erase() {
+            vga::erase_back(width);
         }
     } else {
         let key = if (key as u32) < 0xFF { (key as u8) as char } else { key };
-        stdin.push(key);
-        if is_echo_enabled() {
-            match key {
-                ASCII::<char>::ETX => print!("^C"),
-                ASCII::<char>::EOT => print!("^D"),
-                ASCII::<char>::ESC => print!("^["),
-                _ => print!("{}", key),
-            };
+        if key == ASCII::<char>::ETX && !is_raw_enabled() { signal::interrupt(); }
+
+        if let Err(e) = stdin.reserve(key) {
+            drop(stdin);
+            drop_key(e);
+            return;
+        }
+
+        let width = if is_echo_enabled() { echo(key) } else { 0 };
+        stdin.push(key, width);
+        if is_line_terminator(key) {
+            stdin.complete = stdin.len();
+            if let Some(waker) = LINE_WAKER.lock().take() { waker.wake(); }
         }
+        if get_overflow() == Overflow::Block && stdin.len() >= HIGH_WATERMARK && !THROTTLED.swap(true, Ordering::SeqCst) {
+            idt::mask_irq(IRQ::Keyboard);
+        }
+    }
+}
+
+/// Returns the number of queued characters beyond which keys are dropped.
+fn get_limit() -> usize {
+    match get_overflow() {
+        Overflow::Drop => HIGH_WATERMARK,
+        // Keys already on their way when the keyboard is held off still fit in.
+        Overflow::Block => CAPACITY,
+    }
+}
+
+/// Warns that keys are being dropped, once until the queue drains.
+fn drop_key(e: Error) {
+    if !DROPPING.swap(true, Ordering::SeqCst) {
+        let reason = match e {
+            Error::OutOfMemory => "out of memory",
+            _ => "input queue is full",
+        };
+        warning!("console: {}, dropping keys", reason);
+    }
+}
+
+/// Lets the keyboard through again and rearms the warning about dropped keys.
+fn unthrottle() {
+    DROPPING.store(false, Ordering::SeqCst);
+    if THROTTLED.swap(false, Ordering::SeqCst) { idt::unmask_irq(IRQ::Keyboard); }
+}
+
+/// Echoes the key and returns the number of cells it took up on the console.
+fn echo(key: char) -> usize {
+    match key {
+        ASCII::<char>::ETX => print!("^C"),
+        ASCII::<char>::EOT => print!("^D"),
+        ASCII::<char>::ESC => print!("^["),
+        _ => print!("{}", key),
+    };
+    match key {
+        ASCII::<char>::ETX | ASCII::<char>::EOT | ASCII::<char>::ESC => 2,
+        ASCII::<char>::HT => vga::get_tab_width() as usize,
+        ASCII::<char>::BS => 0,
+        _ if is_line_terminator(key) => 0,
+        // Anything else, control characters included, is drawn as a single CP437 glyph.
+        _ => 1,
+    }
+}
+
+/// Echoes the pasted character, control characters in caret notation, and returns the number of cells it took up.
+fn echo_literal(key: char) -> usize {
+    if key != ASCII::<char>::HT && key.is_ascii_control() {
+        print!("^{}", ((key as u8) ^ 0x40) as char);
+        2
+    } else {
+        echo(key)
     }
 }
 
@@ -84,17 +211,7 @@ pub fn read_char() -> char {
     enable_raw();
     loop {
         system::halt();
-        let res = instructions::interrupts::without_interrupts(
-            || {
-                let mut buffer = BUFFER.lock();
-                if !buffer.is_empty() {
-                    Some(buffer.remove(0))
-                } else {
-                    None
-                }
-            }
-        );
-        if let Some(c) = res {
+        if let Some(c) = try_read_char() {
             enable_echo();
             disable_raw();
             return c;
@@ -102,29 +219,192 @@ pub fn read_char() -> char {
     }
 }
 
+/// Removes and returns the next buffered character, without waiting.
+pub fn try_read_char() -> Option<char> {
+    instructions::interrupts::without_interrupts(
+        || {
+            BUFFER.lock().take_char()
+        }
+    )
+}
+
+/// Removes and returns everything buffered, complete line or not.
+pub(crate) fn take_input() -> String {
+    instructions::interrupts::without_interrupts(
+        || BUFFER.lock().take_all()
+    )
+}
+
 pub fn read_line() -> String {
     loop {
         system::halt();
-        let res = instructions::interrupts::without_interrupts(
+        if let Some(line) = instructions::interrupts::without_interrupts(|| { take_line() }) {
+            return line;
+        }
+    }
+}
+
+/// Returns a future that resolves to the next complete line.
+pub fn next_line() -> NextLine { NextLine }
+
+/// Removes and returns the buffered line if it is complete.
+fn take_line() -> Option<String> {
+    let mut stdin = BUFFER.lock();
+
+    match stdin.complete {
+        0 => None,
+        _ => Some(stdin.take_line()),
+    }
+}
+
+/// Returns whether the character ends a line.
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, ASCII::<char>::CR | ASCII::<char>::LF | ASCII::<char>::FF)
+}
+
+//////////////////
+/// Overflow
+//////////////////
+/// Policy once the input queue fills up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Overflow {
+    /// Drops further keys, warning once.
+    Drop = 0x0,
+    /// Holds off the keyboard until the queue drains.
+    Block = 0x1,
+}
+
+impl Overflow {
+    /// Creates a new object from enum index.
+    pub fn from_index(idx: u8) -> Option<Self> {
+        match idx {
+            0x0 => Some(Self::Drop),
+            0x1 => Some(Self::Block),
+            _ => None,
+        }
+    }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Drop => "drop",
+            Self::Block => "block",
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(Self::Drop),
+            "block" => Ok(Self::Block),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+///////////////////////
+/// Line Discipline
+///////////////////////
+/// The buffered input along with the number of cells the echo of each character took up, so that erasing
+/// a character takes back exactly what was drawn, be it a tab or the tail of a wrapped line.
+struct LineDiscipline {
+    input: String,
+    widths: Vec<usize>,
+    /// Number of characters up to the last line terminator typed, i.e. the input ready to be read as lines.
+    complete: usize,
+}
+
+impl LineDiscipline {
+    /// Creates an empty line discipline.
+    const fn new() -> Self {
+        LineDiscipline {
+            input: String::new(),
+            widths: Vec::new(),
+            complete: 0,
+        }
+    }
+
+    /// Makes room for the character, failing if the queue is full or memory runs out.
+    fn reserve(&mut self, c: char) -> Result<(), Error> {
+        if self.len() >= get_limit() { return Err(Error::OutOfResources); }
+        self.input.try_reserve(c.len_utf8())?;
+        self.widths.try_reserve(1)?;
+
+        Ok(())
+    }
+
+    /// Appends a character that was echoed across the given number of cells.
+    ///
+    /// Note: Room has to be made with `reserve` first, so that this does not allocate.
+    fn push(&mut self, c: char, width: usize) {
+        self.input.push(c);
+        self.widths.push(width);
+    }
+
+    /// Removes the last character of the current line and returns the number of cells it was echoed across.
+    ///
+    /// Note: A complete line is never erased, since it might already be on its way to a reader.
+    fn erase(&mut self) -> Option<usize> {
+        if self.len() == self.complete { return None; }
+        self.input.pop();
+        self.widths.pop()
+    }
+
+    /// Returns the number of queued characters.
+    fn len(&self) -> usize { self.widths.len() }
+
+    /// Removes and returns the first character.
+    fn take_char(&mut self) -> Option<char> {
+        let c = self.input.chars().next()?;
+        self.input.remove(0);
+        self.widths.remove(0);
+        self.complete = self.complete.saturating_sub(1);
+        if self.len() <= LOW_WATERMARK { unthrottle(); }
+        Some(c)
+    }
+
+    /// Removes and returns all the input.
+    fn take_all(&mut self) -> String {
+        self.widths.clear();
+        self.complete = 0;
+        unthrottle();
+        core::mem::take(&mut self.input)
+    }
+
+    /// Removes and returns the complete lines, leaving the line being edited.
+    fn take_line(&mut self) -> String {
+        let end = self.input.char_indices().nth(self.complete).map_or(self.input.len(), |(idx, _)| idx);
+        let rest = self.input.split_off(end);
+        self.widths = self.widths.split_off(self.complete);
+        self.complete = 0;
+        if self.len() <= LOW_WATERMARK { unthrottle(); }
+        core::mem::replace(&mut self.input, rest)
+    }
+}
+
+/////////////////
+/// Next Line
+/////////////////
+pub struct NextLine;
+
+impl Future for NextLine {
+    type Output = String;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        instructions::interrupts::without_interrupts(
             || {
-                let mut stdin = BUFFER.lock();
-
-                match stdin.chars().next_back() {
-                    Some(ASCII::<char>::CR) |
-                    Some(ASCII::<char>::LF) |
-                    Some(ASCII::<char>::FF) => {
-                        let line = stdin.clone();
-                        stdin.clear();
-                        Some(line)
-                    }
-                    _ => {
-                        None
+                match take_line() {
+                    Some(line) => Poll::Ready(line),
+                    None => {
+                        *LINE_WAKER.lock() = Some(context.waker().clone());
+                        Poll::Pending
                     }
                 }
             }
-        );
-        if let Some(line) = res {
-            return line;
-        }
+        )
     }
 }
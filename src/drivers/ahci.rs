@@ -0,0 +1,428 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use x86_64::PhysAddr;
+
+use crate::{apprise, failure};
+use crate::kernel::{block, memory, pci, pit, time};
+use crate::kernel::block::BlockDevice;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::memory::{dma, PAGE_SIZE};
+use crate::kernel::memory::dma::DmaBuffer;
+
+// Advanced Host Controller Interface (AHCI)
+//
+// An AHCI controller (HBA) exposes up to 32 SATA ports through a memory-mapped register block, the
+// ABAR, found in BAR5 of its PCI function. Every port is driven through two structures residing in
+// host memory: a command list of up to 32 command headers, and a receive area where the device posts
+// the FISes (Frame Information Structures) it sends back.
+//
+// Each command header points to a command table holding the command FIS (a register host-to-device
+// FIS carrying an ATA command) followed by the physical region descriptor table (PRDT), which lists
+// the memory regions to transfer. Setting a bit in the port's command issue register starts the
+// matching command slot, and the controller clears the bit once the command has completed.
+//
+// Only command slot 0 is used and completions are polled, so no interrupts are required. Every port
+// with an attached SATA drive is registered as a block device named `ahci<controller>p<port>`.
+// Transfers go through a page-sized bounce buffer described by a single PRDT entry.
+//
+// OS Dev Wiki: https://wiki.osdev.org/AHCI
+// Specification: https://www.intel.com/content/www/us/en/io/serial-ata/serial-ata-ahci-spec-rev1-3-1.html
+
+////////////////
+// Attributes
+////////////////
+
+/// PCI class, subclass and programming interface of AHCI controllers.
+const PCI_CLASS: (u8, u8, u8) = (0x01, 0x06, 0x01);
+
+/// Size of a sector.
+const SECTOR_SIZE: usize = 512;
+
+/// Number of ports an HBA can implement.
+const MAX_PORTS: usize = 32;
+
+/// Signature of a port with an attached SATA drive.
+const SATA_SIGNATURE: u32 = 0x0000_0101;
+
+/// Time allowed for a command to complete, in seconds.
+const COMMAND_TIMEOUT: f64 = 5.0;
+/// Time allowed for the command engine of a port to start or stop, in seconds.
+const ENGINE_TIMEOUT: f64 = 0.5;
+
+// Layout of the per-port memory page: the command list (32 headers of 32 bytes), the received FIS
+// area, and the command table of slot 0.
+const COMMAND_LIST_OFFSET: usize = 0x000;
+const RECEIVED_FIS_OFFSET: usize = 0x400;
+const COMMAND_TABLE_OFFSET: usize = 0x800;
+/// Offset of the PRDT within a command table.
+const PRDT_OFFSET: usize = 0x80;
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the AHCI controllers.
+pub(crate) static DEVICE: Device = Device {
+    name: "AHCI",
+    class: Class::Storage,
+    stage: Stage::Driver,
+    critical: false,
+    depends: &["PCI"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+////////////////////////////
+/// Register (HBA)
+////////////////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(usize)]
+enum HbaRegister {
+    /// Global Host Control.
+    GlobalControl = 0x04,
+    /// Ports Implemented.
+    PortsImplemented = 0x0C,
+}
+
+////////////////////////////
+/// Register (Port)
+////////////////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(usize)]
+enum PortRegister {
+    CommandListBase = 0x00,
+    CommandListBaseUpper = 0x04,
+    FisBase = 0x08,
+    FisBaseUpper = 0x0C,
+    InterruptStatus = 0x10,
+    InterruptEnable = 0x14,
+    Command = 0x18,
+    TaskFile = 0x20,
+    Signature = 0x24,
+    SataStatus = 0x28,
+    SataError = 0x30,
+    CommandIssue = 0x38,
+}
+
+/////////////////////
+/// ATA Command
+/////////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+enum AtaCommand {
+    ReadDmaExt = 0x25,
+    WriteDmaExt = 0x35,
+    FlushCacheExt = 0xEA,
+    Identify = 0xEC,
+}
+
+////////////
+/// Port
+////////////
+#[derive(Debug, Clone, Copy)]
+struct Port {
+    base: usize,
+}
+
+impl Port {
+    /// Reads a register.
+    fn read(&self, reg: PortRegister) -> u32 {
+        unsafe { ptr::read_volatile((self.base + reg as usize) as *const u32) }
+    }
+
+    /// Writes a register.
+    fn write(&self, reg: PortRegister, value: u32) {
+        unsafe { ptr::write_volatile((self.base + reg as usize) as *mut u32, value) }
+    }
+
+    /// Waits until `reg` masked with `mask` is zero.
+    fn wait_clear(&self, reg: PortRegister, mask: u32, timeout: f64) -> Result<(), Error> {
+        let deadline = pit::uptime() + timeout;
+        while self.read(reg) & mask != 0 {
+            if pit::uptime() > deadline { return Err(Error::Hardware(FaultKind::Timeout)); }
+            spin_loop();
+        }
+
+        Ok(())
+    }
+}
+
+// Bits of the port command register.
+const CMD_START: u32 = 1 << 0;
+const CMD_FIS_RECEIVE_ENABLE: u32 = 1 << 4;
+const CMD_FIS_RECEIVE_RUNNING: u32 = 1 << 14;
+const CMD_LIST_RUNNING: u32 = 1 << 15;
+
+// Bits of the port task file register.
+const TFD_ERROR: u32 = 1 << 0;
+const TFD_DATA_REQUEST: u32 = 1 << 3;
+const TFD_BUSY: u32 = 1 << 7;
+
+/// Task file error bit of the port interrupt status register.
+const IS_TASK_FILE_ERROR: u32 = 1 << 30;
+
+////////////
+/// Disk
+////////////
+struct Disk {
+    name: String,
+    port: Port,
+    memory: DmaBuffer,
+    bounce: DmaBuffer,
+    block_count: u64,
+}
+
+impl Disk {
+    /// Stops the port, attaches its command list and FIS area, and restarts it.
+    fn new(name: String, port: Port) -> Result<Self, Error> {
+        let memory = dma::alloc_coherent(PAGE_SIZE, PAGE_SIZE).ok_or(Error::OutOfMemory)?;
+        let bounce = dma::alloc_coherent(PAGE_SIZE, PAGE_SIZE).ok_or(Error::OutOfMemory)?;
+
+        // The command engine must be idle while the base addresses change.
+        port.write(PortRegister::Command, port.read(PortRegister::Command) & !(CMD_START | CMD_FIS_RECEIVE_ENABLE));
+        port.wait_clear(PortRegister::Command, CMD_LIST_RUNNING | CMD_FIS_RECEIVE_RUNNING, ENGINE_TIMEOUT)?;
+
+        let base = memory.phys_addr().as_u64();
+        let command_list = base + COMMAND_LIST_OFFSET as u64;
+        let received_fis = base + RECEIVED_FIS_OFFSET as u64;
+        port.write(PortRegister::CommandListBase, command_list as u32);
+        port.write(PortRegister::CommandListBaseUpper, (command_list >> 32) as u32);
+        port.write(PortRegister::FisBase, received_fis as u32);
+        port.write(PortRegister::FisBaseUpper, (received_fis >> 32) as u32);
+
+        // Completions are polled, so mask all interrupts and clear any stale status.
+        port.write(PortRegister::InterruptEnable, 0);
+        port.write(PortRegister::InterruptStatus, u32::MAX);
+        port.write(PortRegister::SataError, u32::MAX);
+
+        port.write(PortRegister::Command, port.read(PortRegister::Command) | CMD_FIS_RECEIVE_ENABLE);
+        port.wait_clear(PortRegister::TaskFile, TFD_BUSY | TFD_DATA_REQUEST, ENGINE_TIMEOUT)?;
+        port.write(PortRegister::Command, port.read(PortRegister::Command) | CMD_START);
+
+        let mut disk = Disk {
+            name,
+            port,
+            memory,
+            bounce,
+            block_count: 0,
+        };
+
+        disk.issue(AtaCommand::Identify, 0, 0, false)?;
+        let data = disk.bounce.as_slice();
+        // Words 100 to 103 hold the number of sectors addressable with 48-bit LBAs.
+        disk.block_count = u64::from_le_bytes(data[200..208].try_into().unwrap());
+        if disk.block_count == 0 { return Err(Error::Unsupported); }
+
+        Ok(disk)
+    }
+
+    /// Returns the model string reported by IDENTIFY.
+    ///
+    /// Note: Only valid right after IDENTIFY, while the data is still in the bounce buffer.
+    fn model(&self) -> String {
+        // ATA strings are made of big-endian words.
+        let raw = &self.bounce.as_slice()[54..94];
+        let bytes: Vec<u8> = raw.chunks(2).flat_map(|w| [w[1], w[0]]).collect();
+        String::from_utf8_lossy(&bytes).trim().into()
+    }
+
+    /// Issues an ATA command in slot 0, transferring `sectors` sectors through the bounce buffer.
+    fn issue(&mut self, command: AtaCommand, lba: u64, sectors: u16, write: bool) -> Result<(), Error> {
+        self.port.wait_clear(PortRegister::TaskFile, TFD_BUSY | TFD_DATA_REQUEST, COMMAND_TIMEOUT)?;
+
+        let bytes = match command {
+            AtaCommand::Identify => SECTOR_SIZE,
+            AtaCommand::FlushCacheExt => 0,
+            _ => sectors as usize * SECTOR_SIZE,
+        };
+
+        let table_addr = self.memory.phys_addr().as_u64() + COMMAND_TABLE_OFFSET as u64;
+        let bounce_addr = self.bounce.phys_addr().as_u64();
+        let page = self.memory.as_mut_slice();
+
+        // Command header: FIS length in double words, write direction and PRDT length.
+        const FIS_LENGTH: u32 = 5;
+        let prdt_length = if bytes > 0 { 1 } else { 0 };
+        let flags = FIS_LENGTH | if write { 1 << 6 } else { 0 } | (prdt_length << 16);
+        let header = &mut page[COMMAND_LIST_OFFSET..COMMAND_LIST_OFFSET + 32];
+        header.fill(0);
+        header[0..4].copy_from_slice(&flags.to_le_bytes());
+        header[8..16].copy_from_slice(&table_addr.to_le_bytes());
+
+        // Register host-to-device FIS.
+        const FIS_TYPE_H2D: u8 = 0x27;
+        const FIS_COMMAND: u8 = 0x80;
+        const DEVICE_LBA: u8 = 1 << 6;
+        let table = &mut page[COMMAND_TABLE_OFFSET..COMMAND_TABLE_OFFSET + PRDT_OFFSET + 16];
+        table.fill(0);
+        let lba = lba.to_le_bytes();
+        let count = sectors.to_le_bytes();
+        table[..20].copy_from_slice(&[
+            FIS_TYPE_H2D, FIS_COMMAND, command as u8, 0,
+            lba[0], lba[1], lba[2], DEVICE_LBA,
+            lba[3], lba[4], lba[5], 0,
+            count[0], count[1], 0, 0,
+            0, 0, 0, 0,
+        ]);
+
+        if bytes > 0 {
+            let prdt = &mut table[PRDT_OFFSET..PRDT_OFFSET + 16];
+            prdt[0..8].copy_from_slice(&bounce_addr.to_le_bytes());
+            prdt[12..16].copy_from_slice(&((bytes - 1) as u32).to_le_bytes());
+        }
+
+        compiler_fence(Ordering::SeqCst);
+        self.port.write(PortRegister::InterruptStatus, u32::MAX);
+        self.port.write(PortRegister::CommandIssue, 1);
+
+        let deadline = pit::uptime() + COMMAND_TIMEOUT;
+        while self.port.read(PortRegister::CommandIssue) & 1 != 0 {
+            if self.port.read(PortRegister::InterruptStatus) & IS_TASK_FILE_ERROR != 0 { break; }
+            if pit::uptime() > deadline { return Err(Error::Hardware(FaultKind::Timeout)); }
+            spin_loop();
+        }
+
+        let failed = self.port.read(PortRegister::InterruptStatus) & IS_TASK_FILE_ERROR != 0
+            || self.port.read(PortRegister::TaskFile) & TFD_ERROR != 0;
+        if failed { return Err(Error::Hardware(FaultKind::InvalidResponse)); }
+
+        Ok(())
+    }
+
+    /// Checks that `len` bytes starting at `lba` are whole sectors within the disk.
+    fn check_range(&self, lba: u64, len: usize) -> Result<(), Error> {
+        let blocks = (len / SECTOR_SIZE) as u64;
+        if len % SECTOR_SIZE != 0 { return Err(Error::InvalidArgument); }
+        if lba.checked_add(blocks).map_or(true, |end| end > self.block_count) { return Err(Error::OutOfBounds); }
+
+        Ok(())
+    }
+}
+
+impl BlockDevice for Disk {
+    fn name(&self) -> &str { &self.name }
+
+    fn block_size(&self) -> usize { SECTOR_SIZE }
+
+    fn block_count(&self) -> u64 { self.block_count }
+
+    fn read(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), Error> {
+        self.check_range(lba, buf.len())?;
+        for (idx, chunk) in buf.chunks_mut(PAGE_SIZE).enumerate() {
+            let chunk_lba = lba + (idx * PAGE_SIZE / SECTOR_SIZE) as u64;
+            self.issue(AtaCommand::ReadDmaExt, chunk_lba, (chunk.len() / SECTOR_SIZE) as u16, false)?;
+            chunk.copy_from_slice(&self.bounce.as_slice()[..chunk.len()]);
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, lba: u64, buf: &[u8]) -> Result<(), Error> {
+        self.check_range(lba, buf.len())?;
+        for (idx, chunk) in buf.chunks(PAGE_SIZE).enumerate() {
+            let chunk_lba = lba + (idx * PAGE_SIZE / SECTOR_SIZE) as u64;
+            self.bounce.as_mut_slice()[..chunk.len()].copy_from_slice(chunk);
+            self.issue(AtaCommand::WriteDmaExt, chunk_lba, (chunk.len() / SECTOR_SIZE) as u16, true)?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> { self.issue(AtaCommand::FlushCacheExt, 0, 0, false) }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Initializes all AHCI controllers and registers their SATA drives as block devices.
+pub(crate) fn init() -> Result<(), Error> {
+    if !time::is_ticking() { return Err(Error::NotInitialized); }
+
+    let (class, subclass, prog_if) = PCI_CLASS;
+    let functions = pci::find_by_class(class, subclass, prog_if);
+    if functions.is_empty() { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    let mut registered = 0;
+    for (idx, function) in functions.iter().enumerate() {
+        match probe(idx, function) {
+            Ok(count) => registered += count,
+            Err(e) => failure!("AHCI: controller at {:02x}:{:02x}.{}: {}", function.bus, function.slot, function.func, e),
+        }
+    }
+
+    if registered == 0 { return Err(Error::NotFound); }
+
+    Ok(())
+}
+
+/// Initializes the controller and registers its drives, returning how many were registered.
+fn probe(idx: usize, function: &pci::Function) -> Result<usize, Error> {
+    function.enable_bus_master();
+    let bar = function.memory_bar(5).ok_or(Error::Unsupported)?;
+    let abar = memory::phys_to_virt_addr(PhysAddr::new(bar)).as_u64() as usize;
+
+    let read = |reg: HbaRegister| unsafe { ptr::read_volatile((abar + reg as usize) as *const u32) };
+    let write = |reg: HbaRegister, value: u32| unsafe { ptr::write_volatile((abar + reg as usize) as *mut u32, value) };
+
+    // Switch to AHCI mode and mask interrupts for the whole HBA.
+    const GHC_AHCI_ENABLE: u32 = 1 << 31;
+    const GHC_INTERRUPT_ENABLE: u32 = 1 << 1;
+    write(HbaRegister::GlobalControl, (read(HbaRegister::GlobalControl) | GHC_AHCI_ENABLE) & !GHC_INTERRUPT_ENABLE);
+
+    let implemented = read(HbaRegister::PortsImplemented);
+    let mut count = 0;
+    for n in (0..MAX_PORTS).filter(|n| implemented & (1 << n) != 0) {
+        let port = Port { base: abar + 0x100 + n * 0x80 };
+
+        // A device is present and communication is established when DET reads 3.
+        const DET_PRESENT: u32 = 0x3;
+        if port.read(PortRegister::SataStatus) & 0xF != DET_PRESENT { continue; }
+        if port.read(PortRegister::Signature) != SATA_SIGNATURE { continue; }
+
+        let disk = match Disk::new(format!("ahci{}p{}", idx, n), port) {
+            Ok(disk) => disk,
+            Err(e) => {
+                failure!("AHCI: port {}: {}", n, e);
+                continue;
+            }
+        };
+
+        apprise!("AHCI: port {}: {} ({} sectors)", n, disk.model(), disk.block_count);
+        block::register(Box::new(disk))?;
+        count += 1;
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,100 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+
+use crate::kernel::portio::Port;
+
+// Debug Console
+//
+// QEMU (with `-debugcon`) and Bochs (with the `port_e9_hack` option) print every byte written to port
+// 0xE9 to the host, with no setup, no FIFO and no status to poll. It is faster than the UART and
+// works from the very first instruction of the kernel, so it also serves the panic path before the
+// serial port is initialized. Reading the port returns 0xE9 while the console is attached; otherwise,
+// writes are simply lost.
+//
+// QEMU: `-debugcon stdio` or `-debugcon file:debugcon.log`
+
+////////////////
+// Attributes
+////////////////
+
+/// Port of the debug console.
+const PORT_NUM: u16 = 0xE9;
+
+///////////////////////////
+/// Debug Console Writer
+///////////////////////////
+struct DebugconWriter;
+
+impl fmt::Write for DebugconWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Each byte is a single `out`, so there is no state to protect with a lock.
+        let mut port = Port::<u8>::new(PORT_NUM);
+        for byte in s.bytes() {
+            unsafe { port.write(byte) };
+        }
+        Ok(())
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether an emulator is listening on the debug console.
+pub fn is_present() -> bool {
+    let mut port = Port::<u8>::new(PORT_NUM);
+    unsafe { port.read() == PORT_NUM as u8 }
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use fmt::Write;
+
+    DebugconWriter.write_fmt(args).ok();
+}
+
+/// Writes the given string.
+///
+/// Note: It never waits, so it is safe to use from exception and panic handlers at any point of the boot.
+pub(crate) fn write_unsynchronized(s: &str) {
+    use fmt::Write;
+
+    DebugconWriter.write_str(s).ok();
+}
+
+////////////
+// Macros
+////////////
+
+#[macro_export]
+macro_rules! debugcon_print {
+    ($($arg:tt)*) => ($crate::drivers::debugcon::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! debugcon_println {
+    () => ($crate::debugcon_print!("\n"));
+    ($fmt:expr) => ($crate::debugcon_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::debugcon_print!(concat!($fmt, "\n"), $($arg)*));
+}
@@ -0,0 +1,414 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use conquer_once::spin::OnceCell;
+use x86_64::instructions;
+use x86_64::PhysAddr;
+
+use crate::api::vga::{Default, Font, Palette};
+use crate::drivers::vga::{TEXT_BUFFER_COLS, TEXT_BUFFER_ROWS};
+use crate::kernel::{boot, memory};
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::gfx::image::Image;
+use crate::kernel::sync::Mutex;
+
+mod font;
+
+// Framebuffer Terminal
+//
+// Machines booted through UEFI have no VGA text mode; the firmware's Graphics Output Protocol (GOP)
+// leaves a linear framebuffer instead, which the bootloader passes on. The VGA writer then keeps its
+// text grid in memory and the grid is drawn here, one glyph per cell, so that everything printed
+// still shows up on screen.
+//
+// Each cell is eight pixels wide and sixteen tall, scaled up by a whole factor to fill as much of
+// the screen as possible and centered. Only the cells that changed since the last refresh are
+// redrawn, along with the cells under the old and the new cursor.
+//
+// An image may be shown in place of the terminal, scaled to fit the screen. It stays until anything
+// is printed, or until the terminal is restored, which redraws all of it.
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the framebuffer.
+pub(crate) static DEVICE: Device = Device {
+    name: "Framebuffer",
+    class: Class::Display,
+    stage: Stage::Console,
+    critical: true,
+    depends: &[],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+////////////////
+// Attributes
+////////////////
+
+/// Width of a cell in pixels.
+const CELL_WIDTH: usize = 8;
+
+/// Height of a cell in pixels.
+const CELL_HEIGHT: usize = 16;
+
+/// Number of cells in the text grid.
+const CELLS: usize = TEXT_BUFFER_ROWS * TEXT_BUFFER_COLS;
+
+///////////////////
+// Cached Values
+///////////////////
+
+/// Framebuffer handed over by the bootloader, if any.
+static SURFACE: OnceCell<Option<Surface>> = OnceCell::uninit();
+
+////////////
+// States
+////////////
+
+/// Terminal drawn onto the framebuffer.
+static TERMINAL: Mutex<Terminal> = Mutex::new(Terminal::new());
+
+/// Offset of the cell under the cursor, or `usize::MAX` if the cursor is hidden.
+static CURSOR: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Flag to check whether the screen is blanked, in which case nothing is drawn.
+static IS_BLANKED: AtomicBool = AtomicBool::new(false);
+
+///////////////
+/// Surface
+///////////////
+struct Surface {
+    /// Virtual address of the first pixel.
+    base: u64,
+    width: usize,
+    height: usize,
+    /// Number of bytes per row.
+    pitch: usize,
+    bytes_per_pixel: usize,
+    /// Bit offsets of the red, green, and blue components.
+    shifts: (u8, u8, u8),
+    /// Factor by which cells are scaled up.
+    scale: usize,
+    /// Pixel coordinates of the top-left corner of the grid.
+    origin: (usize, usize),
+}
+
+impl Surface {
+    /// Packs the given color into a pixel value.
+    fn pixel(&self, (r, g, b): (u8, u8, u8)) -> u32 {
+        (r as u32) << self.shifts.0 | (g as u32) << self.shifts.1 | (b as u32) << self.shifts.2
+    }
+
+    /// Fills the given rectangle with a pixel value.
+    fn fill(&self, x: usize, y: usize, width: usize, height: usize, pixel: u32) {
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                self.put(x, y, pixel);
+            }
+        }
+    }
+
+    /// Writes a pixel value at the given position, which must lie on the screen.
+    fn put(&self, x: usize, y: usize, pixel: u32) {
+        let addr = self.base + (y * self.pitch + x * self.bytes_per_pixel) as u64;
+        unsafe {
+            match self.bytes_per_pixel {
+                4 => (addr as *mut u32).write_volatile(pixel),
+                _ => for byte in 0..3 {
+                    (addr as *mut u8).add(byte).write_volatile((pixel >> (byte * 8)) as u8);
+                },
+            }
+        }
+    }
+
+    /// Draws a cell, inverting the scanlines within `cursor`.
+    fn draw_cell(&self, terminal: &Terminal, offset: usize, cell: u16, cursor: Option<(u8, u8)>) {
+        let (row, col) = (offset / TEXT_BUFFER_COLS, offset % TEXT_BUFFER_COLS);
+        let (ch, attr) = (cell as u8, (cell >> 8) as u8);
+        let fg = self.pixel(terminal.palette[(attr & 0xF) as usize]);
+        let bg = self.pixel(terminal.palette[(attr >> 4) as usize]);
+
+        let x = self.origin.0 + col * CELL_WIDTH * self.scale;
+        let y = self.origin.1 + row * CELL_HEIGHT * self.scale;
+        for line in 0..CELL_HEIGHT {
+            let mut bits = terminal.glyph_row(ch, line);
+            if let Some((begin, end)) = cursor {
+                if (begin as usize..=end as usize).contains(&line) { bits = !bits; }
+            }
+            for px in 0..CELL_WIDTH {
+                let pixel = if bits & (0x80 >> px) != 0 { fg } else { bg };
+                self.fill(x + px * self.scale, y + line * self.scale, self.scale, self.scale, pixel);
+            }
+        }
+    }
+}
+
+////////////////
+/// Terminal
+////////////////
+struct Terminal {
+    /// Colors of the sixteen attributes.
+    palette: [(u8, u8, u8); 16],
+    /// Glyphs and their height set through `set_font`, replacing the built-in ones.
+    font: Option<(Vec<u8>, usize)>,
+    /// Cells as last drawn.
+    drawn: [u16; CELLS],
+    /// Whether `drawn` matches the screen.
+    valid: bool,
+    /// Offset of the cell the cursor was last drawn on.
+    cursor: Option<usize>,
+}
+
+impl Terminal {
+    /// Creates a new object.
+    const fn new() -> Self {
+        Terminal {
+            palette: Default::PALETTE.colors,
+            font: None,
+            drawn: [0; CELLS],
+            valid: false,
+            cursor: None,
+        }
+    }
+
+    /// Returns the pixels of the given scanline of a character's glyph.
+    fn glyph_row(&self, ch: u8, line: usize) -> u8 {
+        if let Some((data, height)) = &self.font {
+            return data.get(ch as usize * height + line * height / CELL_HEIGHT).copied().unwrap_or(0);
+        }
+
+        match ch {
+            font::FIRST..=font::LAST => font::GLYPHS[(ch - font::FIRST) as usize][line * font::HEIGHT / CELL_HEIGHT],
+            0 => 0,
+            _ if (2..CELL_HEIGHT - 2).contains(&line) => 0x7E,
+            _ => 0,
+        }
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Initializes the framebuffer.
+pub(crate) fn init() -> Result<(), Error> {
+    let surface = surface().ok_or(Error::Hardware(FaultKind::NotPresent))?;
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut terminal = TERMINAL.lock();
+            clear_surface(surface, &terminal);
+            terminal.valid = false;
+        }
+    );
+
+    crate::api::vga::clear();
+
+    Ok(())
+}
+
+/// Returns whether the bootloader handed over a framebuffer that can be drawn onto.
+pub(crate) fn is_present() -> bool { surface().is_some() }
+
+/// Returns the framebuffer, setting it up on first use.
+fn surface() -> Option<&'static Surface> {
+    SURFACE.get_or_init(
+        || {
+            if !cfg!(feature = "gfx") {
+                return None;
+            }
+
+            let protocol = boot::protocol()?;
+            let framebuffer = protocol.framebuffer()?;
+            let bytes_per_pixel = match framebuffer.bpp {
+                24 => 3,
+                32 => 4,
+                _ => return None,
+            };
+
+            let (width, height) = (framebuffer.width as usize, framebuffer.height as usize);
+            let (grid_width, grid_height) = (TEXT_BUFFER_COLS * CELL_WIDTH, TEXT_BUFFER_ROWS * CELL_HEIGHT);
+            let scale = (width / grid_width).min(height / grid_height).max(1);
+
+            Some(
+                Surface {
+                    base: memory::phys_to_virt_addr(PhysAddr::new(framebuffer.address)).as_u64(),
+                    width,
+                    height,
+                    pitch: framebuffer.pitch as usize,
+                    bytes_per_pixel,
+                    shifts: framebuffer.shifts,
+                    scale,
+                    origin: (width.saturating_sub(grid_width * scale) / 2, height.saturating_sub(grid_height * scale) / 2),
+                }
+            )
+        }
+    ).as_ref()
+}
+
+/// Fills the whole framebuffer with the default background.
+fn clear_surface(surface: &Surface, terminal: &Terminal) {
+    let pixel = surface.pixel(terminal.palette[Default::BACKGROUND as usize]);
+    surface.fill(0, 0, surface.width, surface.height, pixel);
+}
+
+/// Draws the cells that changed since the last refresh, along with the cursor.
+///
+/// The cursor is given as the offset of its cell and the scanlines it covers.
+pub(crate) fn refresh(cells: &[u16], cursor: Option<(usize, (u8, u8))>) {
+    let surface = match surface() {
+        Some(surface) => surface,
+        None => return,
+    };
+
+    if IS_BLANKED.load(Ordering::Relaxed) { return; }
+
+    let mut terminal = TERMINAL.lock();
+    let previous = terminal.cursor.take();
+    for (offset, &cell) in cells.iter().enumerate().take(CELLS) {
+        let under_cursor = cursor.filter(|(at, _)| *at == offset).map(|(_, scanlines)| scanlines);
+        let stale = !terminal.valid || terminal.drawn[offset] != cell || previous == Some(offset);
+        if !stale && under_cursor.is_none() { continue; }
+
+        surface.draw_cell(&terminal, offset, cell, under_cursor);
+        terminal.drawn[offset] = cell;
+    }
+    terminal.valid = true;
+    terminal.cursor = cursor.map(|(at, _)| at);
+
+    CURSOR.store(terminal.cursor.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Draws all cells without waiting for the terminal.
+///
+/// This is only meant for exception and panic handlers; the built-in font and default palette are
+/// used if the terminal is held.
+pub(crate) fn refresh_unsynchronized(cells: &[u16]) {
+    let surface = match surface() {
+        Some(surface) => surface,
+        None => return,
+    };
+
+    let fallback = Terminal::new();
+    let terminal = TERMINAL.try_lock();
+    let terminal = terminal.as_deref().unwrap_or(&fallback);
+    for (offset, &cell) in cells.iter().enumerate().take(CELLS) {
+        surface.draw_cell(terminal, offset, cell, None);
+    }
+}
+
+/// Returns the offset of the cell under the cursor, if it is shown.
+pub(crate) fn cursor_offset() -> Option<usize> {
+    Some(CURSOR.load(Ordering::Relaxed)).filter(|&offset| offset != usize::MAX)
+}
+
+/// Sets the colors of the sixteen attributes, redrawing everything on the next refresh.
+pub(crate) fn set_palette(palette: Palette) {
+    let mut terminal = TERMINAL.lock();
+    terminal.palette = palette.colors;
+    terminal.valid = false;
+    if let Some(surface) = surface() { clear_surface(surface, &terminal); }
+}
+
+/// Sets the font, redrawing everything on the next refresh.
+pub(crate) fn set_font(font: &Font) {
+    let mut terminal = TERMINAL.lock();
+    terminal.font = Some((font.data.clone(), font.height.max(1) as usize));
+    terminal.valid = false;
+}
+
+/// Shows the image in place of the terminal, scaled to fit the screen with nearest neighbor sampling.
+pub(crate) fn show_image(image: &Image) -> Result<(), Error> {
+    let surface = surface().ok_or(Error::Unsupported)?;
+    if image.width() == 0 || image.height() == 0 { return Err(Error::InvalidArgument); }
+
+    // Keep the aspect ratio, filling either the width or the height of the screen.
+    let (width, height) = if image.width() * surface.height <= image.height() * surface.width {
+        (image.width() * surface.height / image.height(), surface.height)
+    } else {
+        (surface.width, image.height() * surface.width / image.width())
+    };
+    let origin = ((surface.width - width) / 2, (surface.height - height) / 2);
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut terminal = TERMINAL.lock();
+            clear_surface(surface, &terminal);
+            for y in 0..height {
+                let src_y = y * image.height() / height;
+                for x in 0..width {
+                    let pixel = surface.pixel(image.pixel(x * image.width() / width, src_y));
+                    surface.put(origin.0 + x, origin.1 + y, pixel);
+                }
+            }
+            // Whatever is printed next redraws the terminal in full.
+            terminal.valid = false;
+        }
+    );
+
+    Ok(())
+}
+
+/// Clears the screen, so that the terminal is redrawn in full on the next refresh.
+pub(crate) fn invalidate() {
+    let surface = match surface() {
+        Some(surface) => surface,
+        None => return,
+    };
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut terminal = TERMINAL.lock();
+            clear_surface(surface, &terminal);
+            terminal.valid = false;
+        }
+    );
+}
+
+/// Paints the screen black and stops drawing, or redraws everything on the next refresh.
+pub(crate) fn set_blanked(blanked: bool) {
+    let surface = match surface() {
+        Some(surface) => surface,
+        None => return,
+    };
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut terminal = TERMINAL.lock();
+            IS_BLANKED.store(blanked, Ordering::Relaxed);
+            if blanked {
+                surface.fill(0, 0, surface.width, surface.height, surface.pixel((0, 0, 0)));
+            } else {
+                clear_surface(surface, &terminal);
+            }
+            terminal.valid = false;
+        }
+    );
+}
@@ -20,21 +20,87 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
-use pc_keyboard::{DecodedKey, Error, HandleControl, Keyboard, KeyCode, KeyEvent, KeyState, ScancodeSet1};
+use pc_keyboard::{DecodedKey, Error, HandleControl, Keyboard, KeyCode, KeyEvent, KeyState};
+use pc_keyboard::{ScancodeSet, ScancodeSet1, ScancodeSet2};
 use pc_keyboard::layouts::{Azerty, Dvorak104Key, Us104Key};
-use spin::Mutex;
-use x86_64::instructions::port::Port;
+use x86_64::instructions;
 
-use crate::{api, omneity};
-use crate::api::keyboard::Layout;
+use crate::{api, omneity, warning};
+use crate::api::keyboard::{Layout, Scancodes};
 use crate::devices::console;
 use crate::encodings::ASCII;
 use crate::encodings::Charset;
-use crate::kernel::apic::local::LAPIC_EOI;
+use crate::kernel::acpi;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error;
+use crate::kernel::error::FaultKind;
 use crate::kernel::idt;
 use crate::kernel::idt::IRQ;
+use crate::kernel::portio;
+use crate::kernel::portio::Port;
+use crate::kernel::power;
+use crate::kernel::sync::Mutex;
+
+////////////////
+// Attributes
+////////////////
+
+/// Data port of the PS/2 controller.
+const DATA_PORT: u16 = 0x60;
+/// Command/status port of the PS/2 controller.
+const CMD_PORT: u16 = 0x64;
+
+/// Status bit set while a byte waits in the output buffer.
+const STATUS_OUTPUT_FULL: u8 = 0x01;
+/// Status bit set while the input buffer holds a byte the controller has not taken yet.
+const STATUS_INPUT_FULL: u8 = 0x02;
+/// Controller command reading the configuration byte.
+const CMD_READ_CONFIG: u8 = 0x20;
+/// Controller command writing the configuration byte.
+const CMD_WRITE_CONFIG: u8 = 0x60;
+/// Configuration bit having the controller translate scancode set 2 into set 1.
+const CONFIG_TRANSLATION: u8 = 0x40;
+
+/// Keyboard command reporting the identification bytes.
+const KBD_IDENTIFY: u8 = 0xF2;
+/// Keyboard command selecting the scancode set, given as the next byte.
+const KBD_SCANCODE_SET: u8 = 0xF0;
+/// Keyboard command resuming sending scancodes.
+const KBD_ENABLE_SCANNING: u8 = 0xF4;
+/// Keyboard command stopping sending scancodes.
+const KBD_DISABLE_SCANNING: u8 = 0xF5;
+/// Keyboard response acknowledging a command.
+const KBD_ACK: u8 = 0xFA;
+/// Keyboard response asking for the command again.
+const KBD_RESEND: u8 = 0xFE;
+/// Second identification byte of an MF2 keyboard whose scancodes are translated.
+const KBD_ID_TRANSLATED: [u8; 2] = [0x41, 0xC1];
+
+/// Number of times the controller status is polled before giving up.
+const TIMEOUT: usize = 100_000;
+/// Number of times a command is sent again when the keyboard asks for it.
+const RETRIES: usize = 3;
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the keyboard.
+pub(crate) static DEVICE: Device = Device {
+    name: "Keyboard",
+    class: Class::Input,
+    stage: Stage::Driver,
+    critical: false,
+    depends: &["PICS", "Allocator"],
+    tolerates_absent: false,
+    init: || init(api::keyboard::Default::LAYOUT),
+    suspend: Some(suspend),
+    resume: Some(resume),
+};
 
 /////////////
 // Mutexes
@@ -43,6 +109,9 @@ use crate::kernel::idt::IRQ;
 /// A keyboard interface with mutex protection.
 static KEYBOARD: Mutex<Option<LayoutWrapper>> = Mutex::new(None);
 
+/// Keys that act as other keys.
+static REMAP: Mutex<BTreeMap<KeyCode, KeyCode>> = Mutex::new(BTreeMap::new());
+
 ////////////
 // States
 ////////////
@@ -53,28 +122,59 @@ static ALT: AtomicBool = AtomicBool::new(false);
 static CTRL: AtomicBool = AtomicBool::new(false);
 /// State of the SHIFT key.
 static SHIFT: AtomicBool = AtomicBool::new(false);
+/// Scancode set being decoded.
+static SCANCODES: AtomicU8 = AtomicU8::new(Scancodes::Set1 as u8);
+
+///////////////
+/// Decoder
+///////////////
+/// Decoder of the scancode set the keyboard sends.
+enum Decoder {
+    Set1(ScancodeSet1),
+    Set2(ScancodeSet2),
+}
+
+impl Decoder {
+    /// Creates a decoder of the given scancode set.
+    fn new(scancodes: Scancodes) -> Self {
+        match scancodes {
+            Scancodes::Set1 => Decoder::Set1(ScancodeSet1::new()),
+            Scancodes::Set2 => Decoder::Set2(ScancodeSet2::new()),
+        }
+    }
+}
+
+impl ScancodeSet for Decoder {
+    fn advance_state(&mut self, code: u8) -> Result<Option<KeyEvent>, Error> {
+        match self {
+            Decoder::Set1(set) => set.advance_state(code),
+            Decoder::Set2(set) => set.advance_state(code),
+        }
+    }
+}
 
 //////////////////////
 /// Layout Wrapper
 //////////////////////
 enum LayoutWrapper {
-    AZERTY(Keyboard<Azerty, ScancodeSet1>),
-    Dvorak(Keyboard<Dvorak104Key, ScancodeSet1>),
-    QWERTY(Keyboard<Us104Key, ScancodeSet1>),
+    AZERTY(Keyboard<Azerty, Decoder>),
+    Dvorak(Keyboard<Dvorak104Key, Decoder>),
+    QWERTY(Keyboard<Us104Key, Decoder>),
 }
 
 impl LayoutWrapper {
-    /// Creates an object from layout.
+    /// Creates an object from layout, decoding the current scancode set.
     fn from(lyt: Layout) -> Self {
+        let decoder = Decoder::new(get_scancodes());
         match lyt {
             Layout::AZERTY => {
-                LayoutWrapper::AZERTY(Keyboard::new(ScancodeSet1::new(), Azerty, HandleControl::MapLettersToUnicode))
+                LayoutWrapper::AZERTY(Keyboard::new(decoder, Azerty, HandleControl::MapLettersToUnicode))
             }
             Layout::Dvorak => {
-                LayoutWrapper::Dvorak(Keyboard::new(ScancodeSet1::new(), Dvorak104Key, HandleControl::MapLettersToUnicode))
+                LayoutWrapper::Dvorak(Keyboard::new(decoder, Dvorak104Key, HandleControl::MapLettersToUnicode))
             }
             Layout::QWERTY => {
-                LayoutWrapper::QWERTY(Keyboard::new(ScancodeSet1::new(), Us104Key, HandleControl::MapLettersToUnicode))
+                LayoutWrapper::QWERTY(Keyboard::new(decoder, Us104Key, HandleControl::MapLettersToUnicode))
             }
         }
     }
@@ -124,26 +224,184 @@ pub(crate) fn set_layout(lyt: Layout) {
 /// Resets the layout.
 pub(crate) fn reset_layout() { set_layout(api::keyboard::Default::LAYOUT); }
 
+/// Returns the remapped keys along with the keys they act as.
+pub(crate) fn get_remap() -> Vec<(KeyCode, KeyCode)> {
+    instructions::interrupts::without_interrupts(
+        || { REMAP.lock().iter().map(|(&from, &to)| (from, to)).collect() }
+    )
+}
+
+/// Makes each key act as the one it is paired with, replacing the previous remapping.
+pub(crate) fn set_remap(remap: &[(KeyCode, KeyCode)]) {
+    let remap = remap.iter().copied().filter(|(from, to)| from != to).collect();
+    instructions::interrupts::without_interrupts(
+        || { *REMAP.lock() = remap; }
+    );
+}
+
+/// Returns the scancode set being decoded.
+pub(crate) fn get_scancodes() -> Scancodes { Scancodes::from_index(SCANCODES.load(Ordering::Relaxed)).unwrap() }
+
+/// Switches to the given scancode set, or to the one the keyboard identifies with, and returns the set
+/// switched to.
+pub(crate) fn set_scancodes(scancodes: Option<Scancodes>) -> Result<Scancodes, error::Error> {
+    idt::mask_irq(IRQ::Keyboard);
+    let res = instructions::interrupts::without_interrupts(|| configure(scancodes));
+    if let Ok(scancodes) = res {
+        SCANCODES.store(scancodes as u8, Ordering::Relaxed);
+        // Start decoding afresh, as a scancode might have been cut in half.
+        let mut keyboard = KEYBOARD.lock();
+        if let Some(lyt) = keyboard.as_ref().map(|keyboard| keyboard.unwrap()) {
+            keyboard.replace(LayoutWrapper::from(lyt));
+        }
+    }
+    idt::unmask_irq(IRQ::Keyboard);
+
+    res
+}
+
 ///////////////
 // Utilities
 ///////////////
 
 /// Initializes the keyboard.
-pub(crate) fn init(lyt: Layout) -> Result<(), ()> {
-    // Set layout.
+pub(crate) fn init(lyt: Layout) -> Result<(), error::Error> {
+    // Set layout, which keyboards on other buses go through as well.
     set_layout(lyt);
 
+    // Legacy-free platforms leave the controller out, which the FADT tells if the ports do not.
+    if acpi::fadt::info().map_or(false, |info| !info.has_8042) || !is_controller_present() {
+        return Err(error::Error::Hardware(FaultKind::NotPresent));
+    }
+
+    // Reserve the PS/2 controller ports.
+    portio::reserve("Keyboard", DATA_PORT, 1).ok();
+    portio::reserve("Keyboard", CMD_PORT, 1).ok();
+
+    // Pick the scancode set, keeping to set 1 if the controller cannot be configured.
+    if let Err(e) = set_scancodes(None) {
+        warning!("Keyboard: could not select the scancode set: {}", e);
+    }
+
     // Set interrupt handler.
     idt::set_irq_handler(IRQ::Keyboard, keyboard_irq_handler);
 
     Ok(())
 }
 
+/// Stops delivery of keyboard interrupts.
+fn suspend() -> Result<(), error::Error> {
+    idt::mask_irq(IRQ::Keyboard);
+
+    Ok(())
+}
+
+/// Resumes delivery of keyboard interrupts.
+fn resume() -> Result<(), error::Error> {
+    idt::unmask_irq(IRQ::Keyboard);
+
+    Ok(())
+}
+
+/// Returns whether a PS/2 controller is present.
+///
+/// Note: The status register of a missing controller floats high.
+pub(crate) fn is_controller_present() -> bool {
+    let mut port = Port::<u8>::new(CMD_PORT);
+    unsafe { port.read() != 0xFF }
+}
+
+/// Sets the controller up for the given scancode set, or for the one the keyboard identifies with.
+///
+/// Note: The keyboard is always asked for set 2, which the controller translates into set 1 if need be.
+fn configure(scancodes: Option<Scancodes>) -> Result<Scancodes, error::Error> {
+    controller_command(CMD_READ_CONFIG)?;
+    let config = read_data()?;
+
+    keyboard_command(KBD_DISABLE_SCANNING)?;
+    let scancodes = match scancodes {
+        Some(scancodes) => scancodes,
+        None if config & CONFIG_TRANSLATION != 0 => Scancodes::Set1,
+        None => identify()?,
+    };
+    // Keyboards start up in set 2 anyway, so one that does not take the command is fine as it is.
+    if keyboard_command(KBD_SCANCODE_SET).and_then(|_| keyboard_command(Scancodes::Set2 as u8)).is_err() {
+        omneity!("Keyboard: scancode set 2 not acknowledged");
+    }
+
+    let config = match scancodes {
+        Scancodes::Set1 => config | CONFIG_TRANSLATION,
+        Scancodes::Set2 => config & !CONFIG_TRANSLATION,
+    };
+    controller_command(CMD_WRITE_CONFIG)?;
+    write_data(config)?;
+    keyboard_command(KBD_ENABLE_SCANNING)?;
+
+    Ok(scancodes)
+}
+
+/// Returns the scancode set an untranslated keyboard should be decoded with, from its identification.
+///
+/// Note: A keyboard that reports being translated, i.e. by the keyboard itself or by firmware the
+/// controller configuration does not reflect, is taken at its word.
+fn identify() -> Result<Scancodes, error::Error> {
+    keyboard_command(KBD_IDENTIFY)?;
+    let id = (read_data().ok(), read_data().ok());
+    match id {
+        (Some(0xAB), Some(second)) if KBD_ID_TRANSLATED.contains(&second) => Ok(Scancodes::Set1),
+        _ => Ok(Scancodes::Set2),
+    }
+}
+
+/// Sends a command to the controller.
+fn controller_command(cmd: u8) -> Result<(), error::Error> {
+    wait_status(STATUS_INPUT_FULL, false)?;
+    let mut port = Port::new(CMD_PORT);
+    unsafe { port.write(cmd); }
+
+    Ok(())
+}
+
+/// Sends a command to the keyboard and waits for it to be acknowledged.
+fn keyboard_command(cmd: u8) -> Result<(), error::Error> {
+    for _ in 0..RETRIES {
+        write_data(cmd)?;
+        match read_data()? {
+            KBD_ACK => return Ok(()),
+            KBD_RESEND => continue,
+            _ => return Err(error::Error::Hardware(FaultKind::InvalidResponse)),
+        }
+    }
+    Err(error::Error::Hardware(FaultKind::InvalidResponse))
+}
+
+/// Writes a byte to the data port once the controller is ready to take it.
+fn write_data(byte: u8) -> Result<(), error::Error> {
+    wait_status(STATUS_INPUT_FULL, false)?;
+    let mut port = Port::new(DATA_PORT);
+    unsafe { port.write(byte); }
+
+    Ok(())
+}
+
+/// Reads a byte from the data port once there is one.
+fn read_data() -> Result<u8, error::Error> {
+    wait_status(STATUS_OUTPUT_FULL, true)?;
+    Ok(read_scancode())
+}
+
+/// Polls the controller status until the given bit is set or clear.
+fn wait_status(bit: u8, set: bool) -> Result<(), error::Error> {
+    let mut port = Port::<u8>::new(CMD_PORT);
+    for _ in 0..TIMEOUT {
+        if (unsafe { port.read() } & bit != 0) == set { return Ok(()); }
+    }
+    Err(error::Error::Hardware(FaultKind::Timeout))
+}
+
 /// Returns a byte read from the input port.
 fn read_scancode() -> u8 {
-    const PORT_NUM: u16 = 0x60;
-
-    let mut port = Port::new(PORT_NUM);
+    let mut port = Port::new(DATA_PORT);
     unsafe { port.read() }
 }
 
@@ -164,48 +422,57 @@ fn send_csi(code: &'static str) {
 //////////////
 
 /// An irq handler for keyboard.
-fn keyboard_irq_handler() {
+fn keyboard_irq_handler() { process_scancode(read_scancode()); }
+
+/// Decodes a scancode received from the keyboard and sends the resulting key to the console.
+pub(crate) fn process_scancode(scancode: u8) {
+    let key_event = match KEYBOARD.lock().as_mut() {
+        Some(keyboard) => keyboard.add_byte(scancode),
+        None => return,
+    };
+
+    if let Ok(Some(key_event)) = key_event { process_keyevent(key_event); }
+}
+
+/// Applies the remapping, modifiers and layout to a key event and sends the resulting key to the console.
+///
+/// Note: Besides the PS/2 keyboard, it is fed by keyboards that report key events directly, such as USB ones.
+pub(crate) fn process_keyevent(mut key_event: KeyEvent) {
     let mut mutex_guarded_kbd = KEYBOARD.lock();
-    let keyboard = mutex_guarded_kbd.as_mut().unwrap();
+    let keyboard = match mutex_guarded_kbd.as_mut() {
+        Some(keyboard) => keyboard,
+        None => return,
+    };
 
-    let scancode: u8 = read_scancode();
+    if let Some(&code) = REMAP.lock().get(&key_event.code) { key_event.code = code; }
 
-    if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-        match key_event.code {
-            KeyCode::LAlt | KeyCode::RAltGr => {
-                ALT.store(key_event.state == KeyState::Down, Ordering::Relaxed)
-            }
-            KeyCode::LShift | KeyCode::RShift => {
-                SHIFT.store(key_event.state == KeyState::Down, Ordering::Relaxed)
-            }
-            KeyCode::LControl | KeyCode::RControl => {
-                CTRL.store(key_event.state == KeyState::Down, Ordering::Relaxed)
-            }
-            _ => {}
+    match key_event.code {
+        KeyCode::LAlt | KeyCode::RAltGr => {
+            ALT.store(key_event.state == KeyState::Down, Ordering::Relaxed)
         }
-
-        let is_alt = ALT.load(Ordering::Relaxed);
-        let is_ctrl = CTRL.load(Ordering::Relaxed);
-        let is_shift = SHIFT.load(Ordering::Relaxed);
-
-        if let Some(key) = keyboard.process_keyevent(key_event) {
-            match key {
-                DecodedKey::RawKey(KeyCode::ArrowUp) => send_csi("1A"),
-                DecodedKey::RawKey(KeyCode::ArrowDown) => send_csi("1B"),
-                DecodedKey::RawKey(KeyCode::ArrowRight) => send_csi("1C"),
-                DecodedKey::RawKey(KeyCode::ArrowLeft) => send_csi("1D"),
-                DecodedKey::Unicode(ASCII::<char>::HT) if is_shift => send_csi("Z"),
-                DecodedKey::Unicode(ASCII::<char>::DEL) if is_alt && is_ctrl => api::system::reboot(),
-                DecodedKey::Unicode(key) => send_key(key),
-                _ => {}
-            }
+        KeyCode::LShift | KeyCode::RShift => {
+            SHIFT.store(key_event.state == KeyState::Down, Ordering::Relaxed)
+        }
+        KeyCode::LControl | KeyCode::RControl => {
+            CTRL.store(key_event.state == KeyState::Down, Ordering::Relaxed)
         }
+        _ => {}
     }
-    let base: usize = 0x180fee00000;
-
-    let dest = LAPIC_EOI + base;
-    let dest = dest as *mut u32;
-
-    unsafe { core::ptr::write_volatile(dest, 0); }
 
+    let is_alt = ALT.load(Ordering::Relaxed);
+    let is_ctrl = CTRL.load(Ordering::Relaxed);
+    let is_shift = SHIFT.load(Ordering::Relaxed);
+
+    if let Some(key) = keyboard.process_keyevent(key_event) {
+        match key {
+            DecodedKey::RawKey(KeyCode::ArrowUp) => send_csi("1A"),
+            DecodedKey::RawKey(KeyCode::ArrowDown) => send_csi("1B"),
+            DecodedKey::RawKey(KeyCode::ArrowRight) => send_csi("1C"),
+            DecodedKey::RawKey(KeyCode::ArrowLeft) => send_csi("1D"),
+            DecodedKey::Unicode(ASCII::<char>::HT) if is_shift => send_csi("Z"),
+            DecodedKey::Unicode(ASCII::<char>::DEL) if is_alt && is_ctrl => power::reboot(),
+            DecodedKey::Unicode(key) => send_key(key),
+            _ => {}
+        }
+    }
 }
@@ -20,6 +20,36 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use crate::kernel::dev;
+
+#[cfg(feature = "fs")]
+pub mod ahci;
+pub mod debugcon;
+pub mod framebuffer;
 pub mod keyboard;
+#[cfg(feature = "fs")]
+pub mod nvme;
 pub mod serial;
+#[cfg(feature = "usb")]
+#[cfg_attr(not(feature = "fs"), allow(dead_code))]
+pub mod usb;
 pub mod vga;
+
+/// Registers the built-in drivers.
+pub(crate) fn register_devices() {
+    if vga::is_text_mode() {
+        dev::register(&vga::DEVICE).ok();
+    } else {
+        dev::register(&framebuffer::DEVICE).ok();
+    }
+    for device in &serial::DEVICES {
+        dev::register(device).ok();
+    }
+    dev::register(&keyboard::DEVICE).ok();
+    #[cfg(feature = "fs")]
+    dev::register(&ahci::DEVICE).ok();
+    #[cfg(feature = "fs")]
+    dev::register(&nvme::DEVICE).ok();
+    #[cfg(feature = "usb")]
+    dev::register(&usb::DEVICE).ok();
+}
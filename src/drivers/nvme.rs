@@ -0,0 +1,587 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use spin::Mutex;
+use x86_64::{instructions, PhysAddr};
+
+use crate::{apprise, failure};
+use crate::kernel::{apic, block, idt, memory, pci, pit, time};
+use crate::kernel::allocator::fallible;
+use crate::kernel::block::BlockDevice;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::memory::{dma, PAGE_SIZE};
+use crate::kernel::memory::dma::DmaBuffer;
+
+// Non-Volatile Memory Express (NVMe)
+//
+// An NVMe controller is a PCI device whose registers are mapped through BAR0. Commands are placed in
+// submission queues residing in host memory, and the controller posts the outcome of each command to
+// a completion queue. Queues come in pairs: the admin pair (queue ID 0) is used to configure the
+// controller, and at least one I/O pair carries the reads and writes.
+//
+// The host announces new submissions by writing the queue's tail to its doorbell register. The
+// controller marks every completion entry with a phase bit, which it inverts each time it wraps around
+// the queue, so a new entry is recognized by its phase bit matching the expected one.
+//
+// Completions of the I/O queue are signalled through MSI-X when the controller supports it and the
+// local APIC is enabled: the code waiting for a command halts the CPU until the vector fires, rather
+// than spinning on the completion queue. Without MSI-X, all interrupts of the controller are masked
+// and completions are polled, as they always are on the admin queue, which only serves the setup.
+//
+// Each namespace of a controller is registered as a block device named `nvme<controller>n<namespace>`.
+// Transfers go through a single page-sized bounce buffer so that a command never needs more than one
+// PRP entry.
+//
+// OS Dev Wiki: https://wiki.osdev.org/NVMe
+// Specification: https://nvmexpress.org/specifications/
+
+////////////////
+// Attributes
+////////////////
+
+/// PCI class, subclass and programming interface of NVMe controllers.
+const PCI_CLASS: (u8, u8, u8) = (0x01, 0x08, 0x02);
+
+/// Number of entries in each queue.
+const QUEUE_DEPTH: usize = 64;
+/// Size of a submission queue entry.
+const SQ_ENTRY_SIZE: usize = 64;
+/// Size of a completion queue entry.
+const CQ_ENTRY_SIZE: usize = 16;
+
+/// Maximum number of namespaces registered per controller.
+const MAX_NAMESPACES: u32 = 16;
+
+/// Time allowed for a command to complete, in seconds.
+const COMMAND_TIMEOUT: f64 = 5.0;
+
+/// MSI-X table entry of the I/O completion queue; entry 0 belongs to the admin queue.
+const IO_MSI_X_ENTRY: u16 = 1;
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the NVMe controllers.
+pub(crate) static DEVICE: Device = Device {
+    name: "NVMe",
+    class: Class::Storage,
+    stage: Stage::Driver,
+    critical: false,
+    depends: &["PCI"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+////////////////
+/// Register
+////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(usize)]
+enum Register {
+    /// Controller Capabilities.
+    Capabilities = 0x00,
+    /// Interrupt Mask Set.
+    InterruptMaskSet = 0x0C,
+    /// Controller Configuration.
+    Configuration = 0x14,
+    /// Controller Status.
+    Status = 0x1C,
+    /// Admin Queue Attributes.
+    AdminQueueAttributes = 0x24,
+    /// Admin Submission Queue Base Address.
+    AdminSubmissionQueue = 0x28,
+    /// Admin Completion Queue Base Address.
+    AdminCompletionQueue = 0x30,
+}
+
+//////////////
+/// Opcode
+//////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+enum Opcode {
+    // Admin commands.
+    CreateIoSq = 0x01,
+    CreateIoCq = 0x05,
+    Identify = 0x06,
+    // I/O commands.
+    Flush = 0x00,
+    Write = 0x81,
+    Read = 0x82,
+}
+
+impl Opcode {
+    /// Returns the opcode as it appears in the command.
+    ///
+    /// Note: I/O and admin opcodes overlap, so I/O opcodes are kept with bit 7 set to tell them apart.
+    fn as_u8(&self) -> u8 {
+        match self {
+            Self::Write | Self::Read => (*self as u8) & 0x7F,
+            _ => *self as u8,
+        }
+    }
+}
+
+///////////////
+/// Command
+///////////////
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+struct Command {
+    opcode: u8,
+    flags: u8,
+    cid: u16,
+    nsid: u32,
+    reserved: u64,
+    mptr: u64,
+    prp1: u64,
+    prp2: u64,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+}
+
+impl Command {
+    /// Creates a new object.
+    fn new(opcode: Opcode, nsid: u32, prp1: PhysAddr) -> Self {
+        Command {
+            opcode: opcode.as_u8(),
+            nsid,
+            prp1: prp1.as_u64(),
+            ..Default::default()
+        }
+    }
+}
+
+//////////////////
+/// Completion
+//////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Completion {
+    dw0: u32,
+    dw1: u32,
+    sq_head: u16,
+    sq_id: u16,
+    cid: u16,
+    status: u16,
+}
+
+////////////
+/// Regs
+////////////
+#[derive(Debug, Clone, Copy)]
+struct Regs {
+    base: usize,
+    doorbell_stride: usize,
+}
+
+impl Regs {
+    /// Reads a 32-bit register.
+    fn read32(&self, reg: Register) -> u32 {
+        unsafe { ptr::read_volatile((self.base + reg as usize) as *const u32) }
+    }
+
+    /// Writes a 32-bit register.
+    fn write32(&self, reg: Register, value: u32) {
+        unsafe { ptr::write_volatile((self.base + reg as usize) as *mut u32, value) }
+    }
+
+    /// Reads a 64-bit register.
+    fn read64(&self, reg: Register) -> u64 {
+        unsafe { ptr::read_volatile((self.base + reg as usize) as *const u64) }
+    }
+
+    /// Writes a 64-bit register as two 32-bit halves, low half first.
+    fn write64(&self, reg: Register, value: u64) {
+        unsafe {
+            ptr::write_volatile((self.base + reg as usize) as *mut u32, value as u32);
+            ptr::write_volatile((self.base + reg as usize + 4) as *mut u32, (value >> 32) as u32);
+        }
+    }
+
+    /// Writes the doorbell with the given index.
+    ///
+    /// Note: The submission queue tail doorbell of queue `n` has index `2n`, and its completion queue
+    /// head doorbell has index `2n + 1`.
+    fn ring(&self, idx: usize, value: u16) {
+        const DOORBELL_BASE: usize = 0x1000;
+        let addr = self.base + DOORBELL_BASE + idx * self.doorbell_stride;
+        unsafe { ptr::write_volatile(addr as *mut u32, value as u32) }
+    }
+}
+
+//////////////////
+/// Queue Pair
+//////////////////
+struct QueuePair {
+    id: u16,
+    sq: DmaBuffer,
+    cq: DmaBuffer,
+    depth: usize,
+    sq_tail: usize,
+    cq_head: usize,
+    phase: bool,
+    next_cid: u16,
+    interrupts: bool,
+}
+
+impl QueuePair {
+    /// Creates a new object, whose completions are signalled by interrupts or polled.
+    fn new(id: u16, depth: usize, interrupts: bool) -> Result<Self, Error> {
+        let sq = dma::alloc_coherent(depth * SQ_ENTRY_SIZE, PAGE_SIZE).ok_or(Error::OutOfMemory)?;
+        let cq = dma::alloc_coherent(depth * CQ_ENTRY_SIZE, PAGE_SIZE).ok_or(Error::OutOfMemory)?;
+
+        Ok(QueuePair {
+            id,
+            sq,
+            cq,
+            depth,
+            sq_tail: 0,
+            cq_head: 0,
+            phase: true,
+            next_cid: 0,
+            interrupts,
+        })
+    }
+
+    /// Submits the command and waits for its completion.
+    fn submit(&mut self, regs: Regs, mut cmd: Command) -> Result<Completion, Error> {
+        cmd.cid = self.next_cid;
+        self.next_cid = self.next_cid.wrapping_add(1);
+
+        unsafe { ptr::write_volatile(self.sq.as_mut_ptr::<Command>().add(self.sq_tail), cmd); }
+        self.sq_tail = (self.sq_tail + 1) % self.depth;
+        compiler_fence(Ordering::SeqCst);
+        regs.ring(2 * self.id as usize, self.sq_tail as u16);
+
+        let completion = self.wait().ok_or(Error::Hardware(FaultKind::Timeout))?;
+
+        self.cq_head += 1;
+        if self.cq_head == self.depth {
+            self.cq_head = 0;
+            self.phase = !self.phase;
+        }
+        regs.ring(2 * self.id as usize + 1, self.cq_head as u16);
+
+        // Bits 1 to 15 hold the status code and its type; all zero means success.
+        if completion.status >> 1 != 0 { return Err(Error::Hardware(FaultKind::InvalidResponse)); }
+
+        Ok(completion)
+    }
+
+    /// Waits for the controller to post the entry at the head of the completion queue, and returns it
+    /// unless the command timed out.
+    ///
+    /// Note: With interrupts, the CPU halts until the next one instead of spinning. Interrupts are held
+    /// off from the check to the halt, which enables them again atomically, so that a completion
+    /// signalled in between still ends the halt.
+    fn wait(&self) -> Option<Completion> {
+        let deadline = pit::uptime() + COMMAND_TIMEOUT;
+        loop {
+            let entry = if self.interrupts {
+                instructions::interrupts::without_interrupts(
+                    || {
+                        let entry = self.posted();
+                        if entry.is_none() { pit::halt(); }
+                        entry
+                    }
+                )
+            } else {
+                self.posted()
+            };
+
+            if entry.is_some() { return entry; }
+            if pit::uptime() > deadline { return None; }
+            if !self.interrupts { spin_loop(); }
+        }
+    }
+
+    /// Returns the entry at the head of the completion queue if the controller has posted it.
+    fn posted(&self) -> Option<Completion> {
+        let entry = unsafe { ptr::read_volatile(self.cq.as_ptr::<Completion>().add(self.cq_head)) };
+        if (entry.status & 0x1 != 0) == self.phase { Some(entry) } else { None }
+    }
+}
+
+//////////////////
+/// Controller
+//////////////////
+struct Controller {
+    regs: Regs,
+    admin: QueuePair,
+    io: QueuePair,
+    bounce: DmaBuffer,
+}
+
+impl Controller {
+    /// Resets and configures the controller behind the given PCI function, signalling the completions
+    /// of its I/O queue on the given vector if there is one.
+    fn new(function: &pci::Function, vector: Option<u8>) -> Result<Self, Error> {
+        function.enable_bus_master();
+        let bar = function.memory_bar(0).ok_or(Error::Unsupported)?;
+        let base = memory::phys_to_virt_addr(PhysAddr::new(bar)).as_u64() as usize;
+
+        let cap = Regs { base, doorbell_stride: 0 }.read64(Register::Capabilities);
+        let regs = Regs { base, doorbell_stride: 4 << ((cap >> 32) & 0xF) };
+
+        // The queues are laid out in 4 KiB pages, which must be a supported memory page size.
+        let mps_min = (cap >> 48) & 0xF;
+        let supports_nvm = (cap >> 37) & 0x1 != 0;
+        if mps_min != 0 || !supports_nvm { return Err(Error::Unsupported); }
+
+        let max_entries = (cap & 0xFFFF) as usize + 1;
+        let depth = QUEUE_DEPTH.min(max_entries);
+        let timeout = ((cap >> 24) & 0xFF) as f64 * 0.5;
+
+        // Disable the controller before touching the admin queue registers.
+        const CC_ENABLE: u32 = 0x1;
+        regs.write32(Register::Configuration, regs.read32(Register::Configuration) & !CC_ENABLE);
+        wait_ready(regs, false, timeout)?;
+
+        let admin = QueuePair::new(0, depth, false)?;
+        regs.write32(Register::AdminQueueAttributes, (((depth - 1) << 16) | (depth - 1)) as u32);
+        regs.write64(Register::AdminSubmissionQueue, admin.sq.phys_addr().as_u64());
+        regs.write64(Register::AdminCompletionQueue, admin.cq.phys_addr().as_u64());
+
+        // Without MSI-X, completions are polled, so mask all interrupts. With it, the mask registers
+        // must be left alone, and the entry of the admin queue stays masked in the table instead.
+        // Without interrupt remapping, messages can only address the first 256 local APICs.
+        let interrupts = vector.map_or(false, |vector| {
+            function.enable_msi_x(IO_MSI_X_ENTRY, vector, apic::local::id() as u8).is_ok()
+        });
+        if !interrupts { regs.write32(Register::InterruptMaskSet, u32::MAX); }
+
+        // Enable the controller with 64-byte submission and 16-byte completion entries.
+        const IOSQES: u32 = 6 << 16;
+        const IOCQES: u32 = 4 << 20;
+        regs.write32(Register::Configuration, CC_ENABLE | IOSQES | IOCQES);
+        wait_ready(regs, true, timeout)?;
+
+        let io = QueuePair::new(1, depth, interrupts)?;
+        let bounce = dma::alloc_coherent(PAGE_SIZE, PAGE_SIZE).ok_or(Error::OutOfMemory)?;
+        let mut controller = Controller { regs, admin, io, bounce };
+
+        // Create the I/O completion queue first, since the submission queue refers to it.
+        const PHYSICALLY_CONTIGUOUS: u32 = 0x1;
+        const INTERRUPTS_ENABLED: u32 = 0x2;
+        let queue_attrs = (((depth - 1) << 16) | 1) as u32;
+
+        let mut cmd = Command::new(Opcode::CreateIoCq, 0, controller.io.cq.phys_addr());
+        cmd.cdw10 = queue_attrs;
+        cmd.cdw11 = PHYSICALLY_CONTIGUOUS;
+        if interrupts { cmd.cdw11 |= INTERRUPTS_ENABLED | (IO_MSI_X_ENTRY as u32) << 16; }
+        controller.admin(cmd)?;
+
+        let mut cmd = Command::new(Opcode::CreateIoSq, 0, controller.io.sq.phys_addr());
+        cmd.cdw10 = queue_attrs;
+        cmd.cdw11 = (1 << 16) | PHYSICALLY_CONTIGUOUS;
+        controller.admin(cmd)?;
+
+        Ok(controller)
+    }
+
+    /// Submits an admin command.
+    fn admin(&mut self, cmd: Command) -> Result<Completion, Error> { self.admin.submit(self.regs, cmd) }
+
+    /// Runs the IDENTIFY command and returns the 4 KiB data structure.
+    fn identify(&mut self, cns: u32, nsid: u32) -> Result<&[u8], Error> {
+        let mut cmd = Command::new(Opcode::Identify, nsid, self.bounce.phys_addr());
+        cmd.cdw10 = cns;
+        self.admin(cmd)?;
+
+        Ok(self.bounce.as_slice())
+    }
+
+    /// Transfers `buf.len() / block_size` blocks (at most one page) between `buf` and the namespace.
+    fn transfer(&mut self, opcode: Opcode, nsid: u32, lba: u64, block_size: usize, buf: &mut [u8]) -> Result<(), Error> {
+        let blocks = buf.len() / block_size;
+        if let Opcode::Write = opcode { self.bounce.as_mut_slice()[..buf.len()].copy_from_slice(buf); }
+
+        let mut cmd = Command::new(opcode, nsid, self.bounce.phys_addr());
+        cmd.cdw10 = lba as u32;
+        cmd.cdw11 = (lba >> 32) as u32;
+        cmd.cdw12 = (blocks - 1) as u32;
+        self.io.submit(self.regs, cmd)?;
+
+        if let Opcode::Read = opcode { buf.copy_from_slice(&self.bounce.as_slice()[..buf.len()]); }
+
+        Ok(())
+    }
+}
+
+/////////////////
+/// Namespace
+/////////////////
+struct Namespace {
+    name: String,
+    nsid: u32,
+    block_size: usize,
+    block_count: u64,
+    controller: Arc<Mutex<Controller>>,
+}
+
+impl Namespace {
+    /// Transfers whole blocks between `buf` and the namespace, one page at a time.
+    fn transfer(&mut self, opcode: Opcode, lba: u64, buf: &mut [u8]) -> Result<(), Error> {
+        let blocks = (buf.len() / self.block_size) as u64;
+        if buf.len() % self.block_size != 0 { return Err(Error::InvalidArgument); }
+        if lba.checked_add(blocks).map_or(true, |end| end > self.block_count) { return Err(Error::OutOfBounds); }
+
+        let mut controller = self.controller.lock();
+        for (idx, chunk) in buf.chunks_mut(PAGE_SIZE).enumerate() {
+            let chunk_lba = lba + (idx * PAGE_SIZE / self.block_size) as u64;
+            controller.transfer(opcode, self.nsid, chunk_lba, self.block_size, chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BlockDevice for Namespace {
+    fn name(&self) -> &str { &self.name }
+
+    fn block_size(&self) -> usize { self.block_size }
+
+    fn block_count(&self) -> u64 { self.block_count }
+
+    fn read(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), Error> { self.transfer(Opcode::Read, lba, buf) }
+
+    fn write(&mut self, lba: u64, buf: &[u8]) -> Result<(), Error> {
+        // The data is only read from, but the transfer loop works on mutable chunks.
+        let mut data = fallible::try_to_vec(buf)?;
+        self.transfer(Opcode::Write, lba, &mut data)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        let mut controller = self.controller.lock();
+        let regs = controller.regs;
+        let cmd = Command::new(Opcode::Flush, self.nsid, PhysAddr::new(0));
+        controller.io.submit(regs, cmd).map(|_| ())
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Initializes all NVMe controllers and registers their namespaces as block devices.
+pub(crate) fn init() -> Result<(), Error> {
+    if !time::is_ticking() { return Err(Error::NotInitialized); }
+
+    let (class, subclass, prog_if) = PCI_CLASS;
+    let functions = pci::find_by_class(class, subclass, prog_if);
+    if functions.is_empty() { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    // The I/O queues of all controllers share a vector, as their handler has nothing to tell apart.
+    let vector = if apic::is_enabled() { idt::alloc_msi_vector(irq_handler) } else { None };
+
+    let mut registered = 0;
+    for (idx, function) in functions.iter().enumerate() {
+        match probe(idx, function, vector) {
+            Ok(count) => registered += count,
+            Err(e) => failure!("NVMe: controller at {:02x}:{:02x}.{}: {}", function.bus, function.slot, function.func, e),
+        }
+    }
+
+    if registered == 0 { return Err(Error::NotFound); }
+
+    Ok(())
+}
+
+/// Initializes the controller and registers its namespaces, returning how many were registered.
+fn probe(idx: usize, function: &pci::Function, vector: Option<u8>) -> Result<usize, Error> {
+    let mut controller = Controller::new(function, vector)?;
+
+    let data = controller.identify(1, 0)?;
+    let model: String = String::from_utf8_lossy(&data[24..64]).trim().into();
+    let namespaces = u32::from_le_bytes([data[516], data[517], data[518], data[519]]);
+
+    let mut found = Vec::new();
+    for nsid in 1..=namespaces.min(MAX_NAMESPACES) {
+        let data = controller.identify(0, nsid)?;
+        let block_count = u64::from_le_bytes(data[0..8].try_into().unwrap());
+        if block_count == 0 { continue; }
+
+        // The formatted LBA size selects one of the LBA formats, whose bits 16 to 23 hold log2 of the
+        // block size.
+        let format = (data[26] & 0xF) as usize;
+        let lba_format = u32::from_le_bytes(data[128 + 4 * format..132 + 4 * format].try_into().unwrap());
+        let block_size = 1usize << ((lba_format >> 16) & 0xFF);
+        if !(512..=PAGE_SIZE).contains(&block_size) { continue; }
+
+        found.push((nsid, block_size, block_count));
+    }
+
+    apprise!("NVMe: {} with {} namespace(s)", model, found.len());
+
+    let controller = Arc::new(Mutex::new(controller));
+    let count = found.len();
+    for (nsid, block_size, block_count) in found {
+        block::register(Box::new(Namespace {
+            name: format!("nvme{}n{}", idx, nsid),
+            nsid,
+            block_size,
+            block_count,
+            controller: controller.clone(),
+        }))?;
+    }
+
+    Ok(count)
+}
+
+/// Waits for the controller to report the given readiness.
+fn wait_ready(regs: Regs, ready: bool, timeout: f64) -> Result<(), Error> {
+    const CSTS_READY: u32 = 0x1;
+    const CSTS_FATAL: u32 = 0x2;
+
+    let deadline = pit::uptime() + timeout;
+    loop {
+        let csts = regs.read32(Register::Status);
+        if csts & CSTS_FATAL != 0 { return Err(Error::Hardware(FaultKind::InvalidResponse)); }
+        if (csts & CSTS_READY != 0) == ready { return Ok(()); }
+        if pit::uptime() > deadline { return Err(Error::Hardware(FaultKind::Timeout)); }
+        spin_loop();
+    }
+}
+
+/// Handles the completion interrupts of the I/O queues.
+///
+/// Note: The interrupt itself ends the halt of the code waiting for a command, which then finds the
+/// new entry in its completion queue, so there is nothing left to do here.
+fn irq_handler() {}
@@ -21,35 +21,514 @@
 // SOFTWARE.
 
 use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU8, Ordering};
 
-use lazy_static::lazy_static;
 use spin::Mutex;
 use uart_16550::SerialPort;
-use x86_64::instructions;
+use x86_64::{instructions, PhysAddr};
 
-///////////////////////
-// Global Interfaces
-///////////////////////
+use crate::api::serial::{Com, Config, Default, Parity};
+use crate::devices::console;
+use crate::encodings::ASCII;
+use crate::encodings::Charset;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::idt;
+use crate::kernel::idt::IRQ;
+use crate::kernel::memory;
+use crate::kernel::portio;
+use crate::kernel::portio::Port;
 
-lazy_static! {
-    /// Global interface for serial outputting to host system.
-    static ref SERIAL_3F8: Mutex<SerialPort> = {
-        const PORT_NUM: u16 = 0x3F8;
+// Serial Ports
+//
+// Up to four UARTs are supported, COM1 to COM4. Each is a device of its own, probed at the base port
+// the firmware recorded in the BIOS data area, or else at the standard one, by sending a byte to
+// itself in loopback mode. A port is set up on first use, so that COM1 can be written to before
+// any device is initialized, as it always could.
+//
+// The remote console, which takes input and receives `serial_print!`, and the logger each write to a
+// port of their own choosing, COM1 unless changed.
+
+////////////////
+// Attributes
+////////////////
+
+/// Standard base ports of COM1 to COM4.
+const STANDARD_BASES: [u16; 4] = [0x3F8, 0x2F8, 0x3E8, 0x2E8];
+/// Physical address of the base ports of COM1 to COM4 in the BIOS data area.
+const BDA_PORTS: u64 = 0x400;
+/// Number of ports used by a serial port.
+const PORT_COUNT: u16 = 8;
+/// Highest baud rate, i.e. that of a divisor of one.
+const MAX_BAUD: u32 = 115200;
+
+/// Offset of the data register, or of the low byte of the divisor while the latch is accessible.
+const DATA: u16 = 0;
+/// Offset of the interrupt enable register, or of the high byte of the divisor while the latch is accessible.
+const INTERRUPT_ENABLE: u16 = 1;
+/// Offset of the line control register.
+const LINE_CONTROL: u16 = 3;
+/// Offset of the modem control register.
+const MODEM_CONTROL: u16 = 4;
+/// Offset of the line status register.
+const LINE_STATUS: u16 = 5;
+
+/// Interrupt enable bit for received data.
+const RECEIVED_DATA: u8 = 0x01;
+/// Line control bit giving access to the divisor latch.
+const DIVISOR_LATCH: u8 = 0x80;
+/// Line control bits for eight data bits and one stop bit.
+const EIGHT_BITS: u8 = 0x03;
+/// Modem control bits looping the output back into the input.
+const LOOPBACK: u8 = 0x1E;
+/// Bit of the line status register set while a received byte waits to be read.
+const DATA_READY: u8 = 0x01;
+/// Byte sent in loopback mode to tell whether a UART is there.
+const PROBE_BYTE: u8 = 0xAE;
+/// Number of times the line status is polled for the probe byte.
+const PROBE_ATTEMPTS: usize = 1000;
+
+/// Introducer of a frame, followed by the channel and a `;`.
+const FRAME_START: &str = "\x1B_";
+/// Terminator of a frame.
+const FRAME_END: &str = "\x1B\\";
+/// Channel of the remote console.
+const CONSOLE_CHANNEL: char = 'c';
+/// Channel of the logger.
+const LOG_CHANNEL: char = 'l';
+
+/// Sequence asking the host terminal to bracket pasted text.
+const ENABLE_BRACKETED_PASTE: &str = "\x1B[?2004h";
+/// Sequence the host terminal sends before pasted text.
+const PASTE_START: &[u8] = b"\x1B[200~";
+/// Sequence the host terminal sends after pasted text.
+const PASTE_END: &[u8] = b"\x1B[201~";
+
+////////////
+// States
+////////////
+
+/// Base ports of COM1 to COM4.
+static BASES: [AtomicU16; 4] = [
+    AtomicU16::new(STANDARD_BASES[0]),
+    AtomicU16::new(STANDARD_BASES[1]),
+    AtomicU16::new(STANDARD_BASES[2]),
+    AtomicU16::new(STANDARD_BASES[3]),
+];
+/// Whether a UART answered at each of COM1 to COM4.
+static PRESENT: [AtomicBool; 4] = [AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false)];
+/// Port of the remote console.
+static CONSOLE: AtomicU8 = AtomicU8::new(Com::COM1 as u8);
+/// Port the logger writes to.
+static LOG_PORT: AtomicU8 = AtomicU8::new(Com::COM1 as u8);
+/// Whether the output is framed by channel.
+static MUX_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/////////////
+// Mutexes
+/////////////
 
-        let mut port = unsafe { SerialPort::new(PORT_NUM) };
+/// Ports set up so far.
+static PORTS: Mutex<[Option<Uart>; 4]> = Mutex::new([None, None, None, None]);
+
+// Multiplexing
+//
+// The console and the logger may well share a port, in which case a host sees the two streams mixed
+// up. For a host that knows better, e.g. a test harness, the output can be multiplexed: each write
+// is then sent as a frame of its own, in the form of an application program command that terminals
+// ignore,
+//
+//     ESC _ <channel> ; <payload> ESC \
+//
+// with `c` for the console and `l` for the logger as the channel, and each ESC of the payload doubled.
+// Anything outside a frame, such as a panic report, is plain console output. Multiplexing is off by
+// default, so that a plain terminal shows plain text.
+
+// Serial Input
+//
+// Bytes received over the console port are decoded as UTF-8 and handed to the console like keys, a
+// DEL standing for a backspace as most terminals send it. The host terminal is asked to bracket
+// pasted text with `ESC [ 200 ~` and `ESC [ 201 ~`, which are picked out of the input to have the
+// console take the paste literally.
+
+/// Received bytes not yet handed to the console.
+static INPUT: Mutex<Input> = Mutex::new(Input::new());
+
+/////////////
+// Devices
+/////////////
+
+/// Device descriptors of the serial ports.
+pub(crate) static DEVICES: [Device; 4] = [
+    Device {
+        name: "COM1",
+        class: Class::Serial,
+        stage: Stage::Console,
+        critical: false,
+        depends: &[],
+        tolerates_absent: false,
+        init: || init(Com::COM1),
+        suspend: None,
+        resume: None,
+    },
+    Device {
+        name: "COM2",
+        class: Class::Serial,
+        stage: Stage::Console,
+        critical: false,
+        depends: &[],
+        tolerates_absent: false,
+        init: || init(Com::COM2),
+        suspend: None,
+        resume: None,
+    },
+    Device {
+        name: "COM3",
+        class: Class::Serial,
+        stage: Stage::Console,
+        critical: false,
+        depends: &[],
+        tolerates_absent: false,
+        init: || init(Com::COM3),
+        suspend: None,
+        resume: None,
+    },
+    Device {
+        name: "COM4",
+        class: Class::Serial,
+        stage: Stage::Console,
+        critical: false,
+        depends: &[],
+        tolerates_absent: false,
+        init: || init(Com::COM4),
+        suspend: None,
+        resume: None,
+    },
+];
+
+////////////
+/// Uart
+////////////
+/// A serial port along with its line settings.
+struct Uart {
+    port: SerialPort,
+    base: u16,
+    config: Config,
+}
+
+impl Uart {
+    /// Sets up the port at the given base with the default line settings.
+    fn new(com: Com, base: u16) -> Self {
+        let mut port = unsafe { SerialPort::new(base) };
         port.init();
 
-        Mutex::new(port)
-    };
+        let uart = Uart { port, base, config: Default::CONFIG };
+        uart.set_receive_interrupt(com == get_console());
+        uart
+    }
+
+    /// Sets whether the port raises an interrupt on received data.
+    fn set_receive_interrupt(&self, enabled: bool) {
+        let mut interrupt_enable = Port::<u8>::new(self.base + INTERRUPT_ENABLE);
+        unsafe { interrupt_enable.write(if enabled { RECEIVED_DATA } else { 0 }); }
+    }
+
+    /// Programs the line settings.
+    fn configure(&mut self, config: Config) -> Result<(), Error> {
+        if config.baud == 0 || MAX_BAUD % config.baud != 0 { return Err(Error::InvalidArgument); }
+        let divisor = (MAX_BAUD / config.baud) as u16;
+        let parity = match config.parity {
+            Parity::None => 0x00,
+            Parity::Odd => 0x08,
+            Parity::Even => 0x18,
+            Parity::Mark => 0x28,
+            Parity::Space => 0x38,
+        };
+
+        let mut data = Port::<u8>::new(self.base + DATA);
+        let mut interrupt_enable = Port::<u8>::new(self.base + INTERRUPT_ENABLE);
+        let mut line_control = Port::<u8>::new(self.base + LINE_CONTROL);
+        unsafe {
+            line_control.write(DIVISOR_LATCH);
+            data.write(divisor as u8);
+            interrupt_enable.write((divisor >> 8) as u8);
+            line_control.write(EIGHT_BITS | parity);
+        }
+        self.config = config;
+
+        Ok(())
+    }
 }
 
-#[doc(hidden)]
-pub fn _print(args: fmt::Arguments) {
-    use fmt::Write;
+///////////////
+// Utilities
+///////////////
+
+/// Probes the serial port and sets it up if a UART answers.
+fn init(com: Com) -> Result<(), Error> {
+    let base = firmware_base(com).unwrap_or(STANDARD_BASES[com as usize]);
+    BASES[com as usize].store(base, Ordering::Relaxed);
+
+    if !instructions::interrupts::without_interrupts(|| probe(base)) {
+        return Err(Error::Hardware(FaultKind::NotPresent));
+    }
+    portio::reserve(DEVICES[com as usize].name, base, PORT_COUNT).ok();
 
     instructions::interrupts::without_interrupts(
-        || { SERIAL_3F8.lock().write_fmt(args).expect("could not print to serial output"); }
+        || { PORTS.lock()[com as usize] = Some(Uart::new(com, base)); }
     );
+    PRESENT[com as usize].store(true, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Returns the base port of the serial port recorded in the BIOS data area, if any.
+///
+/// Note: Only standard bases are trusted, as the area holds anything on machines without a BIOS.
+fn firmware_base(com: Com) -> Option<u16> {
+    if memory::physical_memory_offset() == 0 { return None; }
+
+    let addr = memory::phys_to_virt_addr(PhysAddr::new(BDA_PORTS + 2 * com as u64));
+    let base = unsafe { core::ptr::read_volatile(addr.as_ptr::<u16>()) };
+    STANDARD_BASES.contains(&base).then_some(base)
+}
+
+/// Returns whether a UART at the base port gets back a byte sent to itself in loopback mode.
+fn probe(base: u16) -> bool {
+    let mut data = Port::<u8>::new(base + DATA);
+    let mut modem_control = Port::<u8>::new(base + MODEM_CONTROL);
+    let mut line_status = Port::<u8>::new(base + LINE_STATUS);
+
+    unsafe {
+        let saved = modem_control.read();
+        modem_control.write(LOOPBACK);
+        // Drop whatever was received before, giving up on a floating bus.
+        for _ in 0..PROBE_ATTEMPTS {
+            if line_status.read() & DATA_READY == 0 { break; }
+            data.read();
+        }
+        data.write(PROBE_BYTE);
+
+        let answered = (0..PROBE_ATTEMPTS).any(|_| line_status.read() & DATA_READY != 0) && data.read() == PROBE_BYTE;
+        modem_control.write(saved);
+        answered
+    }
+}
+
+/// Runs the given function on the serial port, setting it up first if needed.
+fn with_port<R>(com: Com, f: impl FnOnce(&mut Uart) -> R) -> R {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut ports = PORTS.lock();
+            let uart = ports[com as usize].get_or_insert_with(|| Uart::new(com, get_base(com)));
+            f(uart)
+        }
+    )
+}
+
+/// Returns whether a UART answered at the serial port.
+pub(crate) fn is_present(com: Com) -> bool { PRESENT[com as usize].load(Ordering::Relaxed) }
+
+/// Returns the base port of the serial port.
+pub(crate) fn get_base(com: Com) -> u16 { BASES[com as usize].load(Ordering::Relaxed) }
+
+/// Returns the line settings of the serial port.
+pub(crate) fn get_config(com: Com) -> Result<Config, Error> {
+    if !is_present(com) { return Err(Error::Hardware(FaultKind::NotPresent)); }
+    Ok(with_port(com, |uart| uart.config))
+}
+
+/// Changes the line settings of the serial port.
+pub(crate) fn set_config(com: Com, config: Config) -> Result<(), Error> {
+    if !is_present(com) { return Err(Error::Hardware(FaultKind::NotPresent)); }
+    with_port(com, |uart| uart.configure(config))
+}
+
+/// Returns the port of the remote console.
+pub(crate) fn get_console() -> Com { Com::from_index(CONSOLE.load(Ordering::Relaxed)).unwrap() }
+
+/// Moves the remote console to the serial port, along with its input.
+pub(crate) fn set_console(com: Com) -> Result<(), Error> {
+    if !is_present(com) { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    let previous = get_console();
+    CONSOLE.store(com as u8, Ordering::Relaxed);
+    with_port(previous, |uart| uart.set_receive_interrupt(false));
+    instructions::interrupts::without_interrupts(|| { *INPUT.lock() = Input::new(); });
+    enable_input()
+}
+
+/// Returns the port the logger writes to.
+pub(crate) fn get_log_port() -> Com { Com::from_index(LOG_PORT.load(Ordering::Relaxed)).unwrap() }
+
+/// Has the logger write to the serial port.
+pub(crate) fn set_log_port(com: Com) -> Result<(), Error> {
+    if !is_present(com) { return Err(Error::Hardware(FaultKind::NotPresent)); }
+    LOG_PORT.store(com as u8, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Returns the interrupt line of the serial port.
+fn irq(com: Com) -> IRQ {
+    match com {
+        Com::COM1 | Com::COM3 => IRQ::COM1,
+        Com::COM2 | Com::COM4 => IRQ::COM2,
+    }
+}
+
+/// Starts handing the bytes received over the console port to the console.
+pub(crate) fn enable_input() -> Result<(), Error> {
+    let com = get_console();
+    with_port(com, |uart| uart.set_receive_interrupt(true));
+    idt::set_irq_handler(irq(com), serial_irq_handler);
+    _print(format_args!("{}", ENABLE_BRACKETED_PASTE));
+
+    Ok(())
+}
+
+/// Returns whether the output is framed by channel.
+pub(crate) fn is_mux_enabled() -> bool { MUX_ENABLED.load(Ordering::Relaxed) }
+
+/// Starts framing the output by channel.
+pub(crate) fn enable_mux() { MUX_ENABLED.store(true, Ordering::Relaxed); }
+
+/// Stops framing the output by channel.
+pub(crate) fn disable_mux() { MUX_ENABLED.store(false, Ordering::Relaxed); }
+
+/// Writes the formatted output to the serial port, as a frame of the given channel if multiplexing.
+fn write_channel(com: Com, channel: char, args: fmt::Arguments) {
+    use fmt::Write;
+
+    with_port(
+        com,
+        |uart| {
+            let res = if is_mux_enabled() {
+                write!(uart.port, "{}{};", FRAME_START, channel)
+                    .and_then(|_| Frame(&mut uart.port).write_fmt(args))
+                    .and_then(|_| uart.port.write_str(FRAME_END))
+            } else {
+                uart.port.write_fmt(args)
+            };
+            res.expect("could not print to serial output");
+        }
+    );
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) { write_channel(get_console(), CONSOLE_CHANNEL, args); }
+
+#[doc(hidden)]
+pub fn _print_log(args: fmt::Arguments) { write_channel(get_log_port(), LOG_CHANNEL, args); }
+
+/// Writes the given string to the console port without waiting for the port lock.
+///
+/// Note: Output may interleave with a holder of the lock, so this is only meant for exception and
+/// panic handlers.
+pub(crate) fn write_unsynchronized(s: &str) {
+    use fmt::Write;
+
+    // The port has no state besides its registers, so a second handle is as good as the first.
+    let mut port = unsafe { SerialPort::new(get_base(get_console())) };
+    port.write_str(s).ok();
+}
+
+/////////////
+/// Frame
+/////////////
+/// Writer of the payload of a frame, doubling each ESC.
+struct Frame<'a>(&'a mut SerialPort);
+
+impl fmt::Write for Frame<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (idx, part) in s.split(ASCII::<char>::ESC).enumerate() {
+            if idx > 0 { self.0.write_str("\x1B\x1B")?; }
+            self.0.write_str(part)?;
+        }
+        Ok(())
+    }
+}
+
+/////////////
+/// Input
+/////////////
+/// Decoder of the received bytes.
+struct Input {
+    /// Bytes that might start a paste bracket.
+    bracket: [u8; PASTE_START.len()],
+    bracket_len: usize,
+    /// Bytes of an incomplete UTF-8 sequence.
+    utf8: [u8; 4],
+    utf8_len: usize,
+    pasting: bool,
+}
+
+impl Input {
+    /// Creates a decoder with nothing received.
+    const fn new() -> Self {
+        Input {
+            bracket: [0; PASTE_START.len()],
+            bracket_len: 0,
+            utf8: [0; 4],
+            utf8_len: 0,
+            pasting: false,
+        }
+    }
+
+    /// Takes in a received byte.
+    fn receive(&mut self, byte: u8) {
+        self.bracket[self.bracket_len] = byte;
+        self.bracket_len += 1;
+
+        let bracket = &self.bracket[..self.bracket_len];
+        if bracket == PASTE_START || bracket == PASTE_END {
+            self.pasting = bracket == PASTE_START;
+            if self.pasting { console::begin_paste(); } else { console::end_paste(); }
+            self.bracket_len = 0;
+        } else if !PASTE_START.starts_with(bracket) && !PASTE_END.starts_with(bracket) {
+            let (bytes, len) = (self.bracket, self.bracket_len);
+            self.bracket_len = 0;
+            for &byte in &bytes[..len] {
+                self.decode(byte);
+            }
+        }
+    }
+
+    /// Decodes a byte that is not part of a paste bracket, handing the completed character to the console.
+    fn decode(&mut self, byte: u8) {
+        if self.utf8_len == self.utf8.len() { self.utf8_len = 0; }
+        self.utf8[self.utf8_len] = byte;
+        self.utf8_len += 1;
+
+        let c = match core::str::from_utf8(&self.utf8[..self.utf8_len]) {
+            Ok(s) => s.chars().next().unwrap(),
+            // Wait for the rest of the sequence.
+            Err(e) if e.error_len().is_none() => return,
+            Err(_) => char::REPLACEMENT_CHARACTER,
+        };
+        self.utf8_len = 0;
+
+        match c {
+            ASCII::<char>::DEL if !self.pasting => console::key_handle(ASCII::<char>::BS),
+            _ => console::key_handle(c),
+        }
+    }
+}
+
+//////////////
+// Handlers
+//////////////
+
+/// An irq handler for the console port, taking in every byte received.
+fn serial_irq_handler() {
+    let base = get_base(get_console());
+    let mut line_status = Port::<u8>::new(base + LINE_STATUS);
+    let mut data = Port::<u8>::new(base + DATA);
+
+    let mut input = INPUT.lock();
+    while unsafe { line_status.read() } & DATA_READY != 0 {
+        input.receive(unsafe { data.read() });
+    }
 }
 
 ////////////
@@ -0,0 +1,421 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{apprise, failure};
+use crate::api::usb::{Info, Speed};
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::pit;
+use crate::kernel::time;
+use crate::kernel::sync::Mutex;
+
+pub mod hid;
+#[cfg(feature = "fs")]
+pub mod storage;
+pub mod uhci;
+
+// Universal Serial Bus (USB)
+//
+// USB is a tiered star of devices hanging off a host controller, whose root hub provides the first
+// ports. Every transfer is initiated by the host: a device only ever answers. A freshly attached
+// device listens on address 0 with a control endpoint (endpoint 0) whose packet size is at least 8
+// bytes, and is brought up by the host through standard requests sent as control transfers.
+//
+// A control transfer consists of a setup stage, carrying the 8-byte setup packet describing the
+// request, an optional data stage, and a status stage in the opposite direction of the data. The
+// host reads the first 8 bytes of the device descriptor to learn the packet size of endpoint 0,
+// assigns an address, reads the full device and configuration descriptors, and finally selects a
+// configuration. The configuration descriptor is followed by the descriptors of its interfaces,
+// each followed by those of its endpoints, and drivers bind to interfaces by class.
+//
+// Only UHCI host controllers (USB 1.1, as emulated by QEMU by default) are driven. On machines where
+// an EHCI controller owns the ports, low- and full-speed devices are routed to its UHCI companions
+// as long as EHCI is left unconfigured. Devices are enumerated once at boot, hubs are not supported,
+// and input devices are polled by a task rather than through interrupts.
+//
+// OS Dev Wiki: https://wiki.osdev.org/Universal_Serial_Bus
+// Specification: https://www.usb.org/document-library/usb-20-specification
+
+////////////////
+// Attributes
+////////////////
+
+/// Interval at which input devices are polled, in seconds.
+const POLL_INTERVAL: f64 = 0.01;
+
+/// Packet size of endpoint 0 every device supports before its descriptor is read.
+const DEFAULT_MAX_PACKET: u16 = 8;
+/// Highest address a device can be assigned.
+const MAX_ADDRESS: u8 = 127;
+/// Time a device is given to settle after being assigned an address, in seconds.
+const SET_ADDRESS_RECOVERY: f64 = 0.002;
+/// Size of the buffer the configuration descriptor and its trailing descriptors are read into.
+const CONFIG_BUFFER_SIZE: usize = 512;
+
+/// Size of the device descriptor.
+const DEVICE_DESCRIPTOR_SIZE: usize = 18;
+/// Size of the configuration descriptor, without the descriptors that follow it.
+const CONFIG_DESCRIPTOR_SIZE: usize = 9;
+
+// Bits of the request type of a setup packet.
+pub(crate) const REQ_DEVICE_TO_HOST: u8 = 0x80;
+pub(crate) const REQ_CLASS: u8 = 0x20;
+pub(crate) const REQ_INTERFACE: u8 = 0x01;
+pub(crate) const REQ_ENDPOINT: u8 = 0x02;
+
+// Standard requests.
+const REQ_CLEAR_FEATURE: u8 = 0x01;
+const REQ_SET_ADDRESS: u8 = 0x05;
+const REQ_GET_DESCRIPTOR: u8 = 0x06;
+const REQ_SET_CONFIGURATION: u8 = 0x09;
+
+// Descriptor types.
+const DESC_DEVICE: u8 = 0x01;
+const DESC_CONFIGURATION: u8 = 0x02;
+const DESC_INTERFACE: u8 = 0x04;
+const DESC_ENDPOINT: u8 = 0x05;
+
+/// Feature cleared to resume an endpoint that stalled.
+const FEATURE_ENDPOINT_HALT: u16 = 0;
+
+/// Drivers tried, in order, on every interface of a configured device.
+const DRIVERS: &[Driver] = &[
+    Driver { name: hid::NAME, probe: hid::probe, attach: hid::attach },
+    #[cfg(feature = "fs")]
+    Driver { name: storage::NAME, probe: storage::probe, attach: storage::attach },
+];
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the USB host controllers and the devices attached to them.
+pub(crate) static DEVICE: Device = Device {
+    name: "USB",
+    class: Class::Bus,
+    stage: Stage::Driver,
+    critical: false,
+    depends: &["PCI"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+/////////////
+// Mutexes
+/////////////
+
+/// Enumerated devices.
+static DEVICES: Mutex<Vec<Info>> = Mutex::new(Vec::new());
+
+/////////////
+/// Setup
+/////////////
+/// Setup packet opening a control transfer.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Setup {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+impl Setup {
+    /// Creates a standard request reading a descriptor of the device.
+    fn get_descriptor(kind: u8, idx: u8, length: usize) -> Self {
+        Setup {
+            request_type: REQ_DEVICE_TO_HOST,
+            request: REQ_GET_DESCRIPTOR,
+            value: (kind as u16) << 8 | idx as u16,
+            index: 0,
+            length: length as u16,
+        }
+    }
+
+    /// Creates a standard request without a data stage.
+    fn set(request: u8, value: u16) -> Self {
+        Setup {
+            request_type: 0,
+            request,
+            value,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// Returns whether the data stage moves data from the device to the host.
+    pub fn is_in(&self) -> bool { self.request_type & REQ_DEVICE_TO_HOST != 0 }
+
+    /// Returns the packet as sent on the bus.
+    pub fn to_bytes(self) -> [u8; 8] {
+        let [value_lo, value_hi] = self.value.to_le_bytes();
+        let [index_lo, index_hi] = self.index.to_le_bytes();
+        let [length_lo, length_hi] = self.length.to_le_bytes();
+        [self.request_type, self.request, value_lo, value_hi, index_lo, index_hi, length_lo, length_hi]
+    }
+}
+
+//////////////
+/// Target
+//////////////
+/// A device as addressed by its host controller.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Target {
+    /// Index of the host controller.
+    pub controller: usize,
+    /// Root hub port the device is attached to.
+    pub port: u8,
+    pub address: u8,
+    pub speed: Speed,
+    /// Packet size of endpoint 0.
+    pub max_packet: u16,
+}
+
+impl Target {
+    /// Performs a control transfer on endpoint 0, returning the number of bytes moved in the data stage.
+    pub fn control(&self, setup: Setup, data: &mut [u8]) -> Result<usize, Error> { uhci::control(self, &setup, data) }
+
+    /// Resumes an endpoint that stalled.
+    ///
+    /// Note: The data toggle of the endpoint starts over from DATA0.
+    pub fn clear_halt(&self, endpoint: &Endpoint) -> Result<(), Error> {
+        let setup = Setup {
+            request_type: REQ_ENDPOINT,
+            request: REQ_CLEAR_FEATURE,
+            value: FEATURE_ENDPOINT_HALT,
+            index: endpoint.address as u16,
+            length: 0,
+        };
+        self.control(setup, &mut []).map(|_| ())
+    }
+}
+
+////////////////
+/// Endpoint
+////////////////
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Endpoint {
+    /// Endpoint number, with bit 7 set for IN endpoints.
+    pub address: u8,
+    pub attributes: u8,
+    pub max_packet: u16,
+}
+
+impl Endpoint {
+    /// Returns the endpoint number.
+    pub fn number(&self) -> u8 { self.address & 0x0F }
+
+    /// Returns whether data moves from the device to the host.
+    pub fn is_in(&self) -> bool { self.address & REQ_DEVICE_TO_HOST != 0 }
+
+    /// Returns whether it is a bulk endpoint.
+    pub fn is_bulk(&self) -> bool { self.attributes & 0x3 == 0x2 }
+
+    /// Returns whether it is an interrupt endpoint.
+    pub fn is_interrupt(&self) -> bool { self.attributes & 0x3 == 0x3 }
+}
+
+/////////////////
+/// Interface
+/////////////////
+#[derive(Debug, Clone)]
+pub(crate) struct Interface {
+    pub number: u8,
+    pub class: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+    pub endpoints: Vec<Endpoint>,
+}
+
+//////////////
+/// Driver
+//////////////
+/// A driver for a class of interfaces.
+struct Driver {
+    name: &'static str,
+    /// Returns whether the driver handles the interface.
+    probe: fn(&Interface) -> bool,
+    /// Takes over the interface of a configured device.
+    attach: fn(Target, &Interface) -> Result<(), Error>,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Initializes the host controllers and enumerates the devices attached to their root hubs.
+pub(crate) fn init() -> Result<(), Error> {
+    if !time::is_ticking() { return Err(Error::NotInitialized); }
+
+    let controllers = uhci::init()?;
+
+    for controller in 0..controllers {
+        let mut address = 1;
+        for port in 0..uhci::port_count(controller) {
+            let speed = match uhci::reset_port(controller, port) {
+                Ok(Some(speed)) => speed,
+                Ok(None) => continue,
+                Err(e) => {
+                    failure!("USB: port {}-{}: {}", controller, port, e);
+                    continue;
+                }
+            };
+
+            if address > MAX_ADDRESS { break; }
+            match enumerate(controller, port, speed, address) {
+                Ok(info) => {
+                    apprise!(
+                        "USB: port {}-{}: {:04x}:{:04x} ({} speed, {})",
+                        controller, port, info.vendor, info.product, info.speed.as_str(), info.driver.unwrap_or("no driver")
+                    );
+                    DEVICES.lock().push(info);
+                }
+                Err(e) => failure!("USB: port {}-{}: {}", controller, port, e),
+            }
+            address += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the enumerated devices.
+pub(crate) fn devices() -> Vec<Info> { DEVICES.lock().clone() }
+
+/// Brings the device on a root hub port to the configured state and binds drivers to its interfaces.
+fn enumerate(controller: usize, port: u8, speed: Speed, address: u8) -> Result<Info, Error> {
+    let mut target = Target { controller, port, address: 0, speed, max_packet: DEFAULT_MAX_PACKET };
+
+    // Only the first 8 bytes of the descriptor, which hold the packet size of endpoint 0, are read
+    // at first, since the packet size may be as small as that.
+    let mut desc = [0; DEVICE_DESCRIPTOR_SIZE];
+    target.control(Setup::get_descriptor(DESC_DEVICE, 0, 8), &mut desc[..8])?;
+    target.max_packet = (desc[7] as u16).max(DEFAULT_MAX_PACKET);
+
+    target.control(Setup::set(REQ_SET_ADDRESS, address as u16), &mut [])?;
+    pit::sleep(SET_ADDRESS_RECOVERY);
+    target.address = address;
+
+    let len = target.control(Setup::get_descriptor(DESC_DEVICE, 0, DEVICE_DESCRIPTOR_SIZE), &mut desc)?;
+    if len < DEVICE_DESCRIPTOR_SIZE { return Err(Error::Hardware(FaultKind::InvalidResponse)); }
+
+    let mut config = vec![0; CONFIG_BUFFER_SIZE];
+    target.control(Setup::get_descriptor(DESC_CONFIGURATION, 0, CONFIG_DESCRIPTOR_SIZE), &mut config[..CONFIG_DESCRIPTOR_SIZE])?;
+    let total = (u16::from_le_bytes([config[2], config[3]]) as usize).clamp(CONFIG_DESCRIPTOR_SIZE, CONFIG_BUFFER_SIZE);
+    let len = target.control(Setup::get_descriptor(DESC_CONFIGURATION, 0, total), &mut config[..total])?;
+    let interfaces = parse_interfaces(&config[..len]);
+
+    target.control(Setup::set(REQ_SET_CONFIGURATION, config[5] as u16), &mut [])?;
+
+    // A device class of zero leaves the class to each interface.
+    let class = match (desc[4], interfaces.first()) {
+        (0, Some(interface)) => (interface.class, interface.subclass, interface.protocol),
+        _ => (desc[4], desc[5], desc[6]),
+    };
+
+    let mut driver = None;
+    for interface in &interfaces {
+        for candidate in DRIVERS.iter().filter(|candidate| (candidate.probe)(interface)) {
+            match (candidate.attach)(target, interface) {
+                Ok(()) => {
+                    driver = Some(candidate.name);
+                    break;
+                }
+                Err(e) => failure!("USB: port {}-{}: {}: {}", controller, port, candidate.name, e),
+            }
+        }
+    }
+
+    Ok(Info {
+        controller,
+        port,
+        address,
+        speed,
+        vendor: u16::from_le_bytes([desc[8], desc[9]]),
+        product: u16::from_le_bytes([desc[10], desc[11]]),
+        class,
+        driver,
+    })
+}
+
+/// Collects the interfaces, along with their endpoints, from the descriptors trailing a configuration descriptor.
+///
+/// Note: Alternate settings other than the default one are skipped.
+fn parse_interfaces(config: &[u8]) -> Vec<Interface> {
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut skipping = false;
+
+    let mut offset = 0;
+    while offset + 2 <= config.len() {
+        let len = config[offset] as usize;
+        if len < 2 || offset + len > config.len() { break; }
+        let desc = &config[offset..offset + len];
+
+        match desc[1] {
+            DESC_INTERFACE if len >= 9 => {
+                skipping = desc[3] != 0;
+                if !skipping {
+                    interfaces.push(Interface {
+                        number: desc[2],
+                        class: desc[5],
+                        subclass: desc[6],
+                        protocol: desc[7],
+                        endpoints: Vec::new(),
+                    });
+                }
+            }
+            DESC_ENDPOINT if len >= 7 && !skipping => {
+                if let Some(interface) = interfaces.last_mut() {
+                    interface.endpoints.push(Endpoint {
+                        address: desc[2],
+                        attributes: desc[3],
+                        max_packet: u16::from_le_bytes([desc[4], desc[5]]) & 0x7FF,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        offset += len;
+    }
+
+    interfaces
+}
+
+//////////////
+// Handlers
+//////////////
+
+/// Polls the input devices; returns right away if none is attached.
+pub(crate) async fn poll_task() {
+    if !hid::is_attached() { return; }
+
+    loop {
+        pit::delay(POLL_INTERVAL).await;
+        hid::poll();
+    }
+}
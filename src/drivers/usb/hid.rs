@@ -0,0 +1,272 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+
+use pc_keyboard::{KeyCode, KeyEvent, KeyState};
+use x86_64::instructions;
+
+use crate::drivers::keyboard;
+use crate::drivers::usb::{Interface, Setup, Target, REQ_CLASS, REQ_INTERFACE};
+use crate::drivers::usb::uhci::InterruptPipe;
+use crate::kernel::error::Error;
+use crate::kernel::pit;
+use crate::kernel::sync::Mutex;
+
+// Human Interface Device (HID)
+//
+// HID devices describe the layout of their reports with a report descriptor, which takes a parser
+// of its own. Keyboards that implement the boot protocol, meant for firmware that has no such parser,
+// can instead be switched to a fixed 8-byte report: a bitmap of the modifier keys, a reserved byte,
+// and the usage codes of up to six other keys held down.
+//
+// Reports are read from the interrupt IN endpoint of the keyboard and compared with the previous
+// one, and every key that appeared or disappeared becomes a key event, which goes through the same
+// remapping, modifiers and layout as the keys of the PS/2 keyboard. Unlike PS/2 keyboards, USB
+// keyboards leave typematic repeat to the host, so the last key pressed is repeated in software.
+//
+// Specification: https://www.usb.org/document-library/device-class-definition-hid-111
+
+////////////////
+// Attributes
+////////////////
+
+/// Name of the driver.
+pub(crate) const NAME: &str = "hid-kbd";
+
+// Class, subclass and protocol of boot keyboard interfaces.
+const CLASS_HID: u8 = 0x03;
+const SUBCLASS_BOOT: u8 = 0x01;
+const PROTOCOL_KEYBOARD: u8 = 0x01;
+
+// Class requests.
+const REQ_SET_IDLE: u8 = 0x0A;
+const REQ_SET_PROTOCOL: u8 = 0x0B;
+/// Value of the set protocol request selecting the boot protocol.
+const PROTOCOL_BOOT: u16 = 0;
+
+/// Size of a boot protocol report.
+const REPORT_SIZE: usize = 8;
+/// Usage reported in every key slot when too many keys are held down.
+const ERROR_ROLLOVER: u8 = 0x01;
+
+/// Time a key is held before it repeats, in seconds.
+const REPEAT_DELAY: f64 = 0.5;
+/// Time between repeats of a held key, in seconds.
+const REPEAT_INTERVAL: f64 = 0.033;
+
+/// Modifier keys, in the order of the bits of the first byte of a report.
+const MODIFIERS: [KeyCode; 8] = [
+    KeyCode::LControl,
+    KeyCode::LShift,
+    KeyCode::LAlt,
+    KeyCode::LWin,
+    KeyCode::RControl,
+    KeyCode::RShift,
+    KeyCode::RAltGr,
+    KeyCode::RWin,
+];
+
+/////////////
+// Mutexes
+/////////////
+
+/// Attached keyboards.
+static KEYBOARDS: Mutex<Vec<Keyboard>> = Mutex::new(Vec::new());
+
+////////////////
+/// Keyboard
+////////////////
+struct Keyboard {
+    pipe: InterruptPipe,
+    /// Last report received.
+    report: [u8; REPORT_SIZE],
+    /// Key being repeated, along with the uptime of its next repeat.
+    repeat: Option<(KeyCode, f64)>,
+}
+
+impl Keyboard {
+    /// Reads the latest report and collects the resulting key events, including repeats.
+    fn poll(&mut self, now: f64, events: &mut Vec<KeyEvent>) {
+        let mut report = [0; REPORT_SIZE];
+        // A failed report is dropped, as the next one carries the whole state again.
+        if let Ok(Some(len)) = self.pipe.poll(&mut report) {
+            if len > 2 { self.update(report, now, events); }
+        }
+
+        if let Some((code, at)) = self.repeat {
+            if now >= at {
+                events.push(KeyEvent::new(code, KeyState::Down));
+                self.repeat = Some((code, now + REPEAT_INTERVAL));
+            }
+        }
+    }
+
+    /// Compares a report with the previous one and collects the keys that changed.
+    fn update(&mut self, report: [u8; REPORT_SIZE], now: f64, events: &mut Vec<KeyEvent>) {
+        let (old, new) = (&self.report[2..], &report[2..]);
+        if new.contains(&ERROR_ROLLOVER) { return; }
+
+        let changed = self.report[0] ^ report[0];
+        for (bit, &code) in MODIFIERS.iter().enumerate().filter(|(bit, _)| changed & (1 << bit) != 0) {
+            let state = if report[0] & (1 << bit) != 0 { KeyState::Down } else { KeyState::Up };
+            events.push(KeyEvent::new(code, state));
+        }
+
+        for code in old.iter().filter(|usage| !new.contains(usage)).filter_map(|&usage| key_code(usage)) {
+            events.push(KeyEvent::new(code, KeyState::Up));
+            if matches!(self.repeat, Some((repeated, _)) if repeated == code) { self.repeat = None; }
+        }
+
+        for code in new.iter().filter(|usage| !old.contains(usage)).filter_map(|&usage| key_code(usage)) {
+            events.push(KeyEvent::new(code, KeyState::Down));
+            // Lock keys would toggle on every repeat.
+            self.repeat = match code {
+                KeyCode::CapsLock | KeyCode::NumpadLock | KeyCode::ScrollLock => None,
+                _ => Some((code, now + REPEAT_DELAY)),
+            };
+        }
+
+        self.report = report;
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether the interface is a keyboard that implements the boot protocol.
+pub(crate) fn probe(interface: &Interface) -> bool {
+    (interface.class, interface.subclass, interface.protocol) == (CLASS_HID, SUBCLASS_BOOT, PROTOCOL_KEYBOARD)
+}
+
+/// Switches the keyboard to the boot protocol and starts polling it.
+pub(crate) fn attach(target: Target, interface: &Interface) -> Result<(), Error> {
+    let endpoint = interface.endpoints.iter().find(|endpoint| endpoint.is_interrupt() && endpoint.is_in()).ok_or(Error::NotFound)?;
+    let class_request = |request, value| Setup {
+        request_type: REQ_CLASS | REQ_INTERFACE,
+        request,
+        value,
+        index: interface.number as u16,
+        length: 0,
+    };
+
+    target.control(class_request(REQ_SET_PROTOCOL, PROTOCOL_BOOT), &mut [])?;
+    // Reports are only wanted when keys change; keyboards that turn the request down report more
+    // often, which is harmless.
+    target.control(class_request(REQ_SET_IDLE, 0), &mut []).ok();
+
+    let pipe = InterruptPipe::open(&target, endpoint.number(), endpoint.max_packet)?;
+    KEYBOARDS.lock().push(Keyboard { pipe, report: [0; REPORT_SIZE], repeat: None });
+
+    Ok(())
+}
+
+/// Returns whether a keyboard is attached.
+pub(crate) fn is_attached() -> bool { !KEYBOARDS.lock().is_empty() }
+
+/// Polls the keyboards and sends their key events to the console.
+pub(crate) fn poll() {
+    let now = pit::uptime();
+    let mut events = Vec::new();
+    for keyboard in KEYBOARDS.lock().iter_mut() {
+        keyboard.poll(now, &mut events);
+    }
+
+    // The keyboard interrupt handler takes the same locks.
+    for event in events {
+        instructions::interrupts::without_interrupts(
+            || keyboard::process_keyevent(event)
+        );
+    }
+}
+
+/// Returns the key with the given usage code, from the keyboard page of the HID usage tables.
+fn key_code(usage: u8) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I,
+        KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R,
+        KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+        KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Key0,
+    ];
+    const FUNCTIONS: [KeyCode; 12] = [
+        KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+        KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    ];
+    const NUMPAD: [KeyCode; 9] = [
+        KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4, KeyCode::Numpad5,
+        KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    ];
+
+    let code = match usage {
+        0x04..=0x1D => LETTERS[(usage - 0x04) as usize],
+        0x1E..=0x27 => DIGITS[(usage - 0x1E) as usize],
+        0x28 => KeyCode::Return,
+        0x29 => KeyCode::Escape,
+        0x2A => KeyCode::Backspace,
+        0x2B => KeyCode::Tab,
+        0x2C => KeyCode::Spacebar,
+        0x2D => KeyCode::OemMinus,
+        0x2E => KeyCode::OemPlus,
+        0x2F => KeyCode::Oem4,
+        0x30 => KeyCode::Oem6,
+        0x31 => KeyCode::Oem5,
+        0x32 => KeyCode::Oem7,
+        0x33 => KeyCode::Oem1,
+        0x34 => KeyCode::Oem3,
+        0x35 => KeyCode::Oem8,
+        0x36 => KeyCode::OemComma,
+        0x37 => KeyCode::OemPeriod,
+        0x38 => KeyCode::Oem2,
+        0x39 => KeyCode::CapsLock,
+        0x3A..=0x45 => FUNCTIONS[(usage - 0x3A) as usize],
+        0x46 => KeyCode::PrintScreen,
+        0x47 => KeyCode::ScrollLock,
+        0x48 => KeyCode::PauseBreak,
+        0x49 => KeyCode::Insert,
+        0x4A => KeyCode::Home,
+        0x4B => KeyCode::PageUp,
+        0x4C => KeyCode::Delete,
+        0x4D => KeyCode::End,
+        0x4E => KeyCode::PageDown,
+        0x4F => KeyCode::ArrowRight,
+        0x50 => KeyCode::ArrowLeft,
+        0x51 => KeyCode::ArrowDown,
+        0x52 => KeyCode::ArrowUp,
+        0x53 => KeyCode::NumpadLock,
+        0x54 => KeyCode::NumpadDivide,
+        0x55 => KeyCode::NumpadMultiply,
+        0x56 => KeyCode::NumpadSubtract,
+        0x57 => KeyCode::NumpadAdd,
+        0x58 => KeyCode::NumpadEnter,
+        0x59..=0x61 => NUMPAD[(usage - 0x59) as usize],
+        0x62 => KeyCode::Numpad0,
+        0x63 => KeyCode::NumpadPeriod,
+        0x65 => KeyCode::Apps,
+        _ => return None,
+    };
+
+    Some(code)
+}
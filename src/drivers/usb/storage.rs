@@ -0,0 +1,363 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+
+use crate::{apprise, warning};
+use crate::drivers::usb::{Endpoint, Interface, Setup, Target, REQ_CLASS, REQ_INTERFACE};
+use crate::drivers::usb::uhci;
+use crate::kernel::{block, pit};
+use crate::kernel::block::BlockDevice;
+use crate::kernel::error::{Error, FaultKind};
+
+// Mass Storage (Bulk-Only Transport)
+//
+// USB flash drives and card readers implement the mass storage class with the bulk-only transport
+// (BOT), which carries SCSI commands over a pair of bulk endpoints. Every command takes three steps:
+// the host sends a 31-byte command block wrapper (CBW) holding the SCSI command block, data moves in
+// the direction the CBW announced, and the device returns a 13-byte command status wrapper (CSW)
+// reporting whether the command passed. A device stalls the data endpoint when it has less data
+// than announced, in which case the host clears the stall and reads the CSW all the same.
+//
+// A device that loses track of the protocol is brought back with a reset recovery: a class reset
+// request followed by clearing both bulk endpoints.
+//
+// Only logical unit 0 is used, and it is registered as a block device named `usb<controller>p<port>`.
+// The 10-byte READ and WRITE commands limit the addressable blocks to 2^32.
+//
+// OS Dev Wiki: https://wiki.osdev.org/USB_Mass_Storage_Class_Devices
+// Specification: https://www.usb.org/document-library/mass-storage-bulk-only-10
+
+////////////////
+// Attributes
+////////////////
+
+/// Name of the driver.
+pub(crate) const NAME: &str = "usb-storage";
+
+// Class, subclass and protocol of SCSI interfaces using the bulk-only transport.
+const CLASS_MASS_STORAGE: u8 = 0x08;
+const SUBCLASS_SCSI: u8 = 0x06;
+const PROTOCOL_BULK_ONLY: u8 = 0x50;
+
+/// Class request resetting the transport of the interface.
+const REQ_RESET: u8 = 0xFF;
+
+// Command block wrapper.
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CBW_SIZE: usize = 31;
+const CBW_DATA_IN: u8 = 0x80;
+
+// Command status wrapper.
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+const CSW_SIZE: usize = 13;
+const CSW_PASSED: u8 = 0x00;
+const CSW_FAILED: u8 = 0x01;
+
+/// Size of the standard inquiry data.
+const INQUIRY_SIZE: usize = 36;
+/// Size of the fixed-format sense data.
+const SENSE_SIZE: usize = 18;
+/// Size of the data returned by READ CAPACITY (10).
+const CAPACITY_SIZE: usize = 8;
+
+/// Largest number of bytes moved by a single command.
+const MAX_TRANSFER: usize = 0x10000;
+/// Largest supported block size.
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// Number of times the unit is asked whether it is ready before giving up.
+const READY_RETRIES: usize = 20;
+/// Time between checks of whether the unit is ready, in seconds.
+const READY_DELAY: f64 = 0.1;
+
+////////////////////
+/// SCSI Command
+////////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+enum ScsiCommand {
+    TestUnitReady = 0x00,
+    RequestSense = 0x03,
+    Inquiry = 0x12,
+    ReadCapacity10 = 0x25,
+    Read10 = 0x28,
+    Write10 = 0x2A,
+    SynchronizeCache10 = 0x35,
+}
+
+////////////
+/// Data
+////////////
+/// Data stage of a command.
+enum Data<'a> {
+    None,
+    In(&'a mut [u8]),
+    Out(&'a [u8]),
+}
+
+////////////
+/// Disk
+////////////
+struct Disk {
+    name: String,
+    target: Target,
+    interface: u8,
+    bulk_in: Endpoint,
+    bulk_out: Endpoint,
+    /// Data toggles of the bulk IN and OUT endpoints.
+    toggles: (bool, bool),
+    /// Tag of the last command, which its status echoes.
+    tag: u32,
+    block_size: usize,
+    block_count: u64,
+}
+
+impl Disk {
+    /// Brings up the unit and reads its capacity.
+    fn new(name: String, target: Target, interface: &Interface) -> Result<Self, Error> {
+        let bulk_in = interface.endpoints.iter().find(|endpoint| endpoint.is_bulk() && endpoint.is_in());
+        let bulk_out = interface.endpoints.iter().find(|endpoint| endpoint.is_bulk() && !endpoint.is_in());
+        let (bulk_in, bulk_out) = match (bulk_in, bulk_out) {
+            (Some(bulk_in), Some(bulk_out)) => (*bulk_in, *bulk_out),
+            _ => return Err(Error::NotFound),
+        };
+
+        let mut disk = Disk {
+            name,
+            target,
+            interface: interface.number,
+            bulk_in,
+            bulk_out,
+            toggles: (false, false),
+            tag: 0,
+            block_size: 0,
+            block_count: 0,
+        };
+
+        // Units usually need a moment after being configured, and report a unit attention condition
+        // that has to be read back before they accept commands.
+        let mut retries = READY_RETRIES;
+        while let Err(e) = disk.command(&[ScsiCommand::TestUnitReady as u8, 0, 0, 0, 0, 0], Data::None) {
+            retries -= 1;
+            if retries == 0 { return Err(e); }
+            disk.sense().ok();
+            pit::sleep(READY_DELAY);
+        }
+
+        let mut capacity = [0; CAPACITY_SIZE];
+        disk.command(&[ScsiCommand::ReadCapacity10 as u8, 0, 0, 0, 0, 0, 0, 0, 0, 0], Data::In(&mut capacity))?;
+        let last_lba = u32::from_be_bytes([capacity[0], capacity[1], capacity[2], capacity[3]]);
+        disk.block_size = u32::from_be_bytes([capacity[4], capacity[5], capacity[6], capacity[7]]) as usize;
+        disk.block_count = last_lba as u64 + 1;
+        if disk.block_size == 0 || disk.block_size > MAX_BLOCK_SIZE { return Err(Error::Unsupported); }
+
+        Ok(disk)
+    }
+
+    /// Returns the vendor and product identification of the unit.
+    fn model(&mut self) -> String {
+        let mut inquiry = [0; INQUIRY_SIZE];
+        let cb = [ScsiCommand::Inquiry as u8, 0, 0, 0, INQUIRY_SIZE as u8, 0];
+        if self.command(&cb, Data::In(&mut inquiry)).is_err() { return String::from("unknown"); }
+
+        let vendor = String::from_utf8_lossy(&inquiry[8..16]);
+        let product = String::from_utf8_lossy(&inquiry[16..32]);
+        format!("{} {}", vendor.trim(), product.trim())
+    }
+
+    /// Reads the sense data of the last failed command, which also clears it.
+    fn sense(&mut self) -> Result<(), Error> {
+        let mut sense = [0; SENSE_SIZE];
+        self.command(&[ScsiCommand::RequestSense as u8, 0, 0, 0, SENSE_SIZE as u8, 0], Data::In(&mut sense))
+    }
+
+    /// Runs a SCSI command through the three steps of the transport.
+    fn command(&mut self, cb: &[u8], data: Data) -> Result<(), Error> {
+        self.tag = self.tag.wrapping_add(1);
+        let (len, flags) = match &data {
+            Data::None => (0, 0),
+            Data::In(buf) => (buf.len(), CBW_DATA_IN),
+            Data::Out(buf) => (buf.len(), 0),
+        };
+
+        let mut cbw = [0; CBW_SIZE];
+        cbw[0..4].copy_from_slice(&CBW_SIGNATURE.to_le_bytes());
+        cbw[4..8].copy_from_slice(&self.tag.to_le_bytes());
+        cbw[8..12].copy_from_slice(&(len as u32).to_le_bytes());
+        cbw[12] = flags;
+        cbw[14] = cb.len() as u8;
+        cbw[15..15 + cb.len()].copy_from_slice(cb);
+
+        if let Err(e) = uhci::bulk_out(&self.target, &self.bulk_out, &mut self.toggles.1, &cbw) {
+            self.reset_recovery();
+            return Err(e);
+        }
+
+        // A stall ends the data stage early.
+        let res = match data {
+            Data::None => Ok(()),
+            Data::In(buf) => uhci::bulk_in(&self.target, &self.bulk_in, &mut self.toggles.0, buf).map(|_| ()),
+            Data::Out(buf) => uhci::bulk_out(&self.target, &self.bulk_out, &mut self.toggles.1, buf),
+        };
+        match res {
+            Ok(()) => {}
+            Err(Error::Unsupported) => {
+                let endpoint = if flags == CBW_DATA_IN { self.bulk_in } else { self.bulk_out };
+                self.clear_halt(&endpoint)?;
+            }
+            Err(e) => {
+                self.reset_recovery();
+                return Err(e);
+            }
+        }
+
+        // The status may be stalled once as well.
+        let mut csw = [0; CSW_SIZE];
+        let len = match uhci::bulk_in(&self.target, &self.bulk_in, &mut self.toggles.0, &mut csw) {
+            Err(Error::Unsupported) => {
+                let bulk_in = self.bulk_in;
+                self.clear_halt(&bulk_in)?;
+                uhci::bulk_in(&self.target, &self.bulk_in, &mut self.toggles.0, &mut csw)
+            }
+            res => res,
+        };
+
+        let valid = matches!(len, Ok(CSW_SIZE))
+            && u32::from_le_bytes([csw[0], csw[1], csw[2], csw[3]]) == CSW_SIGNATURE
+            && u32::from_le_bytes([csw[4], csw[5], csw[6], csw[7]]) == self.tag;
+        if !valid {
+            self.reset_recovery();
+            return Err(len.err().unwrap_or(Error::Hardware(FaultKind::InvalidResponse)));
+        }
+
+        match csw[12] {
+            CSW_PASSED => Ok(()),
+            CSW_FAILED => Err(Error::Failed),
+            // A phase error takes a reset recovery.
+            _ => {
+                self.reset_recovery();
+                Err(Error::Hardware(FaultKind::InvalidResponse))
+            }
+        }
+    }
+
+    /// Resumes a bulk endpoint that stalled.
+    fn clear_halt(&mut self, endpoint: &Endpoint) -> Result<(), Error> {
+        self.target.clear_halt(endpoint)?;
+        if endpoint.is_in() { self.toggles.0 = false; } else { self.toggles.1 = false; }
+
+        Ok(())
+    }
+
+    /// Resets the transport and both bulk endpoints.
+    fn reset_recovery(&mut self) {
+        let setup = Setup {
+            request_type: REQ_CLASS | REQ_INTERFACE,
+            request: REQ_RESET,
+            value: 0,
+            index: self.interface as u16,
+            length: 0,
+        };
+        let (bulk_in, bulk_out) = (self.bulk_in, self.bulk_out);
+        let res = self.target.control(setup, &mut [])
+            .and_then(|_| self.clear_halt(&bulk_in))
+            .and_then(|_| self.clear_halt(&bulk_out));
+        if let Err(e) = res { warning!("USB: {}: reset recovery failed: {}", self.name, e); }
+    }
+
+    /// Reads or writes whole blocks starting at `lba`, in as many commands as the transfer limit takes.
+    fn transfer(&mut self, lba: u64, mut data: Data) -> Result<(), Error> {
+        let len = match &data {
+            Data::None => 0,
+            Data::In(buf) => buf.len(),
+            Data::Out(buf) => buf.len(),
+        };
+        self.check_range(lba, len)?;
+
+        let step = MAX_TRANSFER / self.block_size * self.block_size;
+        for offset in (0..len).step_by(step) {
+            let bytes = (len - offset).min(step);
+            let blocks = (bytes / self.block_size) as u16;
+            let [lba0, lba1, lba2, lba3] = ((lba + (offset / self.block_size) as u64) as u32).to_be_bytes();
+            let [count0, count1] = blocks.to_be_bytes();
+
+            let (command, chunk) = match &mut data {
+                Data::None => return Ok(()),
+                Data::In(buf) => (ScsiCommand::Read10, Data::In(&mut buf[offset..offset + bytes])),
+                Data::Out(buf) => (ScsiCommand::Write10, Data::Out(&buf[offset..offset + bytes])),
+            };
+            self.command(&[command as u8, 0, lba0, lba1, lba2, lba3, 0, count0, count1, 0], chunk)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `len` bytes starting at `lba` are whole blocks within the disk.
+    fn check_range(&self, lba: u64, len: usize) -> Result<(), Error> {
+        let blocks = (len / self.block_size) as u64;
+        if len % self.block_size != 0 { return Err(Error::InvalidArgument); }
+        if lba.checked_add(blocks).map_or(true, |end| end > self.block_count) { return Err(Error::OutOfBounds); }
+
+        Ok(())
+    }
+}
+
+impl BlockDevice for Disk {
+    fn name(&self) -> &str { &self.name }
+
+    fn block_size(&self) -> usize { self.block_size }
+
+    fn block_count(&self) -> u64 { self.block_count }
+
+    fn read(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), Error> { self.transfer(lba, Data::In(buf)) }
+
+    fn write(&mut self, lba: u64, buf: &[u8]) -> Result<(), Error> { self.transfer(lba, Data::Out(buf)) }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        // Plenty of flash drives have no write cache and turn the command down.
+        match self.command(&[ScsiCommand::SynchronizeCache10 as u8, 0, 0, 0, 0, 0, 0, 0, 0, 0], Data::None) {
+            Err(Error::Failed) => Ok(()),
+            res => res,
+        }
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether the interface is a SCSI unit using the bulk-only transport.
+pub(crate) fn probe(interface: &Interface) -> bool {
+    (interface.class, interface.subclass, interface.protocol) == (CLASS_MASS_STORAGE, SUBCLASS_SCSI, PROTOCOL_BULK_ONLY)
+}
+
+/// Brings up the unit and registers it as a block device.
+pub(crate) fn attach(target: Target, interface: &Interface) -> Result<(), Error> {
+    let mut disk = Disk::new(format!("usb{}p{}", target.controller, target.port), target, interface)?;
+
+    let model = disk.model();
+    apprise!("USB: {}: {} ({} blocks of {} bytes)", disk.name, model, disk.block_count, disk.block_size);
+    block::register(Box::new(disk))
+}
@@ -0,0 +1,684 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::failure;
+use crate::api::usb::Speed;
+use crate::drivers::usb::{Endpoint, Setup, Target};
+use crate::kernel::{pci, pit, portio};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::memory::{dma, PAGE_SIZE};
+use crate::kernel::memory::dma::DmaBuffer;
+use crate::kernel::portio::Port;
+use crate::kernel::sync::Mutex;
+
+// Universal Host Controller Interface (UHCI)
+//
+// A UHCI controller is programmed through 32 I/O ports found in BAR4 of its PCI function, and walks
+// a frame list of 1024 entries in host memory, one per millisecond frame. Each entry points to a
+// chain of transfer descriptors (TDs), each describing a single packet, and queue heads (QHs), which
+// hold a queue of TDs the controller works through in order, retrying NAKed packets on later frames.
+//
+// Every frame list entry points to the same periodic head QH. Interrupt endpoints hang their own QH
+// off it, so they are polled on every frame, and the chain ends with the asynchronous QH through
+// which control transfers are run, one at a time, by pointing its element link to the first TD and
+// waiting for the controller to retire them. Data goes through a page-sized bounce buffer.
+//
+// The root hub has two ports, although some controllers implement more. A port is reset before the
+// device attached to it is enumerated, and reports whether the device is a low-speed one.
+//
+// OS Dev Wiki: https://wiki.osdev.org/Universal_Host_Controller_Interface
+// Specification: https://ftp.netbsd.org/pub/NetBSD/misc/blymn/uhci11d.pdf
+
+////////////////
+// Attributes
+////////////////
+
+/// PCI class, subclass and programming interface of UHCI controllers.
+const PCI_CLASS: (u8, u8, u8) = (0x0C, 0x03, 0x00);
+/// PCI configuration register controlling the legacy keyboard emulation of the firmware.
+const PCI_LEGACY_SUPPORT: u8 = 0xC0;
+/// Value turning the legacy emulation off and clearing its pending status.
+const LEGACY_DISABLE: u32 = 0x8F00;
+
+/// Number of I/O ports of the register block.
+const PORT_COUNT: u16 = 0x20;
+/// Highest number of root hub ports probed.
+const MAX_PORTS: u8 = 8;
+
+/// Number of entries of the frame list.
+const FRAME_COUNT: usize = 1024;
+
+/// Time the global reset is held, in seconds.
+const GLOBAL_RESET_DELAY: f64 = 0.05;
+/// Time a port reset is held, in seconds.
+const PORT_RESET_DELAY: f64 = 0.05;
+/// Time a device is given to recover from a port reset, in seconds.
+const PORT_RECOVERY_DELAY: f64 = 0.01;
+/// Time the controller is given to move off retired TDs, in seconds.
+const FRAME_DELAY: f64 = 0.001;
+/// Time allowed for the controller to reset, start or stop, in seconds.
+const CONTROLLER_TIMEOUT: f64 = 0.1;
+/// Time allowed for a control transfer to complete, in seconds.
+const CONTROL_TIMEOUT: f64 = 1.0;
+/// Time allowed for a bulk transfer to complete, in seconds.
+const BULK_TIMEOUT: f64 = 5.0;
+
+// Layout of the controller memory: the frame list fills the first page, the QHs and the pool of TDs
+// the second, and the bounce buffer the third.
+const FRAME_LIST_OFFSET: usize = 0x0000;
+const PERIODIC_QH_OFFSET: usize = 0x1000;
+const ASYNC_QH_OFFSET: usize = 0x1020;
+const TD_POOL_OFFSET: usize = 0x1040;
+const BUFFER_OFFSET: usize = 0x2000;
+const MEMORY_SIZE: usize = 3 * PAGE_SIZE;
+
+/// Size of a TD, including the words reserved for software.
+const TD_SIZE: usize = 32;
+/// Number of TDs in the pool.
+const TD_COUNT: usize = (BUFFER_OFFSET - TD_POOL_OFFSET) / TD_SIZE;
+/// Size of the bounce buffer.
+const BUFFER_SIZE: usize = PAGE_SIZE;
+/// Size of a setup packet, which precedes the data in the bounce buffer.
+const SETUP_SIZE: usize = 8;
+
+// Layout of the memory of an interrupt pipe.
+const PIPE_QH_OFFSET: usize = 0x00;
+const PIPE_TD_OFFSET: usize = 0x20;
+const PIPE_BUFFER_OFFSET: usize = 0x40;
+
+// Bits of the command register.
+const CMD_RUN: u16 = 1 << 0;
+const CMD_HOST_RESET: u16 = 1 << 1;
+const CMD_GLOBAL_RESET: u16 = 1 << 2;
+const CMD_CONFIGURED: u16 = 1 << 6;
+const CMD_MAX_PACKET_64: u16 = 1 << 7;
+
+// Bits of the status register.
+const STS_HALTED: u16 = 1 << 5;
+const STS_ALL: u16 = 0x3F;
+
+/// Default length of a frame, as the start-of-frame timing register holds it.
+const SOF_DEFAULT: u8 = 0x40;
+
+// Bits of a port status and control register.
+const PORT_CONNECTED: u16 = 1 << 0;
+const PORT_CONNECT_CHANGE: u16 = 1 << 1;
+const PORT_ENABLED: u16 = 1 << 2;
+const PORT_ENABLE_CHANGE: u16 = 1 << 3;
+const PORT_ALWAYS_SET: u16 = 1 << 7;
+const PORT_LOW_SPEED: u16 = 1 << 8;
+const PORT_RESET: u16 = 1 << 9;
+/// Bits cleared by writing ones to them.
+const PORT_CHANGES: u16 = PORT_CONNECT_CHANGE | PORT_ENABLE_CHANGE;
+
+// Bits of a link pointer.
+const LINK_TERMINATE: u32 = 1 << 0;
+const LINK_QH: u32 = 1 << 1;
+const LINK_DEPTH_FIRST: u32 = 1 << 2;
+
+// Bits of the status word of a TD.
+const TD_BITSTUFF: u32 = 1 << 17;
+const TD_CRC_TIMEOUT: u32 = 1 << 18;
+const TD_BABBLE: u32 = 1 << 20;
+const TD_BUFFER_ERROR: u32 = 1 << 21;
+const TD_STALLED: u32 = 1 << 22;
+const TD_ACTIVE: u32 = 1 << 23;
+const TD_LOW_SPEED: u32 = 1 << 26;
+const TD_ERROR_LIMIT: u32 = 3 << 27;
+const TD_SHORT_PACKET: u32 = 1 << 29;
+const TD_ERRORS: u32 = TD_BITSTUFF | TD_CRC_TIMEOUT | TD_BABBLE | TD_BUFFER_ERROR | TD_STALLED;
+
+// Packet identifiers.
+const PID_IN: u8 = 0x69;
+const PID_OUT: u8 = 0xE1;
+const PID_SETUP: u8 = 0x2D;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Started controllers.
+static CONTROLLERS: Mutex<Vec<Controller>> = Mutex::new(Vec::new());
+
+////////////////
+/// Register
+////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(u16)]
+enum Register {
+    Command = 0x00,
+    Status = 0x02,
+    InterruptEnable = 0x04,
+    FrameNumber = 0x06,
+    FrameBase = 0x08,
+    StartOfFrame = 0x0C,
+    /// Status and control of the first root hub port, followed by those of the others.
+    Port = 0x10,
+}
+
+//////////////////////////
+/// TransferDescriptor
+//////////////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct TransferDescriptor {
+    link: u32,
+    status: u32,
+    token: u32,
+    buffer: u32,
+}
+
+impl TransferDescriptor {
+    /// Creates an active descriptor moving a packet of up to `len` bytes between `buffer` and an endpoint.
+    fn new(target: &Target, endpoint: u8, pid: u8, toggle: bool, buffer: u32, len: usize) -> Self {
+        let mut status = TD_ACTIVE | TD_ERROR_LIMIT;
+        if target.speed == Speed::Low { status |= TD_LOW_SPEED; }
+        if pid == PID_IN { status |= TD_SHORT_PACKET; }
+
+        // The maximum length is encoded minus one, so that 0x7FF stands for a packet without data.
+        let max_len = (len as u32).wrapping_sub(1) & 0x7FF;
+        let token = pid as u32
+            | (target.address as u32) << 8
+            | (endpoint as u32 & 0xF) << 15
+            | (toggle as u32) << 19
+            | max_len << 21;
+
+        TransferDescriptor {
+            link: LINK_TERMINATE,
+            status,
+            token,
+            buffer,
+        }
+    }
+
+    /// Returns whether the descriptor reads from the device.
+    fn is_in(&self) -> bool { self.token & 0xFF == PID_IN as u32 }
+
+    /// Returns the number of bytes moved, once the controller has retired the descriptor.
+    fn actual_len(&self) -> usize { (self.status.wrapping_add(1) & 0x7FF) as usize }
+
+    /// Returns the number of bytes the descriptor asked for.
+    fn max_len(&self) -> usize { ((self.token >> 21).wrapping_add(1) & 0x7FF) as usize }
+}
+
+/////////////////
+/// QueueHead
+/////////////////
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct QueueHead {
+    link: u32,
+    element: u32,
+}
+
+//////////////////
+/// Controller
+//////////////////
+struct Controller {
+    /// First port of the register block.
+    base: u16,
+    /// Number of root hub ports.
+    ports: u8,
+    memory: DmaBuffer,
+}
+
+impl Controller {
+    /// Reads a 16-bit register.
+    fn read(&self, reg: Register) -> u16 {
+        let mut port = Port::<u16>::new(self.base + reg as u16);
+        unsafe { port.read() }
+    }
+
+    /// Writes a 16-bit register.
+    fn write(&self, reg: Register, value: u16) {
+        let mut port = Port::<u16>::new(self.base + reg as u16);
+        unsafe { port.write(value); }
+    }
+
+    /// Reads the status and control register of a root hub port.
+    fn read_port(&self, port: u8) -> u16 {
+        let mut io = Port::<u16>::new(self.base + Register::Port as u16 + port as u16 * 2);
+        unsafe { io.read() }
+    }
+
+    /// Writes the status and control register of a root hub port.
+    fn write_port(&self, port: u8, value: u16) {
+        let mut io = Port::<u16>::new(self.base + Register::Port as u16 + port as u16 * 2);
+        unsafe { io.write(value); }
+    }
+
+    /// Waits until the register masked with `mask` reads `value`.
+    fn wait(&self, reg: Register, mask: u16, value: u16, timeout: f64) -> Result<(), Error> {
+        let deadline = pit::uptime() + timeout;
+        while self.read(reg) & mask != value {
+            if pit::uptime() > deadline { return Err(Error::Hardware(FaultKind::Timeout)); }
+            spin_loop();
+        }
+
+        Ok(())
+    }
+
+    /// Returns a pointer to the memory at the given offset.
+    fn at<T>(&self, offset: usize) -> *mut T { (self.memory.virt_addr() + offset as u64).as_mut_ptr() }
+
+    /// Returns the physical address of the memory at the given offset.
+    fn phys(&self, offset: usize) -> u32 { (self.memory.phys_addr().as_u64() + offset as u64) as u32 }
+
+    /// Returns the offset of a TD of the pool.
+    fn td_offset(idx: usize) -> usize { TD_POOL_OFFSET + idx * TD_SIZE }
+
+    /// Reads a TD of the pool.
+    fn read_td(&self, idx: usize) -> TransferDescriptor {
+        unsafe { ptr::read_volatile(self.at(Self::td_offset(idx))) }
+    }
+
+    /// Writes a TD of the pool.
+    fn write_td(&self, idx: usize, td: TransferDescriptor) {
+        unsafe { ptr::write_volatile(self.at(Self::td_offset(idx)), td); }
+    }
+
+    /// Points the element link of the asynchronous QH to the given TD, or terminates it.
+    fn set_async_element(&self, td: Option<usize>) {
+        let element = td.map_or(LINK_TERMINATE, |idx| self.phys(Self::td_offset(idx)));
+        compiler_fence(Ordering::SeqCst);
+        unsafe { ptr::write_volatile(self.at::<u32>(ASYNC_QH_OFFSET + 4), element); }
+    }
+
+    /// Resets the controller, builds the schedule and starts it.
+    fn start(&self) -> Result<(), Error> {
+        self.write(Register::Command, CMD_GLOBAL_RESET);
+        pit::sleep(GLOBAL_RESET_DELAY);
+        self.write(Register::Command, 0);
+
+        self.write(Register::Command, CMD_HOST_RESET);
+        self.wait(Register::Command, CMD_HOST_RESET, 0, CONTROLLER_TIMEOUT)?;
+
+        // Completions are polled.
+        self.write(Register::InterruptEnable, 0);
+
+        let periodic = QueueHead { link: self.phys(ASYNC_QH_OFFSET) | LINK_QH, element: LINK_TERMINATE };
+        let asynchronous = QueueHead { link: LINK_TERMINATE, element: LINK_TERMINATE };
+        unsafe {
+            ptr::write_volatile(self.at(PERIODIC_QH_OFFSET), periodic);
+            ptr::write_volatile(self.at(ASYNC_QH_OFFSET), asynchronous);
+        }
+
+        let entry = self.phys(PERIODIC_QH_OFFSET) | LINK_QH;
+        for frame in 0..FRAME_COUNT {
+            unsafe { ptr::write_volatile(self.at::<u32>(FRAME_LIST_OFFSET + frame * 4), entry); }
+        }
+
+        self.write(Register::FrameNumber, 0);
+        let mut frame_base = Port::<u32>::new(self.base + Register::FrameBase as u16);
+        let mut start_of_frame = Port::<u8>::new(self.base + Register::StartOfFrame as u16);
+        unsafe {
+            frame_base.write(self.phys(FRAME_LIST_OFFSET));
+            start_of_frame.write(SOF_DEFAULT);
+        }
+
+        self.write(Register::Status, STS_ALL);
+        self.write(Register::Command, CMD_RUN | CMD_CONFIGURED | CMD_MAX_PACKET_64);
+        self.wait(Register::Status, STS_HALTED, 0, CONTROLLER_TIMEOUT)
+    }
+
+    /// Counts the root hub ports.
+    ///
+    /// Note: Bit 7 of a port register always reads one, which tells the ports from the registers past them.
+    fn count_ports(&self) -> u8 {
+        (0..MAX_PORTS)
+            .take_while(
+                |&port| {
+                    let reg = self.read_port(port);
+                    reg != 0xFFFF && reg & PORT_ALWAYS_SET != 0
+                }
+            )
+            .count() as u8
+    }
+
+    /// Resets a root hub port and returns the speed of the attached device, if any.
+    fn reset_port(&self, port: u8) -> Result<Option<Speed>, Error> {
+        if self.read_port(port) & PORT_CONNECTED == 0 { return Ok(None); }
+
+        self.write_port(port, PORT_RESET);
+        pit::sleep(PORT_RESET_DELAY);
+        self.write_port(port, 0);
+        pit::sleep(PORT_RECOVERY_DELAY);
+
+        // The port may need several attempts before it reports being enabled.
+        let deadline = pit::uptime() + CONTROLLER_TIMEOUT;
+        loop {
+            let reg = self.read_port(port);
+            if reg & PORT_CONNECTED == 0 { return Ok(None); }
+
+            self.write_port(port, PORT_ENABLED | PORT_CHANGES);
+            if reg & PORT_ENABLED != 0 {
+                let speed = if reg & PORT_LOW_SPEED != 0 { Speed::Low } else { Speed::Full };
+                return Ok(Some(speed));
+            }
+
+            if pit::uptime() > deadline { return Err(Error::Hardware(FaultKind::Timeout)); }
+            spin_loop();
+        }
+    }
+
+    /// Performs a control transfer, returning the number of bytes moved in the data stage.
+    fn control(&self, target: &Target, setup: &Setup, data: &mut [u8]) -> Result<usize, Error> {
+        let len = data.len().min(setup.length as usize);
+        let max_packet = target.max_packet.max(1) as usize;
+        // The setup and status stages take a TD each.
+        let count = (len + max_packet - 1) / max_packet + 2;
+        if SETUP_SIZE + len > BUFFER_SIZE || count > TD_COUNT { return Err(Error::InvalidArgument); }
+
+        let is_in = setup.is_in();
+        let buffer = self.at::<u8>(BUFFER_OFFSET);
+        unsafe {
+            ptr::copy_nonoverlapping(setup.to_bytes().as_ptr(), buffer, SETUP_SIZE);
+            if !is_in { ptr::copy_nonoverlapping(data.as_ptr(), buffer.add(SETUP_SIZE), len); }
+        }
+
+        let mut tds = Vec::with_capacity(count);
+        tds.push(TransferDescriptor::new(target, 0, PID_SETUP, false, self.phys(BUFFER_OFFSET), SETUP_SIZE));
+
+        // Data packets alternate their toggle, starting from DATA1.
+        let pid = if is_in { PID_IN } else { PID_OUT };
+        let mut toggle = true;
+        for offset in (0..len).step_by(max_packet) {
+            let chunk = (len - offset).min(max_packet);
+            let buffer = self.phys(BUFFER_OFFSET + SETUP_SIZE + offset);
+            tds.push(TransferDescriptor::new(target, 0, pid, toggle, buffer, chunk));
+            toggle = !toggle;
+        }
+
+        // The status stage goes the other way, and always uses DATA1.
+        let status_pid = if is_in && len > 0 { PID_OUT } else { PID_IN };
+        tds.push(TransferDescriptor::new(target, 0, status_pid, true, 0, 0));
+
+        for (idx, mut td) in tds.into_iter().enumerate() {
+            if idx + 1 < count { td.link = self.phys(Self::td_offset(idx + 1)) | LINK_DEPTH_FIRST; }
+            self.write_td(idx, td);
+        }
+
+        // A short packet ends the data stage early and leaves the queue halted on it, so the transfer
+        // resumes with the status stage.
+        if self.run(0, count, CONTROL_TIMEOUT)?.is_some() {
+            self.run(count - 1, count, CONTROL_TIMEOUT)?;
+        }
+
+        let moved = (1..count - 1).map(|idx| self.read_td(idx).actual_len()).sum::<usize>().min(len);
+        if is_in {
+            unsafe { ptr::copy_nonoverlapping(buffer.add(SETUP_SIZE), data.as_mut_ptr(), moved); }
+        }
+
+        Ok(moved)
+    }
+
+    /// Moves up to `len` bytes between the bounce buffer and a bulk endpoint, returning the number of bytes moved.
+    ///
+    /// Note: `toggle` holds the data toggle of the endpoint, and is updated with the packets moved.
+    fn bulk(&self, target: &Target, endpoint: &Endpoint, toggle: &mut bool, len: usize) -> Result<usize, Error> {
+        let max_packet = endpoint.max_packet.max(1) as usize;
+        let pid = if endpoint.is_in() { PID_IN } else { PID_OUT };
+
+        // A transfer without data still takes a zero-length packet.
+        let count = ((len + max_packet - 1) / max_packet).max(1);
+        if len > BUFFER_SIZE || count > TD_COUNT { return Err(Error::InvalidArgument); }
+
+        for idx in 0..count {
+            let offset = idx * max_packet;
+            let chunk = len.saturating_sub(offset).min(max_packet);
+            let buffer = if chunk > 0 { self.phys(BUFFER_OFFSET + offset) } else { 0 };
+            let mut td = TransferDescriptor::new(target, endpoint.number(), pid, *toggle ^ (idx % 2 == 1), buffer, chunk);
+            if idx + 1 < count { td.link = self.phys(Self::td_offset(idx + 1)) | LINK_DEPTH_FIRST; }
+            self.write_td(idx, td);
+        }
+
+        // A short packet ends the transfer.
+        let done = match self.run(0, count, BULK_TIMEOUT) {
+            Ok(short) => short.map_or(count, |idx| idx + 1),
+            Err(e) => {
+                // Packets that went through before the failure still count towards the toggle.
+                let retired = (0..count).take_while(|&idx| self.read_td(idx).status & (TD_ACTIVE | TD_ERRORS) == 0).count();
+                *toggle ^= retired % 2 == 1;
+                return Err(e);
+            }
+        };
+        *toggle ^= done % 2 == 1;
+
+        Ok((0..done).map(|idx| self.read_td(idx).actual_len()).sum::<usize>().min(len))
+    }
+
+    /// Runs the TDs of the pool from `first` up to `count` on the asynchronous QH until they are retired.
+    ///
+    /// Note: Returns the index of the TD that completed with a short packet, if any, in which case the
+    /// ones after it were left alone.
+    fn run(&self, first: usize, count: usize, timeout: f64) -> Result<Option<usize>, Error> {
+        self.set_async_element(Some(first));
+
+        let deadline = pit::uptime() + timeout;
+        let res = loop {
+            match self.check(first, count) {
+                Ok(Some(short)) => break Ok(short),
+                Ok(None) if pit::uptime() > deadline => break Err(Error::Hardware(FaultKind::Timeout)),
+                Ok(None) => spin_loop(),
+                Err(e) => break Err(e),
+            }
+        };
+
+        // Give the controller a frame to leave the TDs before they are reused.
+        self.set_async_element(None);
+        pit::sleep(FRAME_DELAY);
+
+        res
+    }
+
+    /// Checks the TDs of the pool from `first` up to `count`, returning `None` while they are still in flight.
+    fn check(&self, first: usize, count: usize) -> Result<Option<Option<usize>>, Error> {
+        for idx in first..count {
+            let td = self.read_td(idx);
+            // A stall is how a device turns down a request it does not support.
+            if td.status & TD_STALLED != 0 { return Err(Error::Unsupported); }
+            if td.status & TD_ERRORS != 0 { return Err(Error::Hardware(FaultKind::InvalidResponse)); }
+            if td.status & TD_ACTIVE != 0 { return Ok(None); }
+
+            if td.is_in() && idx + 1 < count && td.actual_len() < td.max_len() { return Ok(Some(Some(idx))); }
+        }
+
+        Ok(Some(None))
+    }
+}
+
+/////////////////////
+/// InterruptPipe
+/////////////////////
+/// An interrupt IN endpoint polled on every frame through the periodic schedule.
+pub(crate) struct InterruptPipe {
+    target: Target,
+    endpoint: u8,
+    max_packet: usize,
+    toggle: bool,
+    memory: DmaBuffer,
+}
+
+impl InterruptPipe {
+    /// Creates a pipe and links it into the periodic schedule of the controller of the device.
+    ///
+    /// Note: Pipes are never unlinked, so they must be kept for as long as the kernel runs.
+    pub(crate) fn open(target: &Target, endpoint: u8, max_packet: u16) -> Result<Self, Error> {
+        let memory = dma::alloc_coherent(PAGE_SIZE, PAGE_SIZE).ok_or(Error::OutOfMemory)?;
+        let pipe = InterruptPipe {
+            target: *target,
+            endpoint,
+            max_packet: (max_packet as usize).clamp(1, PAGE_SIZE - PIPE_BUFFER_OFFSET),
+            toggle: false,
+            memory,
+        };
+        pipe.arm();
+
+        let controllers = CONTROLLERS.lock();
+        let controller = controllers.get(target.controller).ok_or(Error::NotFound)?;
+        let head = controller.at::<u32>(PERIODIC_QH_OFFSET);
+        unsafe {
+            ptr::write_volatile(pipe.at::<u32>(PIPE_QH_OFFSET), ptr::read_volatile(head));
+            compiler_fence(Ordering::SeqCst);
+            ptr::write_volatile(head, pipe.phys(PIPE_QH_OFFSET) | LINK_QH);
+        }
+
+        Ok(pipe)
+    }
+
+    /// Copies the data of the last completed transfer into `buf` and polls the endpoint again.
+    ///
+    /// Note: Returns `None` while the device has nothing to report.
+    pub(crate) fn poll(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        let td: TransferDescriptor = unsafe { ptr::read_volatile(self.at(PIPE_TD_OFFSET)) };
+        if td.status & TD_ACTIVE != 0 { return Ok(None); }
+
+        if td.status & TD_ERRORS != 0 {
+            self.arm();
+            return Err(Error::Hardware(FaultKind::InvalidResponse));
+        }
+
+        let len = td.actual_len().min(buf.len());
+        unsafe { ptr::copy_nonoverlapping(self.at::<u8>(PIPE_BUFFER_OFFSET), buf.as_mut_ptr(), len); }
+
+        self.toggle = !self.toggle;
+        self.arm();
+
+        Ok(Some(len))
+    }
+
+    /// Activates the TD and queues it on the QH of the pipe.
+    fn arm(&self) {
+        let buffer = self.phys(PIPE_BUFFER_OFFSET);
+        let td = TransferDescriptor::new(&self.target, self.endpoint, PID_IN, self.toggle, buffer, self.max_packet);
+        unsafe {
+            ptr::write_volatile(self.at(PIPE_TD_OFFSET), td);
+            compiler_fence(Ordering::SeqCst);
+            ptr::write_volatile(self.at::<u32>(PIPE_QH_OFFSET + 4), self.phys(PIPE_TD_OFFSET));
+        }
+    }
+
+    /// Returns a pointer to the memory at the given offset.
+    fn at<T>(&self, offset: usize) -> *mut T { (self.memory.virt_addr() + offset as u64).as_mut_ptr() }
+
+    /// Returns the physical address of the memory at the given offset.
+    fn phys(&self, offset: usize) -> u32 { (self.memory.phys_addr().as_u64() + offset as u64) as u32 }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Starts all UHCI controllers, returning how many were started.
+pub(crate) fn init() -> Result<usize, Error> {
+    let (class, subclass, prog_if) = PCI_CLASS;
+    let functions = pci::find_by_class(class, subclass, prog_if);
+    if functions.is_empty() { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    for function in &functions {
+        match probe(function) {
+            Ok(controller) => CONTROLLERS.lock().push(controller),
+            Err(e) => failure!("UHCI: controller at {:02x}:{:02x}.{}: {}", function.bus, function.slot, function.func, e),
+        }
+    }
+
+    let count = CONTROLLERS.lock().len();
+    if count == 0 { return Err(Error::NotFound); }
+
+    Ok(count)
+}
+
+/// Takes the controller over from the firmware and starts it.
+fn probe(function: &pci::Function) -> Result<Controller, Error> {
+    let base = function.io_bar(4).ok_or(Error::Unsupported)?;
+    function.enable_io_bus_master();
+    function.write(PCI_LEGACY_SUPPORT, LEGACY_DISABLE);
+    portio::reserve("UHCI", base, PORT_COUNT).ok();
+
+    let memory = dma::alloc_coherent(MEMORY_SIZE, PAGE_SIZE).ok_or(Error::OutOfMemory)?;
+    let mut controller = Controller { base, ports: 0, memory };
+    controller.start()?;
+    controller.ports = controller.count_ports();
+
+    Ok(controller)
+}
+
+/// Returns the number of root hub ports of a controller.
+pub(crate) fn port_count(controller: usize) -> u8 {
+    CONTROLLERS.lock().get(controller).map_or(0, |controller| controller.ports)
+}
+
+/// Resets a root hub port of a controller and returns the speed of the attached device, if any.
+pub(crate) fn reset_port(controller: usize, port: u8) -> Result<Option<Speed>, Error> {
+    let controllers = CONTROLLERS.lock();
+    controllers.get(controller).ok_or(Error::NotFound)?.reset_port(port)
+}
+
+/// Performs a control transfer on endpoint 0 of the device, returning the number of bytes moved in the data stage.
+pub(crate) fn control(target: &Target, setup: &Setup, data: &mut [u8]) -> Result<usize, Error> {
+    let controllers = CONTROLLERS.lock();
+    controllers.get(target.controller).ok_or(Error::NotFound)?.control(target, setup, data)
+}
+
+/// Reads from a bulk IN endpoint of the device into `buf`, returning the number of bytes read.
+///
+/// Note: A short packet ends the transfer early, and a stall is reported as `Error::Unsupported`.
+pub(crate) fn bulk_in(target: &Target, endpoint: &Endpoint, toggle: &mut bool, buf: &mut [u8]) -> Result<usize, Error> {
+    let controllers = CONTROLLERS.lock();
+    let controller = controllers.get(target.controller).ok_or(Error::NotFound)?;
+
+    let mut read = 0;
+    for chunk in buf.chunks_mut(chunk_size(endpoint)) {
+        let len = controller.bulk(target, endpoint, toggle, chunk.len())?;
+        unsafe { ptr::copy_nonoverlapping(controller.at::<u8>(BUFFER_OFFSET), chunk.as_mut_ptr(), len); }
+        read += len;
+        if len < chunk.len() { break; }
+    }
+
+    Ok(read)
+}
+
+/// Writes `buf` to a bulk OUT endpoint of the device.
+///
+/// Note: A stall is reported as `Error::Unsupported`.
+pub(crate) fn bulk_out(target: &Target, endpoint: &Endpoint, toggle: &mut bool, buf: &[u8]) -> Result<(), Error> {
+    let controllers = CONTROLLERS.lock();
+    let controller = controllers.get(target.controller).ok_or(Error::NotFound)?;
+
+    for chunk in buf.chunks(chunk_size(endpoint)) {
+        unsafe { ptr::copy_nonoverlapping(chunk.as_ptr(), controller.at::<u8>(BUFFER_OFFSET), chunk.len()); }
+        controller.bulk(target, endpoint, toggle, chunk.len())?;
+    }
+
+    Ok(())
+}
+
+/// Returns the largest number of bytes moved through the bounce buffer at once, in whole packets.
+fn chunk_size(endpoint: &Endpoint) -> usize {
+    let max_packet = endpoint.max_packet.max(1) as usize;
+    (BUFFER_SIZE / max_packet).min(TD_COUNT) * max_packet
+}
@@ -20,17 +20,22 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use alloc::sync::Arc;
 use core::cmp::min;
 use core::fmt;
-use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use core::task::{Context, Poll, Waker};
 
+use conquer_once::spin::OnceCell;
+use crossbeam_queue::ArrayQueue;
 use lazy_static::lazy_static;
-use spin::Mutex;
 use volatile::Volatile;
 use vte::{Params, Parser};
 use vte::Perform;
 use x86_64::instructions;
-use x86_64::instructions::port::Port;
+use x86_64::PhysAddr;
 
 use crate::api::vga::{color, cursor};
 use crate::api::vga::clear;
@@ -38,8 +43,18 @@ use crate::api::vga::Color;
 use crate::api::vga::Default;
 use crate::api::vga::Font;
 use crate::api::vga::Palette;
+use crate::aux::earlyprintk;
 use crate::encodings::ASCII;
-use crate::encodings::Charset;
+use crate::drivers::framebuffer;
+use crate::encodings::{Charset, CP437};
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::{clipboard, memory, pit, portio};
+use crate::kernel::portio::Port;
+use crate::kernel::sync::{Mutex, MutexGuard};
+
+mod csi;
+pub(crate) mod width;
 
 // Video Graphics Array (VGA)
 //
@@ -56,6 +71,28 @@ use crate::encodings::Charset;
 // Format: BLINK(15 : 1) + BG(14..12 : 3) + FG(11..8 : 4) + ASCII(7..0 : 8) [ MSB(15)..LSB(0) ]
 //
 // Wikipedia: https://en.wikipedia.org/wiki/VGA_text_mode
+//
+// Without a text mode, e.g. when booted through UEFI, the same grid is kept in memory instead and
+// drawn onto the framebuffer, and the VGA registers are left alone.
+//
+// With asynchronous output enabled, `print!` only formats into a lock-free queue, and a task drains
+// the queue onto the screen a batch at a time. Interrupt handlers then never wait for the writer or
+// for escape sequences to be parsed. Everything else that touches the screen goes through `writer`,
+// which drains the queue first, so output stays in order; the panic path does the same when it can.
+//
+// With output tags enabled, each line a task starts is prefixed with the task's ID and name, in a
+// color picked by its ID, so that interleaved output can still be told apart. The executor sets the
+// output context of the writer around each poll. Tags are added as lines reach the screen, so output
+// that went through the queue is written without them.
+//
+// After a configurable stretch without input, the screen is blanked: in text mode the sequencer turns
+// the display off, and a framebuffer is painted black. Any key brings it back. A task keeps the time,
+// and only wakes up when the screen is due to be blanked, or when input or a new timeout arrive.
+//
+// Programs may reserve rectangular regions of the screen (see `api::vga::Screen`), which they draw
+// into on their own. The writer keeps a mask of the reserved cells and never writes over them: the
+// console keeps to the rows that are not reserved entirely, and scrolls and clears around the cells
+// of any region within them.
 
 ///////////////////////
 // Global Interfaces
@@ -75,6 +112,23 @@ lazy_static! {
     static ref PARSER: Mutex<Parser> = Mutex::new(Parser::new());
 }
 
+////////////
+// Device
+////////////
+
+/// Device descriptor of the VGA.
+pub(crate) static DEVICE: Device = Device {
+    name: "VGA",
+    class: Class::Display,
+    stage: Stage::Console,
+    critical: true,
+    depends: &[],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
 ////////////////////
 // Configurations
 ////////////////////
@@ -88,20 +142,68 @@ static CURSOR_ENABLED: AtomicBool = AtomicBool::new(Default::CURSOR_ENABLED);
 /// Cursor style.
 static CURSOR_STYLE: AtomicU8 = AtomicU8::new(Default::CURSOR_STYLE as u8);
 
+/// Asynchronous output enabled.
+static ASYNC_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Output tags enabled.
+static OUTPUT_TAGS: AtomicBool = AtomicBool::new(false);
+
+/// Minutes without input after which the screen is blanked, or zero to never blank it.
+static BLANK_TIMEOUT: AtomicUsize = AtomicUsize::new(0);
+
+////////////
+// States
+////////////
+
+/// Bytes printed but not yet written to the screen, when output is asynchronous.
+static OUTPUT_QUEUE: OnceCell<ArrayQueue<u8>> = OnceCell::uninit();
+
+/// Task waiting for queued output.
+static OUTPUT_WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+
+/// Tick of the latest input.
+static LAST_INPUT: AtomicUsize = AtomicUsize::new(0);
+
+/// Flag to check whether the screen is blanked or not.
+static IS_BLANKED: AtomicBool = AtomicBool::new(false);
+
+/// Number of inputs and timeout changes, which the blanking task waits on.
+static BLANK_EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Blanking task waiting for input or a timeout change.
+static BLANK_WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+
+/// Offset of the next character written by the emergency path, or `usize::MAX` if not yet known.
+static EMERGENCY_OFFSET: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Text grid drawn onto the framebuffer when there is no text mode.
+static mut SHADOW_BUFFER: [u16; TEXT_BUFFER_ROWS * TEXT_BUFFER_COLS] = [0; TEXT_BUFFER_ROWS * TEXT_BUFFER_COLS];
+
 ///////////////////////
 // Buffer Attributes
 ///////////////////////
 
 /// The VGA text buffer can be accessed via memory mapped at 0xB8000.
-const TEXT_BUFFER: isize = 0xB8000;
+pub(crate) const TEXT_BUFFER: u64 = 0xB8000;
 /// The VGA graphics buffer can be accessed via memory mapped at 0xA0000.
-const GRAPHICS_BUFFER: isize = 0xA0000;
+const GRAPHICS_BUFFER: u64 = 0xA0000;
 /// The VGA text buffer is typically 25 rows.
-const TEXT_BUFFER_ROWS: usize = 25;
+pub(crate) const TEXT_BUFFER_ROWS: usize = 25;
 /// The VGA text buffer is typically 80 columns.
-const TEXT_BUFFER_COLS: usize = 80;
+pub(crate) const TEXT_BUFFER_COLS: usize = 80;
 /// Coordinates of origin.
 const ORIGIN: (usize, usize) = (0, 0);
+/// Colors of output tags, picked by task ID.
+const TAG_COLORS: [Color; 6] = [Color::LightCyan, Color::LightGreen, Color::Yellow, Color::Pink, Color::LightBlue, Color::LightRed];
+
+//////////////////////
+// Queue Attributes
+//////////////////////
+
+/// Capacity of the output queue, in bytes.
+const OUTPUT_QUEUE_SIZE: usize = 16384;
+/// Most bytes written to the screen at a time by the output task.
+const OUTPUT_BATCH_SIZE: usize = 512;
 
 ////////////////
 /// Register
@@ -115,6 +217,8 @@ enum Register {
     AttrData = 0x3C1,
     /// Sequence Memory Mode Register.
     SequencerAddr = 0x3C4,
+    /// Sequencer Data Register.
+    SequencerData = 0x3C5,
     /// DAC Address Register.
     DACAddr = 0x3C8,
     /// DAC Data Register.
@@ -174,6 +278,28 @@ struct Buffer {
     chars: [[Volatile<ScreenChar>; TEXT_BUFFER_COLS]; TEXT_BUFFER_ROWS],
 }
 
+////////////
+/// Rect
+////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub row: usize,
+    pub col: usize,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+impl Rect {
+    /// Returns the row past the last one.
+    pub fn bottom(&self) -> usize { self.row.saturating_add(self.rows) }
+
+    /// Returns the column past the last one.
+    pub fn right(&self) -> usize { self.col.saturating_add(self.columns) }
+
+    /// Returns the columns covered, a bit per column.
+    fn column_mask(&self) -> u128 { ((1u128 << self.columns) - 1) << self.col }
+}
+
 //////////////
 /// Writer
 //////////////
@@ -181,6 +307,13 @@ pub(crate) struct Writer {
     row_pos: usize,
     col_pos: usize,
     color_code: ColorCode,
+    color_mapping: [(u8, u8, u8); 16],
+    saved_position: csi::Position,
+    /// ID and name of the task writing, if any.
+    context: Option<(u64, Arc<str>)>,
+    at_line_start: bool,
+    /// Cells reserved by regions, a bit per column.
+    reserved: [u128; TEXT_BUFFER_ROWS],
     buffer: &'static mut Buffer,
 }
 
@@ -191,7 +324,12 @@ impl Writer {
             row_pos: ORIGIN.0,
             col_pos: ORIGIN.1,
             color_code: ColorCode::new(Default::FOREGROUND, Default::BACKGROUND),
-            buffer: unsafe { &mut *(TEXT_BUFFER as *mut Buffer) },
+            color_mapping: Default::COLOR_MAPPING.colors,
+            saved_position: ORIGIN,
+            context: None,
+            at_line_start: true,
+            reserved: [0; TEXT_BUFFER_ROWS],
+            buffer: unsafe { &mut *(buffer_address() as *mut Buffer) },
         }
     }
 
@@ -201,12 +339,16 @@ impl Writer {
     /// Returns the columns in the VGA buffer.
     pub(crate) fn columns(&self) -> usize { TEXT_BUFFER_COLS }
 
+    /// Returns the size of the VGA buffer, as rows and columns.
+    fn size(&self) -> csi::Size { (self.rows(), self.columns()) }
+
     /// Returns the cursor's position.
     pub(crate) fn get_cursor_position(&self) -> (usize, usize) { (self.row_pos, self.col_pos) }
 
     /// Sets the cursor to the specified position.
     pub(crate) fn set_cursor_position(&mut self, row: usize, col: usize) {
-        self.row_pos = min(row, self.rows() - 1);
+        let (top, bottom) = self.console_rows();
+        self.row_pos = row.clamp(top, bottom);
         self.col_pos = min(col, self.columns() - 1);
         self.update_cursor();
     }
@@ -241,12 +383,12 @@ impl Writer {
     }
 
     /// Returns data at the specified position from the VGA buffer.
-    pub(crate) fn query_data_at(&self, row: usize, col: usize) -> Result<(u8, u8), ()> {
+    pub(crate) fn query_data_at(&self, row: usize, col: usize) -> Result<(u8, u8), Error> {
         if row < self.rows() && col < self.columns() {
             let screen_char = self.buffer.chars[row][col].read();
             Ok((screen_char.ascii_char, screen_char.color_code.as_u8()))
         } else {
-            Err(())
+            Err(Error::OutOfBounds)
         }
     }
 
@@ -254,6 +396,12 @@ impl Writer {
     pub(crate) fn set_palette(&mut self, palette: Palette) {
         const CONTRAST: u8 = 2;
 
+        if !is_text_mode() {
+            framebuffer::set_palette(palette);
+            self.update_cursor();
+            return;
+        }
+
         let vga_color = |color: u8| -> u8 { color >> CONTRAST };
 
         let mut addr = Port::<u8>::new(Register::DACAddr as u16);
@@ -270,11 +418,57 @@ impl Writer {
         }
     }
 
+    /// Sets the table that extended colors are mapped onto.
+    pub(crate) fn set_color_mapping(&mut self, palette: Palette) { self.color_mapping = palette.colors; }
+
+    /// Shows or hides the cursor on behalf of an escape sequence.
+    fn set_cursor_visible(&mut self, visible: bool) {
+        if is_text_mode() {
+            if visible { enable_cursor(); } else { disable_cursor(); }
+        } else {
+            // Redrawing would need the writer, which is held; the next cursor update draws it instead.
+            CURSOR_ENABLED.store(visible, Ordering::SeqCst);
+        }
+    }
+
+    /// Sets the task whose output follows, for output tags.
+    pub(crate) fn set_context(&mut self, context: Option<(u64, Arc<str>)>) { self.context = context; }
+
+    /// Writes the tag of the current task, if there is one.
+    fn write_tag(&mut self) {
+        use fmt::Write;
+
+        let (id, name) = match &self.context {
+            Some((id, name)) => (*id, name.clone()),
+            None => return,
+        };
+        let color_code = self.color_code;
+        self.color_code.set_foreground(TAG_COLORS[id as usize % TAG_COLORS.len()]);
+        write!(TagWriter(self), "[{}:{}]", id, name).ok();
+        self.color_code = color_code;
+        self.put_byte(ASCII::<u8>::SP);
+    }
+
+    /// Returns the color used to display the given extended color.
+    fn map_color(&self, color: csi::ExtendedColor) -> Color {
+        match color {
+            csi::ExtendedColor::Indexed(idx) if idx < 8 => Color::from_ansi(30 + idx).unwrap(),
+            csi::ExtendedColor::Indexed(idx) if idx < 16 => Color::from_ansi(90 + idx - 8).unwrap(),
+            color => Color::nearest(color.rgb(), &self.color_mapping),
+        }
+    }
+
     /// Sets the VGA font.
     pub(crate) fn set_font(&mut self, font: &Font) {
-        const BUFFER: *mut u8 = GRAPHICS_BUFFER as *mut u8;
         const CHAR_BYTE_BOUNDARY: u8 = 32;
 
+        if !is_text_mode() {
+            framebuffer::set_font(font);
+            self.update_cursor();
+            return;
+        }
+
+        let buffer = memory::phys_to_virt_addr(PhysAddr::new(GRAPHICS_BUFFER)).as_mut_ptr::<u8>();
         let mut sequencer = Port::<u16>::new(Register::SequencerAddr as u16);
         let mut graphics = Port::<u16>::new(Register::GraphicsAddr as u16);
 
@@ -291,7 +485,7 @@ impl Writer {
                 for j in 0..font.height as usize {
                     let vga_offset = j + i * CHAR_BYTE_BOUNDARY as usize;
                     let fnt_offset = j + i * font.height as usize;
-                    BUFFER.add(vga_offset).write_volatile(font.data[fnt_offset]);
+                    buffer.add(vga_offset).write_volatile(font.data[fnt_offset]);
                 }
             }
 
@@ -307,6 +501,13 @@ impl Writer {
 
     /// Updates the cursor position.
     fn update_cursor(&mut self) {
+        if !is_text_mode() {
+            let offset = (self.row_pos * self.columns()) + self.col_pos.min(self.columns() - 1);
+            let cursor = is_cursor_enabled().then(|| (offset, get_cursor_style().scanline_bounds()));
+            framebuffer::refresh(self.cells(), cursor);
+            return;
+        }
+
         let mut car = Port::<u16>::new(Register::CRTControlAddr as u16);
         let mut cdr = Port::<u16>::new(Register::CRTControlData as u16);
 
@@ -319,11 +520,17 @@ impl Writer {
         };
     }
 
+    /// Returns the cells of the buffer, each a character in the low byte and its attribute in the high.
+    fn cells(&self) -> &[u16] {
+        unsafe { core::slice::from_raw_parts(self.buffer as *const Buffer as *const u16, TEXT_BUFFER_ROWS * TEXT_BUFFER_COLS) }
+    }
+
     /// Writes the given byte to the VGA buffer.
     fn write_byte(&mut self, byte: u8) {
         match byte {
             ASCII::<u8>::LF => {
                 self.linefeed();
+                self.at_line_start = true;
             }
             ASCII::<u8>::BS => {
                 self.backspace();
@@ -337,35 +544,53 @@ impl Writer {
             ASCII::<u8>::FF => {
                 self.form_feed();
             }
-            byte => {
-                if self.col_pos >= self.columns() { self.linefeed(); }
-                let row = self.row_pos;
-                let col = self.col_pos;
-                let color_code = self.color_code;
-                let data = ScreenChar {
-                    ascii_char: byte,
-                    color_code,
-                };
-                self.buffer.chars[row][col].write(data);
-                self.col_pos += 1;
-            }
+            byte => self.write_printable(byte),
         }
     }
 
+    /// Puts the code at the cursor as a printable character, even if it is a control character.
+    fn write_printable(&mut self, byte: u8) {
+        if self.at_line_start {
+            self.at_line_start = false;
+            if is_output_tags_enabled() { self.write_tag(); }
+        }
+        self.put_byte(byte);
+    }
+
+    /// Puts the given character at the cursor, wrapping onto the next line if needed.
+    fn put_byte(&mut self, byte: u8) {
+        if self.col_pos >= self.columns() { self.linefeed(); }
+        let row = self.row_pos;
+        let col = self.col_pos;
+        let color_code = self.color_code;
+        let data = ScreenChar {
+            ascii_char: byte,
+            color_code,
+        };
+        self.put_char(row, col, data);
+        self.col_pos += 1;
+    }
+
     /// Uni-directionally scrolls the view.
     fn scroll_view(&mut self) {
-        for row in 1..self.rows() {
+        let (top, bottom) = self.console_rows();
+        let blank = ScreenChar {
+            ascii_char: ASCII::<u8>::SP,
+            color_code: self.color_code,
+        };
+        for row in (top + 1)..=bottom {
             for col in 0..self.columns() {
-                let ch = self.buffer.chars[row][col].read();
-                self.buffer.chars[row - 1][col].write(ch);
+                // Cells of regions neither move nor get overwritten.
+                let ch = if self.is_reserved(row, col) { blank } else { self.buffer.chars[row][col].read() };
+                self.put_char(row - 1, col, ch);
             }
         }
-        self.clear_row(self.rows() - 1);
+        self.clear_row(bottom);
     }
 
     /// Outputs a new line.
     fn linefeed(&mut self) {
-        if self.row_pos < (self.rows() - 1) {
+        if self.row_pos < self.console_rows().1 {
             self.row_pos += 1;
         } else {
             self.scroll_view();
@@ -381,8 +606,29 @@ impl Writer {
                 color_code: self.color_code,
             };
             self.col_pos -= 1;
-            self.buffer.chars[self.row_pos][self.col_pos].write(blank);
+            self.put_char(self.row_pos, self.col_pos, blank);
+        }
+    }
+
+    /// Blanks the given number of cells before the cursor and moves it back, onto the previous rows if need be.
+    ///
+    /// Note: Unlike a backspace, this undoes wrapping, so it can take back anything that was drawn on the console.
+    pub(crate) fn erase_back(&mut self, cells: usize) {
+        let columns = self.columns();
+        let (top, _) = self.console_rows();
+        let blank = ScreenChar {
+            ascii_char: ASCII::<u8>::SP,
+            color_code: self.color_code,
+        };
+
+        let end = self.row_pos * columns + self.col_pos;
+        let start = end.saturating_sub(cells).max(top * columns);
+        for offset in start..end {
+            self.put_char(offset / columns, offset % columns, blank);
         }
+        self.row_pos = start / columns;
+        self.col_pos = start % columns;
+        self.update_cursor();
     }
 
     /// Outputs a tab.
@@ -408,7 +654,7 @@ impl Writer {
             color_code: self.color_code,
         };
         for col in begin..self.columns() {
-            self.buffer.chars[row][col].write(blank);
+            self.put_char(row, col, blank);
         }
     }
 
@@ -419,7 +665,7 @@ impl Writer {
             color_code: self.color_code,
         };
         for col in 0..end {
-            self.buffer.chars[row][col].write(blank);
+            self.put_char(row, col, blank);
         }
     }
 
@@ -437,33 +683,128 @@ impl Writer {
     pub(crate) fn clear(&mut self) {
         self.idle_clear();
         self.set_cursor_position(ORIGIN.0, ORIGIN.1);
+        self.at_line_start = true;
+    }
+
+    /// Writes the character at the given position, unless the cell is reserved by a region.
+    fn put_char(&mut self, row: usize, col: usize, ch: ScreenChar) {
+        if !self.is_reserved(row, col) { self.buffer.chars[row][col].write(ch); }
+    }
+
+    /// Returns whether the cell is reserved by a region.
+    fn is_reserved(&self, row: usize, col: usize) -> bool { self.reserved[row] & (1 << col) != 0 }
+
+    /// Returns the first and the last row of the console, i.e. the span between the rows reserved entirely.
+    fn console_rows(&self) -> (usize, usize) {
+        let full = (1u128 << self.columns()) - 1;
+        let top = self.reserved.iter().position(|mask| *mask != full).unwrap_or(0);
+        let bottom = self.reserved.iter().rposition(|mask| *mask != full).unwrap_or(self.rows() - 1);
+        (top, bottom.max(top))
+    }
+
+    /// Reserves the cells of the rectangle for a region.
+    pub(crate) fn reserve(&mut self, rect: Rect) -> Result<(), Error> {
+        if rect.rows == 0 || rect.columns == 0 || rect.bottom() > self.rows() || rect.right() > self.columns() {
+            return Err(Error::OutOfBounds);
+        }
+        let mask = rect.column_mask();
+        if self.reserved[rect.row..rect.bottom()].iter().any(|reserved| reserved & mask != 0) { return Err(Error::Busy); }
+        // The console keeps at least a row.
+        let full = (1u128 << self.columns()) - 1;
+        let mut reserved = self.reserved;
+        reserved[rect.row..rect.bottom()].iter_mut().for_each(|reserved| *reserved |= mask);
+        if reserved.iter().all(|mask| *mask == full) { return Err(Error::OutOfResources); }
+
+        self.reserved = reserved;
+        let (top, bottom) = self.console_rows();
+        if !(top..=bottom).contains(&self.row_pos) { self.set_cursor_position(self.row_pos, self.col_pos); }
+
+        Ok(())
+    }
+
+    /// Hands the cells of the rectangle back to the console, blanked.
+    pub(crate) fn release(&mut self, rect: Rect) {
+        let mask = rect.column_mask();
+        let blank = ScreenChar {
+            ascii_char: ASCII::<u8>::SP,
+            color_code: ColorCode::new(Default::FOREGROUND, Default::BACKGROUND),
+        };
+        for row in rect.row..rect.bottom().min(self.rows()) {
+            self.reserved[row] &= !mask;
+            for col in rect.col..rect.right().min(self.columns()) {
+                self.buffer.chars[row][col].write(blank);
+            }
+        }
+        self.update_cursor();
+    }
+
+    /// Writes a character into a region at the given position of the screen.
+    pub(crate) fn put_region_char(&mut self, row: usize, col: usize, byte: u8, fg: Color, bg: Color) {
+        let data = ScreenChar {
+            ascii_char: byte,
+            color_code: ColorCode::new(fg, bg),
+        };
+        self.buffer.chars[row][col].write(data);
+    }
+
+    /// Scrolls the rectangle of a region up by a row, blanking the last one with the given colors.
+    pub(crate) fn scroll_region(&mut self, rect: Rect, fg: Color, bg: Color) {
+        for row in (rect.row + 1)..rect.bottom() {
+            for col in rect.col..rect.right() {
+                let ch = self.buffer.chars[row][col].read();
+                self.buffer.chars[row - 1][col].write(ch);
+            }
+        }
+        for col in rect.col..rect.right() {
+            self.put_region_char(rect.bottom() - 1, col, ASCII::<u8>::SP, fg, bg);
+        }
     }
+
+    /// Makes the changes to regions visible.
+    pub(crate) fn refresh(&mut self) { self.update_cursor(); }
 }
 
 impl Perform for Writer {
     fn print(&mut self, c: char) {
-        self.write_byte(c as u8);
+        self.write_printable(CP437::encode_lossy(c));
     }
 
     fn execute(&mut self, byte: u8) {
         self.write_byte(byte);
     }
 
-    fn csi_dispatch(&mut self, params: &Params, _: &[u8], _: bool, c: char) {
+    fn osc_dispatch(&mut self, params: &[&[u8]], _: bool) {
+        // Note: The parser keeps at most a kilobyte of a sequence, which limits what can be copied this way.
+        if let [clipboard::OSC, rest @ ..] = params { clipboard::handle_osc(rest); }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _: bool, c: char) {
         // Reference: https://en.wikipedia.org/wiki/ANSI_escape_code
         //
-        // Note: 0 has been used as the default value instead of 1.
+        // Only the first value of each parameter is used, except by extended colors.
+        let mut values = [0u16; csi::MAX_PARAMS];
+        let mut len = 0;
+        for param in params.iter().take(csi::MAX_PARAMS) {
+            values[len] = param[0];
+            len += 1;
+        }
+        let args = &values[..len];
+
         match c {
             'm' => {
                 const RESET: u16 = 0;
 
                 const FG_D_BEGIN: u16 = 30;
                 const FG_D_END: u16 = 37;
+                const FG_EXTENDED: u16 = 38;
+                const FG_DEFAULT: u16 = 39;
                 const FG_B_BEGIN: u16 = 90;
                 const FG_B_END: u16 = 97;
 
                 const BG_D_BEGIN: u16 = 40;
                 const BG_D_END: u16 = 47;
+                const BG_EXTENDED: u16 = 48;
+                const BG_DEFAULT: u16 = 49;
                 const BG_B_BEGIN: u16 = 100;
                 const BG_B_END: u16 = 107;
 
@@ -471,7 +812,12 @@ impl Perform for Writer {
 
                 let mut fg = Default::FOREGROUND;
                 let mut bg = Default::BACKGROUND;
-                for param in params.iter() {
+                let mut skip = 0;
+                for (i, param) in params.iter().take(csi::MAX_PARAMS).enumerate() {
+                    if skip > 0 {
+                        skip -= 1;
+                        continue;
+                    }
                     match param[0] {
                         RESET => {
                             fg = Default::FOREGROUND;
@@ -483,94 +829,94 @@ impl Perform for Writer {
                         BG_D_BEGIN..=BG_D_END | BG_B_BEGIN..=BG_B_END => {
                             bg = Color::from_ansi((param[0] as u8) - FG_BG_DIFF).unwrap();
                         }
+                        FG_DEFAULT => fg = Default::FOREGROUND,
+                        BG_DEFAULT => bg = Default::BACKGROUND,
+                        FG_EXTENDED | BG_EXTENDED => {
+                            // Either `38:5:n`, with sub-parameters, or `38;5;n`, spread over the following parameters.
+                            let color = if param.len() > 1 {
+                                csi::ExtendedColor::parse(&param[1..]).map(|(color, _)| color)
+                            } else {
+                                csi::ExtendedColor::parse(&args[(i + 1)..]).map(|(color, used)| {
+                                    skip = used;
+                                    color
+                                })
+                            };
+                            if let Some(color) = color {
+                                let color = self.map_color(color);
+                                if param[0] == FG_EXTENDED { fg = color; } else { bg = color; }
+                            }
+                        }
                         _ => {}
                     }
                 }
                 self.set_color_code(fg, bg);
             }
             'A' => {
-                let mut n = 0;
-                for param in params.iter() {
-                    n = param[0] as usize;
-                }
-                self.row_pos -= min(self.row_pos, n);
+                let (r, c) = csi::cursor_up(self.get_cursor_position(), self.size(), args);
+                self.set_cursor_position(r, c);
             }
             'B' => {
-                let mut n = 0;
-                for param in params.iter() {
-                    n = param[0] as usize;
-                }
-                self.row_pos = min(self.row_pos + n, self.rows() - 1);
+                let (r, c) = csi::cursor_down(self.get_cursor_position(), self.size(), args);
+                self.set_cursor_position(r, c);
             }
             'C' => {
-                let mut n = 0;
-                for param in params.iter() {
-                    n = param[0] as usize;
-                }
-                self.col_pos = min(self.col_pos + n, self.columns() - 1);
+                let (r, c) = csi::cursor_forward(self.get_cursor_position(), self.size(), args);
+                self.set_cursor_position(r, c);
             }
             'D' => {
-                let mut n = 0;
-                for param in params.iter() {
-                    n = param[0] as usize;
-                }
-                self.col_pos -= min(self.col_pos, n);
+                let (r, c) = csi::cursor_back(self.get_cursor_position(), self.size(), args);
+                self.set_cursor_position(r, c);
             }
             'G' => {
-                let mut c = 0;
-                for param in params.iter() {
-                    c = param[0] as usize;
-                }
-                self.col_pos = min(self.columns(), c);
+                let (r, c) = csi::cursor_horizontal_absolute(self.get_cursor_position(), self.size(), args);
+                self.set_cursor_position(r, c);
             }
             'H' => {
-                let (mut r, mut c) = (0, 0);
-                for (i, param) in params.iter().enumerate() {
-                    match i {
-                        0 => r = param[0] as usize,
-                        1 => c = param[0] as usize,
-                        _ => break,
-                    };
-                }
-                (self.row_pos, self.col_pos) = (min(self.rows(), r), min(self.columns(), c));
+                let (r, c) = csi::cursor_position(self.size(), args);
+                self.set_cursor_position(r, c);
             }
             'J' => {
-                let mut n = 0;
-                for param in params.iter() {
-                    n = param[0] as usize;
-                }
-                match n {
-                    0 => {
-                        self.clear_row_right(self.row_pos, self.col_pos);
-                        for r in (self.row_pos + 1)..self.rows() {
+                let (r, c) = csi::clamp(self.get_cursor_position(), self.size());
+                match csi::Erase::from_params(args) {
+                    Some(csi::Erase::ToEnd) => {
+                        self.clear_row_right(r, c);
+                        for r in (r + 1)..self.rows() {
                             self.clear_row(r);
                         }
                     }
-                    1 => {
-                        self.clear_row_left(self.row_pos, self.col_pos);
-                        for r in 0..self.row_pos {
+                    Some(csi::Erase::ToBeginning) => {
+                        self.clear_row_left(r, c + 1);
+                        for r in 0..r {
                             self.clear_row(r);
                         }
                     }
-                    2 => {
+                    Some(csi::Erase::All) => {
                         self.idle_clear();
                     }
-                    _ => {}
+                    None => {}
                 }
             }
             'K' => {
-                let (r, c) = self.get_cursor_position();
-                let mut n = 0;
-                for param in params.iter() {
-                    n = param[0] as usize;
-                }
-                match n {
-                    0 => self.clear_row_right(r, c),
-                    1 => self.clear_row_left(r, c),
-                    2 => self.clear_row(r),
-                    _ => return,
+                let (r, c) = csi::clamp(self.get_cursor_position(), self.size());
+                match csi::Erase::from_params(args) {
+                    Some(csi::Erase::ToEnd) => self.clear_row_right(r, c),
+                    Some(csi::Erase::ToBeginning) => self.clear_row_left(r, c + 1),
+                    Some(csi::Erase::All) => self.clear_row(r),
+                    None => {}
                 }
             }
+            'h' | 'l' if intermediates == [csi::PRIVATE] => {
+                const DECTCEM: u16 = 25;
+
+                if args.contains(&DECTCEM) { self.set_cursor_visible(c == 'h'); }
+            }
+            's' if intermediates.is_empty() => {
+                self.saved_position = self.get_cursor_position();
+            }
+            'u' if intermediates.is_empty() => {
+                let (r, c) = self.saved_position;
+                self.set_cursor_position(r, c);
+            }
             _ => {}
         }
     }
@@ -636,6 +982,12 @@ pub(crate) fn enable_cursor() {
     const REG_CURSOR_START: u8 = 0x0A;
     const REG_CURSOR_END: u8 = 0x0B;
 
+    if !is_text_mode() {
+        CURSOR_ENABLED.store(true, Ordering::SeqCst);
+        redraw();
+        return;
+    }
+
     let mut addr = Port::<u8>::new(Register::CRTControlAddr as u16);
     let mut data = Port::<u8>::new(Register::CRTControlData as u16);
 
@@ -658,6 +1010,12 @@ pub(crate) fn enable_cursor() {
 
 /// Disables the cursor.
 pub(crate) fn disable_cursor() {
+    if !is_text_mode() {
+        CURSOR_ENABLED.store(false, Ordering::SeqCst);
+        redraw();
+        return;
+    }
+
     let mut addr = Port::<u8>::new(Register::CRTControlAddr as u16);
     let mut data = Port::<u8>::new(Register::CRTControlData as u16);
 
@@ -698,6 +1056,8 @@ pub(crate) fn reset_cursor_style() { CURSOR_STYLE.store(Default::CURSOR_STYLE.as
 pub(crate) fn set_underline_location(location: u8) {
     const REG_UNDERLINE_LOC: u8 = 0x14;
 
+    if !is_text_mode() { return; }
+
     instructions::interrupts::without_interrupts(
         || {
             let mut addr = Port::<u8>::new(Register::CRTControlAddr as u16);
@@ -716,7 +1076,14 @@ pub(crate) fn set_underline_location(location: u8) {
 ///////////////
 
 /// Initializes the VGA.
-pub(crate) fn init() -> Result<(), ()> {
+pub(crate) fn init() -> Result<(), Error> {
+    if !is_text_mode() { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    // Reserve the VGA registers.
+    const FIRST_REG: u16 = Register::AttrAddr as u16;
+    const LAST_REG: u16 = Register::InputStatus as u16;
+    portio::reserve("VGA", FIRST_REG, LAST_REG - FIRST_REG + 1).ok();
+
     // Map VGA color palette registers.
     for color in color::COLORS.iter() {
         set_attr_ctrl_reg(*color as u8, color.associated_vga_register());
@@ -737,21 +1104,394 @@ pub(crate) fn init() -> Result<(), ()> {
         enable_cursor();
     }
 
-    // Clear the screen.
-    clear();
+    // Clear the screen, unless it holds the messages of the early console.
+    if !earlyprintk::has_screen_output() {
+        clear();
+    }
 
     Ok(())
 }
 
+/// Returns whether the VGA text mode is available, i.e. the bootloader did not set up a framebuffer.
+pub(crate) fn is_text_mode() -> bool { !framebuffer::is_present() }
+
+/// Returns the address of the buffer written to, be it the VGA text buffer or the framebuffer's grid.
+fn buffer_address() -> usize {
+    if is_text_mode() { memory::phys_to_virt_addr(PhysAddr::new(TEXT_BUFFER)).as_u64() as usize } else { unsafe { core::ptr::addr_of_mut!(SHADOW_BUFFER) as usize } }
+}
+
+/// Redraws the framebuffer after a change outside the writer, e.g. to the cursor.
+pub(crate) fn redraw() {
+    instructions::interrupts::without_interrupts(
+        || { writer().update_cursor(); }
+    );
+}
+
+/// Locks the writer, after writing out any queued output so that it stays in order.
+#[track_caller]
+pub(crate) fn writer() -> MutexGuard<'static, Writer> {
+    let mut writer = WRITER.lock();
+    drain_output(&mut writer, usize::MAX);
+    writer
+}
+
+/// Writes at most `limit` bytes of queued output to the screen.
+fn drain_output(writer: &mut Writer, limit: usize) {
+    let queue = match OUTPUT_QUEUE.get() {
+        Some(queue) if !queue.is_empty() => queue,
+        _ => return,
+    };
+
+    // The queued bytes were not necessarily printed by the current task.
+    let context = writer.context.take();
+    let mut parser = PARSER.lock();
+    for _ in 0..limit {
+        match queue.pop() {
+            Ok(byte) => parser.advance(writer, byte),
+            Err(_) => break,
+        }
+    }
+    writer.context = context;
+    writer.update_cursor();
+}
+
+/// Returns whether printing only queues the output.
+pub(crate) fn is_async_output_enabled() -> bool { ASYNC_OUTPUT.load(Ordering::SeqCst) }
+
+/// Makes printing queue the output, to be written to the screen by `output_task`.
+pub(crate) fn enable_async_output() {
+    OUTPUT_QUEUE.get_or_init(|| ArrayQueue::new(OUTPUT_QUEUE_SIZE));
+    ASYNC_OUTPUT.store(true, Ordering::SeqCst);
+}
+
+/// Makes printing write to the screen directly, writing out whatever has been queued.
+pub(crate) fn disable_async_output() {
+    ASYNC_OUTPUT.store(false, Ordering::SeqCst);
+    instructions::interrupts::without_interrupts(
+        || { writer(); }
+    );
+}
+
+/// Returns whether lines are tagged with the task that writes them.
+pub(crate) fn is_output_tags_enabled() -> bool { OUTPUT_TAGS.load(Ordering::SeqCst) }
+
+/// Tags lines with the task that writes them.
+pub(crate) fn enable_output_tags() { OUTPUT_TAGS.store(true, Ordering::SeqCst); }
+
+/// Stops tagging lines with the task that writes them.
+pub(crate) fn disable_output_tags() { OUTPUT_TAGS.store(false, Ordering::SeqCst); }
+
+/// Returns the number of columns taken up by the tag that the writer would put at the start of a line
+/// right now.
+pub(crate) fn tag_width() -> usize {
+    use fmt::Write;
+
+    if !is_output_tags_enabled() || is_async_output_enabled() { return 0; }
+
+    // Whoever holds the writer is in the middle of printing, so the tag is left out rather than waited for.
+    instructions::interrupts::without_interrupts(
+        || {
+            let writer = match WRITER.try_lock() {
+                Some(writer) => writer,
+                None => return 0,
+            };
+            let mut width = width::Width::new();
+            match &writer.context {
+                Some((id, name)) => write!(width, "[{}:{}] ", id, name).map_or(0, |_| width.columns()),
+                None => 0,
+            }
+        }
+    )
+}
+
+/// Sets the task whose output follows, as its ID and name, or `None` outside of tasks.
+pub(crate) fn set_output_context(context: Option<(u64, Arc<str>)>) {
+    instructions::interrupts::without_interrupts(
+        || { WRITER.lock().set_context(context); }
+    );
+}
+
+/// Writes queued output to the screen whenever there is any.
+pub(crate) async fn output_task() {
+    loop {
+        QueuedOutput { yielded: false }.await;
+        instructions::interrupts::without_interrupts(
+            || { drain_output(&mut WRITER.lock(), OUTPUT_BATCH_SIZE); }
+        );
+    }
+}
+
+/// Returns the minutes without input after which the screen is blanked, or zero if it never is.
+pub(crate) fn get_blank_timeout() -> usize { BLANK_TIMEOUT.load(Ordering::Relaxed) }
+
+/// Sets the minutes without input after which the screen is blanked, or zero to never blank it.
+pub(crate) fn set_blank_timeout(minutes: usize) {
+    BLANK_TIMEOUT.store(minutes, Ordering::Relaxed);
+    record_input();
+}
+
+/// Returns whether the screen is blanked.
+pub(crate) fn is_blanked() -> bool { IS_BLANKED.load(Ordering::Relaxed) }
+
+/// Records input from the user, which restarts the timeout and brings a blanked screen back.
+pub(crate) fn record_input() {
+    LAST_INPUT.store(pit::ticks(), Ordering::Relaxed);
+    BLANK_EVENTS.fetch_add(1, Ordering::Relaxed);
+    let waker = instructions::interrupts::without_interrupts(|| BLANK_WAKER.lock().take());
+    if let Some(waker) = waker { waker.wake(); }
+}
+
+/// Blanks the screen once the timeout expires, and brings it back on input; spawned once by the kernel.
+pub(crate) async fn blank_task() {
+    loop {
+        let events = BLANK_EVENTS.load(Ordering::Relaxed);
+        if is_blanked() {
+            BlankEvent { events }.await;
+            IS_BLANKED.store(false, Ordering::Relaxed);
+            set_screen_off(false);
+            continue;
+        }
+
+        let timeout = get_blank_timeout().saturating_mul(60) as f64;
+        if timeout == 0.0 {
+            BlankEvent { events }.await;
+            continue;
+        }
+
+        let idle = pit::ticks().saturating_sub(LAST_INPUT.load(Ordering::Relaxed)) as f64 * pit::tick_interval();
+        if idle < timeout {
+            pit::delay(timeout - idle).await;
+        } else if BLANK_EVENTS.load(Ordering::Relaxed) == events {
+            IS_BLANKED.store(true, Ordering::Relaxed);
+            set_screen_off(true);
+        }
+    }
+}
+
+/// Turns the display off or back on.
+fn set_screen_off(off: bool) {
+    if !is_text_mode() {
+        framebuffer::set_blanked(off);
+        redraw();
+        return;
+    }
+
+    // Bit 5 of the Clocking Mode Register turns the screen off, while the memory stays accessible.
+    const CLOCKING_MODE: u8 = 0x1;
+    const SCREEN_OFF: u8 = 0x20;
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut addr = Port::<u8>::new(Register::SequencerAddr as u16);
+            let mut data = Port::<u8>::new(Register::SequencerData as u16);
+            unsafe {
+                addr.write(CLOCKING_MODE);
+                let mode = data.read();
+                data.write(if off { mode | SCREEN_OFF } else { mode & !SCREEN_OFF });
+            }
+        }
+    );
+}
+
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
     use fmt::Write;
 
+    if is_async_output_enabled() {
+        if let Some(queue) = OUTPUT_QUEUE.get() {
+            OutputQueue(queue).write_fmt(args).unwrap();
+            let waker = instructions::interrupts::without_interrupts(|| OUTPUT_WAKER.lock().take());
+            if let Some(waker) = waker { waker.wake(); }
+            return;
+        }
+    }
+
     instructions::interrupts::without_interrupts(
-        || { WRITER.lock().write_fmt(args).unwrap(); }
+        || { writer().write_fmt(args).unwrap(); }
     );
 }
 
+/// Writes the given bytes through the escape sequence parser, even if they are not valid UTF-8.
+pub(crate) fn write_bytes(bytes: &[u8]) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut writer = writer();
+            let mut parser = PARSER.lock();
+            for &byte in bytes {
+                parser.advance(&mut *writer, byte);
+            }
+            writer.update_cursor();
+        }
+    );
+}
+
+/// Erases the given number of cells before the cursor, see [`Writer::erase_back`].
+pub(crate) fn erase_back(cells: usize) {
+    instructions::interrupts::without_interrupts(
+        || { writer().erase_back(cells); }
+    );
+}
+
+/// Writes the given string without waiting for the writer.
+///
+/// If the writer is held, e.g. by the code that faulted, the text is written straight into the text
+/// buffer from the hardware cursor onwards, dropping escape sequences. The writer is not informed,
+/// so this is only meant for exception and panic handlers.
+pub(crate) fn write_unsynchronized(s: &str) {
+    use fmt::Write;
+
+    if let Some(mut writer) = WRITER.try_lock() {
+        drain_output(&mut writer, usize::MAX);
+        writer.write_str(s).ok();
+        return;
+    }
+
+    let cells = TEXT_BUFFER_ROWS * TEXT_BUFFER_COLS;
+    let buffer = buffer_address() as *mut u16;
+    let color_code = ColorCode::new(Color::White, Color::Red).as_u8() as u16;
+
+    let mut offset = EMERGENCY_OFFSET.load(Ordering::Relaxed);
+    if offset == usize::MAX {
+        let cursor = if is_text_mode() { read_hardware_cursor() } else { framebuffer::cursor_offset().unwrap_or(0) };
+        offset = cursor.min(cells - 1);
+    }
+
+    let mut in_escape = false;
+    for byte in s.bytes() {
+        // Skip escape sequences up to and including their final byte.
+        if in_escape {
+            in_escape = !(byte.is_ascii_alphabetic() || byte == b'~');
+            continue;
+        }
+        match byte {
+            ASCII::<u8>::ESC => in_escape = true,
+            ASCII::<u8>::LF => offset += TEXT_BUFFER_COLS - offset % TEXT_BUFFER_COLS,
+            ASCII::<u8>::CR => offset -= offset % TEXT_BUFFER_COLS,
+            0x20..=0x7E => {
+                unsafe { core::ptr::write_volatile(buffer.add(offset), color_code << 8 | byte as u16); }
+                offset += 1;
+            }
+            _ => {}
+        }
+
+        // Scroll the whole screen up by a row once the end is reached.
+        if offset >= cells {
+            unsafe {
+                for cell in TEXT_BUFFER_COLS..cells {
+                    let data = core::ptr::read_volatile(buffer.add(cell));
+                    core::ptr::write_volatile(buffer.add(cell - TEXT_BUFFER_COLS), data);
+                }
+                for cell in (cells - TEXT_BUFFER_COLS)..cells {
+                    core::ptr::write_volatile(buffer.add(cell), color_code << 8 | ASCII::<u8>::SP as u16);
+                }
+            }
+            offset -= TEXT_BUFFER_COLS;
+        }
+    }
+
+    EMERGENCY_OFFSET.store(offset, Ordering::Relaxed);
+
+    if !is_text_mode() {
+        framebuffer::refresh_unsynchronized(unsafe { core::slice::from_raw_parts(buffer, cells) });
+    }
+}
+
+//////////////////
+/// Tag Writer
+//////////////////
+/// Puts text straight onto the screen, bypassing the escape sequence parser, which may be in use.
+struct TagWriter<'a>(&'a mut Writer);
+
+impl fmt::Write for TagWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().for_each(|byte| self.0.put_byte(byte));
+        Ok(())
+    }
+}
+
+////////////////////
+/// Output Queue
+////////////////////
+struct OutputQueue(&'static ArrayQueue<u8>);
+
+impl fmt::Write for OutputQueue {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            // Once full, the queue is emptied on the spot rather than dropping or reordering output.
+            while self.0.push(byte).is_err() {
+                instructions::interrupts::without_interrupts(
+                    || { writer(); }
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/////////////////////
+/// Queued Output
+/////////////////////
+/// A future that resolves once there is queued output, yielding to the other tasks at least once.
+struct QueuedOutput {
+    yielded: bool,
+}
+
+impl Future for QueuedOutput {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        instructions::interrupts::without_interrupts(
+            || {
+                let pending = OUTPUT_QUEUE.get().map_or(false, |queue| !queue.is_empty());
+                if pending && self.yielded { return Poll::Ready(()); }
+                if pending {
+                    self.yielded = true;
+                    context.waker().wake_by_ref();
+                } else {
+                    *OUTPUT_WAKER.lock() = Some(context.waker().clone());
+                }
+                Poll::Pending
+            }
+        )
+    }
+}
+
+///////////////////
+/// Blank Event
+///////////////////
+/// Completes once input arrives or the timeout changes, after `events` of them were counted.
+struct BlankEvent {
+    events: usize,
+}
+
+impl Future for BlankEvent {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        instructions::interrupts::without_interrupts(
+            || {
+                if BLANK_EVENTS.load(Ordering::Relaxed) != self.events { return Poll::Ready(()); }
+                *BLANK_WAKER.lock() = Some(context.waker().clone());
+                Poll::Pending
+            }
+        )
+    }
+}
+
+/// Reads the offset of the hardware cursor.
+fn read_hardware_cursor() -> usize {
+    let mut car = Port::<u8>::new(Register::CRTControlAddr as u16);
+    let mut cdr = Port::<u8>::new(Register::CRTControlData as u16);
+
+    unsafe {
+        car.write(0x0F);
+        let low = cdr.read() as usize;
+        car.write(0x0E);
+        let high = cdr.read() as usize;
+        (high << 8) | low
+    }
+}
+
 ////////////
 // Macros
 ////////////
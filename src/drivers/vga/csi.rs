@@ -0,0 +1,325 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::cmp::min;
+
+// Control Sequence Introducer (CSI)
+//
+// A control sequence is `ESC [`, followed by numeric parameters separated by `;`, and a final byte
+// that selects the function. Coordinates in control sequences are 1-based, counting from the top
+// left corner, whereas the writer indexes its buffer from zero; the functions below do the
+// conversion, and never return a position outside the screen.
+//
+// A missing parameter takes the default of its function. For movement, a parameter of 0 also means
+// the default of 1, so that `ESC [ 0 A` still moves the cursor up by a row.
+//
+// Function                     Sequence        Default
+// Cursor Up (CUU)              CSI n A         n = 1
+// Cursor Down (CUD)            CSI n B         n = 1
+// Cursor Forward (CUF)         CSI n C         n = 1
+// Cursor Back (CUB)            CSI n D         n = 1
+// Cursor Horizontal Abs (CHA)  CSI n G         n = 1
+// Cursor Position (CUP)        CSI n ; m H     n = 1, m = 1
+// Erase in Display (ED)        CSI n J         n = 0
+// Erase in Line (EL)           CSI n K         n = 0
+// Select Graphic Rendition     CSI n ; ... m   n = 0
+// Save Cursor Position         CSI s
+// Restore Cursor Position      CSI u
+// Show Cursor (DECTCEM)        CSI ? 25 h
+// Hide Cursor (DECTCEM)        CSI ? 25 l
+//
+// Besides the 16 colors of the VGA, SGR accepts extended colors, as `38;5;n` for one of the 256
+// colors of xterm, and `38;2;r;g;b` for a truecolor, or `48` instead of `38` for the background.
+// These cannot be displayed as they are, so they are mapped onto the nearest entry of a table.
+//
+// Wikipedia: https://en.wikipedia.org/wiki/ANSI_escape_code#CSI_(Control_Sequence_Introducer)_sequences
+
+////////////////
+// Constants
+////////////////
+
+/// The most parameters of a sequence that are looked at; the rest are ignored.
+pub(super) const MAX_PARAMS: usize = 16;
+
+/// The marker that precedes the parameters of a private sequence, such as DECTCEM.
+pub(super) const PRIVATE: u8 = b'?';
+
+////////////
+// Types
+////////////
+/// A zero-based position on the screen, as `(row, column)`.
+pub(super) type Position = (usize, usize);
+
+/// The size of the screen, as `(rows, columns)`.
+pub(super) type Size = (usize, usize);
+
+/////////////
+/// Erase
+/////////////
+/// The part of the display or line an erase function clears, relative to the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Erase {
+    ToEnd,
+    ToBeginning,
+    All,
+}
+
+impl Erase {
+    /// Returns the mode selected by the parameters, if it is supported.
+    pub(super) fn from_params(params: &[u16]) -> Option<Self> {
+        match params.first().copied().unwrap_or(0) {
+            0 => Some(Erase::ToEnd),
+            1 => Some(Erase::ToBeginning),
+            2 => Some(Erase::All),
+            _ => None,
+        }
+    }
+}
+
+/////////////////////
+/// Extended Color
+/////////////////////
+/// A color given by an extended SGR parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ExtendedColor {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl ExtendedColor {
+    /// Parses the values that follow `38` or `48`, returning the color and the number of values used.
+    pub(super) fn parse(values: &[u16]) -> Option<(Self, usize)> {
+        const INDEXED: u16 = 5;
+        const RGB: u16 = 2;
+
+        let component = |i: usize| values.get(i).map(|&v| min(v, u8::MAX as u16) as u8);
+        match values.first().copied() {
+            Some(INDEXED) => Some((ExtendedColor::Indexed(component(1)?), 2)),
+            Some(RGB) => Some((ExtendedColor::Rgb(component(1)?, component(2)?, component(3)?), 4)),
+            _ => None,
+        }
+    }
+
+    /// Returns the RGB value of the color, using the xterm values for indexed colors.
+    pub(super) fn rgb(&self) -> (u8, u8, u8) {
+        const SYSTEM: [(u8, u8, u8); 16] = [
+            (0x00, 0x00, 0x00), (0x80, 0x00, 0x00), (0x00, 0x80, 0x00), (0x80, 0x80, 0x00),
+            (0x00, 0x00, 0x80), (0x80, 0x00, 0x80), (0x00, 0x80, 0x80), (0xC0, 0xC0, 0xC0),
+            (0x80, 0x80, 0x80), (0xFF, 0x00, 0x00), (0x00, 0xFF, 0x00), (0xFF, 0xFF, 0x00),
+            (0x00, 0x00, 0xFF), (0xFF, 0x00, 0xFF), (0x00, 0xFF, 0xFF), (0xFF, 0xFF, 0xFF),
+        ];
+        const CUBE_BEGIN: u8 = 16;
+        const GRAY_BEGIN: u8 = 232;
+
+        let level = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+        match *self {
+            ExtendedColor::Rgb(r, g, b) => (r, g, b),
+            ExtendedColor::Indexed(idx) if idx < CUBE_BEGIN => SYSTEM[idx as usize],
+            ExtendedColor::Indexed(idx) if idx < GRAY_BEGIN => {
+                let idx = idx - CUBE_BEGIN;
+                (level(idx / 36), level((idx / 6) % 6), level(idx % 6))
+            }
+            ExtendedColor::Indexed(idx) => {
+                let v = 8 + 10 * (idx - GRAY_BEGIN);
+                (v, v, v)
+            }
+        }
+    }
+}
+
+////////////////
+// Utilities
+////////////////
+
+/// Returns the count at the given index, defaulting to 1 when missing or 0.
+fn count(params: &[u16], index: usize) -> usize {
+    match params.get(index) {
+        Some(&n) if n > 0 => n as usize,
+        _ => 1,
+    }
+}
+
+/// Converts a 1-based coordinate to a zero-based index below `limit`.
+fn to_index(coordinate: usize, limit: usize) -> usize { min(coordinate.max(1) - 1, limit - 1) }
+
+/// Clamps the position onto the screen; the column may otherwise be one past the last while a wrap is pending.
+pub(super) fn clamp((row, col): Position, (rows, columns): Size) -> Position { (min(row, rows - 1), min(col, columns - 1)) }
+
+/// Moves the cursor up (CUU).
+pub(super) fn cursor_up(position: Position, size: Size, params: &[u16]) -> Position {
+    let (row, col) = clamp(position, size);
+    (row.saturating_sub(count(params, 0)), col)
+}
+
+/// Moves the cursor down (CUD).
+pub(super) fn cursor_down(position: Position, size: Size, params: &[u16]) -> Position {
+    let (row, col) = clamp(position, size);
+    (min(row.saturating_add(count(params, 0)), size.0 - 1), col)
+}
+
+/// Moves the cursor forward (CUF).
+pub(super) fn cursor_forward(position: Position, size: Size, params: &[u16]) -> Position {
+    let (row, col) = clamp(position, size);
+    (row, min(col.saturating_add(count(params, 0)), size.1 - 1))
+}
+
+/// Moves the cursor back (CUB).
+pub(super) fn cursor_back(position: Position, size: Size, params: &[u16]) -> Position {
+    let (row, col) = clamp(position, size);
+    (row, col.saturating_sub(count(params, 0)))
+}
+
+/// Moves the cursor to the given column of the row (CHA).
+pub(super) fn cursor_horizontal_absolute(position: Position, size: Size, params: &[u16]) -> Position {
+    let (row, _) = clamp(position, size);
+    (row, to_index(count(params, 0), size.1))
+}
+
+/// Moves the cursor to the given row and column (CUP).
+pub(super) fn cursor_position(size: Size, params: &[u16]) -> Position {
+    (to_index(count(params, 0), size.0), to_index(count(params, 1), size.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Size = (25, 80);
+
+    #[test_case]
+    fn cursor_up_defaults_to_one_row() {
+        assert_eq!(cursor_up((5, 7), SIZE, &[]), (4, 7));
+        assert_eq!(cursor_up((5, 7), SIZE, &[0]), (4, 7));
+        assert_eq!(cursor_up((5, 7), SIZE, &[3]), (2, 7));
+    }
+
+    #[test_case]
+    fn cursor_up_stops_at_the_top() {
+        assert_eq!(cursor_up((2, 7), SIZE, &[9]), (0, 7));
+        assert_eq!(cursor_up((0, 0), SIZE, &[u16::MAX]), (0, 0));
+    }
+
+    #[test_case]
+    fn cursor_down_defaults_to_one_row() {
+        assert_eq!(cursor_down((5, 7), SIZE, &[]), (6, 7));
+        assert_eq!(cursor_down((5, 7), SIZE, &[0]), (6, 7));
+        assert_eq!(cursor_down((5, 7), SIZE, &[3]), (8, 7));
+    }
+
+    #[test_case]
+    fn cursor_down_stops_at_the_bottom() {
+        assert_eq!(cursor_down((20, 7), SIZE, &[9]), (24, 7));
+        assert_eq!(cursor_down((24, 0), SIZE, &[u16::MAX]), (24, 0));
+    }
+
+    #[test_case]
+    fn cursor_forward_defaults_to_one_column() {
+        assert_eq!(cursor_forward((5, 7), SIZE, &[]), (5, 8));
+        assert_eq!(cursor_forward((5, 7), SIZE, &[0]), (5, 8));
+        assert_eq!(cursor_forward((5, 7), SIZE, &[3]), (5, 10));
+    }
+
+    #[test_case]
+    fn cursor_forward_stops_at_the_last_column() {
+        assert_eq!(cursor_forward((5, 75), SIZE, &[9]), (5, 79));
+        assert_eq!(cursor_forward((5, 80), SIZE, &[]), (5, 79));
+    }
+
+    #[test_case]
+    fn cursor_back_defaults_to_one_column() {
+        assert_eq!(cursor_back((5, 7), SIZE, &[]), (5, 6));
+        assert_eq!(cursor_back((5, 7), SIZE, &[0]), (5, 6));
+        assert_eq!(cursor_back((5, 7), SIZE, &[3]), (5, 4));
+    }
+
+    #[test_case]
+    fn cursor_back_stops_at_the_first_column() {
+        assert_eq!(cursor_back((5, 2), SIZE, &[9]), (5, 0));
+        assert_eq!(cursor_back((5, 80), SIZE, &[]), (5, 78));
+    }
+
+    #[test_case]
+    fn cursor_horizontal_absolute_is_one_based() {
+        assert_eq!(cursor_horizontal_absolute((5, 7), SIZE, &[]), (5, 0));
+        assert_eq!(cursor_horizontal_absolute((5, 7), SIZE, &[0]), (5, 0));
+        assert_eq!(cursor_horizontal_absolute((5, 7), SIZE, &[1]), (5, 0));
+        assert_eq!(cursor_horizontal_absolute((5, 7), SIZE, &[80]), (5, 79));
+    }
+
+    #[test_case]
+    fn cursor_horizontal_absolute_stops_at_the_last_column() {
+        assert_eq!(cursor_horizontal_absolute((5, 7), SIZE, &[81]), (5, 79));
+        assert_eq!(cursor_horizontal_absolute((5, 7), SIZE, &[u16::MAX]), (5, 79));
+    }
+
+    #[test_case]
+    fn cursor_position_is_one_based() {
+        assert_eq!(cursor_position(SIZE, &[]), (0, 0));
+        assert_eq!(cursor_position(SIZE, &[0, 0]), (0, 0));
+        assert_eq!(cursor_position(SIZE, &[1, 1]), (0, 0));
+        assert_eq!(cursor_position(SIZE, &[3]), (2, 0));
+        assert_eq!(cursor_position(SIZE, &[0, 5]), (0, 4));
+        assert_eq!(cursor_position(SIZE, &[25, 80]), (24, 79));
+    }
+
+    #[test_case]
+    fn cursor_position_stops_at_the_last_cell() {
+        assert_eq!(cursor_position(SIZE, &[26, 81]), (24, 79));
+        assert_eq!(cursor_position(SIZE, &[u16::MAX, u16::MAX]), (24, 79));
+    }
+
+    #[test_case]
+    fn clamp_keeps_a_pending_wrap_on_screen() {
+        assert_eq!(clamp((5, 80), SIZE), (5, 79));
+        assert_eq!(clamp((24, 79), SIZE), (24, 79));
+    }
+
+    #[test_case]
+    fn extended_color_parses_both_forms() {
+        assert_eq!(ExtendedColor::parse(&[5, 196]), Some((ExtendedColor::Indexed(196), 2)));
+        assert_eq!(ExtendedColor::parse(&[5, 300, 1]), Some((ExtendedColor::Indexed(255), 2)));
+        assert_eq!(ExtendedColor::parse(&[2, 10, 20, 30, 1]), Some((ExtendedColor::Rgb(10, 20, 30), 4)));
+        assert_eq!(ExtendedColor::parse(&[2, 10, 20]), None);
+        assert_eq!(ExtendedColor::parse(&[5]), None);
+        assert_eq!(ExtendedColor::parse(&[7, 1]), None);
+        assert_eq!(ExtendedColor::parse(&[]), None);
+    }
+
+    #[test_case]
+    fn extended_color_follows_xterm() {
+        assert_eq!(ExtendedColor::Indexed(1).rgb(), (0x80, 0x00, 0x00));
+        assert_eq!(ExtendedColor::Indexed(16).rgb(), (0, 0, 0));
+        assert_eq!(ExtendedColor::Indexed(196).rgb(), (255, 0, 0));
+        assert_eq!(ExtendedColor::Indexed(231).rgb(), (255, 255, 255));
+        assert_eq!(ExtendedColor::Indexed(232).rgb(), (8, 8, 8));
+        assert_eq!(ExtendedColor::Indexed(255).rgb(), (238, 238, 238));
+    }
+
+    #[test_case]
+    fn erase_defaults_to_the_end() {
+        assert_eq!(Erase::from_params(&[]), Some(Erase::ToEnd));
+        assert_eq!(Erase::from_params(&[0]), Some(Erase::ToEnd));
+        assert_eq!(Erase::from_params(&[1]), Some(Erase::ToBeginning));
+        assert_eq!(Erase::from_params(&[2]), Some(Erase::All));
+        assert_eq!(Erase::from_params(&[3]), None);
+    }
+}
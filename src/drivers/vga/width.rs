@@ -0,0 +1,124 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+
+use crate::encodings::{ASCII, Charset};
+
+use super::get_tab_width;
+
+// Display Width
+//
+// The number of columns text takes up on the console is not the number of its bytes: characters
+// beyond ASCII take up several bytes but a single cell, be they in the code page or shown as its
+// replacement, escape sequences take up none, and a tab takes up as many cells as the tab width.
+// Text is measured the way the writer would put it on the screen, so that whatever is aligned with
+// it, e.g. the status tags of the logger, lines up.
+//
+// Measuring is done through `fmt::Write`, so that formatted text can be measured without being
+// formatted into a buffer first.
+
+////////////
+/// Mode
+////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Printable text.
+    Text,
+    /// Right after `ESC`.
+    Escape,
+    /// Within a control sequence, up to its final byte.
+    Control,
+    /// Within an operating system command, up to `BEL` or `ESC \`.
+    Command,
+    /// Right after `ESC` within an operating system command.
+    CommandEscape,
+}
+
+/////////////
+/// Width
+/////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Width {
+    columns: usize,
+    mode: Mode,
+}
+
+impl Width {
+    /// Creates a new object measuring from the start of a line.
+    pub const fn new() -> Self { Self::at(0) }
+
+    /// Creates a new object measuring from the given column.
+    pub const fn at(column: usize) -> Self { Width { columns: column, mode: Mode::Text } }
+
+    /// Returns the number of columns the last line of the text written so far takes up.
+    pub fn columns(&self) -> usize { self.columns }
+
+    /// Accounts for the character.
+    fn advance(&mut self, c: char) {
+        self.mode = match (self.mode, c) {
+            (Mode::Text, ASCII::<char>::ESC) => Mode::Escape,
+            (Mode::Text, ASCII::<char>::HT) => {
+                self.columns += get_tab_width() as usize;
+                Mode::Text
+            }
+            (Mode::Text, ASCII::<char>::LF | ASCII::<char>::CR | ASCII::<char>::FF) => {
+                self.columns = 0;
+                Mode::Text
+            }
+            (Mode::Text, ASCII::<char>::BS) => {
+                self.columns = self.columns.saturating_sub(1);
+                Mode::Text
+            }
+            (Mode::Text, c) if c < ASCII::<char>::SP || c == ASCII::<char>::DEL => Mode::Text,
+            (Mode::Text, _) => {
+                self.columns += 1;
+                Mode::Text
+            }
+            (Mode::Escape, '[') => Mode::Control,
+            (Mode::Escape, ']') => Mode::Command,
+            (Mode::Control, '\x40'..='\x7E') => Mode::Text,
+            (Mode::Command, ASCII::<char>::BEL) => Mode::Text,
+            (Mode::Command, ASCII::<char>::ESC) => Mode::CommandEscape,
+            (Mode::CommandEscape, '\\') => Mode::Text,
+            (Mode::CommandEscape, _) => Mode::Command,
+            (Mode::Escape, _) => Mode::Text,
+            (mode, _) => mode,
+        };
+    }
+}
+
+impl fmt::Write for Width {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.chars().for_each(|c| self.advance(c));
+        Ok(())
+    }
+}
+
+/// Returns the number of columns the last line of the text takes up on the console.
+pub fn of(s: &str) -> usize {
+    use fmt::Write;
+
+    let mut width = Width::new();
+    width.write_str(s).ok();
+    width.columns()
+}
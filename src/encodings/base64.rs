@@ -0,0 +1,92 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Base64
+//
+// Binary data is written as text by splitting every three bytes into four groups of six bits, each
+// standing for a character of the alphabet below. The output is padded with `=` to a multiple of four
+// characters.
+//
+// RFC 4648: https://www.rfc-editor.org/rfc/rfc4648
+
+//////////////
+/// Base64
+//////////////
+pub struct Base64;
+
+impl Base64 {
+    /// Characters standing for the values of six bits.
+    const ALPHABET: &'static [u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    /// Character padding the output.
+    const PAD: u8 = b'=';
+
+    /// Encodes the bytes.
+    pub fn encode(data: &[u8]) -> String {
+        let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+        for chunk in data.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (idx, &byte)| bits | (byte as u32) << (16 - 8 * idx));
+            for idx in 0..4 {
+                let c = if idx <= chunk.len() { Self::ALPHABET[(bits >> (18 - 6 * idx) & 0x3F) as usize] } else { Self::PAD };
+                encoded.push(c as char);
+            }
+        }
+        encoded
+    }
+
+    /// Decodes the text, ignoring whitespace, or returns `None` if it is not valid.
+    pub fn decode(text: &str) -> Option<Vec<u8>> {
+        let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+        let (mut bits, mut count) = (0u32, 0);
+        let mut padding = 0;
+        for byte in text.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+            if byte == Self::PAD {
+                padding += 1;
+                continue;
+            }
+            // Nothing but padding may follow the padding.
+            if padding > 0 { return None; }
+
+            let value = Self::ALPHABET.iter().position(|&c| c == byte)? as u32;
+            bits = bits << 6 | value;
+            count += 1;
+            if count == 4 {
+                decoded.extend_from_slice(&bits.to_be_bytes()[1..]);
+                (bits, count) = (0, 0);
+            }
+        }
+
+        // The last group holds two or three characters, and one or two bytes.
+        match count {
+            0 => {}
+            2 => decoded.push((bits >> 4) as u8),
+            3 => decoded.extend_from_slice(&((bits >> 2) as u16).to_be_bytes()),
+            _ => return None,
+        }
+        if padding > 2 || (padding > 0 && (count + padding) % 4 != 0) { return None; }
+
+        Some(decoded)
+    }
+}
@@ -0,0 +1,75 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+// Code Page 437
+//
+// The character set of the VGA font, and thus of the screen. Its printable ASCII range is that of
+// ASCII, while the other codes, including the controls, stand for accented letters, Greek letters,
+// box drawing characters and symbols. Characters that are not in the code page are shown as a small
+// square.
+//
+// Wikipedia: https://en.wikipedia.org/wiki/Code_page_437
+
+/////////////
+/// CP437
+/////////////
+pub struct CP437;
+
+impl CP437 {
+    /// Code shown in place of characters that are not in the code page.
+    pub const REPLACEMENT: u8 = 0xFE;
+
+    /// Characters drawn for the codes of the ASCII control characters.
+    const LOWER: [char; 32] = [
+        '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+        '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ];
+
+    /// Character drawn for the code of DEL.
+    const DEL: char = '⌂';
+
+    /// Characters drawn for the codes beyond ASCII.
+    const UPPER: [char; 128] = [
+        'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+        'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+        'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+        '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+        '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+        '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+        'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+        '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+    ];
+
+    /// Returns the code of the printable character, or `None` if it is not in the code page.
+    ///
+    /// Note: Control characters are not printable, even though their codes have glyphs.
+    pub fn encode(c: char) -> Option<u8> {
+        if (' '..='~').contains(&c) { return Some(c as u8); }
+        if c == Self::DEL { return Some(0x7F); }
+
+        Self::LOWER.iter().skip(1).position(|&g| g == c).map(|idx| idx as u8 + 1)
+            .or_else(|| Self::UPPER.iter().position(|&g| g == c).map(|idx| idx as u8 + 0x80))
+    }
+
+    /// Returns the code of the printable character, or the replacement if it is not in the code page.
+    pub fn encode_lossy(c: char) -> u8 { Self::encode(c).unwrap_or(Self::REPLACEMENT) }
+}
@@ -21,7 +21,11 @@
 // SOFTWARE.
 
 pub use ascii::ASCII;
+pub use base64::Base64;
 pub use charset::Charset;
+pub use cp437::CP437;
 
 mod ascii;
+mod base64;
 mod charset;
+mod cp437;
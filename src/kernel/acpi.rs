@@ -20,42 +20,153 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::ptr::NonNull;
 
 use acpi::{AcpiError, AcpiTables, PhysicalMapping};
 use acpi::AcpiHandler;
 use acpi::fadt::Fadt;
 use acpi::madt::Madt;
-use acpi::sdt::Signature;
-use aml::AmlError;
+use acpi::sdt::{SdtHeader, Signature};
+use conquer_once::spin::OnceCell;
 use x86_64::PhysAddr;
 
+use crate::kernel::boot;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
 use crate::kernel::memory;
+use crate::warning;
 
 pub mod dsdt;
 pub mod fadt;
 pub mod madt;
+pub mod namespace;
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the ACPI.
+pub(crate) static DEVICE: Device = Device {
+    name: "ACPI",
+    class: Class::Firmware,
+    stage: Stage::Platform,
+    critical: false,
+    depends: &["Allocator"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+///////////////////
+// Cached Values
+///////////////////
+
+static TABLES: OnceCell<Vec<Table>> = OnceCell::uninit();
+
+/////////////
+/// Table
+/////////////
+#[derive(Debug, Clone)]
+pub struct Table {
+    /// Four-character signature of the table.
+    pub signature: String,
+    /// Physical address of the table, including its header.
+    pub address: usize,
+    /// Length of the table in bytes, including its header.
+    pub length: usize,
+    /// Revision of the table structure.
+    pub revision: u8,
+    /// OEM identifier reported by the firmware.
+    pub oem_id: String,
+}
+
+impl Table {
+    /// Returns the raw contents of the table, including its header.
+    pub fn bytes(&self) -> &'static [u8] {
+        let virt_addr = memory::phys_to_virt_addr(PhysAddr::new(self.address as u64));
+        unsafe { core::slice::from_raw_parts(virt_addr.as_ptr::<u8>(), self.length) }
+    }
+}
 
 ///////////////
 // Utilities
 ///////////////
 
 /// Initializes the ACPI and stores required parameters.
-pub(crate) fn init() -> Result<(), GenericError> {
-    let acpi = unsafe { AcpiTables::search_for_rsdp_bios(CustomACPIHandler) }?;
+pub(crate) fn init() -> Result<(), Error> {
+    let acpi = locate()?;
+    TABLES.try_init_once(|| collect_tables(&acpi)).ok();
 
     let fadt = unsafe { acpi.get_sdt::<Fadt>(Signature::FADT) }?.ok_or(AcpiError::TableMissing(Signature::FADT))?;
     fadt::read(&fadt)?;
 
     let dsdt = acpi.dsdt.as_ref().ok_or(AcpiError::TableMissing(Signature::DSDT))?;
-    dsdt::read(&dsdt)?;
+    dsdt::read(&dsdt, &acpi.ssdts)?;
 
     let madt = unsafe { acpi.get_sdt::<Madt>(Signature::MADT) }?.ok_or(AcpiError::TableMissing(Signature::MADT))?;
-    madt::read(&madt).unwrap();
+    madt::read(&madt)?;
 
     Ok(())
 }
 
+/// Locates the tables through the RSDP handed over by the bootloader, scanning the BIOS areas otherwise.
+///
+/// UEFI firmware does not place the RSDP in the areas scanned, so the bootloader's pointer is the
+/// only way to find it there.
+fn locate() -> Result<AcpiTables<CustomACPIHandler>, AcpiError> {
+    if let Some(addr) = boot::rsdp_address() {
+        match unsafe { AcpiTables::from_rsdp(CustomACPIHandler, addr as usize) } {
+            Ok(acpi) => return Ok(acpi),
+            Err(e) => warning!("ACPI: the RSDP at {:#x} is invalid ({:?}), scanning instead", addr, e),
+        }
+    }
+
+    unsafe { AcpiTables::search_for_rsdp_bios(CustomACPIHandler) }
+}
+
+/// Returns the tables found by the firmware, or none if the ACPI is not initialized.
+pub fn tables() -> &'static [Table] { TABLES.try_get().map_or(&[], |tables| tables.as_slice()) }
+
+/// Returns the first table with the given signature.
+pub fn find(signature: &str) -> Option<&'static Table> {
+    tables().iter().find(|table| table.signature.eq_ignore_ascii_case(signature))
+}
+
+/// Lists the tables, including the DSDT and SSDTs.
+fn collect_tables(acpi: &AcpiTables<CustomACPIHandler>) -> Vec<Table> {
+    let mut tables: Vec<Table> = acpi.sdts.iter()
+        .map(|(signature, sdt)| describe(signature.as_str(), sdt.physical_address, sdt.length as usize))
+        .collect();
+
+    // AML tables are recorded by their bytecode stream, which directly follows the header.
+    let header = core::mem::size_of::<SdtHeader>();
+    let aml = acpi.dsdt.iter().map(|t| (Signature::DSDT, t)).chain(acpi.ssdts.iter().map(|t| (Signature::SSDT, t)));
+    for (signature, table) in aml {
+        tables.push(describe(signature.as_str(), table.address - header, table.length as usize + header));
+    }
+
+    tables
+}
+
+/// Builds a table descriptor, reading the remaining fields from the header.
+fn describe(signature: &str, address: usize, length: usize) -> Table {
+    // Offsets of the fields within the standard SDT header.
+    const REVISION: usize = 8;
+    const OEM_ID: usize = 10;
+
+    let oem_id: [u8; 6] = read_addr(address + OEM_ID);
+    Table {
+        signature: signature.to_string(),
+        address,
+        length,
+        revision: read_addr(address + REVISION),
+        oem_id: String::from_utf8_lossy(&oem_id).trim_end().to_string(),
+    }
+}
+
 /// Converts the given physical address to virtual address and returns it.
 fn read_addr<T>(phys_addr: usize) -> T where T: Copy {
     let virt_addr = memory::phys_to_virt_addr(PhysAddr::new(phys_addr as u64));
@@ -76,20 +187,3 @@ impl AcpiHandler for CustomACPIHandler {
 
     fn unmap_physical_region<T>(_region: &PhysicalMapping<Self, T>) {}
 }
-
-/////////////////////
-/// Generic Error
-/////////////////////
-#[derive(Debug)]
-pub enum GenericError {
-    ACPI(AcpiError),
-    AML(AmlError),
-}
-
-impl From<AcpiError> for GenericError {
-    fn from(value: AcpiError) -> Self { Self::ACPI(value) }
-}
-
-impl From<AmlError> for GenericError {
-    fn from(value: AmlError) -> Self { Self::AML(value) }
-}
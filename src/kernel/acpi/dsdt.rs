@@ -20,20 +20,32 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-///////////////////
-// Cached Values
-///////////////////
-
 use alloc::boxed::Box;
+use core::ptr;
 use core::slice;
 use core::sync::atomic::{AtomicU16, Ordering};
 
 use acpi::AmlTable;
 use aml::{AmlContext, AmlError, AmlName, AmlValue, DebugVerbosity};
 use aml::Handler;
+use conquer_once::spin::OnceCell;
+use spin::Mutex;
 use x86_64::PhysAddr;
 
+use crate::warning;
 use crate::kernel::memory;
+use crate::kernel::pci;
+use crate::kernel::portio::Port;
+
+// Differentiated System Description Table (DSDT)
+//
+// The DSDT and the SSDTs carry AML bytecode that builds the ACPI namespace: a tree of devices whose
+// objects describe their identity (_HID), resources (_CRS), and power states. The objects are either
+// plain values or control methods, which the interpreter runs on demand. Methods may touch memory,
+// I/O ports, and the PCI configuration space through operation regions, all of which are forwarded to
+// the handler below.
+//
+// The interpreter context is kept after boot so the namespace can be queried by drivers.
 
 ///////////////
 // Constants
@@ -54,6 +66,9 @@ static SLP_TYP_A: AtomicU16 = AtomicU16::new(u16::MAX);
 /// Value of SLP_TYP_B from the AML tables.
 static SLP_TYP_B: AtomicU16 = AtomicU16::new(u16::MAX);
 
+/// Interpreter holding the parsed namespace.
+static CONTEXT: OnceCell<Mutex<AmlContext>> = OnceCell::uninit();
+
 ////////////////
 /// Block S5
 ////////////////
@@ -68,14 +83,14 @@ enum BlockS5 {
 // Utilities
 ///////////////
 
-pub(super) fn read(sdt: &AmlTable) -> Result<(), AmlError> {
+pub(super) fn read(dsdt: &AmlTable, ssdts: &[AmlTable]) -> Result<(), AmlError> {
     let mut aml = AmlContext::new(Box::new(CustomAMLHandler), DebugVerbosity::None);
 
-    let address = memory::phys_to_virt_addr(PhysAddr::new(sdt.address as u64));
-
-    // Create AML table from raw parts.
-    let stream = unsafe { slice::from_raw_parts(address.as_ptr(), sdt.length as usize) };
-    aml.parse_table(stream)?;
+    parse(&mut aml, dsdt)?;
+    for ssdt in ssdts {
+        // A broken SSDT only loses the objects it defines.
+        if let Err(e) = parse(&mut aml, ssdt) { warning!("ACPI: skipping SSDT at {:#x}: {:?}", ssdt.address, e); }
+    }
 
     // Parse S5 block code from the AML table.
     let name = AmlName::from_str(BLOCK_CODE_S5)?;
@@ -88,6 +103,11 @@ pub(super) fn read(sdt: &AmlTable) -> Result<(), AmlError> {
         }
     }
 
+    // Run the _INI methods of the present devices, as the firmware expects before the namespace is used.
+    if let Err(e) = aml.initialize_objects() { warning!("ACPI: device initialization failed: {:?}", e); }
+
+    CONTEXT.try_init_once(|| Mutex::new(aml)).ok();
+
     Ok(())
 }
 
@@ -97,6 +117,44 @@ pub fn slp_typ_a() -> u16 { SLP_TYP_A.load(Ordering::Relaxed) }
 /// Returns the value of SLP_TYP_B register.
 pub fn slp_typ_b() -> u16 { SLP_TYP_B.load(Ordering::Relaxed) }
 
+/// Runs the given function with the interpreter, or returns none if the AML tables were not parsed.
+pub fn with_context<R>(f: impl FnOnce(&mut AmlContext) -> R) -> Option<R> {
+    CONTEXT.try_get().ok().map(|context| f(&mut context.lock()))
+}
+
+/// Loads the objects defined by the given table into the namespace.
+fn parse(aml: &mut AmlContext, table: &AmlTable) -> Result<(), AmlError> {
+    let address = memory::phys_to_virt_addr(PhysAddr::new(table.address as u64));
+
+    // Create AML table from raw parts.
+    let stream = unsafe { slice::from_raw_parts(address.as_ptr(), table.length as usize) };
+    aml.parse_table(stream)
+}
+
+/// Writes the given value to the given physical address.
+fn write_addr<T>(phys_addr: usize, value: T) {
+    let virt_addr = memory::phys_to_virt_addr(PhysAddr::new(phys_addr as u64));
+    unsafe { ptr::write_volatile(virt_addr.as_mut_ptr::<T>(), value) };
+}
+
+/// Reads the configuration register containing the given offset, or returns all ones if it is not reachable.
+///
+/// Note: Only the first segment and the legacy 256-byte configuration space are reachable through the I/O ports.
+fn read_pci(segment: u16, bus: u8, device: u8, function: u8, offset: u16) -> u32 {
+    if segment != 0 || offset > u8::MAX as u16 { return u32::MAX; }
+    pci::read_config(bus, device, function, offset as u8)
+}
+
+/// Replaces `width` bytes of the configuration register containing the given offset.
+fn write_pci(segment: u16, bus: u8, device: u8, function: u8, offset: u16, width: u16, value: u32) {
+    if segment != 0 || offset > u8::MAX as u16 { return; }
+
+    let shift = (offset & 0x3) * 8;
+    let mask = if width == 4 { u32::MAX } else { ((1 << (width * 8)) - 1) << shift };
+    let reg = pci::read_config(bus, device, function, offset as u8);
+    pci::write_config(bus, device, function, offset as u8, (reg & !mask) | ((value << shift) & mask));
+}
+
 //////////////////////////
 /// Custom AML Handler
 //////////////////////////
@@ -112,35 +170,47 @@ impl Handler for CustomAMLHandler {
 
     fn read_u64(&self, address: usize) -> u64 { super::read_addr::<u64>(address) }
 
-    fn write_u8(&mut self, _address: usize, _value: u8) { unimplemented!() }
+    fn write_u8(&mut self, address: usize, value: u8) { write_addr(address, value); }
 
-    fn write_u16(&mut self, _address: usize, _value: u16) { unimplemented!() }
+    fn write_u16(&mut self, address: usize, value: u16) { write_addr(address, value); }
 
-    fn write_u32(&mut self, _address: usize, _value: u32) { unimplemented!() }
+    fn write_u32(&mut self, address: usize, value: u32) { write_addr(address, value); }
 
-    fn write_u64(&mut self, _address: usize, _value: u64) { unimplemented!() }
+    fn write_u64(&mut self, address: usize, value: u64) { write_addr(address, value); }
 
-    fn read_io_u8(&self, _port: u16) -> u8 { unimplemented!() }
+    fn read_io_u8(&self, port: u16) -> u8 { unsafe { Port::<u8>::new(port).read() } }
 
-    fn read_io_u16(&self, _port: u16) -> u16 { unimplemented!() }
+    fn read_io_u16(&self, port: u16) -> u16 { unsafe { Port::<u16>::new(port).read() } }
 
-    fn read_io_u32(&self, _port: u16) -> u32 { unimplemented!() }
+    fn read_io_u32(&self, port: u16) -> u32 { unsafe { Port::<u32>::new(port).read() } }
 
-    fn write_io_u8(&self, _port: u16, _value: u8) { unimplemented!() }
+    fn write_io_u8(&self, port: u16, value: u8) { unsafe { Port::<u8>::new(port).write(value) } }
 
-    fn write_io_u16(&self, _port: u16, _value: u16) { unimplemented!() }
+    fn write_io_u16(&self, port: u16, value: u16) { unsafe { Port::<u16>::new(port).write(value) } }
 
-    fn write_io_u32(&self, _port: u16, _value: u32) { unimplemented!() }
+    fn write_io_u32(&self, port: u16, value: u32) { unsafe { Port::<u32>::new(port).write(value) } }
 
-    fn read_pci_u8(&self, _segment: u16, _bus: u8, _device: u8, _function: u8, _offset: u16) -> u8 { unimplemented!() }
+    fn read_pci_u8(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16) -> u8 {
+        (read_pci(segment, bus, device, function, offset) >> ((offset & 0x3) * 8)) as u8
+    }
 
-    fn read_pci_u16(&self, _segment: u16, _bus: u8, _device: u8, _function: u8, _offset: u16) -> u16 { unimplemented!() }
+    fn read_pci_u16(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16) -> u16 {
+        (read_pci(segment, bus, device, function, offset) >> ((offset & 0x2) * 8)) as u16
+    }
 
-    fn read_pci_u32(&self, _segment: u16, _bus: u8, _device: u8, _function: u8, _offset: u16) -> u32 { unimplemented!() }
+    fn read_pci_u32(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16) -> u32 {
+        read_pci(segment, bus, device, function, offset)
+    }
 
-    fn write_pci_u8(&self, _segment: u16, _bus: u8, _device: u8, _function: u8, _offset: u16, _value: u8) { unimplemented!() }
+    fn write_pci_u8(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16, value: u8) {
+        write_pci(segment, bus, device, function, offset, 1, value as u32);
+    }
 
-    fn write_pci_u16(&self, _segment: u16, _bus: u8, _device: u8, _function: u8, _offset: u16, _value: u16) { unimplemented!() }
+    fn write_pci_u16(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16, value: u16) {
+        write_pci(segment, bus, device, function, offset, 2, value as u32);
+    }
 
-    fn write_pci_u32(&self, _segment: u16, _bus: u8, _device: u8, _function: u8, _offset: u16, _value: u32) { unimplemented!() }
+    fn write_pci_u32(&self, segment: u16, bus: u8, device: u8, function: u8, offset: u16, value: u32) {
+        write_pci(segment, bus, device, function, offset, 4, value);
+    }
 }
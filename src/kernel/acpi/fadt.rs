@@ -22,8 +22,12 @@
 
 use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 
-use acpi::AcpiError;
+use acpi::{AcpiError, AcpiTable};
+use acpi::platform::address::{AddressSpace, GenericAddress};
 use acpi::fadt::Fadt;
+use conquer_once::spin::OnceCell;
+
+use crate::kernel::portio;
 
 ///////////////////
 // Cached Values
@@ -35,6 +39,43 @@ static ACPI_ENABLE: AtomicU8 = AtomicU8::new(u8::MAX);
 static ACPI_DISABLE: AtomicU8 = AtomicU8::new(u8::MAX);
 /// Cached `PM-1A Control Block` register value.
 static PM1A_CTRL_BLK_PTR: AtomicU64 = AtomicU64::new(u64::MAX);
+/// Cached summary of the table.
+static INFO: OnceCell<Info> = OnceCell::uninit();
+
+////////////
+/// Info
+////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Info {
+    /// System vector the SCI interrupt is wired to.
+    pub sci_interrupt: u16,
+    /// Port of the SMI command register.
+    pub smi_cmd_port: u32,
+    /// Address of the `PM-1A Event Block`.
+    pub pm1a_event_block: Option<GenericAddress>,
+    /// Address of the `PM-1A Control Block`.
+    pub pm1a_control_block: Option<GenericAddress>,
+    /// Address of the power management timer, if provided.
+    pub pm_timer_block: Option<GenericAddress>,
+    /// Whether the power management timer is 32 bits wide instead of 24.
+    pub pm_timer_32_bit: bool,
+    /// Index of the century field in the CMOS, or zero if not supported.
+    pub century: u8,
+    /// Whether legacy devices are present on the LPC or ISA bus.
+    pub legacy_devices: bool,
+    /// Whether an 8042 controller is present.
+    pub has_8042: bool,
+    /// Whether the CMOS RTC is present.
+    pub has_cmos_rtc: bool,
+    /// Whether the platform is hardware-reduced.
+    pub hardware_reduced: bool,
+    /// Whether the reset register is supported.
+    pub reset_supported: bool,
+    /// Address of the reset register.
+    pub reset_register: Option<GenericAddress>,
+    /// Value to write to the reset register.
+    pub reset_value: u8,
+}
 
 ///////////////
 // Utilities
@@ -44,11 +85,43 @@ static PM1A_CTRL_BLK_PTR: AtomicU64 = AtomicU64::new(u64::MAX);
 pub(super) fn read(sdt: &Fadt) -> Result<(), AcpiError> {
     ACPI_ENABLE.store(sdt.acpi_enable, Ordering::Relaxed);
     ACPI_DISABLE.store(sdt.acpi_disable, Ordering::Relaxed);
-    PM1A_CTRL_BLK_PTR.store(sdt.pm1a_control_block()?.address, Ordering::Relaxed);
+    let pm1a_ctrl_blk = sdt.pm1a_control_block()?;
+    PM1A_CTRL_BLK_PTR.store(pm1a_ctrl_blk.address, Ordering::Relaxed);
+
+    // Reserve the control block if it lives in the I/O space.
+    if pm1a_ctrl_blk.address_space == AddressSpace::SystemIo {
+        portio::reserve("ACPI", pm1a_ctrl_blk.address as u16, 2).ok();
+    }
+
+    // The table is packed, so the flags are copied out before use.
+    let (flags, boot_arch) = (sdt.flags, sdt.iapc_boot_arch);
+    // The boot architecture flags are reserved before revision 2, where the legacy devices are assumed.
+    let has_boot_arch = sdt.header().revision >= 2;
+    INFO.try_init_once(
+        || Info {
+            sci_interrupt: sdt.sci_interrupt,
+            smi_cmd_port: sdt.smi_cmd_port,
+            pm1a_event_block: sdt.pm1a_event_block().ok(),
+            pm1a_control_block: Some(pm1a_ctrl_blk),
+            pm_timer_block: sdt.pm_timer_block().ok().flatten(),
+            pm_timer_32_bit: flags.pm_timer_is_32_bit(),
+            century: sdt.century,
+            legacy_devices: boot_arch.legacy_devices_are_accessible(),
+            has_8042: !has_boot_arch || boot_arch.motherboard_implements_8042(),
+            has_cmos_rtc: !has_boot_arch || !boot_arch.use_time_and_alarm_namespace_for_rtc(),
+            hardware_reduced: flags.system_is_hw_reduced_acpi(),
+            reset_supported: flags.supports_system_reset_via_fadt(),
+            reset_register: sdt.reset_register().ok(),
+            reset_value: sdt.reset_value,
+        }
+    ).ok();
 
     Ok(())
 }
 
+/// Returns the summary of the table, or none if the ACPI is not initialized.
+pub fn info() -> Option<&'static Info> { INFO.try_get().ok() }
+
 /// Returns the `ACPI Enable` register value.
 pub fn acpi_enable() -> u8 { ACPI_ENABLE.load(Ordering::Relaxed) }
 
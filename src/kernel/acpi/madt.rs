@@ -20,22 +20,178 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use alloc::vec::Vec;
+
 use acpi::AcpiError;
 use acpi::InterruptModel;
 use acpi::madt::Madt;
+use acpi::platform::interrupt::{Apic, LocalInterruptLine, NmiProcessor, Polarity, TriggerMode};
 use acpi::platform::ProcessorInfo;
 use conquer_once::spin::OnceCell;
 
+/////////////////
+// Attributes
+/////////////////
+
+/// Number of legacy ISA interrupt lines.
+pub const ISA_IRQ_COUNT: u8 = 16;
+
 ///////////////////
 // Cached Values
 ///////////////////
 
 static INTERRUPT_MODEL: OnceCell<Option<InterruptModel>> = OnceCell::uninit();
 static PROCESSOR_INFO: OnceCell<Option<ProcessorInfo>> = OnceCell::uninit();
+static ROUTING: OnceCell<Routing> = OnceCell::uninit();
+
+//////////////
+/// Signal
+//////////////
+/// Electrical characteristics of an interrupt line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signal {
+    /// Whether the line is asserted low instead of high.
+    pub active_low: bool,
+    /// Whether the line is level-triggered instead of edge-triggered.
+    pub level_triggered: bool,
+}
+
+impl Signal {
+    /// Signal of the ISA bus: active high and edge-triggered.
+    pub const ISA: Signal = Signal { active_low: false, level_triggered: false };
+
+    /// Resolves the flags of an MADT entry, substituting the ISA defaults for bus-conforming ones.
+    fn from_flags(polarity: &Polarity, trigger_mode: &TriggerMode) -> Self {
+        Self {
+            active_low: matches!(polarity, Polarity::ActiveLow),
+            level_triggered: matches!(trigger_mode, TriggerMode::Level),
+        }
+    }
+}
+
+/////////////
+/// Route
+/////////////
+/// Global system interrupt an ISA line is wired to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    /// Legacy ISA interrupt line.
+    pub irq: u8,
+    /// Global system interrupt the line is delivered on.
+    pub gsi: u32,
+    /// Electrical characteristics of the line.
+    pub signal: Signal,
+}
+
+//////////////////
+/// IO APIC
+//////////////////
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic {
+    /// Identifier of the IO APIC.
+    pub id: u8,
+    /// Physical address of the registers.
+    pub address: u32,
+    /// First global system interrupt served by the IO APIC.
+    pub gsi_base: u32,
+}
+
+/////////////////
+/// Local NMI
+/////////////////
+/// Local interrupt pin that is wired to the NMI signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalNmi {
+    /// Processor UID the entry applies to, or none if it applies to all of them.
+    pub processor: Option<u32>,
+    /// Index of the local interrupt pin, `LINT0` or `LINT1`.
+    pub pin: u8,
+}
+
+///////////////
+/// Routing
+///////////////
+/// Interrupt routing described by the MADT.
+#[derive(Debug, Clone)]
+pub struct Routing {
+    /// Physical address of the local APIC registers.
+    pub local_apic_address: u64,
+    /// Available IO APICs.
+    pub io_apics: Vec<IoApic>,
+    /// Routes of the ISA interrupt lines, with the overrides applied.
+    pub routes: [Route; ISA_IRQ_COUNT as usize],
+    /// Global system interrupts that are wired to the NMI signal.
+    pub nmi_sources: Vec<(u32, Signal)>,
+    /// Local interrupt pins that are wired to the NMI signal.
+    pub local_nmis: Vec<LocalNmi>,
+    /// Whether legacy PICs are present and must be masked.
+    pub has_legacy_pics: bool,
+}
+
+impl Routing {
+    /// Builds the routing from the interrupt model.
+    ///
+    /// ISA lines are identity-mapped to global system interrupts unless an override says otherwise.
+    fn new(apic: &Apic) -> Self {
+        let mut routes = [Route { irq: 0, gsi: 0, signal: Signal::ISA }; ISA_IRQ_COUNT as usize];
+        for (irq, route) in routes.iter_mut().enumerate() {
+            route.irq = irq as u8;
+            route.gsi = irq as u32;
+        }
+        for iso in apic.interrupt_source_overrides.iter() {
+            if let Some(route) = routes.get_mut(iso.isa_source as usize) {
+                route.gsi = iso.global_system_interrupt;
+                route.signal = Signal::from_flags(&iso.polarity, &iso.trigger_mode);
+            }
+        }
+
+        Self {
+            local_apic_address: apic.local_apic_address,
+            io_apics: apic.io_apics.iter()
+                .map(|io_apic| IoApic {
+                    id: io_apic.id,
+                    address: io_apic.address,
+                    gsi_base: io_apic.global_system_interrupt_base,
+                })
+                .collect(),
+            routes,
+            nmi_sources: apic.nmi_sources.iter()
+                .map(|source| (source.global_system_interrupt, Signal::from_flags(&source.polarity, &source.trigger_mode)))
+                .collect(),
+            local_nmis: apic.local_apic_nmi_lines.iter()
+                .map(|line| LocalNmi {
+                    processor: match line.processor {
+                        NmiProcessor::All => None,
+                        NmiProcessor::ProcessorUid(uid) => Some(uid),
+                    },
+                    pin: match line.line {
+                        LocalInterruptLine::Lint0 => 0,
+                        LocalInterruptLine::Lint1 => 1,
+                    },
+                })
+                .collect(),
+            has_legacy_pics: apic.also_has_legacy_pics,
+        }
+    }
+
+    /// Returns the route of the given ISA interrupt line.
+    pub fn route(&self, irq: u8) -> Option<&Route> { self.routes.get(irq as usize) }
+
+    /// Returns the local interrupt pins wired to the NMI signal on the given processor.
+    pub fn local_nmi_pins(&self, processor_uid: u32) -> impl Iterator<Item=u8> + '_ {
+        self.local_nmis.iter()
+            .filter(move |nmi| nmi.processor.map_or(true, |uid| uid == processor_uid))
+            .map(|nmi| nmi.pin)
+    }
+}
 
 pub(super) fn read(sdt: &Madt) -> Result<(), AcpiError> {
     let (interrupt_model, processor_info) = sdt.parse_interrupt_model()?;
 
+    if let InterruptModel::Apic(apic) = &interrupt_model {
+        ROUTING.try_init_once(|| Routing::new(apic)).ok();
+    }
+
     INTERRUPT_MODEL.try_init_once(
         || { Some(interrupt_model) }
     ).expect("failed to initialize interrupt model");
@@ -50,3 +206,6 @@ pub(super) fn read(sdt: &Madt) -> Result<(), AcpiError> {
 pub fn get_interrupt_model() -> Option<&'static InterruptModel> { INTERRUPT_MODEL.try_get().unwrap_or(&None).as_ref() }
 
 pub fn get_processor_info() -> Option<&'static ProcessorInfo> { PROCESSOR_INFO.try_get().unwrap_or(&None).as_ref() }
+
+/// Returns the interrupt routing, or none if the firmware does not describe an APIC.
+pub fn get_routing() -> Option<&'static Routing> { ROUTING.try_get().ok() }
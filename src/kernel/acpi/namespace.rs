@@ -0,0 +1,220 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use aml::{AmlContext, AmlError, AmlName, AmlValue, LevelType};
+use aml::pci_routing::{Pin, PciRoutingTable};
+use aml::resource;
+use aml::resource::{InterruptPolarity, InterruptTrigger};
+use aml::value::Args;
+use conquer_once::spin::OnceCell;
+
+pub use aml::resource::Resource;
+
+use crate::kernel::error::Error;
+
+use super::dsdt;
+use super::madt::Signal;
+
+// ACPI Namespace
+//
+// Devices that cannot be discovered by probing a bus, such as the embedded controller or the legacy
+// ISA peripherals, are described by the firmware in the namespace instead. Each device object names
+// its hardware ID (_HID) and the resources it currently decodes (_CRS), which drivers use to locate
+// their registers and interrupts.
+//
+// Hardware IDs are either strings or 32-bit EISA IDs, which pack a three-letter vendor code and a
+// 16-bit product number; the latter are converted to their usual "PNP0303" form.
+//
+// The namespace also describes how the INTx pins of PCI devices are wired. Each PCI root bridge has a
+// routing table (_PRT) mapping a slot and pin either directly to a GSI or to a link device, whose _CRS
+// names the interrupt. Since the wiring differs between the legacy PICs and the IO APIC, the firmware
+// is told which one is in use through \_PIC before any table is read.
+
+///////////////
+// Constants
+///////////////
+
+/// Hardware IDs of the PCI and PCI Express root bridges.
+const ROOT_BRIDGE_IDS: [&str; 2] = ["PNP0A03", "PNP0A08"];
+
+///////////////////
+// Cached Values
+///////////////////
+
+/// Routing tables of the PCI root bridges along with the number of the bus each one spans.
+static ROOT_BRIDGES: OnceCell<Vec<(u8, PciRoutingTable)>> = OnceCell::uninit();
+
+////////////
+/// Node
+////////////
+#[derive(Debug)]
+pub struct Node {
+    /// Absolute path of the device object.
+    pub path: String,
+    /// Hardware ID, if given.
+    pub hid: Option<String>,
+    /// Unique ID distinguishing devices with the same hardware ID, if given.
+    pub uid: Option<u64>,
+    /// Whether the device is present.
+    pub present: bool,
+    /// Resources currently assigned to the device.
+    pub resources: Vec<Resource>,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the device objects in the namespace, or none if the AML tables were not parsed.
+pub fn devices() -> Vec<Node> {
+    dsdt::with_context(
+        |aml| {
+            let mut paths = Vec::new();
+            aml.namespace.traverse(
+                |path, level| match level.typ {
+                    LevelType::Device => {
+                        paths.push(path.clone());
+                        Ok(true)
+                    }
+                    LevelType::Scope => Ok(true),
+                    _ => Ok(false),
+                }
+            ).ok();
+
+            paths.iter().map(|path| describe(aml, path)).collect()
+        }
+    ).unwrap_or_default()
+}
+
+/// Returns the present devices with the given hardware ID.
+pub fn find_by_hid(hid: &str) -> Vec<Node> {
+    devices().into_iter()
+        .filter(|node| node.present && node.hid.as_deref().map_or(false, |id| id.eq_ignore_ascii_case(hid)))
+        .collect()
+}
+
+/// Evaluates the object at the given absolute path, running it if it is a method.
+pub fn evaluate(path: &str) -> Result<AmlValue, Error> {
+    let name = AmlName::from_str(path)?;
+    dsdt::with_context(|aml| aml.invoke_method(&name, Args::default()))
+        .ok_or(Error::Unsupported)?
+        .map_err(Error::from)
+}
+
+/// Tells the firmware whether interrupts are delivered through the IO APIC or the legacy PICs.
+pub fn set_interrupt_model(apic: bool) -> Result<(), Error> {
+    let name = AmlName::from_str("\\_PIC")?;
+    let args = Args::from_list(vec![AmlValue::Integer(apic as u64)])?;
+    match dsdt::with_context(|aml| aml.invoke_method(&name, args)) {
+        // The method is optional on firmware that only supports a single model.
+        Some(Ok(_)) | Some(Err(AmlError::ValueDoesNotExist(_))) | None => Ok(()),
+        Some(Err(e)) => Err(Error::from(e)),
+    }
+}
+
+/// Returns the GSI and signal the given INTx pin (1 to 4) of a function on a root bus is wired to.
+///
+/// Note: Functions behind PCI bridges have to be resolved to the slot of the bridge on the root bus first.
+pub fn pci_route(bus: u8, device: u8, function: u8, pin: u8) -> Option<(u32, Signal)> {
+    let pin = match pin {
+        1 => Pin::IntA,
+        2 => Pin::IntB,
+        3 => Pin::IntC,
+        4 => Pin::IntD,
+        _ => return None,
+    };
+
+    let (_, table) = root_bridges().iter().find(|(base, _)| *base == bus)?;
+    let irq = dsdt::with_context(|aml| table.route(device as u16, function as u16, pin, aml))?.ok()?;
+
+    let signal = Signal {
+        active_low: irq.polarity == InterruptPolarity::ActiveLow,
+        level_triggered: irq.trigger == InterruptTrigger::Level,
+    };
+    Some((irq.irq, signal))
+}
+
+/// Returns the routing tables of the root bridges, reading them on first use.
+fn root_bridges() -> &'static [(u8, PciRoutingTable)] {
+    ROOT_BRIDGES.get_or_init(
+        || {
+            let bridges = devices().into_iter()
+                .filter(|node| node.present && node.hid.as_deref().map_or(false, |hid| ROOT_BRIDGE_IDS.contains(&hid)));
+
+            let mut tables = Vec::new();
+            for node in bridges {
+                let loaded = dsdt::with_context(
+                    |aml| {
+                        let path = AmlName::from_str(&node.path)?;
+                        // The bridge spans bus 0 unless it gives a base bus number.
+                        let bus = child(aml, &path, "_BBN").and_then(|value| value.as_integer(aml)).unwrap_or(0);
+                        let prt = AmlName::from_str("_PRT")?.resolve(&path)?;
+                        Ok::<_, AmlError>((bus as u8, PciRoutingTable::from_prt_path(&prt, aml)?))
+                    }
+                );
+                if let Some(Ok(entry)) = loaded { tables.push(entry); }
+            }
+            tables
+        }
+    ).as_slice()
+}
+
+/// Builds the node of the device at the given path.
+fn describe(aml: &mut AmlContext, path: &AmlName) -> Node {
+    let present = match child(aml, path, "_STA") {
+        Ok(value) => value.as_status().map_or(true, |status| status.present),
+        // Devices without _STA are always present.
+        Err(_) => true,
+    };
+
+    let hid = child(aml, path, "_HID").ok().and_then(
+        |value| match value {
+            AmlValue::Integer(id) => Some(eisa_id(id as u32)),
+            value => value.as_string(aml).ok(),
+        }
+    );
+    let uid = child(aml, path, "_UID").ok().and_then(|value| value.as_integer(aml).ok());
+    let resources = child(aml, path, "_CRS").ok()
+        .and_then(|value| resource::resource_descriptor_list(&value).ok())
+        .unwrap_or_default();
+
+    Node { path: path.as_string(), hid, uid, present, resources }
+}
+
+/// Evaluates the given child object of a device.
+fn child(aml: &mut AmlContext, path: &AmlName, name: &str) -> Result<AmlValue, AmlError> {
+    let name = AmlName::from_str(name)?.resolve(path)?;
+    aml.invoke_method(&name, Args::default())
+}
+
+/// Converts the given compressed EISA ID to its textual form.
+fn eisa_id(id: u32) -> String {
+    // The ID is stored big-endian: three 5-bit letters followed by four hex digits.
+    let id = id.swap_bytes();
+    let letter = |shift: u32| (b'@' + ((id >> shift) & 0x1F) as u8) as char;
+    format!("{}{}{}{:04X}", letter(26), letter(21), letter(16), id & 0xFFFF)
+}
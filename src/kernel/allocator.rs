@@ -20,43 +20,304 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use alloc::alloc::Layout;
+use alloc::alloc::{GlobalAlloc, Layout};
+use core::str::FromStr;
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
 
-use bootloader::BootInfo;
 use spin::{Mutex, MutexGuard};
 use x86_64::structures::paging::{FrameAllocator, Mapper};
 use x86_64::structures::paging::{Page, PageTableFlags, Size4KiB};
-use x86_64::structures::paging::mapper::MapToError;
 use x86_64::VirtAddr;
 
+pub use arena::Arena;
 pub use bump::BumpAllocator;
+pub use guard::GuardAllocator;
 pub use linked_list::LinkedListAllocator;
 pub use pool::PoolAllocator;
 
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
 use crate::kernel::memory;
+use crate::kernel::memory::frame::GlobalFrameAllocator;
+use crate::kernel::memory::layout;
+use crate::kernel::memory::pressure;
+use crate::kernel::memory::pressure::Resource;
 
+mod arena;
 mod bump;
+#[cfg(feature = "heap-debug")]
+pub mod debug;
+pub mod fallible;
+mod guard;
 mod linked_list;
 mod pool;
+#[cfg(feature = "heap-redzone")]
+mod redzone;
 
 ////////////////
 // Attributes
 ////////////////
 
 /// Start address of the the heap in the virtual space.
-pub const HEAP_START: usize = 0x4444_4444_0000;
+pub const HEAP_START: usize = layout::HEAP_START;
 /// Size of heap.
 pub const HEAP_SIZE: usize = 0x100000;
 /// End address of heap in the virtual space.
 pub const HEAP_END: usize = HEAP_START + HEAP_SIZE;
 
+/// Start address of the heap of the linked list strategy, mapped once the strategy is selected.
+pub const LINKED_LIST_START: usize = HEAP_START + 0x1000_0000;
+/// Start address of the heap of the bump strategy, mapped once the strategy is selected.
+pub const BUMP_START: usize = HEAP_START + 0x2000_0000;
+
+/// Number of available strategies.
+pub const STRATEGY_COUNT: usize = 4;
+
 ///////////////////////
 // Global Interfaces
 ///////////////////////
 
 /// A global interface for memory allocator.
+#[cfg(not(feature = "heap-debug"))]
+#[global_allocator]
+static ALLOCATOR: Dispatcher = Dispatcher::new();
+
+/// A global interface for memory allocator, validating every free.
+#[cfg(feature = "heap-debug")]
 #[global_allocator]
-static ALLOCATOR: Locked<PoolAllocator> = Locked::new(PoolAllocator::new());
+static ALLOCATOR: debug::Checked<Dispatcher> = debug::Checked::new(Dispatcher::new());
+
+////////////////
+/// Strategy
+////////////////
+/// Allocator that serves new allocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Strategy {
+    /// Fixed-size blocks, falling back to a linked list for large allocations.
+    Pool = 0x0,
+    /// First fit over a list of free regions.
+    LinkedList = 0x1,
+    /// Pointer bumping, reclaiming memory only once everything is freed.
+    Bump = 0x2,
+    /// Dedicated pages flanked by unmapped guard pages.
+    Guard = 0x3,
+}
+
+impl Strategy {
+    /// Creates a new object from enum index.
+    pub fn from_index(idx: u8) -> Option<Self> {
+        match idx {
+            0x0 => Some(Self::Pool),
+            0x1 => Some(Self::LinkedList),
+            0x2 => Some(Self::Bump),
+            0x3 => Some(Self::Guard),
+            _ => None,
+        }
+    }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Pool => "pool",
+            Self::LinkedList => "linked_list",
+            Self::Bump => "bump",
+            Self::Guard => "guard",
+        }
+    }
+}
+
+impl Strategy {
+    /// Returns the strategy that served the allocation at the given address.
+    fn owning(addr: usize) -> Self {
+        if GuardAllocator::contains(addr) {
+            Self::Guard
+        } else if (BUMP_START..BUMP_START + HEAP_SIZE).contains(&addr) {
+            Self::Bump
+        } else if (LINKED_LIST_START..LINKED_LIST_START + HEAP_SIZE).contains(&addr) {
+            Self::LinkedList
+        } else {
+            Self::Pool
+        }
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pool" => Ok(Self::Pool),
+            "linked_list" => Ok(Self::LinkedList),
+            "bump" => Ok(Self::Bump),
+            "guard" => Ok(Self::Guard),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/////////////
+/// Stats
+/////////////
+/// Usage of a strategy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    /// Number of allocations served.
+    pub allocations: usize,
+    /// Number of allocations released.
+    pub frees: usize,
+    /// Number of allocations that could not be served.
+    pub failures: usize,
+    /// Number of bytes currently allocated.
+    pub bytes: usize,
+    /// Highest number of bytes allocated at once.
+    pub peak: usize,
+}
+
+////////////////
+/// Counters
+////////////////
+struct Counters {
+    allocations: AtomicUsize,
+    frees: AtomicUsize,
+    failures: AtomicUsize,
+    bytes: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl Counters {
+    /// Creates a new object.
+    const fn new() -> Self {
+        Counters {
+            allocations: AtomicUsize::new(0),
+            frees: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records an allocation attempt.
+    fn allocated(&self, ptr: *mut u8, size: usize) {
+        if ptr.is_null() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        let bytes = self.bytes.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(bytes, Ordering::Relaxed);
+    }
+
+    /// Records a free.
+    fn freed(&self, size: usize) {
+        self.frees.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    /// Returns a snapshot of the counters.
+    fn snapshot(&self) -> Stats {
+        Stats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            frees: self.frees.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+        }
+    }
+}
+
+//////////////////
+/// Dispatcher
+//////////////////
+/// Forwards allocations to the selected strategy.
+///
+/// Each strategy owns a distinct region of the virtual space, so frees are routed by address and the
+/// strategy can be switched at any time, even with allocations outstanding.
+pub(crate) struct Dispatcher {
+    strategy: AtomicU8,
+    pool: Locked<PoolAllocator>,
+    linked_list: Locked<LinkedListAllocator>,
+    bump: Locked<BumpAllocator>,
+    guard: Locked<GuardAllocator>,
+    counters: [Counters; STRATEGY_COUNT],
+}
+
+impl Dispatcher {
+    /// Creates a new object.
+    const fn new() -> Self {
+        Dispatcher {
+            strategy: AtomicU8::new(Strategy::Pool as u8),
+            pool: Locked::new(PoolAllocator::new()),
+            linked_list: Locked::new(LinkedListAllocator::new()),
+            bump: Locked::new(BumpAllocator::new()),
+            guard: Locked::new(GuardAllocator::new()),
+            counters: [Counters::new(), Counters::new(), Counters::new(), Counters::new()],
+        }
+    }
+}
+
+impl Dispatcher {
+    /// Allocates memory with the given strategy.
+    unsafe fn alloc_with(&self, strategy: Strategy, layout: Layout) -> *mut u8 {
+        match strategy {
+            Strategy::Pool => self.pool_alloc(layout),
+            Strategy::LinkedList => self.linked_list.alloc(layout),
+            Strategy::Bump => self.bump.alloc(layout),
+            Strategy::Guard => self.guard.alloc(layout),
+        }
+    }
+
+    /// Allocates memory from the pool, flanked by redzones with the `heap-redzone` feature.
+    unsafe fn pool_alloc(&self, layout: Layout) -> *mut u8 {
+        #[cfg(feature = "heap-redzone")]
+        return match redzone::expand(layout) {
+            Some(expanded) => {
+                let block = self.pool.alloc(expanded);
+                if block.is_null() { block } else { redzone::arm(block, layout) }
+            }
+            None => core::ptr::null_mut(),
+        };
+
+        #[cfg(not(feature = "heap-redzone"))]
+        self.pool.alloc(layout)
+    }
+
+    /// Releases memory to the pool, along with its redzones with the `heap-redzone` feature.
+    unsafe fn pool_dealloc(&self, ptr: *mut u8, layout: Layout) {
+        #[cfg(feature = "heap-redzone")]
+        if let Some(expanded) = redzone::expand(layout) {
+            self.pool.dealloc(redzone::disarm(ptr, layout), expanded);
+        }
+
+        #[cfg(not(feature = "heap-redzone"))]
+        self.pool.dealloc(ptr, layout);
+    }
+}
+
+unsafe impl GlobalAlloc for Dispatcher {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let strategy = Strategy::from_index(self.strategy.load(Ordering::Relaxed)).unwrap_or(Strategy::Pool);
+        let mut ptr = self.alloc_with(strategy, layout);
+        if ptr.is_null() {
+            // Give the shrinkers a chance before failing; the guard strategy runs out of frames rather than heap.
+            let resource = if strategy == Strategy::Guard { Resource::Frames } else { Resource::Heap };
+            if pressure::reclaim(resource) > 0 { ptr = self.alloc_with(strategy, layout); }
+        }
+        self.counters[strategy as usize].allocated(ptr, layout.size());
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let strategy = Strategy::owning(ptr as usize);
+        match strategy {
+            Strategy::Pool => self.pool_dealloc(ptr, layout),
+            Strategy::LinkedList => self.linked_list.dealloc(ptr, layout),
+            Strategy::Bump => self.bump.dealloc(ptr, layout),
+            Strategy::Guard => self.guard.dealloc(ptr, layout),
+        }
+        self.counters[strategy as usize].freed(layout.size());
+    }
+}
 
 //////////////
 /// Locked
@@ -81,37 +342,116 @@ impl<A> Locked<A> {
 #[alloc_error_handler]
 fn alloc_error_handler(layout: Layout) -> ! { panic!("allocation failure: {:?}", layout) }
 
+////////////
+// Device
+////////////
+
+/// Device descriptor of the heap allocator.
+pub(crate) static DEVICE: Device = Device {
+    name: "Allocator",
+    class: Class::Memory,
+    stage: Stage::Core,
+    critical: true,
+    depends: &[],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
 ///////////////
 // Utilities
 ///////////////
 
 /// Initializes the heap using a memory mapper and frame allocator.
-pub(crate) fn init(boot_info: &'static BootInfo) -> Result<(), MapToError<Size4KiB>> {
+pub(crate) fn init() -> Result<(), Error> {
+    let mut frame_allocator = unsafe { memory::BootInfoFrameAllocator::new(memory::memory_map()) };
+
+    map_region(HEAP_START, HEAP_SIZE, &mut frame_allocator)?;
+    unsafe { ALLOCATOR.pool.lock().init(HEAP_START, HEAP_SIZE) };
+
+    // Hand the remaining physical memory over to the frame allocator now that the heap is ready.
+    memory::frame::init(frame_allocator);
+
+    Ok(())
+}
+
+/// Maps the given region of the virtual space to physical frames.
+fn map_region(start: usize, size: usize, frame_allocator: &mut impl FrameAllocator<Size4KiB>) -> Result<(), Error> {
     let mut mapper = unsafe { memory::mapper() };
-    let mut frame_allocator = unsafe { memory::BootInfoFrameAllocator::new(&boot_info.memory_map) };
 
     let page_range = {
-        let heap_start = VirtAddr::new(HEAP_START as u64);
-        let heap_end = VirtAddr::new(HEAP_END as u64);
-        let heap_start_page = Page::containing_address(heap_start);
-        let heap_end_page = Page::containing_address(heap_end);
-        Page::range_inclusive(heap_start_page, heap_end_page)
+        let start_page = Page::containing_address(VirtAddr::new(start as u64));
+        let end_page = Page::containing_address(VirtAddr::new((start + size - 1) as u64));
+        Page::range_inclusive(start_page, end_page)
     };
 
     // Map each page to a physical frame.
     for page in page_range {
-        let frame = frame_allocator.allocate_frame().ok_or(MapToError::FrameAllocationFailed)?;
+        let frame = frame_allocator.allocate_frame().ok_or(Error::OutOfMemory)?;
         let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
         unsafe {
-            mapper.map_to(page, frame, flags, &mut frame_allocator)?.flush();
+            mapper.map_to(page, frame, flags, frame_allocator)?.flush();
         }
     }
 
-    unsafe { ALLOCATOR.lock().init(HEAP_START, HEAP_SIZE) };
+    Ok(())
+}
+
+/// Returns the number of bytes of the heap that are in use.
+///
+/// Note: Blocks that are cached by the pool for reuse are counted as in use.
+pub fn used() -> usize { ALLOCATOR.pool.lock().used() }
+
+/// Returns the strategy serving new allocations.
+pub fn strategy() -> Strategy {
+    Strategy::from_index(ALLOCATOR.strategy.load(Ordering::Relaxed)).unwrap_or(Strategy::Pool)
+}
+
+/// Selects the strategy serving new allocations, setting up its heap on first use.
+///
+/// Note: Existing allocations are still released to the strategy that served them.
+pub fn set_strategy(strategy: Strategy) -> Result<(), Error> {
+    // A heap is set up once, and its mapping records that it has been.
+    let is_mapped = |start: usize| memory::virt_to_phys_addr(VirtAddr::new(start as u64)).is_some();
+    match strategy {
+        Strategy::LinkedList if !is_mapped(LINKED_LIST_START) => {
+            map_region(LINKED_LIST_START, HEAP_SIZE, &mut GlobalFrameAllocator)?;
+            unsafe { ALLOCATOR.linked_list.lock().init(LINKED_LIST_START, HEAP_SIZE) };
+        }
+        Strategy::Bump if !is_mapped(BUMP_START) => {
+            map_region(BUMP_START, HEAP_SIZE, &mut GlobalFrameAllocator)?;
+            ALLOCATOR.bump.lock().init(BUMP_START, HEAP_SIZE);
+        }
+        _ => {}
+    }
 
+    ALLOCATOR.strategy.store(strategy as u8, Ordering::Relaxed);
     Ok(())
 }
 
+/// Returns the usage of the given strategy.
+pub fn stats(strategy: Strategy) -> Stats { ALLOCATOR.counters[strategy as usize].snapshot() }
+
+/// Returns whether the given address lies on a guard page of the guard allocator.
+pub fn is_guard_page(addr: usize) -> bool {
+    GuardAllocator::contains(addr) && memory::virt_to_phys_addr(VirtAddr::new(addr as u64)).is_none()
+}
+
+/// Runs the given closure, attributing the allocations it makes to the given tag.
+///
+/// Note: Tags are only recorded with the `heap-debug` feature.
+pub fn tagged<R>(tag: &'static str, f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "heap-debug")]
+    return debug::tagged(tag, f);
+
+    #[cfg(not(feature = "heap-debug"))]
+    {
+        let _ = tag;
+        f()
+    }
+}
+
 /// Align the given address `addr` upwards to alignment `align`.
 ///
 /// Note: Requires that `align` is a power of two.
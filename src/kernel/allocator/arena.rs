@@ -0,0 +1,121 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::NonNull;
+use core::{ptr, slice, str};
+
+use x86_64::structures::paging::PhysFrame;
+
+use crate::kernel::error::Error;
+use crate::kernel::memory;
+use crate::kernel::memory::frame;
+use crate::kernel::memory::PAGE_SIZE;
+
+use super::{BumpAllocator, Locked};
+
+// Arena
+//
+// Jobs such as parsing firmware tables or loading an executable make many small allocations that all
+// die together once the job is done. Serving them from the heap leaves it fragmented, so an arena
+// serves them instead: it bumps a pointer through frames of its own and gives them all back at once
+// when dropped.
+//
+// The frames are physically contiguous and accessed through the physical memory mapping, so creating
+// an arena does not touch the page tables. Destructors of the values placed in an arena are not run.
+
+/////////////
+/// Arena
+/////////////
+pub struct Arena {
+    bump: Locked<BumpAllocator>,
+    start: PhysFrame,
+    frames: usize,
+}
+
+// The arena exclusively owns its frames.
+unsafe impl Send for Arena {}
+
+impl Arena {
+    /// Creates a new object with room for at least the given number of bytes.
+    pub fn new(size: usize) -> Result<Self, Error> {
+        let frames = ((size + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+        let start = frame::allocate_contiguous(frames, 1, None).ok_or(Error::OutOfMemory)?;
+
+        let arena = Arena { bump: Locked::new(BumpAllocator::new()), start, frames };
+        arena.bump.lock().init(arena.base(), arena.capacity());
+
+        Ok(arena)
+    }
+
+    /// Returns the number of bytes the arena can hold.
+    pub fn capacity(&self) -> usize { self.frames * PAGE_SIZE }
+
+    /// Returns the number of bytes handed out so far, including alignment padding.
+    pub fn used(&self) -> usize { self.bump.lock().used() }
+
+    /// Allocates memory for the given layout.
+    pub fn alloc(&self, layout: Layout) -> Result<NonNull<u8>, Error> {
+        let ptr = unsafe { self.bump.alloc(layout) };
+        NonNull::new(ptr).ok_or(Error::OutOfMemory)
+    }
+
+    /// Moves the given value into the arena.
+    pub fn alloc_value<T>(&self, value: T) -> Result<&mut T, Error> {
+        let ptr = self.alloc(Layout::new::<T>())?.cast::<T>().as_ptr();
+        unsafe {
+            ptr.write(value);
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// Copies the given slice into the arena.
+    pub fn alloc_slice<T: Copy>(&self, src: &[T]) -> Result<&mut [T], Error> {
+        let layout = Layout::array::<T>(src.len()).map_err(|_| Error::OutOfMemory)?;
+        let ptr = self.alloc(layout)?.cast::<T>().as_ptr();
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            Ok(slice::from_raw_parts_mut(ptr, src.len()))
+        }
+    }
+
+    /// Copies the given string into the arena.
+    pub fn alloc_str(&self, src: &str) -> Result<&mut str, Error> {
+        let bytes = self.alloc_slice(src.as_bytes())?;
+        Ok(unsafe { str::from_utf8_unchecked_mut(bytes) })
+    }
+
+    /// Releases everything allocated so far, keeping the frames for reuse.
+    pub fn reset(&mut self) {
+        let (base, capacity) = (self.base(), self.capacity());
+        self.bump.lock().init(base, capacity);
+    }
+
+    /// Returns the virtual address of the first byte.
+    fn base(&self) -> usize { memory::phys_to_virt_addr(self.start.start_address()).as_u64() as usize }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        unsafe { frame::deallocate_contiguous(self.start, self.frames) };
+    }
+}
@@ -47,6 +47,9 @@ impl BumpAllocator {
         }
     }
 
+    /// Returns the number of bytes handed out since the last reset.
+    pub fn used(&self) -> usize { self.next - self.heap_start }
+
     /// Initializes the allocator.
     pub fn init(&mut self, heap_start: usize, heap_size: usize) {
         self.heap_start = heap_start;
@@ -68,9 +71,9 @@ unsafe impl GlobalAlloc for Locked<BumpAllocator> {
         if alloc_end > bump.heap_end {
             ptr::null_mut()
         } else {
-            bump.next = alloc_end as usize;
+            bump.next = alloc_end;
             bump.allocations += 1;
-            bump.next as *mut u8
+            alloc_start as *mut u8
         }
     }
 
@@ -0,0 +1,291 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ops::Deref;
+use core::ptr;
+
+use spin::Mutex;
+
+#[cfg(feature = "heap-redzone")]
+use crate::kernel::pit;
+#[cfg(feature = "heap-redzone")]
+use super::redzone;
+
+// Heap Debugging
+//
+// With the `heap-debug` feature, the global allocator is wrapped in `Checked`, which keeps a map of
+// the live allocations along with the tag that was active when they were made. Every free is checked
+// against the map, so that freeing a pointer twice, freeing a pointer that was never handed out, or
+// freeing with a layout that differs from the allocation panics on the spot rather than corrupting
+// the heap for somebody else to trip over. Freed memory is overwritten with `FREE_POISON`, and fresh
+// memory with `ALLOC_POISON`, so that use-after-free and uninitialized reads stand out in a dump.
+//
+// The map lives outside the heap and has a fixed capacity. Once it overflows, allocations are no
+// longer tracked, and frees of unknown pointers can no longer be told apart from invalid ones.
+//
+// With the `heap-redzone` feature as well, the redzones around pool allocations are verified on
+// every free, and the scrub task verifies those of every tracked allocation once per interval. Either
+// way, an overwritten redzone panics with the tag of the allocation whose neighbour was clobbered.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of live allocations that are tracked.
+const CAPACITY: usize = 4096;
+/// Number of recent frees that are remembered to recognize double frees.
+const RECENT_FREES: usize = 64;
+/// Byte pattern written over freshly allocated memory.
+pub const ALLOC_POISON: u8 = 0xA5;
+/// Byte pattern written over freed memory.
+pub const FREE_POISON: u8 = 0xDE;
+/// Tag of the allocations made outside of any tagged scope.
+pub const DEFAULT_TAG: &str = "kernel";
+/// Tag reported for the allocations that are not tracked.
+#[cfg(feature = "heap-redzone")]
+const UNTRACKED_TAG: &str = "untracked";
+/// Time between two scrubs of the redzones, in seconds.
+#[cfg(feature = "heap-redzone")]
+const SCRUB_INTERVAL: f64 = 1.0;
+
+/////////////
+/// Entry
+/////////////
+#[derive(Clone, Copy)]
+struct Entry {
+    ptr: usize,
+    size: usize,
+    align: usize,
+    tag: &'static str,
+}
+
+impl Entry {
+    const EMPTY: Entry = Entry { ptr: 0, size: 0, align: 0, tag: DEFAULT_TAG };
+}
+
+///////////
+/// Map
+///////////
+struct Map {
+    entries: [Entry; CAPACITY],
+    recent: [usize; RECENT_FREES],
+    next_recent: usize,
+    overflowed: bool,
+    tag: &'static str,
+}
+
+impl Map {
+    /// Removes the allocation at the given address and returns it, or none if it is not tracked.
+    fn release(&mut self, addr: usize, layout: Layout) -> Result<Option<Entry>, Fault> {
+        match self.entries.iter_mut().find(|entry| entry.ptr == addr) {
+            Some(entry) if entry.size != layout.size() || entry.align != layout.align() => {
+                Err(Fault::Layout(*entry))
+            }
+            Some(entry) => {
+                let released = core::mem::replace(entry, Entry::EMPTY);
+                self.recent[self.next_recent] = addr;
+                self.next_recent = (self.next_recent + 1) % RECENT_FREES;
+                Ok(Some(released))
+            }
+            None if self.recent.contains(&addr) => Err(Fault::DoubleFree),
+            None if !self.overflowed => Err(Fault::Unknown),
+            None => Ok(None),
+        }
+    }
+}
+
+/////////////
+/// Fault
+/////////////
+enum Fault {
+    /// The address was freed already.
+    DoubleFree,
+    /// The address was never allocated.
+    Unknown,
+    /// The layout differs from the one of the allocation.
+    Layout(Entry),
+}
+
+/// Live allocations.
+static MAP: Mutex<Map> = Mutex::new(Map {
+    entries: [Entry::EMPTY; CAPACITY],
+    recent: [0; RECENT_FREES],
+    next_recent: 0,
+    overflowed: false,
+    tag: DEFAULT_TAG,
+});
+
+/////////////
+/// Usage
+/////////////
+/// Outstanding allocations of a tag.
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    /// Tag of the allocations.
+    pub tag: &'static str,
+    /// Number of allocations.
+    pub count: usize,
+    /// Total size of the allocations in bytes.
+    pub bytes: usize,
+}
+
+///////////////
+/// Checked
+///////////////
+/// Allocator wrapper that validates every free against the live allocations.
+pub struct Checked<A> {
+    inner: A,
+}
+
+impl<A> Checked<A> {
+    /// Creates a new object.
+    pub const fn new(inner: A) -> Self { Self { inner } }
+}
+
+impl<A> Deref for Checked<A> {
+    type Target = A;
+
+    fn deref(&self) -> &A { &self.inner }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for Checked<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if ptr.is_null() { return ptr; }
+
+        ptr::write_bytes(ptr, ALLOC_POISON, layout.size());
+
+        let mut map = MAP.lock();
+        let tag = map.tag;
+        let entry = Entry { ptr: ptr as usize, size: layout.size(), align: layout.align(), tag };
+        match map.entries.iter_mut().find(|entry| entry.ptr == 0) {
+            Some(slot) => *slot = entry,
+            None => map.overflowed = true,
+        }
+        // The address is live again, so a later free of it is not a double free.
+        map.recent.iter_mut().filter(|freed| **freed == ptr as usize).for_each(|freed| *freed = 0);
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // The lock is released before panicking, as the panic handler may allocate.
+        let released = MAP.lock().release(ptr as usize, layout);
+        let entry = match released {
+            Err(Fault::DoubleFree) => panic!("heap: double free of {:p} ({:?})", ptr, layout),
+            Err(Fault::Unknown) => panic!("heap: free of {:p}, which was never allocated ({:?})", ptr, layout),
+            Err(Fault::Layout(entry)) => panic!(
+                "heap: {:p} freed with size {} and alignment {}, but allocated by '{}' with size {} and alignment {}",
+                ptr, layout.size(), layout.align(), entry.tag, entry.size, entry.align
+            ),
+            Ok(entry) => entry,
+        };
+
+        #[cfg(feature = "heap-redzone")]
+        if let Some(offset) = redzone::check(ptr, layout) {
+            let tag = entry.map_or(UNTRACKED_TAG, |entry| entry.tag);
+            overwritten(ptr as usize, layout.size(), tag, offset);
+        }
+        #[cfg(not(feature = "heap-redzone"))]
+        let _ = entry;
+
+        ptr::write_bytes(ptr, FREE_POISON, layout.size());
+        self.inner.dealloc(ptr, layout);
+    }
+}
+
+/// Runs the given closure, attributing the allocations it makes to the given tag.
+pub fn tagged<R>(tag: &'static str, f: impl FnOnce() -> R) -> R {
+    let previous = core::mem::replace(&mut MAP.lock().tag, tag);
+    let result = f();
+    MAP.lock().tag = previous;
+    result
+}
+
+/// Calls the given closure with the outstanding allocations of each tag.
+pub fn for_each_tag<F>(mut f: F) where F: FnMut(Usage) {
+    // The usage is collected on the stack, as allocating would alter the map being walked.
+    const MAX_TAGS: usize = 64;
+    let mut usages = [Usage { tag: DEFAULT_TAG, count: 0, bytes: 0 }; MAX_TAGS];
+    let mut len = 0;
+
+    {
+        let map = MAP.lock();
+        for entry in map.entries.iter().filter(|entry| entry.ptr != 0) {
+            let slot = match usages[..len].iter().position(|usage| usage.tag == entry.tag) {
+                Some(idx) => idx,
+                None if len < MAX_TAGS => {
+                    usages[len] = Usage { tag: entry.tag, count: 0, bytes: 0 };
+                    len += 1;
+                    len - 1
+                }
+                None => continue,
+            };
+            usages[slot].count += 1;
+            usages[slot].bytes += entry.size;
+        }
+    }
+
+    usages[..len].iter().for_each(|usage| f(*usage));
+}
+
+/// Returns whether more allocations were made than could be tracked.
+pub fn has_overflowed() -> bool { MAP.lock().overflowed }
+
+/// Verifies the redzones of every tracked allocation, panicking on the first one overwritten.
+#[cfg(feature = "heap-redzone")]
+pub fn scrub() {
+    // The lock is released before panicking, as the panic handler may allocate.
+    let corrupted = {
+        let map = MAP.lock();
+        map.entries.iter().filter(|entry| entry.ptr != 0).find_map(
+            |entry| {
+                let layout = Layout::from_size_align(entry.size, entry.align).ok()?;
+                let offset = unsafe { redzone::check(entry.ptr as *const u8, layout) }?;
+                Some((*entry, offset))
+            }
+        )
+    };
+
+    if let Some((entry, offset)) = corrupted {
+        overwritten(entry.ptr, entry.size, entry.tag, offset);
+    }
+}
+
+/// Periodically verifies the redzones of the tracked allocations.
+#[cfg(feature = "heap-redzone")]
+pub async fn scrub_task() {
+    loop {
+        pit::delay(SCRUB_INTERVAL).await;
+        scrub();
+    }
+}
+
+/// Reports an overwritten redzone.
+#[cfg(feature = "heap-redzone")]
+fn overwritten(ptr: usize, size: usize, tag: &'static str, offset: isize) -> ! {
+    panic!(
+        "heap: redzone of {:#x} overwritten at offset {}, past an allocation of {} bytes by '{}'",
+        ptr, offset, size, tag
+    )
+}
@@ -0,0 +1,72 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::alloc::{alloc, Layout};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::kernel::error::Error;
+
+// Fallible Allocation
+//
+// An allocation through `Box::new`, `vec!` or `to_vec` that cannot be served ends up in the allocation
+// error handler, which panics. Code that handles data as it arrives, such as input or I/O, can do
+// better by dropping what does not fit, so the helpers below hand back `Error::OutOfMemory` instead.
+// They go through the shrinkers first, like any other allocation. Growing vectors and strings can use
+// `try_reserve` directly, its error converting into `Error::OutOfMemory` as well.
+
+/// Moves the value to the heap.
+pub fn try_box<T>(value: T) -> Result<Box<T>, Error> {
+    let layout = Layout::new::<T>();
+    // Zero-sized values take no memory.
+    if layout.size() == 0 { return Ok(Box::new(value)); }
+
+    unsafe {
+        let ptr = alloc(layout) as *mut T;
+        if ptr.is_null() { return Err(Error::OutOfMemory); }
+        ptr.write(value);
+        Ok(Box::from_raw(ptr))
+    }
+}
+
+/// Creates a vector holding `len` clones of the value.
+pub fn try_vec<T: Clone>(value: T, len: usize) -> Result<Vec<T>, Error> {
+    let mut vec = Vec::new();
+    vec.try_reserve_exact(len)?;
+    vec.resize(len, value);
+    Ok(vec)
+}
+
+/// Copies the slice into a new vector.
+pub fn try_to_vec<T: Clone>(slice: &[T]) -> Result<Vec<T>, Error> {
+    let mut vec = Vec::new();
+    vec.try_reserve_exact(slice.len())?;
+    vec.extend_from_slice(slice);
+    Ok(vec)
+}
+
+/// Appends the value to the vector.
+pub fn try_push<T>(vec: &mut Vec<T>, value: T) -> Result<(), Error> {
+    vec.try_reserve(1)?;
+    vec.push(value);
+    Ok(())
+}
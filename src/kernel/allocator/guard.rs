@@ -0,0 +1,144 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::alloc::GlobalAlloc;
+use core::alloc::Layout;
+use core::ptr;
+
+use x86_64::structures::paging::{Mapper, Page, PageTableFlags};
+use x86_64::VirtAddr;
+
+use crate::kernel::memory;
+use crate::kernel::memory::frame::{self, GlobalFrameAllocator};
+use crate::kernel::memory::layout;
+use crate::kernel::memory::PAGE_SIZE;
+
+use super::Locked;
+
+// Guard Allocator
+//
+// Every allocation gets pages of its own, placed so that the allocation ends exactly where an
+// unmapped guard page begins. Writing past the end of a buffer then faults on the very first byte
+// out of bounds, and the faulting address points at the culprit instead of at a corrupted neighbour
+// found much later. Virtual addresses are never reused, so accesses after a free fault as well.
+//
+// Each allocation costs at least two pages of address space and one frame, so this is only meant
+// for chasing memory corruption.
+
+////////////////
+// Attributes
+////////////////
+
+/// Start address of the guarded region in the virtual space.
+pub const GUARD_START: usize = layout::GUARD_START;
+/// End address of the guarded region in the virtual space.
+pub const GUARD_END: usize = layout::GUARD_END;
+
+///////////////////////
+/// Guard Allocator
+///////////////////////
+pub struct GuardAllocator {
+    next: usize,
+}
+
+impl GuardAllocator {
+    /// Creates a new object.
+    pub const fn new() -> Self {
+        // The first page is left unmapped to guard against underruns of the first allocation.
+        Self { next: GUARD_START + PAGE_SIZE }
+    }
+
+    /// Returns whether the given address lies within the guarded region.
+    pub fn contains(addr: usize) -> bool { (GUARD_START..GUARD_END).contains(&addr) }
+
+    /// Maps the given number of pages starting at the given address.
+    fn map(start: usize, pages: usize) -> bool {
+        let mut mapper = unsafe { memory::mapper() };
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+
+        for idx in 0..pages {
+            let page = Page::containing_address(VirtAddr::new((start + idx * PAGE_SIZE) as u64));
+            let frame = match frame::allocate() {
+                Some(frame) => frame,
+                None => {
+                    Self::unmap(start, idx);
+                    return false;
+                }
+            };
+            match unsafe { mapper.map_to(page, frame, flags, &mut GlobalFrameAllocator) } {
+                Ok(flush) => flush.flush(),
+                Err(_) => {
+                    unsafe { frame::deallocate(frame) };
+                    Self::unmap(start, idx);
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Unmaps the given number of pages starting at the given address and releases their frames.
+    fn unmap(start: usize, pages: usize) {
+        let mut mapper = unsafe { memory::mapper() };
+
+        for idx in 0..pages {
+            let page: Page = Page::containing_address(VirtAddr::new((start + idx * PAGE_SIZE) as u64));
+            if let Ok((frame, flush)) = mapper.unmap(page) {
+                flush.flush();
+                unsafe { frame::deallocate(frame) };
+            }
+        }
+    }
+}
+
+/// Returns the number of pages spanned by the given number of bytes.
+fn pages_for(bytes: usize) -> usize { (bytes + PAGE_SIZE - 1) / PAGE_SIZE }
+
+unsafe impl GlobalAlloc for Locked<GuardAllocator> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Alignments beyond a page would leave a gap before the guard page.
+        if layout.align() > PAGE_SIZE { return ptr::null_mut(); }
+
+        let mut guard = self.lock();
+
+        let size = layout.size().max(1);
+        let pages = pages_for(size);
+        let start = guard.next;
+        // One more page is skipped, which stays unmapped and guards the allocation.
+        let end = start + (pages + 1) * PAGE_SIZE;
+        if end > GUARD_END || !GuardAllocator::map(start, pages) { return ptr::null_mut(); }
+        guard.next = end;
+
+        // Place the allocation at the end of its pages, rounding down to honour the alignment.
+        let offset = (pages * PAGE_SIZE - size) & !(layout.align() - 1);
+        (start + offset) as *mut u8
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _guard = self.lock();
+
+        let start = ptr as usize & !(PAGE_SIZE - 1);
+        let pages = pages_for(ptr as usize + layout.size().max(1) - start);
+        GuardAllocator::unmap(start, pages);
+    }
+}
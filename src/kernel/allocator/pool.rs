@@ -65,6 +65,9 @@ impl PoolAllocator {
         self.fallback_allocator.init(heap_start as *mut u8, heap_end);
     }
 
+    /// Returns the number of bytes handed out by the fallback allocator.
+    pub fn used(&self) -> usize { self.fallback_allocator.used() }
+
     /// Allocates memory using fallback allocator.
     fn fallback_alloc(&mut self, layout: Layout) -> *mut u8 {
         match self.fallback_allocator.allocate_first_fit(layout) {
@@ -0,0 +1,88 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::alloc::Layout;
+use core::ptr;
+
+use super::Strategy;
+
+// Redzones
+//
+// With the `heap-redzone` feature, every allocation served by the pool is flanked by two redzones
+// filled with `PATTERN`. A write that runs a few bytes past either end of a buffer lands in one of
+// them instead of in the neighbouring block, and shows as a byte that no longer holds the pattern
+// when the allocation is freed or when the scrub task walks the live allocations. This catches the
+// common off-by-a-few overflows for a few dozen bytes per allocation, without the shadow memory
+// and instrumented accesses a full address sanitizer needs.
+//
+// The front redzone is as large as the alignment of the allocation, so that the pointer handed out
+// stays aligned. The other strategies are left as they are: the guard strategy already faults on the
+// first byte out of bounds, and the bump and linked list strategies are not meant for daily use.
+
+////////////////
+// Attributes
+////////////////
+
+/// Smallest size of a redzone.
+const SIZE: usize = 16;
+/// Byte pattern the redzones are filled with.
+pub const PATTERN: u8 = 0xFD;
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the layout of an allocation along with its redzones.
+pub(super) fn expand(layout: Layout) -> Option<Layout> {
+    let size = front(layout).checked_add(layout.size())?.checked_add(SIZE)?;
+    Layout::from_size_align(size, layout.align()).ok()
+}
+
+/// Fills the redzones of the block at the given address and returns the address handed out.
+pub(super) unsafe fn arm(block: *mut u8, layout: Layout) -> *mut u8 {
+    let ptr = block.add(front(layout));
+    ptr::write_bytes(block, PATTERN, front(layout));
+    ptr::write_bytes(ptr.add(layout.size()), PATTERN, SIZE);
+    ptr
+}
+
+/// Returns the address of the block the given allocation was carved from.
+pub(super) unsafe fn disarm(ptr: *mut u8, layout: Layout) -> *mut u8 { ptr.sub(front(layout)) }
+
+/// Returns the offset, relative to the allocation, of the overwritten byte of the redzones closest to
+/// it, or none if they are intact or the allocation has none.
+///
+/// Note: The allocation must be live, as the redzones of a freed block belong to the pool again.
+pub(super) unsafe fn check(ptr: *const u8, layout: Layout) -> Option<isize> {
+    if Strategy::owning(ptr as usize) != Strategy::Pool { return None; }
+
+    let before = core::slice::from_raw_parts(ptr.sub(front(layout)), front(layout));
+    if let Some(idx) = before.iter().rposition(|byte| *byte != PATTERN) {
+        return Some(idx as isize - before.len() as isize);
+    }
+
+    let after = core::slice::from_raw_parts(ptr.add(layout.size()), SIZE);
+    after.iter().position(|byte| *byte != PATTERN).map(|idx| (layout.size() + idx) as isize)
+}
+
+/// Returns the size of the redzone in front of an allocation.
+fn front(layout: Layout) -> usize { SIZE.max(layout.align()) }
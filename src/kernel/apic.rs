@@ -21,88 +21,97 @@
 // SOFTWARE.
 
 use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use ::acpi::InterruptModel;
 use x86_64::instructions::port::Port;
 use x86_64::PhysAddr;
 use x86_64::registers::model_specific::Msr;
 
-use crate::{omneity, print, println};
-use crate::kernel::{acpi, idt, memory, pics, pit};
+use crate::{omneity, print, println, warning};
+use crate::kernel::{acpi, idt, memory, pics};
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
 
 pub mod io;
 pub mod local;
 
+////////////
+// Device
+////////////
 
-pub(crate) fn init() -> Result<(), ()> {
+/// Device descriptor of the APIC.
+pub(crate) static DEVICE: Device = Device {
+    name: "APIC",
+    class: Class::System,
+    stage: Stage::Platform,
+    critical: false,
+    depends: &["ACPI", "PICS"],
+    tolerates_absent: false,
+    init: || {
+        init().map_err(|e| {
+            warning!("APIC: falling back to the legacy PICs");
+            e
+        })
+    },
+    suspend: None,
+    resume: None,
+};
+
+////////////
+// States
+////////////
+
+/// Flag to check whether the APIC has taken over from the legacy PICs.
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+///////////////
+// Utilities
+///////////////
+
+/// Initializes the APIC and disables the legacy PICs.
+///
+/// Note: The PICs are left untouched if the firmware does not describe an APIC, so that interrupts
+/// keep being delivered through them.
+pub(crate) fn init() -> Result<(), Error> {
+    let routing = match acpi::madt::get_routing() {
+        Some(routing) => routing,
+        None => return Err(Error::Unsupported),
+    };
+    let processor_uid = acpi::madt::get_processor_info().map_or(0, |info| info.boot_processor.processor_uid);
+
+    // Lines that are masked on the PICs stay masked once routed through the IO APIC.
+    let masked = unsafe {
+        let master = Port::<u8>::new(pics::M_DATA_PORT).read();
+        let slave = Port::<u8>::new(pics::S_DATA_PORT).read();
+        u16::from_le_bytes([master, slave])
+    };
     unsafe { pics::PIC_8259.lock().disable() };
 
-    let apic = acpi::madt::get_interrupt_model().unwrap();
-    let proc_info = acpi::madt::get_processor_info().unwrap();
-
-    pub const APIC_BASE: u32 = 0x1B;
-
-    match apic {
-        InterruptModel::Apic(apic) => unsafe {
-            local::init(&apic);
-            io::init(&apic);
-
-            // local_apic_out(base, LAPIC_TPR, 0);
-            //
-            // local_apic_out(base, LAPIC_TDCR, 0x3);
-            // local_apic_out(base, LAPIC_TICR, u32::MAX);
-            // // pit::sleep(0.01);
-            // local_apic_out(base, LAPIC_TIMER, 0);
-            //
-            // let TMR_PERIODIC = 0x20000;
-            //
-            // let ticks_in_10_ms = 0xFFFFFFFF - local_apic_in(base, LAPIC_TCCR);
-            // local_apic_out(base, LAPIC_TIMER, 32 | TMR_PERIODIC);
-            // local_apic_out(base, LAPIC_TDCR, 0x3);
-            // local_apic_out(base, LAPIC_TICR, ticks_in_10_ms);
-            //
-            // for (io_apic, iso) in apic.io_apics.iter().zip(apic.interrupt_source_overrides.iter()) {
-            //     let address = memory::phys_to_virt_addr(PhysAddr::new(io_apic.address as u64));
-            //     let address = address.as_u64();
-            //     let mut ioapic = x86::apic::ioapic::IoApic::new(address as usize);
-            //     omneity!("{:?}", (
-            //         ioapic.id(),
-            //         ioapic.version(),
-            //         ioapic.supported_interrupts(),
-            //     ));
-            //
-            //     for i in 0..ioapic.supported_interrupts() {
-            //         let val: u64 = (1u64 << 16) | (0x20u64 + i as u64);
-            //         io_apic_set_entry(address as usize, i, val);
-            //     }
-            //
-            //
-            //     // Get number of entries supported by the IO APIC
-            //     let x: u32 = io_apic_in(address as usize, IOAPICVER as u8);
-            //     let count: u32 = ((x >> 16) & 0xff) + 1;
-            //     // maximum redirection entry
-            //     omneity!("I/O APIC pins = {}", count);
-            //
-            //     let isr = 1; // keyboard
-            //     let mut entry = IOREDTBL + (isr * 2) as usize;
-            //     let mut entry_val = io_apic_in(address as usize, entry as u8);
-            //     omneity!("{:b}", entry_val);
-            //     entry_val &= !(1 << 16);
-            //     omneity!("{:b}", entry_val);
-            //     entry &= !0x700;
-            //     omneity!("{:b}", entry);
-            //     entry &= !0x800;
-            //     omneity!("{:b}", entry);
-            //     entry = entry | ((local_apic_get_id(base) as usize) << 56);
-            //     omneity!("{:b}", entry);
-            //     io_apic_set_entry(address as usize, isr as u8, entry as u64);
-        }
-        _ => {}
+    unsafe {
+        local::init(routing, processor_uid);
+        // Without interrupt remapping, the IO APIC can only address the first 256 local APICs.
+        io::init(routing, local::id() as u8, masked);
     }
 
+    IS_ENABLED.store(true, Ordering::Relaxed);
+
+    // PCI interrupts are routed differently once the IO APIC is in use.
+    if let Err(e) = acpi::namespace::set_interrupt_model(true) { warning!("APIC: {}", e); }
+
+    // The timer lets the periodic tick be stopped while idle.
+    local::calibrate_timer();
+
     Ok(())
 }
 
-fn interrupt_hander_apic_timer() {
-    pit::timer_irq_handler();
+/// Returns whether the APIC has taken over from the legacy PICs.
+pub fn is_enabled() -> bool { IS_ENABLED.load(Ordering::Relaxed) }
+
+/// Notifies the end of an interrupt to the active interrupt controller.
+pub(crate) fn notify_end_of_interrupt(vector: u8) {
+    if is_enabled() {
+        local::notify_end_of_interrupt();
+    } else {
+        unsafe { pics::PIC_8259.lock().notify_end_of_interrupt(vector); }
+    }
 }
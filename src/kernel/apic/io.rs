@@ -1,11 +1,12 @@
+use alloc::vec::Vec;
 use core::fmt;
 use core::fmt::{Formatter, LowerHex};
-use acpi::platform::interrupt::Apic;
 use bitflags::bitflags;
+use spin::Mutex;
 use x86_64::PhysAddr;
 
-use crate::kernel::memory;
-use crate::omneity;
+use crate::kernel::{memory, pics};
+use crate::kernel::acpi::madt::{ISA_IRQ_COUNT, Routing};
 
 macro_rules! define {
     ($name:ident, $val:expr) => {
@@ -218,26 +219,101 @@ unsafe fn write(base: usize, reg: u8, value: u32) {
     core::ptr::write_volatile(tgt_io_win, value);
 }
 
-unsafe fn io_apic_set_entry(base: usize, index: u8, data: u64) {
-    write(base, (IOREDTBL + (index as usize) * 2) as u8, data as u32);
-    write(base, (IOREDTBL + (index as usize) * 2 + 1) as u8, (data >> 32) as u32);
+/// Location of an ISA interrupt line within the IO APICs.
+#[derive(Clone, Copy)]
+struct Pin {
+    /// Virtual address of the IO APIC registers.
+    base: usize,
+    /// Index of the redirection table entry.
+    index: u8,
 }
 
-pub unsafe fn init(apic: &Apic) {
-    for io_apic in apic.io_apics.iter() {
-        let base = memory::phys_to_virt_addr(PhysAddr::new(io_apic.address as u64));
-        let base = base.as_u64();
+/// Redirection table entries the ISA interrupt lines are programmed into.
+static PINS: Mutex<[Option<Pin>; ISA_IRQ_COUNT as usize]> = Mutex::new([None; ISA_IRQ_COUNT as usize]);
 
-        let irq = 1;
-        let mut reg = RedirectionTableEntry::default();
+/// Returns the number of redirection table entries of the IO APIC.
+unsafe fn entry_count(base: usize) -> u32 {
+    ((read(base, IOAPICVER as u8) >> 16) & 0xFF) + 1
+}
+
+/// Masks every entry and routes the ISA interrupt lines to the given local APIC.
+///
+/// Lines are wired as described by the MADT, so that e.g. the PIT reaches vector 32 even when its
+/// line is overridden to a different GSI. Lines that are in `masked` are left masked.
+pub unsafe fn init(routing: &Routing, dest: u8, masked: u16) {
+    let mut bases = Vec::new();
+    for io_apic in routing.io_apics.iter() {
+        let base = memory::phys_to_virt_addr(PhysAddr::new(io_apic.address as u64)).as_u64() as usize;
+        let count = entry_count(base);
+        for index in 0..count as u8 {
+            let mut entry = RedirectionTableEntry::default();
+            entry.set_flags(IrqFlags::MASKED);
+            write(base, lo(index) as u8, entry.low);
+        }
+        bases.push((base, io_apic.gsi_base, count));
+    }
 
-        reg.set_vector(33);
+    let mut pins = PINS.lock();
+    for route in routing.routes.iter() {
+        // The cascade line of the PICs is never raised by a device.
+        if route.irq == 2 {
+            continue;
+        }
+
+        let owner = bases.iter().find(|(_, gsi_base, count)| (*gsi_base..gsi_base + count).contains(&route.gsi));
+        let (base, gsi_base, _) = match owner {
+            Some(owner) => *owner,
+            None => continue,
+        };
+        let index = (route.gsi - gsi_base) as u8;
+
+        let mut flags = IrqFlags::empty();
+        flags.set(IrqFlags::LOW_ACTIVE, route.signal.active_low);
+        flags.set(IrqFlags::LEVEL_TRIGGERED, route.signal.level_triggered);
+        flags.set(IrqFlags::MASKED, masked & (1 << route.irq) != 0);
+
+        let mut entry = RedirectionTableEntry::default();
+        entry.set_vector(pics::M_OFFSET + route.irq);
+        entry.set_mode(IrqMode::Fixed);
+        entry.set_flags(flags);
+        entry.set_dest(dest);
+
+        let (low, high) = entry.into_raw();
+        write(base, hi(index) as u8, high);
+        write(base, lo(index) as u8, low);
+
+        pins[route.irq as usize] = Some(Pin { base, index });
+    }
 
-        omneity!("{:?}", reg);
+    // Sources wired to the NMI signal are not usable by devices.
+    for (gsi, signal) in routing.nmi_sources.iter() {
+        if let Some((base, gsi_base, _)) = bases.iter().find(|(_, gsi_base, count)| (*gsi_base..gsi_base + count).contains(gsi)) {
+            let index = (gsi - gsi_base) as u8;
 
-        let (low, high) = reg.into_raw();
+            let mut flags = IrqFlags::empty();
+            flags.set(IrqFlags::LOW_ACTIVE, signal.active_low);
+            flags.set(IrqFlags::LEVEL_TRIGGERED, signal.level_triggered);
 
-        write(base as usize, lo(irq) as u8, low);
-        write(base as usize, hi(irq) as u8, high);
+            let mut entry = RedirectionTableEntry::default();
+            entry.set_mode(IrqMode::NonMaskable);
+            entry.set_flags(flags);
+            entry.set_dest(dest);
+
+            let (low, high) = entry.into_raw();
+            write(*base, hi(index) as u8, high);
+            write(*base, lo(index) as u8, low);
+        }
+    }
+}
+
+/// Masks or unmasks the given ISA interrupt line.
+pub(crate) fn set_masked(irq: u8, masked: bool) {
+    let pins = PINS.lock();
+    if let Some(Some(pin)) = pins.get(irq as usize) {
+        unsafe {
+            let low = read(pin.base, lo(pin.index) as u8);
+            let low = if masked { low | IrqFlags::MASKED.bits() } else { low & !IrqFlags::MASKED.bits() };
+            write(pin.base, lo(pin.index) as u8, low);
+        }
     }
 }
@@ -1,10 +1,12 @@
-use acpi::platform::interrupt::Apic;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use raw_cpuid::CpuId;
 use x86::msr::APIC_BASE;
-use x86_64::PhysAddr;
+use x86_64::{instructions, PhysAddr};
 use x86_64::registers::model_specific::Msr;
 
-use crate::kernel::memory;
-use crate::omneity;
+use crate::kernel::{idt, memory, pit};
+use crate::kernel::acpi::madt::Routing;
 
 macro_rules! define {
     ($name:ident, $val:expr) => {
@@ -38,13 +40,54 @@ define!(LAPIC_TICR, 0x0380);// Initial Count (for Timer)
 define!(LAPIC_TCCR, 0x0390);// Current Count (for Timer)
 define!(LAPIC_TDCR, 0x03e0);// Divide Configuration (for Timer)
 
+// In x2APIC mode, the registers are accessed through MSRs whose index is derived from the MMIO
+// offset, and the interrupt command register becomes a single 64-bit MSR.
+//
+// Intel SDM: Volume 3, Chapter 10.12 "Extended XAPIC (x2APIC)"
+const X2APIC_MSR_BASE: u32 = 0x800;
+const X2APIC_ICR: u32 = 0x830;
+
+/// APIC base MSR bit enabling the local APIC.
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+/// APIC base MSR bit enabling the x2APIC mode.
+const APIC_BASE_EXTD: u64 = 1 << 10;
+
+/// Delivery status bit of the interrupt command register.
+const ICR_SEND_PENDING: u32 = 0x0000_1000;
+
+/// LVT delivery mode of the NMI signal.
+const LVT_NMI: u32 = 0x0400;
+/// LVT mask bit.
+const LVT_MASKED: u32 = 0x0001_0000;
+
+/// Timer divide configuration value selecting a divisor of 16.
+const TIMER_DIVIDE_BY_16: u32 = 0x3;
+/// PIT ticks over which the timer is calibrated.
+const TIMER_CALIBRATION_TICKS: usize = 10;
+
+/// Virtual address of the local APIC registers.
+static BASE: AtomicUsize = AtomicUsize::new(0);
+/// Flag to check whether the local APIC is accessed through MSRs.
+static X2APIC: AtomicBool = AtomicBool::new(false);
+/// Timer counts per PIT tick, or zero if the timer is not calibrated.
+static TIMER_RATE: AtomicU32 = AtomicU32::new(0);
+
 unsafe fn read(base: usize, register: usize) -> u32 {
+    if X2APIC.load(Ordering::Relaxed) {
+        return Msr::new(X2APIC_MSR_BASE + (register >> 4) as u32).read() as u32;
+    }
+
     let tgt = base + register;
     let tgt = tgt as *mut u32;
     core::ptr::read_volatile(tgt)
 }
 
 unsafe fn write(base: usize, register: usize, value: u32) {
+    if X2APIC.load(Ordering::Relaxed) {
+        Msr::new(X2APIC_MSR_BASE + (register >> 4) as u32).write(value as u64);
+        return;
+    }
+
     let tgt = base + register;
     let tgt = tgt as *mut u32;
     core::ptr::write_volatile(tgt, value);
@@ -52,18 +95,126 @@ unsafe fn write(base: usize, register: usize, value: u32) {
 
 unsafe fn get_id(base: usize) -> u32
 {
-    read(base, LAPIC_ID) >> 24
+    // The x2APIC reports the full 32-bit identifier.
+    if X2APIC.load(Ordering::Relaxed) {
+        read(base, LAPIC_ID)
+    } else {
+        read(base, LAPIC_ID) >> 24
+    }
 }
 
-pub unsafe fn init(apic: &Apic) {
+pub unsafe fn init(routing: &Routing, processor_uid: u32) {
     let mut msr = Msr::new(APIC_BASE);
     let cur = msr.read();
-    msr.write(cur | 0x800); // Set bit 11.
-    let cur = msr.read();
+    msr.write(cur | APIC_BASE_ENABLE);
 
-    let apic_base_addr = memory::phys_to_virt_addr(PhysAddr::new(apic.local_apic_address));
+    // The x2APIC mode can only be entered from the enabled xAPIC mode.
+    let has_x2apic = CpuId::new().get_feature_info().map_or(false, |info| info.has_x2apic());
+    if has_x2apic {
+        msr.write(cur | APIC_BASE_ENABLE | APIC_BASE_EXTD);
+    }
+    X2APIC.store(has_x2apic, Ordering::Relaxed);
+
+    let apic_base_addr = memory::phys_to_virt_addr(PhysAddr::new(routing.local_apic_address));
     let base = apic_base_addr.as_u64() as usize;
+    BASE.store(base, Ordering::Relaxed);
+
+    // Accept every priority.
+    write(base, LAPIC_TPR, 0);
+
+    // The local pins carry the legacy PIC output and the NMI in virtual wire mode; with the PICs
+    // disabled, only the pins that the firmware wires to the NMI are left unmasked.
+    let mut lints = [LVT_MASKED; 2];
+    for pin in routing.local_nmi_pins(processor_uid) {
+        lints[pin as usize] = LVT_NMI;
+    }
+    write(base, LAPIC_LINT0, lints[0]);
+    write(base, LAPIC_LINT1, lints[1]);
 
     // spurious vectors.
-    write(base, LAPIC_SVR, 0x100 | 0xFF); // enable or disable apic.
+    write(base, LAPIC_SVR, 0x100 | idt::APIC_SPURIOUS_VECTOR as u32); // enable or disable apic.
+}
+
+/// Returns the identifier of the local APIC.
+pub(crate) fn id() -> u32 {
+    unsafe { get_id(BASE.load(Ordering::Relaxed)) }
+}
+
+/// Returns whether the local APIC is in x2APIC mode.
+pub fn is_x2apic() -> bool { X2APIC.load(Ordering::Relaxed) }
+
+/// Measures the rate of the timer against the PIT.
+///
+/// Note: It requires the PIT to be running, and does nothing otherwise.
+pub(crate) fn calibrate_timer() {
+    if !pit::is_initialized() || !instructions::interrupts::are_enabled() { return; }
+
+    let base = BASE.load(Ordering::Relaxed);
+    unsafe {
+        write(base, LAPIC_TIMER, LVT_MASKED);
+        write(base, LAPIC_TDCR, TIMER_DIVIDE_BY_16);
+
+        // Start on a tick boundary so that the whole duration is covered.
+        let start = pit::ticks();
+        while pit::ticks() == start { core::hint::spin_loop(); }
+
+        write(base, LAPIC_TICR, u32::MAX);
+        let start = pit::ticks();
+        while pit::ticks() - start < TIMER_CALIBRATION_TICKS { core::hint::spin_loop(); }
+        let elapsed = u32::MAX - read(base, LAPIC_TCCR);
+        write(base, LAPIC_TICR, 0);
+
+        TIMER_RATE.store(elapsed / TIMER_CALIBRATION_TICKS as u32, Ordering::Relaxed);
+    }
+}
+
+/// Returns whether the timer is calibrated and can be armed.
+pub(crate) fn has_timer() -> bool { TIMER_RATE.load(Ordering::Relaxed) != 0 }
+
+/// Arms the timer in one-shot mode to raise the given vector after the given number of PIT ticks.
+pub(crate) fn arm_timer(vector: u8, ticks: usize) {
+    let base = BASE.load(Ordering::Relaxed);
+    let count = (ticks as u64 * TIMER_RATE.load(Ordering::Relaxed) as u64).min(u32::MAX as u64) as u32;
+    unsafe {
+        write(base, LAPIC_TDCR, TIMER_DIVIDE_BY_16);
+        // A clear mode field selects the one-shot mode.
+        write(base, LAPIC_TIMER, vector as u32);
+        write(base, LAPIC_TICR, count);
+    }
+}
+
+/// Stops the timer and returns the number of PIT ticks elapsed since it was armed.
+pub(crate) fn stop_timer() -> usize {
+    let base = BASE.load(Ordering::Relaxed);
+    let rate = TIMER_RATE.load(Ordering::Relaxed).max(1);
+    unsafe {
+        let elapsed = read(base, LAPIC_TICR) - read(base, LAPIC_TCCR);
+        write(base, LAPIC_TIMER, LVT_MASKED);
+        write(base, LAPIC_TICR, 0);
+        (elapsed / rate) as usize
+    }
+}
+
+/// Sends an inter-processor interrupt to the given local APIC.
+///
+/// The command holds the low half of the interrupt command register: vector, delivery mode,
+/// level, trigger mode and destination shorthand.
+pub fn send_ipi(dest: u32, command: u32) {
+    let base = BASE.load(Ordering::Relaxed);
+    unsafe {
+        if X2APIC.load(Ordering::Relaxed) {
+            Msr::new(X2APIC_ICR).write((dest as u64) << 32 | command as u64);
+        } else {
+            write(base, LAPIC_ICRHI, dest << 24);
+            write(base, LAPIC_ICRLO, command);
+            while read(base, LAPIC_ICRLO) & ICR_SEND_PENDING != 0 {
+                core::hint::spin_loop();
+            }
+        }
+    }
+}
+
+/// Signals the end of an interrupt to the local APIC.
+pub(crate) fn notify_end_of_interrupt() {
+    unsafe { write(BASE.load(Ordering::Relaxed), LAPIC_EOI, 0); }
 }
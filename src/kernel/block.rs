@@ -0,0 +1,119 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::error::Error;
+
+pub mod cache;
+
+// Block Devices
+//
+// A block device is storage addressed in fixed-size blocks through a Logical Block Address (LBA).
+// Drivers register each disk (or namespace, or partition) they find under a unique name, after which
+// filesystems and user commands can reach it by that name without knowing the driver behind it.
+//
+// Filesystems should go through the block cache rather than the devices themselves.
+
+/////////////
+// Mutexes
+/////////////
+
+/// Registered block devices.
+static DEVICES: Mutex<Vec<Box<dyn BlockDevice>>> = Mutex::new(Vec::new());
+
+////////////////////
+/// Block Device
+////////////////////
+pub trait BlockDevice: Send {
+    /// Returns the unique name of the device.
+    fn name(&self) -> &str;
+
+    /// Returns the size of a block in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Returns the number of blocks on the device.
+    fn block_count(&self) -> u64;
+
+    /// Reads consecutive blocks starting at `lba` into `buf`, whose length is a multiple of the block
+    /// size.
+    fn read(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), Error>;
+
+    /// Writes consecutive blocks starting at `lba` from `buf`, whose length is a multiple of the block
+    /// size.
+    fn write(&mut self, lba: u64, buf: &[u8]) -> Result<(), Error>;
+
+    /// Flushes any write caches of the device.
+    fn flush(&mut self) -> Result<(), Error> { Ok(()) }
+}
+
+////////////
+/// Info
+////////////
+#[derive(Debug, Clone)]
+pub struct Info {
+    pub name: String,
+    pub block_size: usize,
+    pub block_count: u64,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Registers a block device.
+pub fn register(device: Box<dyn BlockDevice>) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut devices = DEVICES.lock();
+            if devices.iter().any(|d| d.name() == device.name()) { return Err(Error::AlreadyExists); }
+            devices.push(device);
+            Ok(())
+        }
+    )
+}
+
+/// Calls the given function with the named block device.
+///
+/// Note: Interrupts stay enabled while the function runs, so drivers may wait for the hardware.
+pub fn with_device<R>(name: &str, f: impl FnOnce(&mut dyn BlockDevice) -> R) -> Result<R, Error> {
+    let mut devices = DEVICES.lock();
+    let device = devices.iter_mut().find(|d| d.name() == name).ok_or(Error::NotFound)?;
+    Ok(f(device.as_mut()))
+}
+
+/// Calls the given function for each registered block device.
+pub fn for_each(f: impl FnMut(&Info)) {
+    let infos: Vec<Info> = instructions::interrupts::without_interrupts(
+        || {
+            DEVICES.lock().iter()
+                .map(|d| Info { name: String::from(d.name()), block_size: d.block_size(), block_count: d.block_count() })
+                .collect()
+        }
+    );
+    infos.iter().for_each(f);
+}
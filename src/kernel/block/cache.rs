@@ -0,0 +1,302 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use spin::Mutex;
+
+use crate::warning;
+use crate::kernel::allocator::fallible;
+use crate::kernel::block;
+use crate::kernel::error::Error;
+use crate::kernel::memory::pressure;
+use crate::kernel::memory::pressure::Resource;
+use crate::kernel::pit;
+
+// Block Cache
+//
+// Filesystems reach block devices through this cache rather than directly, since they tend to read
+// the same metadata blocks (superblocks, allocation tables, directories) over and over.
+//
+// Cached blocks are evicted in least-recently-used order once the cache is full. A miss that directly
+// follows the previously read block of the same device is treated as sequential access, and the next
+// few blocks are read ahead in the same request.
+//
+// Writes only update the cache and mark the blocks dirty. Dirty blocks reach the device when they are
+// evicted, when `sync` is called, or when the background write-back task runs, whichever comes first.
+//
+// When memory runs low, the clean blocks are dropped, as they can always be read again.
+
+////////////////////
+// Configurations
+////////////////////
+
+/// Maximum number of cached blocks.
+const CAPACITY: usize = 1024;
+
+/// Number of blocks read ahead on sequential access.
+const READ_AHEAD: u64 = 8;
+
+/// Time between successive write-backs of dirty blocks, in seconds.
+const WRITE_BACK_INTERVAL: f64 = 5.0;
+
+/////////////
+// Mutexes
+/////////////
+
+/// The block cache.
+static CACHE: Mutex<Cache> = Mutex::new(Cache::new());
+
+/////////////
+/// Stats
+/////////////
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    pub cached: usize,
+    pub dirty: usize,
+}
+
+/////////////
+/// Entry
+/////////////
+struct Entry {
+    data: Vec<u8>,
+    dirty: bool,
+    last_used: u64,
+}
+
+/////////////
+/// Cache
+/////////////
+struct Cache {
+    entries: BTreeMap<(String, u64), Entry>,
+    last_read: BTreeMap<String, u64>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Cache {
+    /// Creates a new object.
+    const fn new() -> Self {
+        Cache {
+            entries: BTreeMap::new(),
+            last_read: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Advances the clock used to order entries by recency.
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Inserts or replaces the block, evicting the least recently used one if the cache is full.
+    fn insert(&mut self, device: &str, lba: u64, data: Vec<u8>, dirty: bool) -> Result<(), Error> {
+        let key = (String::from(device), lba);
+        if !self.entries.contains_key(&key) && self.entries.len() >= CAPACITY { self.evict()?; }
+
+        let last_used = self.tick();
+        self.entries.insert(key, Entry { data, dirty, last_used });
+
+        Ok(())
+    }
+
+    /// Evicts the least recently used block, writing it back first if dirty.
+    fn evict(&mut self) -> Result<(), Error> {
+        let key = match self.entries.iter().min_by_key(|(_, entry)| entry.last_used) {
+            Some((key, _)) => key.clone(),
+            None => return Ok(()),
+        };
+
+        let entry = &self.entries[&key];
+        if entry.dirty { block::with_device(&key.0, |d| d.write(key.1, &entry.data))??; }
+        self.entries.remove(&key);
+
+        Ok(())
+    }
+
+    /// Writes the dirty blocks of the matching devices back and flushes those devices.
+    fn sync(&mut self, filter: impl Fn(&str) -> bool) -> Result<(), Error> {
+        let mut flushed: Vec<String> = Vec::new();
+
+        for ((device, lba), entry) in self.entries.iter_mut().filter(|((d, _), e)| e.dirty && filter(d)) {
+            block::with_device(device, |d| d.write(*lba, &entry.data))??;
+            entry.dirty = false;
+            if !flushed.contains(device) { flushed.push(device.clone()); }
+        }
+
+        for device in flushed {
+            block::with_device(&device, |d| d.flush())??;
+        }
+
+        Ok(())
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Reads consecutive blocks starting at `lba` into `buf`, whose length is a multiple of the block size.
+pub fn read(device: &str, lba: u64, buf: &mut [u8]) -> Result<(), Error> {
+    let (block_size, block_count) = geometry(device)?;
+    check_range(lba, buf.len(), block_size, block_count)?;
+
+    let blocks = buf.len() / block_size;
+    let mut cache = CACHE.lock();
+    let sequential = lba > 0 && cache.last_read.get(device) == Some(&(lba - 1));
+
+    for (idx, chunk) in buf.chunks_mut(block_size).enumerate() {
+        let current = lba + idx as u64;
+        let key = (String::from(device), current);
+
+        if !cache.entries.contains_key(&key) {
+            cache.misses += 1;
+
+            // Fetch the rest of the request in one go, extended by the read-ahead window on sequential
+            // access, but stop short of blocks that are already cached since they may be dirty. A fetch
+            // never exceeds the cache, so that it does not evict its own first blocks.
+            let remaining = (blocks - idx) as u64;
+            let wanted = if sequential || idx > 0 { remaining.max(READ_AHEAD) } else { remaining };
+            let wanted = wanted.min(CAPACITY as u64);
+            let mut count = 1;
+            while count < wanted.min(block_count - current)
+                && !cache.entries.contains_key(&(String::from(device), current + count)) {
+                count += 1;
+            }
+
+            let mut data = fallible::try_vec(0, count as usize * block_size)?;
+            block::with_device(device, |d| d.read(current, &mut data))??;
+            for (offset, block) in data.chunks(block_size).enumerate() {
+                cache.insert(device, current + offset as u64, fallible::try_to_vec(block)?, false)?;
+            }
+            chunk.copy_from_slice(&data[..block_size]);
+        } else {
+            cache.hits += 1;
+
+            let last_used = cache.tick();
+            let entry = cache.entries.get_mut(&key).ok_or(Error::NotFound)?;
+            entry.last_used = last_used;
+            chunk.copy_from_slice(&entry.data);
+        }
+    }
+
+    let last = lba + blocks as u64 - 1;
+    cache.last_read.insert(String::from(device), last);
+
+    Ok(())
+}
+
+/// Writes consecutive blocks starting at `lba` from `buf`, whose length is a multiple of the block size.
+///
+/// Note: The blocks only reach the device once they are written back.
+pub fn write(device: &str, lba: u64, buf: &[u8]) -> Result<(), Error> {
+    let (block_size, block_count) = geometry(device)?;
+    check_range(lba, buf.len(), block_size, block_count)?;
+
+    let mut cache = CACHE.lock();
+    for (idx, chunk) in buf.chunks(block_size).enumerate() {
+        cache.insert(device, lba + idx as u64, fallible::try_to_vec(chunk)?, true)?;
+    }
+
+    Ok(())
+}
+
+/// Writes all dirty blocks back and flushes the devices they belong to.
+pub fn sync() -> Result<(), Error> { CACHE.lock().sync(|_| true) }
+
+/// Writes the dirty blocks of the device back and flushes it.
+pub fn sync_device(device: &str) -> Result<(), Error> { CACHE.lock().sync(|d| d == device) }
+
+/// Writes the dirty blocks of the device back, then drops all of its blocks from the cache.
+pub fn invalidate(device: &str) -> Result<(), Error> {
+    let mut cache = CACHE.lock();
+    cache.sync(|d| d == device)?;
+    cache.entries.retain(|(d, _), _| d != device);
+    cache.last_read.remove(device);
+
+    Ok(())
+}
+
+/// Registers the cache as a shrinker, to give up its clean blocks when memory runs low.
+pub(crate) fn init() -> Result<(), Error> { pressure::register_shrinker("Block Cache", shrink) }
+
+/// Drops the clean blocks and returns the number of bytes released.
+///
+/// Note: Dirty blocks are kept, as writing them back might take memory, and so is everything if the
+/// cache is in use, as the allocation that ran out might have been made from within it.
+fn shrink(_: Resource) -> usize {
+    let mut cache = match CACHE.try_lock() {
+        Some(cache) => cache,
+        None => return 0,
+    };
+
+    let mut released = 0;
+    cache.entries.retain(
+        |_, entry| {
+            if !entry.dirty { released += entry.data.len(); }
+            entry.dirty
+        }
+    );
+    released
+}
+
+/// Returns the statistics of the cache.
+pub fn stats() -> Stats {
+    let cache = CACHE.lock();
+    Stats {
+        hits: cache.hits,
+        misses: cache.misses,
+        cached: cache.entries.len(),
+        dirty: cache.entries.values().filter(|entry| entry.dirty).count(),
+    }
+}
+
+/// Periodically writes dirty blocks back.
+pub async fn write_back() {
+    loop {
+        pit::delay(WRITE_BACK_INTERVAL).await;
+        if let Err(e) = sync() { warning!("Block cache: write-back failed: {}", e); }
+    }
+}
+
+/// Returns the block size and block count of the device.
+fn geometry(device: &str) -> Result<(usize, u64), Error> {
+    block::with_device(device, |d| (d.block_size(), d.block_count()))
+}
+
+/// Checks that `len` bytes starting at `lba` are whole blocks within the device.
+fn check_range(lba: u64, len: usize, block_size: usize, block_count: u64) -> Result<(), Error> {
+    let blocks = (len / block_size) as u64;
+    if len == 0 || len % block_size != 0 { return Err(Error::InvalidArgument); }
+    if lba.checked_add(blocks).map_or(true, |end| end > block_count) { return Err(Error::OutOfBounds); }
+
+    Ok(())
+}
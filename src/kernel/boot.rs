@@ -0,0 +1,256 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use conquer_once::spin::OnceCell;
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::{pit, time};
+
+pub mod bootloader;
+pub mod multiboot2;
+
+// Boot Protocols
+//
+// Whatever loaded the kernel describes the machine in its own format: the `bootloader` crate hands
+// over a `BootInfo`, while GRUB and Limine pass a Multiboot2 information structure. Each format is
+// wrapped in an implementation of `BootProtocol`, so the rest of the kernel only ever sees the
+// memory map, framebuffer, RSDP, modules, and command line in the types below.
+//
+// Every protocol is expected to have paged the kernel in and mapped all physical memory at a fixed
+// offset, as the memory manager builds upon that mapping.
+
+// Boot Timings
+//
+// Each step of the boot, be it a device or one of the steps around them, is timed with the TSC so
+// that regressions in boot latency can be told apart. The steps run before the heap is ready, so
+// the samples are kept in a fixed table; cycles are converted to seconds only when the timings are
+// read, since the TSC is calibrated partway through the boot.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of steps recorded.
+const MAX_STEPS: usize = 48;
+
+/// Name of a step along with its start and end TSC values.
+type Step = (&'static str, u64, u64);
+
+////////////
+// States
+////////////
+
+/// Protocol the kernel was booted through.
+static PROTOCOL: OnceCell<&'static (dyn BootProtocol + Sync)> = OnceCell::uninit();
+
+/// TSC value at the start of the boot.
+static BOOT_START: AtomicU64 = AtomicU64::new(0);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Recorded steps.
+static STEPS: Mutex<[Option<Step>; MAX_STEPS]> = Mutex::new([None; MAX_STEPS]);
+
+///////////////////
+/// Region Kind
+///////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Free for use by the kernel.
+    Usable,
+    /// Occupied by the kernel, its page tables, or data handed over by the bootloader.
+    InUse,
+    /// Holds ACPI tables, which can be reclaimed once they are read.
+    AcpiReclaimable,
+    /// Reserved for the firmware across sleep states.
+    AcpiNvs,
+    /// Reported as defective.
+    BadMemory,
+    /// Not available to the kernel.
+    Reserved,
+}
+
+/////////////////////
+/// Memory Region
+/////////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    /// Physical address of the first byte.
+    pub start: u64,
+    /// Physical address past the last byte.
+    pub end: u64,
+    pub kind: RegionKind,
+}
+
+impl MemoryRegion {
+    /// Creates an empty object.
+    pub const fn empty() -> Self { MemoryRegion { start: 0, end: 0, kind: RegionKind::Reserved } }
+
+    /// Returns the size of the region in bytes.
+    pub fn size(&self) -> u64 { self.end - self.start }
+}
+
+///////////////////
+/// Framebuffer
+///////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Framebuffer {
+    /// Physical address of the first pixel.
+    pub address: u64,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Number of bytes per row.
+    pub pitch: u32,
+    /// Number of bits per pixel.
+    pub bpp: u8,
+    /// Bit offsets of the red, green, and blue components within a pixel.
+    pub shifts: (u8, u8, u8),
+}
+
+//////////////
+/// Module
+//////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Module {
+    /// Physical address of the first byte.
+    pub start: u64,
+    /// Physical address past the last byte.
+    pub end: u64,
+    /// Name or command line given to the module.
+    pub name: &'static str,
+}
+
+/////////////////////
+/// Boot Protocol
+/////////////////////
+pub trait BootProtocol {
+    /// Returns the name of the protocol.
+    fn name(&self) -> &'static str;
+
+    /// Returns the offset at which all physical memory is mapped in the virtual space.
+    fn physical_memory_offset(&self) -> u64;
+
+    /// Calls the given function for each region of the physical memory map.
+    fn for_each_region(&self, f: &mut dyn FnMut(MemoryRegion));
+
+    /// Returns the linear framebuffer set up by the bootloader, if any.
+    fn framebuffer(&self) -> Option<Framebuffer> { None }
+
+    /// Returns the physical address of the ACPI RSDP, if the bootloader found it.
+    fn rsdp_address(&self) -> Option<u64> { None }
+
+    /// Calls the given function for each module loaded alongside the kernel.
+    fn for_each_module(&self, _f: &mut dyn FnMut(Module)) {}
+
+    /// Returns the command line the kernel was started with, if any.
+    fn command_line(&self) -> Option<&'static str> { None }
+}
+
+//////////////
+/// Timing
+//////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    /// Name of the step.
+    pub name: &'static str,
+    /// Time from the start of the boot to the start of the step, in seconds.
+    pub start: f64,
+    /// Time taken by the step, in seconds.
+    pub duration: f64,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Records the protocol the kernel was booted through.
+pub(crate) fn init(protocol: &'static (dyn BootProtocol + Sync)) { PROTOCOL.try_init_once(|| protocol).ok(); }
+
+/// Returns the protocol the kernel was booted through.
+pub fn protocol() -> Option<&'static (dyn BootProtocol + Sync)> { PROTOCOL.try_get().ok().copied() }
+
+/// Returns the command line the kernel was started with, if any.
+pub fn command_line() -> Option<&'static str> { protocol().and_then(|protocol| protocol.command_line()) }
+
+/// Returns the linear framebuffer set up by the bootloader, if any.
+pub fn framebuffer() -> Option<Framebuffer> { protocol().and_then(|protocol| protocol.framebuffer()) }
+
+/// Returns the physical address of the RSDP, if the bootloader passed it on.
+pub fn rsdp_address() -> Option<u64> { protocol().and_then(|protocol| protocol.rsdp_address()) }
+
+/// Returns the modules loaded alongside the kernel.
+pub fn modules() -> Vec<Module> {
+    let mut modules = Vec::new();
+    if let Some(protocol) = protocol() {
+        protocol.for_each_module(&mut |module| modules.push(module));
+    }
+    modules
+}
+
+/// Marks the start of the boot.
+pub(crate) fn start() { BOOT_START.store(pit::rdtsc(), Ordering::Relaxed); }
+
+/// Runs the given step of the boot and records how long it took.
+pub(crate) fn measure<R>(name: &'static str, step: impl FnOnce() -> R) -> R {
+    let start = pit::rdtsc();
+    let res = step();
+    let end = pit::rdtsc();
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut steps = STEPS.lock();
+            if let Some(slot) = steps.iter_mut().find(|slot| slot.is_none()) {
+                *slot = Some((name, start, end));
+            }
+        }
+    );
+
+    res
+}
+
+/// Returns the recorded steps in order, or none if the TSC is not calibrated.
+pub fn timings() -> Vec<Timing> {
+    let hz = match time::tsc_frequency() {
+        Some(hz) => hz as f64,
+        None => return Vec::new(),
+    };
+    let boot_start = BOOT_START.load(Ordering::Relaxed);
+
+    let steps = instructions::interrupts::without_interrupts(|| *STEPS.lock());
+    steps.iter().flatten()
+        .map(
+            |(name, start, end)| Timing {
+                name,
+                start: start.saturating_sub(boot_start) as f64 / hz,
+                duration: end.saturating_sub(*start) as f64 / hz,
+            }
+        )
+        .collect()
+}
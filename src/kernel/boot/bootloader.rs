@@ -0,0 +1,52 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use ::bootloader::BootInfo;
+use ::bootloader::bootinfo::MemoryRegionType;
+
+use super::{BootProtocol, MemoryRegion, RegionKind};
+
+// `bootloader` Crate
+//
+// The BIOS path of the `bootloader` crate enters the kernel in long mode with all physical memory
+// mapped, and describes the memory in its own map. It sets up no framebuffer, passes neither modules
+// nor a command line, and leaves finding the RSDP to the kernel.
+
+impl BootProtocol for BootInfo {
+    fn name(&self) -> &'static str { "bootloader" }
+
+    fn physical_memory_offset(&self) -> u64 { self.physical_memory_offset }
+
+    fn for_each_region(&self, f: &mut dyn FnMut(MemoryRegion)) {
+        for region in self.memory_map.iter() {
+            let kind = match region.region_type {
+                MemoryRegionType::Usable => RegionKind::Usable,
+                MemoryRegionType::AcpiReclaimable => RegionKind::AcpiReclaimable,
+                MemoryRegionType::AcpiNvs => RegionKind::AcpiNvs,
+                MemoryRegionType::BadMemory => RegionKind::BadMemory,
+                MemoryRegionType::Reserved => RegionKind::Reserved,
+                _ => RegionKind::InUse,
+            };
+            f(MemoryRegion { start: region.range.start_addr(), end: region.range.end_addr(), kind });
+        }
+    }
+}
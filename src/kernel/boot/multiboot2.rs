@@ -0,0 +1,228 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::ffi::{c_char, CStr};
+use core::ops::Range;
+
+use super::{BootProtocol, Framebuffer, MemoryRegion, Module, RegionKind};
+
+// Multiboot2
+//
+// GRUB, Limine, and most other general-purpose bootloaders can load the kernel through the
+// Multiboot2 protocol. On entry, the physical address of the boot information structure is left in
+// EBX. The structure is a sequence of tags, each eight-byte aligned and starting with its type and
+// size, that ends with a tag of type zero.
+//
+// The protocol enters the kernel in 32-bit protected mode without paging, so a small entry stub has
+// to build the page tables, switch to long mode, and map physical memory before handing the
+// structure over. The memory map reports the kernel image, the boot information, and the modules as
+// available, hence those ranges are carved out of the usable regions here.
+//
+// Specification: https://www.gnu.org/software/grub/manual/multiboot2/multiboot.html
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of modules tracked.
+const MAX_MODULES: usize = 16;
+
+///////////////
+// Constants
+///////////////
+
+const TAG_END: u32 = 0;
+const TAG_COMMAND_LINE: u32 = 1;
+const TAG_MODULE: u32 = 3;
+const TAG_MEMORY_MAP: u32 = 6;
+const TAG_FRAMEBUFFER: u32 = 8;
+const TAG_ACPI_OLD: u32 = 14;
+const TAG_ACPI_NEW: u32 = 15;
+
+const MEMORY_AVAILABLE: u32 = 1;
+const MEMORY_ACPI_RECLAIMABLE: u32 = 3;
+const MEMORY_NVS: u32 = 4;
+const MEMORY_BAD: u32 = 5;
+
+/// Framebuffer type for direct RGB color.
+const FRAMEBUFFER_RGB: u8 = 1;
+
+//////////////////
+/// Multiboot2
+//////////////////
+pub struct Multiboot2 {
+    /// Physical address of the boot information structure.
+    info: u64,
+    /// Offset at which all physical memory is mapped.
+    offset: u64,
+    /// Physical range occupied by the kernel image.
+    kernel: Range<u64>,
+}
+
+impl Multiboot2 {
+    /// Wraps the boot information structure at the given physical address.
+    ///
+    /// # Safety
+    ///
+    /// The structure must be valid and all physical memory must be mapped at `offset`.
+    pub unsafe fn new(info: u64, offset: u64, kernel: Range<u64>) -> Self { Multiboot2 { info, offset, kernel } }
+
+    /// Returns the total size of the boot information structure.
+    fn size(&self) -> u64 { self.read::<u32>(self.info) as u64 }
+
+    /// Reads a value at the given physical address.
+    fn read<T: Copy>(&self, addr: u64) -> T {
+        unsafe { core::ptr::read_unaligned((self.offset + addr) as *const T) }
+    }
+
+    /// Reads the null-terminated string at the given physical address.
+    fn read_str(&self, addr: u64) -> Option<&'static str> {
+        let string = unsafe { CStr::from_ptr((self.offset + addr) as *const c_char) };
+        string.to_str().ok()
+    }
+
+    /// Calls the given function with the type, address, and size of each tag.
+    fn for_each_tag(&self, mut f: impl FnMut(u32, u64, u64)) {
+        let end = self.info + self.size();
+        let mut addr = self.info + 8;
+        while addr + 8 <= end {
+            let kind = self.read::<u32>(addr);
+            let size = self.read::<u32>(addr + 4) as u64;
+            if kind == TAG_END || size < 8 { break; }
+            f(kind, addr, size);
+            addr += (size + 7) & !7;
+        }
+    }
+
+    /// Returns the address of the first tag of the given type.
+    fn find_tag(&self, kind: u32) -> Option<u64> {
+        let mut found = None;
+        self.for_each_tag(|tag, addr, _| if tag == kind && found.is_none() { found = Some(addr); });
+        found
+    }
+
+    /// Returns the physical ranges that the memory map reports as available but are in use.
+    fn occupied(&self) -> ([Range<u64>; MAX_MODULES + 2], usize) {
+        const EMPTY: Range<u64> = 0..0;
+        let mut ranges = [EMPTY; MAX_MODULES + 2];
+        ranges[0] = self.kernel.clone();
+        ranges[1] = self.info..self.info + self.size();
+        let mut len = 2;
+        self.for_each_module(&mut |module| {
+            if len < ranges.len() {
+                ranges[len] = module.start..module.end;
+                len += 1;
+            }
+        });
+        ranges[..len].sort_unstable_by_key(|range| range.start);
+        (ranges, len)
+    }
+}
+
+impl BootProtocol for Multiboot2 {
+    fn name(&self) -> &'static str { "Multiboot2" }
+
+    fn physical_memory_offset(&self) -> u64 { self.offset }
+
+    fn for_each_region(&self, f: &mut dyn FnMut(MemoryRegion)) {
+        let (occupied, len) = self.occupied();
+        let occupied = &occupied[..len];
+
+        self.for_each_tag(|tag, addr, size| {
+            if tag != TAG_MEMORY_MAP { return; }
+
+            let entry_size = self.read::<u32>(addr + 8) as u64;
+            if entry_size < 24 { return; }
+            let mut entry = addr + 16;
+            while entry + entry_size <= addr + size {
+                let start = self.read::<u64>(entry);
+                let end = start + self.read::<u64>(entry + 8);
+                let kind = match self.read::<u32>(entry + 16) {
+                    MEMORY_AVAILABLE => RegionKind::Usable,
+                    MEMORY_ACPI_RECLAIMABLE => RegionKind::AcpiReclaimable,
+                    MEMORY_NVS => RegionKind::AcpiNvs,
+                    MEMORY_BAD => RegionKind::BadMemory,
+                    _ => RegionKind::Reserved,
+                };
+                entry += entry_size;
+
+                if kind != RegionKind::Usable {
+                    f(MemoryRegion { start, end, kind });
+                    continue;
+                }
+
+                // Split the usable region around the ranges in use.
+                let mut cursor = start;
+                for range in occupied.iter().filter(|range| range.start < end && range.end > start) {
+                    if range.start > cursor {
+                        f(MemoryRegion { start: cursor, end: range.start, kind: RegionKind::Usable });
+                    }
+                    let used = range.start.max(cursor)..range.end.min(end);
+                    if used.end > used.start {
+                        f(MemoryRegion { start: used.start, end: used.end, kind: RegionKind::InUse });
+                    }
+                    cursor = cursor.max(used.end);
+                }
+                if cursor < end {
+                    f(MemoryRegion { start: cursor, end, kind: RegionKind::Usable });
+                }
+            }
+        });
+    }
+
+    fn framebuffer(&self) -> Option<Framebuffer> {
+        let tag = self.find_tag(TAG_FRAMEBUFFER)?;
+        if self.read::<u8>(tag + 29) != FRAMEBUFFER_RGB { return None; }
+
+        Some(
+            Framebuffer {
+                address: self.read::<u64>(tag + 8),
+                pitch: self.read::<u32>(tag + 16),
+                width: self.read::<u32>(tag + 20),
+                height: self.read::<u32>(tag + 24),
+                bpp: self.read::<u8>(tag + 28),
+                shifts: (self.read::<u8>(tag + 32), self.read::<u8>(tag + 34), self.read::<u8>(tag + 36)),
+            }
+        )
+    }
+
+    fn rsdp_address(&self) -> Option<u64> {
+        // The tags hold a copy of the RSDP, so its address is that of the tag's payload.
+        self.find_tag(TAG_ACPI_NEW).or_else(|| self.find_tag(TAG_ACPI_OLD)).map(|tag| tag + 8)
+    }
+
+    fn for_each_module(&self, f: &mut dyn FnMut(Module)) {
+        self.for_each_tag(|tag, addr, _| {
+            if tag != TAG_MODULE { return; }
+
+            let start = self.read::<u32>(addr + 8) as u64;
+            let end = self.read::<u32>(addr + 12) as u64;
+            let name = self.read_str(addr + 16).unwrap_or("");
+            f(Module { start, end, name });
+        });
+    }
+
+    fn command_line(&self) -> Option<&'static str> {
+        let tag = self.find_tag(TAG_COMMAND_LINE)?;
+        self.read_str(tag + 8).filter(|cmdline| !cmdline.is_empty())
+    }
+}
@@ -0,0 +1,140 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::kernel::error::Error;
+
+// Capabilities
+//
+// The functions of `api` reach deep into the kernel, and some of them, such as rebooting the machine
+// or reprogramming the VGA registers, are not meant for every command. Such functions require a
+// capability, and fail with `PermissionDenied` when the running code does not hold it.
+//
+// Every task holds a set of capabilities, which the executor makes current while it polls the task.
+// Kernel tasks hold all of them. A process is given the capabilities of its spawner, and a task may
+// drop capabilities at any time, but never regain them; the shell drops all of them once the init
+// script has run, so the commands typed by the user are unprivileged unless run with `sudo`.
+
+//////////////////
+/// Capability
+//////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Capability {
+    /// Shutting down, rebooting, and changing the idle policy.
+    Power = 0x1,
+    /// Reprogramming devices, such as the VGA registers and font.
+    Hardware = 0x2,
+    /// Mounting and unmounting filesystems.
+    Mount = 0x4,
+}
+
+impl Capability {
+    /// All the capabilities.
+    pub const ALL: [Capability; 3] = [Self::Power, Self::Hardware, Self::Mount];
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Power => "power",
+            Self::Hardware => "hardware",
+            Self::Mount => "mount",
+        }
+    }
+}
+
+impl FromStr for Capability {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().find(|cap| cap.as_str() == s).copied().ok_or(Error::InvalidArgument)
+    }
+}
+
+////////////////////
+/// Capabilities
+////////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    /// No capabilities, as given to user commands.
+    pub const NONE: Capabilities = Capabilities(0);
+    /// Every capability, as held by the kernel.
+    pub const ALL: Capabilities = Capabilities(0x7);
+
+    /// Creates an object from the given capabilities.
+    pub fn from_slice(caps: &[Capability]) -> Self { Capabilities(caps.iter().fold(0, |bits, cap| bits | *cap as u8)) }
+
+    /// Returns whether the set holds the capability.
+    pub fn contains(&self, cap: Capability) -> bool { self.0 & cap as u8 != 0 }
+
+    /// Returns whether the set holds every capability of the other one.
+    pub fn contains_all(&self, other: Capabilities) -> bool { self.0 & other.0 == other.0 }
+
+    /// Returns the capabilities held by both sets.
+    pub fn intersection(&self, other: Capabilities) -> Self { Capabilities(self.0 & other.0) }
+
+    /// Returns an iterator over the capabilities in the set.
+    pub fn iter(&self) -> impl Iterator<Item=Capability> + '_ { Capability::ALL.into_iter().filter(|cap| self.contains(*cap)) }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::NONE { return write!(f, "none"); }
+        for (idx, cap) in self.iter().enumerate() {
+            if idx > 0 { write!(f, ",")?; }
+            write!(f, "{}", cap.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+////////////
+// States
+////////////
+
+/// Capabilities of the running code.
+static CURRENT: AtomicU8 = AtomicU8::new(Capabilities::ALL.0);
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the capabilities of the running code.
+pub fn current() -> Capabilities { Capabilities(CURRENT.load(Ordering::Relaxed)) }
+
+/// Makes the given capabilities current and returns the previous ones.
+///
+/// Note: It is meant for the executor and the process runner, which switch between tasks and commands.
+pub(crate) fn set_current(caps: Capabilities) -> Capabilities { Capabilities(CURRENT.swap(caps.0, Ordering::Relaxed)) }
+
+/// Gives up all the capabilities of the running code except the given ones.
+pub fn restrict(caps: Capabilities) { CURRENT.fetch_and(caps.0, Ordering::Relaxed); }
+
+/// Fails unless the running code holds the capability.
+pub fn require(cap: Capability) -> Result<(), Error> {
+    if current().contains(cap) { Ok(()) } else { Err(Error::PermissionDenied) }
+}
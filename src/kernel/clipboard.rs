@@ -0,0 +1,118 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::drivers::serial;
+use crate::encodings::Base64;
+use crate::kernel::error::Error;
+
+// Clipboard
+//
+// The kernel keeps a single clipboard shared by every program. Text is placed there either through
+// the api or by writing an OSC 52 escape sequence to the console, as terminal programs do:
+//
+//     ESC ] 52 ; c ; <base64 text> BEL
+//
+// Each time the clipboard changes, the same sequence is sent over the serial port, so a terminal on
+// the host that understands it (xterm, kitty, iTerm2 and others, once allowed to) receives the text
+// into its own clipboard. Queries (`?` in place of the text) are not answered, since the console has
+// no way of replying to the program.
+//
+// Reference: https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Operating-System-Commands
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum size of the contents in bytes.
+pub const MAX_SIZE: usize = 8192;
+/// Number of the operating system command that sets the clipboard.
+pub(crate) const OSC: &[u8] = b"52";
+/// Selection that the contents are propagated to.
+const SELECTION: &str = "c";
+
+/////////////
+// Mutexes
+/////////////
+
+/// Contents of the clipboard.
+static CONTENTS: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+///////////////
+// Utilities
+///////////////
+
+/// Replaces the contents of the clipboard and propagates them to the host.
+pub fn set(data: &[u8]) -> Result<(), Error> {
+    if data.len() > MAX_SIZE { return Err(Error::OutOfBounds); }
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut contents = CONTENTS.lock();
+            contents.clear();
+            contents.extend_from_slice(data);
+        }
+    );
+    propagate(data);
+
+    Ok(())
+}
+
+/// Returns a copy of the contents of the clipboard.
+pub fn get() -> Vec<u8> {
+    instructions::interrupts::without_interrupts(
+        || CONTENTS.lock().clone()
+    )
+}
+
+/// Empties the clipboard.
+pub fn clear() -> Result<(), Error> { set(&[]) }
+
+/// Handles an OSC 52 sequence, given the parameters that follow its number.
+///
+/// Note: The selection is ignored, as there is only one clipboard.
+pub(crate) fn handle_osc(params: &[&[u8]]) {
+    let data = match params {
+        [_, data] => *data,
+        _ => return,
+    };
+    if data == b"?" { return; }
+
+    let decoded = core::str::from_utf8(data).ok().and_then(Base64::decode);
+    if let Some(decoded) = decoded { set(&decoded).ok(); }
+}
+
+/// Sends the contents to the terminal on the other end of the serial port.
+fn propagate(data: &[u8]) {
+    let mut sequence = String::from("\x1B]52;");
+    sequence.push_str(SELECTION);
+    sequence.push(';');
+    sequence.push_str(&Base64::encode(data));
+    sequence.push('\x07');
+
+    serial::_print(format_args!("{}", sequence));
+}
@@ -23,7 +23,9 @@
 use core::hint::spin_loop;
 
 use x86_64::instructions;
-use x86_64::instructions::port::Port;
+
+use crate::kernel::portio;
+use crate::kernel::portio::Port;
 
 ////////////////////
 // Configurations
@@ -32,12 +34,21 @@ use x86_64::instructions::port::Port;
 /// Current century.
 const RTC_CENTURY: u16 = 2000;
 
+////////////////
+// Attributes
+////////////////
+
+/// Address port of the CMOS.
+const ADDR_PORT: u16 = 0x70;
+/// Data port of the CMOS.
+const DATA_PORT: u16 = 0x71;
+
 /////////////////////////////
 /// Real-Time Clock (RTC)
 /////////////////////////////
 ///
 /// OS Dev Wiki: https://wiki.osdev.org/RTC
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RTC {
     pub year: u16,
     pub month: u8,
@@ -48,11 +59,64 @@ pub struct RTC {
 }
 
 impl RTC {
+    /// Start of the current century, which the clock reads before it is ever set.
+    pub const EPOCH: RTC = RTC { year: RTC_CENTURY, month: 1, day: 1, hour: 0, minute: 0, second: 0 };
+
     /// Creates a new object.
     pub fn new() -> Self { CMOS::new().rtc() }
 
     /// Syncs with the CMOS chip.
     pub fn sync(&mut self) { *self = RTC::new(); }
+
+    /// Returns the time shifted by the given number of minutes.
+    pub fn offset_by(&self, minutes: i32) -> RTC {
+        const SECS_PER_DAY: i64 = 86400;
+
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        let secs = days * SECS_PER_DAY
+            + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+            + minutes as i64 * 60;
+
+        let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+        let secs_of_day = secs.rem_euclid(SECS_PER_DAY);
+
+        RTC {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+        }
+    }
+}
+
+/// Returns the number of days since 1970-01-01 for the given proleptic Gregorian date.
+///
+/// Reference: https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Returns the proleptic Gregorian date for the given number of days since 1970-01-01.
+///
+/// Reference: https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
 }
 
 ///////////////////////
@@ -77,12 +141,17 @@ enum Register {
 /////////////////////////
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
-enum Interrupt {
+pub enum Interrupt {
     Periodic = 0x40,
     Alarm = 0x20,
     Update = 0x10,
 }
 
+impl Interrupt {
+    /// Returns whether the interrupt is set in the given flags of register C.
+    pub fn is_set(self, flags: u8) -> bool { flags & self as u8 != 0 }
+}
+
 //////////////////////////////////////////////////////
 /// Complementary Metal-Oxide Semiconductor (CMOS)
 //////////////////////////////////////////////////////
@@ -107,15 +176,21 @@ pub struct CMOS {
 impl CMOS {
     /// Creates a new object.
     pub fn new() -> Self {
-        const ADDR_PORT: u16 = 0x70;
-        const DATA_PORT: u16 = 0x71;
-
         CMOS {
             addr: Port::new(ADDR_PORT),
             data: Port::new(DATA_PORT),
         }
     }
 
+    /// Returns whether the chip responds.
+    ///
+    /// Note: Reads from a missing chip float high, which no sane configuration of the status registers does.
+    pub fn is_present(&mut self) -> bool {
+        let status_reg_a = self.read_register(Register::A);
+        let status_reg_b = self.read_register(Register::B);
+        status_reg_a != 0xFF || status_reg_b != 0xFF
+    }
+
     /// Returns a raw Real-Time Clock (RTC).
     fn rtc_raw(&mut self) -> RTC {
         RTC {
@@ -198,6 +273,9 @@ impl CMOS {
     /// Enables update interrupts.
     pub fn enable_update_interrupt(&mut self) { self.enable_interrupt(Interrupt::Update); }
 
+    /// Disables periodic interrupts.
+    pub fn disable_periodic_interrupt(&mut self) { self.disable_interrupt(Interrupt::Periodic); }
+
     /// Enables the specified interrupt.
     fn enable_interrupt(&mut self, interrupt: Interrupt) {
         // OS Dev Wiki: https://wiki.osdev.org/RTC
@@ -212,11 +290,24 @@ impl CMOS {
         );
     }
 
-    /// Notifies the end of an interrupt.
-    pub fn notify_end_of_interrupt(&mut self) {
+    /// Disables the specified interrupt.
+    fn disable_interrupt(&mut self, interrupt: Interrupt) {
+        instructions::interrupts::without_interrupts(
+            || {
+                self.disable_nmi();
+                let byte = self.read_register(Register::B);
+                self.write_register(Register::B, byte & !(interrupt as u8));
+                self.enable_nmi();
+                self.notify_end_of_interrupt();
+            }
+        );
+    }
+
+    /// Notifies the end of an interrupt and returns the flags of the interrupts that were pending.
+    pub fn notify_end_of_interrupt(&mut self) -> u8 {
         unsafe {
             self.addr.write(Register::C as u8);
-            self.data.read();
+            self.data.read()
         }
     }
 
@@ -247,8 +338,13 @@ impl CMOS {
     }
 
     /// Enters a spin loop while an update is in progress.
+    ///
+    /// Note: An update takes about 2 ms, so the loop is bounded to get past a flag that never clears.
     fn wait_while_updating(&mut self) {
-        while self.is_updating() {
+        const MAX_POLLS: usize = 1_000_000;
+
+        for _ in 0..MAX_POLLS {
+            if !self.is_updating() { return; }
             spin_loop();
         }
     }
@@ -273,3 +369,10 @@ impl CMOS {
         }
     }
 }
+
+///////////////
+// Utilities
+///////////////
+
+/// Reserves the address and data ports of the CMOS.
+pub(crate) fn reserve_ports() { portio::reserve("CMOS", ADDR_PORT, DATA_PORT - ADDR_PORT + 1).ok(); }
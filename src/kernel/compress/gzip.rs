@@ -0,0 +1,113 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+
+use crate::kernel::compress::inflate;
+use crate::kernel::crypto::crc32;
+use crate::kernel::error::Error;
+
+// Gzip
+//
+// A gzip file is a series of members, each a header, a DEFLATE stream, and a trailer holding the
+// CRC-32 and the size, modulo 2^32, of the decompressed data. The header may name the original file
+// and carry a comment, extra fields and a checksum of its own, all of which are skipped. The members
+// decompress to consecutive parts of the data.
+//
+// RFC 1952: https://www.rfc-editor.org/rfc/rfc1952
+
+////////////////
+// Attributes
+////////////////
+
+/// Bytes every member starts with.
+const MAGIC: [u8; 2] = [0x1F, 0x8B];
+/// Compression method of DEFLATE.
+const DEFLATE: u8 = 8;
+
+/// Flag of a checksum of the header.
+const FHCRC: u8 = 0x02;
+/// Flag of extra fields.
+const FEXTRA: u8 = 0x04;
+/// Flag of the original file name.
+const FNAME: u8 = 0x08;
+/// Flag of a comment.
+const FCOMMENT: u8 = 0x10;
+
+/// Size of the fixed part of the header.
+const HEADER_SIZE: usize = 10;
+/// Size of the trailer.
+const TRAILER_SIZE: usize = 8;
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether the data looks like gzip.
+pub fn is_gzip(data: &[u8]) -> bool { data.starts_with(&MAGIC) }
+
+/// Decompresses the data, holding one or more members.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> { decompress_with_limit(data, inflate::MAX_OUTPUT) }
+
+/// Decompresses the data, holding one or more members, into at most `limit` bytes.
+pub fn decompress_with_limit(mut data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    loop {
+        let start = header_size(data)?;
+        let (member, len) = inflate::decompress(&data[start..], limit - output.len())?;
+
+        let trailer = data.get((start + len)..(start + len + TRAILER_SIZE)).ok_or(Error::InvalidArgument)?;
+        let crc = u32::from_le_bytes(trailer[..4].try_into().unwrap());
+        let size = u32::from_le_bytes(trailer[4..].try_into().unwrap());
+        if crc32::checksum(&member) != crc || member.len() as u32 != size { return Err(Error::InvalidArgument); }
+
+        output.extend_from_slice(&member);
+        data = &data[(start + len + TRAILER_SIZE)..];
+
+        // Anything after the last member that is not another member is ignored, as padding.
+        if !is_gzip(data) { return Ok(output); }
+    }
+}
+
+/// Returns the size of the header of the member at the start of the data.
+fn header_size(data: &[u8]) -> Result<usize, Error> {
+    if data.len() < HEADER_SIZE || !is_gzip(data) { return Err(Error::InvalidArgument); }
+    if data[2] != DEFLATE { return Err(Error::Unsupported); }
+
+    let flags = data[3];
+    let mut pos = HEADER_SIZE;
+    if flags & FEXTRA != 0 {
+        let len = data.get(pos..(pos + 2)).ok_or(Error::InvalidArgument)?;
+        pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    // The name and the comment end with a zero byte.
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let rest = data.get(pos..).ok_or(Error::InvalidArgument)?;
+            pos += rest.iter().position(|&byte| byte == 0).ok_or(Error::InvalidArgument)? + 1;
+        }
+    }
+    if flags & FHCRC != 0 { pos += 2; }
+
+    if pos > data.len() { return Err(Error::InvalidArgument); }
+    Ok(pos)
+}
@@ -0,0 +1,300 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+
+use crate::kernel::error::Error;
+
+// Inflate
+//
+// A DEFLATE stream is a series of blocks, each stored as is, or compressed with either fixed or
+// dynamic Huffman codes. Compressed blocks hold literal bytes and back-references, which repeat a
+// run of the last 32 KiB of output. Bits are packed starting at the least significant bit of each
+// byte, whereas Huffman codes are packed starting at their most significant bit.
+//
+// Huffman codes are canonical, so a code is fully described by the length of the code of each
+// symbol, and is decoded a bit at a time by counting the codes of each length.
+//
+// RFC 1951: https://www.rfc-editor.org/rfc/rfc1951
+
+////////////////
+// Attributes
+////////////////
+
+/// Default maximum size of the decompressed data.
+pub const MAX_OUTPUT: usize = 512 * 1024;
+
+/// Maximum length of a code in bits.
+const MAX_BITS: usize = 15;
+/// Number of literal and length symbols.
+const MAX_LITERALS: usize = 288;
+/// Number of distance symbols.
+const MAX_DISTANCES: usize = 30;
+/// Symbol that ends a block.
+const END_OF_BLOCK: u16 = 256;
+
+/// Base lengths of length symbols 257 onwards.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+/// Extra bits of length symbols 257 onwards.
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// Base distances of distance symbols.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+/// Extra bits of distance symbols.
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+/// Order in which the lengths of the code length code are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+////////////
+/// Bits
+////////////
+/// Reader of the input a few bits at a time.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    /// Creates a new object reading from the start of the data.
+    fn new(data: &'a [u8]) -> Self { Bits { data, pos: 0, buffer: 0, count: 0 } }
+
+    /// Returns the next `n` bits, with the first one as the least significant.
+    fn take(&mut self, n: u32) -> Result<u32, Error> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or(Error::InvalidArgument)?;
+            self.buffer |= (byte as u32) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let bits = self.buffer & ((1u32 << n) - 1);
+        self.buffer = if n == 32 { 0 } else { self.buffer >> n };
+        self.count -= n;
+
+        Ok(bits)
+    }
+
+    /// Drops the bits left in the current byte.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    /// Returns the next `len` bytes, which must start on a byte boundary.
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self.data.get(self.pos..(self.pos + len)).ok_or(Error::InvalidArgument)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Returns the number of bytes consumed, counting a partly consumed one.
+    fn consumed(&self) -> usize { self.pos - (self.count / 8) as usize }
+}
+
+///////////////
+/// Huffman
+///////////////
+/// Canonical Huffman code.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; MAX_BITS + 1],
+    /// Symbols ordered by their codes.
+    symbols: [u16; MAX_LITERALS],
+}
+
+impl Huffman {
+    /// Creates the code from the length of the code of each symbol, where zero means unused.
+    fn new(lengths: &[u8]) -> Result<Self, Error> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Each length doubles the codes available; more codes than that can not be told apart.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 { return Err(Error::InvalidArgument); }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = [0u16; MAX_LITERALS];
+        for (symbol, &len) in lengths.iter().enumerate().filter(|(_, &len)| len != 0) {
+            symbols[offsets[len as usize] as usize] = symbol as u16;
+            offsets[len as usize] += 1;
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    /// Returns the next symbol.
+    fn decode(&self, bits: &mut Bits) -> Result<u16, Error> {
+        // The codes of each length follow those of the previous length, doubled.
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.take(1)? as i32;
+            let count = count as i32;
+            if code - first < count { return Ok(self.symbols[(index + code - first) as usize]); }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(Error::InvalidArgument)
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Decompresses the raw DEFLATE stream, and returns the data along with the number of bytes of the
+/// stream, which may be followed by other data.
+pub fn decompress(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), Error> {
+    let mut bits = Bits::new(data);
+    let mut output = Vec::new();
+
+    loop {
+        let is_last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => stored(&mut bits, &mut output, limit)?,
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                codes(&mut bits, &mut output, limit, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                codes(&mut bits, &mut output, limit, &literals, &distances)?;
+            }
+            _ => return Err(Error::InvalidArgument),
+        }
+        if is_last { break; }
+    }
+
+    Ok((output, bits.consumed()))
+}
+
+/// Copies a stored block.
+fn stored(bits: &mut Bits, output: &mut Vec<u8>, limit: usize) -> Result<(), Error> {
+    bits.align();
+    let header = bits.bytes(4)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+    if len != !complement { return Err(Error::InvalidArgument); }
+
+    let data = bits.bytes(len as usize)?;
+    if output.len() + data.len() > limit { return Err(Error::OutOfBounds); }
+    output.extend_from_slice(data);
+
+    Ok(())
+}
+
+/// Returns the fixed literal/length and distance codes.
+fn fixed_codes() -> Result<(Huffman, Huffman), Error> {
+    let mut lengths = [0u8; MAX_LITERALS];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; MAX_DISTANCES])?))
+}
+
+/// Reads the literal/length and distance codes of a dynamic block.
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), Error> {
+    let literal_count = bits.take(5)? as usize + 257;
+    let distance_count = bits.take(5)? as usize + 1;
+    let code_length_count = bits.take(4)? as usize + 4;
+    if literal_count > 286 || distance_count > MAX_DISTANCES { return Err(Error::InvalidArgument); }
+
+    // The lengths of both codes are themselves Huffman coded.
+    let mut code_lengths = [0u8; 19];
+    for &idx in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[idx] = bits.take(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    // The lengths of both codes run on from one into the other, repeats included.
+    let mut lengths = [0u8; MAX_LITERALS + MAX_DISTANCES];
+    let total = literal_count + distance_count;
+    let mut idx = 0;
+    while idx < total {
+        let (len, repeat) = match code_length_code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 if idx > 0 => (lengths[idx - 1], 3 + bits.take(2)? as usize),
+            17 => (0, 3 + bits.take(3)? as usize),
+            18 => (0, 11 + bits.take(7)? as usize),
+            _ => return Err(Error::InvalidArgument),
+        };
+        if idx + repeat > total { return Err(Error::InvalidArgument); }
+        lengths[idx..(idx + repeat)].fill(len);
+        idx += repeat;
+    }
+    if lengths[END_OF_BLOCK as usize] == 0 { return Err(Error::InvalidArgument); }
+
+    let literals = Huffman::new(&lengths[..literal_count])?;
+    let distances = Huffman::new(&lengths[literal_count..total])?;
+
+    Ok((literals, distances))
+}
+
+/// Decodes a compressed block.
+fn codes(bits: &mut Bits, output: &mut Vec<u8>, limit: usize, literals: &Huffman, distances: &Huffman) -> Result<(), Error> {
+    loop {
+        let symbol = literals.decode(bits)?;
+        match symbol {
+            0..=255 => {
+                if output.len() >= limit { return Err(Error::OutOfBounds); }
+                output.push(symbol as u8);
+            }
+            END_OF_BLOCK => return Ok(()),
+            _ => {
+                let idx = (symbol - 257) as usize;
+                if idx >= LENGTH_BASE.len() { return Err(Error::InvalidArgument); }
+                let len = LENGTH_BASE[idx] as usize + bits.take(LENGTH_EXTRA[idx] as u32)? as usize;
+
+                let idx = distances.decode(bits)? as usize;
+                if idx >= DISTANCE_BASE.len() { return Err(Error::InvalidArgument); }
+                let distance = DISTANCE_BASE[idx] as usize + bits.take(DISTANCE_EXTRA[idx] as u32)? as usize;
+
+                if distance > output.len() { return Err(Error::InvalidArgument); }
+                if output.len() + len > limit { return Err(Error::OutOfBounds); }
+                // The run may overlap the bytes it produces, so it is copied a byte at a time.
+                let start = output.len() - distance;
+                for idx in 0..len {
+                    output.push(output[start + idx]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+
+use crate::kernel::compress::inflate;
+use crate::kernel::error::Error;
+
+// Zlib
+//
+// A zlib stream is a two-byte header, a DEFLATE stream, and the Adler-32 checksum of the decompressed
+// data. Streams that depend on a preset dictionary are not supported.
+//
+// RFC 1950: https://www.rfc-editor.org/rfc/rfc1950
+
+////////////////
+// Attributes
+////////////////
+
+/// Compression method of DEFLATE.
+const DEFLATE: u8 = 8;
+/// Flag of a preset dictionary.
+const FDICT: u8 = 0x20;
+/// Modulus of the sums of the Adler-32 checksum.
+const ADLER_MODULUS: u32 = 65521;
+
+///////////////
+// Utilities
+///////////////
+
+/// Decompresses the stream.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> { decompress_with_limit(data, inflate::MAX_OUTPUT) }
+
+/// Decompresses the stream into at most `limit` bytes.
+pub fn decompress_with_limit(data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    let (cmf, flg) = match data {
+        [cmf, flg, ..] => (*cmf, *flg),
+        _ => return Err(Error::InvalidArgument),
+    };
+    // The header read as a big-endian number is a multiple of 31.
+    if (cmf as u16 * 256 + flg as u16) % 31 != 0 { return Err(Error::InvalidArgument); }
+    if cmf & 0x0F != DEFLATE || flg & FDICT != 0 { return Err(Error::Unsupported); }
+
+    let (output, len) = inflate::decompress(&data[2..], limit)?;
+    let trailer = data.get((2 + len)..(2 + len + 4)).ok_or(Error::InvalidArgument)?;
+    if adler32(&output) != u32::from_be_bytes(trailer.try_into().unwrap()) { return Err(Error::InvalidArgument); }
+
+    Ok(output)
+}
+
+/// Returns the Adler-32 checksum of the data.
+pub fn adler32(data: &[u8]) -> u32 {
+    // The sums can take this many bytes before they may overflow and have to be reduced.
+    const CHUNK_SIZE: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(CHUNK_SIZE) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= ADLER_MODULUS;
+        b %= ADLER_MODULUS;
+    }
+    b << 16 | a
+}
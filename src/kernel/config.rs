@@ -0,0 +1,275 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::api::{keyboard, serial, vga};
+use crate::api::keyboard::{Key, Layout, Scancodes};
+use crate::aux::locale::Locale;
+use crate::aux::logger;
+use crate::aux::logger::{LogLevel, Theme};
+use crate::devices::console;
+use crate::devices::console::Overflow;
+use crate::kernel::allocator;
+use crate::kernel::allocator::Strategy;
+use crate::kernel::env;
+#[cfg(debug_assertions)]
+use crate::kernel::portio;
+use crate::kernel::error::Error;
+use crate::kernel::memory::pressure;
+use crate::kernel::memory::pressure::Resource;
+use crate::kernel::selftest;
+
+pub mod persistent;
+
+// System Configuration
+//
+// Settings are named values that take effect as soon as they are set. Only the settings that have
+// been set are recorded; the others keep the defaults chosen at boot, so that a configuration file
+// only has to mention what it changes.
+
+/////////////
+// Globals
+/////////////
+
+/// Validates the value of a setting and puts it into effect.
+pub type Apply = fn(&str) -> Result<(), Error>;
+
+/// Available settings.
+pub const SETTINGS: [(&str, Apply); 19] = [
+    ("allocator", apply_allocator),
+    ("async_output", apply_async_output),
+    ("frame_watermark", apply_frame_watermark),
+    ("heap_watermark", apply_heap_watermark),
+    ("history_size", apply_history_size),
+    ("hostname", apply_hostname),
+    ("input_overflow", apply_input_overflow),
+    ("key_remap", apply_key_remap),
+    ("keyboard", apply_keyboard),
+    ("language", apply_language),
+    ("log_level", apply_log_level),
+    ("log_theme", apply_log_theme),
+    ("output_tags", apply_output_tags),
+    ("palette", apply_palette),
+    ("port_trace", apply_port_trace),
+    ("scancodes", apply_scancodes),
+    ("screen_blank", apply_screen_blank),
+    ("selftest", apply_selftest),
+    ("serial_mux", apply_serial_mux),
+];
+
+/////////////
+// Mutexes
+/////////////
+
+/// Values of the settings that have been set.
+static VALUES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the value of the setting, or `None` if it has not been set.
+pub fn get(key: &str) -> Result<Option<String>, Error> {
+    if !SETTINGS.iter().any(|(name, _)| *name == key) { return Err(Error::NotFound); }
+    Ok(instructions::interrupts::without_interrupts(|| VALUES.lock().get(key).cloned()))
+}
+
+/// Puts the value of the setting into effect and records it.
+pub fn set(key: &str, value: &str) -> Result<(), Error> {
+    let (_, apply) = SETTINGS.iter().find(|(name, _)| *name == key).ok_or(Error::NotFound)?;
+    apply(value)?;
+
+    instructions::interrupts::without_interrupts(
+        || { VALUES.lock().insert(key.to_string(), value.to_string()); }
+    );
+
+    Ok(())
+}
+
+/// Calls the given function for each available setting along with its value, if set.
+pub fn for_each(mut f: impl FnMut(&str, Option<&str>)) {
+    let values = instructions::interrupts::without_interrupts(|| VALUES.lock().clone());
+    for (key, _) in SETTINGS {
+        f(key, values.get(key).map(|value| value.as_str()));
+    }
+}
+
+/// Selects the allocator serving new heap allocations.
+fn apply_allocator(value: &str) -> Result<(), Error> {
+    allocator::set_strategy(Strategy::from_str(value)?)
+}
+
+/// Sets whether printing only queues the output, to be written to the screen by a task.
+fn apply_async_output(value: &str) -> Result<(), Error> {
+    match value {
+        "0" => vga::disable_async_output(),
+        "1" => vga::enable_async_output(),
+        _ => return Err(Error::InvalidArgument),
+    }
+    Ok(())
+}
+
+/// Sets the percentage of the physical frames that should stay free before memory is reclaimed.
+fn apply_frame_watermark(value: &str) -> Result<(), Error> {
+    pressure::set_watermark(Resource::Frames, value.parse::<usize>().map_err(|_| Error::InvalidArgument)?)
+}
+
+/// Sets the percentage of the heap that should stay free before memory is reclaimed.
+fn apply_heap_watermark(value: &str) -> Result<(), Error> {
+    pressure::set_watermark(Resource::Heap, value.parse::<usize>().map_err(|_| Error::InvalidArgument)?)
+}
+
+/// Sets the number of lines kept in the shell history, which is exported as `HISTSIZE`.
+fn apply_history_size(value: &str) -> Result<(), Error> {
+    value.parse::<usize>().map_err(|_| Error::InvalidArgument)?;
+    env::set("HISTSIZE", value)
+}
+
+/// Sets the host name, which is also exported as `HOSTNAME`.
+fn apply_hostname(value: &str) -> Result<(), Error> {
+    const MAX_LEN: usize = 63;
+
+    let is_valid = !value.is_empty()
+        && value.len() <= MAX_LEN
+        && !value.starts_with('-')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !is_valid { return Err(Error::InvalidArgument); }
+
+    env::set("HOSTNAME", value)
+}
+
+/// Sets whether keys are dropped or the keyboard is held off once the console input queue fills up.
+fn apply_input_overflow(value: &str) -> Result<(), Error> {
+    console::set_overflow(Overflow::from_str(value)?);
+    Ok(())
+}
+
+/// Makes keys act as others, given as comma-separated `from:to` pairs of key names.
+fn apply_key_remap(value: &str) -> Result<(), Error> {
+    let remap = value.split(',').filter(|pair| !pair.trim().is_empty()).map(
+        |pair| match pair.split_once(':') {
+            Some((from, to)) => Ok((Key::from_name(from.trim())?, Key::from_name(to.trim())?)),
+            None => Err(Error::InvalidArgument),
+        }
+    ).collect::<Result<Vec<_>, Error>>()?;
+
+    keyboard::set_remap(&remap);
+    Ok(())
+}
+
+/// Sets the keyboard layout.
+fn apply_keyboard(value: &str) -> Result<(), Error> {
+    keyboard::set_layout(Layout::from_str(value)?);
+    Ok(())
+}
+
+/// Sets the language of messages, which is exported as `LANG`.
+fn apply_language(value: &str) -> Result<(), Error> {
+    Locale::from_str(value)?;
+    env::set("LANG", value)
+}
+
+/// Sets the log level.
+fn apply_log_level(value: &str) -> Result<(), Error> {
+    logger::set_log_level(LogLevel::from_str(value)?);
+    Ok(())
+}
+
+/// Sets the theme of the logger and command diagnostics.
+fn apply_log_theme(value: &str) -> Result<(), Error> {
+    logger::set_theme(Theme::from_str(value)?);
+    Ok(())
+}
+
+/// Sets whether lines are tagged with the task that writes them.
+fn apply_output_tags(value: &str) -> Result<(), Error> {
+    match value {
+        "0" => vga::disable_output_tags(),
+        "1" => vga::enable_output_tags(),
+        _ => return Err(Error::InvalidArgument),
+    }
+    Ok(())
+}
+
+/// Sets the VGA color palette.
+fn apply_palette(value: &str) -> Result<(), Error> {
+    vga::set_palette(vga::palette::from_name(value)?);
+    Ok(())
+}
+
+/// Sets whether port accesses are traced to the serial output, which only debug builds support.
+fn apply_port_trace(value: &str) -> Result<(), Error> {
+    let enabled = match value {
+        "0" => false,
+        "1" => true,
+        _ => return Err(Error::InvalidArgument),
+    };
+
+    #[cfg(debug_assertions)]
+    portio::set_tracing(enabled);
+    #[cfg(not(debug_assertions))]
+    if enabled { return Err(Error::Unsupported); }
+
+    Ok(())
+}
+
+/// Selects the scancode set of the keyboard, or `auto` for the one it identifies with.
+fn apply_scancodes(value: &str) -> Result<(), Error> {
+    let scancodes = match value {
+        "auto" => None,
+        _ => Some(Scancodes::from_str(value)?),
+    };
+    keyboard::set_scancodes(scancodes).map(|_| ())
+}
+
+/// Sets the minutes without input after which the screen is blanked, or zero to never blank it.
+fn apply_screen_blank(value: &str) -> Result<(), Error> {
+    vga::set_blank_timeout(value.parse::<usize>().map_err(|_| Error::InvalidArgument)?);
+    Ok(())
+}
+
+/// Sets whether the self-tests run at boot.
+fn apply_selftest(value: &str) -> Result<(), Error> {
+    match value {
+        "0" => selftest::set_enabled(false),
+        "1" => selftest::set_enabled(true),
+        _ => return Err(Error::InvalidArgument),
+    }
+    Ok(())
+}
+
+/// Sets whether the serial output is framed by channel, to tell the console from the logger.
+fn apply_serial_mux(value: &str) -> Result<(), Error> {
+    match value {
+        "0" => serial::disable_mux(),
+        "1" => serial::enable_mux(),
+        _ => return Err(Error::InvalidArgument),
+    }
+    Ok(())
+}
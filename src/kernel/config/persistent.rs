@@ -0,0 +1,86 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::warning;
+use crate::kernel::config;
+use crate::kernel::error::Error;
+use crate::kernel::vfs;
+
+// Persistent Configuration
+//
+// The configuration file holds one `key=value` pair per line. Blank lines and lines starting with `#`
+// are ignored. It is read once the root filesystem has been mounted, and rewritten with the recorded
+// settings whenever they are stored.
+//
+// Note: A setting that can not be applied is reported and skipped, so that a single bad line does not
+// throw away the rest of the file.
+
+////////////////
+// Attributes
+////////////////
+
+/// Path of the configuration file.
+pub const PATH: &str = "/etc/system.conf";
+
+///////////////
+// Utilities
+///////////////
+
+/// Applies the settings found in the configuration file, if there is one.
+pub(crate) fn load() -> Result<(), Error> {
+    let data = match vfs::read(PATH) {
+        Ok(data) => data,
+        Err(Error::NotFound) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let text = String::from_utf8(data).map_err(|_| Error::InvalidArgument)?;
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        let res = match line.split_once('=') {
+            Some((key, value)) => config::set(key.trim(), value.trim()),
+            None => Err(Error::InvalidArgument),
+        };
+        if let Err(e) = res { warning!("Config: {}:{}: {}", PATH, idx + 1, e); }
+    }
+
+    Ok(())
+}
+
+/// Writes the recorded settings to the configuration file.
+pub fn store() -> Result<(), Error> {
+    let mut text = String::from("# System configuration, written by `config`.\n");
+    let mut res = Ok(());
+    config::for_each(
+        |key, value| {
+            if let Some(value) = value { res = res.and_then(|_| writeln!(text, "{}={}", key, value)); }
+        }
+    );
+    res?;
+
+    vfs::write(PATH, text.as_bytes())
+}
@@ -0,0 +1,124 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use raw_cpuid::CpuId;
+
+use crate::kernel::acpi::madt;
+
+// CPU Identification
+//
+// The CPUID instruction describes the processor: its vendor, its family, model and stepping, the
+// marketing name (brand string), and the instruction set extensions and features it implements.
+//
+// OS Dev Wiki: https://wiki.osdev.org/CPUID
+
+////////////
+/// Info
+////////////
+#[derive(Debug, Clone)]
+pub struct Info {
+    pub vendor: String,
+    pub brand: String,
+    pub family: u8,
+    pub model: u8,
+    pub stepping: u8,
+    /// Number of processors described by the firmware, including the bootstrap processor.
+    pub count: usize,
+    /// Names of the notable features the processor implements.
+    pub features: Vec<&'static str>,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the identification of the processor.
+pub fn info() -> Info {
+    let cpuid = CpuId::new();
+
+    let vendor = cpuid.get_vendor_info().map_or_else(String::new, |vendor| vendor.as_str().to_string());
+    let brand = cpuid.get_processor_brand_string().map_or_else(String::new, |brand| brand.as_str().trim().to_string());
+    let (family, model, stepping) = cpuid.get_feature_info()
+        .map_or((0, 0, 0), |info| (info.family_id(), info.model_id(), info.stepping_id()));
+    let count = madt::get_processor_info().map_or(1, |info| info.application_processors.len() + 1);
+
+    Info { vendor, brand, family, model, stepping, count, features: features(&cpuid) }
+}
+
+/// Returns the names of the notable features the processor implements.
+fn features(cpuid: &CpuId) -> Vec<&'static str> {
+    let mut features = Vec::new();
+
+    if let Some(info) = cpuid.get_feature_info() {
+        let flags = [
+            ("apic", info.has_apic()),
+            ("x2apic", info.has_x2apic()),
+            ("tsc", info.has_tsc()),
+            ("tsc-deadline", info.has_tsc_deadline()),
+            ("msr", info.has_msr()),
+            ("pae", info.has_pae()),
+            ("pge", info.has_pge()),
+            ("pat", info.has_pat()),
+            ("pcid", info.has_pcid()),
+            ("mwait", info.has_monitor_mwait()),
+            ("sse", info.has_sse()),
+            ("sse2", info.has_sse2()),
+            ("sse3", info.has_sse3()),
+            ("ssse3", info.has_ssse3()),
+            ("sse4.1", info.has_sse41()),
+            ("sse4.2", info.has_sse42()),
+            ("popcnt", info.has_popcnt()),
+            ("aes", info.has_aesni()),
+            ("avx", info.has_avx()),
+            ("fma", info.has_fma()),
+            ("xsave", info.has_xsave()),
+            ("rdrand", info.has_rdrand()),
+            ("vmx", info.has_vmx()),
+            ("hypervisor", info.has_hypervisor()),
+        ];
+        features.extend(flags.iter().filter(|(_, present)| *present).map(|(name, _)| *name));
+    }
+
+    if let Some(info) = cpuid.get_extended_feature_info() {
+        let flags = [
+            ("avx2", info.has_avx2()),
+            ("avx512f", info.has_avx512f()),
+            ("bmi1", info.has_bmi1()),
+            ("rdseed", info.has_rdseed()),
+        ];
+        features.extend(flags.iter().filter(|(_, present)| *present).map(|(name, _)| *name));
+    }
+
+    if let Some(info) = cpuid.get_extended_processor_and_feature_identifiers() {
+        let flags = [
+            ("nx", info.has_execute_disable()),
+            ("rdtscp", info.has_rdtscp()),
+            ("1gb-pages", info.has_1gib_pages()),
+        ];
+        features.extend(flags.iter().filter(|(_, present)| *present).map(|(name, _)| *name));
+    }
+
+    features
+}
@@ -0,0 +1,91 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+// CRC-32
+//
+// The cyclic redundancy check used by Ethernet, PNG, zip and gzip, with the reflected polynomial
+// 0xEDB88320, an initial value of all ones and the result inverted. The table for processing a byte
+// at a time is computed at compile time.
+//
+// Wikipedia: https://en.wikipedia.org/wiki/Cyclic_redundancy_check
+
+////////////////
+// Attributes
+////////////////
+
+/// Reflected generator polynomial.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Remainders of each byte value.
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ POLYNOMIAL } else { crc >> 1 };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+};
+
+/////////////
+/// CRC32
+/////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    /// Creates a new object with no data checked yet.
+    pub const fn new() -> Self { Crc32 { state: !0 } }
+
+    /// Feeds the data into the checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.state = TABLE[((self.state ^ byte as u32) & 0xFF) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// Returns the checksum of the data fed so far.
+    pub fn finish(&self) -> u32 { !self.state }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self { Self::new() }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the checksum of the data.
+pub fn checksum(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
@@ -0,0 +1,424 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::{apprise, failure, success};
+use crate::kernel::{allocator, boot, initcall};
+use crate::kernel::initcall::Level;
+use crate::kernel::error::{Error, FaultKind};
+
+// Device Manager
+//
+// Every driver describes itself with a static `Device` descriptor and registers it during boot. The
+// device manager then initializes the registered devices in dependency order: a device is only
+// initialized once all the devices it depends on have been initialized successfully. A device whose
+// dependency failed (or was never registered) is marked as failed itself.
+//
+// Initialization is split into stages, which run in order. A critical device that fails aborts the
+// boot once its stage has completed, whereas a non-critical device that fails is merely recorded in
+// the boot report and the system carries on without it.
+//
+// A driver whose hardware is not installed reports `FaultKind::NotPresent`; the device is then marked
+// as absent rather than failed. The devices depending on it are marked as absent too, unless they
+// tolerate an absent dependency, in which case the dependency merely orders their initialization.
+//
+// Devices may optionally provide power hooks that are invoked when the system is suspended and
+// resumed.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of registered devices.
+const MAX_DEVICES: usize = 32;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of registered devices.
+static REGISTRY: Mutex<[Option<Entry>; MAX_DEVICES]> = Mutex::new([None; MAX_DEVICES]);
+
+/////////////
+/// Class
+/////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Class {
+    System = 0x0,
+    Processor = 0x1,
+    Memory = 0x2,
+    Firmware = 0x3,
+    Display = 0x4,
+    Input = 0x5,
+    Serial = 0x6,
+    Storage = 0x7,
+    Network = 0x8,
+    Bus = 0x9,
+}
+
+impl Class {
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::System => "system",
+            Self::Processor => "processor",
+            Self::Memory => "memory",
+            Self::Firmware => "firmware",
+            Self::Display => "display",
+            Self::Input => "input",
+            Self::Serial => "serial",
+            Self::Storage => "storage",
+            Self::Network => "network",
+            Self::Bus => "bus",
+        }
+    }
+}
+
+/////////////
+/// Stage
+/////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Stage {
+    /// Output devices needed to report the progress of the boot.
+    Console = 0x0,
+    /// Processor tables, interrupts, timers and memory.
+    Core = 0x1,
+    /// Firmware tables and the devices discovered through them.
+    Platform = 0x2,
+    /// Peripheral devices.
+    Driver = 0x3,
+}
+
+impl Stage {
+    /// All stages in order of execution.
+    pub const ALL: [Stage; 4] = [Stage::Console, Stage::Core, Stage::Platform, Stage::Driver];
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Console => "console",
+            Self::Core => "core",
+            Self::Platform => "platform",
+            Self::Driver => "driver",
+        }
+    }
+}
+
+//////////////
+/// Status
+//////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Status {
+    Pending = 0x0,
+    Active = 0x1,
+    Failed = 0x2,
+    Suspended = 0x3,
+    Absent = 0x4,
+}
+
+impl Status {
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Pending => "pending",
+            Self::Active => "active",
+            Self::Failed => "failed",
+            Self::Suspended => "suspended",
+            Self::Absent => "absent",
+        }
+    }
+}
+
+/// Initialization or power hook of a device.
+pub type Hook = fn() -> Result<(), Error>;
+
+//////////////
+/// Device
+//////////////
+pub struct Device {
+    /// Unique name of the device.
+    pub name: &'static str,
+    /// Class of the device.
+    pub class: Class,
+    /// Stage in which the device is initialized.
+    pub stage: Stage,
+    /// Whether the system can not boot without the device.
+    pub critical: bool,
+    /// Names of the devices that must be initialized first.
+    pub depends: &'static [&'static str],
+    /// Whether the device is still initialized when a dependency is absent.
+    pub tolerates_absent: bool,
+    /// Initializes the device.
+    pub init: Hook,
+    /// Prepares the device for a system suspend.
+    pub suspend: Option<Hook>,
+    /// Restores the device after a system resume.
+    pub resume: Option<Hook>,
+}
+
+/////////////
+/// Entry
+/////////////
+#[derive(Clone, Copy)]
+struct Entry {
+    device: &'static Device,
+    status: Status,
+    /// Position of the device in the order of initialization, once it has been initialized.
+    order: Option<usize>,
+}
+
+////////////
+/// Info
+////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Info {
+    pub name: &'static str,
+    pub class: Class,
+    pub stage: Stage,
+    pub critical: bool,
+    pub status: Status,
+    pub has_power_hooks: bool,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Registers a device.
+pub fn register(device: &'static Device) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut registry = REGISTRY.lock();
+
+            if registry.iter().flatten().any(|e| e.device.name == device.name) { return Err(Error::AlreadyExists); }
+
+            let slot = registry.iter_mut().find(|e| e.is_none()).ok_or(Error::OutOfResources)?;
+            *slot = Some(Entry { device, status: Status::Pending, order: None });
+
+            Ok(())
+        }
+    )
+}
+
+/// Initializes all pending devices, stage by stage, in dependency order, running the initcalls of
+/// each level after the stage it follows.
+///
+/// Note: Panics if a critical device fails to initialize.
+pub fn init() {
+    for stage in Stage::ALL {
+        init_stage(stage);
+
+        let mut critical_failure = None;
+        for_each(|info| {
+            if info.stage == stage && info.critical && info.status == Status::Failed {
+                critical_failure = Some(info.name);
+            }
+        });
+        if let Some(name) = critical_failure {
+            panic!("boot aborted: critical device '{}' failed during the {} stage", name, stage.as_str());
+        }
+
+        if let Some(level) = Level::after(stage) { initcall::run(level); }
+    }
+}
+
+/// Initializes the pending devices of the given stage in dependency order.
+fn init_stage(stage: Stage) {
+    while let Some((idx, device)) = next_ready(stage) {
+        let status = match boot::measure(device.name, || allocator::tagged(device.name, device.init)) {
+            Ok(_) => {
+                success!("{}: initialized", device.name);
+                Status::Active
+            }
+            Err(Error::Hardware(FaultKind::NotPresent)) => {
+                apprise!("{}: not present", device.name);
+                Status::Absent
+            }
+            Err(e) => {
+                failure!("{}: initialization failed: {}", device.name, e);
+                Status::Failed
+            }
+        };
+        set_initialized(idx, status);
+    }
+
+    // Whatever is still pending depends on a device that is missing or has not been initialized yet.
+    for idx in 0..MAX_DEVICES {
+        if let Some(device) = pending_at(idx).filter(|d| d.stage == stage) {
+            failure!("{}: unmet dependencies", device.name);
+            set_status(idx, Status::Failed);
+        }
+    }
+}
+
+/// Suspends all active devices, in reverse order of initialization.
+pub fn suspend() {
+    let (order, count) = init_order();
+    for &idx in order[..count].iter().rev() {
+        if let Some(hook) = hook_at(idx, Status::Active, |d| d.suspend) {
+            if hook().is_ok() { set_status(idx, Status::Suspended); }
+        }
+    }
+}
+
+/// Resumes all suspended devices, in order of initialization.
+pub fn resume() {
+    let (order, count) = init_order();
+    for &idx in order[..count].iter() {
+        if let Some(hook) = hook_at(idx, Status::Suspended, |d| d.resume) {
+            if hook().is_ok() { set_status(idx, Status::Active); }
+        }
+    }
+}
+
+/// Returns the status of the named device.
+pub fn status(name: &str) -> Option<Status> {
+    instructions::interrupts::without_interrupts(
+        || { REGISTRY.lock().iter().flatten().find(|e| e.device.name == name).map(|e| e.status) }
+    )
+}
+
+/// Calls the given function for each registered device.
+pub fn for_each(mut f: impl FnMut(&Info)) {
+    let registry = instructions::interrupts::without_interrupts(|| { *REGISTRY.lock() });
+    for entry in registry.iter().flatten() {
+        f(&Info {
+            name: entry.device.name,
+            class: entry.device.class,
+            stage: entry.device.stage,
+            critical: entry.device.critical,
+            status: entry.status,
+            has_power_hooks: entry.device.suspend.is_some() || entry.device.resume.is_some(),
+        });
+    }
+}
+
+/// Returns the first pending device of the given stage whose dependencies are all resolved.
+///
+/// Note: Pending devices with a failed dependency are marked as failed along the way, and those with
+/// an absent dependency they do not tolerate as absent.
+fn next_ready(stage: Stage) -> Option<(usize, &'static Device)> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut registry = REGISTRY.lock();
+
+            let status_of = |registry: &[Option<Entry>], name: &str| {
+                registry.iter().flatten().find(|e| e.device.name == name).map(|e| e.status)
+            };
+
+            for idx in 0..MAX_DEVICES {
+                let device = match registry[idx] {
+                    Some(Entry { device, status: Status::Pending, .. }) if device.stage == stage => device,
+                    _ => continue,
+                };
+                let deps = device.depends.iter().map(|d| status_of(&registry[..], d));
+                let is_resolved = |s: Option<Status>| {
+                    s == Some(Status::Active) || (device.tolerates_absent && s == Some(Status::Absent))
+                };
+                if deps.clone().any(|s| s == Some(Status::Failed)) {
+                    failure!("{}: dependency failed", device.name);
+                    registry[idx].as_mut().unwrap().status = Status::Failed;
+                } else if !device.tolerates_absent && deps.clone().any(|s| s == Some(Status::Absent)) {
+                    apprise!("{}: dependency not present", device.name);
+                    registry[idx].as_mut().unwrap().status = Status::Absent;
+                } else if deps.clone().all(is_resolved) {
+                    return Some((idx, device));
+                }
+            }
+
+            None
+        }
+    )
+}
+
+/// Returns the device at the given index if it is pending.
+fn pending_at(idx: usize) -> Option<&'static Device> {
+    instructions::interrupts::without_interrupts(
+        || {
+            match REGISTRY.lock()[idx] {
+                Some(Entry { device, status: Status::Pending, .. }) => Some(device),
+                _ => None,
+            }
+        }
+    )
+}
+
+/// Returns a power hook of the device at the given index if it is in the given state.
+fn hook_at(idx: usize, state: Status, hook: fn(&Device) -> Option<Hook>) -> Option<Hook> {
+    instructions::interrupts::without_interrupts(
+        || {
+            match REGISTRY.lock()[idx] {
+                Some(Entry { device, status, .. }) if status == state => hook(device),
+                _ => None,
+            }
+        }
+    )
+}
+
+/// Returns the indices of the initialized devices in order of initialization, along with their count.
+fn init_order() -> ([usize; MAX_DEVICES], usize) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut order = [0; MAX_DEVICES];
+            let mut count = 0;
+            for (idx, entry) in REGISTRY.lock().iter().enumerate() {
+                if let Some(pos) = entry.and_then(|e| e.order) {
+                    order[pos] = idx;
+                    count += 1;
+                }
+            }
+
+            (order, count)
+        }
+    )
+}
+
+/// Sets the status of the device at the given index once it has been initialized, and records its
+/// position in the order of initialization.
+fn set_initialized(idx: usize, status: Status) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut registry = REGISTRY.lock();
+            let order = registry.iter().flatten().filter(|e| e.order.is_some()).count();
+            if let Some(entry) = registry[idx].as_mut() {
+                entry.status = status;
+                entry.order = Some(order);
+            }
+        }
+    );
+}
+
+/// Sets the status of the device at the given index.
+fn set_status(idx: usize, status: Status) {
+    instructions::interrupts::without_interrupts(
+        || {
+            if let Some(entry) = REGISTRY.lock()[idx].as_mut() {
+                entry.status = status;
+            }
+        }
+    );
+}
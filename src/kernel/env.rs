@@ -0,0 +1,178 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::error::Error;
+
+// Environment
+//
+// A kernel-wide table of variables shared by every process. Variables are plain strings; consumers
+// such as the shell (PROMPT, PATH), the clock (TZ) and the message catalog (LANG) read them whenever
+// they need them, so a change takes effect immediately.
+//
+// The table can be exported to and imported from `NAME=VALUE` lines, which is the format used to keep
+// it on disk once a filesystem is available.
+
+////////////////////
+// Configurations
+////////////////////
+
+/// Variables defined at boot.
+const DEFAULTS: [(&str, &str); 4] = [
+    ("LANG", "en"),
+    ("PATH", "/bin"),
+    ("PROMPT", "\x1B[94m$\x1B[0m "),
+    ("TZ", "UTC"),
+];
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of variables.
+static VARIABLES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+///////////////
+// Utilities
+///////////////
+
+/// Defines the default variables.
+pub(crate) fn init() -> Result<(), Error> {
+    for (name, value) in DEFAULTS {
+        set(name, value).ok();
+    }
+    Ok(())
+}
+
+/// Returns the value of the variable.
+pub fn get(name: &str) -> Option<String> {
+    instructions::interrupts::without_interrupts(
+        || { VARIABLES.lock().get(name).cloned() }
+    )
+}
+
+/// Sets the value of the variable, defining it if necessary.
+pub fn set(name: &str, value: &str) -> Result<(), Error> {
+    if !is_valid_name(name) { return Err(Error::InvalidArgument); }
+
+    instructions::interrupts::without_interrupts(
+        || { VARIABLES.lock().insert(name.to_string(), value.to_string()); }
+    );
+
+    Ok(())
+}
+
+/// Removes the variable.
+pub fn unset(name: &str) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || { VARIABLES.lock().remove(name) }
+    ).map(|_| ()).ok_or(Error::NotFound)
+}
+
+/// Calls the given function for each variable, in alphabetical order.
+pub fn for_each(mut f: impl FnMut(&str, &str)) {
+    let variables = instructions::interrupts::without_interrupts(
+        || { VARIABLES.lock().clone() }
+    );
+    for (name, value) in variables.iter() {
+        f(name, value);
+    }
+}
+
+/// Returns the variables as `NAME=VALUE` lines.
+pub fn export() -> String {
+    let mut text = String::new();
+    for_each(|name, value| {
+        text.push_str(name);
+        text.push('=');
+        text.push_str(&escape(value));
+        text.push('\n');
+    });
+    text
+}
+
+/// Defines the variables from `NAME=VALUE` lines, skipping blank lines and `#` comments.
+pub fn import(text: &str) -> Result<(), Error> {
+    for line in text.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let (name, value) = line.split_once('=').ok_or(Error::InvalidArgument)?;
+        set(name.trim(), &unescape(value))?;
+    }
+
+    Ok(())
+}
+
+/// Returns whether the given string can be used as the name of a variable.
+///
+/// Note: A name consists of ASCII letters, digits and underscores, and does not begin with a digit.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+/// Escapes the characters that can not appear verbatim on a line.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\x1B' => escaped.push_str("\\e"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverts `escape`.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('e') => unescaped.push('\x1B'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Returns the directories listed in `PATH`.
+pub fn search_path() -> Vec<String> {
+    get("PATH")
+        .map(|path| path.split(':').filter(|d| !d.is_empty()).map(|d| d.to_string()).collect())
+        .unwrap_or_default()
+}
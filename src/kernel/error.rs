@@ -0,0 +1,162 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::TryReserveError;
+use core::fmt;
+
+use acpi::AcpiError;
+use aml::AmlError;
+#[cfg(target_arch = "x86_64")]
+use x86_64::structures::paging::mapper::MapToError;
+#[cfg(target_arch = "x86_64")]
+use x86_64::structures::paging::Size4KiB;
+
+// Kernel Error
+//
+// A single error type shared by the kernel, the drivers and the public API, so that failures can be
+// propagated with `?` across subsystem boundaries and reported to the user with a meaningful message
+// rather than a bare `Err(())`.
+
+/////////////////
+/// Fault Kind
+/////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FaultKind {
+    /// The device did not respond.
+    NotPresent = 0x0,
+    /// The device did not complete an operation in time.
+    Timeout = 0x1,
+    /// The device failed its self-test.
+    SelfTest = 0x2,
+    /// The device responded with something unexpected.
+    InvalidResponse = 0x3,
+}
+
+impl FaultKind {
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::NotPresent => "device not present",
+            Self::Timeout => "operation timed out",
+            Self::SelfTest => "self-test failed",
+            Self::InvalidResponse => "invalid response",
+        }
+    }
+}
+
+/////////////
+/// Error
+/////////////
+#[derive(Debug)]
+pub enum Error {
+    /// An index or position lies outside of the valid range.
+    OutOfBounds,
+    /// An argument could not be parsed or is not acceptable.
+    InvalidArgument,
+    /// The subsystem has not been initialized yet.
+    NotInitialized,
+    /// The operation is not supported by the hardware or the firmware.
+    Unsupported,
+    /// The requested object does not exist.
+    NotFound,
+    /// An object with the same identity already exists.
+    AlreadyExists,
+    /// The resource is in use by someone else.
+    Busy,
+    /// A fixed-size table has no free slots left.
+    OutOfResources,
+    /// Physical or heap memory is exhausted.
+    OutOfMemory,
+    /// The other end of a stream is gone or can not accept more data.
+    BrokenPipe,
+    /// A directory was expected.
+    NotADirectory,
+    /// A file was expected, but the path names a directory.
+    IsADirectory,
+    /// The directory still has entries.
+    NotEmpty,
+    /// The filesystem is mounted read-only.
+    ReadOnly,
+    /// The running code lacks the capability required by the operation.
+    PermissionDenied,
+    /// The operation failed, and the reasons have already been reported to the user.
+    Failed,
+    /// The hardware misbehaved.
+    Hardware(FaultKind),
+    /// The ACPI tables could not be parsed.
+    Acpi(AcpiError),
+    /// The AML bytecode could not be parsed or evaluated.
+    Aml(AmlError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds => write!(f, "out of bounds"),
+            Self::InvalidArgument => write!(f, "invalid argument"),
+            Self::NotInitialized => write!(f, "not initialized"),
+            Self::Unsupported => write!(f, "not supported"),
+            Self::NotFound => write!(f, "not found"),
+            Self::AlreadyExists => write!(f, "already exists"),
+            Self::Busy => write!(f, "resource busy"),
+            Self::OutOfResources => write!(f, "out of resources"),
+            Self::OutOfMemory => write!(f, "out of memory"),
+            Self::BrokenPipe => write!(f, "broken pipe"),
+            Self::NotADirectory => write!(f, "not a directory"),
+            Self::IsADirectory => write!(f, "is a directory"),
+            Self::NotEmpty => write!(f, "directory not empty"),
+            Self::ReadOnly => write!(f, "read-only filesystem"),
+            Self::PermissionDenied => write!(f, "permission denied"),
+            Self::Failed => write!(f, "operation failed"),
+            Self::Hardware(kind) => write!(f, "hardware fault: {}", kind.as_str()),
+            Self::Acpi(e) => write!(f, "ACPI error: {:?}", e),
+            Self::Aml(e) => write!(f, "AML error: {:?}", e),
+        }
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self { Self::BrokenPipe }
+}
+
+impl From<TryReserveError> for Error {
+    fn from(_: TryReserveError) -> Self { Self::OutOfMemory }
+}
+
+impl From<AcpiError> for Error {
+    fn from(value: AcpiError) -> Self { Self::Acpi(value) }
+}
+
+impl From<AmlError> for Error {
+    fn from(value: AmlError) -> Self { Self::Aml(value) }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl From<MapToError<Size4KiB>> for Error {
+    fn from(value: MapToError<Size4KiB>) -> Self {
+        match value {
+            MapToError::FrameAllocationFailed => Self::OutOfMemory,
+            MapToError::ParentEntryHugePage | MapToError::PageAlreadyMapped(_) => Self::AlreadyExists,
+        }
+    }
+}
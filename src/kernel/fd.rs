@@ -0,0 +1,239 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::error::Error;
+use crate::kernel::vfs;
+
+// File Descriptors
+//
+// An open file is referred to by a small integer, its file descriptor, which indexes the descriptor
+// table. Each entry remembers the canonical path of the file, the mode it was opened with, and the
+// offset at which the next read or write takes place.
+//
+// The table is global for now, since commands run as tasks sharing a single address space. The lowest
+// free descriptor is handed out first, as on Unix.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of files open at once.
+const MAX_OPEN_FILES: usize = 64;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of open files, indexed by file descriptor.
+static TABLE: Mutex<Vec<Option<OpenFile>>> = Mutex::new(Vec::new());
+
+//////////
+/// Fd
+//////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fd(usize);
+
+impl Fd {
+    /// Returns the descriptor as a primitive.
+    pub fn as_usize(&self) -> usize { self.0 }
+
+    /// Creates a new object from a primitive.
+    pub fn from_usize(fd: usize) -> Self { Fd(fd) }
+}
+
+/////////////////
+/// Open Mode
+/////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Reads an existing file.
+    Read,
+    /// Writes a file from the start, creating or truncating it.
+    Write,
+    /// Writes at the end of a file, creating it if necessary.
+    Append,
+    /// Reads and writes an existing file, starting at its beginning.
+    ReadWrite,
+}
+
+impl OpenMode {
+    /// Returns whether the file can be read through the descriptor.
+    fn is_readable(&self) -> bool { matches!(self, Self::Read | Self::ReadWrite) }
+
+    /// Returns whether the file can be written through the descriptor.
+    fn is_writable(&self) -> bool { !matches!(self, Self::Read) }
+}
+
+/////////////////
+/// Seek From
+/////////////////
+#[derive(Debug, Clone, Copy)]
+pub enum SeekFrom {
+    Start(usize),
+    End(isize),
+    Current(isize),
+}
+
+/////////////////
+/// Open File
+/////////////////
+struct OpenFile {
+    path: String,
+    mode: OpenMode,
+    offset: usize,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Opens the file in the given mode and returns its descriptor.
+pub fn open(path: &str, mode: OpenMode) -> Result<Fd, Error> {
+    let path = vfs::normalize(path)?;
+
+    match mode {
+        OpenMode::Read | OpenMode::ReadWrite => {
+            if vfs::metadata(&path)?.is_dir() { return Err(Error::IsADirectory); }
+        }
+        OpenMode::Write => vfs::write(&path, &[])?,
+        OpenMode::Append => vfs::append(&path, &[])?,
+    }
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut table = TABLE.lock();
+            let file = OpenFile { path, mode, offset: 0 };
+            match table.iter().position(|entry| entry.is_none()) {
+                Some(idx) => {
+                    table[idx] = Some(file);
+                    Ok(Fd(idx))
+                }
+                None if table.len() < MAX_OPEN_FILES => {
+                    table.push(Some(file));
+                    Ok(Fd(table.len() - 1))
+                }
+                None => Err(Error::OutOfResources),
+            }
+        }
+    )
+}
+
+/// Closes the descriptor.
+pub fn close(fd: Fd) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut table = TABLE.lock();
+            table.get_mut(fd.0).and_then(|entry| entry.take()).ok_or(Error::NotFound)?;
+            // Keep the table short once its tail is free.
+            while let Some(None) = table.last() { table.pop(); }
+            Ok(())
+        }
+    )
+}
+
+/// Reads from the current offset into `buf`, returning the number of bytes read (0 at the end).
+pub fn read(fd: Fd, buf: &mut [u8]) -> Result<usize, Error> {
+    let (path, mode, offset) = lookup(fd)?;
+    if !mode.is_readable() { return Err(Error::InvalidArgument); }
+
+    let data = vfs::read(&path)?;
+    let start = offset.min(data.len());
+    let count = buf.len().min(data.len() - start);
+    buf[..count].copy_from_slice(&data[start..start + count]);
+
+    set_offset(fd, start + count)?;
+    Ok(count)
+}
+
+/// Writes `data` at the current offset (or at the end in append mode), returning the number of bytes
+/// written.
+pub fn write(fd: Fd, data: &[u8]) -> Result<usize, Error> {
+    let (path, mode, offset) = lookup(fd)?;
+    if !mode.is_writable() { return Err(Error::InvalidArgument); }
+
+    if mode == OpenMode::Append {
+        vfs::append(&path, data)?;
+        let size = vfs::metadata(&path)?.size;
+        set_offset(fd, size)?;
+        return Ok(data.len());
+    }
+
+    // Writing past the end fills the gap with zeros.
+    let mut contents = vfs::read(&path)?;
+    let end = offset + data.len();
+    if contents.len() < end { contents.resize(end, 0); }
+    contents[offset..end].copy_from_slice(data);
+    vfs::write(&path, &contents)?;
+
+    set_offset(fd, end)?;
+    Ok(data.len())
+}
+
+/// Moves the offset of the descriptor, returning the new offset from the start of the file.
+pub fn seek(fd: Fd, pos: SeekFrom) -> Result<usize, Error> {
+    let (path, _, offset) = lookup(fd)?;
+
+    let target = match pos {
+        SeekFrom::Start(n) => Some(n),
+        SeekFrom::Current(n) => offset.checked_add_signed(n),
+        SeekFrom::End(n) => vfs::metadata(&path)?.size.checked_add_signed(n),
+    };
+    let target = target.ok_or(Error::OutOfBounds)?;
+
+    set_offset(fd, target)?;
+    Ok(target)
+}
+
+/// Returns the metadata of the file behind the descriptor.
+pub fn metadata(fd: Fd) -> Result<vfs::Metadata, Error> {
+    let (path, _, _) = lookup(fd)?;
+    vfs::metadata(&path)
+}
+
+/// Returns the path, mode and offset of the descriptor.
+fn lookup(fd: Fd) -> Result<(String, OpenMode, usize), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let table = TABLE.lock();
+            let file = table.get(fd.0).and_then(|entry| entry.as_ref()).ok_or(Error::NotFound)?;
+            Ok((file.path.clone(), file.mode, file.offset))
+        }
+    )
+}
+
+/// Sets the offset of the descriptor.
+fn set_offset(fd: Fd, offset: usize) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut table = TABLE.lock();
+            let file = table.get_mut(fd.0).and_then(|entry| entry.as_mut()).ok_or(Error::NotFound)?;
+            file.offset = offset;
+            Ok(())
+        }
+    )
+}
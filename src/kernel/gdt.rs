@@ -20,15 +20,40 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use conquer_once::spin::OnceCell;
 use lazy_static::lazy_static;
 use x86_64::addr::VirtAddr;
 use x86_64::instructions;
 use x86_64::instructions::segmentation::CS;
 use x86_64::instructions::segmentation::Segment;
 use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
+use x86_64::structures::paging::{Mapper, Page, PageTableFlags};
 use x86_64::structures::tss::TaskStateSegment;
 
+use crate::warning;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
 use crate::kernel::memory;
+use crate::kernel::memory::frame::{self, GlobalFrameAllocator};
+use crate::kernel::memory::layout;
+use crate::kernel::memory::PAGE_SIZE;
+
+// Kernel Stacks
+//
+// The stacks of the Interrupt Stack Table are mapped in a region of their own, each one directly
+// above an unmapped guard page. A stack that overflows faults on its guard page instead of silently
+// corrupting whatever lies below it.
+//
+// Since the CPU cannot push the exception frame of such a fault onto the very stack that overflowed,
+// the fault escalates to a double fault, which runs on a stack of its own and reports the overflow.
+// The boot stack set up by the bootloader has a guard page below it as well, which is located by
+// walking down the mapped pages from the current stack pointer.
+//
+// Should a stack fail to be mapped, e.g. for lack of frames, it falls back to a static one without a
+// guard page, so that the boot goes on.
 
 ////////////////
 // Attributes
@@ -37,53 +62,81 @@ use crate::kernel::memory;
 // A stack size of 8 pages (32 KiB).
 pub const STACK_SIZE: usize = 8 * memory::PAGE_SIZE;
 
+/// Start address of the region the stacks are mapped in.
+pub const STACKS_START: usize = layout::STACKS_START;
+/// Space reserved for each stack, including its guard page.
+const STACK_SLOT: usize = STACK_SIZE + PAGE_SIZE;
+
+/// Upper bound on the size of the boot stack, in pages.
+const MAX_BOOT_STACK_PAGES: usize = 4096;
+
 /////////////
 /// Stack
 /////////////
+///
+/// Indices of the stacks in the Interrupt Stack Table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
 pub enum Stack {
     DoubleFault = 0x0,
+    NonMaskable = 0x1,
+    MachineCheck = 0x2,
+    Debug = 0x3,
+    Interrupt = 0x4,
+}
+
+impl Stack {
+    /// List of all stacks.
+    pub const ALL: [Stack; 5] = [Stack::DoubleFault, Stack::NonMaskable, Stack::MachineCheck, Stack::Debug, Stack::Interrupt];
+
+    /// Returns the stack with the given index.
+    pub fn from_index(idx: usize) -> Option<Self> { Self::ALL.get(idx).copied() }
+
+    /// Returns the name of the stack.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stack::DoubleFault => "double fault",
+            Stack::NonMaskable => "NMI",
+            Stack::MachineCheck => "machine check",
+            Stack::Debug => "debug",
+            Stack::Interrupt => "interrupt",
+        }
+    }
 }
 
+///////////////////
+// Cached Values
+///////////////////
+
+/// Address of the guard page below the boot stack, or zero if it was not found.
+static BOOT_STACK_GUARD: AtomicUsize = AtomicUsize::new(0);
+
+/// Stacks used in place of those that could not be mapped.
+static mut FALLBACK_STACKS: [[u8; STACK_SIZE]; Stack::ALL.len()] = [[0; STACK_SIZE]; Stack::ALL.len()];
+
+/// Task State Segment (TSS)
+///
+/// A Task State Segment is a binary data structure specific to the IA-32 and x86-64 architectures.
+/// The TSS was used to hold various pieces of information (e.g., processor register state) about a
+/// task in 32-bit mode and was, for example, used for hardware context switching. However, hardware
+/// context switching is no longer supported in 64-bit mode and the format of the TSS has changed
+/// completely.
+///
+/// On x86, the TSS no longer holds any task-specific information at all. Instead, it holds two
+/// stack tables and an I/O port permissions bitmap:
+///
+/// 1. Privilege Stack Table (PST)
+/// 2. Interrupt Stack Table (IST).
+///
+/// The TSS is used to change the stack pointer after an interrupt or permission level is changed.
+///
+/// OS Dev Wiki: https://wiki.osdev.org/Global_Descriptor_Table
+static TSS: OnceCell<TaskStateSegment> = OnceCell::uninit();
+
 ////////////////
 // Interfaces
 ////////////////
 
-lazy_static! {
-    /// Task State Segment (TSS)
-    ///
-    /// A Task State Segment is a binary data structure specific to the IA-32 and x86-64 architectures.
-    /// The TSS was used to hold various pieces of information (e.g., processor register state) about a
-    /// task in 32-bit mode and was, for example, used for hardware context switching. However, hardware
-    /// context switching is no longer supported in 64-bit mode and the format of the TSS has changed
-    /// completely.
-    ///
-    /// On x86, the TSS no longer holds any task-specific information at all. Instead, it holds two
-    /// stack tables and an I/O port permissions bitmap:
-    ///
-    /// 1. Privilege Stack Table (PST)
-    /// 2. Interrupt Stack Table (IST).
-    ///
-    /// The TSS is used to change the stack pointer after an interrupt or permission level is changed.
-    ///
-    /// OS Dev Wiki: https://wiki.osdev.org/Global_Descriptor_Table
-    static ref TSS: TaskStateSegment = {
-        let mut tss = TaskStateSegment::new();
-
-        // Set up a separate stack for double fault exceptions to avoid a triple fault exception,
-        // which will reboot the machine. A triple fault exception is triggered if the stack is full
-        // and the guard page is hit.
-        tss.interrupt_stack_table[Stack::DoubleFault as usize] = {
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-            let stack_begin = VirtAddr::from_ptr(unsafe {&STACK});
-            let stack_end = stack_begin + STACK_SIZE;
-            stack_end
-        };
-
-        tss
-    };
-}
-
 lazy_static! {
     /// Global Descriptor Table (GDT)
     ///
@@ -99,7 +152,7 @@ lazy_static! {
         let mut gdt = GlobalDescriptorTable::new();
 
         let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
-        let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
+        let tss_selector = gdt.add_entry(Descriptor::tss_segment(TSS.get().expect("TSS not initialized")));
 
         (gdt, Selectors{code_selector, tss_selector})
     };
@@ -113,12 +166,54 @@ struct Selectors {
     tss_selector: SegmentSelector,
 }
 
+////////////
+// Device
+////////////
+
+/// Device descriptor of the GDT.
+pub(crate) static DEVICE: Device = Device {
+    name: "GDT",
+    class: Class::Processor,
+    stage: Stage::Core,
+    critical: true,
+    // The stacks are mapped to frames from the frame allocator.
+    depends: &["Allocator"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
 ///////////////
 // Utilities
 ///////////////
 
 /// Initializes the GDT.
-pub(crate) fn init() -> Result<(), ()> {
+pub(crate) fn init() -> Result<(), Error> {
+    locate_boot_stack_guard();
+
+    let mut tss = TaskStateSegment::new();
+
+    // The double fault stack avoids a triple fault exception, which would reboot the machine, when
+    // the current stack overflows into its guard page.
+    //
+    // NMIs and machine checks can arrive at any instruction, including the first ones of another
+    // handler before it has switched stacks, and debug exceptions can be raised in the middle of
+    // a stack switch. Each gets a known-good stack of its own.
+    //
+    // Hardware interrupts run on a dedicated stack so that they do not eat into the (possibly
+    // small) stack of whatever task they interrupt. Handlers run with interrupts disabled, so the
+    // stack is never entered twice.
+    for stack in Stack::ALL {
+        tss.interrupt_stack_table[stack as usize] = map_stack(stack).unwrap_or_else(
+            |e| {
+                warning!("GDT: {} stack is not guarded: {}", stack.as_str(), e);
+                fallback_stack(stack)
+            }
+        );
+    }
+    TSS.try_init_once(|| tss).map_err(|_| Error::Failed)?;
+
     GDT.0.load();
     unsafe {
         CS::set_reg(GDT.1.code_selector);
@@ -127,3 +222,52 @@ pub(crate) fn init() -> Result<(), ()> {
 
     Ok(())
 }
+
+/// Returns the name of the stack whose guard page contains the given address.
+pub fn overflowed_stack(addr: usize) -> Option<&'static str> {
+    let boot_guard = BOOT_STACK_GUARD.load(Ordering::Relaxed);
+    if boot_guard != 0 && (boot_guard..boot_guard + PAGE_SIZE).contains(&addr) {
+        return Some("boot");
+    }
+
+    let offset = addr.checked_sub(STACKS_START)?;
+    if offset % STACK_SLOT >= PAGE_SIZE { return None; }
+    Stack::from_index(offset / STACK_SLOT).map(|stack| stack.as_str())
+}
+
+/// Maps the given stack above its guard page and returns the address of its top.
+fn map_stack(stack: Stack) -> Result<VirtAddr, Error> {
+    let mut mapper = unsafe { memory::mapper() };
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+
+    // The first page of the slot is left unmapped.
+    let bottom = STACKS_START + stack as usize * STACK_SLOT + PAGE_SIZE;
+    for offset in (0..STACK_SIZE).step_by(PAGE_SIZE) {
+        let page = Page::containing_address(VirtAddr::new((bottom + offset) as u64));
+        let frame = frame::allocate().ok_or(Error::OutOfMemory)?;
+        unsafe { mapper.map_to(page, frame, flags, &mut GlobalFrameAllocator)?.flush() };
+    }
+
+    Ok(VirtAddr::new((bottom + STACK_SIZE) as u64))
+}
+
+/// Returns the address of the top of the static stack standing in for the given one.
+fn fallback_stack(stack: Stack) -> VirtAddr {
+    let stack_begin = VirtAddr::from_ptr(unsafe { core::ptr::addr_of!(FALLBACK_STACKS[stack as usize]) });
+    stack_begin + STACK_SIZE
+}
+
+/// Finds the unmapped page below the boot stack by walking down from the current stack pointer.
+fn locate_boot_stack_guard() {
+    let rsp: usize;
+    unsafe { asm!("mov {}, rsp", out(reg) rsp, options(nomem, nostack, preserves_flags)) };
+
+    let mut page = rsp & !(PAGE_SIZE - 1);
+    for _ in 0..MAX_BOOT_STACK_PAGES {
+        page -= PAGE_SIZE;
+        if memory::virt_to_phys_addr(VirtAddr::new(page as u64)).is_none() {
+            BOOT_STACK_GUARD.store(page, Ordering::Relaxed);
+            return;
+        }
+    }
+}
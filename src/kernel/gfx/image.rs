@@ -0,0 +1,304 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::kernel::error::Error;
+
+// Images
+//
+// An image is decoded into a grid of 24-bit pixels, stored row by row from the top. Only the Windows
+// bitmap (BMP) format is understood so far: uncompressed images of 1, 4, 8, 16, 24 and 32 bits per
+// pixel, images with color masks (`BI_BITFIELDS`), and run-length encoded images of 4 and 8 bits per
+// pixel (`BI_RLE4` and `BI_RLE8`). Headers older than `BITMAPINFOHEADER` are not supported.
+//
+// The heap is small, so images are limited to `MAX_PIXELS` pixels.
+//
+// Microsoft Docs: https://learn.microsoft.com/en-us/windows/win32/gdi/bitmap-storage
+
+////////////////
+// Attributes
+////////////////
+
+/// Largest number of pixels in an image.
+pub const MAX_PIXELS: usize = 1 << 17;
+
+/// Size of the file header of a bitmap.
+const FILE_HEADER_SIZE: usize = 14;
+
+/// Size of `BITMAPINFOHEADER`, the oldest header supported.
+const INFO_HEADER_SIZE: usize = 40;
+
+/// Signature of a PNG file.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/////////////
+// Globals
+/////////////
+
+/// Color of the pixels an image does not define.
+const BLACK: Rgb = (0, 0, 0);
+
+/// A pixel, as its red, green and blue components.
+pub type Rgb = (u8, u8, u8);
+
+///////////////////
+/// Compression
+///////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Compression {
+    Rgb = 0x0,
+    Rle8 = 0x1,
+    Rle4 = 0x2,
+    Bitfields = 0x3,
+}
+
+impl Compression {
+    /// Creates a new object from the value in the header.
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0x0 => Some(Self::Rgb),
+            0x1 => Some(Self::Rle8),
+            0x2 => Some(Self::Rle4),
+            0x3 => Some(Self::Bitfields),
+            _ => None,
+        }
+    }
+}
+
+/////////////
+/// Image
+/////////////
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    /// Creates a black image of the given size.
+    fn new(width: usize, height: usize) -> Result<Self, Error> {
+        let count = width.checked_mul(height).ok_or(Error::OutOfResources)?;
+        if count > MAX_PIXELS { return Err(Error::OutOfResources); }
+
+        Ok(Image { width, height, pixels: vec![BLACK; count] })
+    }
+
+    /// Returns the width in pixels.
+    pub fn width(&self) -> usize { self.width }
+
+    /// Returns the height in pixels.
+    pub fn height(&self) -> usize { self.height }
+
+    /// Returns the pixel at the given position, counted from the top left corner.
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb { self.pixels[y * self.width + x] }
+
+    /// Sets the pixel at the given position, ignoring positions outside of the image.
+    fn set_pixel(&mut self, x: usize, y: usize, rgb: Rgb) {
+        if x < self.width && y < self.height { self.pixels[y * self.width + x] = rgb; }
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Decodes an image, detecting its format.
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    if data.starts_with(b"BM") { return decode_bmp(data); }
+    if data.starts_with(&PNG_SIGNATURE) { return Err(Error::Unsupported); }
+
+    Err(Error::InvalidArgument)
+}
+
+/// Decodes a Windows bitmap.
+pub fn decode_bmp(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(b"BM") { return Err(Error::InvalidArgument); }
+
+    let pixel_offset = read_u32(data, 10)? as usize;
+    let header_size = read_u32(data, 14)? as usize;
+    if header_size < INFO_HEADER_SIZE { return Err(Error::Unsupported); }
+
+    let width = read_u32(data, 18)? as i32;
+    let height = read_u32(data, 22)? as i32;
+    let bpp = read_u16(data, 28)?;
+    let compression = Compression::from_u32(read_u32(data, 30)?).ok_or(Error::Unsupported)?;
+    let colors_used = read_u32(data, 46)? as usize;
+    if width <= 0 || height == 0 { return Err(Error::InvalidArgument); }
+
+    // Rows are stored from the bottom up, unless the height is negative.
+    let top_down = height < 0;
+    let mut image = Image::new(width as usize, height.unsigned_abs() as usize)?;
+
+    let palette = if bpp <= 8 {
+        let count = if colors_used == 0 { 1 << bpp } else { colors_used.min(1 << bpp) };
+        let begin = FILE_HEADER_SIZE + header_size;
+        let entries = data.get(begin..begin + count * 4).ok_or(Error::InvalidArgument)?;
+        entries.chunks_exact(4).map(|bgrx| (bgrx[2], bgrx[1], bgrx[0])).collect()
+    } else {
+        Vec::new()
+    };
+
+    let masks = match (compression, bpp) {
+        // The masks follow `BITMAPINFOHEADER`, and are part of the later headers at the same offset.
+        (Compression::Bitfields, 16 | 32) => {
+            let begin = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+            [read_u32(data, begin)?, read_u32(data, begin + 4)?, read_u32(data, begin + 8)?]
+        }
+        (Compression::Rgb, 16) => [0x7C00, 0x03E0, 0x001F],
+        (Compression::Rgb, 32) => [0xFF_0000, 0x00_FF00, 0x00_00FF],
+        (Compression::Rgb, 1 | 4 | 8 | 24) => [0; 3],
+        (Compression::Rle8, 8) | (Compression::Rle4, 4) if !top_down => [0; 3],
+        _ => return Err(Error::Unsupported),
+    };
+
+    let pixels = data.get(pixel_offset..).ok_or(Error::InvalidArgument)?;
+    match compression {
+        Compression::Rle8 | Compression::Rle4 => decode_rle(&mut image, pixels, bpp, &palette)?,
+        _ => decode_rows(&mut image, pixels, bpp, &palette, masks, top_down)?,
+    }
+
+    Ok(image)
+}
+
+/// Decodes uncompressed rows of pixels.
+fn decode_rows(image: &mut Image, data: &[u8], bpp: u16, palette: &[Rgb], masks: [u32; 3], top_down: bool) -> Result<(), Error> {
+    let bpp = bpp as usize;
+    // Each row is padded to a multiple of four bytes.
+    let stride = (image.width * bpp + 31) / 32 * 4;
+    if data.len() < stride * image.height { return Err(Error::InvalidArgument); }
+
+    let indexed = |index: usize| palette.get(index).copied().unwrap_or(BLACK);
+    let masked = |value: u32| (channel(value, masks[0]), channel(value, masks[1]), channel(value, masks[2]));
+
+    for row in 0..image.height {
+        let y = if top_down { row } else { image.height - 1 - row };
+        let bytes = &data[row * stride..(row + 1) * stride];
+        for x in 0..image.width {
+            let rgb = match bpp {
+                1 | 4 | 8 => {
+                    let bit = x * bpp;
+                    let shift = 8 - bpp - bit % 8;
+                    indexed(((bytes[bit / 8] >> shift) & ((1 << bpp) - 1) as u8) as usize)
+                }
+                16 => masked(u16::from_le_bytes([bytes[x * 2], bytes[x * 2 + 1]]) as u32),
+                24 => (bytes[x * 3 + 2], bytes[x * 3 + 1], bytes[x * 3]),
+                _ => masked(u32::from_le_bytes([bytes[x * 4], bytes[x * 4 + 1], bytes[x * 4 + 2], bytes[x * 4 + 3]])),
+            };
+            image.set_pixel(x, y, rgb);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes run-length encoded pixels of 4 or 8 bits.
+///
+/// Runs are either encoded, i.e. a count followed by the color indices to repeat, or absolute, i.e.
+/// a zero, the count and the indices themselves, padded to an even number of bytes. A zero followed by
+/// 0, 1 or 2 marks the end of a row, the end of the image, or a jump ahead by the next two bytes.
+fn decode_rle(image: &mut Image, data: &[u8], bpp: u16, palette: &[Rgb]) -> Result<(), Error> {
+    const END_OF_LINE: u8 = 0;
+    const END_OF_BITMAP: u8 = 1;
+    const DELTA: u8 = 2;
+
+    let indexed = |index: u8| palette.get(index as usize).copied().unwrap_or(BLACK);
+    // The index of the n-th pixel packed in a byte.
+    let nth = |byte: u8, n: usize| if bpp == 8 { byte } else if n % 2 == 0 { byte >> 4 } else { byte & 0xF };
+
+    let (mut x, mut row) = (0usize, 0usize);
+    let mut pos = 0;
+    let mut next = || -> Result<u8, Error> {
+        let byte = *data.get(pos).ok_or(Error::InvalidArgument)?;
+        pos += 1;
+        Ok(byte)
+    };
+
+    // Rows are stored from the bottom up.
+    let height = image.height;
+    let y = |row: usize| height.checked_sub(row + 1);
+
+    while row < height {
+        let (count, value) = (next()?, next()?);
+        if count > 0 {
+            for n in 0..count as usize {
+                if let Some(y) = y(row) { image.set_pixel(x, y, indexed(nth(value, n))); }
+                x += 1;
+            }
+            continue;
+        }
+
+        match value {
+            END_OF_LINE => {
+                x = 0;
+                row += 1;
+            }
+            END_OF_BITMAP => break,
+            DELTA => {
+                x += next()? as usize;
+                row += next()? as usize;
+            }
+            count => {
+                let count = count as usize;
+                let bytes = if bpp == 8 { count } else { (count + 1) / 2 };
+                let mut byte = 0;
+                for n in 0..count {
+                    if bpp == 8 || n % 2 == 0 { byte = next()?; }
+                    if let Some(y) = y(row) { image.set_pixel(x, y, indexed(nth(byte, n))); }
+                    x += 1;
+                }
+                if bytes % 2 != 0 { next()?; }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts the component selected by the mask and scales it to eight bits.
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 { return 0; }
+
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).count_ones();
+    let component = (value & mask) >> shift;
+    if bits >= 8 {
+        (component >> (bits - 8)) as u8
+    } else {
+        (component * 255 / ((1 << bits) - 1)) as u8
+    }
+}
+
+/// Reads a little-endian `u16` at the given offset.
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    let bytes = data.get(offset..offset + 2).ok_or(Error::InvalidArgument)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a little-endian `u32` at the given offset.
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = data.get(offset..offset + 4).ok_or(Error::InvalidArgument)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
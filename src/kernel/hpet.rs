@@ -0,0 +1,204 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use x86_64::{instructions, PhysAddr};
+
+use crate::kernel::{acpi, memory, pit, time};
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+
+// High Precision Event Timer (HPET)
+//
+// The HPET is a block of memory-mapped timers found on every chipset since the ICH6, described by
+// a table of its own in the ACPI. It consists of an up-counter running at 10 MHz or more, whose period
+// is read from its capabilities, and a handful of comparators that raise interrupts when it reaches
+// them. The first comparator can reload itself, which makes it a periodic timer.
+//
+// On legacy-free platforms the HPET stands in for the PIT. Its legacy replacement route wires the
+// first comparator to IRQ 0 and the second to IRQ 8, so the tick keeps its line and the rest of the
+// kernel is none the wiser. The route takes IRQ 8 from the RTC, which such platforms lack as well.
+// The counter also serves as the reference the TSC is calibrated against when nothing else can.
+//
+// OS Dev Wiki: https://wiki.osdev.org/HPET
+
+////////////////
+// Attributes
+////////////////
+
+/// General capabilities and identification register.
+const GENERAL_CAPABILITIES: usize = 0x000;
+/// General configuration register.
+const GENERAL_CONFIGURATION: usize = 0x010;
+/// Main counter value register.
+const MAIN_COUNTER: usize = 0x0F0;
+/// Configuration and capabilities register of timer 0.
+const TIMER_0_CONFIGURATION: usize = 0x100;
+/// Comparator value register of timer 0.
+const TIMER_0_COMPARATOR: usize = 0x108;
+
+/// Whether the legacy replacement route is supported, in the capabilities.
+const LEGACY_ROUTE_CAPABLE: u64 = 1 << 15;
+/// Whether the main counter runs, in the configuration.
+const ENABLE: u64 = 1 << 0;
+/// Whether the legacy replacement route is taken, in the configuration.
+const LEGACY_ROUTE: u64 = 1 << 1;
+
+/// Whether the timer raises interrupts, in its configuration.
+const TIMER_INTERRUPT: u64 = 1 << 2;
+/// Whether the timer is periodic, in its configuration.
+const TIMER_PERIODIC: u64 = 1 << 3;
+/// Whether the timer can be periodic, in its configuration.
+const TIMER_PERIODIC_CAPABLE: u64 = 1 << 4;
+/// Whether the next comparator write sets the period rather than the deadline, in its configuration.
+const TIMER_SET_PERIOD: u64 = 1 << 6;
+
+/// Longest counter period allowed by the specification, in femtoseconds.
+const MAX_PERIOD_FS: u64 = 100_000_000;
+
+////////////
+// States
+////////////
+
+/// Virtual address of the registers, or zero if the HPET is not initialized.
+static BASE: AtomicU64 = AtomicU64::new(0);
+
+/// Frequency of the main counter in hertz.
+static FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the HPET.
+pub(crate) static DEVICE: Device = Device {
+    name: "HPET",
+    class: Class::System,
+    stage: Stage::Platform,
+    critical: false,
+    depends: &["ACPI"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+///////////////
+// Utilities
+///////////////
+
+/// Starts the main counter, and takes over the tick if the PIT is absent.
+pub(crate) fn init() -> Result<(), Error> {
+    let address = locate().ok_or(Error::Hardware(FaultKind::NotPresent))?;
+    let base = memory::phys_to_virt_addr(PhysAddr::new(address)).as_u64();
+
+    // The period is in femtoseconds, and is zero or out of range if nothing is mapped there.
+    let capabilities = unsafe { read(base, GENERAL_CAPABILITIES) };
+    let period = capabilities >> 32;
+    if period == 0 || period > MAX_PERIOD_FS { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    let frequency = 1_000_000_000_000_000 / period;
+    unsafe { write(base, GENERAL_CONFIGURATION, read(base, GENERAL_CONFIGURATION) | ENABLE) };
+
+    BASE.store(base, Ordering::Relaxed);
+    FREQUENCY.store(frequency, Ordering::Relaxed);
+
+    if !pit::is_initialized() {
+        if capabilities & LEGACY_ROUTE_CAPABLE == 0 { return Err(Error::Unsupported); }
+        start_periodic(base, frequency)?;
+        pit::start_ticking();
+    }
+
+    time::calibrate_against(counter, frequency);
+
+    Ok(())
+}
+
+/// Returns whether the HPET is initialized.
+pub fn is_initialized() -> bool { BASE.load(Ordering::Relaxed) != 0 }
+
+/// Returns the frequency of the main counter in hertz, or none if the HPET is not initialized.
+pub fn frequency() -> Option<u64> { is_initialized().then(|| FREQUENCY.load(Ordering::Relaxed)) }
+
+/// Returns the value of the main counter.
+///
+/// Note: It reads zero if the HPET is not initialized.
+pub fn counter() -> u64 {
+    match BASE.load(Ordering::Relaxed) {
+        0 => 0,
+        base => unsafe { read(base, MAIN_COUNTER) },
+    }
+}
+
+/// Returns the physical address of the registers, as described by the ACPI.
+fn locate() -> Option<u64> {
+    /// Offset of the address space of the base address.
+    const ADDRESS_SPACE: usize = 40;
+    /// Offset of the base address.
+    const ADDRESS: usize = 44;
+    /// Address space of the system memory.
+    const SYSTEM_MEMORY: u8 = 0;
+
+    let bytes = acpi::find("HPET")?.bytes();
+    if bytes.len() < ADDRESS + 8 || bytes[ADDRESS_SPACE] != SYSTEM_MEMORY { return None; }
+
+    let address = u64::from_le_bytes(bytes[ADDRESS..ADDRESS + 8].try_into().ok()?);
+    (address != 0).then_some(address)
+}
+
+/// Programs timer 0 to fire once per tick and routes it to IRQ 0 in place of the PIT.
+fn start_periodic(base: u64, frequency: u64) -> Result<(), Error> {
+    let config = unsafe { read(base, TIMER_0_CONFIGURATION) };
+    if config & TIMER_PERIODIC_CAPABLE == 0 { return Err(Error::Unsupported); }
+
+    let ticks = (pit::tick_interval() * frequency as f64) as u64;
+
+    instructions::interrupts::without_interrupts(
+        || unsafe {
+            // The counter is halted and reset, so that the first deadline lies one period ahead.
+            let general = read(base, GENERAL_CONFIGURATION) & !ENABLE;
+            write(base, GENERAL_CONFIGURATION, general);
+            write(base, MAIN_COUNTER, 0);
+
+            // The first write sets the deadline and, with the flag set, the second one the period.
+            write(base, TIMER_0_CONFIGURATION, config | TIMER_INTERRUPT | TIMER_PERIODIC | TIMER_SET_PERIOD);
+            write(base, TIMER_0_COMPARATOR, ticks);
+            write(base, TIMER_0_COMPARATOR, ticks);
+
+            write(base, GENERAL_CONFIGURATION, general | LEGACY_ROUTE | ENABLE);
+        }
+    );
+
+    Ok(())
+}
+
+/// Reads the register at the given offset.
+unsafe fn read(base: u64, offset: usize) -> u64 {
+    ptr::read_volatile((base as usize + offset) as *const u64)
+}
+
+/// Writes the value to the register at the given offset.
+unsafe fn write(base: u64, offset: usize, value: u64) {
+    ptr::write_volatile((base as usize + offset) as *mut u64, value);
+}
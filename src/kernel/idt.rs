@@ -20,22 +20,33 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use instructions::port::Port;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use lazy_static::lazy_static;
 use spin::Mutex;
 use x86_64::instructions;
 use x86_64::registers::control::Cr2;
 use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
 
-use crate::{hlt_loop, omneity, println};
+use crate::{emergency_println, hlt_loop, omneity};
+use crate::aux::emergency;
+use crate::kernel::allocator;
+use crate::kernel::apic;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
 use crate::kernel::gdt;
+use crate::kernel::memory::space;
 use crate::kernel::pics;
-use crate::kernel::pics::PIC_8259;
+use crate::kernel::portio::Port;
 
 /// Maps the interrupt handler.
 macro_rules! map_irq_handler {
     ($reference:ident, $handler:ident, $interrupt:expr) => {
-        $reference[IRQ::index_to_pin($interrupt) as usize].set_handler_fn($handler);
+        unsafe {
+            $reference[IRQ::index_to_pin($interrupt) as usize]
+                .set_handler_fn($handler)
+                .set_stack_index(gdt::Stack::Interrupt as u16);
+        }
     };
 }
 
@@ -43,13 +54,47 @@ macro_rules! map_irq_handler {
 macro_rules! generate_irq_handler {
     ($handler:ident, $irq_idx:expr) => {
         extern "x86-interrupt" fn $handler(_stack_frame: InterruptStackFrame) {
+            if !apic::is_enabled() && pics::is_spurious($irq_idx) {
+                SPURIOUS_COUNT.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+
             let irq_handlers = IRQ_HANDLERS.lock();
             irq_handlers[$irq_idx]();
-            unsafe { PIC_8259.lock().notify_end_of_interrupt(IRQ::index_to_pin($irq_idx)); }
+            apic::notify_end_of_interrupt(IRQ::index_to_pin($irq_idx));
+        }
+    };
+}
+
+/// Generates the handler of a message signaled interrupt.
+macro_rules! generate_msi_handler {
+    ($handler:ident, $msi_idx:expr) => {
+        extern "x86-interrupt" fn $handler(_stack_frame: InterruptStackFrame) {
+            if let Some(handler) = MSI_HANDLERS.lock()[$msi_idx] { handler(); }
+            apic::local::notify_end_of_interrupt();
         }
     };
 }
 
+/// Vector raised by the local APIC timer, right after the ISA lines.
+pub(crate) const APIC_TIMER_VECTOR: u8 = pics::M_OFFSET + pics::TOTAL_PIN_COUNT;
+/// First vector handed out to message signaled interrupts, right after the local APIC timer.
+pub(crate) const MSI_VECTOR_BASE: u8 = APIC_TIMER_VECTOR + 1;
+/// Number of vectors handed out to message signaled interrupts.
+const MSI_VECTOR_COUNT: usize = 4;
+/// Vector raised by the local APIC for spurious interrupts.
+pub(crate) const APIC_SPURIOUS_VECTOR: u8 = 0xFF;
+
+/// Handler of a message signaled interrupt.
+type MsiHandler = fn();
+
+////////////
+// States
+////////////
+
+/// Number of spurious interrupts received from either interrupt controller.
+static SPURIOUS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
 lazy_static! {
     /// List of all IRQ handlers.
     static ref IRQ_HANDLERS: Mutex<[fn(); pics::TOTAL_PIN_COUNT as usize]> = Mutex::new(
@@ -57,6 +102,9 @@ lazy_static! {
     );
 }
 
+/// Handlers of the message signaled interrupts, by vector past `MSI_VECTOR_BASE`.
+static MSI_HANDLERS: Mutex<[Option<MsiHandler>; MSI_VECTOR_COUNT]> = Mutex::new([None; MSI_VECTOR_COUNT]);
+
 lazy_static! {
     /// Interrupt Descriptor Table (IDT)
     ///
@@ -71,6 +119,19 @@ lazy_static! {
         // Set breakpoint handler.
         idt.breakpoint.set_handler_fn(breakpoint_handler);
 
+        // Set handlers of the exceptions that can be raised on any stack, each with a stack of its own.
+        unsafe {
+            idt.debug
+                .set_handler_fn(debug_handler)
+                .set_stack_index(gdt::Stack::Debug as u16);
+            idt.non_maskable_interrupt
+                .set_handler_fn(non_maskable_interrupt_handler)
+                .set_stack_index(gdt::Stack::NonMaskable as u16);
+            idt.machine_check
+                .set_handler_fn(machine_check_handler)
+                .set_stack_index(gdt::Stack::MachineCheck as u16);
+        }
+
         // Set double fault handler and stack index.
         unsafe {
             idt.double_fault
@@ -99,6 +160,26 @@ lazy_static! {
         map_irq_handler!(idt, irq_0xe_handler, 0xE);
         map_irq_handler!(idt, irq_0xf_handler, 0xF);
 
+        // Set local APIC timer and spurious interrupt handlers.
+        unsafe {
+            idt[APIC_TIMER_VECTOR as usize]
+                .set_handler_fn(apic_timer_handler)
+                .set_stack_index(gdt::Stack::Interrupt as u16);
+            idt[APIC_SPURIOUS_VECTOR as usize]
+                .set_handler_fn(apic_spurious_handler)
+                .set_stack_index(gdt::Stack::Interrupt as u16);
+        }
+
+        // Set message signaled interrupt handlers.
+        let msi_handlers = [msi_0x0_handler, msi_0x1_handler, msi_0x2_handler, msi_0x3_handler];
+        for (idx, handler) in msi_handlers.into_iter().enumerate() {
+            unsafe {
+                idt[MSI_VECTOR_BASE as usize + idx]
+                    .set_handler_fn(handler)
+                    .set_stack_index(gdt::Stack::Interrupt as u16);
+            }
+        }
+
         idt
     };
 }
@@ -111,6 +192,8 @@ lazy_static! {
 pub enum IRQ {
     Timer = pics::M_OFFSET,
     Keyboard,
+    COM2 = pics::M_OFFSET + 3,
+    COM1 = pics::M_OFFSET + 4,
     RTC = pics::S_OFFSET,
 }
 
@@ -143,14 +226,40 @@ generate_irq_handler!(irq_0xd_handler, 0xD);
 generate_irq_handler!(irq_0xe_handler, 0xE);
 generate_irq_handler!(irq_0xf_handler, 0xF);
 
+// Stamp out MSI handlers.
+generate_msi_handler!(msi_0x0_handler, 0x0);
+generate_msi_handler!(msi_0x1_handler, 0x1);
+generate_msi_handler!(msi_0x2_handler, 0x2);
+generate_msi_handler!(msi_0x3_handler, 0x3);
+
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the IDT.
+pub(crate) static DEVICE: Device = Device {
+    name: "IDT",
+    class: Class::Processor,
+    stage: Stage::Core,
+    critical: true,
+    depends: &["GDT"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
 
 /// Initializes the IDT.
-pub(crate) fn init() -> Result<(), ()> {
+pub(crate) fn init() -> Result<(), Error> {
     IDT.load();
 
     Ok(())
 }
 
+/// Returns the number of spurious interrupts received so far.
+pub fn spurious_count() -> usize { SPURIOUS_COUNT.load(Ordering::Relaxed) }
+
 /// Sets the interrupt handler for the given index.
 pub(crate) fn set_irq_handler(pin: IRQ, handler: fn()) {
     instructions::interrupts::without_interrupts(
@@ -165,9 +274,57 @@ pub(crate) fn set_irq_handler(pin: IRQ, handler: fn()) {
     );
 }
 
+/// Assigns a free vector to the given handler of message signaled interrupts and returns it, or none
+/// if all of them are taken.
+///
+/// Note: The messages are delivered to the local APIC, which must therefore be enabled.
+#[cfg_attr(not(feature = "fs"), allow(dead_code))]
+pub(crate) fn alloc_msi_vector(handler: MsiHandler) -> Option<u8> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut msi_handlers = MSI_HANDLERS.lock();
+
+            let idx = msi_handlers.iter().position(|h| h.is_none())?;
+            msi_handlers[idx] = Some(handler);
+
+            Some(MSI_VECTOR_BASE + idx as u8)
+        }
+    )
+}
+
+/// Runs the handler registered for the given interrupt line, as if the interrupt had fired.
+///
+/// Note: No end of interrupt is signaled, as no interrupt is in service.
+pub(crate) fn dispatch_irq(pin: IRQ) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let irq_handlers = IRQ_HANDLERS.lock();
+            irq_handlers[IRQ::pin_to_index(pin) as usize]();
+        }
+    );
+}
+
+/// Masks the given interrupt line.
+pub(crate) fn mask_irq(pin: IRQ) {
+    instructions::interrupts::without_interrupts(
+        || { set_interrupt_mask(IRQ::pin_to_index(pin)); }
+    );
+}
+
+/// Unmasks the given interrupt line.
+pub(crate) fn unmask_irq(pin: IRQ) {
+    instructions::interrupts::without_interrupts(
+        || { clear_interrupt_mask(IRQ::pin_to_index(pin)); }
+    );
+}
+
 /// Sets interrupt mask for the specified index.
-#[allow(dead_code)]
 fn set_interrupt_mask(idx: u8) {
+    if apic::is_enabled() {
+        apic::io::set_masked(idx, true);
+        return;
+    }
+
     let (interrupt_line, port_num) = if idx < pics::M_PIN_COUNT {
         (idx, pics::M_DATA_PORT)
     } else {
@@ -184,6 +341,11 @@ fn set_interrupt_mask(idx: u8) {
 
 /// Clears interrupt mask for the specified index.
 fn clear_interrupt_mask(idx: u8) {
+    if apic::is_enabled() {
+        apic::io::set_masked(idx, false);
+        return;
+    }
+
     let (interrupt_line, port_num) = if idx < pics::M_PIN_COUNT {
         (idx, pics::M_DATA_PORT)
     } else {
@@ -198,24 +360,72 @@ fn clear_interrupt_mask(idx: u8) {
     }
 }
 
+/// A handler for the local APIC timer.
+///
+/// Note: The timer only wakes the CPU up from a tickless idle, where the elapsed time is accounted.
+extern "x86-interrupt" fn apic_timer_handler(_stack_frame: InterruptStackFrame) {
+    apic::local::notify_end_of_interrupt();
+}
+
+/// A handler for spurious interrupts of the local APIC.
+///
+/// Note: Spurious interrupts are not in service, so they must not be acknowledged.
+extern "x86-interrupt" fn apic_spurious_handler(_stack_frame: InterruptStackFrame) {
+    SPURIOUS_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A handler for debug exceptions.
+extern "x86-interrupt" fn debug_handler(stack_frame: InterruptStackFrame) {
+    emergency_println!("EXCEPTION: DEBUG");
+    emergency_println!("{:#?}", stack_frame);
+}
+
+/// A handler for non-maskable interrupts.
+///
+/// Note: NMIs signal hardware failures on PCs, such as memory parity or bus errors.
+extern "x86-interrupt" fn non_maskable_interrupt_handler(stack_frame: InterruptStackFrame) {
+    emergency_println!("EXCEPTION: NON-MASKABLE INTERRUPT");
+    emergency_println!("{:#?}", stack_frame);
+}
+
+/// A handler for machine check exceptions.
+extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame) -> ! {
+    emergency_println!("EXCEPTION: MACHINE CHECK");
+    panic!("{:#?}", stack_frame);
+}
+
 /// A handler for breakpoint exceptions.
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
-    println!("EXCEPTION: BREAKPOINT");
-    println!("{:#?}", stack_frame);
+    emergency_println!("EXCEPTION: BREAKPOINT");
+    emergency_println!("{:#?}", stack_frame);
 }
 
 /// A handler for double fault exceptions.
 extern "x86-interrupt" fn double_fault_handler(stack_frame: InterruptStackFrame, _err_code: u64) -> ! {
-    println!("EXCEPTION: DOUBLE FAULT");
+    emergency_println!("EXCEPTION: DOUBLE FAULT");
+    // A stack overflow turns into a double fault, as the page fault cannot be pushed onto the full stack.
+    if let Some(stack) = gdt::overflowed_stack(Cr2::read().as_u64() as usize) {
+        emergency_println!("Kernel stack overflow: the {} stack ran into its guard page", stack);
+    }
     panic!("{:#?}", stack_frame);
 }
 
 /// A handler for page fault exceptions.
 extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, err_code: PageFaultErrorCode) {
-    println!("EXCEPTION: PAGE FAULT");
-    println!("Accessed address: {:?}", Cr2::read());
-    println!("Error code: {:?}", err_code);
-    println!("{:#?}", stack_frame);
+    // The kernel may have mapped a new region since the loaded address space was last synced.
+    if space::sync_kernel_entry(Cr2::read()) { return; }
+
+    emergency_println!("EXCEPTION: PAGE FAULT");
+    emergency_println!("Accessed address: {:?}", Cr2::read());
+    if allocator::is_guard_page(Cr2::read().as_u64() as usize) {
+        emergency_println!("The address lies on a guard page: heap buffer overrun or use after free");
+    }
+    if let Some(stack) = gdt::overflowed_stack(Cr2::read().as_u64() as usize) {
+        emergency_println!("Kernel stack overflow: the {} stack ran into its guard page", stack);
+    }
+    emergency_println!("Error code: {:?}", err_code);
+    emergency_println!("{:#?}", stack_frame);
+    emergency::print_recent_log();
 
     hlt_loop();
 }
@@ -0,0 +1,163 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::{failure, omneity};
+use crate::kernel::{allocator, boot};
+use crate::kernel::dev::Stage;
+use crate::kernel::error::Error;
+
+// Initcalls
+//
+// Besides devices, the boot consists of steps that set up a subsystem, such as mounting the root
+// filesystem or applying the configuration. Rather than being called one by one from `init`, each
+// step is registered as an initcall with the level at which it runs, through `register_initcall!`.
+// The levels run in order, interleaved with the device stages, and the initcalls of a level run in
+// order of registration.
+//
+// Level        Runs
+// Early        before any device, once the memory map is known
+// Core         after the core devices, i.e. interrupts, timers and the heap
+// Driver       after all the devices
+// Late         at the end of the boot
+//
+// Each initcall is timed like a device, and its outcome is logged. A failed initcall is reported and
+// the boot carries on, so an initcall must leave its subsystem usable, if degraded, when it fails.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of registered initcalls.
+const MAX_INITCALLS: usize = 32;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of registered initcalls.
+static REGISTRY: Mutex<[Option<&'static Initcall>; MAX_INITCALLS]> = Mutex::new([None; MAX_INITCALLS]);
+
+/////////////
+/// Level
+/////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Early = 0x0,
+    Core = 0x1,
+    Driver = 0x2,
+    Late = 0x3,
+}
+
+impl Level {
+    /// All levels in order of execution.
+    pub const ALL: [Level; 4] = [Level::Early, Level::Core, Level::Driver, Level::Late];
+
+    /// Returns the level that runs right after the given device stage, if any.
+    pub fn after(stage: Stage) -> Option<Self> {
+        match stage {
+            Stage::Core => Some(Self::Core),
+            Stage::Driver => Some(Self::Driver),
+            _ => None,
+        }
+    }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Early => "early",
+            Self::Core => "core",
+            Self::Driver => "driver",
+            Self::Late => "late",
+        }
+    }
+}
+
+////////////////
+/// Initcall
+////////////////
+pub struct Initcall {
+    /// Name of the step, as shown in the log and the boot timings.
+    pub name: &'static str,
+    /// Level at which the step runs.
+    pub level: Level,
+    /// Runs the step.
+    pub init: fn() -> Result<(), Error>,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Registers an initcall.
+pub fn register(initcall: &'static Initcall) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut registry = REGISTRY.lock();
+
+            if registry.iter().flatten().any(|i| i.name == initcall.name) { return Err(Error::AlreadyExists); }
+
+            let slot = registry.iter_mut().find(|i| i.is_none()).ok_or(Error::OutOfResources)?;
+            *slot = Some(initcall);
+
+            Ok(())
+        }
+    )
+}
+
+/// Runs the initcalls of the given level in order of registration.
+pub fn run(level: Level) {
+    let registry = instructions::interrupts::without_interrupts(|| { *REGISTRY.lock() });
+    for initcall in registry.iter().flatten().filter(|i| i.level == level) {
+        match boot::measure(initcall.name, || allocator::tagged(initcall.name, initcall.init)) {
+            Ok(()) => omneity!("{}: done", initcall.name),
+            Err(e) => failure!("{}: initialization failed: {}", initcall.name, e),
+        }
+    }
+}
+
+/// Calls the given function for each registered initcall, in order of registration.
+pub fn for_each(mut f: impl FnMut(&Initcall)) {
+    let registry = instructions::interrupts::without_interrupts(|| { *REGISTRY.lock() });
+    registry.iter().flatten().for_each(|initcall| f(initcall));
+}
+
+////////////
+// Macros
+////////////
+
+/// Registers a function as an initcall of the given level, named after the function unless a name is given.
+#[macro_export]
+macro_rules! register_initcall {
+    ($level:expr, $init:path) => ($crate::register_initcall!($level, stringify!($init), $init));
+    ($level:expr, $name:expr, $init:path) => {{
+        static INITCALL: $crate::kernel::initcall::Initcall = $crate::kernel::initcall::Initcall {
+            name: $name,
+            level: $level,
+            init: $init,
+        };
+        $crate::kernel::initcall::register(&INITCALL)
+    }};
+}
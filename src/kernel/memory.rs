@@ -22,13 +22,21 @@
 
 use core::sync::atomic::{AtomicU64, Ordering};
 
-use bootloader::BootInfo;
-use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use conquer_once::spin::OnceCell;
 use x86_64::{PhysAddr, VirtAddr};
 use x86_64::registers::control::Cr3;
 use x86_64::structures::paging::{FrameAllocator, Translate};
 use x86_64::structures::paging::{OffsetPageTable, PageTable, PhysFrame, Size4KiB};
 
+use crate::kernel::boot;
+use crate::kernel::boot::{BootProtocol, MemoryRegion, RegionKind};
+
+pub mod dma;
+pub mod frame;
+pub mod layout;
+pub mod pressure;
+pub mod space;
+
 // PAGING
 //
 // Paging is a system which allows each process to see a full virtual address space, without
@@ -52,6 +60,9 @@ use x86_64::structures::paging::{OffsetPageTable, PageTable, PhysFrame, Size4KiB
 /// Size of page.
 pub const PAGE_SIZE: usize = 4096;
 
+/// Maximum number of memory regions kept from the boot protocol.
+const MAX_REGIONS: usize = 64;
+
 /////////////
 // Globals
 /////////////
@@ -59,17 +70,28 @@ pub const PAGE_SIZE: usize = 4096;
 /// Physical memory offset in the virtual space.
 static PHYS_MEM_OFFSET: AtomicU64 = AtomicU64::new(u64::MAX);
 
+/// Memory map provided by the boot protocol.
+static MEMORY_MAP: OnceCell<MemoryMap> = OnceCell::uninit();
+
+//////////////////
+/// Memory Map
+//////////////////
+struct MemoryMap {
+    regions: [MemoryRegion; MAX_REGIONS],
+    len: usize,
+}
+
 /////////////////////////////////
 /// Boot Info Frame Allocator
 /////////////////////////////////
 pub struct BootInfoFrameAllocator {
-    memory_map: &'static MemoryMap,
+    memory_map: &'static [MemoryRegion],
     next: usize,
 }
 
 impl BootInfoFrameAllocator {
     /// Initializes the boot info frame allocator.
-    pub unsafe fn new(memory_map: &'static MemoryMap) -> Self {
+    pub unsafe fn new(memory_map: &'static [MemoryRegion]) -> Self {
         BootInfoFrameAllocator {
             memory_map,
             next: 0,
@@ -81,9 +103,14 @@ impl BootInfoFrameAllocator {
         let regions = self.memory_map.iter();
 
         // Filter usable regions.
-        let usable_regions = regions.filter(|r| r.region_type == MemoryRegionType::Usable);
-        // Convert regions to ranges.
-        let addr_ranges = usable_regions.map(|r| r.range.start_addr()..r.range.end_addr());
+        let usable_regions = regions.filter(|r| r.kind == RegionKind::Usable);
+        // Convert regions to ranges, skipping partial frames at either end.
+        let frame_size = PAGE_SIZE as u64;
+        let addr_ranges = usable_regions.map(move |r| {
+            let start = (r.start + frame_size - 1) / frame_size * frame_size;
+            let end = r.end / frame_size * frame_size;
+            start..end.max(start)
+        });
         // Compute frame addresses.
         let frame_addresses = addr_ranges.flat_map(|r| r.step_by(PAGE_SIZE));
         frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
@@ -103,18 +130,65 @@ unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
 ///////////////
 
 /// Initializes the required parameters for memory management.
-pub(crate) fn init(boot_info: &'static BootInfo) -> Result<(), ()> {
-    PHYS_MEM_OFFSET.store(boot_info.physical_memory_offset, Ordering::Relaxed);
+pub(crate) fn init(protocol: &dyn BootProtocol) -> Result<(), ()> {
+    let offset = protocol.physical_memory_offset() as usize;
+    assert!(
+        (layout::PHYS_MAP_START..layout::PHYS_MAP_END).contains(&offset),
+        "physical memory mapped at {:#x}, outside of the window", offset
+    );
+    assert!(layout::is_kernel(init as usize), "kernel not mapped in the upper half");
+    PHYS_MEM_OFFSET.store(offset as u64, Ordering::Relaxed);
+
+    let mut map = MemoryMap { regions: [MemoryRegion::empty(); MAX_REGIONS], len: 0 };
+    protocol.for_each_region(&mut |region| {
+        if map.len < MAX_REGIONS && region.end > region.start {
+            map.regions[map.len] = region;
+            map.len += 1;
+        }
+    });
+    MEMORY_MAP.try_init_once(|| map).map_err(|_| ())?;
+
+    space::init();
 
     Ok(())
 }
 
+/// Returns the memory map provided by the boot protocol.
+pub fn memory_map() -> &'static [MemoryRegion] {
+    let map = MEMORY_MAP.get().expect("memory map not initialized");
+    &map.regions[..map.len]
+}
+
+/// Returns the size of the RAM described by the memory map, excluding reserved regions, in bytes.
+pub fn total_memory() -> u64 { region_bytes(|kind| !matches!(kind, RegionKind::Reserved | RegionKind::BadMemory)) }
+
+/// Returns the size of the RAM that was free for use at boot, in bytes.
+pub fn usable_memory() -> u64 { region_bytes(|kind| kind == RegionKind::Usable) }
+
+/// Returns the combined size of the memory regions whose kind matches, in bytes.
+fn region_bytes(f: impl Fn(RegionKind) -> bool) -> u64 {
+    memory_map().iter()
+        .filter(|region| f(region.kind))
+        .map(|region| region.size())
+        .sum()
+}
+
 /// Returns physical memory offset in virtual space.
-pub fn physical_memory_offset() -> u64 { PHYS_MEM_OFFSET.load(Ordering::Relaxed) }
+///
+/// Note: Before initialization, it is the one reported by the boot protocol, if known, or the start of
+/// the window otherwise.
+pub fn physical_memory_offset() -> u64 {
+    match PHYS_MEM_OFFSET.load(Ordering::Relaxed) {
+        u64::MAX => boot::protocol().map_or(layout::PHYS_MAP_START as u64, |protocol| protocol.physical_memory_offset()),
+        offset => offset,
+    }
+}
 
-/// Returns active L4 page table set up by the bootloader to enable paging.
-unsafe fn get_active_l4_table() -> &'static mut PageTable {
-    let (l4_page_table, _) = Cr3::read();
+/// Returns the L4 page table of the kernel, set up by the bootloader to enable paging.
+///
+/// Note: It is the one the kernel maps itself in, whichever address space is loaded.
+unsafe fn get_kernel_l4_table() -> &'static mut PageTable {
+    let l4_page_table = space::kernel_root().unwrap_or_else(|| Cr3::read().0);
     let phys_mem_offset = VirtAddr::new(physical_memory_offset());
 
     let phys_addr = l4_page_table.start_address();
@@ -126,7 +200,7 @@ unsafe fn get_active_l4_table() -> &'static mut PageTable {
 
 /// Returns the Offset Page Table.
 pub(crate) unsafe fn mapper() -> OffsetPageTable<'static> {
-    let l4_table = get_active_l4_table();
+    let l4_table = get_kernel_l4_table();
     let phys_mem_offset = VirtAddr::new(physical_memory_offset());
 
     OffsetPageTable::new(l4_table, phys_mem_offset)
@@ -134,7 +208,7 @@ pub(crate) unsafe fn mapper() -> OffsetPageTable<'static> {
 
 /// Translates physical address into virtual address.
 pub fn phys_to_virt_addr(addr: PhysAddr) -> VirtAddr {
-    VirtAddr::new(addr.as_u64()) + PHYS_MEM_OFFSET.load(Ordering::Relaxed)
+    VirtAddr::new(addr.as_u64() + physical_memory_offset())
 }
 
 /// Translates virtual address into physical address.
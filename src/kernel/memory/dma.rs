@@ -0,0 +1,117 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::ptr;
+use core::slice;
+
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::structures::paging::PhysFrame;
+
+use super::{frame, PAGE_SIZE};
+
+// Direct Memory Access (DMA)
+//
+// Bus-mastering devices access memory by physical address and know nothing about paging, so the
+// buffers they use must be physically contiguous. Many devices (and every 32-bit PCI device) can only
+// address the low 4 GiB of memory.
+//
+// On x86, PCI bus-master accesses snoop the CPU caches, so ordinary write-back memory is coherent
+// with the device. The buffer is accessed through the physical memory mapping set up by the
+// bootloader, so no additional page table entries are needed.
+
+////////////////
+// Attributes
+////////////////
+
+/// Upper limit (exclusive) of the physical addresses handed out for DMA.
+pub const DMA_LIMIT: u64 = 0x1_0000_0000;
+
+//////////////////
+/// DMA Buffer
+//////////////////
+#[derive(Debug)]
+pub struct DmaBuffer {
+    virt: VirtAddr,
+    phys: PhysAddr,
+    size: usize,
+    frames: usize,
+}
+
+// The buffer exclusively owns its frames.
+unsafe impl Send for DmaBuffer {}
+
+impl DmaBuffer {
+    /// Returns the virtual address of the buffer.
+    pub fn virt_addr(&self) -> VirtAddr { self.virt }
+
+    /// Returns the physical address of the buffer, as seen by the device.
+    pub fn phys_addr(&self) -> PhysAddr { self.phys }
+
+    /// Returns the requested size of the buffer.
+    pub fn size(&self) -> usize { self.size }
+
+    /// Returns a raw pointer to the buffer.
+    pub fn as_ptr<T>(&self) -> *const T { self.virt.as_ptr() }
+
+    /// Returns a raw mutable pointer to the buffer.
+    pub fn as_mut_ptr<T>(&mut self) -> *mut T { self.virt.as_mut_ptr() }
+
+    /// Returns the buffer as a byte slice.
+    pub fn as_slice(&self) -> &[u8] { unsafe { slice::from_raw_parts(self.as_ptr(), self.size) } }
+
+    /// Returns the buffer as a mutable byte slice.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] { unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.size) } }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        let start = PhysFrame::containing_address(self.phys);
+        unsafe { frame::deallocate_contiguous(start, self.frames); }
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Allocates a zeroed, physically contiguous buffer below 4 GiB.
+///
+/// Note: `align` must be a power of two; alignments below the page size are rounded up to it.
+pub fn alloc_coherent(size: usize, align: usize) -> Option<DmaBuffer> {
+    if size == 0 || !align.is_power_of_two() { return None; }
+
+    let frames = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+    let align = (align / PAGE_SIZE).max(1);
+
+    let start = frame::allocate_contiguous(frames, align, Some(PhysAddr::new(DMA_LIMIT)))?;
+    let phys = start.start_address();
+    let virt = super::phys_to_virt_addr(phys);
+
+    unsafe { ptr::write_bytes(virt.as_mut_ptr::<u8>(), 0, frames * PAGE_SIZE); }
+
+    Some(DmaBuffer {
+        virt,
+        phys,
+        size,
+        frames,
+    })
+}
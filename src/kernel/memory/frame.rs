@@ -0,0 +1,244 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use spin::Mutex;
+use x86_64::instructions;
+use x86_64::PhysAddr;
+use x86_64::structures::paging::{FrameAllocator, FrameDeallocator, PhysFrame, Size4KiB};
+
+use crate::kernel::boot::{MemoryRegion, RegionKind};
+
+use super::{BootInfoFrameAllocator, PAGE_SIZE};
+
+// Physical Frame Allocator
+//
+// The boot info frame allocator hands out frames in a strictly increasing order and can never take
+// them back, which is fine for mapping the heap but not for drivers that come and go. Once the heap
+// is available, the boot allocator is retired and replaced with a bitmap allocator that keeps one bit
+// per usable frame. Frames already handed out during early boot are marked as used.
+//
+// The bitmap allocator also supports physically contiguous allocations with an upper address limit,
+// which is a requirement for DMA buffers.
+
+/////////////
+// Mutexes
+/////////////
+
+/// A global interface for the physical frame allocator.
+static FRAME_ALLOCATOR: Mutex<BitmapFrameAllocator> = Mutex::new(BitmapFrameAllocator::new());
+
+//////////////
+/// Region
+//////////////
+struct Region {
+    /// Number of the first frame in the region.
+    first: u64,
+    /// Number of frames in the region.
+    frames: usize,
+    /// One bit per frame; a set bit marks a used frame.
+    bitmap: Vec<u64>,
+}
+
+impl Region {
+    /// Creates a new object with all frames marked as free.
+    fn new(first: u64, frames: usize) -> Self {
+        Region {
+            first,
+            frames,
+            bitmap: vec![0; (frames + 63) / 64],
+        }
+    }
+
+    /// Returns whether the frame at the given index is in use.
+    fn is_used(&self, idx: usize) -> bool { self.bitmap[idx / 64] & (1 << (idx % 64)) != 0 }
+
+    /// Marks the frame at the given index as used or free.
+    fn set_used(&mut self, idx: usize, used: bool) {
+        if used {
+            self.bitmap[idx / 64] |= 1 << (idx % 64);
+        } else {
+            self.bitmap[idx / 64] &= !(1 << (idx % 64));
+        }
+    }
+
+    /// Returns whether the given frame number lies within the region.
+    fn contains(&self, number: u64) -> bool { number >= self.first && number < self.first + self.frames as u64 }
+}
+
+//////////////////////////////
+/// Bitmap Frame Allocator
+//////////////////////////////
+pub struct BitmapFrameAllocator {
+    regions: Vec<Region>,
+    free: usize,
+}
+
+impl BitmapFrameAllocator {
+    /// Creates a new empty object.
+    const fn new() -> Self {
+        BitmapFrameAllocator {
+            regions: Vec::new(),
+            free: 0,
+        }
+    }
+
+    /// Builds the bitmap from the memory map, marking the first `used` usable frames as allocated.
+    fn populate(&mut self, memory_map: &[MemoryRegion], mut used: usize) {
+        let frame_size = PAGE_SIZE as u64;
+
+        for region in memory_map.iter().filter(|r| r.kind == RegionKind::Usable) {
+            let first = (region.start + frame_size - 1) / frame_size;
+            let last = region.end / frame_size;
+            if last <= first { continue; }
+
+            let mut region = Region::new(first, (last - first) as usize);
+            for idx in 0..region.frames {
+                if used == 0 { break; }
+                region.set_used(idx, true);
+                used -= 1;
+            }
+            self.free += region.frames - region.bitmap.iter().map(|w| w.count_ones() as usize).sum::<usize>();
+            self.regions.push(region);
+        }
+    }
+
+    /// Allocates `count` physically contiguous frames aligned to `align` frames, ending below `limit`, if any.
+    fn allocate_contiguous(&mut self, count: usize, align: usize, limit: Option<PhysAddr>) -> Option<PhysFrame> {
+        let frame_size = PAGE_SIZE as u64;
+        let limit = limit.map_or(u64::MAX, |limit| limit.as_u64() / frame_size);
+        let align = align.max(1) as u64;
+
+        for region in self.regions.iter_mut() {
+            let mut number = (region.first + align - 1) / align * align;
+
+            while number + count as u64 <= (region.first + region.frames as u64).min(limit) {
+                let base = (number - region.first) as usize;
+                match (0..count).rev().find(|i| region.is_used(base + i)) {
+                    Some(i) => {
+                        // Skip past the used frame, preserving alignment.
+                        number = (number + i as u64 + 1 + align - 1) / align * align;
+                    }
+                    None => {
+                        for i in 0..count {
+                            region.set_used(base + i, true);
+                        }
+                        self.free -= count;
+                        return Some(PhysFrame::containing_address(PhysAddr::new(number * frame_size)));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Releases `count` contiguous frames starting at `start`.
+    fn deallocate_contiguous(&mut self, start: PhysFrame, count: usize) {
+        let number = start.start_address().as_u64() / PAGE_SIZE as u64;
+
+        let region = self.regions
+                         .iter_mut()
+                         .find(|r| r.contains(number))
+                         .expect("deallocating a frame that is not managed by the allocator");
+
+        let base = (number - region.first) as usize;
+        for i in 0..count {
+            assert!(region.is_used(base + i), "double free of physical frame");
+            region.set_used(base + i, false);
+        }
+        self.free += count;
+    }
+}
+
+//////////////////////////
+/// Global Frame Handle
+//////////////////////////
+///
+/// A handle that forwards to the global frame allocator, usable wherever the `x86_64` crate expects
+/// a frame allocator (e.g. `Mapper::map_to`).
+pub struct GlobalFrameAllocator;
+
+unsafe impl FrameAllocator<Size4KiB> for GlobalFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> { allocate() }
+}
+
+impl FrameDeallocator<Size4KiB> for GlobalFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size4KiB>) { deallocate(frame); }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Retires the boot info frame allocator and takes over the management of physical memory.
+pub(crate) fn init(boot_allocator: BootInfoFrameAllocator) {
+    instructions::interrupts::without_interrupts(
+        || { FRAME_ALLOCATOR.lock().populate(boot_allocator.memory_map, boot_allocator.next); }
+    );
+}
+
+/// Allocates a single physical frame.
+pub fn allocate() -> Option<PhysFrame> { allocate_contiguous(1, 1, None) }
+
+/// Releases a single physical frame.
+///
+/// # Safety
+///
+/// The frame must have been returned by the allocator, and must no longer be mapped or otherwise in use.
+pub unsafe fn deallocate(frame: PhysFrame) { deallocate_contiguous(frame, 1); }
+
+/// Allocates `count` physically contiguous frames aligned to `align` frames, lying entirely below `limit`
+/// if one is given.
+pub fn allocate_contiguous(count: usize, align: usize, limit: Option<PhysAddr>) -> Option<PhysFrame> {
+    instructions::interrupts::without_interrupts(
+        || { FRAME_ALLOCATOR.lock().allocate_contiguous(count, align, limit) }
+    )
+}
+
+/// Releases `count` physically contiguous frames starting at `start`.
+///
+/// # Safety
+///
+/// The frames must have been returned together by the allocator, and must no longer be mapped or
+/// otherwise in use.
+pub unsafe fn deallocate_contiguous(start: PhysFrame, count: usize) {
+    instructions::interrupts::without_interrupts(
+        || { FRAME_ALLOCATOR.lock().deallocate_contiguous(start, count); }
+    );
+}
+
+/// Returns the number of free physical frames.
+pub fn free_frames() -> usize {
+    instructions::interrupts::without_interrupts(
+        || { FRAME_ALLOCATOR.lock().free }
+    )
+}
+
+/// Returns the number of physical frames managed by the allocator.
+pub fn total_frames() -> usize {
+    instructions::interrupts::without_interrupts(
+        || { FRAME_ALLOCATOR.lock().regions.iter().map(|r| r.frames).sum() }
+    )
+}
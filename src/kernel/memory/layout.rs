@@ -0,0 +1,105 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+// Virtual Address Space Layout
+//
+// The 48-bit virtual space is split in two halves by the non-canonical hole. The lower half belongs
+// to user address spaces, except for its first 512 GiB, where the bootloader identity-maps the code
+// that switches to the page tables of the kernel. The upper half belongs to the kernel, which is
+// mapped at the same place in every address space (see `space`):
+//
+//   0x0000_0080_0000_0000 - 0x0000_7FFF_FFFF_FFFF   user range
+//   0xFFFF_8000_0000_0000 - 0xFFFF_BFFF_FFFF_FFFF   physical memory window (64 TiB)
+//   0xFFFF_C000_0000_0000 - 0xFFFF_C7FF_FFFF_FFFF   kernel heaps
+//   0xFFFF_C800_0000_0000 - 0xFFFF_CFFF_FFFF_FFFF   guarded allocations
+//   0xFFFF_D000_0000_0000 - 0xFFFF_D7FF_FFFF_FFFF   interrupt stacks
+//   0xFFFF_FE80_0000_0000 - 0xFFFF_FEFF_FFFF_FFFF   boot information
+//   0xFFFF_FF00_0000_0000 - 0xFFFF_FF7F_FFFF_FFFF   boot stack
+//   0xFFFF_FFFF_8000_0000 - 0xFFFF_FFFF_FFFF_FFFF   kernel image (top 2 GiB)
+//
+// All of physical memory is reachable through the window, and the kernel never dereferences a
+// physical address directly: it goes through `memory::phys_to_virt_addr`, be it for page tables,
+// firmware tables, memory-mapped registers, or the VGA buffers. The window, the boot information and
+// the boot stack are placed by the bootloader as configured under `package.metadata.bootloader` in
+// `Cargo.toml`, and the kernel image at the `--image-base` passed to the linker in
+// `x86_64-asm-os-kernel.json`, so those addresses must be kept in step with both files.
+//
+// The regions are checked against each other when the kernel is compiled, and against what the boot
+// protocol reports when it starts.
+
+////////////////
+// Attributes
+////////////////
+
+/// Start address of the user range.
+pub const USER_START: usize = 0x0000_0080_0000_0000;
+/// End address of the user range, which is the end of the lower half.
+pub const USER_END: usize = 0x0000_8000_0000_0000;
+
+/// Start address of the upper half, which belongs to the kernel.
+pub const KERNEL_HALF_START: usize = 0xFFFF_8000_0000_0000;
+
+/// Start address of the window all physical memory is mapped in.
+pub const PHYS_MAP_START: usize = KERNEL_HALF_START;
+/// End address of the physical memory window.
+pub const PHYS_MAP_END: usize = 0xFFFF_C000_0000_0000;
+
+/// Start address of the region the kernel heaps are mapped in.
+pub const HEAP_START: usize = 0xFFFF_C000_0000_0000;
+/// End address of the region the kernel heaps are mapped in.
+pub const HEAP_END: usize = 0xFFFF_C800_0000_0000;
+
+/// Start address of the region the guard allocator maps its allocations in.
+pub const GUARD_START: usize = 0xFFFF_C800_0000_0000;
+/// End address of the region the guard allocator maps its allocations in.
+pub const GUARD_END: usize = 0xFFFF_D000_0000_0000;
+
+/// Start address of the region the interrupt stacks are mapped in.
+pub const STACKS_START: usize = 0xFFFF_D000_0000_0000;
+/// End address of the region the interrupt stacks are mapped in.
+pub const STACKS_END: usize = 0xFFFF_D800_0000_0000;
+
+/// Start address of the boot information, as placed by the bootloader.
+pub const BOOT_INFO_START: usize = 0xFFFF_FE80_0000_0000;
+/// Start address of the boot stack, as placed by the bootloader.
+pub const BOOT_STACK_START: usize = 0xFFFF_FF00_0000_0000;
+
+/// Start address of the kernel image, as linked.
+pub const KERNEL_START: usize = 0xFFFF_FFFF_8000_0000;
+
+// The regions follow each other in order, without overlapping.
+const _: () = {
+    if !(USER_START < USER_END && USER_END <= KERNEL_HALF_START) { panic!("user range overlaps the upper half"); }
+    if !(PHYS_MAP_END <= HEAP_START && HEAP_END <= GUARD_START) { panic!("heap region overlaps a neighbour"); }
+    if !(GUARD_END <= STACKS_START && STACKS_END <= BOOT_INFO_START) { panic!("stack region overlaps a neighbour"); }
+    if !(BOOT_INFO_START < BOOT_STACK_START && BOOT_STACK_START < KERNEL_START) { panic!("boot regions overlap"); }
+};
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether the address lies in the user range.
+pub fn is_user(addr: usize) -> bool { (USER_START..USER_END).contains(&addr) }
+
+/// Returns whether the address lies in the upper half, which belongs to the kernel.
+pub fn is_kernel(addr: usize) -> bool { addr >= KERNEL_HALF_START }
@@ -0,0 +1,237 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::{apprise, warning};
+use crate::kernel::allocator;
+use crate::kernel::error::Error;
+use crate::kernel::pit;
+
+use super::{frame, PAGE_SIZE};
+
+// Memory Pressure
+//
+// The heap and the physical frames are each checked against a low watermark, the percentage of the
+// resource that should stay free. Once a resource falls below its watermark, a low memory event is
+// published: it is logged and counted, and each registered shrinker is asked to release what it can
+// spare, e.g. the block cache drops its clean blocks. The event is published again only after the
+// resource has recovered above the watermark.
+//
+// Checks run periodically from the monitor task, and right away when an allocation fails, in which
+// case the allocation is retried once the shrinkers have released something. A shrinker may thus be
+// called with a lock held that it also takes, so it has to back off rather than wait for a lock, and
+// it must not allocate.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of registered shrinkers.
+const MAX_SHRINKERS: usize = 8;
+
+/// Time between successive checks of the memory usage, in seconds.
+const CHECK_INTERVAL: f64 = 1.0;
+
+///////////////
+/// Default
+///////////////
+pub struct Default;
+
+impl Default {
+    /// Percentage of the heap that should stay free.
+    pub const HEAP_WATERMARK: usize = 10;
+    /// Percentage of the physical frames that should stay free.
+    pub const FRAME_WATERMARK: usize = 5;
+}
+
+////////////
+// States
+////////////
+
+/// Low watermarks of the resources, as percentages that should stay free.
+static WATERMARKS: [AtomicUsize; 2] = [
+    AtomicUsize::new(Default::HEAP_WATERMARK),
+    AtomicUsize::new(Default::FRAME_WATERMARK),
+];
+/// Whether each resource is below its watermark.
+static LOW: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+/// Number of low memory events published.
+static EVENTS: AtomicUsize = AtomicUsize::new(0);
+/// Number of bytes released by the shrinkers.
+static RECLAIMED: AtomicUsize = AtomicUsize::new(0);
+/// Whether the shrinkers are running, so that they are not run again from within.
+static RECLAIMING: AtomicBool = AtomicBool::new(false);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of registered shrinkers.
+static SHRINKERS: Mutex<[Option<(&'static str, Shrink)>; MAX_SHRINKERS]> = Mutex::new([None; MAX_SHRINKERS]);
+
+/// Releases memory of the given resource and returns the number of bytes released.
+pub type Shrink = fn(Resource) -> usize;
+
+////////////////
+/// Resource
+////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Resource {
+    Heap = 0x0,
+    Frames = 0x1,
+}
+
+impl Resource {
+    /// All resources.
+    pub const ALL: [Resource; 2] = [Resource::Heap, Resource::Frames];
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Heap => "heap",
+            Self::Frames => "frames",
+        }
+    }
+
+    /// Returns the number of bytes of the resource that are free, along with its size.
+    pub fn usage(&self) -> (usize, usize) {
+        match self {
+            Self::Heap => {
+                let used = allocator::stats(allocator::strategy()).bytes;
+                (allocator::HEAP_SIZE.saturating_sub(used), allocator::HEAP_SIZE)
+            }
+            Self::Frames => (frame::free_frames() * PAGE_SIZE, frame::total_frames() * PAGE_SIZE),
+        }
+    }
+
+    /// Returns whether the free part of the resource is below its watermark.
+    fn is_below_watermark(&self) -> bool {
+        let (free, total) = self.usage();
+        free * 100 < total * get_watermark(*self)
+    }
+}
+
+/////////////
+/// Stats
+/////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    /// Number of low memory events published.
+    pub events: usize,
+    /// Number of bytes released by the shrinkers.
+    pub reclaimed: usize,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Registers a shrinker, called with the resource that runs low.
+pub fn register_shrinker(name: &'static str, shrink: Shrink) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut shrinkers = SHRINKERS.lock();
+
+            if shrinkers.iter().flatten().any(|(n, _)| *n == name) { return Err(Error::AlreadyExists); }
+
+            let slot = shrinkers.iter_mut().find(|s| s.is_none()).ok_or(Error::OutOfResources)?;
+            *slot = Some((name, shrink));
+
+            Ok(())
+        }
+    )
+}
+
+/// Returns the low watermark of the resource, as a percentage that should stay free.
+pub fn get_watermark(resource: Resource) -> usize { WATERMARKS[resource as usize].load(Ordering::Relaxed) }
+
+/// Sets the low watermark of the resource, as a percentage that should stay free.
+pub fn set_watermark(resource: Resource, percent: usize) -> Result<(), Error> {
+    if percent > 100 { return Err(Error::OutOfBounds); }
+    WATERMARKS[resource as usize].store(percent, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Returns whether the resource is below its watermark as of the last check.
+pub fn is_low(resource: Resource) -> bool { LOW[resource as usize].load(Ordering::Relaxed) }
+
+/// Returns the number of low memory events and of the bytes reclaimed so far.
+pub fn stats() -> Stats {
+    Stats {
+        events: EVENTS.load(Ordering::Relaxed),
+        reclaimed: RECLAIMED.load(Ordering::Relaxed),
+    }
+}
+
+/// Checks each resource against its watermark, publishing a low memory event for those that fell below.
+pub fn check() {
+    for resource in Resource::ALL {
+        let is_below = resource.is_below_watermark();
+        let was_below = LOW[resource as usize].swap(is_below, Ordering::Relaxed);
+
+        match (was_below, is_below) {
+            (false, true) => {
+                EVENTS.fetch_add(1, Ordering::Relaxed);
+                let released = reclaim(resource);
+                let (free, total) = resource.usage();
+                warning!(
+                    "low memory: {} {} of {} bytes free, {} bytes reclaimed",
+                    resource.as_str(), free, total, released
+                );
+            }
+            (true, false) => apprise!("memory recovered: {} back above its watermark", resource.as_str()),
+            _ => {}
+        }
+    }
+}
+
+/// Asks the shrinkers to release memory of the resource and returns the number of bytes released.
+///
+/// Note: Returns zero without calling anything if the shrinkers are already running.
+pub fn reclaim(resource: Resource) -> usize {
+    if RECLAIMING.swap(true, Ordering::Acquire) { return 0; }
+
+    let shrinkers = instructions::interrupts::without_interrupts(|| *SHRINKERS.lock());
+    let mut released = 0;
+    for (_, shrink) in shrinkers.iter().flatten() {
+        released += shrink(resource);
+        if !resource.is_below_watermark() { break; }
+    }
+    RECLAIMED.fetch_add(released, Ordering::Relaxed);
+
+    RECLAIMING.store(false, Ordering::Release);
+    released
+}
+
+/// Periodically checks the memory usage.
+pub async fn monitor() {
+    loop {
+        pit::delay(CHECK_INTERVAL).await;
+        check();
+    }
+}
@@ -0,0 +1,295 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::ops::Range;
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use bitflags::bitflags;
+use spin::Mutex;
+use x86_64::{instructions, PhysAddr, VirtAddr};
+use x86_64::registers::control::Cr3;
+use x86_64::structures::paging::{Mapper, OffsetPageTable, Page, PageTable, PageTableFlags, PhysFrame, Translate};
+use x86_64::structures::paging::page_table::PageTableEntry;
+
+use crate::kernel::error::Error;
+
+use super::{frame, layout, PAGE_SIZE};
+use super::frame::GlobalFrameAllocator;
+
+// Address Spaces
+//
+// Each address space owns a level-4 page table (PML4) of its own. The entries covering the user range
+// are private to the space, along with the tables and frames below them, while every other entry is
+// copied from the table of the kernel. The kernel is thus mapped at the same place in every space and
+// shares its lower-level tables with them, but nothing a space maps in the user range is visible from
+// another one, whatever the privilege level of the code looking.
+//
+// The executor loads the space of a task into CR3 before polling it and goes back to the kernel space
+// afterwards. Entries the kernel adds to its own table later on, such as the heap of a strategy that
+// is selected for the first time, are copied again whenever a space is loaded, and the page fault
+// handler copies them on demand for a space that is already loaded.
+//
+// OS Dev Wiki: https://wiki.osdev.org/Paging#Manipulation
+
+////////////////
+// Attributes
+////////////////
+
+/// Start address of the user range in the virtual space.
+pub const USER_START: usize = layout::USER_START;
+/// End address of the user range in the virtual space, which is the end of the lower half.
+pub const USER_END: usize = layout::USER_END;
+
+/// Indices of the level-4 entries that cover the user range.
+const USER_ENTRIES: Range<usize> = (USER_START >> 39)..(USER_END >> 39);
+
+////////////
+// States
+////////////
+
+/// Physical address of the level-4 table of the kernel, or zero before initialization.
+static KERNEL_ROOT: AtomicU64 = AtomicU64::new(0);
+
+/// Physical address of the level-4 table that is loaded.
+static ACTIVE_ROOT: AtomicU64 = AtomicU64::new(0);
+
+bitflags! {
+    /// Access rights of a mapped region, on top of reading.
+    pub struct Access: u8 {
+        const WRITE = 1 << 0;
+        const EXECUTE = 1 << 1;
+    }
+}
+
+/////////////////////
+/// Address Space
+/////////////////////
+pub struct AddressSpace {
+    root: PhysFrame,
+    /// Serializes the changes to the tables.
+    lock: Mutex<()>,
+}
+
+impl AddressSpace {
+    /// Creates a new space, with nothing mapped in the user range.
+    pub fn new() -> Result<Self, Error> {
+        let root = frame::allocate().ok_or(Error::OutOfMemory)?;
+        let table = unsafe { table(root) };
+        table.zero();
+        copy_kernel_entries(table);
+
+        Ok(AddressSpace { root, lock: Mutex::new(()) })
+    }
+
+    /// Maps a region of the user range to fresh, zeroed frames.
+    ///
+    /// Note: The region must be page-aligned and must not overlap a mapped one.
+    pub fn map(&self, start: VirtAddr, size: usize, access: Access) -> Result<(), Error> {
+        let pages = user_pages(start, size)?;
+
+        let mut flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        if access.contains(Access::WRITE) { flags |= PageTableFlags::WRITABLE; }
+        if !access.contains(Access::EXECUTE) { flags |= PageTableFlags::NO_EXECUTE; }
+
+        let _guard = self.lock.lock();
+        let mut mapper = unsafe { self.mapper() };
+        for (mapped, page) in pages.clone().enumerate() {
+            let res = frame::allocate().ok_or(Error::OutOfMemory).and_then(
+                |frame| {
+                    let virt_addr = super::phys_to_virt_addr(frame.start_address());
+                    unsafe { ptr::write_bytes(virt_addr.as_mut_ptr::<u8>(), 0, PAGE_SIZE) };
+                    match unsafe { mapper.map_to(page, frame, flags, &mut GlobalFrameAllocator) } {
+                        Ok(flush) => {
+                            if self.is_active() { flush.flush() } else { flush.ignore() }
+                            Ok(())
+                        }
+                        Err(e) => {
+                            unsafe { frame::deallocate(frame) };
+                            Err(Error::from(e))
+                        }
+                    }
+                }
+            );
+            if let Err(e) = res {
+                // Roll back, so that a failed mapping leaves nothing behind.
+                pages.take(mapped).for_each(|page| self.unmap_page(&mut mapper, page));
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Unmaps a region of the user range and releases its frames.
+    ///
+    /// Note: The pages of the region that are not mapped are skipped.
+    pub fn unmap(&self, start: VirtAddr, size: usize) -> Result<(), Error> {
+        let pages = user_pages(start, size)?;
+
+        let _guard = self.lock.lock();
+        let mut mapper = unsafe { self.mapper() };
+        pages.for_each(|page| self.unmap_page(&mut mapper, page));
+
+        Ok(())
+    }
+
+    /// Translates a virtual address of the space into a physical address.
+    pub fn translate(&self, addr: VirtAddr) -> Option<PhysAddr> {
+        let _guard = self.lock.lock();
+        unsafe { self.mapper() }.translate_addr(addr)
+    }
+
+    /// Returns whether the space is loaded.
+    pub fn is_active(&self) -> bool { ACTIVE_ROOT.load(Ordering::Relaxed) == self.root.start_address().as_u64() }
+
+    /// Unmaps the given page and releases its frame.
+    fn unmap_page(&self, mapper: &mut OffsetPageTable, page: Page) {
+        if let Ok((frame, flush)) = mapper.unmap(page) {
+            if self.is_active() { flush.flush() } else { flush.ignore() }
+            unsafe { frame::deallocate(frame) };
+        }
+    }
+
+    /// Returns a mapper over the tables of the space.
+    ///
+    /// Note: The caller must hold the lock.
+    unsafe fn mapper(&self) -> OffsetPageTable<'static> {
+        OffsetPageTable::new(table(self.root), VirtAddr::new(super::physical_memory_offset()))
+    }
+}
+
+impl Drop for AddressSpace {
+    fn drop(&mut self) {
+        // The tables must not be in use once they are released.
+        if self.is_active() { switch(None); }
+
+        let root = unsafe { table(self.root) };
+        for idx in USER_ENTRIES {
+            unsafe { release(&mut root[idx], 3) };
+        }
+        unsafe { frame::deallocate(self.root) };
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Records the tables set up by the bootloader as those of the kernel.
+pub(crate) fn init() {
+    let (root, _) = Cr3::read();
+    KERNEL_ROOT.store(root.start_address().as_u64(), Ordering::Relaxed);
+    ACTIVE_ROOT.store(root.start_address().as_u64(), Ordering::Relaxed);
+}
+
+/// Returns the level-4 table of the kernel, or none before initialization.
+pub(crate) fn kernel_root() -> Option<PhysFrame> {
+    match KERNEL_ROOT.load(Ordering::Relaxed) {
+        0 => None,
+        addr => Some(PhysFrame::containing_address(PhysAddr::new(addr))),
+    }
+}
+
+/// Loads the given space, or the kernel space if none is given.
+pub(crate) fn switch(space: Option<&AddressSpace>) {
+    let kernel = match kernel_root() {
+        Some(kernel) => kernel,
+        None => return,
+    };
+    let root = space.map_or(kernel, |space| space.root);
+    if ACTIVE_ROOT.load(Ordering::Relaxed) == root.start_address().as_u64() { return; }
+
+    instructions::interrupts::without_interrupts(
+        || {
+            if space.is_some() { copy_kernel_entries(unsafe { table(root) }); }
+            let (_, flags) = Cr3::read();
+            unsafe { Cr3::write(root, flags) };
+            ACTIVE_ROOT.store(root.start_address().as_u64(), Ordering::Relaxed);
+        }
+    );
+}
+
+/// Copies the kernel entry covering the given address into the loaded space if it lacks it, and returns
+/// whether it did, in which case the faulting access can be retried.
+///
+/// Note: It is called by the page fault handler.
+pub(crate) fn sync_kernel_entry(addr: VirtAddr) -> bool {
+    let (kernel, active) = match kernel_root() {
+        Some(kernel) => (kernel, ACTIVE_ROOT.load(Ordering::Relaxed)),
+        None => return false,
+    };
+    let idx = usize::from(addr.p4_index());
+    if active == kernel.start_address().as_u64() || USER_ENTRIES.contains(&idx) { return false; }
+
+    let kernel_entry = unsafe { &table(kernel)[idx] };
+    let entry = unsafe { &mut table(PhysFrame::containing_address(PhysAddr::new(active)))[idx] };
+    if kernel_entry.is_unused() || !entry.is_unused() { return false; }
+
+    *entry = kernel_entry.clone();
+    true
+}
+
+/// Returns the pages of a region, provided it is page-aligned and lies within the user range.
+fn user_pages(start: VirtAddr, size: usize) -> Result<impl Iterator<Item=Page> + Clone, Error> {
+    let addr = start.as_u64() as usize;
+    let end = addr.checked_add(size).ok_or(Error::InvalidArgument)?;
+    if size == 0 || addr % PAGE_SIZE != 0 || size % PAGE_SIZE != 0 { return Err(Error::InvalidArgument); }
+    if addr < USER_START || end > USER_END { return Err(Error::OutOfBounds); }
+
+    let first = Page::containing_address(start);
+    Ok((0..size / PAGE_SIZE).map(move |idx| first + idx as u64))
+}
+
+/// Copies the entries outside of the user range from the level-4 table of the kernel.
+fn copy_kernel_entries(root: &mut PageTable) {
+    let kernel = match kernel_root() {
+        Some(kernel) => unsafe { table(kernel) },
+        None => return,
+    };
+    for (idx, entry) in root.iter_mut().enumerate().filter(|(idx, _)| !USER_ENTRIES.contains(idx)) {
+        *entry = kernel[idx].clone();
+    }
+}
+
+/// Releases the table or frame the entry points to, along with everything mapped below it.
+///
+/// Note: `level` is the level of the table pointed to, zero standing for a mapped frame.
+unsafe fn release(entry: &mut PageTableEntry, level: u8) {
+    // Spaces never map huge pages, so the frame is missing only if the entry is unused.
+    let frame = match entry.frame() {
+        Ok(frame) => frame,
+        Err(_) => return,
+    };
+    if level > 0 {
+        for child in table(frame).iter_mut() {
+            release(child, level - 1);
+        }
+    }
+    frame::deallocate(frame);
+    entry.set_unused();
+}
+
+/// Returns the table stored in the given frame.
+unsafe fn table(frame: PhysFrame) -> &'static mut PageTable {
+    &mut *super::phys_to_virt_addr(frame.start_address()).as_mut_ptr::<PageTable>()
+}
@@ -20,14 +20,79 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use crate::register_initcall;
+use crate::aux::earlyprintk;
+use crate::drivers::serial;
+use self::initcall::Level;
+
 pub mod acpi;
 pub mod allocator;
+#[cfg_attr(not(feature = "smp"), allow(dead_code))]
 pub mod apic;
+#[cfg(feature = "fs")]
+pub mod block;
+pub mod boot;
+pub mod capability;
+pub mod clipboard;
 pub mod cmos;
+pub mod compress;
+pub mod config;
+pub mod cpu;
+pub mod crypto;
+pub mod dev;
+pub mod env;
+pub mod error;
+pub mod fd;
 pub mod gdt;
+pub mod gfx;
+pub mod hpet;
 pub mod idt;
+pub mod initcall;
 pub mod memory;
+pub mod mqueue;
+pub mod pci;
 pub mod pics;
+pub mod pipe;
 pub mod pit;
+pub mod portio;
 pub mod power;
+pub mod process;
+pub mod ramfs;
+pub mod rtc;
+pub mod selftest;
+pub mod sensors;
+pub mod signal;
+pub mod stats;
+pub mod strace;
+pub mod sync;
 pub mod task;
+pub mod time;
+pub mod vfs;
+
+/// Registers the devices provided by the kernel.
+pub(crate) fn register_devices() {
+    dev::register(&gdt::DEVICE).ok();
+    dev::register(&idt::DEVICE).ok();
+    dev::register(&pics::DEVICE).ok();
+    dev::register(&pit::DEVICE).ok();
+    dev::register(&time::DEVICE).ok();
+    dev::register(&allocator::DEVICE).ok();
+    dev::register(&acpi::DEVICE).ok();
+    dev::register(&hpet::DEVICE).ok();
+    dev::register(&rtc::DEVICE).ok();
+    dev::register(&pci::DEVICE).ok();
+    #[cfg(feature = "smp")]
+    dev::register(&apic::DEVICE).ok();
+}
+
+/// Registers the boot steps of the kernel that are not devices.
+pub(crate) fn register_initcalls() {
+    register_initcall!(Level::Core, "Early Console", earlyprintk::handoff).ok();
+    register_initcall!(Level::Core, "Environment", env::init).ok();
+    register_initcall!(Level::Core, "Serial Input", serial::enable_input).ok();
+    #[cfg(feature = "fs")]
+    register_initcall!(Level::Core, "Block Cache", block::cache::init).ok();
+    register_initcall!(Level::Driver, "VFS", vfs::init).ok();
+    register_initcall!(Level::Late, "Config", config::persistent::load).ok();
+    register_initcall!(Level::Late, "Self-Test", selftest::init).ok();
+}
@@ -0,0 +1,219 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::allocator::fallible;
+use crate::kernel::error::Error;
+
+// Message Queues
+//
+// A message queue carries whole messages, rather than a stream of bytes like a pipe, between any
+// number of senders and receivers that find it by name. Messages are received in the order they were
+// sent, each by exactly one receiver. Sending never blocks and fails once the queue is full; receiving
+// is asynchronous, and receivers waiting on an empty queue are woken up in turn as messages arrive.
+//
+// A queue lives until it is unlinked; handles opened before then keep working, but the name can be
+// taken by a new queue right away.
+
+////////////////
+// Attributes
+////////////////
+
+/// Default number of messages a queue holds.
+pub const DEFAULT_CAPACITY: usize = 64;
+/// Maximum size of a message in bytes.
+pub const MAX_MESSAGE_SIZE: usize = 4096;
+/// Maximum length of the name of a queue.
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of the named queues.
+static QUEUES: Mutex<BTreeMap<String, MessageQueue>> = Mutex::new(BTreeMap::new());
+
+//////////////
+/// Shared
+//////////////
+struct Shared {
+    messages: VecDeque<Vec<u8>>,
+    capacity: usize,
+    wakers: VecDeque<Waker>,
+}
+
+////////////
+/// Info
+////////////
+#[derive(Debug, Clone)]
+pub struct Info {
+    pub name: String,
+    /// Number of messages waiting.
+    pub len: usize,
+    /// Maximum number of messages waiting.
+    pub capacity: usize,
+}
+
+/////////////////////
+/// Message Queue
+/////////////////////
+#[derive(Clone)]
+pub struct MessageQueue {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl MessageQueue {
+    /// Creates a new object holding up to `capacity` messages.
+    fn new(capacity: usize) -> Self {
+        let shared = Shared { messages: VecDeque::new(), capacity, wakers: VecDeque::new() };
+        MessageQueue { shared: Arc::new(Mutex::new(shared)) }
+    }
+
+    /// Appends the message to the queue, failing if it is too large or the queue is full.
+    pub fn send(&self, message: &[u8]) -> Result<(), Error> {
+        if message.len() > MAX_MESSAGE_SIZE { return Err(Error::InvalidArgument); }
+
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = self.shared.lock();
+                if shared.messages.len() >= shared.capacity { return Err(Error::OutOfResources); }
+                let message = fallible::try_to_vec(message)?;
+                shared.messages.try_reserve(1)?;
+                shared.messages.push_back(message);
+                if let Some(waker) = shared.wakers.pop_front() { waker.wake(); }
+                Ok(())
+            }
+        )
+    }
+
+    /// Removes and returns the oldest message, if any.
+    pub fn try_receive(&self) -> Option<Vec<u8>> {
+        instructions::interrupts::without_interrupts(
+            || { self.shared.lock().messages.pop_front() }
+        )
+    }
+
+    /// Waits for a message, then removes and returns it.
+    pub fn receive(&self) -> Receive<'_> { Receive { queue: self } }
+
+    /// Returns the number of messages waiting.
+    pub fn len(&self) -> usize {
+        instructions::interrupts::without_interrupts(|| { self.shared.lock().messages.len() })
+    }
+
+    /// Returns whether no message is waiting.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Returns the maximum number of messages waiting.
+    pub fn capacity(&self) -> usize {
+        instructions::interrupts::without_interrupts(|| { self.shared.lock().capacity })
+    }
+}
+
+impl fmt::Debug for MessageQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageQueue").field("len", &self.len()).field("capacity", &self.capacity()).finish()
+    }
+}
+
+///////////////
+/// Receive
+///////////////
+pub struct Receive<'a> {
+    queue: &'a MessageQueue,
+}
+
+impl Future for Receive<'_> {
+    type Output = Vec<u8>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = self.queue.shared.lock();
+                match shared.messages.pop_front() {
+                    Some(message) => Poll::Ready(message),
+                    None => {
+                        if !shared.wakers.iter().any(|waker| waker.will_wake(context.waker())) {
+                            shared.wakers.push_back(context.waker().clone());
+                        }
+                        Poll::Pending
+                    }
+                }
+            }
+        )
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Creates a queue with the given name, holding up to `capacity` messages, and returns it.
+pub fn create(name: &str, capacity: usize) -> Result<MessageQueue, Error> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH || capacity == 0 { return Err(Error::InvalidArgument); }
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut queues = QUEUES.lock();
+            if queues.contains_key(name) { return Err(Error::AlreadyExists); }
+            let queue = MessageQueue::new(capacity);
+            queues.insert(name.to_string(), queue.clone());
+            Ok(queue)
+        }
+    )
+}
+
+/// Returns the queue with the given name.
+pub fn open(name: &str) -> Result<MessageQueue, Error> {
+    instructions::interrupts::without_interrupts(
+        || { QUEUES.lock().get(name).cloned().ok_or(Error::NotFound) }
+    )
+}
+
+/// Removes the name of the queue; the queue itself lives on while handles to it remain.
+pub fn unlink(name: &str) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || { QUEUES.lock().remove(name).map(|_| ()).ok_or(Error::NotFound) }
+    )
+}
+
+/// Calls the given function for each named queue, in alphabetical order.
+pub fn for_each(f: impl FnMut(&Info)) {
+    let queues: Vec<(String, MessageQueue)> = instructions::interrupts::without_interrupts(
+        || { QUEUES.lock().iter().map(|(name, queue)| (name.clone(), queue.clone())).collect() }
+    );
+    let infos: Vec<Info> = queues.into_iter()
+        .map(|(name, queue)| Info { name, len: queue.len(), capacity: queue.capacity() })
+        .collect();
+    infos.iter().for_each(f);
+}
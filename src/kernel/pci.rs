@@ -0,0 +1,447 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+use core::ptr;
+
+use spin::Mutex;
+use x86_64::{instructions, PhysAddr};
+
+use crate::kernel::acpi::madt;
+use crate::kernel::acpi::madt::Signal;
+use crate::kernel::acpi::namespace;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
+use crate::kernel::memory;
+use crate::kernel::portio;
+use crate::kernel::portio::Port;
+
+// Peripheral Component Interconnect (PCI)
+//
+// Each PCI function has a 256-byte configuration space describing the device (vendor, class, and so
+// on) and the resources assigned to it by the firmware. The configuration space is reached through
+// two I/O ports: the address of a 32-bit register is written to CONFIG_ADDRESS, after which the
+// register can be accessed through CONFIG_DATA.
+//
+// The buses are enumerated once at boot by probing every slot of every bus; a slot is empty if its
+// vendor ID reads as 0xFFFF.
+//
+// Devices without MSI raise their interrupts on one of four INTx pins. How the pins reach the
+// interrupt controller is described by the ACPI routing tables of the root buses; pins of devices
+// behind a bridge are rotated by the slot number on their way up. The interrupt line register is
+// only a hint left by the firmware for the legacy PICs, and is used only when ACPI has no answer.
+//
+// A function with MSI-X signals its interrupts by writing the message of one of the entries of its
+// MSI-X table, which lives in one of its BARs, to the address of a local APIC instead. Each entry
+// can be masked on its own, and they all start out masked.
+//
+// OS Dev Wiki: https://wiki.osdev.org/PCI
+
+////////////////
+// Attributes
+////////////////
+
+/// Port used to select a configuration register.
+const CONFIG_ADDRESS: u16 = 0xCF8;
+/// Port used to access the selected configuration register.
+const CONFIG_DATA: u16 = 0xCFC;
+
+/// Vendor ID read from an empty slot.
+const NO_VENDOR: u16 = 0xFFFF;
+
+/// Offset of the command register.
+const REG_COMMAND: u8 = 0x04;
+/// Offset of the header type register.
+const REG_HEADER_TYPE: u8 = 0x0C;
+/// Offset of the first Base Address Register (BAR).
+const REG_BAR0: u8 = 0x10;
+/// Offset of the bus number registers of a PCI-to-PCI bridge.
+const REG_BUS_NUMBERS: u8 = 0x18;
+/// Offset of the pointer to the capability list.
+const REG_CAPABILITIES: u8 = 0x34;
+/// Offset of the interrupt line and pin registers.
+const REG_INTERRUPT: u8 = 0x3C;
+
+/// Command register bit enabling responses to I/O space accesses.
+const CMD_IO_SPACE: u16 = 1 << 0;
+/// Command register bit enabling responses to memory space accesses.
+const CMD_MEMORY_SPACE: u16 = 1 << 1;
+/// Command register bit allowing the device to act as a bus master.
+const CMD_BUS_MASTER: u16 = 1 << 2;
+/// Command register bit keeping the device from asserting its INTx pin.
+const CMD_INTX_DISABLE: u16 = 1 << 10;
+
+/// Status register bit indicating the presence of a capability list.
+const STATUS_CAPABILITIES: u16 = 1 << 4;
+
+/// Capability ID of Message Signaled Interrupts (MSI).
+const CAP_MSI: u8 = 0x05;
+/// Capability ID of MSI-X.
+const CAP_MSI_X: u8 = 0x11;
+
+/// Address that messages are written to in order to reach a local APIC, whose ID goes in bits 12 to 19.
+const MSI_ADDRESS: u32 = 0xFEE0_0000;
+
+/// Header type of PCI-to-PCI bridges.
+const HEADER_BRIDGE: u8 = 0x01;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Functions found during enumeration.
+static FUNCTIONS: Mutex<Vec<Function>> = Mutex::new(Vec::new());
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the PCI bus.
+pub(crate) static DEVICE: Device = Device {
+    name: "PCI",
+    class: Class::System,
+    stage: Stage::Platform,
+    critical: false,
+    depends: &["Allocator"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+/////////////////
+/// Interrupt
+/////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupt {
+    /// Global system interrupt the pin is delivered on.
+    pub gsi: u32,
+    /// Polarity and trigger mode of the pin.
+    pub signal: Signal,
+}
+
+////////////////
+/// Function
+////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Function {
+    pub bus: u8,
+    pub slot: u8,
+    pub func: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+}
+
+impl Function {
+    /// Reads a 32-bit configuration register.
+    pub fn read(&self, offset: u8) -> u32 { read_config(self.bus, self.slot, self.func, offset) }
+
+    /// Writes a 32-bit configuration register.
+    pub fn write(&self, offset: u8, value: u32) { write_config(self.bus, self.slot, self.func, offset, value); }
+
+    /// Returns the physical address of the memory region described by the given BAR.
+    ///
+    /// Note: Returns `None` for I/O space and unassigned BARs.
+    pub fn memory_bar(&self, idx: u8) -> Option<u64> {
+        const IO_SPACE: u32 = 0x1;
+        const TYPE_MASK: u32 = 0x6;
+        const TYPE_64: u32 = 0x4;
+        const ADDR_MASK: u32 = !0xF;
+
+        let offset = REG_BAR0 + idx * 4;
+        let low = self.read(offset);
+        if low & IO_SPACE != 0 { return None; }
+
+        let mut addr = (low & ADDR_MASK) as u64;
+        if low & TYPE_MASK == TYPE_64 {
+            addr |= (self.read(offset + 4) as u64) << 32;
+        }
+
+        if addr == 0 { None } else { Some(addr) }
+    }
+
+    /// Returns the base of the I/O port range described by the given BAR.
+    ///
+    /// Note: Returns `None` for memory space and unassigned BARs.
+    pub fn io_bar(&self, idx: u8) -> Option<u16> {
+        const IO_SPACE: u32 = 0x1;
+        const ADDR_MASK: u32 = !0x3;
+
+        let bar = self.read(REG_BAR0 + idx * 4);
+        if bar & IO_SPACE == 0 { return None; }
+
+        let base = (bar & ADDR_MASK) as u16;
+        if base == 0 { None } else { Some(base) }
+    }
+
+    /// Returns a short description of the class and subclass.
+    pub fn class_name(&self) -> &str {
+        match (self.class, self.subclass) {
+            (0x01, 0x01) => "IDE controller",
+            (0x01, 0x06) => "SATA controller",
+            (0x01, 0x08) => "NVMe controller",
+            (0x01, _) => "storage controller",
+            (0x02, 0x00) => "Ethernet controller",
+            (0x02, _) => "network controller",
+            (0x03, 0x00) => "VGA controller",
+            (0x03, _) => "display controller",
+            (0x04, _) => "multimedia controller",
+            (0x05, _) => "memory controller",
+            (0x06, 0x00) => "host bridge",
+            (0x06, 0x01) => "ISA bridge",
+            (0x06, 0x04) => "PCI bridge",
+            (0x06, _) => "bridge",
+            (0x07, _) => "communication controller",
+            (0x08, _) => "system peripheral",
+            (0x0C, 0x03) => "USB controller",
+            (0x0C, 0x05) => "SMBus controller",
+            (0x0C, _) => "serial bus controller",
+            _ => "unclassified device",
+        }
+    }
+
+    /// Returns the INTx pin used by the function (1 to 4), or zero if it uses none.
+    pub fn interrupt_pin(&self) -> u8 { (self.read(REG_INTERRUPT) >> 8) as u8 }
+
+    /// Returns whether the function can signal interrupts through MSI or MSI-X.
+    pub fn has_msi(&self) -> bool { self.capability(CAP_MSI).is_some() || self.capability(CAP_MSI_X).is_some() }
+
+    /// Returns the offset of the capability with the given ID, if the function has it.
+    fn capability(&self, id: u8) -> Option<u8> {
+        let status = (self.read(REG_COMMAND) >> 16) as u16;
+        if status & STATUS_CAPABILITIES == 0 { return None; }
+
+        // Each capability starts with its ID followed by the offset of the next one. The walk is bounded
+        // in case the list loops.
+        let mut offset = self.read(REG_CAPABILITIES) as u8 & 0xFC;
+        for _ in 0..48 {
+            if offset == 0 { break; }
+            let header = self.read(offset);
+            if header as u8 == id { return Some(offset); }
+            offset = (header >> 8) as u8 & 0xFC;
+        }
+
+        None
+    }
+
+    /// Points the given entry of the MSI-X table at the vector on the local APIC with the given ID,
+    /// unmasks it and switches the function from its INTx pin to MSI-X.
+    ///
+    /// Note: The other entries are left as they are, which is masked unless set up before.
+    pub fn enable_msi_x(&self, entry: u16, vector: u8, apic_id: u8) -> Result<(), Error> {
+        const CTRL_ENABLE: u32 = 1 << 31;
+        const CTRL_FUNCTION_MASK: u32 = 1 << 30;
+        const CTRL_TABLE_SIZE: u32 = 0x7FF << 16;
+        const TABLE_BIR: u32 = 0x7;
+        const ENTRY_SIZE: u64 = 16;
+
+        let offset = self.capability(CAP_MSI_X).ok_or(Error::Unsupported)?;
+        let header = self.read(offset);
+        // The table size is encoded as one less than the number of entries.
+        if entry as u32 > (header & CTRL_TABLE_SIZE) >> 16 { return Err(Error::OutOfBounds); }
+
+        let table = self.read(offset + 4);
+        let bar = self.memory_bar((table & TABLE_BIR) as u8).ok_or(Error::Unsupported)?;
+        let addr = bar + (table & !TABLE_BIR) as u64 + entry as u64 * ENTRY_SIZE;
+        let regs = memory::phys_to_virt_addr(PhysAddr::new(addr)).as_mut_ptr::<u32>();
+
+        // Each entry holds the message address (low and high halves), the message data, and the vector
+        // control word whose lowest bit masks the entry.
+        unsafe {
+            ptr::write_volatile(regs, MSI_ADDRESS | (apic_id as u32) << 12);
+            ptr::write_volatile(regs.add(1), 0);
+            ptr::write_volatile(regs.add(2), vector as u32);
+            ptr::write_volatile(regs.add(3), 0);
+        }
+
+        self.write(offset, (header | CTRL_ENABLE) & !CTRL_FUNCTION_MASK);
+        let reg = self.read(REG_COMMAND);
+        self.write(REG_COMMAND, reg | CMD_INTX_DISABLE as u32);
+
+        Ok(())
+    }
+
+    /// Returns the interrupt the INTx pin of the function is delivered on, for use when MSI is unavailable.
+    pub fn legacy_interrupt(&self) -> Option<Interrupt> {
+        let pin = self.interrupt_pin();
+        if !(1..=4).contains(&pin) { return None; }
+
+        let (mut bus, mut slot, mut func, mut pin) = (self.bus, self.slot, self.func, pin);
+        loop {
+            if let Some((gsi, signal)) = namespace::pci_route(bus, slot, func, pin) {
+                return Some(Interrupt { gsi, signal });
+            }
+            let bridge = match bridge_to(bus) {
+                Some(bridge) => bridge,
+                None => break,
+            };
+            pin = (pin - 1 + slot) % 4 + 1;
+            (bus, slot, func) = (bridge.bus, bridge.slot, bridge.func);
+        }
+
+        // PCI interrupts are shared, level-triggered and active low.
+        const LINE_UNKNOWN: u8 = 0xFF;
+        let line = self.read(REG_INTERRUPT) as u8;
+        if line == LINE_UNKNOWN || line >= madt::ISA_IRQ_COUNT { return None; }
+        let gsi = madt::get_routing().and_then(|routing| routing.route(line)).map_or(line as u32, |route| route.gsi);
+        Some(Interrupt { gsi, signal: Signal { active_low: true, level_triggered: true } })
+    }
+
+    /// Returns whether the function is a PCI-to-PCI bridge.
+    pub fn is_bridge(&self) -> bool { (self.read(REG_HEADER_TYPE) >> 16) as u8 & 0x7F == HEADER_BRIDGE }
+
+    /// Enables I/O space accesses and bus mastering, for DMA-capable devices driven through ports.
+    pub fn enable_io_bus_master(&self) {
+        let reg = self.read(REG_COMMAND);
+        self.write(REG_COMMAND, reg | (CMD_IO_SPACE | CMD_BUS_MASTER) as u32);
+    }
+
+    /// Enables memory space accesses and bus mastering, which DMA-capable devices require.
+    pub fn enable_bus_master(&self) {
+        let reg = self.read(REG_COMMAND);
+        self.write(REG_COMMAND, reg | (CMD_MEMORY_SPACE | CMD_BUS_MASTER) as u32);
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Enumerates the functions on all buses.
+pub(crate) fn init() -> Result<(), Error> {
+    portio::reserve("PCI", CONFIG_ADDRESS, 8).ok();
+
+    let mut functions = Vec::new();
+    for bus in 0..=255u8 {
+        for slot in 0..32u8 {
+            if vendor_id(bus, slot, 0) == NO_VENDOR { continue; }
+
+            // Multi-function devices have bit 7 of the header type set.
+            const MULTI_FUNCTION: u32 = 0x80;
+            let header_type = (read_config(bus, slot, 0, REG_HEADER_TYPE) >> 16) & 0xFF;
+            let funcs = if header_type & MULTI_FUNCTION != 0 { 8 } else { 1 };
+
+            for func in 0..funcs {
+                if let Some(function) = probe(bus, slot, func) {
+                    functions.push(function);
+                }
+            }
+        }
+    }
+
+    instructions::interrupts::without_interrupts(
+        || { *FUNCTIONS.lock() = functions; }
+    );
+
+    Ok(())
+}
+
+/// Calls the given function for each enumerated PCI function.
+pub fn for_each(f: impl FnMut(&Function)) {
+    let functions = instructions::interrupts::without_interrupts(
+        || { FUNCTIONS.lock().clone() }
+    );
+    functions.iter().for_each(f);
+}
+
+/// Returns the functions with the given class, subclass, and programming interface.
+pub fn find_by_class(class: u8, subclass: u8, prog_if: u8) -> Vec<Function> {
+    let mut found = Vec::new();
+    for_each(|function| {
+        if (function.class, function.subclass, function.prog_if) == (class, subclass, prog_if) {
+            found.push(*function);
+        }
+    });
+    found
+}
+
+/// Returns the bridge whose secondary bus is the given one.
+///
+/// Note: Secondary buses are numbered above the bus of their bridge, which also rules out unconfigured bridges.
+fn bridge_to(bus: u8) -> Option<Function> {
+    let mut found = None;
+    for_each(|function| {
+        let secondary = (function.read(REG_BUS_NUMBERS) >> 8) as u8;
+        if found.is_none() && function.is_bridge() && secondary == bus && secondary > function.bus {
+            found = Some(*function);
+        }
+    });
+    found
+}
+
+/// Reads the identification of the function, if present.
+fn probe(bus: u8, slot: u8, func: u8) -> Option<Function> {
+    let id = read_config(bus, slot, func, 0x00);
+    if id as u16 == NO_VENDOR { return None; }
+
+    let class = read_config(bus, slot, func, 0x08);
+    Some(Function {
+        bus,
+        slot,
+        func,
+        vendor_id: id as u16,
+        device_id: (id >> 16) as u16,
+        class: (class >> 24) as u8,
+        subclass: (class >> 16) as u8,
+        prog_if: (class >> 8) as u8,
+    })
+}
+
+/// Returns the vendor ID of the function.
+fn vendor_id(bus: u8, slot: u8, func: u8) -> u16 { read_config(bus, slot, func, 0x00) as u16 }
+
+/// Returns the value to write to CONFIG_ADDRESS to select the given register.
+fn config_address(bus: u8, slot: u8, func: u8, offset: u8) -> u32 {
+    const ENABLE: u32 = 1 << 31;
+    ENABLE | (bus as u32) << 16 | (slot as u32) << 11 | (func as u32) << 8 | (offset as u32 & 0xFC)
+}
+
+/// Reads a 32-bit configuration register.
+pub(crate) fn read_config(bus: u8, slot: u8, func: u8, offset: u8) -> u32 {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut addr = Port::<u32>::new(CONFIG_ADDRESS);
+            let mut data = Port::<u32>::new(CONFIG_DATA);
+            unsafe {
+                addr.write(config_address(bus, slot, func, offset));
+                data.read()
+            }
+        }
+    )
+}
+
+/// Writes a 32-bit configuration register.
+pub(crate) fn write_config(bus: u8, slot: u8, func: u8, offset: u8, value: u32) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut addr = Port::<u32>::new(CONFIG_ADDRESS);
+            let mut data = Port::<u32>::new(CONFIG_DATA);
+            unsafe {
+                addr.write(config_address(bus, slot, func, offset));
+                data.write(value);
+            }
+        }
+    );
+}
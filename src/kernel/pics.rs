@@ -24,6 +24,11 @@ use pic8259::ChainedPics;
 use spin::Mutex;
 use x86_64::instructions;
 
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
+use crate::kernel::portio;
+use crate::kernel::portio::Port;
+
 ////////////////
 // Attributes
 ////////////////
@@ -32,6 +37,8 @@ use x86_64::instructions;
 pub const M_OFFSET: u8 = 32;
 /// Pins in Master PIC.
 pub const M_PIN_COUNT: u8 = 8;
+/// Command port of Master PIC.
+pub const M_COMMAND_PORT: u16 = 0x20;
 /// Data port of Master PIC.
 pub const M_DATA_PORT: u16 = 0x21;
 
@@ -39,6 +46,8 @@ pub const M_DATA_PORT: u16 = 0x21;
 pub const S_OFFSET: u8 = M_OFFSET + M_PIN_COUNT;
 /// Pins in Slave PIC.
 pub const S_PIN_COUNT: u8 = 8;
+/// Command port of Slave PIC.
+pub const S_COMMAND_PORT: u16 = 0xA0;
 /// Data port of Slave PIC.
 pub const S_DATA_PORT: u16 = 0xA1;
 
@@ -62,12 +71,33 @@ pub(crate) static PIC_8259: Mutex<ChainedPics> = Mutex::new(
     unsafe { ChainedPics::new(M_OFFSET, S_OFFSET) }
 );
 
+////////////
+// Device
+////////////
+
+/// Device descriptor of the PICs.
+pub(crate) static DEVICE: Device = Device {
+    name: "PICS",
+    class: Class::System,
+    stage: Stage::Core,
+    critical: true,
+    depends: &["IDT"],
+    tolerates_absent: false,
+    init: || init().and_then(|_| enable()),
+    suspend: None,
+    resume: None,
+};
+
 ///////////////
 // Utilities
 ///////////////
 
 /// Initializes the PICs.
-pub(crate) fn init() -> Result<(), ()> {
+pub(crate) fn init() -> Result<(), Error> {
+    // Reserve the command and data ports of both PICs.
+    portio::reserve("PICS", M_COMMAND_PORT, M_DATA_PORT - M_COMMAND_PORT + 1).ok();
+    portio::reserve("PICS", S_COMMAND_PORT, S_DATA_PORT - S_COMMAND_PORT + 1).ok();
+
     unsafe {
         PIC_8259.lock().initialize();
     }
@@ -76,8 +106,40 @@ pub(crate) fn init() -> Result<(), ()> {
 }
 
 /// Enables interrupts.
-pub(crate) fn enable() -> Result<(), ()> {
+pub(crate) fn enable() -> Result<(), Error> {
     instructions::interrupts::enable();
 
     Ok(())
 }
+
+/// Returns whether the given line was raised spuriously, acknowledging the master PIC if needed.
+///
+/// A PIC raises its lowest-priority line (IRQ 7 or IRQ 15) when a request is withdrawn before it is
+/// acknowledged. Such interrupts are told apart by their bit not being set in the In-Service Register,
+/// and must not be acknowledged on the PIC that raised them. The master, however, did see a genuine
+/// request on its cascade line when the slave raises a spurious IRQ 15.
+pub(crate) fn is_spurious(idx: u8) -> bool {
+    /// Operation Command Word 3 (OCW3) selecting the In-Service Register for the next read.
+    const OCW3_READ_ISR: u8 = 0x0B;
+    /// Lowest-priority line of each PIC.
+    const LAST_PIN: u8 = M_PIN_COUNT - 1;
+
+    let (port_num, line) = match idx {
+        LAST_PIN => (M_COMMAND_PORT, LAST_PIN),
+        _ if idx == TOTAL_PIN_COUNT - 1 => (S_COMMAND_PORT, LAST_PIN),
+        _ => return false,
+    };
+
+    let mut port = Port::<u8>::new(port_num);
+    let isr = unsafe {
+        port.write(OCW3_READ_ISR);
+        port.read()
+    };
+    if isr & (1 << line) != 0 { return false; }
+
+    if port_num == S_COMMAND_PORT {
+        unsafe { PIC_8259.lock().notify_end_of_interrupt(M_OFFSET); }
+    }
+
+    true
+}
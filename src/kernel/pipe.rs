@@ -0,0 +1,341 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::error::Error;
+
+// Pipe
+//
+// A pipe is a ring buffer shared between a writing and a reading end. A plain write never blocks:
+// once the buffer is full, or the reading end is gone, it fails. `write_all` instead waits for the
+// reader to make room. Reading is asynchronous; a reader waiting for data is woken up whenever
+// something is written or the writing end is dropped, which marks the end of the stream. Likewise, a
+// writer waiting for room is woken up whenever something is read or the reading end is dropped.
+//
+// An unbounded pipe never runs out of room. It suits a writer that runs to completion before anyone
+// reads, such as a stage of a shell pipeline, which would otherwise fail once the buffer fills up.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of bytes buffered in a pipe.
+pub const CAPACITY: usize = 64 * 1024;
+
+/// Creates a new pipe and returns its reading and writing ends.
+pub fn new() -> (Reader, Writer) { with_capacity(CAPACITY) }
+
+/// Creates a new pipe that buffers whatever is written to it, and returns its reading and writing ends.
+pub fn unbounded() -> (Reader, Writer) { with_capacity(usize::MAX) }
+
+/// Creates a new pipe buffering up to the given number of bytes.
+fn with_capacity(capacity: usize) -> (Reader, Writer) {
+    let shared = Arc::new(Mutex::new(Shared {
+        buffer: VecDeque::new(),
+        capacity,
+        has_writer: true,
+        has_reader: true,
+        waker: None,
+        writer_waker: None,
+    }));
+
+    (Reader { shared: shared.clone() }, Writer { shared })
+}
+
+//////////////
+/// Shared
+//////////////
+struct Shared {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    has_writer: bool,
+    has_reader: bool,
+    waker: Option<Waker>,
+    writer_waker: Option<Waker>,
+}
+
+impl Shared {
+    /// Wakes up the waiting reader, if any.
+    fn wake_reader(&mut self) {
+        if let Some(waker) = self.waker.take() { waker.wake(); }
+    }
+
+    /// Wakes up the waiting writer, if any.
+    fn wake_writer(&mut self) {
+        if let Some(waker) = self.writer_waker.take() { waker.wake(); }
+    }
+}
+
+//////////////
+/// Reader
+//////////////
+pub struct Reader {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Reader {
+    /// Moves up to `buf.len()` buffered bytes into `buf` and returns their count.
+    ///
+    /// Note: Returns 0 if nothing is buffered, which marks the end of the stream only once `is_closed`.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = self.shared.lock();
+                let count = buf.len().min(shared.buffer.len());
+                for (dst, src) in buf.iter_mut().zip(shared.buffer.drain(..count)) {
+                    *dst = src;
+                }
+                if count > 0 { shared.wake_writer(); }
+                count
+            }
+        )
+    }
+
+    /// Waits for data and moves up to `buf.len()` bytes into `buf`.
+    ///
+    /// Note: Resolves to 0 once the stream has ended.
+    pub fn read_some<'a>(&'a mut self, buf: &'a mut [u8]) -> ReadSome<'a> { ReadSome { reader: self, buf } }
+
+    /// Returns whether the writing end is gone and everything has been read.
+    pub fn is_closed(&self) -> bool {
+        instructions::interrupts::without_interrupts(
+            || {
+                let shared = self.shared.lock();
+                !shared.has_writer && shared.buffer.is_empty()
+            }
+        )
+    }
+
+    /// Returns everything buffered so far, lossily decoded as UTF-8.
+    pub fn read_available(&mut self) -> String {
+        let bytes: Vec<u8> = instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = self.shared.lock();
+                let bytes = shared.buffer.drain(..).collect();
+                shared.wake_writer();
+                bytes
+            }
+        );
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// Reads the next line, including its terminating newline if any.
+    ///
+    /// Note: Resolves to `None` once the stream has ended.
+    pub fn read_line(&mut self) -> ReadLine<'_> { ReadLine { reader: self } }
+
+    /// Reads until the writing end is dropped.
+    pub fn read_to_end(&mut self) -> ReadToEnd<'_> { ReadToEnd { reader: self, data: Vec::new() } }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = self.shared.lock();
+                shared.has_reader = false;
+                shared.wake_writer();
+            }
+        );
+    }
+}
+
+//////////////
+/// Writer
+//////////////
+pub struct Writer {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Writer {
+    /// Appends all of `data` to the pipe, failing if it does not fit or nobody is reading.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = self.shared.lock();
+                if !shared.has_reader { return Err(Error::BrokenPipe); }
+                if data.len() > shared.capacity - shared.buffer.len() { return Err(Error::OutOfResources); }
+                shared.buffer.extend(data);
+                shared.wake_reader();
+                Ok(())
+            }
+        )
+    }
+
+    /// Writes all of `data` to the pipe, waiting for the reader to make room whenever it is full.
+    ///
+    /// Note: Fails with `BrokenPipe` if the reading end is dropped before everything is written.
+    pub fn write_all<'a>(&'a mut self, data: &'a [u8]) -> WriteAll<'a> { WriteAll { writer: self, data } }
+}
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result { self.write(s.as_bytes()).map_err(|_| fmt::Error) }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = self.shared.lock();
+                shared.has_writer = false;
+                shared.wake_reader();
+            }
+        );
+    }
+}
+
+///////////////////
+/// Read To End
+///////////////////
+pub struct ReadToEnd<'a> {
+    reader: &'a mut Reader,
+    data: Vec<u8>,
+}
+
+impl Future for ReadToEnd<'_> {
+    type Output = Vec<u8>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = this.reader.shared.lock();
+                this.data.extend(shared.buffer.drain(..));
+                shared.wake_writer();
+                if shared.has_writer {
+                    shared.waker = Some(context.waker().clone());
+                    Poll::Pending
+                } else {
+                    Poll::Ready(core::mem::take(&mut this.data))
+                }
+            }
+        )
+    }
+}
+
+/////////////////
+/// Read Line
+/////////////////
+pub struct ReadLine<'a> {
+    reader: &'a mut Reader,
+}
+
+impl Future for ReadLine<'_> {
+    type Output = Option<String>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = this.reader.shared.lock();
+                let end = match shared.buffer.iter().position(|b| *b == b'\n') {
+                    Some(idx) => idx + 1,
+                    None if !shared.has_writer && !shared.buffer.is_empty() => shared.buffer.len(),
+                    None if !shared.has_writer => return Poll::Ready(None),
+                    None => {
+                        shared.waker = Some(context.waker().clone());
+                        return Poll::Pending;
+                    }
+                };
+                let line: Vec<u8> = shared.buffer.drain(..end).collect();
+                shared.wake_writer();
+                Poll::Ready(Some(String::from_utf8_lossy(&line).into_owned()))
+            }
+        )
+    }
+}
+
+/////////////////
+/// Read Some
+/////////////////
+pub struct ReadSome<'a> {
+    reader: &'a mut Reader,
+    buf: &'a mut [u8],
+}
+
+impl Future for ReadSome<'_> {
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = this.reader.shared.lock();
+                if shared.buffer.is_empty() && shared.has_writer && !this.buf.is_empty() {
+                    shared.waker = Some(context.waker().clone());
+                    return Poll::Pending;
+                }
+                let count = this.buf.len().min(shared.buffer.len());
+                for (dst, src) in this.buf.iter_mut().zip(shared.buffer.drain(..count)) {
+                    *dst = src;
+                }
+                if count > 0 { shared.wake_writer(); }
+                Poll::Ready(count)
+            }
+        )
+    }
+}
+
+/////////////////
+/// Write All
+/////////////////
+pub struct WriteAll<'a> {
+    writer: &'a mut Writer,
+    data: &'a [u8],
+}
+
+impl Future for WriteAll<'_> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut shared = this.writer.shared.lock();
+                if !shared.has_reader { return Poll::Ready(Err(Error::BrokenPipe)); }
+
+                let count = this.data.len().min(shared.capacity - shared.buffer.len());
+                shared.buffer.extend(&this.data[..count]);
+                this.data = &this.data[count..];
+                if count > 0 { shared.wake_reader(); }
+
+                if this.data.is_empty() {
+                    Poll::Ready(Ok(()))
+                } else {
+                    shared.writer_waker = Some(context.waker().clone());
+                    Poll::Pending
+                }
+            }
+        )
+    }
+}
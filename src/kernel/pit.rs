@@ -20,15 +20,23 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use alloc::vec::Vec;
 use core::arch;
+use core::future::Future;
+use core::pin::Pin;
 use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Context, Poll, Waker};
 
+use spin::Mutex;
 use x86_64::instructions;
-use x86_64::instructions::port::Port;
 
-use crate::kernel::cmos::CMOS;
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
 use crate::kernel::idt;
 use crate::kernel::idt::IRQ;
+use crate::kernel::{apic, portio, power, signal, time};
+use crate::kernel::portio::Port;
+use crate::kernel::stats;
 
 // Programmable Interval Timer (PIT | Intel 8253/8254)
 //
@@ -50,6 +58,9 @@ use crate::kernel::idt::IRQ;
 // In modern computers, where the cost of electronics is much less, and the CPU and video run at much
 // higher frequencies the PIT lives on as a reminder of "the good ole' days".
 //
+// Legacy-free platforms may leave the PIT out, in which case its ports read all ones and its count
+// never moves. The PIT is then absent, and the HPET takes over the tick on the same IRQ.
+//
 // OS Dev Wiki: https://wiki.osdev.org/Programmable_Interval_Timer
 
 //////////////////
@@ -65,6 +76,9 @@ const DIVIDER: usize = 1193;
 /// Time between successive ticks.
 const INTERVAL: f64 = (DIVIDER as f64) / FREQUENCY;
 
+/// Longest stretch of ticks that may be skipped while idle, so that the time keeping stays fresh.
+const MAX_TICKLESS_TICKS: usize = 1000;
+
 ////////////////
 // Attributes
 ////////////////
@@ -88,15 +102,39 @@ static IS_INITIALIZED: AtomicBool = AtomicBool::new(false);
 /// Ticks elapsed since PIT was initialized.
 static TICKS: AtomicUsize = AtomicUsize::new(0);
 
-/// The latest RTC clock update tick.
-static LAST_RTC_UPDATE: AtomicUsize = AtomicUsize::new(0);
+/// Flag to check whether the periodic tick is stopped.
+static IS_TICKLESS: AtomicBool = AtomicBool::new(false);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Tasks waiting for a delay to expire, along with the tick at which it expires.
+static TIMERS: Mutex<Vec<(usize, Waker)>> = Mutex::new(Vec::new());
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the PIT.
+pub(crate) static DEVICE: Device = Device {
+    name: "PIT",
+    class: Class::System,
+    stage: Stage::Core,
+    critical: true,
+    depends: &["PICS"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
 
 //////////////
 // Utilities
 //////////////
 
 /// Initializes the PIT and sets the relevant interrupt handlers.
-pub(crate) fn init() -> Result<(), ()> {
+pub(crate) fn init() -> Result<(), Error> {
     // The PIT has only 16 bits that are used as frequency divider, which can represent the values from
     // 0 to 65535. Since the frequency can't be divided by 0 in a sane way, many implementations use 0
     // to represent the value 65536.
@@ -105,18 +143,54 @@ pub(crate) fn init() -> Result<(), ()> {
     // Set frequency divider.
     set_pit_frequency_divider(divider as u16, OUTPUT_CHANNEL);
 
+    // The count has just been loaded, so a missing chip shows before anything depends on it.
+    if !is_counting(divider as u16) { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    // Reserve the PIT channels and command register.
+    const PIT_PORT_BASE: u16 = 0x40;
+    const PIT_PORT_COUNT: u16 = 4;
+    portio::reserve("PIT", PIT_PORT_BASE, PIT_PORT_COUNT).ok();
+
+    start_ticking();
+
+    Ok(())
+}
+
+/// Sets the interrupt handler for the timer and starts counting ticks.
+///
+/// Note: It is also called by whichever timer stands in for a missing PIT on IRQ 0.
+pub(crate) fn start_ticking() {
     // Set interrupt handler for timer.
     idt::set_irq_handler(IRQ::Timer, timer_irq_handler);
 
-    // Set interrupt handler for RTC.
-    idt::set_irq_handler(IRQ::RTC, rtc_irq_handler);
-    // Enable RTC update interrupts.
-    CMOS::new().enable_update_interrupt();
-
     // Update flag.
     IS_INITIALIZED.store(true, Ordering::Relaxed);
+}
 
-    Ok(())
+/// Returns whether channel 0 counts down from the given divider.
+///
+/// Note: The count is latched so that both of its bytes are read from the same instant.
+fn is_counting(divider: u16) -> bool {
+    const CMD_PORT: u16 = 0x43;
+    const DATA_PORT: u16 = 0x40;
+    const LATCH_CHANNEL_0: u8 = 0x00;
+
+    let read_count = || {
+        let mut cmd = Port::<u8>::new(CMD_PORT);
+        let mut data = Port::<u8>::new(DATA_PORT);
+        unsafe {
+            cmd.write(LATCH_CHANNEL_0);
+            u16::from_le_bytes([data.read(), data.read()])
+        }
+    };
+
+    // The count drops by about 60 over the wait, and a divider of 0 stands for 65536.
+    let first = read_count();
+    time::delay_us(50);
+    let second = read_count();
+    let in_range = |count: u16| divider == 0 || count <= divider;
+
+    first != second && in_range(first) && in_range(second)
 }
 
 /// Returns whether the PIT is initialized or not.
@@ -128,9 +202,6 @@ pub(crate) fn tick_interval() -> f64 { INTERVAL }
 /// Returns the ticks elapsed since PIT was initialized.
 pub(crate) fn ticks() -> usize { TICKS.load(Ordering::Relaxed) }
 
-/// Returns the latest RTC clock update tick.
-pub(crate) fn last_rtc_update() -> usize { LAST_RTC_UPDATE.load(Ordering::Relaxed) }
-
 /// Returns the Read Time-Stamp Counter (RDTSC).
 ///
 /// Reference: https://www.felixcloutier.com/x86/rdtsc
@@ -149,7 +220,10 @@ pub(crate) fn uptime() -> f64 { (ticks() as f64) * tick_interval() }
 /// Note: It restores the state of interrupts (whether enabled or disabled) after execution.
 pub(crate) fn halt() {
     let disabled = !instructions::interrupts::are_enabled();
-    instructions::interrupts::enable_and_hlt();
+    instructions::interrupts::disable();
+    stats::enter_idle();
+    power::idle();
+    stats::exit_idle();
     if disabled { instructions::interrupts::disable(); }
 }
 
@@ -161,6 +235,59 @@ pub(crate) fn sleep(seconds: f64) {
     }
 }
 
+/// Returns a future that completes once the specified duration has elapsed.
+///
+/// Note: Unlike `sleep`, it lets the executor run other tasks in the meantime.
+pub(crate) fn delay(seconds: f64) -> Delay {
+    let ticks = (seconds / INTERVAL) as usize;
+    Delay { deadline: self::ticks() + ticks.max(1) }
+}
+
+/// Returns the tick at which the earliest pending delay or alarm expires.
+pub(crate) fn next_deadline() -> Option<usize> {
+    let delay = instructions::interrupts::without_interrupts(
+        || TIMERS.lock().iter().map(|(deadline, _)| *deadline).min()
+    );
+    match (delay, signal::next_alarm()) {
+        (Some(delay), Some(alarm)) => Some(delay.min(alarm)),
+        (delay, alarm) => delay.or(alarm),
+    }
+}
+
+/// Stops the periodic tick and arms the local APIC timer for the next deadline instead, so that an
+/// idle CPU is not woken up on every tick. Returns whether the tick was stopped.
+///
+/// Note: It must be called with interrupts disabled, and be followed by `exit_tickless` on wakeup.
+pub(crate) fn enter_tickless() -> bool {
+    if !apic::is_enabled() || !apic::local::has_timer() { return false; }
+
+    let now = ticks();
+    let remaining = next_deadline().map_or(MAX_TICKLESS_TICKS, |deadline| deadline.saturating_sub(now));
+    // Nothing is gained by stopping the tick for the very next one.
+    if remaining <= 1 { return false; }
+
+    idt::mask_irq(IRQ::Timer);
+    apic::local::arm_timer(idt::APIC_TIMER_VECTOR, remaining.min(MAX_TICKLESS_TICKS));
+    IS_TICKLESS.store(true, Ordering::Relaxed);
+
+    true
+}
+
+/// Restarts the periodic tick and accounts for the ticks skipped while it was stopped.
+pub(crate) fn exit_tickless() {
+    if !IS_TICKLESS.swap(false, Ordering::Relaxed) { return; }
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let skipped = apic::local::stop_timer();
+            for _ in 0..skipped {
+                tick();
+            }
+            idt::unmask_irq(IRQ::Timer);
+        }
+    );
+}
+
 /// Sets the frequency divider for the PIT.
 pub(crate) fn set_pit_frequency_divider(divider: u16, channel: u8) {
     instructions::interrupts::without_interrupts(
@@ -189,15 +316,47 @@ pub(crate) fn set_pit_frequency_divider(divider: u16, channel: u8) {
     )
 }
 
+/////////////
+/// Delay
+/////////////
+pub(crate) struct Delay {
+    deadline: usize,
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if ticks() >= self.deadline { return Poll::Ready(()); }
+
+        let entry = (self.deadline, context.waker().clone());
+        instructions::interrupts::without_interrupts(|| TIMERS.lock().push(entry));
+
+        Poll::Pending
+    }
+}
+
 //////////////
 // Handlers
 //////////////
 
 /// Interrupt handler for timer.
-pub(crate) fn timer_irq_handler() { TICKS.fetch_add(1, Ordering::Relaxed); }
-
-/// Interrupt handler for RTC.
-fn rtc_irq_handler() {
-    LAST_RTC_UPDATE.store(ticks(), Ordering::Relaxed);
-    CMOS::new().notify_end_of_interrupt();
+pub(crate) fn timer_irq_handler() { tick(); }
+
+/// Advances the time by one tick and wakes up the expired delays.
+fn tick() {
+    let now = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    stats::tick();
+
+    // Registrations happen with interrupts disabled, so the lock is only ever contended on other CPUs.
+    if let Some(mut timers) = TIMERS.try_lock() {
+        timers.retain(
+            |(deadline, waker)| {
+                if *deadline > now { return true; }
+                waker.wake_by_ref();
+                false
+            }
+        );
+    }
+    signal::tick(now);
 }
@@ -0,0 +1,224 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt;
+use core::marker::PhantomData;
+#[cfg(debug_assertions)]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions;
+use x86_64::instructions::port::{PortRead, PortWrite};
+
+use crate::warning;
+use crate::kernel::error::Error;
+
+// Port I/O
+//
+// The x86 architecture has a separate 16-bit address space for I/O ports, accessed with the `in` and
+// `out` instructions. Nothing stops two drivers from programming the same ports, which typically shows
+// up as hardware behaving erratically rather than as an error.
+//
+// Every subsystem reserves the port ranges it drives during initialization; overlapping reservations
+// are logged along with both owners. In debug builds, all accesses made through `Port` can
+// additionally be traced to the serial output, which the `port_trace` setting turns on.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum number of port range reservations.
+const MAX_RESERVATIONS: usize = 32;
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of reserved port ranges.
+static RESERVATIONS: Mutex<[Option<Reservation>; MAX_RESERVATIONS]> = Mutex::new([None; MAX_RESERVATIONS]);
+
+////////////
+// States
+////////////
+
+/// Flag to check whether port accesses are traced or not.
+#[cfg(debug_assertions)]
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+///////////////////
+/// Reservation
+///////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reservation {
+    pub owner: &'static str,
+    pub base: u16,
+    pub count: u16,
+}
+
+impl Reservation {
+    /// Returns whether the given port lies within the reservation.
+    pub fn contains(&self, port: u16) -> bool { port >= self.base && (port - self.base) < self.count }
+
+    /// Returns whether the reservation overlaps with another.
+    fn overlaps(&self, other: &Reservation) -> bool {
+        let end = self.base as u32 + self.count as u32;
+        let other_end = other.base as u32 + other.count as u32;
+        (self.base as u32) < other_end && (other.base as u32) < end
+    }
+}
+
+//////////////////
+/// Port Value
+//////////////////
+pub trait PortValue: PortRead + PortWrite + Copy + fmt::LowerHex {}
+
+impl PortValue for u8 {}
+
+impl PortValue for u16 {}
+
+impl PortValue for u32 {}
+
+////////////
+/// Port
+////////////
+///
+/// A drop-in replacement for `x86_64::instructions::port::Port` that takes part in access tracing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Port<T> {
+    port: u16,
+    phantom: PhantomData<T>,
+}
+
+impl<T: PortValue> Port<T> {
+    /// Creates a new object.
+    pub const fn new(port: u16) -> Self {
+        Port {
+            port,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Reads from the port.
+    ///
+    /// # Safety
+    ///
+    /// Reading may have side effects on the device behind the port, such as acknowledging an interrupt
+    /// or consuming data, so the caller must own the port and know what the read does.
+    pub unsafe fn read(&mut self) -> T {
+        let value = T::read_from_port(self.port);
+        trace(self.port, "in", value);
+        value
+    }
+
+    /// Writes to the port.
+    ///
+    /// # Safety
+    ///
+    /// Writing may reconfigure the device behind the port in ways that break memory safety, e.g. by
+    /// starting a DMA transfer, so the caller must own the port and write a value the device expects.
+    pub unsafe fn write(&mut self, value: T) {
+        trace(self.port, "out", value);
+        T::write_to_port(self.port, value);
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Reserves `count` ports starting at `base` for the given owner.
+///
+/// Overlapping reservations are still recorded, but a warning naming both owners is logged.
+pub fn reserve(owner: &'static str, base: u16, count: u16) -> Result<(), Error> {
+    let new = Reservation { owner, base, count };
+
+    let (conflict, recorded) = instructions::interrupts::without_interrupts(
+        || {
+            let mut reservations = RESERVATIONS.lock();
+            let conflict = reservations.iter().flatten().find(|r| r.overlaps(&new) && r.owner != owner).copied();
+            let recorded = match reservations.iter_mut().find(|r| r.is_none()) {
+                Some(slot) => {
+                    *slot = Some(new);
+                    true
+                }
+                None => false,
+            };
+            (conflict, recorded)
+        }
+    );
+
+    if let Some(other) = conflict {
+        warning!(
+            "PortIO: {} claims {:#06x}..{:#06x}, already owned by {}",
+            owner, base, base as u32 + count as u32, other.owner
+        );
+    }
+    if !recorded {
+        warning!("PortIO: reservation table is full; {} is not recorded", owner);
+    }
+
+    match (conflict, recorded) {
+        (None, true) => Ok(()),
+        (Some(_), _) => Err(Error::Busy),
+        (None, false) => Err(Error::OutOfResources),
+    }
+}
+
+/// Returns the owner of the given port, if reserved.
+pub fn owner_of(port: u16) -> Option<&'static str> {
+    instructions::interrupts::without_interrupts(
+        || { RESERVATIONS.lock().iter().flatten().find(|r| r.contains(port)).map(|r| r.owner) }
+    )
+}
+
+/// Calls the given function for each port range reservation.
+pub fn for_each_reservation(mut f: impl FnMut(&Reservation)) {
+    let reservations = instructions::interrupts::without_interrupts(|| { *RESERVATIONS.lock() });
+    for reservation in reservations.iter().flatten() {
+        f(reservation);
+    }
+}
+
+/// Returns whether port accesses are traced or not.
+#[cfg(debug_assertions)]
+pub fn is_tracing_enabled() -> bool { TRACING.load(Ordering::Relaxed) }
+
+/// Enables or disables tracing of port accesses to the serial output.
+#[cfg(debug_assertions)]
+pub fn set_tracing(enabled: bool) { TRACING.store(enabled, Ordering::Relaxed); }
+
+/// Traces a port access.
+#[cfg(debug_assertions)]
+fn trace<T: PortValue>(port: u16, direction: &str, value: T) {
+    if !is_tracing_enabled() { return; }
+
+    // The reservation table is only peeked at, so that tracing never blocks an access.
+    let owner = RESERVATIONS.try_lock()
+                            .and_then(|r| r.iter().flatten().find(|r| r.contains(port)).map(|r| r.owner))
+                            .unwrap_or("?");
+    crate::serial_println!("[portio] {:<8} {:<3} {:#06x} {:#x}", owner, direction, port, value);
+}
+
+/// Traces a port access.
+#[cfg(not(debug_assertions))]
+#[inline(always)]
+fn trace<T: PortValue>(_port: u16, _direction: &str, _value: T) {}
@@ -21,15 +21,194 @@
 // SOFTWARE.
 
 use core::arch::asm;
+use core::ptr;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicU8, AtomicU64, Ordering};
 
-use x86_64::instructions::port::Port;
+use acpi::platform::address::{AddressSpace, GenericAddress};
+use conquer_once::spin::OnceCell;
+use raw_cpuid::CpuId;
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::instructions;
+use x86_64::structures::DescriptorTablePointer;
 
+use crate::{apprise, warning};
+use crate::kernel::{memory, pci};
 use crate::kernel::acpi::{dsdt, fadt};
+use crate::kernel::error::Error;
+use crate::kernel::time;
+use crate::kernel::portio::Port;
+
+// Power Management
+//
+// When there is no work left, the CPU waits for the next interrupt in one of two ways, selected by the
+// idle policy. `HLT` enters the C1 state, from which the CPU wakes up almost immediately. `MWAIT` can
+// request deeper C-states, which draw less power at the cost of a longer wake-up latency; it is only
+// used under the powersave policy, and only where CPUID reports MONITOR/MWAIT support.
+//
+// The base and maximum frequencies come from CPUID leaf 0x16 where the CPU implements it. The TSC
+// frequency comes from CPUID leaf 0x15, or is otherwise measured against the PIT.
+//
+// Note: MSR_PLATFORM_INFO is not consulted, since reading an MSR that the CPU (or the hypervisor)
+// does not implement raises a general protection fault.
+
+////////////
+// States
+////////////
+
+/// The active idle policy.
+static POLICY: AtomicU8 = AtomicU8::new(Policy::Performance as u8);
+
+/// `MWAIT` hint selecting the deepest supported C-state, if `MWAIT` is available.
+static MWAIT_HINT: OnceCell<Option<u32>> = OnceCell::uninit();
+
+/// Cache line watched by `MONITOR`; nothing writes to it, so only interrupts end the wait.
+static MONITOR_LINE: AtomicU64 = AtomicU64::new(0);
+
+//////////////
+/// Policy
+//////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Policy {
+    /// Waits in C1 using `HLT`, for the lowest wake-up latency.
+    Performance = 0x0,
+    /// Waits in the deepest available C-state using `MWAIT`, falling back to `HLT`.
+    Powersave = 0x1,
+}
+
+impl Policy {
+    /// Creates a new object from enum index.
+    pub fn from_index(idx: u8) -> Result<Self, Error> {
+        match idx {
+            0x0 => Ok(Self::Performance),
+            0x1 => Ok(Self::Powersave),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Performance => "performance",
+            Self::Powersave => "powersave",
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "performance" => Ok(Self::Performance),
+            "powersave" => Ok(Self::Powersave),
+            _ => Err(Error::InvalidArgument)
+        }
+    }
+}
+
+/////////////////
+/// Frequency
+/////////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Frequency {
+    /// Base frequency in MHz, as reported by CPUID.
+    pub base: Option<u32>,
+    /// Maximum (turbo) frequency in MHz, as reported by CPUID.
+    pub max: Option<u32>,
+    /// TSC frequency in MHz, as reported by CPUID or measured.
+    pub tsc: Option<u32>,
+    /// Whether the TSC frequency was measured rather than reported.
+    pub tsc_measured: bool,
+}
 
 /////////////////
 // Utilities
 /////////////////
 
+/// Returns the idle policy.
+pub fn policy() -> Policy { Policy::from_index(POLICY.load(Ordering::Relaxed)).unwrap_or(Policy::Performance) }
+
+/// Sets the idle policy.
+pub fn set_policy(policy: Policy) { POLICY.store(policy as u8, Ordering::Relaxed); }
+
+/// Returns whether the CPU supports waiting with `MWAIT`.
+pub fn has_mwait() -> bool { mwait_hint().is_some() }
+
+/// Enables interrupts and waits for the next one, as chosen by the idle policy.
+///
+/// Note: Interrupts must be disabled on entry, so that none can slip in between checking for work and
+/// starting to wait.
+pub(crate) fn idle() {
+    match (policy(), mwait_hint()) {
+        (Policy::Powersave, Some(hint)) => unsafe {
+            asm!(
+                "monitor",
+                in("rax") &MONITOR_LINE as *const AtomicU64,
+                in("ecx") 0,
+                in("edx") 0,
+                options(nostack),
+            );
+            // `STI` takes effect after the following instruction, so `MWAIT` starts before any
+            // pending interrupt is delivered, and that interrupt then ends the wait.
+            asm!(
+                "sti",
+                "mwait",
+                in("eax") hint,
+                in("ecx") 0,
+                options(nostack),
+            );
+        },
+        _ => instructions::interrupts::enable_and_hlt(),
+    }
+}
+
+/// Returns the frequencies of the CPU.
+pub fn frequency() -> Frequency {
+    let cpuid = CpuId::new();
+    let info = cpuid.get_processor_frequency_info();
+    let nonzero = |mhz: u16| (mhz != 0).then_some(mhz as u32);
+
+    let base = info.as_ref().and_then(|info| nonzero(info.processor_base_frequency()));
+    let max = info.as_ref().and_then(|info| nonzero(info.processor_max_frequency()));
+
+    let reported = cpuid.get_tsc_info().and_then(|info| info.tsc_frequency()).map(|hz| (hz / 1_000_000) as u32);
+    let (tsc, tsc_measured) = match reported {
+        Some(mhz) => (Some(mhz), false),
+        None => (time::tsc_frequency().map(|hz| (hz / 1_000_000) as u32), true),
+    };
+
+    Frequency { base, max, tsc, tsc_measured }
+}
+
+/// Returns the `MWAIT` hint for the deepest supported C-state, if `MWAIT` is available.
+fn mwait_hint() -> Option<u32> {
+    *MWAIT_HINT.get_or_init(
+        || {
+            let cpuid = CpuId::new();
+            if !cpuid.get_feature_info()?.has_monitor_mwait() { return None; }
+
+            // Bits 4 to 7 of the hint select the C-state minus one; C1 is always available.
+            let info = match cpuid.get_monitor_mwait_info() {
+                Some(info) => info,
+                None => return Some(0),
+            };
+            let states = [
+                info.supported_c1_states(),
+                info.supported_c2_states(),
+                info.supported_c3_states(),
+                info.supported_c4_states(),
+                info.supported_c5_states(),
+                info.supported_c6_states(),
+                info.supported_c7_states(),
+            ];
+            let deepest = states.iter().rposition(|substates| *substates > 0).unwrap_or(0);
+            Some((deepest as u32) << 4)
+        }
+    )
+}
+
 /// Shuts down the machine.
 pub(crate) fn shutdown() {
     let mut port_pm1a_ctrl_blk = Port::new(fadt::pm1a_ctrl_blk_ptr() as u16);
@@ -40,11 +219,94 @@ pub(crate) fn shutdown() {
 }
 
 /// Reboots the machine.
-pub fn reboot() {
+///
+/// The methods are tried in turn: the reset register described by the FADT, the reset line of the
+/// 8042 controller, and finally a triple fault, which no machine survives.
+pub fn reboot() -> ! {
+    instructions::interrupts::disable();
+
+    let info = fadt::info();
+    let reset = info.filter(|info| info.reset_supported).and_then(|info| Some((info.reset_register?, info.reset_value)));
+    match reset {
+        Some((register, value)) if register.address != 0 => {
+            apprise!("Power: resetting through the ACPI reset register");
+            write_reset_register(&register, value);
+            settle();
+            warning!("Power: the ACPI reset register did not reset the machine");
+        }
+        _ => apprise!("Power: no ACPI reset register"),
+    }
+
+    // Without the FADT, the 8042 is assumed to be there, as it is on any PC.
+    if info.map_or(true, |info| info.has_8042) {
+        apprise!("Power: pulsing the reset line of the 8042 controller");
+        pulse_8042_reset();
+        settle();
+        warning!("Power: the 8042 controller did not reset the machine");
+    }
+
+    apprise!("Power: forcing a triple fault");
+    triple_fault();
+}
+
+/// Writes the value to the ACPI reset register.
+fn write_reset_register(register: &GenericAddress, value: u8) {
+    match register.address_space {
+        AddressSpace::SystemIo => {
+            let mut port = Port::<u8>::new(register.address as u16);
+            unsafe { port.write(value) };
+        }
+        AddressSpace::SystemMemory => {
+            let addr = memory::phys_to_virt_addr(PhysAddr::new(register.address));
+            unsafe { ptr::write_volatile(addr.as_mut_ptr::<u8>(), value) };
+        }
+        AddressSpace::PciConfigSpace => {
+            // The address encodes the device, function and register offset of a device on bus 0.
+            let slot = (register.address >> 32) as u8;
+            let func = (register.address >> 16) as u8;
+            let offset = register.address as u8;
+            let shift = (offset & 0x3) * 8;
+            let dword = pci::read_config(0, slot, func, offset & !0x3);
+            let dword = (dword & !(0xFF << shift)) | ((value as u32) << shift);
+            pci::write_config(0, slot, func, offset & !0x3, dword);
+        }
+        _ => warning!("Power: the ACPI reset register lives in an unsupported address space"),
+    }
+}
+
+/// Pulses the reset line of the CPU through the 8042 controller.
+fn pulse_8042_reset() {
+    const STATUS_PORT: u16 = 0x64;
+    const INPUT_BUFFER_FULL: u8 = 1 << 1;
+    const PULSE_RESET: u8 = 0xFE;
+    const MAX_POLLS: usize = 100_000;
+
+    let mut port = Port::<u8>::new(STATUS_PORT);
+    for _ in 0..MAX_POLLS {
+        if unsafe { port.read() } & INPUT_BUFFER_FULL == 0 { break; }
+    }
+    unsafe { port.write(PULSE_RESET) };
+}
+
+/// Loads an empty IDT and raises an exception, which escalates to a triple fault.
+fn triple_fault() -> ! {
+    let idt = DescriptorTablePointer { limit: 0, base: VirtAddr::zero() };
     unsafe {
-        asm!(
-        "xor rax, rax",
-        "mov cr3, rax",
-        );
+        instructions::tables::lidt(&idt);
+        asm!("int3", options(noreturn));
+    }
+}
+
+/// Waits a moment for a reset to take effect.
+///
+/// Note: Interrupts are disabled, so the wait is measured in writes to the POST port, each of which
+/// takes about a microsecond.
+fn settle() {
+    const POST_PORT: u16 = 0x80;
+    const WRITES: usize = 50_000;
+
+    let mut port = Port::<u8>::new(POST_PORT);
+    for _ in 0..WRITES {
+        unsafe { port.write(0) };
     }
 }
@@ -0,0 +1,412 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::api::io::Stdio;
+use crate::kernel::capability;
+use crate::kernel::capability::Capabilities;
+use crate::kernel::error::Error;
+use crate::kernel::memory::space::AddressSpace;
+use crate::kernel::signal;
+use crate::kernel::signal::Signal;
+use crate::kernel::strace;
+use crate::kernel::task::{Task, TaskID};
+
+// Processes
+//
+// A process is a user command together with its arguments and exit code. Each one is run to
+// completion inside an executor task, so a spawned process starts as soon as the currently running
+// task yields to the executor. Commands are plain functions that never yield, so that task finishes
+// within a single poll: a background process is deferred rather than concurrent, and the other tasks,
+// the shell included, wait for it to exit before they run again.
+//
+// Each process is given its standard streams (see `api::io`). A process that fails has its error
+// written to its standard error stream.
+//
+// A process holds the capabilities (see `kernel::capability`) of the code that spawned it.
+//
+// A process spawned in the background runs in an address space of its own (see
+// `kernel::memory::space`), so that whatever it maps in the user range stays out of reach of the
+// others. A process run in the foreground shares the space of its caller.
+//
+// A process may be sent a signal (see `kernel::signal`), which is raised on its task. A process that
+// is terminated by a signal exits with the code 128 plus the number of the signal, and one that has
+// not been started yet is terminated right away.
+//
+// Once a process has exited, its entry is kept in the table until the exit code has been collected
+// with `wait`, `try_wait` or `reap`.
+
+////////////////
+// Attributes
+////////////////
+
+/// Keeps track of IDs.
+static NEXT_PID: AtomicU64 = AtomicU64::new(1);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of processes that have not been collected yet.
+static PROCESSES: Mutex<BTreeMap<Pid, Process>> = Mutex::new(BTreeMap::new());
+
+/// Entry point of a user command.
+pub type Command = fn(&[&str], &mut Stdio) -> Result<(), Error>;
+
+///////////
+/// Pid
+///////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pid(u64);
+
+impl Pid {
+    /// Creates a new object.
+    fn new() -> Self { Pid(NEXT_PID.fetch_add(1, Ordering::Relaxed)) }
+
+    /// Returns the object as a primitive.
+    pub fn as_u64(&self) -> u64 { self.0 }
+
+    /// Creates an object from a primitive.
+    pub fn from_u64(pid: u64) -> Self { Pid(pid) }
+}
+
+/////////////////
+/// Exit Code
+/////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitCode(u8);
+
+impl ExitCode {
+    pub const SUCCESS: ExitCode = ExitCode(0);
+    pub const FAILURE: ExitCode = ExitCode(1);
+
+    /// Creates an object from a primitive.
+    pub const fn from_u8(code: u8) -> Self { ExitCode(code) }
+
+    /// Returns the object as a primitive.
+    pub fn as_u8(&self) -> u8 { self.0 }
+
+    /// Returns the exit code of a process terminated by the given signal.
+    pub fn from_signal(signal: Signal) -> Self { ExitCode(128 + signal.as_u8()) }
+
+    /// Returns whether the process exited successfully.
+    pub fn is_success(&self) -> bool { *self == Self::SUCCESS }
+}
+
+//////////////
+/// Status
+//////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Spawned, but not started by the executor yet.
+    Pending,
+    /// Currently executing.
+    Running,
+    /// Finished with the given exit code.
+    Exited(ExitCode),
+}
+
+impl Status {
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Exited(_) => "exited",
+        }
+    }
+}
+
+///////////////
+/// Process
+///////////////
+struct Process {
+    name: String,
+    args: Vec<String>,
+    command: Command,
+    stdio: Option<Stdio>,
+    status: Status,
+    caps: Capabilities,
+    space: Option<Arc<AddressSpace>>,
+    task: Option<TaskID>,
+    waker: Option<Waker>,
+}
+
+impl Process {
+    /// Records the exit code, closes the streams and wakes up the waiting task.
+    fn exit(&mut self, code: ExitCode) {
+        self.status = Status::Exited(code);
+        self.stdio = None;
+        if let Some(waker) = self.waker.take() { waker.wake(); }
+    }
+}
+
+////////////
+/// Info
+////////////
+#[derive(Debug, Clone)]
+pub struct Info {
+    pub pid: Pid,
+    pub name: String,
+    pub status: Status,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Queues the given command for execution in the background and returns its ID.
+pub fn spawn(name: &str, command: Command, args: &[&str], stdio: Stdio) -> Pid {
+    let pid = Pid::new();
+    let process = Process {
+        name: name.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        command,
+        stdio: Some(stdio),
+        status: Status::Pending,
+        caps: capability::current(),
+        // Short of a frame for the tables, the process runs in the kernel space.
+        space: AddressSpace::new().ok().map(Arc::new),
+        task: None,
+        waker: None,
+    };
+
+    instructions::interrupts::without_interrupts(
+        || { PROCESSES.lock().insert(pid, process); }
+    );
+
+    pid
+}
+
+/// Runs the given command in the foreground and returns its exit code.
+pub fn run(name: &str, command: Command, args: &[&str], stdio: &mut Stdio) -> ExitCode {
+    run_as(name, command, args, stdio, capability::current())
+}
+
+/// Runs the given command in the foreground with the given capabilities and returns its exit code.
+///
+/// Note: The capabilities of the caller are restored afterwards, whatever the command did with its own.
+pub(crate) fn run_as(name: &str, command: Command, args: &[&str], stdio: &mut Stdio, caps: Capabilities) -> ExitCode {
+    let caller = capability::set_current(caps);
+    let code = execute(name, command, args, stdio);
+    capability::set_current(caller);
+    code
+}
+
+/// Returns the exit code of the process if it has exited, collecting it.
+pub fn try_wait(pid: Pid) -> Result<Option<ExitCode>, Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut processes = PROCESSES.lock();
+            match processes.get(&pid).map(|p| p.status) {
+                Some(Status::Exited(code)) => {
+                    processes.remove(&pid);
+                    Ok(Some(code))
+                }
+                Some(_) => Ok(None),
+                None => Err(Error::NotFound),
+            }
+        }
+    )
+}
+
+/// Waits for the process to exit and returns its exit code.
+pub fn wait(pid: Pid) -> Wait { Wait { pid } }
+
+/// Collects all the processes that have exited.
+pub fn reap() -> Vec<(Info, ExitCode)> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut processes = PROCESSES.lock();
+            let mut reaped = Vec::new();
+            processes.retain(|pid, process| {
+                match process.status {
+                    Status::Exited(code) => {
+                        reaped.push((Info { pid: *pid, name: process.name.clone(), status: process.status }, code));
+                        false
+                    }
+                    _ => true,
+                }
+            });
+            reaped
+        }
+    )
+}
+
+/// Calls the given function for each process that has not been collected.
+pub fn for_each(f: impl FnMut(&Info)) {
+    let infos: Vec<Info> = instructions::interrupts::without_interrupts(
+        || {
+            PROCESSES.lock().iter()
+                .map(|(pid, p)| Info { pid: *pid, name: p.name.clone(), status: p.status })
+                .collect()
+        }
+    );
+    infos.iter().for_each(f);
+}
+
+/// Sends the signal to the process.
+pub fn kill(pid: Pid, signal: Signal) -> Result<(), Error> {
+    let task = instructions::interrupts::without_interrupts(
+        || -> Result<Option<TaskID>, Error> {
+            let mut processes = PROCESSES.lock();
+            let process = processes.get_mut(&pid).ok_or(Error::NotFound)?;
+            match process.status {
+                Status::Pending => {
+                    process.exit(ExitCode::from_signal(signal));
+                    Ok(None)
+                }
+                Status::Running => Ok(process.task),
+                _ => Ok(None),
+            }
+        }
+    )?;
+    if let Some(task) = task { signal::raise(task, signal); }
+
+    Ok(())
+}
+
+/// Records the exit of the process running in the task terminated by the signal.
+pub(crate) fn terminate(task: TaskID, signal: Signal) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut processes = PROCESSES.lock();
+            if let Some(process) = processes.values_mut().find(|p| p.task == Some(task)) {
+                process.exit(ExitCode::from_signal(signal));
+            }
+        }
+    );
+}
+
+/// Returns whether any process is waiting to be started.
+pub(crate) fn has_pending() -> bool {
+    instructions::interrupts::without_interrupts(
+        || { PROCESSES.lock().values().any(|p| p.status == Status::Pending) }
+    )
+}
+
+/// Returns a task for each process waiting to be started.
+pub(crate) fn take_pending() -> Vec<Task> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut processes = PROCESSES.lock();
+            processes.iter_mut()
+                .filter(|(_, p)| p.status == Status::Pending)
+                .map(|(pid, p)| {
+                    let mut task = Task::with_name(&p.name, start(*pid)).with_capabilities(p.caps);
+                    if let Some(space) = p.space.take() { task = task.with_address_space(space); }
+                    p.status = Status::Running;
+                    p.task = Some(task.id());
+                    task
+                })
+                .collect()
+        }
+    )
+}
+
+/// Runs the process and records its exit code.
+///
+/// Note: It completes within its first poll, as the command never yields.
+async fn start(pid: Pid) {
+    let launch = instructions::interrupts::without_interrupts(
+        || {
+            PROCESSES.lock().get_mut(&pid).and_then(|p| Some((p.name.clone(), p.command, p.args.clone(), p.stdio.take()?)))
+        }
+    );
+    let (name, command, args, mut stdio) = match launch {
+        Some(launch) => launch,
+        None => return,
+    };
+
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let code = execute(&name, command, &args, &mut stdio);
+    drop(stdio);
+
+    instructions::interrupts::without_interrupts(
+        || {
+            if let Some(process) = PROCESSES.lock().get_mut(&pid) { process.exit(code); }
+        }
+    );
+}
+
+/// Executes the command and converts its result into an exit code.
+fn execute(name: &str, command: Command, args: &[&str], stdio: &mut Stdio) -> ExitCode {
+    // A command runs to completion without yielding, so its calls are the only ones made meanwhile.
+    let tracing = strace::is_tracing();
+    let caller = if tracing { strace::set_current(Some(Arc::from(name))) } else { None };
+    let res = command(args, stdio);
+    if tracing { strace::set_current(caller); }
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Failed) => ExitCode::FAILURE,
+        Err(e) => {
+            writeln!(stdio.stderr, "{}: {}", name, e).ok();
+            ExitCode::FAILURE
+        }
+    }
+}
+
+////////////
+/// Wait
+////////////
+pub struct Wait {
+    pid: Pid,
+}
+
+impl Future for Wait {
+    type Output = Result<ExitCode, Error>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        instructions::interrupts::without_interrupts(
+            || {
+                let mut processes = PROCESSES.lock();
+                let process = match processes.get_mut(&self.pid) {
+                    Some(process) => process,
+                    None => return Poll::Ready(Err(Error::NotFound)),
+                };
+                match process.status {
+                    Status::Exited(code) => {
+                        processes.remove(&self.pid);
+                        Poll::Ready(Ok(code))
+                    }
+                    _ => {
+                        process.waker = Some(context.waker().clone());
+                        Poll::Pending
+                    }
+                }
+            }
+        )
+    }
+}
@@ -0,0 +1,213 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::error::Error;
+use crate::kernel::vfs;
+use crate::kernel::vfs::{DirEntry, FileSystem, FileType, Metadata};
+
+// RAM Filesystem
+//
+// Files and directories are kept in memory, keyed by their canonical path, and are lost on reboot.
+// Since the keys are sorted, the entries underneath a directory directly follow it, which keeps
+// directory listings and emptiness checks to a single range scan.
+//
+// Each mount of type `ramfs` gets its own, initially empty, instance.
+
+////////////
+/// Node
+////////////
+enum Node {
+    File(Vec<u8>),
+    Directory,
+}
+
+/////////////
+/// RamFs
+/////////////
+pub struct RamFs {
+    nodes: Mutex<BTreeMap<String, Node>>,
+}
+
+impl RamFs {
+    /// Creates a new object.
+    pub fn new() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert("/".to_string(), Node::Directory);
+        RamFs { nodes: Mutex::new(nodes) }
+    }
+
+    /// Runs the given function with the table of nodes locked.
+    fn with_nodes<R>(&self, f: impl FnOnce(&mut BTreeMap<String, Node>) -> R) -> R {
+        instructions::interrupts::without_interrupts(|| f(&mut self.nodes.lock()))
+    }
+
+    /// Updates the file through the given function, creating it if its parent directory exists.
+    fn update(&self, path: &str, f: impl FnOnce(&mut Vec<u8>)) -> Result<(), Error> {
+        self.with_nodes(
+            |nodes| {
+                check_parent(nodes, path)?;
+                match nodes.entry(path.to_string()).or_insert_with(|| Node::File(Vec::new())) {
+                    Node::File(data) => f(data),
+                    Node::Directory => return Err(Error::IsADirectory),
+                }
+                Ok(())
+            }
+        )
+    }
+}
+
+impl Default for RamFs {
+    fn default() -> Self { Self::new() }
+}
+
+impl FileSystem for RamFs {
+    fn name(&self) -> &str { "ramfs" }
+
+    fn metadata(&self, path: &str) -> Result<Metadata, Error> {
+        self.with_nodes(
+            |nodes| {
+                let (file_type, size) = match nodes.get(path).ok_or(Error::NotFound)? {
+                    Node::File(data) => (FileType::File, data.len()),
+                    Node::Directory => (FileType::Directory, 0),
+                };
+                Ok(Metadata { file_type, size, read_only: false })
+            }
+        )
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        self.with_nodes(
+            |nodes| match nodes.get(path).ok_or(Error::NotFound)? {
+                Node::File(data) => Ok(data.clone()),
+                Node::Directory => Err(Error::IsADirectory),
+            }
+        )
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<(), Error> {
+        self.update(
+            path,
+            |contents| {
+                contents.clear();
+                contents.extend_from_slice(data);
+            }
+        )
+    }
+
+    fn append(&self, path: &str, data: &[u8]) -> Result<(), Error> {
+        self.update(path, |contents| contents.extend_from_slice(data))
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, Error> {
+        self.with_nodes(
+            |nodes| {
+                match nodes.get(path).ok_or(Error::NotFound)? {
+                    Node::Directory => {}
+                    Node::File(_) => return Err(Error::NotADirectory),
+                }
+
+                let entries = children(nodes, path)
+                    .map(|(name, node)| {
+                        let (file_type, size) = match node {
+                            Node::File(data) => (FileType::File, data.len()),
+                            Node::Directory => (FileType::Directory, 0),
+                        };
+                        DirEntry { name: name.to_string(), file_type, size }
+                    })
+                    .collect();
+                Ok(entries)
+            }
+        )
+    }
+
+    fn create_dir(&self, path: &str) -> Result<(), Error> {
+        self.with_nodes(
+            |nodes| {
+                check_parent(nodes, path)?;
+                if nodes.contains_key(path) { return Err(Error::AlreadyExists); }
+                nodes.insert(path.to_string(), Node::Directory);
+                Ok(())
+            }
+        )
+    }
+
+    fn remove(&self, path: &str) -> Result<(), Error> {
+        if path == "/" { return Err(Error::Busy); }
+
+        self.with_nodes(
+            |nodes| {
+                if let Node::Directory = nodes.get(path).ok_or(Error::NotFound)? {
+                    if children(nodes, path).next().is_some() { return Err(Error::NotEmpty); }
+                }
+                nodes.remove(path);
+                Ok(())
+            }
+        )
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Creates an empty RAM filesystem.
+///
+/// Note: Only the sources `ramfs` and `none` are accepted, so that probing a block device with every
+/// driver does not end up mounting an empty RAM filesystem in its place.
+pub fn probe(source: &str) -> Result<Arc<dyn FileSystem>, Error> {
+    match source {
+        "ramfs" | "none" => Ok(Arc::new(RamFs::new())),
+        _ => Err(Error::Unsupported),
+    }
+}
+
+/// Checks that the parent of the path is an existing directory.
+fn check_parent(nodes: &BTreeMap<String, Node>, path: &str) -> Result<(), Error> {
+    if path == "/" { return Err(Error::AlreadyExists); }
+
+    let (parent, _) = vfs::split(path);
+    match nodes.get(parent).ok_or(Error::NotFound)? {
+        Node::Directory => Ok(()),
+        Node::File(_) => Err(Error::NotADirectory),
+    }
+}
+
+/// Returns the direct children of the directory along with their names.
+fn children<'a>(nodes: &'a BTreeMap<String, Node>, dir: &str) -> impl Iterator<Item=(&'a str, &'a Node)> + 'a {
+    let prefix = if dir == "/" { "/".to_string() } else { format!("{}/", dir) };
+    let skip = prefix.len();
+    nodes.range(prefix.clone()..)
+        .take_while(move |(path, _)| path.starts_with(&prefix))
+        .filter_map(move |(path, node)| {
+            let name = &path[skip..];
+            (!name.is_empty() && !name.contains('/')).then_some((name, node))
+        })
+}
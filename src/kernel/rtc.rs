@@ -0,0 +1,238 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::acpi::fadt;
+use crate::kernel::cmos::{CMOS, Interrupt};
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::{Error, FaultKind};
+use crate::kernel::idt;
+use crate::kernel::idt::IRQ;
+use crate::kernel::{cmos, pit};
+
+// Real-Time Clock (RTC) Periodic Interrupt
+//
+// Besides keeping the time of day, the RTC can raise IRQ 8 periodically at any power of two between
+// 2 Hz and 8 kHz, derived from its 32.768 kHz crystal. The clock is independent of the PIT, so work
+// driven by it, such as sampling what the CPU is doing or blinking parts of the screen, neither
+// depends on nor perturbs the scheduler tick.
+//
+// Callbacks ask for a frequency of their own. The RTC runs at the highest one requested and each
+// callback is invoked on every n-th interrupt; the interrupt is disabled while nothing is registered.
+// Callbacks run in interrupt context and must be short.
+//
+// Legacy-free platforms may lack the CMOS altogether, which the FADT announces and which shows as
+// status registers that read all ones. The device is then absent, and the time of day is unknown.
+//
+// OS Dev Wiki: https://wiki.osdev.org/RTC#Changing_Interrupt_Rate
+
+////////////////
+// Attributes
+////////////////
+
+/// Frequency of the RTC crystal, which the periodic rate is divided from.
+const BASE_FREQUENCY: u32 = 32768;
+
+/// Lowest supported frequency.
+pub const MIN_FREQUENCY: u32 = 2;
+/// Highest supported frequency.
+pub const MAX_FREQUENCY: u32 = 8192;
+
+/// Maximum number of callbacks.
+const MAX_CALLBACKS: usize = 8;
+
+////////////
+// States
+////////////
+
+/// Flag to check whether the RTC is present.
+static IS_PRESENT: AtomicBool = AtomicBool::new(false);
+
+/// Frequency the periodic interrupt is currently raised at, or zero if it is disabled.
+static FREQUENCY: AtomicU32 = AtomicU32::new(0);
+
+/// The latest clock update tick.
+static LAST_UPDATE: AtomicUsize = AtomicUsize::new(0);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Registered callbacks.
+static CALLBACKS: Mutex<[Option<Callback>; MAX_CALLBACKS]> = Mutex::new([None; MAX_CALLBACKS]);
+
+////////////////
+/// Callback
+////////////////
+#[derive(Debug, Clone, Copy)]
+struct Callback {
+    func: fn(),
+    frequency: u32,
+    /// Number of interrupts between two invocations at the current rate.
+    period: u32,
+    /// Interrupts left until the next invocation.
+    countdown: u32,
+}
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the RTC.
+///
+/// Note: It is brought up after the ACPI, so that the FADT can rule the chip out before it is probed.
+pub(crate) static DEVICE: Device = Device {
+    name: "RTC",
+    class: Class::System,
+    stage: Stage::Platform,
+    critical: false,
+    depends: &["PICS"],
+    tolerates_absent: false,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+///////////////
+// Utilities
+///////////////
+
+/// Probes the RTC and enables its update interrupts.
+pub(crate) fn init() -> Result<(), Error> {
+    if fadt::info().map_or(false, |info| !info.has_cmos_rtc) || !CMOS::new().is_present() {
+        return Err(Error::Hardware(FaultKind::NotPresent));
+    }
+
+    // Reserve the CMOS ports.
+    cmos::reserve_ports();
+
+    // Set interrupt handler.
+    idt::set_irq_handler(IRQ::RTC, irq_handler);
+    // Enable update interrupts.
+    CMOS::new().enable_update_interrupt();
+
+    IS_PRESENT.store(true, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Returns whether the RTC is present.
+pub fn is_present() -> bool { IS_PRESENT.load(Ordering::Relaxed) }
+
+/// Returns the latest clock update tick.
+pub(crate) fn last_update() -> usize { LAST_UPDATE.load(Ordering::Relaxed) }
+
+/// Registers a callback to run at the given frequency and returns its identifier.
+///
+/// Note: The frequency must be a power of two between 2 Hz and 8 kHz.
+pub fn register(frequency: u32, func: fn()) -> Result<usize, Error> {
+    if !frequency.is_power_of_two() || !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        return Err(Error::InvalidArgument);
+    }
+    if !is_present() { return Err(Error::Hardware(FaultKind::NotPresent)); }
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut callbacks = CALLBACKS.lock();
+            let id = callbacks.iter().position(|slot| slot.is_none()).ok_or(Error::OutOfMemory)?;
+            callbacks[id] = Some(Callback { func, frequency, period: 1, countdown: 1 });
+            reprogram(&mut callbacks);
+            Ok(id)
+        }
+    )
+}
+
+/// Unregisters the callback with the given identifier.
+pub fn unregister(id: usize) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut callbacks = CALLBACKS.lock();
+            if let Some(slot) = callbacks.get_mut(id) {
+                *slot = None;
+                reprogram(&mut callbacks);
+            }
+        }
+    );
+}
+
+/// Returns the frequency the periodic interrupt is raised at, or zero if it is disabled.
+pub fn frequency() -> u32 { FREQUENCY.load(Ordering::Relaxed) }
+
+/// Runs the callbacks that are due.
+fn tick() {
+    let mut due = [None; MAX_CALLBACKS];
+
+    // Registrations happen with interrupts disabled, so the lock is only ever contended on other CPUs.
+    if let Some(mut callbacks) = CALLBACKS.try_lock() {
+        for (callback, slot) in callbacks.iter_mut().flatten().zip(due.iter_mut()) {
+            callback.countdown -= 1;
+            if callback.countdown == 0 {
+                callback.countdown = callback.period;
+                *slot = Some(callback.func);
+            }
+        }
+    }
+
+    // The lock is released first, so that callbacks may register or unregister.
+    due.iter().flatten().for_each(|func| func());
+}
+
+/// Sets the rate to the highest frequency requested and recomputes the period of each callback.
+fn reprogram(callbacks: &mut [Option<Callback>; MAX_CALLBACKS]) {
+    let frequency = callbacks.iter().flatten().map(|callback| callback.frequency).max().unwrap_or(0);
+    for callback in callbacks.iter_mut().flatten() {
+        callback.period = frequency / callback.frequency;
+        callback.countdown = callback.period;
+    }
+
+    let mut cmos = CMOS::new();
+    if frequency == 0 {
+        cmos.disable_periodic_interrupt();
+    } else if frequency != self::frequency() {
+        // The rate selects a frequency of 32768 >> (rate - 1).
+        let rate = 1 + (BASE_FREQUENCY / frequency).trailing_zeros() as u8;
+        cmos.set_periodic_interrupt_rate(rate);
+        cmos.enable_periodic_interrupt();
+    }
+    FREQUENCY.store(frequency, Ordering::Relaxed);
+}
+
+//////////////
+// Handlers
+//////////////
+
+/// Interrupt handler for RTC.
+///
+/// Note: The update and periodic interrupts share the line, so the pending flags tell them apart.
+fn irq_handler() {
+    let flags = CMOS::new().notify_end_of_interrupt();
+    if Interrupt::Update.is_set(flags) {
+        LAST_UPDATE.store(pit::ticks(), Ordering::Relaxed);
+    }
+    if Interrupt::Periodic.is_set(flags) {
+        tick();
+    }
+}
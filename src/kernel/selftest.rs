@@ -0,0 +1,178 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::{instructions, VirtAddr};
+
+use crate::{apprise, failure, success, warning};
+use crate::drivers::keyboard;
+use crate::kernel::{dev, memory, pit, time};
+use crate::kernel::dev::Status;
+use crate::kernel::error::Error;
+use crate::kernel::memory::frame;
+
+// Power-On Self-Tests (POST)
+//
+// The test harness only runs under QEMU, which says little about how the kernel fares on a given
+// machine. The self-tests are quick checks of invariants the rest of the kernel relies upon, run at
+// the end of the boot when the `selftest` setting is on. Each check logs its outcome, and a summary
+// follows, so that a regression on real hardware shows up on the screen right away.
+
+/////////////
+// Globals
+/////////////
+
+/// Checks an invariant, describing the violation on failure.
+pub type Check = fn() -> Result<(), &'static str>;
+
+/// Available self-tests.
+pub const CHECKS: [(&str, Check); 5] = [
+    ("frames", check_frames),
+    ("heap", check_heap),
+    ("paging", check_paging),
+    ("timer", check_timer),
+    ("keyboard", check_keyboard),
+];
+
+////////////////////
+// Configurations
+////////////////////
+
+/// Whether the self-tests run at boot.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether the self-tests run at boot.
+pub fn is_enabled() -> bool { ENABLED.load(Ordering::Relaxed) }
+
+/// Sets whether the self-tests run at boot.
+pub fn set_enabled(enabled: bool) { ENABLED.store(enabled, Ordering::Relaxed); }
+
+/// Runs the self-tests if they are enabled; failed checks are only logged.
+pub(crate) fn init() -> Result<(), Error> {
+    if is_enabled() { run(); }
+    Ok(())
+}
+
+/// Runs all self-tests, logging each outcome, and returns the number of failures.
+pub fn run() -> usize {
+    let mut failures = 0;
+    for (name, check) in CHECKS {
+        match check() {
+            Ok(()) => success!("Self-test: {}", name),
+            Err(msg) => {
+                failure!("Self-test: {}: {}", name, msg);
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        warning!("Self-test: {} of {} check(s) failed", failures, CHECKS.len());
+    } else {
+        apprise!("Self-test: all {} checks passed", CHECKS.len());
+    }
+
+    failures
+}
+
+/// Checks that a physical frame can be allocated, written through the physical memory window, and freed.
+fn check_frames() -> Result<(), &'static str> {
+    let free = frame::free_frames();
+    let frame = frame::allocate().ok_or("no frame could be allocated")?;
+    if frame::free_frames() != free - 1 { return Err("allocation was not accounted for"); }
+
+    let addr = memory::phys_to_virt_addr(frame.start_address());
+    unsafe {
+        core::ptr::write_volatile(addr.as_mut_ptr::<u64>(), 0x5A5A_5A5A_5A5A_5A5A);
+        if core::ptr::read_volatile(addr.as_ptr::<u64>()) != 0x5A5A_5A5A_5A5A_5A5A { return Err("frame was corrupted"); }
+        frame::deallocate(frame);
+    }
+    if frame::free_frames() != free { return Err("frame was not released"); }
+
+    Ok(())
+}
+
+/// Checks that heap memory can be allocated, written, read back, and freed.
+fn check_heap() -> Result<(), &'static str> {
+    const LEN: usize = 4096;
+
+    let boxed = Box::new(0x5A5A_5A5A_u32);
+    if *boxed != 0x5A5A_5A5A { return Err("boxed value was corrupted"); }
+    drop(boxed);
+
+    let mut data: Vec<u8> = Vec::with_capacity(LEN);
+    data.extend((0..LEN).map(|idx| idx as u8));
+    if data.iter().enumerate().any(|(idx, &byte)| byte != idx as u8) { return Err("buffer was corrupted"); }
+
+    // Grow the buffer to move it, and make sure its contents came along.
+    data.extend_from_slice(&[0xFF; LEN]);
+    if data[..LEN].iter().enumerate().any(|(idx, &byte)| byte != idx as u8) { return Err("buffer was corrupted on reallocation"); }
+
+    Ok(())
+}
+
+/// Checks that a kernel address translates to a physical one that maps back to it.
+fn check_paging() -> Result<(), &'static str> {
+    static PROBE: u64 = 0x0123_4567_89AB_CDEF;
+
+    let virt_addr = VirtAddr::new(&PROBE as *const u64 as u64);
+    let phys_addr = memory::virt_to_phys_addr(virt_addr).ok_or("kernel data is not mapped")?;
+
+    // Read the value back through the mapping of all physical memory.
+    let alias = memory::phys_to_virt_addr(phys_addr);
+    let value = unsafe { core::ptr::read_volatile(alias.as_ptr::<u64>()) };
+    if value != PROBE { return Err("physical memory mapping disagrees with the page tables"); }
+
+    Ok(())
+}
+
+/// Checks that the timer is ticking.
+fn check_timer() -> Result<(), &'static str> {
+    const TICKS: usize = 3;
+
+    if !pit::is_initialized() { return Err("timer is not initialized"); }
+    if !instructions::interrupts::are_enabled() { return Err("interrupts are disabled"); }
+
+    let start = pit::ticks();
+    let timeout_us = (pit::tick_interval() * 1e6) as u64 * (TICKS as u64 + 1);
+    time::delay_us(timeout_us);
+    if pit::ticks().wrapping_sub(start) < TICKS { return Err("timer interrupts are not arriving"); }
+
+    Ok(())
+}
+
+/// Checks that the keyboard controller responds and its driver is up.
+fn check_keyboard() -> Result<(), &'static str> {
+    // A legacy-free machine has no controller to check.
+    if dev::status("Keyboard") == Some(Status::Absent) { return Ok(()); }
+    if !keyboard::is_controller_present() { return Err("PS/2 controller does not respond"); }
+    if dev::status("Keyboard") != Some(Status::Active) { return Err("driver is not active"); }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use raw_cpuid::CpuId;
+use x86_64::registers::model_specific::Msr;
+
+use crate::kernel::error::{Error, FaultKind};
+
+// Hardware Sensors
+//
+// Intel CPUs with a digital thermal sensor (DTS) report how far the current core is below its maximum
+// junction temperature (TjMax), rather than an absolute temperature. The reading lives in
+// IA32_THERM_STATUS, and its package-wide counterpart in IA32_PACKAGE_THERM_STATUS where CPUID reports
+// package thermal management (PTM). Both MSRs are architectural, so they are safe to read whenever
+// CPUID advertises the matching feature.
+//
+// TjMax is read from MSR_TEMPERATURE_TARGET on the Intel models known to implement it (Nehalem and
+// later); other models are assumed to have a TjMax of 100 degrees Celsius.
+//
+// Elsewhere (e.g. AMD CPUs, or most virtual machines) the readings are unsupported.
+//
+// Reference: Intel SDM, Volume 3, Section 15.8 (Platform Specific Power Management Support)
+
+////////////////
+// Attributes
+////////////////
+
+/// IA32_THERM_STATUS.
+const IA32_THERM_STATUS: u32 = 0x19C;
+/// MSR_TEMPERATURE_TARGET.
+const MSR_TEMPERATURE_TARGET: u32 = 0x1A2;
+/// IA32_PACKAGE_THERM_STATUS.
+const IA32_PACKAGE_THERM_STATUS: u32 = 0x1B1;
+
+/// TjMax assumed where it can not be read.
+const DEFAULT_TJ_MAX: i32 = 100;
+
+///////////////
+/// Reading
+///////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Reading {
+    /// Temperature in degrees Celsius.
+    pub celsius: i32,
+    /// Maximum junction temperature in degrees Celsius.
+    pub tj_max: i32,
+    /// Resolution of the sensor in degrees Celsius.
+    pub resolution: i32,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the temperature of the current core.
+pub fn cpu_temp() -> Result<Reading, Error> {
+    let info = CpuId::new().get_thermal_power_info().ok_or(Error::Unsupported)?;
+    if !is_intel() || !info.has_dts() { return Err(Error::Unsupported); }
+
+    read(IA32_THERM_STATUS)
+}
+
+/// Returns the temperature of the package.
+pub fn package_temp() -> Result<Reading, Error> {
+    let info = CpuId::new().get_thermal_power_info().ok_or(Error::Unsupported)?;
+    if !is_intel() || !info.has_ptm() { return Err(Error::Unsupported); }
+
+    read(IA32_PACKAGE_THERM_STATUS)
+}
+
+/// Converts the thermal status MSR into a reading.
+fn read(status_msr: u32) -> Result<Reading, Error> {
+    const READING_VALID: u64 = 1 << 31;
+
+    let status = unsafe { Msr::new(status_msr).read() };
+    if status & READING_VALID == 0 { return Err(Error::Hardware(FaultKind::InvalidResponse)); }
+
+    // Bits 16 to 22 hold the distance to TjMax, and bits 27 to 30 the resolution.
+    let offset = ((status >> 16) & 0x7F) as i32;
+    let resolution = ((status >> 27) & 0xF) as i32;
+    let tj_max = tj_max();
+
+    Ok(Reading { celsius: tj_max - offset, tj_max, resolution })
+}
+
+/// Returns the maximum junction temperature.
+fn tj_max() -> i32 {
+    // Family 6, model 0x1A (Nehalem) introduced MSR_TEMPERATURE_TARGET.
+    const NEHALEM: u8 = 0x1A;
+
+    let has_target = CpuId::new().get_feature_info()
+        .map_or(false, |info| info.family_id() == 6 && info.model_id() >= NEHALEM);
+    if !has_target { return DEFAULT_TJ_MAX; }
+
+    let target = unsafe { Msr::new(MSR_TEMPERATURE_TARGET).read() };
+    match ((target >> 16) & 0xFF) as i32 {
+        0 => DEFAULT_TJ_MAX,
+        tj_max => tj_max,
+    }
+}
+
+/// Returns whether the CPU is made by Intel.
+fn is_intel() -> bool {
+    CpuId::new().get_vendor_info().map_or(false, |vendor| vendor.as_str() == "GenuineIntel")
+}
@@ -0,0 +1,305 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::error::Error;
+use crate::kernel::pit;
+use crate::kernel::task;
+use crate::kernel::task::TaskID;
+
+// Signals
+//
+// A signal notifies a task of an event that happened outside of it: the user pressing Ctrl+C, an
+// alarm going off, or another task asking it to stop. Raising a signal only marks it as pending for
+// the task; the executor delivers the pending signals of a task right before polling it, by calling
+// the handler registered for each signal or by carrying out its default action, which is to terminate
+// the task. A signal may also be ignored. `KILL` can neither be handled nor ignored.
+//
+// Commands run to completion without yielding to the executor, so a command that takes a while should
+// check for pending signals on its own with `take`, and return early when it finds one.
+//
+// Ctrl+C raises `INT` on the foreground task, i.e. the one reading from the console, and an alarm
+// raises `ALRM` on the task that set it once the timer reaches its deadline.
+
+//////////////
+/// Signal
+//////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Signal {
+    /// Interrupt from the keyboard.
+    Int = 2,
+    /// Unconditional termination.
+    Kill = 9,
+    /// User-defined signal 1.
+    Usr1 = 10,
+    /// User-defined signal 2.
+    Usr2 = 12,
+    /// Expiry of an alarm.
+    Alrm = 14,
+}
+
+impl Signal {
+    /// All the signals.
+    pub const ALL: [Signal; 5] = [Self::Int, Self::Kill, Self::Usr1, Self::Usr2, Self::Alrm];
+
+    /// Returns the object as a primitive.
+    pub fn as_u8(&self) -> u8 { *self as u8 }
+
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Int => "INT",
+            Self::Kill => "KILL",
+            Self::Usr1 => "USR1",
+            Self::Usr2 => "USR2",
+            Self::Alrm => "ALRM",
+        }
+    }
+
+    /// Returns whether the signal may be handled or ignored.
+    pub fn is_catchable(&self) -> bool { *self != Self::Kill }
+
+    /// Returns the position of the signal in the masks.
+    fn bit(&self) -> u8 { 1 << Self::ALL.iter().position(|s| s == self).unwrap() }
+}
+
+impl FromStr for Signal {
+    type Err = Error;
+
+    /// Parses a name, with or without the `SIG` prefix, or a number.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix("SIG").unwrap_or(s);
+        Self::ALL.iter()
+            .find(|signal| signal.as_str().eq_ignore_ascii_case(name) || signal.as_u8().to_string() == name)
+            .copied()
+            .ok_or(Error::InvalidArgument)
+    }
+}
+
+//////////////
+/// Action
+//////////////
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    /// Carries out the default action of the signal.
+    Default,
+    /// Discards the signal.
+    Ignore,
+    /// Calls the given function.
+    Handle(fn(Signal)),
+}
+
+////////////
+// States
+////////////
+
+/// Flag to check whether any signal is pending or not.
+static HAS_PENDING: AtomicBool = AtomicBool::new(false);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Mask of pending signals of each task.
+static PENDING: Mutex<BTreeMap<TaskID, u8>> = Mutex::new(BTreeMap::new());
+
+/// Actions registered by each task, in the order of `Signal::ALL`.
+static ACTIONS: Mutex<BTreeMap<TaskID, [Action; Signal::ALL.len()]>> = Mutex::new(BTreeMap::new());
+
+/// Alarms of each task, along with the tick at which they go off.
+static ALARMS: Mutex<BTreeMap<TaskID, usize>> = Mutex::new(BTreeMap::new());
+
+/// Task reading from the console, which receives `INT` on Ctrl+C.
+static FOREGROUND: Mutex<Option<TaskID>> = Mutex::new(None);
+
+///////////////
+// Utilities
+///////////////
+
+/// Marks the signal as pending for the task.
+pub(crate) fn raise(task: TaskID, signal: Signal) {
+    instructions::interrupts::without_interrupts(
+        || {
+            *PENDING.lock().entry(task).or_insert(0) |= signal.bit();
+            HAS_PENDING.store(true, Ordering::Relaxed);
+        }
+    );
+}
+
+/// Raises `INT` on the foreground task.
+pub(crate) fn interrupt() {
+    let foreground = instructions::interrupts::without_interrupts(|| *FOREGROUND.lock());
+    if let Some(task) = foreground { raise(task, Signal::Int); }
+}
+
+/// Makes the running task the foreground task.
+pub(crate) fn claim_foreground() -> Result<(), Error> {
+    let task = task::current().ok_or(Error::NotFound)?;
+    instructions::interrupts::without_interrupts(|| *FOREGROUND.lock() = Some(task));
+
+    Ok(())
+}
+
+/// Sets the action of the running task for the signal and returns the previous one.
+pub fn set_action(signal: Signal, action: Action) -> Result<Action, Error> {
+    if !signal.is_catchable() { return Err(Error::InvalidArgument); }
+    let task = task::current().ok_or(Error::NotFound)?;
+    let idx = Signal::ALL.iter().position(|s| *s == signal).unwrap();
+
+    Ok(instructions::interrupts::without_interrupts(
+        || {
+            let mut actions = ACTIONS.lock();
+            let actions = actions.entry(task).or_insert([Action::Default; Signal::ALL.len()]);
+            core::mem::replace(&mut actions[idx], action)
+        }
+    ))
+}
+
+/// Clears the signal if it is pending for the running task, and returns whether it was.
+pub fn take(signal: Signal) -> bool {
+    let task = match task::current() {
+        Some(task) => task,
+        None => return false,
+    };
+
+    instructions::interrupts::without_interrupts(
+        || {
+            match PENDING.lock().get_mut(&task) {
+                Some(mask) if *mask & signal.bit() != 0 => {
+                    *mask &= !signal.bit();
+                    true
+                }
+                _ => false,
+            }
+        }
+    )
+}
+
+/// Raises `ALRM` on the running task after the given number of seconds, replacing its previous alarm,
+/// and returns the seconds that were left on the previous one. Zero seconds cancel the alarm.
+pub fn alarm(seconds: f64) -> Result<Option<f64>, Error> {
+    if seconds.is_nan() || seconds < 0.0 { return Err(Error::InvalidArgument); }
+    let task = task::current().ok_or(Error::NotFound)?;
+    let now = pit::ticks();
+
+    let previous = instructions::interrupts::without_interrupts(
+        || {
+            let mut alarms = ALARMS.lock();
+            if seconds == 0.0 {
+                alarms.remove(&task)
+            } else {
+                let ticks = ((seconds / pit::tick_interval()) as usize).max(1);
+                alarms.insert(task, now + ticks)
+            }
+        }
+    );
+
+    Ok(previous.map(|deadline| (deadline.saturating_sub(now) as f64) * pit::tick_interval()))
+}
+
+/// Returns the tick at which the earliest alarm goes off.
+pub(crate) fn next_alarm() -> Option<usize> {
+    instructions::interrupts::without_interrupts(
+        || ALARMS.lock().values().min().copied()
+    )
+}
+
+/// Raises `ALRM` on the tasks whose alarms have gone off.
+///
+/// Note: It is called on every tick, so it gives up rather than wait for the lock.
+pub(crate) fn tick(now: usize) {
+    let mut alarms = match ALARMS.try_lock() {
+        Some(alarms) => alarms,
+        None => return,
+    };
+    let mut pending = match PENDING.try_lock() {
+        Some(pending) => pending,
+        None => return,
+    };
+
+    alarms.retain(
+        |task, deadline| {
+            if *deadline > now { return true; }
+            *pending.entry(*task).or_insert(0) |= Signal::Alrm.bit();
+            HAS_PENDING.store(true, Ordering::Relaxed);
+            false
+        }
+    );
+}
+
+/// Returns whether any signal is pending.
+pub(crate) fn has_pending() -> bool { HAS_PENDING.load(Ordering::Relaxed) }
+
+/// Returns the tasks with pending signals.
+pub(crate) fn pending_tasks() -> Vec<TaskID> {
+    instructions::interrupts::without_interrupts(
+        || {
+            HAS_PENDING.store(false, Ordering::Relaxed);
+            PENDING.lock().iter().filter(|(_, mask)| **mask != 0).map(|(task, _)| *task).collect()
+        }
+    )
+}
+
+/// Delivers the pending signals of the task, and returns the signal that terminates it, if any.
+pub(crate) fn deliver(task: TaskID) -> Option<Signal> {
+    let (mask, actions) = instructions::interrupts::without_interrupts(
+        || {
+            let mask = PENDING.lock().get_mut(&task).map_or(0, core::mem::take);
+            let actions = ACTIONS.lock().get(&task).copied().unwrap_or([Action::Default; Signal::ALL.len()]);
+            (mask, actions)
+        }
+    );
+
+    for (signal, action) in Signal::ALL.iter().zip(actions) {
+        if mask & signal.bit() == 0 { continue; }
+        match action {
+            _ if !signal.is_catchable() => return Some(*signal),
+            Action::Default => return Some(*signal),
+            Action::Ignore => {}
+            Action::Handle(handler) => handler(*signal),
+        }
+    }
+
+    None
+}
+
+/// Forgets about the task once it is gone.
+pub(crate) fn release(task: TaskID) {
+    instructions::interrupts::without_interrupts(
+        || {
+            PENDING.lock().remove(&task);
+            ACTIONS.lock().remove(&task);
+            ALARMS.lock().remove(&task);
+            let mut foreground = FOREGROUND.lock();
+            if *foreground == Some(task) { *foreground = None; }
+        }
+    );
+}
@@ -0,0 +1,124 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::kernel::pit;
+
+// CPU Accounting
+//
+// The timer interrupt samples whether the CPU was halted when the tick arrived. Since the CPU only
+// halts when there is nothing left to run, the share of ticks spent halted over a window is the idle
+// share of that window, and the remainder is the busy share.
+//
+// The counts are published once per second, so that readers always see a complete window rather than
+// one that has just started. There is a single CPU for now; the counters become per-CPU once SMP does.
+
+////////////////
+// Attributes
+////////////////
+
+/// Length of an accounting window, in seconds.
+const WINDOW: f64 = 1.0;
+
+////////////
+// States
+////////////
+
+/// Flag to check whether the CPU is halted for lack of work.
+static IS_IDLE: AtomicBool = AtomicBool::new(false);
+
+/// Ticks elapsed in the current window.
+static WINDOW_TICKS: AtomicUsize = AtomicUsize::new(0);
+/// Ticks spent idle in the current window.
+static WINDOW_IDLE_TICKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Ticks elapsed in the last complete window.
+static LAST_TICKS: AtomicUsize = AtomicUsize::new(0);
+/// Ticks spent idle in the last complete window.
+static LAST_IDLE_TICKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Ticks spent idle since boot.
+static TOTAL_IDLE_TICKS: AtomicUsize = AtomicUsize::new(0);
+
+/////////////
+/// Usage
+/////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    /// Share of the last second spent running tasks, in percent.
+    pub busy: f64,
+    /// Share of the last second spent halted, in percent.
+    pub idle: f64,
+    /// Time spent running tasks since boot, in seconds.
+    pub total_busy: f64,
+    /// Time spent halted since boot, in seconds.
+    pub total_idle: f64,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Marks the CPU as halted for lack of work.
+pub(crate) fn enter_idle() { IS_IDLE.store(true, Ordering::Relaxed); }
+
+/// Marks the CPU as running again.
+pub(crate) fn exit_idle() { IS_IDLE.store(false, Ordering::Relaxed); }
+
+/// Accounts for a timer tick.
+///
+/// Note: Called from the timer interrupt handler.
+pub(crate) fn tick() {
+    let window = (WINDOW / pit::tick_interval()) as usize;
+
+    let ticks = WINDOW_TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    if IS_IDLE.load(Ordering::Relaxed) {
+        WINDOW_IDLE_TICKS.fetch_add(1, Ordering::Relaxed);
+        TOTAL_IDLE_TICKS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    if ticks >= window {
+        LAST_TICKS.store(ticks, Ordering::Relaxed);
+        LAST_IDLE_TICKS.store(WINDOW_IDLE_TICKS.swap(0, Ordering::Relaxed), Ordering::Relaxed);
+        WINDOW_TICKS.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Returns the CPU usage.
+pub fn cpu() -> Usage {
+    let ticks = LAST_TICKS.load(Ordering::Relaxed);
+    let idle_ticks = LAST_IDLE_TICKS.load(Ordering::Relaxed).min(ticks);
+
+    // Until the first window completes, report the CPU as busy, which it has been since boot.
+    let idle = if ticks == 0 { 0.0 } else { idle_ticks as f64 * 100.0 / ticks as f64 };
+
+    let total_idle = TOTAL_IDLE_TICKS.load(Ordering::Relaxed) as f64 * pit::tick_interval();
+    let total_busy = (pit::uptime() - total_idle).max(0.0);
+
+    Usage {
+        busy: 100.0 - idle,
+        idle,
+        total_busy,
+        total_idle,
+    }
+}
@@ -0,0 +1,222 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::aux::logger;
+use crate::aux::logger::LogLevel;
+use crate::kernel::error::Error;
+
+// System Call Tracing
+//
+// Commands reach the kernel through the functions of `api`, which thus serve as its system calls.
+// Any task or command can be traced by name: each call it makes is then logged with its arguments
+// and result, in the manner of `strace`, e.g.
+//
+//     [cat] open("/etc/motd", Read) = Ok(Fd(3))
+//
+// Traces only go to the log's ring buffer (see `log show`), as they would otherwise bury the output
+// of the traced command, and are subject to the rate limit of the logger like any other message.
+// Results are cut short, so that reading a large file does not format all of its bytes.
+//
+// The executor and the process runner keep track of the name of the code running at any moment. It
+// is only maintained while something is traced, so tracing costs nothing otherwise.
+
+////////////////
+// Attributes
+////////////////
+
+/// Maximum length of a formatted argument or result.
+const MAX_VALUE_LENGTH: usize = 48;
+
+////////////
+// States
+////////////
+
+/// Flag to check whether anything is traced or not.
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Names of the traced tasks and commands.
+static TRACED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Name of the task or command running, while tracing.
+static CURRENT: Mutex<Option<Arc<str>>> = Mutex::new(None);
+
+///////////////////
+/// Value Buffer
+///////////////////
+///
+/// Holds a formatted value, cut at `MAX_VALUE_LENGTH` bytes.
+struct ValueBuffer {
+    len: usize,
+    text: [u8; MAX_VALUE_LENGTH],
+    truncated: bool,
+}
+
+impl ValueBuffer {
+    /// Formats the value with `Debug`.
+    fn new(value: &dyn fmt::Debug) -> Self {
+        let mut buffer = ValueBuffer { len: 0, text: [0; MAX_VALUE_LENGTH], truncated: false };
+        // Formatting stops at the first error, which the buffer raises once it is full.
+        write!(buffer, "{:?}", value).ok();
+        buffer
+    }
+
+    /// Returns the formatted value.
+    fn as_str(&self) -> &str { core::str::from_utf8(&self.text[..self.len]).unwrap_or("") }
+}
+
+impl fmt::Write for ValueBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut end = s.len().min(MAX_VALUE_LENGTH - self.len);
+        while !s.is_char_boundary(end) { end -= 1; }
+        self.text[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        if end < s.len() {
+            self.truncated = true;
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ValueBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())?;
+        if self.truncated { f.write_str("...")?; }
+        Ok(())
+    }
+}
+
+/////////////////
+/// Arguments
+/////////////////
+struct Arguments<'a>(&'a [&'a dyn fmt::Debug]);
+
+impl fmt::Display for Arguments<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, arg) in self.0.iter().enumerate() {
+            if idx > 0 { f.write_str(", ")?; }
+            write!(f, "{}", ValueBuffer::new(*arg))?;
+        }
+        Ok(())
+    }
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns whether anything is traced or not.
+pub fn is_tracing() -> bool { TRACING.load(Ordering::Relaxed) }
+
+/// Starts tracing the calls made by the task or command with the given name.
+pub fn enable(name: &str) {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut traced = TRACED.lock();
+            if !traced.iter().any(|traced| traced == name) { traced.push(name.to_string()); }
+            TRACING.store(true, Ordering::Relaxed);
+        }
+    );
+}
+
+/// Stops tracing the calls made by the task or command with the given name.
+pub fn disable(name: &str) -> Result<(), Error> {
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut traced = TRACED.lock();
+            let idx = traced.iter().position(|traced| traced == name).ok_or(Error::NotFound)?;
+            traced.remove(idx);
+            TRACING.store(!traced.is_empty(), Ordering::Relaxed);
+            Ok(())
+        }
+    )
+}
+
+/// Calls the given function for the name of each traced task or command.
+pub fn for_each(f: impl FnMut(&str)) {
+    let traced = instructions::interrupts::without_interrupts(|| { TRACED.lock().clone() });
+    traced.iter().map(|name| name.as_str()).for_each(f);
+}
+
+/// Sets the name of the task or command running, returning the previous one.
+pub(crate) fn set_current(name: Option<Arc<str>>) -> Option<Arc<str>> {
+    instructions::interrupts::without_interrupts(
+        || { core::mem::replace(&mut *CURRENT.lock(), name) }
+    )
+}
+
+/// Logs the call, if made by a traced task or command.
+pub fn record<T: fmt::Debug>(name: &str, args: &[&dyn fmt::Debug], res: &Result<T, Error>) {
+    let current = instructions::interrupts::without_interrupts(
+        || {
+            let current = CURRENT.lock().clone()?;
+            TRACED.lock().iter().any(|traced| **traced == *current).then_some(current)
+        }
+    );
+    let current = match current {
+        Some(current) => current,
+        None => return,
+    };
+
+    match res {
+        Ok(value) => logger::log_quietly(
+            LogLevel::Omneity,
+            module_path!(),
+            format_args!("[{}] {}({}) = Ok({})", current, name, Arguments(args), ValueBuffer::new(value)),
+        ),
+        Err(e) => logger::log_quietly(
+            LogLevel::Omneity,
+            module_path!(),
+            format_args!("[{}] {}({}) = Err({})", current, name, Arguments(args), e),
+        ),
+    }
+}
+
+////////////
+// Macros
+////////////
+
+/// Evaluates the call and records it under the given name, along with the arguments and the result.
+#[macro_export]
+macro_rules! traced {
+    ($name:ident($($arg:expr),*) => $call:expr) => {{
+        let res = $call;
+        if $crate::kernel::strace::is_tracing() {
+            $crate::kernel::strace::record(stringify!($name), &[$(&$arg as &dyn core::fmt::Debug),*], &res);
+        }
+        res
+    }};
+}
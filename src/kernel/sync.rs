@@ -0,0 +1,192 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+// Lock Instrumentation
+//
+// The kernel runs on a single CPU and its tasks are cooperative, so a lock that is found held can
+// never be released by waiting: the holder is either the code that was interrupted to get here, or
+// a task that held the guard across an `await`. Either way, spinning on it hangs the machine without
+// a trace, which is notoriously hard to tell apart from a busy kernel.
+//
+// With the `lock-debug` feature, `Mutex` records the call site and the tick of each acquisition. A
+// second acquisition of a held lock panics with both call sites, and a lock held for longer than
+// `LONG_HOLD_TICKS` is reported once released. Without the feature, `Mutex` is the plain spin lock.
+
+#[cfg(not(feature = "lock-debug"))]
+pub use spin::{Mutex, MutexGuard};
+
+#[cfg(feature = "lock-debug")]
+pub use self::debug::{Mutex, MutexGuard};
+
+#[cfg(feature = "lock-debug")]
+mod debug {
+    use core::fmt;
+    use core::fmt::Write;
+    use core::mem::ManuallyDrop;
+    use core::ops::{Deref, DerefMut};
+    use core::panic::Location;
+    use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+    use crate::drivers::serial;
+    use crate::kernel::pit;
+    use crate::warning;
+
+    ////////////////
+    // Attributes
+    ////////////////
+
+    /// Ticks after which holding a lock is reported.
+    pub const LONG_HOLD_TICKS: usize = 100;
+
+    ////////////
+    // States
+    ////////////
+
+    /// Number of instrumented locks currently held.
+    static HELD: AtomicUsize = AtomicUsize::new(0);
+
+    /////////////
+    /// Mutex
+    /////////////
+    pub struct Mutex<T: ?Sized> {
+        owner: AtomicPtr<Location<'static>>,
+        since: AtomicUsize,
+        inner: spin::Mutex<T>,
+    }
+
+    impl<T> Mutex<T> {
+        /// Creates a new object.
+        pub const fn new(value: T) -> Self {
+            Self {
+                owner: AtomicPtr::new(core::ptr::null_mut()),
+                since: AtomicUsize::new(0),
+                inner: spin::Mutex::new(value),
+            }
+        }
+    }
+
+    impl<T: ?Sized> Mutex<T> {
+        /// Acquires the lock, panicking if it is already held.
+        #[track_caller]
+        pub fn lock(&self) -> MutexGuard<T> {
+            let caller = Location::caller();
+            match self.inner.try_lock() {
+                Some(guard) => self.acquired(guard, caller),
+                None => panic!("deadlock: lock acquired at {} is acquired again at {}", self.owner(), caller),
+            }
+        }
+
+        /// Acquires the lock if it is not held.
+        #[track_caller]
+        pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+            let caller = Location::caller();
+            self.inner.try_lock().map(|guard| self.acquired(guard, caller))
+        }
+
+        /// Returns whether the lock is held.
+        pub fn is_locked(&self) -> bool { self.inner.is_locked() }
+
+        /// Records the acquisition and wraps the guard.
+        fn acquired<'a>(&'a self, guard: spin::MutexGuard<'a, T>, caller: &'static Location<'static>) -> MutexGuard<'a, T> {
+            self.owner.store(caller as *const _ as *mut _, Ordering::Relaxed);
+            self.since.store(pit::ticks(), Ordering::Relaxed);
+            HELD.fetch_add(1, Ordering::Relaxed);
+
+            MutexGuard { lock: self, inner: ManuallyDrop::new(guard) }
+        }
+
+        /// Returns the call site of the current holder.
+        fn owner(&self) -> Owner {
+            let owner = self.owner.load(Ordering::Relaxed);
+            Owner(unsafe { owner.as_ref() })
+        }
+    }
+
+    impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.inner.fmt(f) }
+    }
+
+    /////////////
+    /// Owner
+    /////////////
+    struct Owner(Option<&'static Location<'static>>);
+
+    impl fmt::Display for Owner {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.0 {
+                Some(location) => location.fmt(f),
+                None => f.write_str("<unknown>"),
+            }
+        }
+    }
+
+    //////////////
+    /// Serial
+    //////////////
+    /// Writes to the serial port without taking its lock.
+    struct Serial;
+
+    impl fmt::Write for Serial {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            serial::write_unsynchronized(s);
+            Ok(())
+        }
+    }
+
+    ///////////////////
+    /// Mutex Guard
+    ///////////////////
+    pub struct MutexGuard<'a, T: ?Sized + 'a> {
+        lock: &'a Mutex<T>,
+        inner: ManuallyDrop<spin::MutexGuard<'a, T>>,
+    }
+
+    impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T { &self.inner }
+    }
+
+    impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
+        fn deref_mut(&mut self) -> &mut T { &mut self.inner }
+    }
+
+    impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+        fn drop(&mut self) {
+            let owner = self.lock.owner();
+            let held = pit::ticks().saturating_sub(self.lock.since.load(Ordering::Relaxed));
+
+            // Release before reporting, as the report may need this very lock.
+            unsafe { ManuallyDrop::drop(&mut self.inner); }
+            let others = HELD.fetch_sub(1, Ordering::Relaxed) - 1;
+
+            if held > LONG_HOLD_TICKS {
+                // The logger takes locks of its own, which may be among the ones still held.
+                if others == 0 {
+                    warning!("Lock: acquired at {} was held for {} ticks", owner, held);
+                } else {
+                    writeln!(Serial, "Lock: acquired at {} was held for {} ticks", owner, held).ok();
+                }
+            }
+        }
+    }
+}
@@ -21,6 +21,7 @@
 // SOFTWARE.
 
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 use core::future::Future;
 use core::pin::Pin;
 use core::sync::atomic::{AtomicU64, Ordering};
@@ -28,6 +29,9 @@ use core::task::{Context, Poll};
 
 pub use executor::Executor;
 
+use crate::kernel::capability::Capabilities;
+use crate::kernel::memory::space::AddressSpace;
+
 mod executor;
 
 ////////////////
@@ -37,6 +41,16 @@ mod executor;
 /// Keeps track of IDs.
 static NEXT_ID: AtomicU64 = AtomicU64::new(0);
 
+/// Marks the absence of a running task.
+const NO_TASK: u64 = u64::MAX;
+
+////////////
+// States
+////////////
+
+/// ID of the task being polled by the executor.
+static CURRENT: AtomicU64 = AtomicU64::new(NO_TASK);
+
 ///////////////
 /// Task ID
 ///////////////
@@ -55,6 +69,9 @@ impl TaskID {
 ////////////
 pub struct Task {
     id: TaskID,
+    name: Option<Arc<str>>,
+    caps: Capabilities,
+    space: Option<Arc<AddressSpace>>,
     future: Pin<Box<dyn Future<Output=()>>>,
 }
 
@@ -63,10 +80,58 @@ impl Task {
     pub fn new(future: impl Future<Output=()> + 'static) -> Self {
         Task {
             id: TaskID::new(),
+            name: None,
+            caps: Capabilities::ALL,
+            space: None,
             future: Box::pin(future),
         }
     }
 
+    /// Creates a new object with the given name, which tags its output.
+    pub fn with_name(name: &str, future: impl Future<Output=()> + 'static) -> Self {
+        Task {
+            name: Some(Arc::from(name)),
+            ..Task::new(future)
+        }
+    }
+
+    /// Limits the task to the given capabilities.
+    pub fn with_capabilities(self, caps: Capabilities) -> Self {
+        Task {
+            caps: self.caps.intersection(caps),
+            ..self
+        }
+    }
+
+    /// Runs the task in the given address space instead of the kernel space.
+    pub fn with_address_space(self, space: Arc<AddressSpace>) -> Self {
+        Task {
+            space: Some(space),
+            ..self
+        }
+    }
+
+    /// Returns the ID of the task.
+    pub(crate) fn id(&self) -> TaskID { self.id }
+
+    /// Returns the name of the task, if it has one.
+    pub fn name(&self) -> Option<&str> { self.name.as_deref() }
+
     /// Polls the inner future using the given context.
     fn poll(&mut self, context: &mut Context) -> Poll<()> { self.future.as_mut().poll(context) }
 }
+
+///////////////
+// Utilities
+///////////////
+
+/// Returns the ID of the task being polled, if any.
+pub(crate) fn current() -> Option<TaskID> {
+    match CURRENT.load(Ordering::Relaxed) {
+        NO_TASK => None,
+        id => Some(TaskID(id)),
+    }
+}
+
+/// Records the task being polled.
+fn set_current(task: Option<TaskID>) { CURRENT.store(task.map_or(NO_TASK, |task| task.0), Ordering::Relaxed); }
@@ -28,6 +28,10 @@ use core::task::{Context, Poll, Waker};
 use crossbeam_queue::ArrayQueue;
 use x86_64::instructions;
 
+use crate::drivers::vga;
+use crate::kernel::{capability, pit, power, process, signal, stats, strace};
+use crate::kernel::memory::space;
+use crate::kernel::task;
 use crate::kernel::task::{Task, TaskID};
 
 ////////////////
@@ -66,11 +70,49 @@ impl Executor {
     /// Runs all the ready tasks, halts the CPU otherwise.
     pub fn run(&mut self) -> ! {
         loop {
+            self.spawn_processes();
+            self.deliver_signals();
             self.run_ready_tasks();
             self.sleep_if_idle();
         }
     }
 
+    /// Spawns a task for each process waiting to be started.
+    fn spawn_processes(&mut self) {
+        for task in process::take_pending() {
+            self.spawn(task);
+        }
+    }
+
+    /// Delivers the pending signals, terminating the tasks whose signals call for it.
+    fn deliver_signals(&mut self) {
+        if !signal::has_pending() { return; }
+
+        for task_id in signal::pending_tasks() {
+            let (caps, space) = match self.tasks.get(&task_id) {
+                Some(task) => (task.caps, task.space.clone()),
+                None => {
+                    signal::release(task_id);
+                    continue;
+                }
+            };
+            // Handlers run on behalf of the task, with its capabilities and in its address space.
+            task::set_current(Some(task_id));
+            let kernel_caps = capability::set_current(caps);
+            space::switch(space.as_deref());
+            let terminated = signal::deliver(task_id);
+            space::switch(None);
+            capability::set_current(kernel_caps);
+            task::set_current(None);
+            if let Some(signal) = terminated {
+                self.tasks.remove(&task_id);
+                self.waker_cache.remove(&task_id);
+                signal::release(task_id);
+                process::terminate(task_id, signal);
+            }
+        }
+    }
+
     /// Runs all the ready tasks.
     fn run_ready_tasks(&mut self) {
         let Self { tasks, task_queue, waker_cache } = self;
@@ -84,10 +126,25 @@ impl Executor {
                 || { WakerWrapper::new(task_id, task_queue.clone()) }
             );
             let mut context = Context::from_waker(waker);
-            match task.poll(&mut context) {
+            let tagged = vga::is_output_tags_enabled();
+            if tagged { vga::set_output_context(task.name.clone().map(|name| (task_id.0, name))); }
+            let tracing = strace::is_tracing();
+            if tracing { strace::set_current(task.name.clone()); }
+            task::set_current(Some(task_id));
+            let kernel_caps = capability::set_current(task.caps);
+            space::switch(task.space.as_deref());
+            let poll = task.poll(&mut context);
+            space::switch(None);
+            // The task may have dropped some of its capabilities meanwhile.
+            task.caps = capability::set_current(kernel_caps);
+            task::set_current(None);
+            if tagged { vga::set_output_context(None); }
+            if tracing { strace::set_current(None); }
+            match poll {
                 Poll::Ready(()) => {
                     tasks.remove(&task_id);
                     waker_cache.remove(&task_id);
+                    signal::release(task_id);
                 }
                 Poll::Pending => {}
             }
@@ -97,8 +154,13 @@ impl Executor {
     /// Halts the CPU if there are no tasks.
     fn sleep_if_idle(&self) {
         instructions::interrupts::disable();
-        if self.task_queue.is_empty() {
-            instructions::interrupts::enable_and_hlt();
+        if self.task_queue.is_empty() && !process::has_pending() && !signal::has_pending() {
+            stats::enter_idle();
+            // Sleep until the next deadline rather than waking up on every tick.
+            pit::enter_tickless();
+            power::idle();
+            pit::exit_tickless();
+            stats::exit_idle();
         } else {
             instructions::interrupts::enable();
         }
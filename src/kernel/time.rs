@@ -0,0 +1,208 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use raw_cpuid::CpuId;
+use x86_64::instructions;
+
+use crate::kernel::dev::{Class, Device, Stage};
+use crate::kernel::error::Error;
+use crate::kernel::pit;
+use crate::kernel::portio;
+use crate::kernel::portio::Port;
+
+// Busy-Wait Delays
+//
+// Drivers often have to wait a few microseconds for hardware to settle, which is far below the
+// resolution of the PIT tick. Such delays spin on the Time-Stamp Counter (TSC) instead, whose rate is
+// measured once at boot against channel 2 of the PIT. Channel 2 is polled through the status of its
+// output, so the calibration neither needs interrupts nor disturbs the periodic tick on channel 0.
+//
+// Without a PIT, the rate is taken from CPUID when the processor reports it, and is measured against
+// the HPET once the ACPI tables are read otherwise. Delays err on the long side until then.
+//
+// The delays work with interrupts disabled. They assume a TSC that runs at a constant rate, which
+// holds on every processor recent enough to run this kernel in practice.
+//
+// OS Dev Wiki: https://wiki.osdev.org/TSC
+
+////////////////
+// Attributes
+////////////////
+
+/// Port controlling the gate of PIT channel 2 and the PC speaker, and reporting the channel output.
+const CONTROL_PORT: u16 = 0x61;
+/// Command port of the PIT.
+const PIT_COMMAND_PORT: u16 = 0x43;
+/// Data port of PIT channel 2.
+const PIT_CHANNEL_2_PORT: u16 = 0x42;
+
+/// Duration of the calibration, in milliseconds.
+const CALIBRATION_MS: u64 = 10;
+
+///////////////////
+// Cached Values
+///////////////////
+
+/// Frequency of the TSC in hertz, or zero if not calibrated yet.
+static TSC_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+////////////
+// Device
+////////////
+
+/// Device descriptor of the TSC.
+///
+/// Note: It is brought up after the PIT, whose presence decides how the rate is measured.
+pub(crate) static DEVICE: Device = Device {
+    name: "TSC",
+    class: Class::Processor,
+    stage: Stage::Core,
+    critical: false,
+    depends: &["PIT"],
+    tolerates_absent: true,
+    init,
+    suspend: None,
+    resume: None,
+};
+
+///////////////
+// Utilities
+///////////////
+
+/// Calibrates the TSC against the PIT, or reads its rate from CPUID if the PIT is absent.
+pub(crate) fn init() -> Result<(), Error> {
+    if !pit::is_initialized() {
+        // Otherwise, the calibration is left to the HPET.
+        if let Some(hz) = reported_frequency() {
+            TSC_FREQUENCY.store(hz, Ordering::Relaxed);
+        }
+        return Ok(());
+    }
+
+    portio::reserve("TSC", CONTROL_PORT, 1).ok();
+
+    let hz = instructions::interrupts::without_interrupts(calibrate);
+    if hz == 0 { return Err(Error::Failed); }
+    TSC_FREQUENCY.store(hz, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Calibrates the TSC against a counter of the given frequency, unless it is calibrated already.
+pub(crate) fn calibrate_against(counter: impl Fn() -> u64, frequency: u64) {
+    if tsc_frequency().is_some() || frequency == 0 { return; }
+
+    let hz = instructions::interrupts::without_interrupts(
+        || {
+            let count = frequency * CALIBRATION_MS / 1000;
+            let start = counter();
+            let tsc_start = pit::rdtsc();
+            while counter().wrapping_sub(start) < count {
+                spin_loop();
+            }
+            (pit::rdtsc() - tsc_start) * 1000 / CALIBRATION_MS
+        }
+    );
+    TSC_FREQUENCY.store(hz, Ordering::Relaxed);
+}
+
+/// Returns the frequency of the TSC in hertz, or none if it is not calibrated.
+pub fn tsc_frequency() -> Option<u64> {
+    match TSC_FREQUENCY.load(Ordering::Relaxed) {
+        0 => None,
+        hz => Some(hz),
+    }
+}
+
+/// Returns whether ticks are counted, by either the PIT or the HPET standing in for it.
+///
+/// Note: It reads the flag of the PIT, which the HPET sets as well when it takes over the tick
+/// through `pit::start_ticking`. Timeouts measured through `pit::uptime` never expire before it holds.
+#[cfg(any(feature = "fs", feature = "usb"))]
+pub(crate) fn is_ticking() -> bool { pit::is_initialized() }
+
+/// Spins for at least the given number of nanoseconds.
+pub fn delay_ns(ns: u64) {
+    // Before calibration, assume a fast TSC so that the delay errs on the long side.
+    const FALLBACK_FREQUENCY: u64 = 10_000_000_000;
+
+    let hz = tsc_frequency().unwrap_or(FALLBACK_FREQUENCY);
+    let cycles = (ns as u128 * hz as u128 / 1_000_000_000) as u64;
+
+    let start = pit::rdtsc();
+    while pit::rdtsc().wrapping_sub(start) < cycles {
+        spin_loop();
+    }
+}
+
+/// Spins for at least the given number of microseconds.
+pub fn delay_us(us: u64) { delay_ns(us.saturating_mul(1000)); }
+
+/// Returns the TSC frequency in hertz as reported by the processor, if it does.
+fn reported_frequency() -> Option<u64> {
+    CpuId::new().get_tsc_info().and_then(|info| info.tsc_frequency()).filter(|hz| *hz != 0)
+}
+
+/// Counts the TSC cycles over a fixed number of PIT periods and returns the TSC frequency in hertz.
+fn calibrate() -> u64 {
+    /// Gate input of channel 2.
+    const GATE: u8 = 1 << 0;
+    /// Connection of channel 2 to the speaker.
+    const SPEAKER: u8 = 1 << 1;
+    /// Output of channel 2.
+    const OUTPUT: u8 = 1 << 5;
+    /// Channel 2, low byte then high byte, mode 0 (interrupt on terminal count).
+    const ONE_SHOT: u8 = 0b1011_0000;
+
+    let count = (pit::FREQUENCY as u64 * CALIBRATION_MS / 1000) as u16;
+
+    let mut control = Port::<u8>::new(CONTROL_PORT);
+    let mut command = Port::<u8>::new(PIT_COMMAND_PORT);
+    let mut data = Port::<u8>::new(PIT_CHANNEL_2_PORT);
+
+    unsafe {
+        let prev = control.read();
+        control.write((prev & !SPEAKER) | GATE);
+
+        // The count starts once its high byte is written, and the output rises when it reaches zero.
+        command.write(ONE_SHOT);
+        let [low, high] = count.to_le_bytes();
+        data.write(low);
+        data.write(high);
+
+        let start = pit::rdtsc();
+        // The PIT counts down at about 1.19 MHz, so a stuck output is detected long before the bound.
+        let mut polls = 0u64;
+        while control.read() & OUTPUT == 0 {
+            polls += 1;
+            if polls > 100_000_000 { return 0; }
+        }
+        let cycles = pit::rdtsc() - start;
+
+        control.write(prev);
+
+        cycles * 1000 / CALIBRATION_MS
+    }
+}
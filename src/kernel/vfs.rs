@@ -0,0 +1,372 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use spin::Mutex;
+use x86_64::instructions;
+
+use crate::kernel::error::Error;
+use crate::kernel::ramfs;
+
+// Virtual File System (VFS)
+//
+// The VFS joins every mounted filesystem into a single tree rooted at `/`. Each mount attaches a
+// filesystem at a directory of the tree, the mount point, hiding whatever that directory contained
+// before. Mounts may be nested, e.g. a disk mounted at `/mnt/disk0p1` below the root filesystem.
+//
+// A path is first brought into canonical form by resolving `.` and `..` components. The mount whose
+// mount point is the longest prefix of the path then serves the request, with the remainder of the
+// path (relative to the mount point, but still starting with `/`) handed to its filesystem.
+//
+// Filesystem drivers are listed in `FILESYSTEMS` along with a probe, which creates a filesystem from a
+// source (e.g. the name of a block device) or reports that it does not recognize the source.
+
+/////////////
+// Globals
+/////////////
+
+/// Creates a filesystem from the given source.
+pub type Probe = fn(&str) -> Result<Arc<dyn FileSystem>, Error>;
+
+/// Available filesystem drivers.
+pub const FILESYSTEMS: [(&str, Probe); 1] = [
+    ("ramfs", ramfs::probe),
+];
+
+/// Directories created on the root filesystem at boot.
+const ROOT_DIRS: [&str; 5] = ["/boot", "/etc", "/home", "/mnt", "/tmp"];
+
+/////////////
+// Mutexes
+/////////////
+
+/// Table of mounts.
+static MOUNTS: Mutex<Vec<Mount>> = Mutex::new(Vec::new());
+
+/////////////////
+/// File Type
+/////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Directory,
+}
+
+impl FileType {
+    /// Returns the object as a primitive string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::File => "file",
+            Self::Directory => "directory",
+        }
+    }
+}
+
+////////////////
+/// Metadata
+////////////////
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    pub file_type: FileType,
+    pub size: usize,
+    pub read_only: bool,
+}
+
+impl Metadata {
+    /// Returns whether the path names a directory.
+    pub fn is_dir(&self) -> bool { self.file_type == FileType::Directory }
+
+    /// Returns whether the path names a file.
+    pub fn is_file(&self) -> bool { self.file_type == FileType::File }
+}
+
+/////////////////
+/// Dir Entry
+/////////////////
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub file_type: FileType,
+    pub size: usize,
+}
+
+///////////////////
+/// File System
+///////////////////
+pub trait FileSystem: Send + Sync {
+    /// Returns the name of the filesystem driver.
+    fn name(&self) -> &str;
+
+    /// Returns the metadata of the file or directory.
+    fn metadata(&self, path: &str) -> Result<Metadata, Error>;
+
+    /// Returns the contents of the file.
+    fn read(&self, path: &str) -> Result<Vec<u8>, Error>;
+
+    /// Replaces the contents of the file, creating it if necessary.
+    fn write(&self, path: &str, data: &[u8]) -> Result<(), Error>;
+
+    /// Appends to the contents of the file, creating it if necessary.
+    fn append(&self, path: &str, data: &[u8]) -> Result<(), Error>;
+
+    /// Returns the entries of the directory, sorted by name.
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, Error>;
+
+    /// Creates the directory, whose parent must exist.
+    fn create_dir(&self, path: &str) -> Result<(), Error>;
+
+    /// Removes the file or empty directory.
+    fn remove(&self, path: &str) -> Result<(), Error>;
+
+    /// Writes any pending changes to the underlying storage.
+    fn sync(&self) -> Result<(), Error> { Ok(()) }
+}
+
+/////////////
+/// Mount
+/////////////
+struct Mount {
+    path: String,
+    source: String,
+    read_only: bool,
+    fs: Arc<dyn FileSystem>,
+}
+
+/////////////////
+/// Mount Info
+/////////////////
+#[derive(Debug, Clone)]
+pub struct MountInfo {
+    pub path: String,
+    pub source: String,
+    pub fs_type: String,
+    pub read_only: bool,
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Mounts the root filesystem and creates the standard directories.
+pub(crate) fn init() -> Result<(), Error> {
+    mount("ramfs", "/", Some("ramfs"), false)?;
+    for dir in ROOT_DIRS {
+        create_dir(dir)?;
+    }
+
+    Ok(())
+}
+
+/// Mounts the source at the target directory, probing every driver unless a filesystem type is given.
+pub fn mount(source: &str, target: &str, fs_type: Option<&str>, read_only: bool) -> Result<(), Error> {
+    let target = normalize(target)?;
+
+    // Apart from the root filesystem, mount points must be existing directories.
+    let is_root = instructions::interrupts::without_interrupts(|| MOUNTS.lock().is_empty());
+    if !is_root && !metadata(&target)?.is_dir() { return Err(Error::NotADirectory); }
+
+    let fs = match fs_type {
+        Some(fs_type) => {
+            let (_, probe) = FILESYSTEMS.iter().find(|(name, _)| *name == fs_type).ok_or(Error::Unsupported)?;
+            probe(source)?
+        }
+        None => FILESYSTEMS.iter().find_map(|(_, probe)| probe(source).ok()).ok_or(Error::Unsupported)?,
+    };
+
+    instructions::interrupts::without_interrupts(
+        || {
+            let mut mounts = MOUNTS.lock();
+            if mounts.iter().any(|m| m.path == target) { return Err(Error::Busy); }
+            mounts.push(Mount { path: target, source: source.to_string(), read_only, fs });
+            Ok(())
+        }
+    )
+}
+
+/// Unmounts the filesystem mounted at the target directory.
+pub fn unmount(target: &str) -> Result<(), Error> {
+    let target = normalize(target)?;
+    if target == "/" { return Err(Error::Busy); }
+
+    let fs = instructions::interrupts::without_interrupts(
+        || {
+            let mounts = MOUNTS.lock();
+            let mount = mounts.iter().find(|m| m.path == target).ok_or(Error::NotFound)?;
+            // Nested mounts must be detached first.
+            if mounts.iter().any(|m| is_within(&m.path, &target) && m.path != target) { return Err(Error::Busy); }
+            Ok(mount.fs.clone())
+        }
+    )?;
+
+    fs.sync()?;
+
+    instructions::interrupts::without_interrupts(|| MOUNTS.lock().retain(|m| m.path != target));
+
+    Ok(())
+}
+
+/// Calls the given function for each mount, in mounting order.
+pub fn for_each_mount(f: impl FnMut(&MountInfo)) {
+    let infos: Vec<MountInfo> = instructions::interrupts::without_interrupts(
+        || {
+            MOUNTS.lock().iter()
+                .map(|m| MountInfo {
+                    path: m.path.clone(),
+                    source: m.source.clone(),
+                    fs_type: m.fs.name().to_string(),
+                    read_only: m.read_only,
+                })
+                .collect()
+        }
+    );
+    infos.iter().for_each(f);
+}
+
+/// Writes the pending changes of every mounted filesystem to its storage.
+pub fn sync() -> Result<(), Error> {
+    let filesystems: Vec<Arc<dyn FileSystem>> = instructions::interrupts::without_interrupts(
+        || MOUNTS.lock().iter().map(|m| m.fs.clone()).collect()
+    );
+    filesystems.iter().try_for_each(|fs| fs.sync())
+}
+
+/// Returns the metadata of the file or directory.
+pub fn metadata(path: &str) -> Result<Metadata, Error> {
+    let (fs, path, read_only) = resolve(path)?;
+    let mut metadata = fs.metadata(&path)?;
+    metadata.read_only |= read_only;
+    Ok(metadata)
+}
+
+/// Returns the contents of the file.
+pub fn read(path: &str) -> Result<Vec<u8>, Error> {
+    let (fs, path, _) = resolve(path)?;
+    fs.read(&path)
+}
+
+/// Replaces the contents of the file, creating it if necessary.
+pub fn write(path: &str, data: &[u8]) -> Result<(), Error> {
+    let (fs, path) = resolve_writable(path)?;
+    fs.write(&path, data)
+}
+
+/// Appends to the contents of the file, creating it if necessary.
+pub fn append(path: &str, data: &[u8]) -> Result<(), Error> {
+    let (fs, path) = resolve_writable(path)?;
+    fs.append(&path, data)
+}
+
+/// Returns the entries of the directory, sorted by name.
+pub fn read_dir(path: &str) -> Result<Vec<DirEntry>, Error> {
+    let (fs, path, _) = resolve(path)?;
+    fs.read_dir(&path)
+}
+
+/// Creates the directory, whose parent must exist.
+pub fn create_dir(path: &str) -> Result<(), Error> {
+    let (fs, path) = resolve_writable(path)?;
+    fs.create_dir(&path)
+}
+
+/// Removes the file or empty directory.
+pub fn remove(path: &str) -> Result<(), Error> {
+    let normalized = normalize(path)?;
+    let is_mount_point = instructions::interrupts::without_interrupts(
+        || MOUNTS.lock().iter().any(|m| m.path == normalized)
+    );
+    if is_mount_point { return Err(Error::Busy); }
+
+    let (fs, path) = resolve_writable(&normalized)?;
+    fs.remove(&path)
+}
+
+/// Returns whether the file or directory exists.
+pub fn exists(path: &str) -> bool { metadata(path).is_ok() }
+
+/// Returns the canonical form of an absolute path, resolving `.` and `..` components.
+pub fn normalize(path: &str) -> Result<String, Error> {
+    if !path.starts_with('/') { return Err(Error::InvalidArgument); }
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => { components.pop(); }
+            _ => components.push(component),
+        }
+    }
+
+    if components.is_empty() { return Ok("/".to_string()); }
+
+    let mut normalized = String::new();
+    for component in components {
+        normalized.push('/');
+        normalized.push_str(component);
+    }
+    Ok(normalized)
+}
+
+/// Splits a canonical path into its parent directory and its last component.
+pub fn split(path: &str) -> (&str, &str) {
+    match path.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((parent, name)) => (parent, name),
+        None => ("/", path),
+    }
+}
+
+/// Returns the filesystem serving the path, the path relative to its mount point, and whether the
+/// mount is read-only.
+fn resolve(path: &str) -> Result<(Arc<dyn FileSystem>, String, bool), Error> {
+    let path = normalize(path)?;
+    instructions::interrupts::without_interrupts(
+        || {
+            let mounts = MOUNTS.lock();
+            let mount = mounts.iter()
+                .filter(|m| is_within(&path, &m.path))
+                .max_by_key(|m| m.path.len())
+                .ok_or(Error::NotInitialized)?;
+
+            let relative = match &path[mount.path.len()..] {
+                "" => "/".to_string(),
+                _ if mount.path == "/" => path.clone(),
+                rest => rest.to_string(),
+            };
+            Ok((mount.fs.clone(), relative, mount.read_only))
+        }
+    )
+}
+
+/// Resolves the path like `resolve`, failing if the mount is read-only.
+fn resolve_writable(path: &str) -> Result<(Arc<dyn FileSystem>, String), Error> {
+    let (fs, path, read_only) = resolve(path)?;
+    if read_only { return Err(Error::ReadOnly); }
+    Ok((fs, path))
+}
+
+/// Returns whether the canonical path lies at or below the directory.
+fn is_within(path: &str, dir: &str) -> bool {
+    dir == "/" || path == dir || (path.starts_with(dir) && path.as_bytes().get(dir.len()) == Some(&b'/'))
+}
@@ -36,22 +36,41 @@ extern crate alloc;
 #[cfg(test)]
 use core::panic::PanicInfo;
 
-use bootloader::BootInfo;
 #[cfg(test)]
-use bootloader::entry_point;
-use x86_64::instructions;
+use bootloader::{BootInfo, entry_point};
 
+use crate::arch::{Arch, Current};
+#[cfg(target_arch = "x86_64")]
 use crate::aux::logger;
-use crate::aux::logger::{LogLevel, LogResult};
+#[cfg(target_arch = "x86_64")]
+use crate::aux::logger::LogLevel;
+#[cfg(target_arch = "x86_64")]
+use crate::kernel::boot::BootProtocol;
+#[cfg(target_arch = "x86_64")]
+use crate::kernel::initcall::Level;
 #[cfg(test)]
 use crate::aux::testing::serene_test_panic_handler;
 
+#[cfg(target_arch = "x86_64")]
 pub mod api;
+pub mod arch;
+#[cfg(target_arch = "x86_64")]
 pub mod aux;
 pub mod encodings;
+#[cfg(target_arch = "x86_64")]
 pub mod devices;
+#[cfg(target_arch = "x86_64")]
 pub mod drivers;
+#[cfg(target_arch = "x86_64")]
 pub mod kernel;
+#[cfg(target_arch = "x86_64")]
+pub mod usr;
+
+/// The parts of the kernel that have been ported beyond x86_64.
+#[cfg(not(target_arch = "x86_64"))]
+pub mod kernel {
+    pub mod error;
+}
 
 #[cfg(test)]
 entry_point!(test_kernel_main);
@@ -68,28 +87,37 @@ fn test_kernel_main(boot_info: &'static BootInfo) -> ! {
 fn panic(info: &PanicInfo) -> ! { serene_test_panic_handler(info); }
 
 /// Initializes all sub-modules.
-pub fn init(boot_info: &'static BootInfo, log_lvl: LogLevel) {
-    drivers::vga::init().log("VGA", "initialized");
-
+#[cfg(target_arch = "x86_64")]
+pub fn init(boot: &'static (dyn BootProtocol + Sync), log_lvl: LogLevel) {
+    kernel::boot::start();
+    kernel::boot::init(boot);
     logger::init(log_lvl).ok();
 
-    kernel::gdt::init().log("GDT", "initialized");
-    kernel::idt::init().log("IDT", "initialized");
-    kernel::pics::init().log("PICS", "initialized");
-    kernel::pics::enable().log("PICS", "interrupts enabled");
-    kernel::pit::init().log("PIT", "initialized");
+    // Record the boot information before any device needs it.
+    kernel::boot::measure("Memory", || kernel::memory::init(boot)).ok();
 
-    kernel::memory::init(boot_info).log("Memory", "initialized");
-    kernel::allocator::init(boot_info).log("Allocator", "initialized");
-    kernel::acpi::init().log("ACPI", "initialized");
-    drivers::keyboard::init(api::keyboard::Layout::QWERTY).log("Keyboard", "initialized");
+    // The display is registered first so that it is brought up before anything gets logged.
+    drivers::register_devices();
+    kernel::register_devices();
+    kernel::register_initcalls();
 
-    kernel::apic::init().log("APIC", "initialized");
+    kernel::initcall::run(Level::Early);
+    kernel::dev::init();
+    kernel::initcall::run(Level::Late);
+
+    let report = api::system::boot_report();
+    let failures = report.failures().count();
+    if failures > 0 {
+        warning!("Boot: completed with {} failed device(s)", failures);
+    }
+    if !report.apic_enabled {
+        apprise!("Boot: interrupts are delivered through the legacy PICs");
+    }
 }
 
 /// Halts execution of CPU until next interrupt.
 pub fn hlt_loop() -> ! {
     loop {
-        instructions::hlt();
+        Current::wait_for_interrupt();
     }
 }
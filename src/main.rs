@@ -30,20 +30,43 @@ extern crate alloc;
 
 use core::panic::PanicInfo;
 
+#[cfg(target_arch = "x86_64")]
 use bootloader::{BootInfo, entry_point};
 
+#[cfg(target_arch = "riscv64")]
+use asm_os::arch::riscv64;
+#[cfg(target_arch = "x86_64")]
 use asm_os::init;
+#[cfg(target_arch = "x86_64")]
 use asm_os::api::{system, vga};
+#[cfg(all(target_arch = "x86_64", feature = "usb"))]
+use asm_os::api::usb;
+#[cfg(all(target_arch = "x86_64", not(test)))]
+use asm_os::aux::emergency;
+#[cfg(target_arch = "x86_64")]
 use asm_os::aux::logger::LogLevel;
 #[cfg(test)]
 use asm_os::aux::testing::serene_test_panic_handler;
+#[cfg(all(target_arch = "x86_64", not(test)))]
+use asm_os::emergency_println;
 #[cfg(not(test))]
 use asm_os::hlt_loop;
+#[cfg(all(target_arch = "x86_64", feature = "heap-redzone"))]
+use asm_os::kernel::allocator::debug;
+#[cfg(all(target_arch = "x86_64", feature = "fs"))]
+use asm_os::kernel::block::cache;
+#[cfg(target_arch = "x86_64")]
+use asm_os::kernel::memory::pressure;
+#[cfg(target_arch = "x86_64")]
 use asm_os::kernel::task::{Executor, Task};
 use asm_os::println;
+#[cfg(target_arch = "x86_64")]
+use asm_os::usr;
 
+#[cfg(target_arch = "x86_64")]
 entry_point!(kernel_main);
 
+#[cfg(target_arch = "x86_64")]
 fn kernel_main(boot_info: &'static BootInfo) -> ! {
     vga::set_palette(vga::palette::MATERIAL_DARKER_HC);
     init(boot_info, LogLevel::Omneity);
@@ -56,10 +79,43 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
     test_main();
 
     let mut executor = Executor::new();
+    executor.spawn(Task::new(vga::output_task()));
+    executor.spawn(Task::new(vga::blank_task()));
+    executor.spawn(Task::with_name("memory", pressure::monitor()));
+    #[cfg(feature = "heap-redzone")]
+    executor.spawn(Task::with_name("scrub", debug::scrub_task()));
+    #[cfg(feature = "fs")]
+    executor.spawn(Task::with_name("cache", cache::write_back()));
+    #[cfg(feature = "usb")]
+    executor.spawn(Task::with_name("usb", usb::poll_task()));
+    executor.spawn(Task::with_name("shell", usr::shell::main()));
     executor.run();
 }
 
-#[cfg(not(test))]
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+extern "C" fn kernel_main() -> ! {
+    riscv64::init();
+
+    println!();
+    println!("Welcome to asmOS! (riscv64)");
+    println!();
+
+    loop {
+        riscv64::timer::sleep(1.0);
+        println!("uptime: {:.0}s", riscv64::timer::uptime());
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(test)))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    emergency_println!("{}", info);
+    emergency::print_recent_log();
+    hlt_loop();
+}
+
+#[cfg(all(target_arch = "riscv64", not(test)))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     println!("{}", info);
@@ -0,0 +1,247 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::fmt::Write;
+
+use acpi::InterruptModel;
+use acpi::platform::address::GenericAddress;
+use acpi::platform::interrupt::{LocalInterruptLine, NmiProcessor};
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::kernel::acpi::{fadt, find, madt, namespace, Table, tables};
+use crate::msg;
+
+/// Lists the ACPI tables, or dumps or describes the given one.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [_] => list(stdio),
+        [_, "devices"] => devices(stdio),
+        [_, "dump", signature] => dump(lookup(signature, stdio)?, stdio),
+        [_, "show", signature] => show(lookup(signature, stdio)?, stdio),
+        _ => {
+            writeln!(stdio.stderr, "{}: acpi [devices|dump SIGNATURE|show SIGNATURE]", msg!("usage"))?;
+            Err(Error::InvalidArgument)
+        }
+    }
+}
+
+/// Lists the discovered tables.
+fn list(stdio: &mut Stdio) -> Result<(), Error> {
+    let tables = tables();
+    if tables.is_empty() {
+        writeln!(stdio.stderr, "acpi: {}", msg!("acpi.no_tables"))?;
+        return Err(Error::Failed);
+    }
+
+    writeln!(stdio.stdout, "\x1B[93mSIG    ADDRESS      LENGTH   REV  OEM\x1B[0m")?;
+    for table in tables {
+        writeln!(
+            stdio.stdout,
+            "{:<6} {:#010x}   {:<8} {:<4} {}",
+            table.signature, table.address, table.length, table.revision, table.oem_id
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Lists the device objects in the namespace along with their resources.
+fn devices(stdio: &mut Stdio) -> Result<(), Error> {
+    let nodes = namespace::devices();
+    if nodes.is_empty() {
+        writeln!(stdio.stderr, "acpi: {}", msg!("acpi.no_namespace"))?;
+        return Err(Error::Failed);
+    }
+
+    writeln!(stdio.stdout, "\x1B[93m{:<24} {:<10} {:<4} PRESENT\x1B[0m", "PATH", "HID", "UID")?;
+    for node in nodes {
+        let uid = node.uid.map_or(String::from("-"), |uid| format!("{}", uid));
+        writeln!(
+            stdio.stdout,
+            "{:<24} {:<10} {:<4} {}",
+            node.path, node.hid.as_deref().unwrap_or("-"), uid, yes_no(node.present)
+        )?;
+        for resource in &node.resources {
+            writeln!(stdio.stdout, "  \x1B[90m{:?}\x1B[0m", resource)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the table with the given signature, accepting the common names of the FADT and the MADT.
+fn lookup(signature: &str, stdio: &mut Stdio) -> Result<&'static Table, Error> {
+    let signature = match signature.to_ascii_uppercase().as_str() {
+        "FADT" => "FACP",
+        "MADT" => "APIC",
+        _ => signature,
+    };
+    match find(signature) {
+        Some(table) => Ok(table),
+        None => {
+            writeln!(stdio.stderr, "acpi: {}", msg!("acpi.no_table", signature))?;
+            Err(Error::Failed)
+        }
+    }
+}
+
+/// Prints the raw contents of the table.
+fn dump(table: &Table, stdio: &mut Stdio) -> Result<(), Error> {
+    const WIDTH: usize = 16;
+
+    for (i, row) in table.bytes().chunks(WIDTH).enumerate() {
+        write!(stdio.stdout, "\x1B[90m{:08x}\x1B[0m ", i * WIDTH)?;
+        for col in 0..WIDTH {
+            match row.get(col) {
+                Some(byte) => write!(stdio.stdout, " {:02x}", byte)?,
+                None => write!(stdio.stdout, "   ")?,
+            }
+        }
+        let text: String = row.iter()
+            .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+            .collect();
+        writeln!(stdio.stdout, "  {}", text)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the header of the table followed by its decoded fields, if known.
+fn show(table: &Table, stdio: &mut Stdio) -> Result<(), Error> {
+    writeln!(stdio.stdout, "\x1B[93m{}\x1B[0m", table.signature)?;
+    writeln!(stdio.stdout, "  address:   {:#x}", table.address)?;
+    writeln!(stdio.stdout, "  length:    {}", table.length)?;
+    writeln!(stdio.stdout, "  revision:  {}", table.revision)?;
+    writeln!(stdio.stdout, "  oem:       {}", table.oem_id)?;
+
+    match table.signature.as_str() {
+        "FACP" => show_fadt(stdio),
+        "APIC" => show_madt(stdio),
+        _ => Ok(()),
+    }
+}
+
+/// Prints the decoded fields of the FADT.
+fn show_fadt(stdio: &mut Stdio) -> Result<(), Error> {
+    let info = match fadt::info() {
+        Some(info) => info,
+        None => return Ok(()),
+    };
+
+    writeln!(stdio.stdout, "  sci:       {}", info.sci_interrupt)?;
+    writeln!(stdio.stdout, "  smi cmd:   {:#x}", info.smi_cmd_port)?;
+    writeln!(stdio.stdout, "  enable:    {:#x}", fadt::acpi_enable())?;
+    writeln!(stdio.stdout, "  disable:   {:#x}", fadt::acpi_disable())?;
+    writeln!(stdio.stdout, "  pm1a evt:  {}", Address(info.pm1a_event_block))?;
+    writeln!(stdio.stdout, "  pm1a ctrl: {}", Address(info.pm1a_control_block))?;
+    let width = if info.pm_timer_32_bit { 32 } else { 24 };
+    writeln!(stdio.stdout, "  pm timer:  {} ({}-bit)", Address(info.pm_timer_block), width)?;
+    writeln!(stdio.stdout, "  century:   {:#x}", info.century)?;
+    writeln!(stdio.stdout, "  legacy:    {}", yes_no(info.legacy_devices))?;
+    writeln!(stdio.stdout, "  8042:      {}", yes_no(info.has_8042))?;
+    writeln!(stdio.stdout, "  cmos rtc:  {}", yes_no(info.has_cmos_rtc))?;
+    writeln!(stdio.stdout, "  reduced:   {}", yes_no(info.hardware_reduced))?;
+    if info.reset_supported {
+        writeln!(stdio.stdout, "  reset:     {} <- {:#x}", Address(info.reset_register), info.reset_value)?;
+    } else {
+        writeln!(stdio.stdout, "  reset:     not supported")?;
+    }
+
+    Ok(())
+}
+
+/// Prints the processors and interrupt routing described by the MADT.
+fn show_madt(stdio: &mut Stdio) -> Result<(), Error> {
+    if let Some(info) = madt::get_processor_info() {
+        let processors = core::iter::once(&info.boot_processor).chain(info.application_processors.iter());
+        for processor in processors {
+            let role = if processor.is_ap { "AP" } else { "BSP" };
+            writeln!(
+                stdio.stdout,
+                "  cpu:       uid {} apic {} {} {:?}",
+                processor.processor_uid, processor.local_apic_id, role, processor.state
+            )?;
+        }
+    }
+
+    let apic = match madt::get_interrupt_model() {
+        Some(InterruptModel::Apic(apic)) => apic,
+        _ => return Ok(()),
+    };
+    writeln!(stdio.stdout, "  lapic:     {:#x}", apic.local_apic_address)?;
+    writeln!(stdio.stdout, "  8259:      {}", yes_no(apic.also_has_legacy_pics))?;
+    for io_apic in apic.io_apics.iter() {
+        writeln!(
+            stdio.stdout,
+            "  ioapic:    id {} at {:#x}, gsi base {}",
+            io_apic.id, io_apic.address, io_apic.global_system_interrupt_base
+        )?;
+    }
+    for iso in apic.interrupt_source_overrides.iter() {
+        writeln!(
+            stdio.stdout,
+            "  override:  irq {} -> gsi {} ({:?}, {:?})",
+            iso.isa_source, iso.global_system_interrupt, iso.polarity, iso.trigger_mode
+        )?;
+    }
+    for source in apic.nmi_sources.iter() {
+        writeln!(
+            stdio.stdout,
+            "  nmi:       gsi {} ({:?}, {:?})",
+            source.global_system_interrupt, source.polarity, source.trigger_mode
+        )?;
+    }
+    for line in apic.local_apic_nmi_lines.iter() {
+        let processor = match line.processor {
+            NmiProcessor::All => String::from("all"),
+            NmiProcessor::ProcessorUid(uid) => format!("uid {}", uid),
+        };
+        let pin = match line.line {
+            LocalInterruptLine::Lint0 => "LINT0",
+            LocalInterruptLine::Lint1 => "LINT1",
+        };
+        writeln!(stdio.stdout, "  lapic nmi: {} on {}", processor, pin)?;
+    }
+
+    Ok(())
+}
+
+/// Formats a boolean as `yes` or `no`.
+fn yes_no(value: bool) -> &'static str { if value { "yes" } else { "no" } }
+
+///////////////
+/// Address
+///////////////
+struct Address(Option<GenericAddress>);
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(address) => write!(f, "{:?} {:#x}", address.address_space, address.address),
+            None => write!(f, "none"),
+        }
+    }
+}
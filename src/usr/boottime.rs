@@ -0,0 +1,67 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::api::system;
+use crate::msg;
+
+/// Prints how long each step of the boot took, slowest first with `-s`.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    const MS: f64 = 1000.0;
+    const BAR_WIDTH: f64 = 30.0;
+
+    let sorted = match args {
+        [_] => false,
+        [_, "-s"] => true,
+        _ => {
+            writeln!(stdio.stderr, "{}: boottime [-s]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    };
+
+    let mut timings = system::boot_timings();
+    if timings.is_empty() {
+        writeln!(stdio.stderr, "boottime: {}", msg!("boottime.no_timings"))?;
+        return Err(Error::Failed);
+    }
+    if sorted {
+        timings.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+    }
+
+    let total = timings.iter().map(|timing| timing.start + timing.duration).fold(0.0, f64::max);
+
+    writeln!(stdio.stdout, "\x1B[93m{:<14} {:>10} {:>10}\x1B[0m", "STEP", "START(ms)", "TIME(ms)")?;
+    for timing in &timings {
+        let bar = (timing.duration / total * BAR_WIDTH) as usize;
+        writeln!(
+            stdio.stdout,
+            "{:<14} {:>10.2} {:>10.2} \x1B[36m{}\x1B[0m",
+            timing.name, timing.start * MS, timing.duration * MS, "#".repeat(bar)
+        )?;
+    }
+    writeln!(stdio.stdout, "total: {:.2} ms", total * MS)?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::api::{Error, ipc};
+use crate::api::io::Stdio;
+use crate::msg;
+
+/// Copies text, given or from the input, to the clipboard, prints it, or clears it.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        ["copy"] => {
+            match stdio.stdin.read_available() {
+                Some(input) => ipc::set_clipboard(input.as_bytes())?,
+                None => {
+                    writeln!(stdio.stderr, "clip: {}", msg!("clip.no_input"))?;
+                    return Err(Error::InvalidArgument);
+                }
+            }
+        }
+        ["copy", words @ ..] => ipc::set_clipboard(words.join(" ").as_bytes())?,
+        [] | ["paste"] => write!(stdio.stdout, "{}", String::from_utf8_lossy(&ipc::get_clipboard()))?,
+        ["clear"] => ipc::clear_clipboard()?,
+        _ => {
+            writeln!(stdio.stderr, "{}: clip [copy [TEXT...] | paste | clear]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::kernel::config;
+use crate::kernel::config::persistent;
+use crate::msg;
+
+/// Prints the settings, or sets one and writes the configuration file back.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [] => {
+            let mut res = Ok(());
+            config::for_each(
+                |key, value| {
+                    res = res.and_then(|_| writeln!(stdio.stdout, "{}={}", key, value.unwrap_or("\x1B[90m(default)\x1B[0m")));
+                }
+            );
+            res?;
+        }
+        [key] => {
+            if let Some(value) = config::get(key)? { writeln!(stdio.stdout, "{}", value)?; }
+        }
+        [key, value] => {
+            config::set(key, value)?;
+            persistent::store()?;
+        }
+        _ => {
+            writeln!(stdio.stderr, "{}: config [KEY [VALUE]]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
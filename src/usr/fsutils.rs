@@ -0,0 +1,295 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::api::{Error, fs};
+use crate::api::fs::{FileType, Metadata};
+use crate::api::io::Stdio;
+use crate::msg;
+
+// File Utilities
+//
+// The commands take absolute paths. When given several operands, they carry on past the ones that
+// fail, reporting each failure along with the offending path, and exit with a failure code at the end.
+
+//////////////
+// Commands
+//////////////
+
+/// Lists the entries of the given directories (or `/`), with their types and sizes when given `-l`.
+pub fn ls(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let (flags, mut paths) = parse(args, "l").map_err(|e| usage(stdio, "ls [-l] [PATH...]", e))?;
+    let long = flags.contains(&'l');
+    if paths.is_empty() { paths.push("/"); }
+
+    let show_headers = paths.len() > 1;
+    for_each_operand(stdio, "ls", &paths, |stdio, path| {
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_dir() {
+            return print_entry(stdio, path, metadata.file_type, metadata.size, long);
+        }
+
+        if show_headers { writeln!(stdio.stdout, "{}:", path)?; }
+        let entries = fs::read_dir(path)?;
+        if long {
+            for entry in &entries {
+                print_entry(stdio, &entry.name, entry.file_type, entry.size, true)?;
+            }
+        } else if !entries.is_empty() {
+            for entry in &entries {
+                write!(stdio.stdout, "{}  ", colorize(&entry.name, entry.file_type))?;
+            }
+            writeln!(stdio.stdout)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Prints the contents of the given files, or the input if none are given.
+pub fn cat(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    if args.is_empty() {
+        if let Some(input) = stdio.stdin.read_available() { write!(stdio.stdout, "{}", input)?; }
+        return Ok(());
+    }
+
+    for_each_operand(stdio, "cat", args, |stdio, path| {
+        let data = fs::read_file(path)?;
+        write!(stdio.stdout, "{}", String::from_utf8_lossy(&data))?;
+        Ok(())
+    })
+}
+
+/// Copies a file, or a directory tree when given `-r`, to the destination.
+pub fn cp(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let (flags, paths) = parse(args, "r").map_err(|e| usage(stdio, "cp [-r] SOURCE DEST", e))?;
+    let [source, dest] = paths[..] else { return Err(usage(stdio, "cp [-r] SOURCE DEST", Error::InvalidArgument)); };
+
+    let recursive = flags.contains(&'r');
+    for_each_operand(stdio, "cp", &[source], |_, source| {
+        let dest = destination(source, dest)?;
+        copy(source, &dest, recursive)
+    })
+}
+
+/// Moves a file or directory tree to the destination.
+pub fn mv(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let [source, dest] = args[..] else { return Err(usage(stdio, "mv SOURCE DEST", Error::InvalidArgument)); };
+
+    for_each_operand(stdio, "mv", &[source], |_, source| {
+        let source = fs::canonicalize(source)?;
+        let dest = destination(&source, dest)?;
+        if source == dest { return Ok(()); }
+        // Moving a directory into itself would copy forever.
+        if dest.starts_with(&format!("{}/", source)) { return Err(Error::InvalidArgument); }
+
+        copy(&source, &dest, true)?;
+        remove(&source, true)
+    })
+}
+
+/// Removes the given files, and directory trees when given `-r`.
+pub fn rm(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let (flags, paths) = parse(args, "r").map_err(|e| usage(stdio, "rm [-r] PATH...", e))?;
+    if paths.is_empty() { return Err(usage(stdio, "rm [-r] PATH...", Error::InvalidArgument)); }
+
+    let recursive = flags.contains(&'r');
+    for_each_operand(stdio, "rm", &paths, |_, path| remove(path, recursive))
+}
+
+/// Creates the given directories, along with missing parents when given `-p`.
+pub fn mkdir(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let (flags, paths) = parse(args, "p").map_err(|e| usage(stdio, "mkdir [-p] PATH...", e))?;
+    if paths.is_empty() { return Err(usage(stdio, "mkdir [-p] PATH...", Error::InvalidArgument)); }
+
+    let parents = flags.contains(&'p');
+    for_each_operand(stdio, "mkdir", &paths, |_, path| {
+        if !parents { return fs::create_dir(path); }
+
+        let path = fs::canonicalize(path)?;
+        let mut prefix = String::new();
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            prefix.push('/');
+            prefix.push_str(component);
+            match fs::metadata(&prefix) {
+                Ok(metadata) if metadata.is_dir() => {}
+                Ok(_) => return Err(Error::NotADirectory),
+                Err(_) => fs::create_dir(&prefix)?,
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Creates the given files if they do not exist.
+pub fn touch(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    if args.is_empty() { return Err(usage(stdio, "touch PATH...", Error::InvalidArgument)); }
+
+    for_each_operand(stdio, "touch", args, |_, path| fs::append_file(path, &[]))
+}
+
+/// Prints the metadata of the given files and directories.
+pub fn stat(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    if args.is_empty() { return Err(usage(stdio, "stat PATH...", Error::InvalidArgument)); }
+
+    for_each_operand(stdio, "stat", args, |stdio, path| {
+        let Metadata { file_type, size, read_only } = fs::metadata(path)?;
+        writeln!(stdio.stdout, "  File: {}", fs::canonicalize(path)?)?;
+        writeln!(stdio.stdout, "  Type: {}", file_type.as_str())?;
+        writeln!(stdio.stdout, "  Size: {} ({})", size, human_size(size))?;
+        writeln!(stdio.stdout, "  Mode: {}", if read_only { "read-only" } else { "read-write" })?;
+        Ok(())
+    })
+}
+
+///////////////
+// Utilities
+///////////////
+
+/// Splits the arguments into single-letter flags (out of those allowed) and operands.
+fn parse<'a>(args: &[&'a str], allowed: &str) -> Result<(Vec<char>, Vec<&'a str>), Error> {
+    let mut flags = Vec::new();
+    let mut operands = Vec::new();
+
+    for arg in args {
+        match arg.strip_prefix('-') {
+            Some(letters) if !letters.is_empty() => {
+                for flag in letters.chars() {
+                    if !allowed.contains(flag) { return Err(Error::InvalidArgument); }
+                    flags.push(flag);
+                }
+            }
+            _ => operands.push(*arg),
+        }
+    }
+
+    Ok((flags, operands))
+}
+
+/// Prints the usage of the command and returns the given error.
+fn usage(stdio: &mut Stdio, usage: &str, e: Error) -> Error {
+    writeln!(stdio.stderr, "{}: {}", msg!("usage"), usage).ok();
+    e
+}
+
+/// Runs the operation on each operand, reporting failures as `<command>: <path>: <error>`.
+fn for_each_operand(
+    stdio: &mut Stdio,
+    command: &str,
+    operands: &[&str],
+    mut f: impl FnMut(&mut Stdio, &str) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut failed = false;
+    for operand in operands {
+        if let Err(e) = f(stdio, operand) {
+            writeln!(stdio.stderr, "{}: {}: {}", command, operand, e)?;
+            failed = true;
+        }
+    }
+
+    if failed { Err(Error::Failed) } else { Ok(()) }
+}
+
+/// Returns the path to copy or move the source to; into the destination if it is a directory.
+fn destination(source: &str, dest: &str) -> Result<String, Error> {
+    let dest = fs::canonicalize(dest)?;
+    match fs::metadata(&dest) {
+        Ok(metadata) if metadata.is_dir() => {
+            let source = fs::canonicalize(source)?;
+            let name = source.rsplit('/').next().unwrap_or_default();
+            if name.is_empty() { return Err(Error::InvalidArgument); }
+            Ok(if dest == "/" { format!("/{}", name) } else { format!("{}/{}", dest, name) })
+        }
+        _ => Ok(dest),
+    }
+}
+
+/// Copies the file, or the directory tree if `recursive` is set.
+fn copy(source: &str, dest: &str, recursive: bool) -> Result<(), Error> {
+    if !fs::metadata(source)?.is_dir() { return fs::write_file(dest, &fs::read_file(source)?); }
+    if !recursive { return Err(Error::IsADirectory); }
+
+    if !fs::exists(dest) { fs::create_dir(dest)?; }
+    for entry in fs::read_dir(source)? {
+        copy(&join(source, &entry.name), &join(dest, &entry.name), true)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the file, or the directory tree if `recursive` is set.
+fn remove(path: &str, recursive: bool) -> Result<(), Error> {
+    if fs::metadata(path)?.is_dir() {
+        if !recursive { return Err(Error::IsADirectory); }
+        for entry in fs::read_dir(path)? {
+            remove(&join(path, &entry.name), true)?;
+        }
+    }
+
+    fs::remove(path)
+}
+
+/// Joins a directory and a name into a path.
+fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') { format!("{}{}", dir, name) } else { format!("{}/{}", dir, name) }
+}
+
+/// Prints a single entry, with its type and size when `long` is set.
+fn print_entry(stdio: &mut Stdio, name: &str, file_type: FileType, size: usize, long: bool) -> Result<(), Error> {
+    if long {
+        let kind = if file_type == FileType::Directory { 'd' } else { '-' };
+        let size = if file_type == FileType::Directory { String::from("-") } else { human_size(size) };
+        writeln!(stdio.stdout, "{} {:>9}  {}", kind, size, colorize(name, file_type))?;
+    } else {
+        writeln!(stdio.stdout, "{}", colorize(name, file_type))?;
+    }
+
+    Ok(())
+}
+
+/// Wraps the name in the color of its file type.
+fn colorize(name: &str, file_type: FileType) -> String {
+    match file_type {
+        FileType::Directory => format!("\x1B[94m{}/\x1B[0m", name),
+        FileType::File => String::from(name),
+    }
+}
+
+/// Returns the size in bytes, or with a binary prefix if larger than a kibibyte.
+fn human_size(size: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if size < 1024 { return format!("{} B", size); }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -0,0 +1,63 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::{Error, fs};
+use crate::api::io::Stdio;
+use crate::kernel::compress::gzip;
+use crate::msg;
+
+/// Decompresses the given `.gz` files, replacing each with its contents unless given `-k`.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let (keep, paths) = match args {
+        ["-k", paths @ ..] => (true, paths),
+        paths => (false, paths),
+    };
+    if paths.is_empty() || paths.iter().any(|path| path.starts_with('-')) {
+        writeln!(stdio.stderr, "{}: gunzip [-k] FILE...", msg!("usage"))?;
+        return Err(Error::InvalidArgument);
+    }
+
+    let mut failed = false;
+    for path in paths {
+        if let Err(e) = decompress(path, keep) {
+            writeln!(stdio.stderr, "gunzip: {}: {}", path, e)?;
+            failed = true;
+        }
+    }
+
+    if failed { Err(Error::Failed) } else { Ok(()) }
+}
+
+/// Decompresses the file next to it, under its name without the suffix.
+fn decompress(path: &str, keep: bool) -> Result<(), Error> {
+    let target = path.strip_suffix(".gz").filter(|target| !target.is_empty() && !target.ends_with('/'));
+    let target = target.ok_or(Error::InvalidArgument)?;
+    if fs::exists(target) { return Err(Error::AlreadyExists); }
+
+    let data = gzip::decompress(&fs::read_file(path)?)?;
+    fs::write_file(target, &data)?;
+    if !keep { fs::remove(path)?; }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::kernel::allocator;
+use crate::kernel::allocator::Strategy;
+use crate::kernel::memory::pressure;
+use crate::msg;
+
+/// Prints the heap usage of each strategy, along with the outstanding allocations of each tag if the
+/// heap is debugged.
+pub fn main(_args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    const KIB: usize = 1024;
+
+    let used = allocator::used();
+    writeln!(stdio.stdout, "strategy: {}", allocator::strategy().as_str())?;
+    writeln!(stdio.stdout, "pool:     {} of {} KiB used", used / KIB, allocator::HEAP_SIZE / KIB)?;
+    let pressure = pressure::stats();
+    writeln!(stdio.stdout, "pressure: {} low memory events, {} KiB reclaimed", pressure.events, pressure.reclaimed / KIB)?;
+
+    writeln!(
+        stdio.stdout,
+        "\x1B[93m{:<12} {:>8} {:>8} {:>8} {:>10} {:>10}\x1B[0m",
+        "STRATEGY", "ALLOCS", "FREES", "FAILED", "BYTES", "PEAK"
+    )?;
+    for idx in 0..allocator::STRATEGY_COUNT as u8 {
+        let strategy = match Strategy::from_index(idx) {
+            Some(strategy) => strategy,
+            None => continue,
+        };
+        let stats = allocator::stats(strategy);
+        writeln!(
+            stdio.stdout,
+            "{:<12} {:>8} {:>8} {:>8} {:>10} {:>10}",
+            strategy.as_str(), stats.allocations, stats.frees, stats.failures, stats.bytes, stats.peak
+        )?;
+    }
+
+    report(stdio)
+}
+
+/// Prints the outstanding allocations of each tag.
+#[cfg(feature = "heap-debug")]
+fn report(stdio: &mut Stdio) -> Result<(), Error> {
+    use crate::kernel::allocator::debug;
+
+    writeln!(stdio.stdout, "\x1B[93m{:<12} {:>8} {:>10}\x1B[0m", "TAG", "COUNT", "BYTES")?;
+    let mut res = Ok(());
+    debug::for_each_tag(
+        |usage| {
+            res = res.and_then(|_| writeln!(stdio.stdout, "{:<12} {:>8} {:>10}", usage.tag, usage.count, usage.bytes));
+        }
+    );
+    res?;
+    if debug::has_overflowed() {
+        writeln!(stdio.stdout, "\x1B[33m{}\x1B[0m", msg!("heap.partly_untracked"))?;
+    }
+
+    Ok(())
+}
+
+/// Prints the outstanding allocations of each tag.
+#[cfg(not(feature = "heap-debug"))]
+fn report(stdio: &mut Stdio) -> Result<(), Error> {
+    writeln!(stdio.stdout, "{}", msg!("heap.untracked"))?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::str::FromStr;
+
+use crate::api::Error;
+use crate::api::keyboard;
+use crate::api::keyboard::{Key, KeyCode, Layout};
+use crate::api::io::Stdio;
+use crate::kernel::config;
+use crate::kernel::config::persistent;
+use crate::msg;
+
+/// Prints or sets the keyboard layout, prints, changes or clears the remapped keys, or prints or selects the
+/// scancode set.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [] => writeln!(stdio.stdout, "{}", keyboard::get_layout().as_str())?,
+        ["remap"] => {
+            for (from, to) in keyboard::get_remap() {
+                writeln!(stdio.stdout, "{} -> {}", Key::name(from), Key::name(to))?;
+            }
+        }
+        ["set", "remap", from, to] => {
+            let (from, to) = (Key::from_name(from)?, Key::from_name(to)?);
+            let mut remap = keyboard::get_remap();
+            remap.retain(|(key, _)| *key != from);
+            remap.push((from, to));
+            store_remap(&remap)?;
+        }
+        ["reset", "remap"] => store_remap(&[])?,
+        ["scancodes"] => writeln!(stdio.stdout, "{}", keyboard::get_scancodes().as_str())?,
+        ["scancodes", scancodes] => {
+            config::set("scancodes", scancodes)?;
+            persistent::store()?;
+        }
+        ["reset"] => keyboard::reset_layout(),
+        [layout] => keyboard::set_layout(Layout::from_str(layout)?),
+        _ => {
+            writeln!(
+                stdio.stderr,
+                "{}: kbd [azerty | dvorak | qwerty | reset | remap | set remap FROM TO | reset remap | scancodes [auto | set1 | set2]]",
+                msg!("usage")
+            )?;
+            return Err(Error::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
+
+/// Puts the remapping into effect through the configuration, and writes the configuration file back.
+fn store_remap(remap: &[(KeyCode, KeyCode)]) -> Result<(), Error> {
+    let value = remap.iter().map(|&(from, to)| [Key::name(from), Key::name(to)].join(":")).collect::<Vec<String>>();
+    config::set("key_remap", &value.join(","))?;
+    persistent::store()
+}
@@ -0,0 +1,58 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::api::process;
+use crate::api::process::{Pid, Signal};
+use crate::msg;
+
+/// Sends a signal, `KILL` unless given, to the given processes, or lists the signals.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let (signal, pids) = match args {
+        ["-l"] => {
+            for signal in Signal::ALL {
+                writeln!(stdio.stdout, "{:>2} {}", signal.as_u8(), signal.as_str())?;
+            }
+            return Ok(());
+        }
+        [flag, pids @ ..] if flag.starts_with('-') && !pids.is_empty() => (flag[1..].parse::<Signal>()?, pids),
+        [pids @ ..] if !pids.is_empty() && !pids[0].starts_with('-') => (Signal::Kill, pids),
+        _ => {
+            writeln!(stdio.stderr, "{}: kill [-l | [-SIGNAL] PID...]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    };
+
+    let mut res = Ok(());
+    for pid in pids {
+        let pid = pid.parse::<u64>().map_err(|_| Error::InvalidArgument)?;
+        if let Err(e) = process::kill(Pid::from_u64(pid), signal) {
+            writeln!(stdio.stderr, "kill: {}: {}", pid, e)?;
+            res = Err(Error::Failed);
+        }
+    }
+
+    res
+}
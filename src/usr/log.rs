@@ -0,0 +1,133 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::str::FromStr;
+
+use crate::api::{Error, system};
+use crate::api::io::Stdio;
+use crate::aux::logger;
+use crate::aux::logger::{LogLevel, Record, Sink};
+use crate::devices::console;
+use crate::kernel::config;
+use crate::msg;
+
+/// Shows or changes the log level, sinks, theme, and filter, or prints the latest messages.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [] => {
+            writeln!(stdio.stdout, "level: {}", logger::get_log_level().as_str())?;
+            writeln!(stdio.stdout, "theme: {}", logger::get_theme().as_str())?;
+            write!(stdio.stdout, "sinks:")?;
+            for sink in Sink::ALL.iter().filter(|sink| logger::is_sink_enabled(**sink)) {
+                write!(stdio.stdout, " {}", sink.as_str())?;
+            }
+            writeln!(stdio.stdout)?;
+            writeln!(stdio.stdout, "filter: {}", if logger::is_filter_enabled() { "on" } else { "off" })?;
+        }
+        ["level"] => writeln!(stdio.stdout, "{}", logger::get_log_level().as_str())?,
+        ["level", level] => config::set("log_level", level)?,
+        ["theme"] => writeln!(stdio.stdout, "{}", logger::get_theme().as_str())?,
+        ["theme", theme] => config::set("log_theme", theme)?,
+        ["sink", sink, state] => {
+            let sink = Sink::from_str(sink)?;
+            match *state {
+                "on" => logger::enable_sink(sink),
+                "off" => logger::disable_sink(sink),
+                _ => return Err(Error::InvalidArgument),
+            }
+        }
+        ["filter", state] => {
+            match *state {
+                "on" => logger::enable_filter(),
+                "off" => logger::disable_filter(),
+                _ => return Err(Error::InvalidArgument),
+            }
+        }
+        ["show"] => show(stdio, usize::MAX)?,
+        ["show", "-n", count] => show(stdio, count.parse::<usize>().map_err(|_| Error::InvalidArgument)?)?,
+        ["follow"] => follow(stdio)?,
+        _ => {
+            writeln!(stdio.stderr, "{}: log [level [LEVEL] | theme [THEME] | sink SINK on|off | filter on|off | show [-n COUNT] | follow]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the last `count` messages in the ring buffer.
+fn show(stdio: &mut Stdio, count: usize) -> Result<(), Error> {
+    let (records, _) = records_since(0);
+    for record in records.iter().skip(records.len().saturating_sub(count)) {
+        print_record(stdio, record)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the messages as they are logged, until a key is pressed.
+fn follow(stdio: &mut Stdio) -> Result<(), Error> {
+    const POLL_INTERVAL: f64 = 0.1;
+
+    let (_, mut next) = records_since(0);
+    if !stdio.stdin.is_interactive() { return Ok(()); }
+
+    console::disable_echo();
+    console::enable_raw();
+    let mut res = Ok(());
+    while res.is_ok() && console::try_read_char().is_none() {
+        let (records, since) = records_since(next);
+        res = records.iter().try_for_each(|record| print_record(stdio, record));
+        next = since;
+        system::sleep(POLL_INTERVAL);
+    }
+    console::disable_raw();
+    console::enable_echo();
+
+    res
+}
+
+/// Returns a copy of the messages starting at the given sequence number, and the next one.
+fn records_since(since: u64) -> (Vec<Record>, u64) {
+    let mut records = Vec::new();
+    let next = logger::for_each_record(since, |record| records.push(*record));
+    (records, next)
+}
+
+/// Prints a message along with its uptime and log level.
+fn print_record(stdio: &mut Stdio, record: &Record) -> Result<(), Error> {
+    let theme = logger::get_theme();
+    match record.uptime() {
+        Some(uptime) => write!(stdio.stdout, "{}[{:13.4}]{} ", theme.accent(), uptime, theme.reset())?,
+        None => write!(stdio.stdout, "{}[--------.----]{} ", theme.style(LogLevel::Failure), theme.reset())?,
+    }
+    let log_level = record.log_level();
+    write!(stdio.stdout, "{}{:<7}{} {}", theme.style(log_level), log_level.as_str(), theme.reset(), record.message())?;
+    match record.repeats() {
+        0 => writeln!(stdio.stdout)?,
+        repeats => writeln!(stdio.stdout, " (repeated {} times)", repeats)?,
+    }
+
+    Ok(())
+}
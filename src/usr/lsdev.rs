@@ -0,0 +1,56 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::aux::logger;
+use crate::aux::logger::LogLevel;
+use crate::kernel::dev;
+use crate::kernel::dev::Status;
+
+/// Lists the registered devices along with their status.
+pub fn main(_args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let theme = logger::get_theme();
+    writeln!(stdio.stdout, "{}NAME         CLASS      STATUS     POWER{}", theme.accent(), theme.reset())?;
+
+    let mut res = Ok(());
+    dev::for_each(
+        |info| {
+            let color = match info.status {
+                Status::Active => theme.style(LogLevel::Success),
+                Status::Failed => theme.style(LogLevel::Failure),
+                Status::Pending | Status::Suspended => theme.style(LogLevel::Warning),
+                Status::Absent => theme.reset(),
+            };
+            let power = if info.has_power_hooks { "yes" } else { "-" };
+            res = res.and_then(|_| writeln!(
+                stdio.stdout,
+                "{:<12} {:<10} {}{:<10}{} {}",
+                info.name, info.class.as_str(), color, info.status.as_str(), theme.reset(), power
+            ));
+        }
+    );
+
+    Ok(res?)
+}
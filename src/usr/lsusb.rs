@@ -0,0 +1,54 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::format;
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::api::usb;
+use crate::aux::logger;
+
+/// Lists the USB devices along with the drivers bound to them.
+pub fn main(_args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let theme = logger::get_theme();
+    writeln!(stdio.stdout, "{}PORT   ADDR ID        SPEED  CLASS    DRIVER{}", theme.accent(), theme.reset())?;
+
+    for info in usb::devices() {
+        let (class, subclass, protocol) = info.class;
+        writeln!(
+            stdio.stdout,
+            "{:<6} {:<4} {:04x}:{:04x} {:<6} {:02x}:{:02x}:{:02x} {}",
+            format!("{}-{}", info.controller, info.port),
+            info.address,
+            info.vendor,
+            info.product,
+            info.speed.as_str(),
+            class,
+            subclass,
+            protocol,
+            info.driver.unwrap_or("-")
+        )?;
+    }
+
+    Ok(())
+}
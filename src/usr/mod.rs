@@ -0,0 +1,114 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::api;
+use crate::api::process::Command;
+
+pub mod acpi;
+pub mod boottime;
+pub mod clip;
+pub mod config;
+pub mod date;
+pub mod env;
+pub mod fsutils;
+pub mod grep;
+pub mod gunzip;
+pub mod heap;
+pub mod kbd;
+pub mod kill;
+pub mod log;
+pub mod lsdev;
+pub mod lsusb;
+pub mod mount;
+pub mod mq;
+pub mod power;
+pub mod serial;
+pub mod sha256sum;
+pub mod shell;
+pub mod snake;
+pub mod sudo;
+pub mod sync;
+pub mod sysinfo;
+pub mod top;
+pub mod trace;
+pub mod umount;
+pub mod view;
+
+/////////////
+// Globals
+/////////////
+
+/// Directory in which the built-in commands reside.
+pub const BIN_DIR: &str = "/bin";
+
+/// Available commands.
+pub const COMMANDS: [(&str, Command); 35] = [
+    ("acpi", acpi::main),
+    ("boottime", boottime::main),
+    ("cat", fsutils::cat),
+    ("clip", clip::main),
+    ("config", config::main),
+    ("cp", fsutils::cp),
+    ("date", date::main),
+    ("env", env::main),
+    ("grep", grep::main),
+    ("gunzip", gunzip::main),
+    ("heap", heap::main),
+    ("kbd", kbd::main),
+    ("kill", kill::main),
+    ("log", log::main),
+    ("ls", fsutils::ls),
+    ("lsdev", lsdev::main),
+    ("lsusb", lsusb::main),
+    ("mkdir", fsutils::mkdir),
+    ("mount", mount::main),
+    ("mq", mq::main),
+    ("mv", fsutils::mv),
+    ("power", power::main),
+    ("rm", fsutils::rm),
+    ("serial", serial::main),
+    ("sha256sum", sha256sum::main),
+    ("snake", snake::main),
+    ("stat", fsutils::stat),
+    ("sudo", sudo::main),
+    ("sync", sync::main),
+    ("sysinfo", sysinfo::main),
+    ("top", top::main),
+    ("touch", fsutils::touch),
+    ("trace", trace::main),
+    ("umount", umount::main),
+    ("view", view::main),
+];
+
+/// Returns the entry point of the named command.
+pub fn lookup(name: &str) -> Option<Command> {
+    COMMANDS.iter().find(|(n, _)| *n == name).map(|(_, command)| *command)
+}
+
+/// Resolves the given name to a command, searching the directories listed in `PATH` unless the name
+/// contains a `/`.
+pub fn resolve(name: &str) -> Option<Command> {
+    match name.rsplit_once('/') {
+        Some((dir, name)) => if dir == BIN_DIR { lookup(name) } else { None },
+        None => api::env::search_path().iter().any(|dir| dir == BIN_DIR).then(|| lookup(name)).flatten(),
+    }
+}
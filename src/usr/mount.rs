@@ -0,0 +1,74 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::fs;
+use crate::api::io::Stdio;
+use crate::msg;
+
+/// Lists the mounts, or mounts `SOURCE` at `TARGET`.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    if args.is_empty() { return list(stdio); }
+
+    let mut read_only = false;
+    let mut fs_type = None;
+    let mut operands = [""; 2];
+    let mut count = 0;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-r" => read_only = true,
+            "-t" => fs_type = Some(*args.next().ok_or(Error::InvalidArgument)?),
+            operand if count < operands.len() => {
+                operands[count] = operand;
+                count += 1;
+            }
+            _ => return Err(usage(stdio)),
+        }
+    }
+    if count != operands.len() { return Err(usage(stdio)); }
+
+    let [source, target] = operands;
+    fs::mount(source, target, fs_type, read_only)
+}
+
+/// Prints the mounts, one per line.
+fn list(stdio: &mut Stdio) -> Result<(), Error> {
+    let mut res = Ok(());
+    fs::for_each_mount(
+        |info| {
+            let mode = if info.read_only { "ro" } else { "rw" };
+            res = res.and_then(|_| writeln!(stdio.stdout, "{} on {} type {} ({})", info.source, info.path, info.fs_type, mode));
+        }
+    );
+
+    Ok(res?)
+}
+
+/// Prints the usage and returns the matching error.
+fn usage(stdio: &mut Stdio) -> Error {
+    writeln!(stdio.stderr, "{}: mount [-r] [-t TYPE] SOURCE TARGET", msg!("usage")).ok();
+    Error::InvalidArgument
+}
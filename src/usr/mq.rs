@@ -0,0 +1,65 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::api::{Error, ipc};
+use crate::api::io::Stdio;
+use crate::msg;
+
+/// Lists, creates, removes, or sends to and receives from message queues.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [] => {
+            let mut res = Ok(());
+            ipc::for_each_queue(
+                |info| res = res.and_then(|_| writeln!(stdio.stdout, "{:<32} {}/{}", info.name, info.len, info.capacity))
+            );
+            res?;
+        }
+        ["create", name] => { ipc::create_queue(name, ipc::DEFAULT_CAPACITY)?; }
+        ["create", name, capacity] => {
+            let capacity = capacity.parse::<usize>().map_err(|_| Error::InvalidArgument)?;
+            ipc::create_queue(name, capacity)?;
+        }
+        ["rm", name] => ipc::unlink_queue(name)?,
+        ["send", name, words @ ..] if !words.is_empty() => {
+            ipc::open_queue(name)?.send(words.join(" ").as_bytes())?;
+        }
+        ["recv", name] => {
+            match ipc::open_queue(name)?.try_receive() {
+                Some(message) => writeln!(stdio.stdout, "{}", String::from_utf8_lossy(&message))?,
+                None => {
+                    writeln!(stdio.stderr, "mq: {}", msg!("mq.empty", name))?;
+                    return Err(Error::Failed);
+                }
+            }
+        }
+        _ => {
+            writeln!(stdio.stderr, "{}: mq [create NAME [CAPACITY] | rm NAME | send NAME MESSAGE | recv NAME]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
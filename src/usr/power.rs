@@ -0,0 +1,73 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+use core::str::FromStr;
+
+use crate::api::{Error, system};
+use crate::api::io::Stdio;
+use crate::api::system::Policy;
+use crate::msg;
+
+/// Prints the CPU frequencies and the idle policy, or sets the policy.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [] => status(stdio),
+        [policy] => {
+            let policy = Policy::from_str(policy)?;
+            system::set_idle_policy(policy)?;
+            if policy == Policy::Powersave && !system::has_mwait() {
+                writeln!(stdio.stderr, "power: {}", msg!("power.no_mwait"))?;
+            }
+            Ok(())
+        }
+        _ => {
+            writeln!(stdio.stderr, "{}: power [performance | powersave]", msg!("usage"))?;
+            Err(Error::InvalidArgument)
+        }
+    }
+}
+
+/// Prints the CPU frequencies and the idle policy.
+fn status(stdio: &mut Stdio) -> Result<(), Error> {
+    let frequency = system::cpu_frequency();
+    let mhz = |value: Option<u32>| value.map_or_else(|| String::from("unknown"), |mhz| format!("{} MHz", mhz));
+
+    writeln!(stdio.stdout, "base frequency: {}", mhz(frequency.base))?;
+    writeln!(stdio.stdout, "max frequency:  {}", mhz(frequency.max))?;
+    writeln!(
+        stdio.stdout,
+        "tsc frequency:  {}{}",
+        mhz(frequency.tsc),
+        if frequency.tsc_measured && frequency.tsc.is_some() { " (measured)" } else { "" }
+    )?;
+    writeln!(
+        stdio.stdout,
+        "idle policy:    {} ({})",
+        system::idle_policy().as_str(),
+        if system::has_mwait() { "mwait available" } else { "hlt only" }
+    )?;
+
+    Ok(())
+}
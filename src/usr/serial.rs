@@ -0,0 +1,78 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+use core::str::FromStr;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::api::serial;
+use crate::api::serial::{Com, Parity};
+use crate::msg;
+
+/// Lists the serial ports, changes the line settings of one, picks the port of the remote console or the logger,
+/// or shows or changes whether their output is multiplexed.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [] => {
+            for com in Com::ALL.iter().copied().filter(|com| serial::is_present(*com)) {
+                let config = serial::get_config(com)?;
+                write!(
+                    stdio.stdout,
+                    "{} {:#x} {} {}",
+                    com.as_str(),
+                    serial::get_base(com),
+                    config.baud,
+                    config.parity.as_str()
+                )?;
+                if serial::get_console() == com { write!(stdio.stdout, " console")?; }
+                if serial::get_log_port() == com { write!(stdio.stdout, " log")?; }
+                writeln!(stdio.stdout)?;
+            }
+        }
+        ["mux"] => writeln!(stdio.stdout, "{}", if serial::is_mux_enabled() { "on" } else { "off" })?,
+        ["mux", "on"] => serial::enable_mux(),
+        ["mux", "off"] => serial::disable_mux(),
+        ["console", com] => serial::set_console(Com::from_str(com)?)?,
+        ["log", com] => serial::set_log_port(Com::from_str(com)?)?,
+        [com, "baud", baud] => {
+            let com = Com::from_str(com)?;
+            let baud = baud.parse().map_err(|_| Error::InvalidArgument)?;
+            serial::set_config(com, serial::Config { baud, ..serial::get_config(com)? })?;
+        }
+        [com, "parity", parity] => {
+            let com = Com::from_str(com)?;
+            let parity = Parity::from_str(parity)?;
+            serial::set_config(com, serial::Config { parity, ..serial::get_config(com)? })?;
+        }
+        _ => {
+            writeln!(
+                stdio.stderr,
+                "{}: serial [console PORT | log PORT | mux [on|off] | PORT baud RATE | PORT parity none|odd|even|mark|space]",
+                msg!("usage")
+            )?;
+            return Err(Error::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::{Error, fs};
+use crate::api::fs::OpenMode;
+use crate::api::io::Stdio;
+use crate::kernel::crypto::sha256;
+use crate::kernel::crypto::sha256::Sha256;
+
+/// Prints the SHA-256 digest of the given files, or of the input if none are given.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    if args.is_empty() {
+        let input = stdio.stdin.read_available().unwrap_or_default();
+        print_digest(stdio, &sha256::digest(input.as_bytes()), "-")?;
+        return Ok(());
+    }
+
+    let mut failed = false;
+    for path in args {
+        match hash_file(path) {
+            Ok(digest) => print_digest(stdio, &digest, path)?,
+            Err(e) => {
+                writeln!(stdio.stderr, "sha256sum: {}: {}", path, e)?;
+                failed = true;
+            }
+        }
+    }
+
+    if failed { Err(Error::Failed) } else { Ok(()) }
+}
+
+/// Returns the digest of the file, reading it a chunk at a time.
+fn hash_file(path: &str) -> Result<[u8; sha256::DIGEST_SIZE], Error> {
+    const CHUNK_SIZE: usize = 512;
+
+    let fd = fs::open(path, OpenMode::Read)?;
+    let mut sha = Sha256::new();
+    let mut buf = [0u8; CHUNK_SIZE];
+    let res = loop {
+        match fs::read(fd, &mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(len) => sha.update(&buf[..len]),
+            Err(e) => break Err(e),
+        }
+    };
+    fs::close(fd)?;
+
+    res.map(|_| sha.finish())
+}
+
+/// Prints the digest in hexadecimal, followed by the name of what was hashed.
+fn print_digest(stdio: &mut Stdio, digest: &[u8], name: &str) -> Result<(), Error> {
+    for byte in digest {
+        write!(stdio.stdout, "{:02x}", byte)?;
+    }
+    writeln!(stdio.stdout, "  {}", name)?;
+
+    Ok(())
+}
@@ -0,0 +1,733 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use spin::Mutex;
+
+use crate::api::{env, fs, process, system};
+use crate::api::Error;
+use crate::api::io::{Stderr, Stdin, Stdio, Stdout};
+use crate::api::process::{Action, Capabilities, ExitCode, Pid, Signal};
+use crate::aux::logger;
+use crate::aux::logger::LogLevel;
+use crate::devices::console;
+use crate::kernel::{pipe, signal};
+use crate::msg;
+
+// Shell
+//
+// A line first has its aliases expanded (see below), then its variables: `$NAME` and `${NAME}` are
+// replaced by the value of the environment variable, `$?` by the exit code of the previous command,
+// and `\$` stands for a literal dollar sign. It is then split on whitespace into a command name and
+// its arguments. Commands are resolved through `PATH` and run in the foreground, unless the line ends
+// with `&`, in which case the command is spawned as a background process and the prompt returns
+// immediately. Background processes are deferred rather than concurrent (see `kernel::process`): one
+// runs the next time the shell waits, be it for input, `sleep` or `wait`, and the shell only carries on
+// once it has exited. Background processes that have exited are reported right before the next prompt.
+//
+// Commands may be chained with `|`, in which case the output of each command is fed to the next one
+// through a pipe. The output of the last command may be redirected to a file with `> path`, which
+// replaces its contents, or `>> path`, which appends to them.
+//
+// The prompt is taken from the `PROMPT` variable.
+//
+// Aliases
+//
+// `alias name='command args'` makes `name` stand for the given text wherever it is the first word of a
+// command, and `unalias name` forgets it. An alias may refer to other aliases, but not to itself, even
+// indirectly: a name that is already being expanded is left as is, so `alias ls='ls /'` works. Aliases
+// are written to `/etc/aliases` whenever they change, and read back when the shell starts.
+//
+// History
+//
+// Lines typed at the prompt are recorded and listed, numbered, by `history`. Before anything else, a
+// typed line has `!!` replaced by the previous line and `!n` by the line numbered `n`, and `\!` stands
+// for a literal exclamation mark; the line is echoed back whenever it changed. The last `HISTSIZE`
+// lines are kept (see the `history_size` setting) and written to `/home/.history` after each command
+// when that directory is writable, from which they are read back when the shell starts.
+//
+// The shell owns the console, so Ctrl+C raises `INT` on it. The shell itself ignores the signal, and a
+// command running in the foreground may check for it to stop early; `sleep` does.
+//
+// Scripts
+//
+// A script is a file with one command per line, run with `run <path>`. Everything following a `#` at
+// the start of a word is a comment. Commands may be grouped in conditional blocks:
+//
+//     if [command]
+//         ...
+//     else
+//         ...
+//     end
+//
+// If a command is given, it is executed and the first branch is taken when it succeeds; a bare `if`
+// tests the exit code of the previous command instead. Blocks may be nested, and `else` is optional.
+//
+// The script at `/boot/init.sh`, if present, is run when the shell starts. It runs with all the
+// capabilities (see `kernel::capability`), which the shell drops right after; commands typed by the
+// user need `sudo` to regain them.
+
+////////////////////
+// Configurations
+////////////////////
+
+/// Prompt used when `PROMPT` is unset.
+const DEFAULT_PROMPT: &str = "$ ";
+
+/// Script run when the shell starts.
+const INIT_SCRIPT: &str = "/boot/init.sh";
+
+/// Exit code of a command that could not be found.
+const NOT_FOUND: ExitCode = ExitCode::from_u8(127);
+
+/// Directory the aliases file is kept in.
+const ALIASES_DIR: &str = "/etc";
+/// File the aliases are kept in.
+const ALIASES_PATH: &str = "/etc/aliases";
+
+/// Directory the history file is kept in.
+const HISTORY_DIR: &str = "/home";
+/// File the history is kept in.
+const HISTORY_PATH: &str = "/home/.history";
+
+/// Number of lines kept in the history when `HISTSIZE` is unset.
+const DEFAULT_HISTORY_SIZE: usize = 500;
+
+////////////
+// States
+////////////
+
+/// Exit code of the last command.
+static LAST_EXIT_CODE: AtomicU8 = AtomicU8::new(0);
+
+/////////////
+// Mutexes
+/////////////
+
+/// Text each alias stands for, by name.
+static ALIASES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Lines typed at the prompt, oldest first.
+static HISTORY: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Runs the shell.
+pub async fn main() {
+    signal::claim_foreground().ok();
+    process::set_signal_action(Signal::Int, Action::Ignore).ok();
+
+    load_aliases();
+    if fs::exists(INIT_SCRIPT) {
+        exec(&format!("run {}", INIT_SCRIPT)).await;
+    }
+    // Only the init script runs with the capabilities of the kernel; the user has to ask for them.
+    process::restrict_capabilities(Capabilities::NONE);
+    load_history();
+
+    loop {
+        report_exited();
+        write!(Stdout::console(), "{}", env::get("PROMPT").as_deref().unwrap_or(DEFAULT_PROMPT)).ok();
+        let line = console::next_line().await;
+        let line = match expand_history(&line) {
+            Ok(expanded) => {
+                if expanded != line { writeln!(Stdout::console(), "{}", expanded).ok(); }
+                expanded
+            }
+            Err(event) => {
+                report(format_args!("shell: {}: event not found\n", event));
+                continue;
+            }
+        };
+
+        record(&line);
+        exec(&line).await;
+        save_history();
+    }
+}
+
+/// Returns the exit code of the last command.
+pub fn last_exit_code() -> ExitCode { ExitCode::from_u8(LAST_EXIT_CODE.load(Ordering::Relaxed)) }
+
+/// Executes a command line and returns its exit code.
+///
+/// Note: The future is boxed because running a script executes command lines recursively.
+pub fn exec(line: &str) -> Pin<Box<dyn Future<Output=ExitCode> + '_>> {
+    Box::pin(async move {
+        let code = exec_line(line).await;
+        LAST_EXIT_CODE.store(code.as_u8(), Ordering::Relaxed);
+        code
+    })
+}
+
+/// Executes a command line.
+async fn exec_line(line: &str) -> ExitCode {
+    let line = expand(&expand_aliases(strip_comment(line)));
+    let line = line.trim();
+    let (line, background) = match line.strip_suffix('&') {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+    if line.trim().is_empty() { return last_exit_code(); }
+
+    let (line, redirect) = match parse_redirect(line) {
+        Some(parsed) => parsed,
+        None => {
+            report(format_args!("shell: syntax error near '>'\n"));
+            return ExitCode::FAILURE;
+        }
+    };
+    let stages: Vec<Vec<&str>> = line.split('|').map(|stage| stage.split_whitespace().collect()).collect();
+    if stages.iter().any(|stage| stage.is_empty()) {
+        report(format_args!("shell: syntax error near '|'\n"));
+        return ExitCode::FAILURE;
+    }
+
+    if background {
+        return match (&stages[..], &redirect) {
+            ([stage], None) => spawn(stage[0], &stage[1..]),
+            _ => {
+                report(format_args!("shell: only simple commands can run in the background\n"));
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    // Each stage writes into a pipe read by the next one; the last stage writes to the console unless
+    // its output is redirected.
+    let mut input = None;
+    let mut code = ExitCode::SUCCESS;
+    for (idx, stage) in stages.iter().enumerate() {
+        let (stdout, next) = if idx + 1 == stages.len() && redirect.is_none() {
+            (Stdout::console(), None)
+        } else {
+            // Each stage runs to completion before the next one reads, so the pipe must hold all of it.
+            let (reader, writer) = pipe::unbounded();
+            (Stdout::pipe(writer), Some(reader))
+        };
+
+        let stdin = input.take().map_or_else(Stdin::console, Stdin::pipe);
+        let mut stdio = Stdio { stdin, stdout, stderr: Stderr::console() };
+        code = exec_command(stage[0], &stage[1..], &mut stdio).await;
+        input = next;
+    }
+
+    if let (Some(redirect), Some(mut input)) = (redirect, input) {
+        let data = input.read_to_end().await;
+        let res = match redirect {
+            Redirect::Truncate(path) => fs::write_file(path, &data),
+            Redirect::Append(path) => fs::append_file(path, &data),
+        };
+        if let Err(e) = res {
+            report(format_args!("shell: {}\n", e));
+            return ExitCode::FAILURE;
+        }
+    }
+
+    code
+}
+
+/// Executes a single command with the given streams.
+async fn exec_command(name: &str, args: &[&str], stdio: &mut Stdio) -> ExitCode {
+    let res = match name {
+        "alias" => alias(args, stdio),
+        "echo" => writeln!(stdio.stdout, "{}", args.join(" ")).map_err(Error::from),
+        "export" => export(args, stdio),
+        "history" => history(args, stdio),
+        "jobs" => jobs(stdio),
+        "run" => run(args).await,
+        "sleep" => sleep(args).await,
+        "unalias" => unalias(args),
+        "unset" => args.iter().try_for_each(|name| env::unset(name)),
+        "wait" => wait(args).await,
+        _ => {
+            return match process::run_with(name, args, stdio) {
+                Ok(code) => code,
+                Err(_) => {
+                    let theme = logger::get_theme();
+                    writeln!(stdio.stderr, "{}shell: {}{}", theme.style(LogLevel::Failure), msg!("shell.not_found", name), theme.reset()).ok();
+                    NOT_FOUND
+                }
+            };
+        }
+    };
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let theme = logger::get_theme();
+            writeln!(stdio.stderr, "{}shell: {}: {}{}", theme.style(LogLevel::Failure), name, e, theme.reset()).ok();
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Spawns the command as a background process, which runs the next time the shell waits.
+fn spawn(name: &str, args: &[&str]) -> ExitCode {
+    match process::spawn(name, args) {
+        Ok(pid) => {
+            writeln!(Stdout::console(), "[{}] {}", pid.as_u64(), name).ok();
+            ExitCode::SUCCESS
+        }
+        Err(_) => {
+            report(format_args!("shell: {}: command not found\n", name));
+            NOT_FOUND
+        }
+    }
+}
+
+/// Splits the trailing `> path` or `>> path` off the line.
+///
+/// Note: Returns `None` if the redirection is malformed.
+fn parse_redirect(line: &str) -> Option<(&str, Option<Redirect>)> {
+    let idx = match line.find('>') {
+        Some(idx) => idx,
+        None => return Some((line, None)),
+    };
+
+    let (line, target) = line.split_at(idx);
+    let (append, target) = match target.strip_prefix(">>") {
+        Some(target) => (true, target),
+        None => (false, &target[1..]),
+    };
+
+    let mut words = target.split_whitespace();
+    let path = words.next()?;
+    if words.next().is_some() || path.contains('>') { return None; }
+
+    Some((line, Some(if append { Redirect::Append(path) } else { Redirect::Truncate(path) })))
+}
+
+/// Runs the script at the given path.
+async fn run(args: &[&str]) -> Result<(), Error> {
+    let path = match args {
+        [path] => path,
+        _ => return Err(Error::InvalidArgument),
+    };
+    let source = fs::read_to_string(path)?;
+
+    run_script(&source).await
+}
+
+/// Runs the commands in the given script.
+pub async fn run_script(source: &str) -> Result<(), Error> {
+    let mut blocks: Vec<Block> = Vec::new();
+
+    for (num, line) in source.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let is_active = blocks.last().map_or(true, |b| b.is_active());
+
+        match keyword {
+            "" => {}
+            "if" => {
+                let condition = if !is_active {
+                    false
+                } else if rest.trim().is_empty() {
+                    last_exit_code().is_success()
+                } else {
+                    exec(rest).await.is_success()
+                };
+                blocks.push(Block { parent_active: is_active, condition, in_else: false });
+            }
+            "else" => {
+                match blocks.last_mut() {
+                    Some(block) if !block.in_else => block.in_else = true,
+                    _ => return Err(syntax_error(num, keyword)),
+                }
+            }
+            "end" => {
+                if blocks.pop().is_none() { return Err(syntax_error(num, keyword)); }
+            }
+            _ if is_active => { exec(line).await; }
+            _ => {}
+        }
+    }
+
+    if !blocks.is_empty() {
+        report(format_args!("shell: unterminated 'if' block\n"));
+        return Err(Error::InvalidArgument);
+    }
+
+    Ok(())
+}
+
+/// Reports an unexpected keyword in a script.
+fn syntax_error(num: usize, keyword: &str) -> Error {
+    report(format_args!("shell: line {}: unexpected '{}'\n", num + 1, keyword));
+    Error::InvalidArgument
+}
+
+/// Waits for the given number of seconds, letting the other tasks run in the meantime.
+async fn sleep(args: &[&str]) -> Result<(), Error> {
+    let seconds = match args {
+        [seconds] => seconds.parse::<f64>().map_err(|_| Error::InvalidArgument)?,
+        _ => return Err(Error::InvalidArgument),
+    };
+    // Wait a tick at a time, so that Ctrl+C cuts it short.
+    let start = system::uptime();
+    while system::uptime() - start < seconds {
+        if process::take_signal(Signal::Int) { return Err(Error::Failed); }
+        system::delay(system::tick_interval()).await;
+    }
+
+    Ok(())
+}
+
+/// Defines the variables given as `NAME=VALUE`, or lists all variables if none are given.
+fn export(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    if args.is_empty() {
+        let mut res = Ok(());
+        env::for_each(|name, value| res = res.and_then(|_| writeln!(stdio.stdout, "export {}={}", name, value.escape_debug())));
+        res?;
+    }
+
+    for arg in args {
+        let (name, value) = arg.split_once('=').ok_or(Error::InvalidArgument)?;
+        env::set(name, value)?;
+    }
+
+    Ok(())
+}
+
+/// Defines the alias given as `name='command args'`, or prints the given alias, or lists all of them.
+fn alias(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let definition = args.join(" ");
+    match definition.split_once('=') {
+        Some((name, value)) => {
+            if !is_alias_name(name) { return Err(Error::InvalidArgument); }
+            let value = unquote(value.trim());
+            if value.trim().is_empty() { return Err(Error::InvalidArgument); }
+
+            ALIASES.lock().insert(String::from(name), String::from(value));
+            save_aliases()
+        }
+        None if args.len() == 1 => {
+            let value = ALIASES.lock().get(args[0]).cloned().ok_or(Error::NotFound)?;
+            writeln!(stdio.stdout, "alias {}='{}'", args[0], value)?;
+            Ok(())
+        }
+        None if args.is_empty() => {
+            let aliases = ALIASES.lock().clone();
+            for (name, value) in aliases.iter() {
+                writeln!(stdio.stdout, "alias {}='{}'", name, value)?;
+            }
+            Ok(())
+        }
+        None => Err(Error::InvalidArgument),
+    }
+}
+
+/// Forgets the given aliases, or all of them with `-a`.
+fn unalias(args: &[&str]) -> Result<(), Error> {
+    match args {
+        [] => return Err(Error::InvalidArgument),
+        ["-a"] => ALIASES.lock().clear(),
+        _ => {
+            let mut aliases = ALIASES.lock();
+            if !args.iter().all(|name| aliases.contains_key(*name)) { return Err(Error::NotFound); }
+            args.iter().for_each(|name| { aliases.remove(*name); });
+        }
+    }
+
+    save_aliases()
+}
+
+/// Lists the lines in the history, or the last given number of them, or clears it with `-c`.
+fn history(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let entries = HISTORY.lock().clone();
+    let count = match args {
+        [] => entries.len(),
+        ["-c"] => {
+            HISTORY.lock().clear();
+            return Ok(());
+        }
+        [count] => count.parse::<usize>().map_err(|_| Error::InvalidArgument)?,
+        _ => return Err(Error::InvalidArgument),
+    };
+
+    let skip = entries.len().saturating_sub(count);
+    for (idx, line) in entries.iter().enumerate().skip(skip) {
+        writeln!(stdio.stdout, "{:>5}  {}", idx + 1, line)?;
+    }
+
+    Ok(())
+}
+
+/// Lists the background processes.
+fn jobs(stdio: &mut Stdio) -> Result<(), Error> {
+    let mut res = Ok(());
+    process::for_each(|info| {
+        res = res.and_then(|_| writeln!(stdio.stdout, "[{}] {:<10} {}", info.pid.as_u64(), info.status.as_str(), info.name));
+    });
+    Ok(res?)
+}
+
+/// Waits for the given background processes, or for all of them if none are given.
+async fn wait(args: &[&str]) -> Result<(), Error> {
+    let mut pids = Vec::new();
+    for arg in args {
+        let pid = arg.parse::<u64>().map_err(|_| Error::InvalidArgument)?;
+        pids.push(Pid::from_u64(pid));
+    }
+    if pids.is_empty() {
+        process::for_each(|info| pids.push(info.pid));
+    }
+
+    for pid in pids {
+        let code = process::wait(pid).await?;
+        writeln!(Stdout::console(), "[{}] exited with code {}", pid.as_u64(), code.as_u8()).ok();
+    }
+
+    Ok(())
+}
+
+/// Reports the background processes that have exited since the last prompt.
+fn report_exited() {
+    for (info, code) in process::reap() {
+        writeln!(Stdout::console(), "[{}] done {:<10} (exit {})", info.pid.as_u64(), info.name, code.as_u8()).ok();
+    }
+}
+
+/// Writes a diagnostic to the console, in the theme's color for failures.
+fn report(args: fmt::Arguments) {
+    let theme = logger::get_theme();
+    write!(Stderr::console(), "{}{}{}", theme.style(LogLevel::Failure), args, theme.reset()).ok();
+}
+
+/// Returns whether the name can be given to an alias.
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Removes the quotes around the text, if it is quoted.
+fn unquote(text: &str) -> &str {
+    ['\'', '"'].iter()
+        .find_map(|quote| text.strip_prefix(*quote).and_then(|text| text.strip_suffix(*quote)))
+        .unwrap_or(text)
+}
+
+/// Replaces the aliases leading each command of the line with the text they stand for.
+fn expand_aliases(line: &str) -> String {
+    let aliases = ALIASES.lock();
+    if aliases.is_empty() { return String::from(line); }
+
+    let commands: Vec<String> = line.split('|').map(
+        |command| {
+            let mut command = String::from(command);
+            // Names already expanded are left as is, which ends the expansion of recursive aliases.
+            let mut expanded: Vec<&str> = Vec::new();
+            loop {
+                let start = command.len() - command.trim_start().len();
+                let end = command[start..].find(char::is_whitespace).map_or(command.len(), |len| start + len);
+                match aliases.get_key_value(&command[start..end]) {
+                    Some((name, value)) if !expanded.contains(&name.as_str()) => {
+                        expanded.push(name);
+                        command.replace_range(start..end, value);
+                    }
+                    _ => break command,
+                }
+            }
+        }
+    ).collect();
+
+    commands.join("|")
+}
+
+/// Reads the aliases back from their file, if there is one.
+fn load_aliases() {
+    let text = match fs::read_to_string(ALIASES_PATH) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+
+    let mut aliases = ALIASES.lock();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        match line.split_once('=') {
+            Some((name, value)) if is_alias_name(name) => { aliases.insert(String::from(name), String::from(value)); }
+            _ => report(format_args!("shell: {}: malformed alias '{}'\n", ALIASES_PATH, line)),
+        }
+    }
+}
+
+/// Writes the aliases to their file, if the filesystem can hold it.
+fn save_aliases() -> Result<(), Error> {
+    if !is_writable(ALIASES_DIR) { return Ok(()); }
+
+    let mut text = String::from("# Shell aliases, written by `alias` and `unalias`.\n");
+    for (name, value) in ALIASES.lock().iter() {
+        writeln!(text, "{}={}", name, value)?;
+    }
+
+    fs::write_file(ALIASES_PATH, text.as_bytes())
+}
+
+/// Returns whether files can be written to the directory.
+fn is_writable(dir: &str) -> bool {
+    fs::metadata(dir).map_or(false, |metadata| metadata.is_dir() && !metadata.read_only)
+}
+
+/// Returns the number of lines kept in the history.
+fn history_size() -> usize {
+    env::get("HISTSIZE").and_then(|size| size.parse::<usize>().ok()).unwrap_or(DEFAULT_HISTORY_SIZE)
+}
+
+/// Records the line in the history, dropping the oldest lines past the size of the history.
+fn record(line: &str) {
+    let line = line.trim();
+    if line.is_empty() { return; }
+
+    let mut history = HISTORY.lock();
+    history.push(String::from(line));
+    let excess = history.len().saturating_sub(history_size());
+    history.drain(..excess);
+}
+
+/// Reads the history back from its file, if there is one.
+fn load_history() {
+    if let Ok(text) = fs::read_to_string(HISTORY_PATH) {
+        text.lines().for_each(record);
+    }
+}
+
+/// Writes the history to its file, if the filesystem can hold it.
+fn save_history() {
+    if !is_writable(HISTORY_DIR) { return; }
+
+    let mut text = String::new();
+    for line in HISTORY.lock().iter() {
+        text.push_str(line);
+        text.push('\n');
+    }
+    if let Err(e) = fs::write_file(HISTORY_PATH, text.as_bytes()) {
+        report(format_args!("shell: {}: {}\n", HISTORY_PATH, e));
+    }
+}
+
+/// Replaces `!!` and `!n` in the line with the lines they refer to in the history.
+///
+/// Note: Returns the event that could not be found on failure.
+fn expand_history(line: &str) -> Result<String, String> {
+    let history = HISTORY.lock();
+    let mut expanded = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'!') => expanded.push(chars.next().unwrap()),
+            '!' if chars.next_if_eq(&'!').is_some() => {
+                expanded.push_str(history.last().ok_or_else(|| String::from("!!"))?);
+            }
+            '!' if chars.peek().map_or(false, char::is_ascii_digit) => {
+                let mut num = String::new();
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    num.push(c);
+                }
+                let entry = num.parse::<usize>().ok()
+                    .and_then(|num| num.checked_sub(1))
+                    .and_then(|idx| history.get(idx));
+                expanded.push_str(entry.ok_or_else(|| format!("!{}", num))?);
+            }
+            _ => expanded.push(c),
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Removes the comment from the line.
+fn strip_comment(line: &str) -> &str {
+    let mut prev = ' ';
+    for (idx, c) in line.char_indices() {
+        if c == '#' && prev.is_whitespace() { return &line[..idx]; }
+        prev = c;
+    }
+    line
+}
+
+/// Replaces the variables in the line with their values.
+fn expand(line: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'$') => expanded.push(chars.next().unwrap()),
+            '$' if chars.next_if_eq(&'?').is_some() => expanded.push_str(&format!("{}", last_exit_code().as_u8())),
+            '$' => {
+                let braced = chars.next_if_eq(&'{').is_some();
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                let closed = !braced || chars.next_if_eq(&'}').is_some();
+
+                if name.is_empty() || !closed {
+                    // Not a variable; keep the text as is.
+                    expanded.push('$');
+                    if braced { expanded.push('{'); }
+                    expanded.push_str(&name);
+                } else {
+                    expanded.push_str(&env::get(&name).unwrap_or_default());
+                }
+            }
+            _ => expanded.push(c),
+        }
+    }
+
+    expanded
+}
+
+////////////////
+/// Redirect
+////////////////
+enum Redirect<'a> {
+    /// Replace the contents of the file.
+    Truncate(&'a str),
+    /// Append to the contents of the file.
+    Append(&'a str),
+}
+
+/////////////
+/// Block
+/////////////
+struct Block {
+    /// Whether the block containing this one is being executed.
+    parent_active: bool,
+    /// Whether the condition of the block held.
+    condition: bool,
+    /// Whether the `else` branch has been reached.
+    in_else: bool,
+}
+
+impl Block {
+    /// Returns whether the current branch is being executed.
+    fn is_active(&self) -> bool { self.parent_active && (self.condition != self.in_else) }
+}
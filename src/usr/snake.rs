@@ -0,0 +1,344 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::collections::VecDeque;
+use core::fmt::Write;
+
+use crate::api::{Error, system, vga};
+use crate::api::io::Stdio;
+use crate::api::vga::{Color, Screen};
+use crate::aux::testing::Rng;
+use crate::devices::console;
+use crate::encodings::{ASCII, Charset};
+use crate::msg;
+
+// Snake
+//
+// The snake is steered with the arrow keys, or WASD, towards the food; it grows with each bite, and
+// the game is over once it runs into a wall or into itself. Q gives up.
+//
+// The game draws into a screen region covering all but the last row, reads keys in raw mode as they
+// arrive, and advances a step per frame, each frame due a fixed period after the previous one. As
+// every frame depends on input and timer ticks arriving on time, how late the frames ran is reported
+// once the game is over.
+
+////////////////
+// Attributes
+////////////////
+
+/// Seconds between the steps of the snake at the start.
+const START_PERIOD: f64 = 0.15;
+/// Seconds between the steps of the snake at the fastest.
+const MIN_PERIOD: f64 = 0.06;
+/// Seconds taken off the period with each bite.
+const SPEEDUP: f64 = 0.003;
+/// Length of the snake at the start.
+const START_LENGTH: usize = 4;
+
+/////////////////
+/// Direction
+/////////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// Returns the opposite direction.
+    fn opposite(&self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+/////////////
+/// Input
+/////////////
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    Turn(Direction),
+    Quit,
+}
+
+////////////
+/// Keys
+////////////
+/// Decoder of the keys read in raw mode, where arrows arrive as `ESC [ A` through `ESC [ D`.
+#[derive(Default)]
+struct Keys {
+    escape: usize,
+}
+
+impl Keys {
+    /// Returns what the next buffered keys stand for, or `None` once there are none left.
+    fn read(&mut self) -> Option<Option<Input>> {
+        let c = console::try_read_char()?;
+        let input = match (self.escape, c) {
+            (0, ASCII::<char>::ESC) | (1, '[') => {
+                self.escape += 1;
+                return Some(None);
+            }
+            (2, 'A') => Some(Input::Turn(Direction::Up)),
+            (2, 'B') => Some(Input::Turn(Direction::Down)),
+            (2, 'C') => Some(Input::Turn(Direction::Right)),
+            (2, 'D') => Some(Input::Turn(Direction::Left)),
+            (0, 'w' | 'W') => Some(Input::Turn(Direction::Up)),
+            (0, 's' | 'S') => Some(Input::Turn(Direction::Down)),
+            (0, 'a' | 'A') => Some(Input::Turn(Direction::Left)),
+            (0, 'd' | 'D') => Some(Input::Turn(Direction::Right)),
+            (0, 'q' | 'Q' | ASCII::<char>::ETX) => Some(Input::Quit),
+            _ => None,
+        };
+        self.escape = 0;
+        Some(input)
+    }
+}
+
+/////////////
+/// Stats
+/////////////
+#[derive(Debug, Default)]
+struct Stats {
+    score: usize,
+    frames: usize,
+    /// Seconds by which the latest frame ran late, at most.
+    max_lag: f64,
+}
+
+////////////
+/// Game
+////////////
+struct Game {
+    screen: Screen,
+    width: usize,
+    height: usize,
+    /// Cells of the snake, head first.
+    body: VecDeque<(usize, usize)>,
+    heading: Direction,
+    food: (usize, usize),
+    rng: Rng,
+    stats: Stats,
+}
+
+impl Game {
+    /// Creates a new game in the given screen, with the snake in the middle heading right.
+    fn new(screen: Screen) -> Result<Self, Error> {
+        // The top row shows the score, and the board is framed.
+        let (width, height) = (screen.columns().saturating_sub(2), screen.rows().saturating_sub(3));
+        if width < 2 * START_LENGTH || height < 2 { return Err(Error::OutOfBounds); }
+
+        let (x, y) = (width / 2, height / 2);
+        let body = (0..START_LENGTH).map(|idx| (x - idx, y)).collect();
+        let mut game = Game {
+            screen,
+            width,
+            height,
+            body,
+            heading: Direction::Right,
+            food: (0, 0),
+            rng: Rng::new(system::rdtsc()),
+            stats: Stats::default(),
+        };
+        game.place_food();
+
+        Ok(game)
+    }
+
+    /// Draws the frame, the snake, the food and the score.
+    fn draw(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.screen.clear();
+        self.screen.set_foreground(Color::DarkGray);
+        self.put(1, 0, '╔');
+        self.put(1, width + 1, '╗');
+        self.put(height + 2, 0, '╚');
+        self.put(height + 2, width + 1, '╝');
+        for col in 1..=width {
+            self.put(1, col, '═');
+            self.put(height + 2, col, '═');
+        }
+        for row in 2..(height + 2) {
+            self.put(row, 0, '║');
+            self.put(row, width + 1, '║');
+        }
+
+        for idx in 0..self.body.len() { self.draw_segment(idx); }
+        self.draw_food();
+        self.draw_score();
+    }
+
+    /// Draws the segment of the snake at the given index.
+    fn draw_segment(&mut self, idx: usize) {
+        let (x, y) = self.body[idx];
+        self.screen.set_foreground(if idx == 0 { Color::LightGreen } else { Color::Green });
+        self.put_cell(x, y, '█');
+    }
+
+    /// Draws the food.
+    fn draw_food(&mut self) {
+        let (x, y) = self.food;
+        self.screen.set_foreground(Color::LightRed);
+        self.put_cell(x, y, '♦');
+    }
+
+    /// Draws the score, along with a hint on the keys.
+    fn draw_score(&mut self) {
+        self.screen.set_foreground(Color::Yellow);
+        self.screen.set_cursor_position(0, 0);
+        self.screen.clear_line();
+        write!(self.screen, "{}", msg!("snake.status", self.stats.score)).ok();
+    }
+
+    /// Moves the snake a step, and returns whether it is still alive.
+    fn step(&mut self) -> bool {
+        let (x, y) = self.body[0];
+        let head = match self.heading {
+            Direction::Up if y > 0 => (x, y - 1),
+            Direction::Down if y + 1 < self.height => (x, y + 1),
+            Direction::Left if x > 0 => (x - 1, y),
+            Direction::Right if x + 1 < self.width => (x + 1, y),
+            _ => return false,
+        };
+
+        // The tail moves out of the way, unless the snake grows.
+        let grows = head == self.food;
+        if !grows {
+            if let Some((x, y)) = self.body.pop_back() { self.put_cell(x, y, ' '); }
+        }
+        if self.body.contains(&head) { return false; }
+
+        self.body.push_front(head);
+        self.draw_segment(0);
+        if self.body.len() > 1 { self.draw_segment(1); }
+
+        if grows {
+            self.stats.score += 1;
+            self.draw_score();
+            if !self.place_food() { return false; }
+        }
+
+        true
+    }
+
+    /// Puts the food on a random free cell, and returns whether there was one.
+    fn place_food(&mut self) -> bool {
+        let free = self.width * self.height - self.body.len();
+        if free == 0 { return false; }
+
+        // Counts off the free cells up to a random one.
+        let mut nth = self.rng.below(free);
+        for cell in (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y))) {
+            if self.body.contains(&cell) { continue; }
+            if nth == 0 {
+                self.food = cell;
+                break;
+            }
+            nth -= 1;
+        }
+        self.draw_food();
+
+        true
+    }
+
+    /// Puts the character on the given cell of the board.
+    fn put_cell(&mut self, x: usize, y: usize, c: char) { self.put(y + 2, x + 1, c); }
+
+    /// Puts the character at the given position of the screen.
+    fn put(&mut self, row: usize, col: usize, c: char) {
+        self.screen.set_cursor_position(row, col);
+        write!(self.screen, "{}", c).ok();
+    }
+}
+
+/// Plays snake on the screen.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    if !args.is_empty() {
+        writeln!(stdio.stderr, "{}: snake", msg!("usage"))?;
+        return Err(Error::InvalidArgument);
+    }
+    if !stdio.stdin.is_interactive() {
+        writeln!(stdio.stderr, "snake: {}", msg!("snake.not_interactive"))?;
+        return Err(Error::Unsupported);
+    }
+
+    let screen = Screen::new(0, 0, vga::rows() - 1, vga::columns())?;
+    let mut game = Game::new(screen)?;
+
+    console::disable_echo();
+    console::enable_raw();
+    play(&mut game);
+    console::disable_raw();
+    console::enable_echo();
+
+    let stats = core::mem::take(&mut game.stats);
+    drop(game);
+    writeln!(stdio.stdout, "{}", msg!("snake.result", stats.score, stats.frames, (stats.max_lag * 1000.0) as usize))?;
+
+    Ok(())
+}
+
+/// Runs the game until the snake dies or the player quits.
+fn play(game: &mut Game) {
+    let mut keys = Keys::default();
+    let mut period = START_PERIOD;
+    game.draw();
+
+    let mut due = system::uptime() + period;
+    loop {
+        while system::uptime() < due {
+            system::halt();
+        }
+        let now = system::uptime();
+        game.stats.max_lag = game.stats.max_lag.max(now - due);
+        game.stats.frames += 1;
+        // A frame that ran later than a whole period does not make the next ones hurry.
+        due = if now - due > period { now + period } else { due + period };
+
+        // Of the turns since the last step, the last one that does not reverse the snake counts.
+        let heading = game.heading;
+        while let Some(input) = keys.read() {
+            match input {
+                Some(Input::Turn(direction)) if direction != heading.opposite() => game.heading = direction,
+                Some(Input::Quit) => return,
+                _ => {}
+            }
+        }
+
+        let score = game.stats.score;
+        if !game.step() { break; }
+        if game.stats.score > score { period = (period - SPEEDUP).max(MIN_PERIOD); }
+    }
+
+    game.screen.set_foreground(Color::LightRed);
+    game.screen.set_cursor_position(0, 0);
+    game.screen.clear_line();
+    write!(game.screen, "{}", msg!("snake.game_over", game.stats.score)).ok();
+    console::take_input();
+    console::read_char();
+}
@@ -0,0 +1,49 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::io::Stdio;
+use crate::api::process;
+use crate::api::process::ExitCode;
+use crate::msg;
+
+/// Runs a command with all capabilities, or prints the capabilities held.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        ["-l"] => {
+            writeln!(stdio.stdout, "{}", process::capabilities())?;
+            Ok(())
+        }
+        [name, args @ ..] if !name.starts_with('-') => {
+            match process::run_elevated(name, args, stdio)? {
+                ExitCode::SUCCESS => Ok(()),
+                _ => Err(Error::Failed),
+            }
+        }
+        _ => {
+            writeln!(stdio.stderr, "{}: sudo [-l | COMMAND [ARGS...]]", msg!("usage"))?;
+            Err(Error::InvalidArgument)
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::Error;
+use crate::api::fs;
+use crate::api::io::Stdio;
+#[cfg(feature = "fs")]
+use crate::kernel::block::cache;
+use crate::msg;
+
+/// Writes cached blocks back to their devices, optionally printing the cache statistics.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    match args {
+        [] => fs::sync()?,
+        #[cfg(feature = "fs")]
+        ["-s"] => {
+            fs::sync()?;
+            let stats = cache::stats();
+            writeln!(
+                stdio.stdout,
+                "cached: {} blocks, dirty: {}, hits: {}, misses: {}",
+                stats.cached, stats.dirty, stats.hits, stats.misses
+            )?;
+        }
+        _ => {
+            writeln!(stdio.stderr, "{}: sync [-s]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
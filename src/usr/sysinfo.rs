@@ -0,0 +1,119 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::api::{Error, fs, sensors, system};
+use crate::api::io::Stdio;
+#[cfg(feature = "fs")]
+use crate::kernel::block;
+use crate::kernel::{acpi, apic, cpu, idt, memory, pci};
+
+/// Prints a summary of the build, processor, memory, firmware, devices and filesystems.
+pub fn main(_args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let build = system::build_info();
+    section(stdio, "Build")?;
+    writeln!(stdio.stdout, "  version:  {}{}", build.version, if build.debug { " (debug)" } else { "" })?;
+    writeln!(stdio.stdout, "  profile:  {}", build.profile())?;
+    writeln!(stdio.stdout, "  features: {}", if build.features.is_empty() { "none".into() } else { build.features.join(" ") })?;
+
+    let cpu = cpu::info();
+    section(stdio, "CPU")?;
+    writeln!(stdio.stdout, "  model:    {}", if cpu.brand.is_empty() { "unknown" } else { &cpu.brand })?;
+    writeln!(
+        stdio.stdout,
+        "  vendor:   {} (family {:#x}, model {:#x}, stepping {})",
+        cpu.vendor, cpu.family, cpu.model, cpu.stepping
+    )?;
+    writeln!(stdio.stdout, "  count:    {}", cpu.count)?;
+    writeln!(stdio.stdout, "  features: {}", cpu.features.join(" "))?;
+    match sensors::cpu_temp() {
+        Ok(reading) => writeln!(stdio.stdout, "  temp:     {} C (TjMax {} C)", reading.celsius, reading.tj_max)?,
+        Err(e) => writeln!(stdio.stdout, "  temp:     {}", e)?,
+    }
+
+    section(stdio, "Memory")?;
+    const MIB: u64 = 1024 * 1024;
+    writeln!(stdio.stdout, "  total:    {} MiB", memory::total_memory() / MIB)?;
+    writeln!(stdio.stdout, "  usable:   {} MiB", memory::usable_memory() / MIB)?;
+
+    section(stdio, "Firmware")?;
+    let tables = acpi::tables();
+    if tables.is_empty() {
+        writeln!(stdio.stdout, "  ACPI:     not available")?;
+    } else {
+        let signatures: Vec<&str> = tables.iter().map(|table| table.signature.as_str()).collect();
+        writeln!(stdio.stdout, "  ACPI:     {}", signatures.join(" "))?;
+    }
+    let interrupts = match (system::boot_report().apic_enabled, apic::local::is_x2apic()) {
+        (true, true) => "x2APIC",
+        (true, false) => "APIC",
+        (false, _) => "legacy PIC",
+    };
+    writeln!(stdio.stdout, "  IRQs:     {} ({} spurious)", interrupts, idt::spurious_count())?;
+
+    section(stdio, "PCI")?;
+    let mut res = Ok(());
+    pci::for_each(
+        |function| {
+            res = res.and_then(|_| write!(
+                stdio.stdout,
+                "  {:02x}:{:02x}.{} {:04x}:{:04x} {}",
+                function.bus, function.slot, function.func, function.vendor_id, function.device_id, function.class_name()
+            ));
+            res = res.and_then(
+                |_| match (function.has_msi(), function.legacy_interrupt()) {
+                    (true, _) => writeln!(stdio.stdout, " (MSI)"),
+                    (false, Some(interrupt)) => writeln!(stdio.stdout, " (GSI {})", interrupt.gsi),
+                    (false, None) => writeln!(stdio.stdout),
+                }
+            );
+        }
+    );
+    res?;
+
+    section(stdio, "Storage")?;
+    let mut res = Ok(());
+    #[cfg(feature = "fs")]
+    block::for_each(
+        |info| {
+            let size = info.block_count * info.block_size as u64 / MIB;
+            res = res.and_then(|_| writeln!(stdio.stdout, "  {:<10} {} MiB", info.name, size));
+        }
+    );
+    fs::for_each_mount(
+        |info| {
+            let mode = if info.read_only { "ro" } else { "rw" };
+            res = res.and_then(|_| writeln!(stdio.stdout, "  {} on {} ({}, {})", info.source, info.path, info.fs_type, mode));
+        }
+    );
+    res?;
+
+    Ok(())
+}
+
+/// Prints a section header.
+fn section(stdio: &mut Stdio, title: &str) -> Result<(), Error> {
+    writeln!(stdio.stdout, "\x1B[93m{}\x1B[0m", title)?;
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::{Error, process, sensors, stats, system};
+use crate::api::io::Stdio;
+use crate::msg;
+
+/// Prints the CPU usage and the processes, refreshing every second `-n` times.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let count = match args {
+        [] => 1,
+        ["-n", count] => count.parse::<usize>().map_err(|_| Error::InvalidArgument)?,
+        _ => {
+            writeln!(stdio.stderr, "{}: top [-n COUNT]", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    };
+
+    for idx in 0..count {
+        if idx > 0 {
+            system::sleep(1.0);
+            writeln!(stdio.stdout)?;
+        }
+        snapshot(stdio)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the current CPU usage and temperature, and the processes.
+fn snapshot(stdio: &mut Stdio) -> Result<(), Error> {
+    let cpu = stats::cpu();
+    writeln!(
+        stdio.stdout,
+        "up {:.0}s, cpu: {:5.1}% busy, {:5.1}% idle (since boot: {:.1}s busy, {:.1}s idle)",
+        system::uptime(), cpu.busy, cpu.idle, cpu.total_busy, cpu.total_idle
+    )?;
+
+    match sensors::cpu_temp() {
+        Ok(reading) => writeln!(stdio.stdout, "temp: {} C (TjMax {} C)", reading.celsius, reading.tj_max)?,
+        Err(e) => writeln!(stdio.stdout, "temp: {}", e)?,
+    }
+
+    writeln!(stdio.stdout, "\x1B[93mPID    STATUS     NAME\x1B[0m")?;
+    let mut res = Ok(());
+    process::for_each(
+        |info| {
+            res = res.and_then(|_| writeln!(stdio.stdout, "{:<6} {:<10} {}", info.pid.as_u64(), info.status.as_str(), info.name));
+        }
+    );
+
+    Ok(res?)
+}
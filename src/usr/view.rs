@@ -0,0 +1,48 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use core::fmt::Write;
+
+use crate::api::{Error, fs, gfx};
+use crate::api::io::Stdio;
+use crate::devices::console;
+use crate::msg;
+
+/// Shows an image on the framebuffer until a key is pressed.
+pub fn main(args: &[&str], stdio: &mut Stdio) -> Result<(), Error> {
+    let path = match args {
+        [path] => path,
+        _ => {
+            writeln!(stdio.stderr, "{}: view FILE", msg!("usage"))?;
+            return Err(Error::InvalidArgument);
+        }
+    };
+
+    let image = gfx::decode(&fs::read_file(path)?)?;
+    gfx::show_image(&image)?;
+    console::read_char();
+    gfx::restore_console();
+
+    writeln!(stdio.stdout, "{}: {}x{}", path, image.width(), image.height())?;
+
+    Ok(())
+}
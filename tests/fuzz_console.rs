@@ -0,0 +1,172 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(asm_os::aux::testing::serene_test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::panic::PanicInfo;
+
+use bootloader::{BootInfo, entry_point};
+
+use asm_os::{hlt_loop, init};
+use asm_os::api::vga;
+use asm_os::aux::logger::LogLevel;
+use asm_os::aux::testing;
+use asm_os::aux::testing::{Rng, serene_test_panic_handler};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    init(boot_info, LogLevel::Quiet);
+    test_main();
+    hlt_loop();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! { serene_test_panic_handler(info); }
+
+/// Seeds of the fuzzing rounds; a failure names the seed that caused it.
+const SEEDS: [u64; 4] = [0x1, 0xC0FF_EE15_600D, 0xDEAD_BEEF_CAFE_F00D, 0x5EED_0FA5_0005];
+
+/// Inputs generated for each seed.
+const ROUNDS: usize = 2000;
+
+/// Final bytes of the control sequences the writer handles.
+const CSI_FINALS: &[u8] = b"ABCDGHJKm";
+
+/// Control characters the writer handles.
+const CONTROLS: &[u8] = &[0x08, 0x09, 0x0A, 0x0C, 0x0D];
+
+/// Keys the console treats specially, mixed with ordinary and multi-byte ones.
+const KEYS: &[char] = &['a', 'z', '0', ' ', '\n', '\r', '\x08', '\x03', '\x04', '\x1B', '\x7F', 'é', '€', '→'];
+
+/// Returns a parameter for a control sequence, favouring the edges of the screen.
+fn parameter(rng: &mut Rng) -> u16 {
+    match rng.below(4) {
+        0 => rng.below(4) as u16,
+        1 => (vga::rows() as u16).wrapping_add(rng.below(3) as u16).wrapping_sub(1),
+        2 => (vga::columns() as u16).wrapping_add(rng.below(3) as u16).wrapping_sub(1),
+        _ => rng.next_u64() as u16,
+    }
+}
+
+/// Appends a random input for the writer to the given bytes.
+fn generate(rng: &mut Rng, bytes: &mut Vec<u8>) {
+    match rng.below(8) {
+        0..=2 => bytes.push(0x20 + rng.below(0x5F) as u8),
+        3 => bytes.push(*rng.pick(CONTROLS)),
+        4 => bytes.push(rng.next_u64() as u8),
+        _ => {
+            bytes.extend_from_slice(b"\x1B[");
+            for i in 0..rng.below(4) {
+                if i > 0 { bytes.push(b';'); }
+                if !rng.one_in(5) {
+                    bytes.extend_from_slice(parameter(rng).to_string().as_bytes());
+                }
+            }
+            if rng.one_in(10) {
+                bytes.push(0x40 + rng.below(0x3F) as u8);
+            } else {
+                bytes.push(*rng.pick(CSI_FINALS));
+            }
+        }
+    }
+}
+
+/// Asserts that the cursor lies within the screen.
+///
+/// The column may be one past the last, as the writer only wraps once the next character arrives.
+fn assert_cursor_in_bounds(seed: u64, round: usize) {
+    let (row, col) = vga::get_cursor_position();
+    assert!(row < vga::rows(), "seed {:#x}, round {}: row {} out of bounds", seed, round, row);
+    assert!(col <= vga::columns(), "seed {:#x}, round {}: column {} out of bounds", seed, round, col);
+}
+
+#[test_case]
+fn writer_survives_random_sequences() {
+    let mut bytes = Vec::new();
+    for &seed in SEEDS.iter() {
+        let mut rng = Rng::new(seed);
+        for round in 0..ROUNDS {
+            bytes.clear();
+            for _ in 0..=rng.below(8) {
+                generate(&mut rng, &mut bytes);
+            }
+            testing::feed_writer(&bytes);
+            assert_cursor_in_bounds(seed, round);
+        }
+    }
+    vga::reset_color_code();
+    vga::clear();
+}
+
+#[test_case]
+fn writer_survives_random_bytes() {
+    let mut bytes = [0u8; 32];
+    for &seed in SEEDS.iter() {
+        let mut rng = Rng::new(seed);
+        for round in 0..ROUNDS {
+            for byte in bytes.iter_mut() {
+                *byte = rng.next_u64() as u8;
+            }
+            testing::feed_writer(&bytes[..rng.below(bytes.len())]);
+            assert_cursor_in_bounds(seed, round);
+        }
+    }
+    vga::reset_color_code();
+    vga::clear();
+}
+
+#[test_case]
+fn console_buffers_random_keys() {
+    testing::take_console_input();
+    for &seed in SEEDS.iter() {
+        let mut rng = Rng::new(seed);
+        let mut expected = String::new();
+        // A backspace stops at the end of the last line, which its terminator completed.
+        let mut complete = 0;
+        for round in 0..ROUNDS {
+            let key = *rng.pick(KEYS);
+            match key {
+                '\x08' => {
+                    if expected.len() > complete { expected.pop(); }
+                }
+                '\n' | '\r' => {
+                    expected.push(key);
+                    complete = expected.len();
+                }
+                _ => expected.push(key),
+            }
+            testing::type_keys(key.encode_utf8(&mut [0; 4]));
+            assert_cursor_in_bounds(seed, round);
+        }
+        assert_eq!(testing::take_console_input(), expected, "seed {:#x}", seed);
+    }
+    vga::clear();
+}
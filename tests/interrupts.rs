@@ -0,0 +1,136 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(asm_os::aux::testing::serene_test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+use bootloader::{BootInfo, entry_point};
+use x86_64::instructions;
+
+use asm_os::{hlt_loop, init};
+use asm_os::api::system;
+use asm_os::aux::logger::LogLevel;
+use asm_os::aux::testing;
+use asm_os::aux::testing::serene_test_panic_handler;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    init(boot_info, LogLevel::Quiet);
+    test_main();
+    hlt_loop();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! { serene_test_panic_handler(info); }
+
+/// Scancodes (set 1) of a key being pressed and released.
+const KEY_A: [u8; 2] = [0x1E, 0x9E];
+const KEY_B: [u8; 2] = [0x30, 0xB0];
+const KEY_ENTER: [u8; 2] = [0x1C, 0x9C];
+const KEY_BACKSPACE: [u8; 2] = [0x0E, 0x8E];
+const SHIFT_DOWN: u8 = 0x2A;
+const SHIFT_UP: u8 = 0xAA;
+
+#[test_case]
+fn timer_advances_by_injected_ticks() {
+    instructions::interrupts::without_interrupts(
+        || {
+            let start = system::ticks();
+            testing::tick_timer(10);
+            assert_eq!(system::ticks() - start, 10);
+        }
+    );
+}
+
+#[test_case]
+fn uptime_follows_injected_ticks() {
+    instructions::interrupts::without_interrupts(
+        || {
+            let start = system::uptime();
+            testing::tick_timer(100);
+            let elapsed = system::uptime() - start;
+            let expected = 100.0 * system::tick_interval();
+            let tolerance = system::tick_interval() / 2.0;
+            assert!(elapsed > expected - tolerance && elapsed < expected + tolerance);
+        }
+    );
+}
+
+#[test_case]
+fn scancodes_are_decoded_into_keys() {
+    instructions::interrupts::without_interrupts(
+        || {
+            testing::take_console_input();
+            testing::inject_scancodes(&KEY_A);
+            testing::inject_scancodes(&KEY_B);
+            testing::inject_scancodes(&KEY_ENTER);
+            assert_eq!(testing::take_console_input(), "ab\n");
+        }
+    );
+}
+
+#[test_case]
+fn shift_selects_uppercase() {
+    instructions::interrupts::without_interrupts(
+        || {
+            testing::take_console_input();
+            testing::inject_scancodes(&[SHIFT_DOWN]);
+            testing::inject_scancodes(&KEY_A);
+            testing::inject_scancodes(&[SHIFT_UP]);
+            testing::inject_scancodes(&KEY_A);
+            assert_eq!(testing::take_console_input(), "Aa");
+        }
+    );
+}
+
+#[test_case]
+fn backspace_erases_the_last_key() {
+    instructions::interrupts::without_interrupts(
+        || {
+            testing::take_console_input();
+            testing::inject_scancodes(&KEY_A);
+            testing::inject_scancodes(&KEY_B);
+            testing::inject_scancodes(&KEY_BACKSPACE);
+            testing::inject_scancodes(&KEY_ENTER);
+            assert_eq!(testing::take_console_input(), "a\n");
+        }
+    );
+}
+
+#[test_case]
+fn console_erases_typed_characters() {
+    instructions::interrupts::without_interrupts(
+        || {
+            testing::take_console_input();
+            testing::type_keys("lss\x08 -l\n");
+            assert_eq!(testing::take_console_input(), "ls -l\n");
+        }
+    );
+}
@@ -0,0 +1,90 @@
+// MIT License
+//
+// Copyright (c) 2023 Mansoor Ahmed Memon.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(asm_os::aux::testing::serene_test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use core::panic::PanicInfo;
+
+use bootloader::{BootInfo, entry_point};
+
+use asm_os::{hlt_loop, init};
+use asm_os::api::fs;
+use asm_os::aux::logger::LogLevel;
+use asm_os::aux::testing;
+use asm_os::aux::testing::serene_test_panic_handler;
+use asm_os::kernel::pipe;
+use asm_os::usr::shell;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    init(boot_info, LogLevel::Quiet);
+    test_main();
+    hlt_loop();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! { serene_test_panic_handler(info); }
+
+/// Number of lines of the generated file, which comes to more than the capacity of a pipe.
+///
+/// Note: The file is held several times over by the test, the file system and the pipeline, so it is
+/// kept small enough for all of them to fit in the kernel heap.
+const LINES: usize = 1536;
+
+/// Writes a file of numbered lines, every other one marked, and returns its contents.
+fn write_lines(path: &str) -> String {
+    let mut text = String::new();
+    for num in 0..LINES {
+        let mark = if num % 2 == 0 { "even" } else { "odd" };
+        text.push_str(&format!("line {:05} is {}, padded to stand out in a pipe\n", num, mark));
+    }
+    assert!(text.len() > pipe::CAPACITY);
+
+    fs::write_file(path, text.as_bytes()).unwrap();
+    text
+}
+
+#[test_case]
+fn redirect_holds_more_than_a_pipe() {
+    let text = write_lines("/tmp/lines");
+
+    assert!(testing::block_on(shell::exec("cat /tmp/lines > /tmp/copy")).is_success());
+    assert_eq!(fs::read_to_string("/tmp/copy").unwrap(), text);
+}
+
+#[test_case]
+fn pipeline_carries_more_than_a_pipe() {
+    let text = write_lines("/tmp/lines");
+    let expected: String = text.lines().filter(|line| line.contains("even")).map(|line| format!("{}\n", line)).collect();
+
+    assert!(testing::block_on(shell::exec("cat /tmp/lines | grep even > /tmp/even")).is_success());
+    assert_eq!(fs::read_to_string("/tmp/even").unwrap(), expected);
+}